@@ -0,0 +1,362 @@
+use indexmap::IndexMap;
+
+use crate::{AnnotationValues, TypeAnnotation};
+
+/// Join a parent path and a child key with the superjson dot separator.
+///
+/// Keys in annotation maps are already escaped, so this is a plain join.
+fn join_path(prefix: &str, key: &str) -> String {
+    if prefix.is_empty() {
+        key.to_string()
+    } else {
+        format!("{prefix}.{key}")
+    }
+}
+
+/// Returns true if `path` is `prefix` itself or lies beneath it.
+fn path_starts_with(path: &str, prefix: &str) -> bool {
+    prefix.is_empty()
+        || path == prefix
+        || path
+            .strip_prefix(prefix)
+            .is_some_and(|rest| rest.starts_with('.'))
+}
+
+/// A depth-first iterator over the annotations of a tree.
+///
+/// Yields `(path, annotation)` pairs, where `path` is the full dot-notation
+/// path from the root of the tree (the root itself has the empty path).
+/// Parents are yielded before their children, in insertion order.
+pub struct Iter<'a> {
+    stack: Vec<(String, &'a TypeAnnotation)>,
+}
+
+impl<'a> Iter<'a> {
+    fn new(roots: Vec<(String, &'a TypeAnnotation)>) -> Self {
+        let mut stack = roots;
+        stack.reverse();
+        Iter { stack }
+    }
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = (String, &'a TypeAnnotation);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (path, ann) = self.stack.pop()?;
+        if let Some(children) = ann.children() {
+            for (key, child) in children.iter().rev() {
+                self.stack.push((join_path(&path, key), child));
+            }
+        }
+        Some((path, ann))
+    }
+}
+
+impl TypeAnnotation {
+    /// Iterate over this annotation and all nested annotations.
+    ///
+    /// This annotation is yielded first with the empty path; nested
+    /// annotations are yielded with paths relative to it.
+    pub fn iter(&self) -> Iter<'_> {
+        Iter::new(vec![(String::new(), self)])
+    }
+
+    /// Call `f` with the path and type name of every annotation in the tree.
+    pub fn walk<F: FnMut(&str, &str)>(&self, mut f: F) {
+        for (path, ann) in self.iter() {
+            f(&path, ann.type_name());
+        }
+    }
+
+    /// Merge `other` into this annotation.
+    ///
+    /// If both annotations have the same type name, their children are merged
+    /// recursively (entries from `other` win on conflict). Otherwise `other`
+    /// replaces this annotation entirely, as does any custom or symbol
+    /// annotation and any class annotation of another class.
+    pub fn merge(&mut self, other: TypeAnnotation) {
+        if self.type_name() != other.type_name() {
+            *self = other;
+            return;
+        }
+        match (self, other) {
+            (TypeAnnotation::Node(_, children), TypeAnnotation::Node(_, other_children)) => {
+                merge_children(children, other_children);
+            }
+            (this @ TypeAnnotation::Leaf(_), TypeAnnotation::Node(name, other_children)) => {
+                *this = TypeAnnotation::Node(name, other_children);
+            }
+            (
+                TypeAnnotation::Class(name, children),
+                TypeAnnotation::Class(other_name, other_children),
+            ) if *name == other_name => {
+                merge_children(children, other_children);
+            }
+            (
+                this @ (TypeAnnotation::Custom(_)
+                | TypeAnnotation::Class(..)
+                | TypeAnnotation::Symbol(_)
+                | TypeAnnotation::Compound(..)),
+                other,
+            ) => *this = other,
+            _ => {}
+        }
+    }
+}
+
+impl AnnotationValues {
+    /// Iterate over every annotation in the tree with its full path.
+    ///
+    /// For `Root`, the root annotation is yielded first with the empty path.
+    pub fn iter(&self) -> Iter<'_> {
+        match self {
+            AnnotationValues::Root(ann) => ann.iter(),
+            AnnotationValues::Children(children) => Iter::new(
+                children
+                    .iter()
+                    .map(|(key, ann)| (key.clone(), ann))
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Call `f` with the path and type name of every annotation in the tree.
+    pub fn walk<F: FnMut(&str, &str)>(&self, mut f: F) {
+        for (path, ann) in self.iter() {
+            f(&path, ann.type_name());
+        }
+    }
+
+    /// Find all annotations located at `prefix` or beneath it.
+    ///
+    /// The prefix is matched on whole path segments, so `"a"` matches `"a"`
+    /// and `"a.b"` but not `"ab"`.
+    ///
+    /// # Examples
+    /// ```
+    /// use indexmap::IndexMap;
+    /// use superjson_rs::{AnnotationValues, TypeAnnotation};
+    ///
+    /// let mut children = IndexMap::new();
+    /// children.insert("a.b".to_string(), TypeAnnotation::Leaf("Date".into()));
+    /// children.insert("ab".to_string(), TypeAnnotation::Leaf("bigint".into()));
+    /// let values = AnnotationValues::Children(children);
+    ///
+    /// let found: Vec<_> = values.find_by_prefix("a").map(|(path, _)| path).collect();
+    /// assert_eq!(found, vec!["a.b"]);
+    /// ```
+    pub fn find_by_prefix<'a>(
+        &'a self,
+        prefix: &'a str,
+    ) -> impl Iterator<Item = (String, &'a TypeAnnotation)> + 'a {
+        self.iter()
+            .filter(move |(path, _)| path_starts_with(path, prefix))
+    }
+
+    /// Merge `other` into this tree.
+    ///
+    /// - `Children` + `Children`: the maps are combined, recursively merging
+    ///   annotations present in both.
+    /// - `Root` + `Root`: the root annotations are merged with
+    ///   [`TypeAnnotation::merge`].
+    /// - `Root` + `Children` (either order): the root annotation wins and the
+    ///   children are merged into its inner annotations.
+    pub fn merge(&mut self, other: AnnotationValues) {
+        let this = std::mem::replace(self, AnnotationValues::Children(IndexMap::new()));
+        *self = match (this, other) {
+            (AnnotationValues::Children(mut a), AnnotationValues::Children(b)) => {
+                merge_children(&mut a, b);
+                AnnotationValues::Children(a)
+            }
+            (AnnotationValues::Root(mut a), AnnotationValues::Root(b)) => {
+                a.merge(b);
+                AnnotationValues::Root(a)
+            }
+            (AnnotationValues::Root(mut root), AnnotationValues::Children(children))
+            | (AnnotationValues::Children(children), AnnotationValues::Root(mut root)) => {
+                if !children.is_empty() {
+                    let name = root.type_name().to_string();
+                    root.merge(TypeAnnotation::Node(name, children));
+                }
+                AnnotationValues::Root(root)
+            }
+        };
+    }
+}
+
+fn merge_children(
+    target: &mut IndexMap<String, TypeAnnotation>,
+    source: IndexMap<String, TypeAnnotation>,
+) {
+    for (key, ann) in source {
+        match target.get_mut(&key) {
+            Some(existing) => existing.merge(ann),
+            None => {
+                target.insert(key, ann);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn leaf(name: &str) -> TypeAnnotation {
+        TypeAnnotation::Leaf(name.to_string())
+    }
+
+    fn node(name: &str, children: &[(&str, TypeAnnotation)]) -> TypeAnnotation {
+        TypeAnnotation::Node(
+            name.to_string(),
+            children
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.clone()))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_iter_leaf() {
+        let ann = leaf("Date");
+        let items: Vec<_> = ann.iter().map(|(p, a)| (p, a.type_name())).collect();
+        assert_eq!(items, vec![(String::new(), "Date")]);
+    }
+
+    #[test]
+    fn test_iter_nested_node() {
+        let ann = node(
+            "map",
+            &[
+                ("0.0", leaf("number")),
+                ("1.1", node("set", &[("0", leaf("Date"))])),
+            ],
+        );
+        let mut items = Vec::new();
+        ann.walk(|path, name| items.push(format!("{path}={name}")));
+        assert_eq!(items, vec!["=map", "0.0=number", "1.1=set", "1.1.0=Date"]);
+    }
+
+    #[test]
+    fn test_iter_children() {
+        let mut children = IndexMap::new();
+        children.insert("a".to_string(), node("set", &[("1", leaf("undefined"))]));
+        children.insert("b".to_string(), leaf("bigint"));
+        let values = AnnotationValues::Children(children);
+
+        let paths: Vec<_> = values.iter().map(|(p, _)| p).collect();
+        assert_eq!(paths, vec!["a", "a.1", "b"]);
+    }
+
+    #[test]
+    fn test_find_by_prefix_matches_whole_segments() {
+        let mut children = IndexMap::new();
+        children.insert("user.created".to_string(), leaf("Date"));
+        children.insert("users".to_string(), leaf("set"));
+        children.insert("user".to_string(), node("map", &[("0.1", leaf("bigint"))]));
+        let values = AnnotationValues::Children(children);
+
+        let paths: Vec<_> = values.find_by_prefix("user").map(|(p, _)| p).collect();
+        assert_eq!(paths, vec!["user.created", "user", "user.0.1"]);
+    }
+
+    #[test]
+    fn test_merge_children() {
+        let mut a = IndexMap::new();
+        a.insert("x".to_string(), leaf("Date"));
+        a.insert("s".to_string(), node("set", &[("0", leaf("Date"))]));
+        let mut b = IndexMap::new();
+        b.insert("y".to_string(), leaf("bigint"));
+        b.insert("s".to_string(), node("set", &[("1", leaf("undefined"))]));
+
+        let mut values = AnnotationValues::Children(a);
+        values.merge(AnnotationValues::Children(b));
+
+        let mut expected = IndexMap::new();
+        expected.insert("x".to_string(), leaf("Date"));
+        expected.insert(
+            "s".to_string(),
+            node("set", &[("0", leaf("Date")), ("1", leaf("undefined"))]),
+        );
+        expected.insert("y".to_string(), leaf("bigint"));
+        assert_eq!(values, AnnotationValues::Children(expected));
+    }
+
+    #[test]
+    fn test_composite_identifiers_round_trip() {
+        let json = serde_json::json!({
+            "a": [["custom", "Decimal"]],
+            "b": [["symbol", "admin"]],
+            "c": [["class", "Point"], {"x": ["number"]}],
+            "d": [["typed-array", "Int8Array"]]
+        });
+        let values = AnnotationValues::from_json(&json, 8).unwrap();
+        let AnnotationValues::Children(children) = &values else {
+            panic!("expected children");
+        };
+        assert_eq!(
+            children["d"],
+            TypeAnnotation::Compound("typed-array".into(), "Int8Array".into())
+        );
+        assert_eq!(children["d"].type_name(), "typed-array");
+        assert_eq!(serde_json::to_value(&values).unwrap(), json);
+    }
+
+    #[test]
+    fn test_borrowed_and_owned_json_agree() {
+        let json = serde_json::json!({
+            "a": [["custom", "Decimal"]],
+            "b": ["set", {"0": ["map", null], "1": [["class", "Empty"]]}],
+            "c": [["class", "Point"], {"x": ["number", {}]}],
+            "d": [["typed-array", "Int8Array"]]
+        });
+        let mut borrowed_warnings = Vec::new();
+        let borrowed = AnnotationValues::from_json_with_warnings(
+            std::borrow::Cow::Borrowed(&json),
+            8,
+            &mut borrowed_warnings,
+        )
+        .unwrap();
+        let mut owned_warnings = Vec::new();
+        let owned = AnnotationValues::from_json_with_warnings(
+            std::borrow::Cow::Owned(json.clone()),
+            8,
+            &mut owned_warnings,
+        )
+        .unwrap();
+        assert_eq!(borrowed, owned);
+        assert_eq!(borrowed_warnings, owned_warnings);
+        assert_eq!(borrowed_warnings.len(), 2);
+        let AnnotationValues::Children(children) = &owned else {
+            panic!("expected children");
+        };
+        assert_eq!(
+            children["c"],
+            TypeAnnotation::Class("Point".into(), {
+                let mut fields = IndexMap::new();
+                fields.insert("x".to_string(), leaf("number"));
+                fields
+            })
+        );
+    }
+
+    #[test]
+    fn test_merge_different_types_replaces() {
+        let mut ann = node("set", &[("0", leaf("Date"))]);
+        ann.merge(leaf("map"));
+        assert_eq!(ann, leaf("map"));
+    }
+
+    #[test]
+    fn test_merge_root_with_children() {
+        let mut children = IndexMap::new();
+        children.insert("1".to_string(), leaf("undefined"));
+        let mut values = AnnotationValues::Root(leaf("set"));
+        values.merge(AnnotationValues::Children(children));
+        assert_eq!(
+            values,
+            AnnotationValues::Root(node("set", &[("1", leaf("undefined"))]))
+        );
+    }
+}
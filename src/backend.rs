@@ -0,0 +1,172 @@
+//! Swappable JSON engines for the text step of parsing and stringifying.
+//!
+//! Everything between JSON text and a [`Value`] is done by this crate, but
+//! reading and writing the text itself is delegated to a [`JsonBackend`].
+//! The default, [`SerdeJson`], is what [`parse`](crate::parse) and
+//! [`stringify`](crate::stringify) use. Any serde-compatible engine, such as
+//! `sonic-rs` or `simd-json`, can be plugged in by implementing the trait and
+//! calling [`parse_with`] and [`stringify_with`].
+//!
+//! The `json` part of a [`SuperJson`] is a `serde_json::Value` by default.
+//! [`serialize_into`] and [`deserialize_from`] work with a `SuperJson<J>`
+//! holding any serde-compatible document type instead, such as an engine's
+//! own DOM, and such an envelope is read and written with the engine as any
+//! other serde type is.
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+use crate::{ParseOptions, Result, SerializeOptions, SuperJson, Value, deserialize, serialize};
+
+/// A JSON engine that reads and writes any serde type.
+///
+/// Errors from engines other than `serde_json` are reported as
+/// [`Error::Backend`](crate::Error::Backend).
+///
+/// # Examples
+/// ```
+/// use serde::Serialize;
+/// use serde::de::DeserializeOwned;
+/// use superjson_rs::backend::{self, JsonBackend};
+/// use superjson_rs::{Error, ParseOptions, Result, SerializeOptions, Value};
+///
+/// /// Accepts only documents with no leading whitespace.
+/// struct Strict;
+///
+/// impl JsonBackend for Strict {
+///     fn parse<T: DeserializeOwned>(&self, s: &str) -> Result<T> {
+///         if s.starts_with(char::is_whitespace) {
+///             return Err(Error::Backend("leading whitespace".into()));
+///         }
+///         Ok(serde_json::from_str(s)?)
+///     }
+///
+///     fn emit<T: Serialize + ?Sized>(&self, value: &T) -> Result<String> {
+///         Ok(serde_json::to_string(value)?)
+///     }
+/// }
+///
+/// let text = backend::stringify_with(&Strict, &Value::NaN, &SerializeOptions::default()).unwrap();
+/// assert_eq!(backend::parse_with(&Strict, &text, &ParseOptions::default()).unwrap(), Value::NaN);
+/// assert!(matches!(
+///     backend::parse_with(&Strict, " {}", &ParseOptions::default()),
+///     Err(Error::Backend(_))
+/// ));
+/// ```
+pub trait JsonBackend {
+    /// Read a value from JSON text.
+    fn parse<T: DeserializeOwned>(&self, s: &str) -> Result<T>;
+
+    /// Write a value as compact JSON text.
+    fn emit<T: Serialize + ?Sized>(&self, value: &T) -> Result<String>;
+}
+
+/// The default engine, `serde_json`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SerdeJson;
+
+impl JsonBackend for SerdeJson {
+    fn parse<T: DeserializeOwned>(&self, s: &str) -> Result<T> {
+        Ok(serde_json::from_str(s)?)
+    }
+
+    fn emit<T: Serialize + ?Sized>(&self, value: &T) -> Result<String> {
+        Ok(serde_json::to_string(value)?)
+    }
+}
+
+/// Reads JSON5 and writes plain JSON, which is valid JSON5.
+///
+/// See [`parse_json5`](crate::parse_json5).
+#[cfg(feature = "json5")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Json5;
+
+#[cfg(feature = "json5")]
+impl JsonBackend for Json5 {
+    fn parse<T: DeserializeOwned>(&self, s: &str) -> Result<T> {
+        json5::from_str(s).map_err(|e| crate::Error::Backend(Box::new(e)))
+    }
+
+    fn emit<T: Serialize + ?Sized>(&self, value: &T) -> Result<String> {
+        SerdeJson.emit(value)
+    }
+}
+
+/// Parse a superjson JSON string with `backend`, as [`parse_with`](crate::parse_with)
+/// does with `serde_json`.
+pub fn parse_with<B: JsonBackend>(backend: &B, s: &str, options: &ParseOptions) -> Result<Value> {
+    crate::parse_detailed_in(backend, s, options, &[]).map(|parsed| parsed.value)
+}
+
+/// Serialize a `Value` into a superjson JSON string with `backend`, as
+/// [`stringify_with`](crate::stringify_with) does with `serde_json`.
+pub fn stringify_with<B: JsonBackend>(
+    backend: &B,
+    value: &Value,
+    options: &SerializeOptions,
+) -> Result<String> {
+    let superjson = serialize::serialize_detailed(value, options, &[], &mut Vec::new())?;
+    serialize::to_text_in(backend, &superjson, options)
+}
+
+/// Serialize a `Value` into an envelope whose `json` part is a `J`, as
+/// [`serialize_with`](crate::serialize::serialize_with) does with a
+/// `serde_json::Value`.
+///
+/// # Examples
+/// ```
+/// use serde_json::value::RawValue;
+/// use superjson_rs::backend::{deserialize_from, serialize_into};
+/// use superjson_rs::{ParseOptions, SerializeOptions, SuperJson, Value};
+///
+/// let value = Value::Set(vec![Value::from(1)]);
+/// let envelope: SuperJson<Box<RawValue>> =
+///     serialize_into(&value, &SerializeOptions::default()).unwrap();
+/// assert_eq!(envelope.json.get(), "[1]");
+/// assert_eq!(deserialize_from(&envelope, &ParseOptions::default()).unwrap(), value);
+/// ```
+pub fn serialize_into<J: DeserializeOwned>(
+    value: &Value,
+    options: &SerializeOptions,
+) -> Result<SuperJson<J>> {
+    let SuperJson { json, meta } = serialize::serialize_with(value, options)?;
+    Ok(SuperJson {
+        json: J::deserialize(json)?,
+        meta,
+    })
+}
+
+/// Restore a `Value` from an envelope whose `json` part is a `J`, as
+/// [`deserialize_with`](crate::deserialize::deserialize_with) does with a
+/// `serde_json::Value`.
+pub fn deserialize_from<J: Serialize>(
+    superjson: &SuperJson<J>,
+    options: &ParseOptions,
+) -> Result<Value> {
+    let json = serde_json::to_value(&superjson.json)?;
+    deserialize::deserialize_split_with(json, superjson.meta.clone(), options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::value::RawValue;
+
+    #[test]
+    fn test_envelopes_of_other_document_types() {
+        let value = Value::Map(vec![(Value::from("a"), Value::NaN)]);
+        let envelope: SuperJson<Box<RawValue>> =
+            serialize_into(&value, &SerializeOptions::default()).unwrap();
+        let text = SerdeJson.emit(&envelope).unwrap();
+        assert_eq!(text, crate::stringify(&value).unwrap());
+
+        let parsed: SuperJson<Box<RawValue>> = SerdeJson.parse(&text).unwrap();
+        assert_eq!(parsed.json.get(), r#"[["a","NaN"]]"#);
+        assert_eq!(parsed.version(), Some(crate::META_VERSION));
+        assert_eq!(
+            deserialize_from(&parsed, &ParseOptions::default()).unwrap(),
+            value
+        );
+    }
+}
@@ -0,0 +1,91 @@
+//! Several payloads in one document.
+//!
+//! A batch is a JSON array of ordinary envelopes, one per payload, as tRPC
+//! uses for batched responses. Each envelope keeps its own `meta`, so items
+//! can be produced and consumed independently.
+
+use crate::{ParseOptions, Result, Value, deserialize, serialize};
+
+/// Serialize several values into a single batch document.
+///
+/// # Examples
+/// ```
+/// use superjson_rs::{Value, stringify_batch};
+///
+/// let values = [Value::from("a"), Value::Set(vec![Value::from("b")])];
+/// assert_eq!(
+///     stringify_batch(&values).unwrap(),
+///     r#"[{"json":"a"},{"json":["b"],"meta":{"values":["set"],"v":1}}]"#
+/// );
+/// ```
+pub fn stringify_batch(values: &[Value]) -> Result<String> {
+    let envelopes = values
+        .iter()
+        .map(serialize::serialize)
+        .collect::<Result<Vec<_>>>()?;
+    Ok(serde_json::to_string(&envelopes)?)
+}
+
+/// Parse a batch document, restoring each item independently.
+///
+/// The outer `Result` fails only if `s` is not a JSON array. A malformed
+/// item yields an error in its own slot without affecting the others.
+///
+/// # Examples
+/// ```
+/// use superjson_rs::{Value, parse_batch};
+///
+/// let items = parse_batch(r#"[{"json":1},{"json":"x","meta":{"values":["bigint"]}}]"#).unwrap();
+/// assert_eq!(items[0].as_ref().unwrap(), &Value::from(1));
+/// assert!(items[1].is_err());
+/// ```
+pub fn parse_batch(s: &str) -> Result<Vec<Result<Value>>> {
+    parse_batch_with(s, &ParseOptions::default())
+}
+
+/// Parse a batch document, applying `options` to every item.
+pub fn parse_batch_with(s: &str, options: &ParseOptions) -> Result<Vec<Result<Value>>> {
+    let items: Vec<serde_json::Value> = serde_json::from_str(s)?;
+    Ok(items
+        .into_iter()
+        .map(|item| deserialize::deserialize_json_with(item, options))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Error;
+
+    #[test]
+    fn test_batch_roundtrip() {
+        let values = vec![
+            Value::Undefined,
+            Value::Array(vec![Value::NaN, Value::from("a")]),
+            Value::Map(vec![(Value::from(1), Value::Null)]),
+        ];
+        let s = stringify_batch(&values).unwrap();
+        let parsed: Vec<Value> = parse_batch(&s)
+            .unwrap()
+            .into_iter()
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(parsed, values);
+    }
+
+    #[test]
+    fn test_batch_isolates_item_errors() {
+        let items = parse_batch(r#"[{"json":1},{"meta":{}},42,{"json":2}]"#).unwrap();
+        assert_eq!(items.len(), 4);
+        assert_eq!(items[0].as_ref().unwrap(), &Value::from(1));
+        assert!(matches!(items[1], Err(Error::Json(_))));
+        assert!(matches!(items[2], Err(Error::Json(_))));
+        assert_eq!(items[3].as_ref().unwrap(), &Value::from(2));
+    }
+
+    #[test]
+    fn test_batch_requires_array() {
+        assert!(matches!(parse_batch(r#"{"json":1}"#), Err(Error::Json(_))));
+        assert!(parse_batch("[]").unwrap().is_empty());
+    }
+}
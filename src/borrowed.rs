@@ -0,0 +1,336 @@
+//! Parsing that borrows strings from the input.
+//!
+//! [`parse`](crate::parse) copies every key and string it reads into an
+//! owned [`Value`](crate::Value). Proxies and routers that only look at a
+//! payload can use [`parse_borrowed`](crate::parse_borrowed) instead, which
+//! keeps keys and strings as slices of the input wherever they need no
+//! unescaping.
+//!
+//! Only plain JSON is kept borrowed. A value with a type annotation, such as
+//! a `Date` or a `Set`, is restored as usual, as an owned value.
+
+use std::borrow::Cow;
+use std::fmt;
+
+use serde::de::{self, MapAccess, SeqAccess, Visitor};
+use serde::{Deserialize, Deserializer};
+
+use crate::path::PathSegment;
+use crate::value::Map;
+use crate::warning::Warning;
+
+/// The result of [`parse_borrowed_detailed`](crate::parse_borrowed_detailed).
+#[derive(Debug, Clone)]
+pub struct Parsed<'a> {
+    pub value: Value<'a>,
+    /// Non-fatal issues noticed while parsing, in the order they were found.
+    pub warnings: Vec<Warning>,
+}
+
+/// A parsed value whose keys and strings may borrow from the input.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value<'a> {
+    Null,
+    Bool(bool),
+    Number(f64),
+    Int(i64),
+    UInt(u64),
+    String(Cow<'a, str>),
+    Array(Vec<Value<'a>>),
+    Object(Map<Cow<'a, str>, Value<'a>>),
+    /// A value with a type annotation of its own, restored as an owned
+    /// [`Value`](crate::Value).
+    Extended(crate::Value),
+}
+
+impl<'a> Value<'a> {
+    /// The string, if this is one.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// The fields, if this is a plain object.
+    pub fn as_object(&self) -> Option<&Map<Cow<'a, str>, Value<'a>>> {
+        match self {
+            Value::Object(map) => Some(map),
+            _ => None,
+        }
+    }
+
+    /// Look up a value by superjson path, such as `"users.0.name"`.
+    ///
+    /// Only plain objects and arrays are descended into; use
+    /// [`Value::pointer`](crate::Value::pointer) on an
+    /// [`Extended`](Value::Extended) value to look inside it.
+    ///
+    /// # Examples
+    /// ```
+    /// use superjson_rs::parse_borrowed;
+    ///
+    /// let value = parse_borrowed(r#"{"json":{"users":[{"name":"ada"}]}}"#).unwrap();
+    /// assert_eq!(value.pointer("users.0.name").and_then(|v| v.as_str()), Some("ada"));
+    /// ```
+    pub fn pointer(&self, path: &str) -> Option<&Value<'a>> {
+        let mut current = self;
+        for segment in crate::path::parse(path) {
+            current = match (current, segment) {
+                (Value::Object(map), PathSegment::Key(key)) => map.get(key.as_str())?,
+                (Value::Object(map), PathSegment::Index(index)) => {
+                    map.get(index.to_string().as_str())?
+                }
+                (Value::Array(items), PathSegment::Index(index)) => items.get(index)?,
+                _ => return None,
+            };
+        }
+        Some(current)
+    }
+
+    /// Copy any borrowed keys and strings into an owned
+    /// [`Value`](crate::Value).
+    ///
+    /// # Examples
+    /// ```
+    /// use superjson_rs::{Value, parse_borrowed};
+    ///
+    /// let value = parse_borrowed(r#"{"json":{"s":[1]},"meta":{"values":{"s":["set"]}}}"#).unwrap();
+    /// assert_eq!(
+    ///     value.into_owned().pointer("s"),
+    ///     Some(&Value::Set(vec![Value::from(1)]))
+    /// );
+    /// ```
+    pub fn into_owned(self) -> crate::Value {
+        match self {
+            Value::Null => crate::Value::Null,
+            Value::Bool(b) => crate::Value::Bool(b),
+            Value::Number(n) => crate::Value::Number(n),
+            Value::Int(n) => crate::Value::Int(n),
+            Value::UInt(n) => crate::Value::UInt(n),
+            Value::String(s) => crate::Value::String(s.into_owned()),
+            Value::Array(items) => {
+                crate::Value::Array(items.into_iter().map(Value::into_owned).collect())
+            }
+            Value::Object(map) => crate::Value::Object(
+                map.into_iter()
+                    .map(|(key, value)| (key.into_owned(), value.into_owned()))
+                    .collect(),
+            ),
+            Value::Extended(value) => value,
+        }
+    }
+
+    /// The value as ordinary JSON, with extended values in their
+    /// JSON-compatible encodings.
+    ///
+    /// Values are only ever read by `serde_json`, whose recursion limit
+    /// bounds their depth, so converting them recursively is safe.
+    pub(crate) fn to_json(&self) -> serde_json::Value {
+        match self {
+            Value::Null => serde_json::Value::Null,
+            Value::Bool(b) => (*b).into(),
+            Value::Number(n) => (*n).into(),
+            Value::Int(n) => (*n).into(),
+            Value::UInt(n) => (*n).into(),
+            Value::String(s) => s.as_ref().into(),
+            Value::Array(items) => items.iter().map(Value::to_json).collect(),
+            Value::Object(map) => map
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_json()))
+                .collect(),
+            Value::Extended(value) => crate::serialize::serialize(value)
+                .map_or(serde_json::Value::Null, |superjson| superjson.json),
+        }
+    }
+}
+
+impl fmt::Display for Value<'_> {
+    /// Formats the value as [`Value`](crate::Value) does.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.clone().into_owned(), f)
+    }
+}
+
+impl<'de: 'a, 'a> Deserialize<'de> for Value<'a> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        deserializer.deserialize_any(ValueVisitor)
+    }
+}
+
+struct ValueVisitor;
+
+impl<'de> Visitor<'de> for ValueVisitor {
+    type Value = Value<'de>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("any JSON value")
+    }
+
+    fn visit_unit<E: de::Error>(self) -> std::result::Result<Value<'de>, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_bool<E: de::Error>(self, b: bool) -> std::result::Result<Value<'de>, E> {
+        Ok(Value::Bool(b))
+    }
+
+    fn visit_i64<E: de::Error>(self, n: i64) -> std::result::Result<Value<'de>, E> {
+        Ok(Value::Int(n))
+    }
+
+    fn visit_u64<E: de::Error>(self, n: u64) -> std::result::Result<Value<'de>, E> {
+        Ok(i64::try_from(n).map_or(Value::UInt(n), Value::Int))
+    }
+
+    fn visit_f64<E: de::Error>(self, n: f64) -> std::result::Result<Value<'de>, E> {
+        Ok(Value::Number(n))
+    }
+
+    fn visit_borrowed_str<E: de::Error>(self, s: &'de str) -> std::result::Result<Value<'de>, E> {
+        Ok(Value::String(Cow::Borrowed(s)))
+    }
+
+    fn visit_str<E: de::Error>(self, s: &str) -> std::result::Result<Value<'de>, E> {
+        Ok(Value::String(Cow::Owned(s.to_string())))
+    }
+
+    fn visit_string<E: de::Error>(self, s: String) -> std::result::Result<Value<'de>, E> {
+        Ok(Value::String(Cow::Owned(s)))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> std::result::Result<Value<'de>, A::Error> {
+        let mut items = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(item) = seq.next_element()? {
+            items.push(item);
+        }
+        Ok(Value::Array(items))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> std::result::Result<Value<'de>, A::Error> {
+        let mut fields = Map::new();
+        while let Some((Key(key), value)) = map.next_entry()? {
+            fields.insert(key, value);
+        }
+        Ok(Value::Object(fields))
+    }
+}
+
+/// An object key, borrowed where it needs no unescaping.
+///
+/// `Cow<str>` itself always deserializes into an owned string.
+pub(crate) struct Key<'a>(pub(crate) Cow<'a, str>);
+
+impl<'de: 'a, 'a> Deserialize<'de> for Key<'a> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        deserializer.deserialize_str(KeyVisitor)
+    }
+}
+
+struct KeyVisitor;
+
+impl<'de> Visitor<'de> for KeyVisitor {
+    type Value = Key<'de>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("an object key")
+    }
+
+    fn visit_borrowed_str<E: de::Error>(self, s: &'de str) -> std::result::Result<Key<'de>, E> {
+        Ok(Key(Cow::Borrowed(s)))
+    }
+
+    fn visit_str<E: de::Error>(self, s: &str) -> std::result::Result<Key<'de>, E> {
+        Ok(Key(Cow::Owned(s.to_string())))
+    }
+
+    fn visit_string<E: de::Error>(self, s: String) -> std::result::Result<Key<'de>, E> {
+        Ok(Key(Cow::Owned(s)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        ParseOptions, Warning, parse_borrowed, parse_borrowed_detailed, parse_borrowed_with,
+    };
+
+    #[test]
+    fn test_strings_borrow_unless_escaped() {
+        let value = parse_borrowed(r#"{"json":{"plain":"ada","esc\"aped":"a\nb"}}"#).unwrap();
+        let Value::Object(map) = &value else {
+            panic!("expected an object");
+        };
+        let (plain_key, plain) = map.get_key_value("plain").unwrap();
+        assert!(matches!(plain_key, Cow::Borrowed("plain")));
+        assert!(matches!(plain, Value::String(Cow::Borrowed("ada"))));
+        let (escaped_key, escaped) = map.get_key_value("esc\"aped").unwrap();
+        assert!(matches!(escaped_key, Cow::Owned(_)));
+        assert!(matches!(escaped, Value::String(Cow::Owned(s)) if s == "a\nb"));
+    }
+
+    #[test]
+    fn test_annotated_values_are_restored() {
+        let text = r#"{"json":{"a":{"d":"2024-01-01T00:00:00.000Z","n":"NaN","k":"v"},"s":[["x"]]},
+            "meta":{"values":{"a.n":["number"],"s":["set",{"0":["set"]}]}}}"#;
+        let value = parse_borrowed(text).unwrap();
+        assert_eq!(
+            value.pointer("a.n"),
+            Some(&Value::Extended(crate::Value::NaN))
+        );
+        assert!(matches!(
+            value.pointer("a.k"),
+            Some(Value::String(Cow::Borrowed("v")))
+        ));
+        assert_eq!(value.clone().into_owned(), crate::parse(text).unwrap());
+    }
+
+    #[test]
+    fn test_warnings_are_reported() {
+        let text = r#"{"json":{"k":"v","u":null},"meta":{"values":{"u":["undefined"]},"extra":1}}"#;
+        let parsed = parse_borrowed_detailed(text, &ParseOptions::default()).unwrap();
+        assert_eq!(
+            parsed.value.clone().into_owned(),
+            crate::parse(text).unwrap()
+        );
+        assert_eq!(
+            parsed.warnings,
+            [Warning::UnknownField {
+                name: "meta.extra".into()
+            }]
+        );
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn test_date_warnings_are_reported() {
+        let text =
+            r#"{"json":{"at":"2024-01-01T09:00:00.000+09:00"},"meta":{"values":{"at":["Date"]}}}"#;
+        let parsed = parse_borrowed_detailed(text, &ParseOptions::default()).unwrap();
+        assert!(matches!(
+            parsed.warnings.as_slice(),
+            [Warning::CoercedDate { path, .. }] if path == "at"
+        ));
+    }
+
+    #[test]
+    fn test_depth_limit() {
+        let options = ParseOptions {
+            max_depth: 2,
+            ..ParseOptions::default()
+        };
+        assert!(parse_borrowed_with(r#"{"json":{"a":{"b":1}}}"#, &options).is_ok());
+        assert!(matches!(
+            parse_borrowed_with(r#"{"json":{"a":{"b":{"c":1}}}}"#, &options),
+            Err(crate::Error::DepthLimitExceeded(2))
+        ));
+        assert!(matches!(
+            parse_borrowed_with(
+                r#"{"json":{"a":{"s":[[1]]}},"meta":{"values":{"a.s":["set"]}}}"#,
+                &options
+            ),
+            Err(crate::Error::DepthLimitExceeded(2))
+        ));
+    }
+}
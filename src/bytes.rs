@@ -0,0 +1,117 @@
+//! Text encodings of [`Value::Bytes`].
+//!
+//! JSON has no binary type, so bytes travel as a string under the custom
+//! annotation `["custom", "bytes"]`. A JS side reads them by registering a
+//! transformer under the same name, such as:
+//!
+//! ```js
+//! SuperJSON.registerCustom<Uint8Array, string>(
+//!   {
+//!     isApplicable: (v): v is Uint8Array => v instanceof Uint8Array,
+//!     serialize: (v) => Buffer.from(v).toString("base64"),
+//!     deserialize: (v) => new Uint8Array(Buffer.from(v, "base64")),
+//!   },
+//!   "bytes",
+//! );
+//! ```
+//!
+//! Both sides must agree on the [`BytesEncoding`], set by
+//! [`SerializeOptions::bytes_encoding`](crate::SerializeOptions::bytes_encoding)
+//! and [`ParseOptions::bytes_encoding`](crate::ParseOptions::bytes_encoding).
+//!
+//! Node apps often register `Buffer` under a name of their own, e.g.
+//! `"buffer"`; [`SerializeOptions::bytes_name`](crate::SerializeOptions::bytes_name)
+//! and [`ParseOptions::bytes_name`](crate::ParseOptions::bytes_name), or
+//! [`SuperJsonCodec::register_bytes`](crate::SuperJsonCodec::register_bytes),
+//! read and write bytes under that name instead.
+
+use base64::Engine;
+use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
+
+#[cfg(doc)]
+use crate::Value;
+
+/// The custom type name [`Value::Bytes`] is annotated with by default.
+pub const BYTES: &str = "bytes";
+
+/// How [`Value::Bytes`] are written as a string.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BytesEncoding {
+    /// Standard base64 with padding, as `Buffer.toString("base64")` writes
+    /// it.
+    #[default]
+    Base64,
+    /// URL-safe base64 without padding, as `Buffer.toString("base64url")`
+    /// writes it. Padding is accepted when decoding.
+    Base64Url,
+    /// Lowercase hexadecimal. Either case is accepted when decoding.
+    Hex,
+}
+
+impl BytesEncoding {
+    pub fn encode(self, bytes: &[u8]) -> String {
+        match self {
+            BytesEncoding::Base64 => STANDARD.encode(bytes),
+            BytesEncoding::Base64Url => URL_SAFE_NO_PAD.encode(bytes),
+            BytesEncoding::Hex => hex(bytes),
+        }
+    }
+
+    /// The bytes `text` encodes, or `None` if it is not valid in this
+    /// encoding.
+    pub fn decode(self, text: &str) -> Option<Vec<u8>> {
+        match self {
+            BytesEncoding::Base64 => STANDARD.decode(text).ok(),
+            BytesEncoding::Base64Url => URL_SAFE_NO_PAD.decode(text.trim_end_matches('=')).ok(),
+            BytesEncoding::Hex => unhex(text),
+        }
+    }
+
+    /// The name of the encoding, as Node's `Buffer` calls it.
+    pub fn name(self) -> &'static str {
+        match self {
+            BytesEncoding::Base64 => "base64",
+            BytesEncoding::Base64Url => "base64url",
+            BytesEncoding::Hex => "hex",
+        }
+    }
+}
+
+/// `bytes` as lowercase hexadecimal.
+pub(crate) fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// The bytes hexadecimal `text` of either case encodes, or `None` if it is
+/// not hexadecimal.
+pub(crate) fn unhex(text: &str) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(text.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encodings_roundtrip() {
+        let bytes = [0u8, 0xfb, 0xff, 0x10, 0x7e];
+        for (encoding, text) in [
+            (BytesEncoding::Base64, "APv/EH4="),
+            (BytesEncoding::Base64Url, "APv_EH4"),
+            (BytesEncoding::Hex, "00fbff107e"),
+        ] {
+            assert_eq!(encoding.encode(&bytes), text);
+            assert_eq!(encoding.decode(text).unwrap(), bytes);
+        }
+        assert_eq!(BytesEncoding::Base64Url.decode("APv_EH4=").unwrap(), bytes);
+        assert_eq!(BytesEncoding::Hex.decode("00FBFF107E").unwrap(), bytes);
+        assert_eq!(BytesEncoding::Hex.decode("0"), None);
+        assert_eq!(BytesEncoding::Base64.decode("APv_EH4="), None);
+    }
+}
@@ -0,0 +1,511 @@
+//! The canonical encodings of values.
+//!
+//! Values that compare equal, regardless of object key order, have the same
+//! encoding. It is digested by `Value::content_hash` and used as the key of
+//! [`ValueMap`](crate::value_map::ValueMap); the format is documented in the
+//! `hash` module.
+//!
+//! [`to_jcs`] writes JSON text in the JSON Canonicalization Scheme of
+//! RFC 8785 instead, which other implementations, such as JS ones, can
+//! reproduce byte for byte to sign or verify an envelope.
+
+use std::collections::HashMap;
+use std::hash::{BuildHasher, DefaultHasher, Hasher, RandomState};
+
+use crate::Value;
+
+/// A destination for encoded bytes.
+pub(crate) trait Sink {
+    fn write(&mut self, bytes: &[u8]);
+}
+
+impl Sink for Vec<u8> {
+    fn write(&mut self, bytes: &[u8]) {
+        self.extend_from_slice(bytes);
+    }
+}
+
+/// The canonical encoding of `value`.
+pub(crate) fn to_bytes(value: &Value) -> Vec<u8> {
+    let mut out = Vec::new();
+    encode(value, &mut out);
+    out
+}
+
+/// A part of a value's encoding written after the value's own bytes: a
+/// string, or the encoding of a child value.
+pub(crate) enum Item<'a> {
+    Value(&'a Value),
+    Str(&'a str),
+}
+
+/// Write the canonical encoding of `value` to `out`, without recursion.
+pub(crate) fn encode(value: &Value, out: &mut impl Sink) {
+    let mut stack = vec![Item::Value(value)];
+
+    while let Some(item) = stack.pop() {
+        match item {
+            Item::Value(value) => stack.extend(encode_node(value, out).into_iter().rev()),
+            Item::Str(s) => write_str(out, s),
+        }
+    }
+}
+
+/// Write the bytes `value` itself contributes to its encoding to `out`,
+/// returning the strings and children that follow them, in encoding order.
+pub(crate) fn encode_node<'a>(value: &'a Value, out: &mut impl Sink) -> Vec<Item<'a>> {
+    let mut pending = Vec::new();
+    match value {
+        Value::Null => out.write(&[0]),
+        Value::Bool(b) => out.write(&[1, u8::from(*b)]),
+        Value::Number(n) => write_number(out, *n),
+        Value::Int(n) => write_integer(out, (*n).into()),
+        Value::UInt(n) => write_integer(out, (*n).into()),
+        Value::String(s) => {
+            out.write(&[3]);
+            write_str(out, s);
+        }
+        Value::Array(items) => {
+            write_count(out, 4, items.len());
+            pending.extend(items.iter().map(Item::Value));
+        }
+        Value::Object(map) => {
+            write_count(out, 5, map.len());
+            let mut entries: Vec<_> = map.iter().collect();
+            entries.sort_unstable_by_key(|(key, _)| *key);
+            for (key, value) in entries {
+                pending.push(Item::Str(key));
+                pending.push(Item::Value(value));
+            }
+        }
+        Value::Undefined => out.write(&[6]),
+        #[cfg(feature = "chrono")]
+        Value::Date(dt) => {
+            out.write(&[7]);
+            out.write(&dt.timestamp().to_be_bytes());
+            out.write(&dt.timestamp_subsec_nanos().to_be_bytes());
+        }
+        #[cfg(feature = "chrono")]
+        Value::OffsetDate(dt) => {
+            out.write(&[22]);
+            out.write(&dt.timestamp().to_be_bytes());
+            out.write(&dt.timestamp_subsec_nanos().to_be_bytes());
+            out.write(&dt.offset().local_minus_utc().to_be_bytes());
+        }
+        #[cfg(feature = "chrono")]
+        Value::InvalidDate => out.write(&[25]),
+        #[cfg(feature = "bigint")]
+        Value::BigInt(n) => {
+            let bytes = n.to_signed_bytes_be();
+            write_count(out, 8, bytes.len());
+            out.write(&bytes);
+        }
+        Value::Set(items) => {
+            write_count(out, 9, items.len());
+            pending.extend(items.iter().map(Item::Value));
+        }
+        Value::Map(entries) => {
+            write_count(out, 10, entries.len());
+            for (key, value) in entries {
+                pending.push(Item::Value(key));
+                pending.push(Item::Value(value));
+            }
+        }
+        Value::NaN => out.write(&[11]),
+        Value::PosInfinity => out.write(&[12]),
+        Value::NegInfinity => out.write(&[13]),
+        Value::NegZero => out.write(&[14]),
+        Value::RegExp { source, flags } => {
+            out.write(&[15]);
+            write_str(out, source);
+            write_str(out, flags);
+        }
+        Value::Url(url) => {
+            out.write(&[16]);
+            write_str(out, url.as_str());
+        }
+        Value::Error {
+            name,
+            message,
+            stack,
+            cause,
+            extra,
+        } => {
+            out.write(&[17]);
+            write_str(out, name);
+            write_str(out, message);
+            // Errors without extra properties or a stack keep the
+            // encoding they had before either existed.
+            let extra_flag = if extra.is_empty() { 0 } else { 2 };
+            let stack_flag = if stack.is_some() { 4 } else { 0 };
+            out.write(&[u8::from(cause.is_some()) | extra_flag | stack_flag]);
+            if let Some(stack) = stack {
+                write_str(out, stack);
+            }
+            if !extra.is_empty() {
+                out.write(&(extra.len() as u64).to_be_bytes());
+                let mut entries: Vec<_> = extra.iter().collect();
+                entries.sort_unstable_by_key(|(key, _)| *key);
+                for (key, value) in entries {
+                    pending.push(Item::Str(key));
+                    pending.push(Item::Value(value));
+                }
+            }
+            if let Some(cause) = cause {
+                pending.push(Item::Value(cause));
+            }
+        }
+        Value::Custom { name, value } => {
+            out.write(&[18]);
+            write_str(out, name);
+            pending.push(Item::Value(value));
+        }
+        Value::Symbol(id) => {
+            out.write(&[20]);
+            write_str(out, id);
+        }
+        Value::Bytes(bytes) => {
+            write_count(out, 21, bytes.len());
+            out.write(bytes);
+        }
+        Value::Unknown { type_name, raw } => {
+            out.write(&[24]);
+            write_str(out, type_name);
+            encode_json(raw, out);
+        }
+        Value::Class { name, fields } => {
+            write_count(out, 19, fields.len());
+            write_str(out, name);
+            let mut entries: Vec<_> = fields.iter().collect();
+            entries.sort_unstable_by_key(|(key, _)| *key);
+            for (key, value) in entries {
+                pending.push(Item::Str(key));
+                pending.push(Item::Value(value));
+            }
+        }
+    }
+    pending
+}
+
+/// Digests of the canonical encodings of `value` and of each value within
+/// it, keyed by address.
+///
+/// A value's digest is taken over its own bytes and the digests of its
+/// children in place of their encodings, so the whole tree is digested in
+/// time linear in its size. Digests are keyed afresh for each call and only
+/// comparable with each other.
+pub(crate) fn digests(value: &Value) -> HashMap<*const Value, u128> {
+    let keys = (RandomState::new(), RandomState::new());
+    let mut digests = HashMap::new();
+    let mut stack = vec![(value, false)];
+
+    while let Some((value, children_done)) = stack.pop() {
+        if !children_done {
+            stack.push((value, true));
+            for item in encode_node(value, &mut Discard) {
+                if let Item::Value(child) = item {
+                    stack.push((child, false));
+                }
+            }
+            continue;
+        }
+        let mut digest = Digest(keys.0.build_hasher(), keys.1.build_hasher());
+        for item in encode_node(value, &mut digest) {
+            match item {
+                Item::Value(child) => {
+                    let child = digests[&std::ptr::from_ref(child)];
+                    digest.write(&u128::to_be_bytes(child));
+                }
+                Item::Str(s) => write_str(&mut digest, s),
+            }
+        }
+        digests.insert(std::ptr::from_ref(value), digest.finish());
+    }
+    digests
+}
+
+/// Two independently keyed hashes, making a 128-bit digest.
+struct Digest(DefaultHasher, DefaultHasher);
+
+impl Digest {
+    fn finish(&self) -> u128 {
+        (u128::from(self.0.finish()) << 64) | u128::from(self.1.finish())
+    }
+}
+
+impl Sink for Digest {
+    fn write(&mut self, bytes: &[u8]) {
+        self.0.write(bytes);
+        self.1.write(bytes);
+    }
+}
+
+/// A sink that drops what is written, for walking a value's children.
+struct Discard;
+
+impl Sink for Discard {
+    fn write(&mut self, _bytes: &[u8]) {}
+}
+
+/// Write the canonical encoding of a plain JSON tree to `out`, without
+/// recursion.
+///
+/// It is the encoding of the [`Value`] the tree parses to without
+/// annotations, so it does not depend on key order or on how numbers are
+/// spelled.
+pub(crate) fn encode_json(json: &serde_json::Value, out: &mut impl Sink) {
+    enum JsonItem<'a> {
+        Json(&'a serde_json::Value),
+        Key(&'a str),
+    }
+
+    let mut stack = vec![JsonItem::Json(json)];
+    while let Some(item) = stack.pop() {
+        let json = match item {
+            JsonItem::Json(json) => json,
+            JsonItem::Key(key) => {
+                write_str(out, key);
+                continue;
+            }
+        };
+        match json {
+            serde_json::Value::Null => out.write(&[0]),
+            serde_json::Value::Bool(b) => out.write(&[1, u8::from(*b)]),
+            serde_json::Value::Number(n) => match (n.as_i64(), n.as_u64()) {
+                (Some(i), _) => write_integer(out, i.into()),
+                (None, Some(u)) => write_integer(out, u.into()),
+                _ => write_number(out, n.as_f64().unwrap_or(f64::NAN)),
+            },
+            serde_json::Value::String(s) => {
+                out.write(&[3]);
+                write_str(out, s);
+            }
+            serde_json::Value::Array(items) => {
+                write_count(out, 4, items.len());
+                stack.extend(items.iter().rev().map(JsonItem::Json));
+            }
+            serde_json::Value::Object(map) => {
+                write_count(out, 5, map.len());
+                let mut entries: Vec<_> = map.iter().collect();
+                entries.sort_unstable_by_key(|(key, _)| *key);
+                for (key, value) in entries.into_iter().rev() {
+                    stack.push(JsonItem::Json(value));
+                    stack.push(JsonItem::Key(key));
+                }
+            }
+        }
+    }
+}
+
+fn write_number(out: &mut impl Sink, n: f64) {
+    let n = if n.is_nan() {
+        f64::NAN
+    } else if n == 0.0 {
+        0.0
+    } else {
+        n
+    };
+    out.write(&[2]);
+    out.write(&n.to_bits().to_be_bytes());
+}
+
+/// Integers an `f64` holds exactly are written as numbers, so that `1` and
+/// `1.0` encode alike.
+fn write_integer(out: &mut impl Sink, n: i128) {
+    let f = n as f64;
+    if f as i128 == n {
+        write_number(out, f);
+    } else {
+        out.write(&[23]);
+        out.write(&n.to_be_bytes());
+    }
+}
+
+fn write_count(out: &mut impl Sink, tag: u8, count: usize) {
+    out.write(&[tag]);
+    out.write(&(count as u64).to_be_bytes());
+}
+
+pub(crate) fn write_str(out: &mut impl Sink, s: &str) {
+    out.write(&(s.len() as u64).to_be_bytes());
+    out.write(s.as_bytes());
+}
+
+/// `json` as text in the JSON Canonicalization Scheme of RFC 8785, without
+/// recursion: no whitespace, object keys sorted by their UTF-16 code units,
+/// and numbers written as JavaScript's `Number.prototype.toString` writes
+/// them.
+pub(crate) fn to_jcs(json: &serde_json::Value) -> String {
+    enum Token<'a> {
+        Json(&'a serde_json::Value),
+        Key(&'a str),
+        Raw(&'static str),
+    }
+
+    let mut out = String::new();
+    let mut stack = vec![Token::Json(json)];
+    while let Some(token) = stack.pop() {
+        let json = match token {
+            Token::Json(json) => json,
+            Token::Key(key) => {
+                write_jcs_str(&mut out, key);
+                continue;
+            }
+            Token::Raw(raw) => {
+                out.push_str(raw);
+                continue;
+            }
+        };
+
+        // Tokens are collected in writing order, then pushed in reverse.
+        let mut pending = Vec::new();
+        match json {
+            serde_json::Value::Null => out.push_str("null"),
+            serde_json::Value::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+            serde_json::Value::Number(n) => {
+                write_jcs_number(&mut out, n.as_f64().unwrap_or(f64::NAN));
+            }
+            serde_json::Value::String(s) => write_jcs_str(&mut out, s),
+            serde_json::Value::Array(items) => {
+                out.push('[');
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        pending.push(Token::Raw(","));
+                    }
+                    pending.push(Token::Json(item));
+                }
+                pending.push(Token::Raw("]"));
+            }
+            serde_json::Value::Object(map) => {
+                out.push('{');
+                let mut entries: Vec<_> = map.iter().collect();
+                entries.sort_unstable_by(|(a, _), (b, _)| a.encode_utf16().cmp(b.encode_utf16()));
+                for (i, (key, value)) in entries.into_iter().enumerate() {
+                    if i > 0 {
+                        pending.push(Token::Raw(","));
+                    }
+                    pending.push(Token::Key(key));
+                    pending.push(Token::Raw(":"));
+                    pending.push(Token::Json(value));
+                }
+                pending.push(Token::Raw("}"));
+            }
+        }
+        stack.extend(pending.into_iter().rev());
+    }
+    out
+}
+
+/// serde_json escapes exactly the characters RFC 8785 requires, with
+/// lowercase hex digits.
+fn write_jcs_str(out: &mut String, s: &str) {
+    out.push_str(&serde_json::Value::from(s).to_string());
+}
+
+/// Write `n` as ECMAScript's Number::toString does, from the shortest
+/// digits that round-trip.
+fn write_jcs_number(out: &mut String, n: f64) {
+    if n == 0.0 {
+        // Both zeros.
+        out.push('0');
+        return;
+    }
+    if n < 0.0 {
+        out.push('-');
+    }
+    let scientific = format!("{:e}", n.abs());
+    let (mantissa, exponent) = scientific
+        .split_once('e')
+        .expect("`{:e}` always writes an exponent");
+    let digits: String = mantissa.chars().filter(|c| *c != '.').collect();
+    let k = digits.len() as i32;
+    // The position of the decimal point relative to the start of `digits`.
+    let point = exponent
+        .parse::<i32>()
+        .expect("`{:e}` writes an integer exponent")
+        + 1;
+
+    if k <= point && point <= 21 {
+        out.push_str(&digits);
+        out.extend(std::iter::repeat_n('0', (point - k) as usize));
+    } else if 0 < point && point <= 21 {
+        let (int, frac) = digits.split_at(point as usize);
+        out.push_str(int);
+        out.push('.');
+        out.push_str(frac);
+    } else if -6 < point && point <= 0 {
+        out.push_str("0.");
+        out.extend(std::iter::repeat_n('0', (-point) as usize));
+        out.push_str(&digits);
+    } else {
+        let (first, rest) = digits.split_at(1);
+        out.push_str(first);
+        if !rest.is_empty() {
+            out.push('.');
+            out.push_str(rest);
+        }
+        out.push('e');
+        out.push(if point > 0 { '+' } else { '-' });
+        out.push_str(&(point - 1).abs().to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_jcs_numbers() {
+        // The examples of RFC 8785, appendix B.
+        let cases: &[(f64, &str)] = &[
+            (0.0, "0"),
+            (-0.0, "0"),
+            (5e-324, "5e-324"),
+            (1.7976931348623157e308, "1.7976931348623157e+308"),
+            (9007199254740992.0, "9007199254740992"),
+            (295147905179352830000.0, "295147905179352830000"),
+            (1e21, "1e+21"),
+            (9.999999999999997e22, "9.999999999999997e+22"),
+            (0.000001, "0.000001"),
+            (1e-7, "1e-7"),
+            (-1.5e-7, "-1.5e-7"),
+            (333333333.3333333, "333333333.3333333"),
+            (4.5, "4.5"),
+            (-4.0, "-4"),
+        ];
+        for &(n, expected) in cases {
+            let mut out = String::new();
+            write_jcs_number(&mut out, n);
+            assert_eq!(out, expected, "{n:e}");
+        }
+    }
+
+    #[test]
+    fn test_jcs_sorts_keys_by_utf16() {
+        let json = json!({
+            "\u{20ac}": "Euro Sign",
+            "\r": "Carriage Return",
+            "\u{fb33}": "Hebrew Letter Dalet With Dagesh",
+            "1": "One",
+            "\u{1f600}": "Emoji: Grinning Face",
+            "\u{80}": "Control",
+            "\u{f6}": "Latin Small Letter O With Diaeresis"
+        });
+        assert_eq!(
+            to_jcs(&json),
+            "{\"\\r\":\"Carriage Return\",\"1\":\"One\",\"\u{80}\":\"Control\",\
+             \"\u{f6}\":\"Latin Small Letter O With Diaeresis\",\"\u{20ac}\":\"Euro Sign\",\
+             \"\u{1f600}\":\"Emoji: Grinning Face\",\"\u{fb33}\":\"Hebrew Letter Dalet With Dagesh\"}"
+        );
+    }
+
+    #[test]
+    fn test_jcs_structure() {
+        let json = json!({"b": [1, 2.50, {"d": null, "c": true}], "a": "\u{1f}\"", "e": []});
+        assert_eq!(
+            to_jcs(&json),
+            r#"{"a":"\u001f\"","b":[1,2.5,{"c":true,"d":null}],"e":[]}"#
+        );
+    }
+}
@@ -0,0 +1,991 @@
+//! A configurable superjson codec.
+//!
+//! The free functions ([`stringify`](crate::stringify),
+//! [`parse`](crate::parse), ...) use the default behavior. A
+//! [`SuperJsonCodec`] carries per-application configuration, such as field
+//! hooks, and applies it as part of serialization and parsing. [`Overrides`]
+//! adjust that configuration for a single call.
+//!
+//! A codec also holds the application's custom types and symbols, the
+//! counterparts of JS superjson's `registerCustom` and `registerSymbol`: see
+//! [`SuperJsonCodec::register_custom`] and [`SuperJsonCodec::register_symbol`].
+//!
+//! A codec is cheap to clone and safe to share between threads: its
+//! configuration is reference-counted and never changes once shared, so a
+//! configured codec can be kept in application state and used by many
+//! request handlers at once without locking.
+
+use std::any::{Any, TypeId};
+use std::borrow::Cow;
+use std::fmt;
+use std::sync::Arc;
+
+use crate::backend::SerdeJson;
+use crate::bytes::BytesEncoding;
+use crate::path::PathPattern;
+use crate::serialize::SerializeOptions;
+use crate::{Error, ParseOptions, Parsed, Result, SuperJson, Value, deserialize, serialize};
+
+/// A transformation of values, as registered with a hook.
+///
+/// Implemented for every `Fn(&Value) -> Value + Send + Sync`, so closures and
+/// functions such as the [`mask`](crate::mask) helpers can be used directly.
+pub trait Transformer: Send + Sync {
+    fn transform(&self, value: &Value) -> Value;
+}
+
+impl<F> Transformer for F
+where
+    F: Fn(&Value) -> Value + Send + Sync,
+{
+    fn transform(&self, value: &Value) -> Value {
+        self(value)
+    }
+}
+
+/// A transform applied to the values at paths matching a pattern.
+#[derive(Clone)]
+pub(crate) struct Hook {
+    pattern: PathPattern,
+    transform: Arc<dyn Transformer>,
+}
+
+impl Hook {
+    fn new(pattern: impl Into<PathPattern>, transform: Arc<dyn Transformer>) -> Self {
+        Hook {
+            pattern: pattern.into(),
+            transform,
+        }
+    }
+}
+
+/// Apply every hook matching `path` to `value`, in registration order.
+///
+/// Returns `None` if no hook matches.
+pub(crate) fn apply_hooks(hooks: &[Hook], path: &str, value: &Value) -> Option<Value> {
+    let mut result: Option<Value> = None;
+    for hook in hooks {
+        if hook.pattern.matches(path) {
+            let input = result.as_ref().unwrap_or(value);
+            result = Some(hook.transform.transform(input));
+        }
+    }
+    result
+}
+
+/// `base` with `extra` appended, dropping any hook in `base` whose pattern is
+/// the same as one in `extra`.
+fn override_hooks<'a>(base: &'a [Hook], extra: &'a [Hook]) -> Cow<'a, [Hook]> {
+    if extra.is_empty() {
+        return Cow::Borrowed(base);
+    }
+    let mut hooks: Vec<Hook> = base
+        .iter()
+        .filter(|hook| !extra.iter().any(|e| e.pattern == hook.pattern))
+        .cloned()
+        .collect();
+    hooks.extend_from_slice(extra);
+    Cow::Owned(hooks)
+}
+
+type CustomSerializeFn = Arc<dyn Fn(&dyn Any) -> Value + Send + Sync>;
+type CustomDeserializeFn = Arc<dyn Fn(&Value) -> Result<Box<dyn Any>> + Send + Sync>;
+
+/// A Rust type registered as a superjson custom type.
+#[derive(Clone)]
+struct CustomType {
+    name: String,
+    type_id: TypeId,
+    type_name: &'static str,
+    serialize: CustomSerializeFn,
+    deserialize: CustomDeserializeFn,
+}
+
+/// A superjson codec with its own configuration.
+///
+/// # Examples
+/// ```
+/// use superjson_rs::{SuperJsonCodec, Value};
+///
+/// let mut codec = SuperJsonCodec::new();
+/// codec
+///     .on_serialize("user.ssn", |_| Value::from("***"))
+///     .on_parse("user.name", |v| Value::from(v.as_str().unwrap_or("").to_uppercase()));
+///
+/// let s = r#"{"json":{"user":{"name":"ada","ssn":"123"}}}"#;
+/// let value = codec.parse(s).unwrap();
+/// assert_eq!(value.as_object().unwrap()["user"].as_object().unwrap()["name"], Value::from("ADA"));
+///
+/// let out = codec.stringify(&value).unwrap();
+/// assert!(out.contains(r#""ssn":"***""#));
+/// ```
+#[derive(Clone, Default)]
+pub struct SuperJsonCodec {
+    config: Arc<Config>,
+}
+
+/// The configuration of a codec, shared between its clones.
+#[derive(Clone, Default)]
+struct Config {
+    serialize_hooks: Vec<Hook>,
+    parse_hooks: Vec<Hook>,
+    serialize_options: SerializeOptions,
+    parse_options: ParseOptions,
+    custom_types: Vec<CustomType>,
+    symbols: Vec<String>,
+}
+
+impl SuperJsonCodec {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The configuration, for modification.
+    ///
+    /// Clones made before this call keep the configuration they were made
+    /// with.
+    fn config_mut(&mut self) -> &mut Config {
+        Arc::make_mut(&mut self.config)
+    }
+
+    /// Replace the value at every path matching `pattern` before it is
+    /// serialized.
+    ///
+    /// The output of a hook is serialized as-is: hooks are not applied again
+    /// inside it. When several hooks match, they run in registration order.
+    pub fn on_serialize(
+        &mut self,
+        pattern: impl Into<PathPattern>,
+        transform: impl Fn(&Value) -> Value + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.on_serialize_transformer(pattern, Arc::new(transform))
+    }
+
+    /// Like [`on_serialize`](Self::on_serialize), with a transformer that may
+    /// be shared with other hooks or codecs.
+    pub fn on_serialize_transformer(
+        &mut self,
+        pattern: impl Into<PathPattern>,
+        transformer: Arc<dyn Transformer>,
+    ) -> &mut Self {
+        self.config_mut()
+            .serialize_hooks
+            .push(Hook::new(pattern, transformer));
+        self
+    }
+
+    /// Replace the value at every path matching `pattern` after it has been
+    /// restored, before it is placed into its parent.
+    ///
+    /// When several hooks match, they run in registration order.
+    pub fn on_parse(
+        &mut self,
+        pattern: impl Into<PathPattern>,
+        transform: impl Fn(&Value) -> Value + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.on_parse_transformer(pattern, Arc::new(transform))
+    }
+
+    /// Like [`on_parse`](Self::on_parse), with a transformer that may be
+    /// shared with other hooks or codecs.
+    pub fn on_parse_transformer(
+        &mut self,
+        pattern: impl Into<PathPattern>,
+        transformer: Arc<dyn Transformer>,
+    ) -> &mut Self {
+        self.config_mut()
+            .parse_hooks
+            .push(Hook::new(pattern, transformer));
+        self
+    }
+
+    /// Set the checks applied when serializing.
+    pub fn serialize_options(&mut self, options: SerializeOptions) -> &mut Self {
+        self.config_mut().serialize_options = options;
+        self
+    }
+
+    /// Set the limits applied when parsing.
+    pub fn parse_options(&mut self, options: ParseOptions) -> &mut Self {
+        self.config_mut().parse_options = options;
+        self
+    }
+
+    /// Keep the Error properties `props` when serializing and parsing, like
+    /// JS superjson's `allowErrorProps`.
+    ///
+    /// This adds to the `error_props` of both the serialize and the parse
+    /// options; setting either afterwards replaces its list.
+    ///
+    /// # Examples
+    /// ```
+    /// use superjson_rs::{SuperJsonCodec, Value};
+    ///
+    /// let text = r#"{"json":{"name":"Error","message":"gone","code":"ENOENT"},"meta":{"values":["Error"],"v":1}}"#;
+    /// let mut codec = SuperJsonCodec::new();
+    /// codec.allow_error_props(["code"]);
+    /// let value = codec.parse(text).unwrap();
+    /// assert_eq!(value.pointer("code"), Some(&Value::from("ENOENT")));
+    ///
+    /// let text = codec.stringify(&value).unwrap();
+    /// assert!(text.contains(r#""code":"ENOENT""#));
+    /// ```
+    pub fn allow_error_props<I>(&mut self, props: I) -> &mut Self
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        let config = self.config_mut();
+        for prop in props {
+            let prop = prop.into();
+            for allowed in [
+                &mut config.serialize_options.error_props,
+                &mut config.parse_options.error_props,
+            ] {
+                if !allowed.contains(&prop) {
+                    allowed.push(prop.clone());
+                }
+            }
+        }
+        self
+    }
+
+    /// Read and write [`Value::Bytes`] as the custom type `name` in
+    /// `encoding`, such as a Node `Buffer` registered as `"buffer"` and
+    /// written with `toString("base64")`.
+    ///
+    /// This sets `bytes_name` and `bytes_encoding` in both the serialize and
+    /// the parse options; setting either afterwards replaces them.
+    ///
+    /// # Examples
+    /// ```
+    /// use superjson_rs::{BytesEncoding, SuperJsonCodec, Value};
+    ///
+    /// let text = r#"{"json":{"upload":"aGk="},"meta":{"values":{"upload":[["custom","buffer"]]},"v":1}}"#;
+    /// let mut codec = SuperJsonCodec::new();
+    /// codec.register_bytes("buffer", BytesEncoding::Base64);
+    /// let value = codec.parse(text).unwrap();
+    /// assert_eq!(value.pointer("upload"), Some(&Value::Bytes(b"hi".to_vec())));
+    /// assert_eq!(codec.stringify(&value).unwrap(), text);
+    /// ```
+    pub fn register_bytes(
+        &mut self,
+        name: impl Into<String>,
+        encoding: BytesEncoding,
+    ) -> &mut Self {
+        let name = name.into();
+        let config = self.config_mut();
+        config.serialize_options.bytes_name = Some(name.clone());
+        config.serialize_options.bytes_encoding = encoding;
+        config.parse_options.bytes_name = Some(name);
+        config.parse_options.bytes_encoding = encoding;
+        self
+    }
+
+    /// Register `T` as the custom type `name`, written as the JSON value
+    /// `serialize` returns and read back with `deserialize`.
+    ///
+    /// Values of the type are annotated `["custom", name]`, as those of a
+    /// type registered with JS superjson's `registerCustom` are, so both
+    /// sides can exchange them if they register the same name. Build them
+    /// with [`to_custom`](Self::to_custom) and read them with
+    /// [`from_custom`](Self::from_custom). Registering a name or a type
+    /// again replaces the earlier registration.
+    ///
+    /// # Examples
+    /// ```
+    /// use superjson_rs::{Error, SuperJsonCodec, Value};
+    ///
+    /// #[derive(Debug, PartialEq)]
+    /// struct Decimal {
+    ///     units: i64,
+    ///     scale: u32,
+    /// }
+    ///
+    /// let mut codec = SuperJsonCodec::new();
+    /// codec.register_custom(
+    ///     "decimal.js",
+    ///     |d: &Decimal| Value::from(format!("{}e-{}", d.units, d.scale)),
+    ///     |v| {
+    ///         let (units, scale) = v
+    ///             .as_str()
+    ///             .and_then(|s| s.split_once("e-"))
+    ///             .ok_or_else(|| Error::InvalidTypeAnnotation("not a decimal".to_string()))?;
+    ///         Ok(Decimal {
+    ///             units: units.parse().map_err(|_| Error::InvalidTypeAnnotation(units.to_string()))?,
+    ///             scale: scale.parse().map_err(|_| Error::InvalidTypeAnnotation(scale.to_string()))?,
+    ///         })
+    ///     },
+    /// );
+    ///
+    /// let price = Decimal { units: 1999, scale: 2 };
+    /// let text = codec.stringify(&codec.to_custom(&price).unwrap()).unwrap();
+    /// assert_eq!(text, r#"{"json":"1999e-2","meta":{"values":[["custom","decimal.js"]],"v":1}}"#);
+    ///
+    /// let value = codec.parse(&text).unwrap();
+    /// assert_eq!(codec.from_custom::<Decimal>(&value).unwrap(), price);
+    /// ```
+    pub fn register_custom<T: 'static>(
+        &mut self,
+        name: impl Into<String>,
+        serialize: impl Fn(&T) -> Value + Send + Sync + 'static,
+        deserialize: impl Fn(&Value) -> Result<T> + Send + Sync + 'static,
+    ) -> &mut Self {
+        let custom = CustomType {
+            name: name.into(),
+            type_id: TypeId::of::<T>(),
+            type_name: std::any::type_name::<T>(),
+            serialize: Arc::new(move |value| {
+                serialize(
+                    value
+                        .downcast_ref()
+                        .expect("called with the registered type"),
+                )
+            }),
+            deserialize: Arc::new(move |value| Ok(Box::new(deserialize(value)?))),
+        };
+        let custom_types = &mut self.config_mut().custom_types;
+        custom_types.retain(|c| c.name != custom.name && c.type_id != custom.type_id);
+        custom_types.push(custom);
+        self
+    }
+
+    fn custom_type<T: 'static>(&self) -> Result<&CustomType> {
+        self.config
+            .custom_types
+            .iter()
+            .find(|c| c.type_id == TypeId::of::<T>())
+            .ok_or_else(|| Error::UnregisteredCustomType(std::any::type_name::<T>().to_string()))
+    }
+
+    /// `value` as a [`Value::Custom`] of its registered name.
+    ///
+    /// # Errors
+    /// Returns [`Error::UnregisteredCustomType`] if `T` was not registered
+    /// with [`register_custom`](Self::register_custom).
+    pub fn to_custom<T: 'static>(&self, value: &T) -> Result<Value> {
+        let custom = self.custom_type::<T>()?;
+        Ok(Value::Custom {
+            name: custom.name.clone(),
+            value: Box::new((custom.serialize)(value)),
+        })
+    }
+
+    /// Read a `T` from a [`Value::Custom`] of its registered name.
+    ///
+    /// # Errors
+    /// Returns [`Error::UnregisteredCustomType`] if `T` was not registered,
+    /// [`Error::TypeMismatch`] if `value` is not a custom value of its name,
+    /// and whatever the registered `deserialize` returns.
+    pub fn from_custom<T: 'static>(&self, value: &Value) -> Result<T> {
+        let custom = self.custom_type::<T>()?;
+        let payload = match value {
+            Value::Custom { name, value } if *name == custom.name => value,
+            other => {
+                return Err(Error::TypeMismatch {
+                    path: String::new(),
+                    expected: format!("custom '{}'", custom.name),
+                    actual: other.type_name().to_string(),
+                });
+            }
+        };
+        let value = (custom.deserialize)(payload)?;
+        Ok(*value
+            .downcast()
+            .expect("deserialize returns the registered type"))
+    }
+
+    /// Register the symbol `identifier`.
+    ///
+    /// A codec only serializes and parses the [`Value::Symbol`]s registered
+    /// with it, as JS superjson only knows the symbols passed to
+    /// `registerSymbol`; any other is an [`Error::UnregisteredSymbol`].
+    ///
+    /// # Examples
+    /// ```
+    /// use superjson_rs::{SuperJsonCodec, Value};
+    ///
+    /// let text = r#"{"json":"admin","meta":{"values":[["symbol","admin"]],"v":1}}"#;
+    /// assert!(SuperJsonCodec::new().parse(text).is_err());
+    ///
+    /// let mut codec = SuperJsonCodec::new();
+    /// codec.register_symbol("admin");
+    /// let value = codec.parse(text).unwrap();
+    /// assert_eq!(value, Value::Symbol("admin".to_string()));
+    /// assert_eq!(codec.stringify(&value).unwrap(), text);
+    /// ```
+    pub fn register_symbol(&mut self, identifier: impl Into<String>) -> &mut Self {
+        let identifier = identifier.into();
+        let symbols = &mut self.config_mut().symbols;
+        if !symbols.contains(&identifier) {
+            symbols.push(identifier);
+        }
+        self
+    }
+
+    /// Check that every symbol in `value` is registered.
+    fn check_symbols(&self, value: &Value) -> Result<()> {
+        let mut stack = vec![value];
+        while let Some(value) = stack.pop() {
+            match value {
+                Value::Symbol(id) if !self.config.symbols.contains(id) => {
+                    return Err(Error::UnregisteredSymbol(id.clone()));
+                }
+                Value::Array(items) | Value::Set(items) => stack.extend(items),
+                Value::Object(map) | Value::Class { fields: map, .. } => stack.extend(map.values()),
+                Value::Map(entries) => stack.extend(entries.iter().flat_map(|(k, v)| [k, v])),
+                Value::Error { cause, extra, .. } => {
+                    stack.extend(cause.as_deref());
+                    stack.extend(extra.values());
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    pub fn serialize(&self, value: &Value) -> Result<SuperJson> {
+        self.check_symbols(value)?;
+        serialize::serialize_detailed(
+            value,
+            &self.config.serialize_options,
+            &self.config.serialize_hooks,
+            &mut Vec::new(),
+        )
+    }
+
+    pub fn stringify(&self, value: &Value) -> Result<String> {
+        serialize::to_text(&self.serialize(value)?, &self.config.serialize_options)
+    }
+
+    pub fn deserialize(&self, superjson: &SuperJson) -> Result<Value> {
+        let value = deserialize::deserialize_detailed(
+            superjson,
+            &self.config.parse_options,
+            &self.config.parse_hooks,
+            &mut Vec::new(),
+            None,
+        )?;
+        self.check_symbols(&value)?;
+        Ok(value)
+    }
+
+    pub fn parse(&self, s: &str) -> Result<Value> {
+        self.parse_detailed(s).map(|parsed| parsed.value)
+    }
+
+    /// Parse, also reporting warnings and statistics as
+    /// [`parse_detailed`](crate::parse_detailed) does.
+    pub fn parse_detailed(&self, s: &str) -> Result<Parsed> {
+        let parsed = crate::parse_detailed_in(
+            &SerdeJson,
+            s,
+            &self.config.parse_options,
+            &self.config.parse_hooks,
+        )?;
+        self.check_symbols(&parsed.value)?;
+        Ok(parsed)
+    }
+
+    /// Serialize with `overrides` applied on top of this codec's
+    /// configuration.
+    pub fn serialize_with(&self, value: &Value, overrides: &Overrides) -> Result<SuperJson> {
+        let hooks = override_hooks(&self.config.serialize_hooks, &overrides.serialize_hooks);
+        let options = overrides
+            .serialize_options
+            .as_ref()
+            .unwrap_or(&self.config.serialize_options);
+        self.check_symbols(value)?;
+        serialize::serialize_detailed(value, options, &hooks, &mut Vec::new())
+    }
+
+    /// Stringify with `overrides` applied on top of this codec's
+    /// configuration.
+    ///
+    /// # Examples
+    /// ```
+    /// use superjson_rs::codec::{Overrides, SuperJsonCodec};
+    /// use superjson_rs::Value;
+    ///
+    /// let mut codec = SuperJsonCodec::new();
+    /// codec.on_serialize("**.price", |v| Value::from(v.to_string()));
+    ///
+    /// let mut cents = Overrides::new();
+    /// cents.on_serialize("**.price", |v| Value::Number(v.as_f64().unwrap() * 100.0));
+    ///
+    /// let value = superjson_rs::parse(r#"{"json":{"price":1.5}}"#).unwrap();
+    /// assert_eq!(codec.stringify(&value).unwrap(), r#"{"json":{"price":"1.5"}}"#);
+    /// assert_eq!(codec.stringify_with(&value, &cents).unwrap(), r#"{"json":{"price":150.0}}"#);
+    /// ```
+    pub fn stringify_with(&self, value: &Value, overrides: &Overrides) -> Result<String> {
+        let options = overrides
+            .serialize_options
+            .as_ref()
+            .unwrap_or(&self.config.serialize_options);
+        serialize::to_text(&self.serialize_with(value, overrides)?, options)
+    }
+
+    /// Parse with `overrides` applied on top of this codec's configuration.
+    pub fn parse_with(&self, s: &str, overrides: &Overrides) -> Result<Value> {
+        let hooks = override_hooks(&self.config.parse_hooks, &overrides.parse_hooks);
+        let options = overrides
+            .parse_options
+            .as_ref()
+            .unwrap_or(&self.config.parse_options);
+        let parsed = crate::parse_detailed_in(&SerdeJson, s, options, &hooks)?;
+        self.check_symbols(&parsed.value)?;
+        Ok(parsed.value)
+    }
+
+    /// A JSON Schema of the envelopes this codec writes, for API gateways
+    /// and contract tests that validate superjson traffic.
+    ///
+    /// The schema follows the serialize options: the shape of `meta.values`,
+    /// and whether `meta` may hold `referentialEqualities` or a `checksum`.
+    /// Custom types and symbols are limited to those registered with the
+    /// codec, and to the custom type [`Value::Bytes`] are written as. `json`
+    /// may be any JSON value.
+    ///
+    /// Annotations kept from parsing, such as the types of
+    /// [`Value::Unknown`]s, are written back as they were read and may not
+    /// match the schema.
+    ///
+    /// # Examples
+    /// ```
+    /// use superjson_rs::SuperJsonCodec;
+    ///
+    /// let mut codec = SuperJsonCodec::new();
+    /// codec.register_symbol("admin");
+    /// let schema = codec.wire_schema();
+    /// assert_eq!(schema.as_value()["required"], serde_json::json!(["json"]));
+    /// assert!(schema.as_value()["$defs"]["TypeAnnotation"].to_string().contains(r#"{"enum":["admin"]}"#));
+    /// ```
+    #[cfg(feature = "schemars")]
+    pub fn wire_schema(&self) -> ::schemars::Schema {
+        let custom_types: Vec<&str> = self
+            .config
+            .custom_types
+            .iter()
+            .map(|custom| custom.name.as_str())
+            .collect();
+        crate::schemars::wire_schema(
+            &self.config.serialize_options,
+            &custom_types,
+            &self.config.symbols,
+        )
+    }
+}
+
+/// Changes to a [`SuperJsonCodec`]'s configuration for a single call, such
+/// as one endpoint that must encode a field differently from the rest of an
+/// application.
+///
+/// A hook registered here replaces any codec hook with the same pattern, and
+/// otherwise runs after the codec's hooks. The codec itself is not modified.
+#[derive(Clone, Default)]
+pub struct Overrides {
+    serialize_hooks: Vec<Hook>,
+    parse_hooks: Vec<Hook>,
+    serialize_options: Option<SerializeOptions>,
+    parse_options: Option<ParseOptions>,
+}
+
+impl Overrides {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add or replace a serialize hook, as
+    /// [`SuperJsonCodec::on_serialize`] does.
+    pub fn on_serialize(
+        &mut self,
+        pattern: impl Into<PathPattern>,
+        transform: impl Fn(&Value) -> Value + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.serialize_hooks
+            .push(Hook::new(pattern, Arc::new(transform)));
+        self
+    }
+
+    /// Add or replace a parse hook, as [`SuperJsonCodec::on_parse`] does.
+    pub fn on_parse(
+        &mut self,
+        pattern: impl Into<PathPattern>,
+        transform: impl Fn(&Value) -> Value + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.parse_hooks
+            .push(Hook::new(pattern, Arc::new(transform)));
+        self
+    }
+
+    /// Use `options` instead of the codec's serialize checks.
+    pub fn serialize_options(&mut self, options: SerializeOptions) -> &mut Self {
+        self.serialize_options = Some(options);
+        self
+    }
+
+    /// Use `options` instead of the codec's parse limits.
+    pub fn parse_options(&mut self, options: ParseOptions) -> &mut Self {
+        self.parse_options = Some(options);
+        self
+    }
+}
+
+impl fmt::Debug for Overrides {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Overrides")
+            .field("serialize_hooks", &self.serialize_hooks.len())
+            .field("parse_hooks", &self.parse_hooks.len())
+            .field("serialize_options", &self.serialize_options)
+            .field("parse_options", &self.parse_options)
+            .finish()
+    }
+}
+
+impl fmt::Debug for SuperJsonCodec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SuperJsonCodec")
+            .field("serialize_hooks", &self.config.serialize_hooks.len())
+            .field("parse_hooks", &self.config.parse_hooks.len())
+            .field("serialize_options", &self.config.serialize_options)
+            .field("parse_options", &self.config.parse_options)
+            .field(
+                "custom_types",
+                &self
+                    .config
+                    .custom_types
+                    .iter()
+                    .map(|c| (&c.name, c.type_name))
+                    .collect::<Vec<_>>(),
+            )
+            .field("symbols", &self.config.symbols)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::value::Map;
+
+    fn record() -> Value {
+        let mut inner = Map::new();
+        inner.insert("celsius".to_string(), Value::from(100));
+        let mut obj = Map::new();
+        obj.insert("reading".to_string(), Value::Object(inner));
+        obj.insert("tags".to_string(), Value::Set(vec![Value::from("a")]));
+        Value::Object(obj)
+    }
+
+    #[test]
+    fn test_serialize_hook_replaces_matching_path() {
+        let mut codec = SuperJsonCodec::new();
+        codec.on_serialize("reading.celsius", |v| {
+            Value::Number(v.as_f64().unwrap() * 9.0 / 5.0 + 32.0)
+        });
+        let out = codec.serialize(&record()).unwrap();
+        assert_eq!(out.json["reading"]["celsius"], serde_json::json!(212.0));
+    }
+
+    #[test]
+    fn test_serialize_hook_output_is_annotated_but_not_rehooked() {
+        let mut codec = SuperJsonCodec::new();
+        codec
+            .on_serialize("tags", |_| Value::Set(vec![Value::Undefined]))
+            .on_serialize("tags.0", |_| Value::from("unreachable"));
+        let out = codec.serialize(&record()).unwrap();
+        assert_eq!(out.json["tags"], serde_json::json!([null]));
+        let values = out.meta.unwrap().values.unwrap();
+        let paths: Vec<_> = values.iter().map(|(path, _)| path).collect();
+        assert_eq!(paths, vec!["tags", "tags.0"]);
+    }
+
+    #[test]
+    fn test_hooks_chain_in_registration_order() {
+        let mut codec = SuperJsonCodec::new();
+        codec
+            .on_parse("*", |v| Value::Number(v.as_f64().unwrap() + 1.0))
+            .on_parse("*", |v| Value::Number(v.as_f64().unwrap() * 10.0));
+        let value = codec.parse(r#"{"json":[1,2]}"#).unwrap();
+        assert_eq!(
+            value,
+            Value::Array(vec![Value::from(20.0), Value::from(30.0)])
+        );
+    }
+
+    #[test]
+    fn test_parse_hook_sees_restored_value() {
+        let mut codec = SuperJsonCodec::new();
+        codec.on_parse("tags", |v| {
+            assert!(matches!(v, Value::Set(_)));
+            Value::Null
+        });
+        let s = codec.stringify(&record()).unwrap();
+        let value = codec.parse(&s).unwrap();
+        assert_eq!(value.as_object().unwrap()["tags"], Value::Null);
+    }
+
+    #[test]
+    fn test_root_hook() {
+        let mut codec = SuperJsonCodec::new();
+        codec.on_serialize("", |_| Value::Undefined);
+        let out = codec.stringify(&record()).unwrap();
+        assert_eq!(
+            out,
+            r#"{"json":null,"meta":{"values":["undefined"],"v":1}}"#
+        );
+    }
+
+    #[test]
+    fn test_overrides_replace_same_pattern_and_append_others() {
+        let mut codec = SuperJsonCodec::new();
+        codec
+            .on_serialize("reading.celsius", |_| Value::from("codec"))
+            .on_serialize("tags", |_| Value::Null);
+        let mut overrides = Overrides::new();
+        overrides
+            .on_serialize("reading.celsius", |_| Value::from("override"))
+            .on_serialize("**.celsius", |v| Value::Array(vec![v.clone()]));
+
+        let out = codec.serialize_with(&record(), &overrides).unwrap();
+        assert_eq!(
+            out.json["reading"]["celsius"],
+            serde_json::json!(["override"])
+        );
+        assert_eq!(out.json["tags"], serde_json::json!(null));
+
+        // The codec itself is unchanged.
+        let out = codec.serialize(&record()).unwrap();
+        assert_eq!(out.json["reading"]["celsius"], serde_json::json!("codec"));
+    }
+
+    #[test]
+    fn test_overrides_parse_options() {
+        let codec = SuperJsonCodec::new();
+        let mut overrides = Overrides::new();
+        overrides.parse_options(ParseOptions {
+            max_depth: 1,
+            ..ParseOptions::default()
+        });
+        assert!(
+            codec
+                .parse_with(r#"{"json":[[1]]}"#, &Overrides::new())
+                .is_ok()
+        );
+        assert!(matches!(
+            codec.parse_with(r#"{"json":[[1]]}"#, &overrides),
+            Err(crate::Error::DepthLimitExceeded(1))
+        ));
+    }
+
+    #[test]
+    fn test_serialize_options_check_hook_output() {
+        let mut codec = SuperJsonCodec::new();
+        codec
+            .on_serialize("id", |_| Value::Number(1e300))
+            .serialize_options(SerializeOptions {
+                unsafe_integers: serialize::UnsafeIntegers::Error,
+                ..SerializeOptions::default()
+            });
+        let value = crate::parse(r#"{"json":{"id":1}}"#).unwrap();
+        assert!(matches!(
+            codec.serialize(&value),
+            Err(crate::Error::UnsafeInteger { ref path, .. }) if path == "id"
+        ));
+
+        let mut overrides = Overrides::new();
+        overrides.serialize_options(SerializeOptions::default());
+        assert!(codec.serialize_with(&value, &overrides).is_ok());
+    }
+
+    #[test]
+    fn test_codec_is_shareable() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<SuperJsonCodec>();
+        assert_send_sync::<Overrides>();
+
+        let mut codec = SuperJsonCodec::new();
+        codec.on_serialize("reading.celsius", |_| Value::Null);
+        let codec = Arc::new(codec);
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let codec = Arc::clone(&codec);
+                std::thread::spawn(move || codec.stringify(&record()).unwrap())
+            })
+            .collect();
+        for handle in handles {
+            assert!(handle.join().unwrap().contains(r#""celsius":null"#));
+        }
+    }
+
+    #[test]
+    fn test_clones_keep_their_configuration() {
+        let mut codec = SuperJsonCodec::new();
+        codec.on_serialize("reading.celsius", |_| Value::Null);
+        let shared = codec.clone();
+        assert!(Arc::ptr_eq(&codec.config, &shared.config));
+
+        codec.on_serialize("tags", |_| Value::Null);
+        assert_eq!(codec.config.serialize_hooks.len(), 2);
+        assert_eq!(shared.config.serialize_hooks.len(), 1);
+    }
+
+    #[test]
+    fn test_shared_transformer() {
+        let upper: Arc<dyn Transformer> =
+            Arc::new(|v: &Value| Value::from(v.as_str().unwrap_or("").to_uppercase()));
+        let mut codec = SuperJsonCodec::new();
+        codec
+            .on_serialize_transformer("a", Arc::clone(&upper))
+            .on_parse_transformer("b", upper);
+        let value = codec.parse(r#"{"json":{"a":"x","b":"y"}}"#).unwrap();
+        assert_eq!(
+            codec.stringify(&value).unwrap(),
+            r#"{"json":{"a":"X","b":"Y"}}"#
+        );
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct Point(i32, i32);
+
+    fn point_codec() -> SuperJsonCodec {
+        let mut codec = SuperJsonCodec::new();
+        codec.register_custom(
+            "Point",
+            |p: &Point| Value::from(vec![p.0, p.1]),
+            |v| match v.as_array().map(Vec::as_slice) {
+                Some([x, y]) => match (x.as_i64(), y.as_i64()) {
+                    (Some(x), Some(y)) => Ok(Point(x as i32, y as i32)),
+                    _ => Err(Error::InvalidTypeAnnotation("not a point".to_string())),
+                },
+                _ => Err(Error::InvalidTypeAnnotation("not a point".to_string())),
+            },
+        );
+        codec
+    }
+
+    #[test]
+    fn test_custom_types_roundtrip() {
+        let codec = point_codec();
+        let value = Value::from(vec![codec.to_custom(&Point(1, -2)).unwrap()]);
+        let text = codec.stringify(&value).unwrap();
+        assert_eq!(
+            text,
+            r#"{"json":[[1,-2]],"meta":{"values":{"0":[["custom","Point"]]},"v":1}}"#
+        );
+        let parsed = codec.parse(&text).unwrap();
+        let Value::Array(items) = parsed else {
+            panic!("expected an array");
+        };
+        assert_eq!(codec.from_custom::<Point>(&items[0]).unwrap(), Point(1, -2));
+    }
+
+    #[test]
+    fn test_unregistered_symbols() {
+        let mut codec = SuperJsonCodec::new();
+        codec.register_symbol("admin");
+        let mut fields = Map::new();
+        fields.insert("role".to_string(), Value::Symbol("guest".to_string()));
+        let value = Value::Map(vec![(Value::from("k"), Value::Object(fields))]);
+        assert!(matches!(
+            codec.serialize(&value),
+            Err(Error::UnregisteredSymbol(id)) if id == "guest"
+        ));
+
+        let text = crate::stringify(&value).unwrap();
+        assert!(crate::parse(&text).is_ok());
+        assert!(matches!(
+            codec.parse(&text),
+            Err(Error::UnregisteredSymbol(_))
+        ));
+        codec.register_symbol("guest");
+        assert_eq!(codec.parse(&text).unwrap(), value);
+    }
+
+    #[test]
+    fn test_allowed_error_props() {
+        let mut extra = Map::new();
+        extra.insert("code".to_string(), Value::from("ENOENT"));
+        extra.insert("at".to_string(), Value::Set(vec![Value::from(1)]));
+        let error = |extra| Value::Error {
+            name: "Error".into(),
+            message: "gone".into(),
+            stack: None,
+            cause: Some(Box::new(Value::Undefined)),
+            extra,
+        };
+        let allowed = error(extra.clone());
+        extra.insert("secret".to_string(), Value::from("hunter2"));
+        let value = error(extra);
+        let mut codec = SuperJsonCodec::new();
+        codec.allow_error_props(["code", "at"]);
+        let superjson = codec.serialize(&value).unwrap();
+        assert_eq!(
+            superjson.json,
+            serde_json::json!({"name": "Error", "message": "gone", "cause": null, "code": "ENOENT", "at": [1]})
+        );
+        let text = codec.stringify(&value).unwrap();
+        assert!(text.contains(r#""values":["Error",{"cause":["undefined"],"at":["set"]}]"#));
+
+        assert_eq!(codec.parse(&text).unwrap(), allowed);
+        let Value::Error { extra, .. } = crate::parse(&text).unwrap() else {
+            panic!("expected an Error");
+        };
+        assert!(extra.is_empty());
+    }
+
+    #[test]
+    fn test_error_stack() {
+        let value = Value::Error {
+            name: "TypeError".into(),
+            message: "x is undefined".into(),
+            stack: Some("TypeError: x is undefined\n    at f (app.js:1:1)".into()),
+            cause: None,
+            extra: Map::new(),
+        };
+        let text = crate::stringify(&value).unwrap();
+        assert!(!text.contains("stack"));
+
+        let mut codec = SuperJsonCodec::new();
+        codec.allow_error_props(["stack"]);
+        let text = codec.stringify(&value).unwrap();
+        assert!(text.contains(r#""stack":"TypeError: x is undefined\n    at f (app.js:1:1)""#));
+        assert_eq!(codec.parse(&text).unwrap(), value);
+        let Value::Error { stack, extra, .. } = crate::parse(&text).unwrap() else {
+            panic!("expected an Error");
+        };
+        assert_eq!(stack, None);
+        assert!(extra.is_empty());
+    }
+
+    #[test]
+    fn test_custom_type_errors() {
+        let codec = point_codec();
+        assert!(matches!(
+            codec.to_custom(&"unregistered"),
+            Err(Error::UnregisteredCustomType(_))
+        ));
+        assert!(matches!(
+            codec.from_custom::<Point>(&Value::from(vec![1, 2])),
+            Err(Error::TypeMismatch { .. })
+        ));
+        let bad = Value::Custom {
+            name: "Point".to_string(),
+            value: Box::new(Value::from("x")),
+        };
+        assert!(matches!(
+            codec.from_custom::<Point>(&bad),
+            Err(Error::InvalidTypeAnnotation(_))
+        ));
+
+        // Registering the name again replaces the earlier type.
+        let mut codec = codec;
+        codec.register_custom(
+            "Point",
+            |s: &String| Value::from(s.as_str()),
+            |v| Ok(v.as_str().unwrap_or_default().to_string()),
+        );
+        assert!(codec.to_custom(&Point(0, 0)).is_err());
+        assert!(codec.to_custom(&"p".to_string()).is_ok());
+    }
+}
@@ -0,0 +1,4 @@
+//! Source code generated from [`Value`](crate::Value)s, so that types on
+//! other sides of the wire stay in sync with the payloads Rust produces.
+
+pub mod typescript;
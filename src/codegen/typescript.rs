@@ -0,0 +1,110 @@
+//! TypeScript declarations for the values a service sends.
+//!
+//! The declarations describe values as JS superjson restores them: a `Date`
+//! is a `Date`, a BigInt a `bigint`, and sets and maps are `Set<T>` and
+//! `Map<K, V>`. Generating them from sample payloads keeps a frontend's
+//! types in step with the Rust code that produces the data.
+//!
+//! Both functions build on [`schema::infer`](crate::schema::infer); use
+//! [`Schema::to_typescript`](crate::schema::Schema::to_typescript) directly
+//! to emit a schema built or adjusted by hand.
+
+use crate::Value;
+use crate::schema::{infer, infer_all};
+
+/// Emit a TypeScript declaration named `name` describing `sample`: an
+/// `interface` if it is an object and a `type` alias otherwise.
+///
+/// # Examples
+/// ```
+/// use superjson_rs::codegen::typescript;
+/// use superjson_rs::{Map, Value};
+///
+/// let mut user = Map::new();
+/// user.insert("id".to_string(), Value::from(1));
+/// user.insert("roles".to_string(), Value::Set(vec![Value::from("admin")]));
+///
+/// assert_eq!(
+///     typescript::interface(&Value::Object(user), "User"),
+///     "export interface User {\n  id: number;\n  roles: Set<string>;\n}\n"
+/// );
+/// ```
+pub fn interface(sample: &Value, name: &str) -> String {
+    infer(sample).to_typescript(name)
+}
+
+/// Emit a TypeScript declaration named `name` describing every sample.
+///
+/// Fields missing from some samples are optional, and values of different
+/// types at the same position become a union.
+///
+/// # Examples
+/// ```
+/// use superjson_rs::codegen::typescript;
+/// use superjson_rs::{Map, Value};
+///
+/// let mut a = Map::new();
+/// a.insert("id".to_string(), Value::from(1));
+/// let mut b = Map::new();
+/// b.insert("id".to_string(), Value::from("u-2"));
+/// b.insert("note".to_string(), Value::Undefined);
+///
+/// assert_eq!(
+///     typescript::interface_from_samples([&Value::Object(a), &Value::Object(b)], "User"),
+///     "export interface User {\n  id: number | string;\n  note?: undefined;\n}\n"
+/// );
+/// ```
+pub fn interface_from_samples<'a>(
+    samples: impl IntoIterator<Item = &'a Value>,
+    name: &str,
+) -> String {
+    infer_all(samples).to_typescript(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Map;
+
+    #[test]
+    fn test_extended_types() {
+        let mut event = Map::new();
+        event.insert(
+            "counts".to_string(),
+            Value::Map(vec![(Value::from("a"), Value::from(1))]),
+        );
+        event.insert("data-id".to_string(), Value::Bytes(vec![1]));
+        event.insert(
+            "link".to_string(),
+            Value::Url("https://example.com/".parse().unwrap()),
+        );
+        event.insert(
+            "pattern".to_string(),
+            Value::RegExp {
+                source: "a+".to_string(),
+                flags: "g".to_string(),
+            },
+        );
+        assert_eq!(
+            interface(&Value::Object(event), "Event"),
+            "export interface Event {\n  counts: Map<string, number>;\n  \"data-id\": Uint8Array;\n  \
+             link: URL;\n  pattern: RegExp;\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_samples() {
+        let samples = [
+            Value::Array(vec![Value::from(1)]),
+            Value::Array(vec![Value::from(true)]),
+        ];
+        assert_eq!(
+            interface_from_samples(&samples, "Flags"),
+            "export type Flags = (number | boolean)[];\n"
+        );
+        assert_eq!(
+            interface_from_samples([], "Nothing"),
+            "export type Nothing = never;\n"
+        );
+    }
+}
@@ -0,0 +1,316 @@
+//! Conversions between arrays of flat objects and Polars `DataFrame`s.
+//!
+//! [`to_dataframe`] reads a [`Value::Array`] of objects as rows, with one
+//! column per key, in the order keys are first seen. Every value in a column
+//! must have the same type, or be `null`, `undefined` or missing:
+//!
+//! | Values | Column |
+//! |--------|--------|
+//! | boolean | `Boolean` |
+//! | number, including `NaN`, the infinities and `-0` | `Float64` |
+//! | string | `String` |
+//! | Date | `Datetime(Milliseconds)`, without a time zone |
+//! | bigint | `Int64`, or `Decimal(38, 0)` or else `String` for larger values |
+//! | only `null` / `undefined` | `Null` |
+//!
+//! [`from_dataframe`] reverses this, except that a bigint too large for a
+//! `Decimal` comes back as its decimal string. Other integer and float
+//! columns become numbers, and `Datetime` columns of any unit or time zone
+//! become dates.
+//!
+//! # Examples
+//! ```
+//! use superjson_rs::dataframe::{from_dataframe, to_dataframe};
+//! use superjson_rs::{Map, Value};
+//!
+//! let row = |name: &str, score: f64| {
+//!     let mut map = Map::new();
+//!     map.insert("name".to_string(), Value::from(name));
+//!     map.insert("score".to_string(), Value::from(score));
+//!     Value::Object(map)
+//! };
+//! let rows = Value::from(vec![row("ada", 9.5), row("grace", 8.0)]);
+//!
+//! let df = to_dataframe(&rows).unwrap();
+//! assert_eq!(df.shape(), (2, 2));
+//! assert_eq!(from_dataframe(&df).unwrap(), rows);
+//! ```
+
+use indexmap::IndexMap;
+use polars::prelude::*;
+
+use crate::value::Map;
+use crate::{Error, Result, Value};
+
+/// The column type of a value, or `None` for a null.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ColumnKind {
+    Boolean,
+    Number,
+    String,
+    #[cfg(feature = "chrono")]
+    Date,
+    #[cfg(feature = "bigint")]
+    BigInt,
+}
+
+impl ColumnKind {
+    fn of(value: &Value) -> Option<Option<ColumnKind>> {
+        Some(match value {
+            Value::Null | Value::Undefined => None,
+            Value::Bool(_) => Some(ColumnKind::Boolean),
+            Value::Number(_)
+            | Value::Int(_)
+            | Value::UInt(_)
+            | Value::NaN
+            | Value::PosInfinity
+            | Value::NegInfinity
+            | Value::NegZero => Some(ColumnKind::Number),
+            Value::String(_) => Some(ColumnKind::String),
+            #[cfg(feature = "chrono")]
+            Value::Date(_) | Value::OffsetDate(_) | Value::InvalidDate => Some(ColumnKind::Date),
+            #[cfg(feature = "bigint")]
+            Value::BigInt(_) => Some(ColumnKind::BigInt),
+            _ => return None,
+        })
+    }
+}
+
+/// Build a `DataFrame` from an array of flat objects.
+///
+/// # Errors
+/// Returns [`Error::TypeMismatch`] if `rows` is not an array of objects, or
+/// for a value whose type cannot be stored in a column or differs from the
+/// rest of its column.
+pub fn to_dataframe(rows: &Value) -> Result<DataFrame> {
+    let rows = match rows {
+        Value::Array(rows) => rows,
+        other => return Err(mismatch(String::new(), "array", other)),
+    };
+
+    let mut columns: IndexMap<&str, Vec<&Value>> = IndexMap::new();
+    for (i, row) in rows.iter().enumerate() {
+        let Value::Object(map) = row else {
+            return Err(mismatch(i.to_string(), "object", row));
+        };
+        for (key, value) in map {
+            let column = columns
+                .entry(key.as_str())
+                .or_insert_with(|| vec![&Value::Null; i]);
+            column.push(value);
+        }
+        for column in columns.values_mut() {
+            column.resize(i + 1, &Value::Null);
+        }
+    }
+
+    let columns = columns
+        .into_iter()
+        .map(|(name, values)| to_column(name, &values))
+        .collect::<Result<Vec<_>>>()?;
+    Ok(DataFrame::new(columns)?)
+}
+
+fn to_column(name: &str, values: &[&Value]) -> Result<Column> {
+    let path = |i: usize| format!("{i}.{}", crate::path::escape_key(name));
+    let mut kind = None;
+    for (i, value) in values.iter().enumerate() {
+        match (ColumnKind::of(value), kind) {
+            (None, _) => {
+                return Err(mismatch(
+                    path(i),
+                    "a boolean, number, string, Date or bigint",
+                    value,
+                ));
+            }
+            (Some(Some(found)), Some(expected)) if found != expected => {
+                return Err(mismatch(path(i), values_of(expected), value));
+            }
+            (Some(found), _) => kind = kind.or(found),
+        }
+    }
+
+    let name = PlSmallStr::from(name);
+    let series = match kind {
+        None => Series::new_null(name, values.len()),
+        Some(ColumnKind::Boolean) => {
+            let values: Vec<_> = values.iter().map(|v| v.as_bool()).collect();
+            Series::new(name, values)
+        }
+        Some(ColumnKind::Number) => {
+            let values: Vec<_> = values.iter().map(|v| number(v)).collect();
+            Series::new(name, values)
+        }
+        Some(ColumnKind::String) => {
+            let values: Vec<_> = values.iter().map(|v| v.as_str()).collect();
+            Series::new(name, values)
+        }
+        #[cfg(feature = "chrono")]
+        Some(ColumnKind::Date) => {
+            let values: Vec<_> = values
+                .iter()
+                .map(|v| v.as_offset_date().map(|dt| dt.timestamp_millis()))
+                .collect();
+            Series::new(name, values).cast(&DataType::Datetime(TimeUnit::Milliseconds, None))?
+        }
+        #[cfg(feature = "bigint")]
+        Some(ColumnKind::BigInt) => bigint_series(name, values)?,
+    };
+    Ok(series.into_column())
+}
+
+/// The most digits a `Decimal` column holds.
+#[cfg(feature = "bigint")]
+const DECIMAL_DIGITS: u32 = 38;
+
+/// A bigint column in the narrowest type holding all of `values`: `Int64`,
+/// `Decimal(38, 0)`, or the decimal strings of the values.
+#[cfg(feature = "bigint")]
+fn bigint_series(name: PlSmallStr, values: &[&Value]) -> Result<Series> {
+    let bigints = || {
+        values.iter().map(|v| match v {
+            Value::BigInt(n) => Some(n),
+            _ => None,
+        })
+    };
+    let int64: Option<Vec<_>> = bigints()
+        .map(|n| n.map(i64::try_from).transpose().ok())
+        .collect();
+    if let Some(values) = int64 {
+        return Ok(Series::new(name, values));
+    }
+    let limit = 10u128.pow(DECIMAL_DIGITS);
+    let int128: Option<Vec<_>> = bigints()
+        .map(|n| {
+            n.map(|n| i128::try_from(n).ok().filter(|n| n.unsigned_abs() < limit))
+                .map_or(Some(None), |n| n.map(Some))
+        })
+        .collect();
+    if let Some(values) = int128 {
+        let decimals = Int128Chunked::from_iter_options(name, values.into_iter())
+            .into_decimal(Some(DECIMAL_DIGITS as usize), 0)?;
+        return Ok(decimals.into_series());
+    }
+    let strings: Vec<_> = bigints().map(|n| n.map(ToString::to_string)).collect();
+    Ok(Series::new(name, strings))
+}
+
+/// Read a `DataFrame` back as an array of objects, one per row.
+///
+/// Nulls are kept as `null` fields.
+///
+/// # Errors
+/// Returns [`Error::TypeMismatch`] for a column of a type listed in neither
+/// direction, such as a list or a struct.
+pub fn from_dataframe(df: &DataFrame) -> Result<Value> {
+    let mut rows: Vec<Map<String, Value>> = (0..df.height()).map(|_| Map::new()).collect();
+    for column in df.get_columns() {
+        let name = column.name().to_string();
+        let values = column_values(column.as_materialized_series(), &name)?;
+        for (row, value) in rows.iter_mut().zip(values) {
+            row.insert(name.clone(), value);
+        }
+    }
+    Ok(Value::Array(rows.into_iter().map(Value::Object).collect()))
+}
+
+fn column_values(series: &Series, name: &str) -> Result<Vec<Value>> {
+    let or_null = |value: Option<Value>| value.unwrap_or(Value::Null);
+    Ok(match series.dtype() {
+        DataType::Null => vec![Value::Null; series.len()],
+        DataType::Boolean => series
+            .bool()?
+            .into_iter()
+            .map(|v| or_null(v.map(Value::Bool)))
+            .collect(),
+        DataType::String => series
+            .str()?
+            .into_iter()
+            .map(|v| or_null(v.map(Value::from)))
+            .collect(),
+        #[cfg(feature = "bigint")]
+        DataType::Int64 => series
+            .i64()?
+            .into_iter()
+            .map(|v| or_null(v.map(|n| Value::BigInt(n.into()))))
+            .collect(),
+        #[cfg(feature = "bigint")]
+        DataType::Decimal(_, Some(0)) => series
+            .decimal()?
+            .physical()
+            .into_iter()
+            .map(|v| or_null(v.map(|n| Value::BigInt(n.into()))))
+            .collect(),
+        #[cfg(feature = "chrono")]
+        DataType::Datetime(unit, _) => {
+            let unit = *unit;
+            series
+                .cast(&DataType::Int64)?
+                .i64()?
+                .into_iter()
+                .map(|v| {
+                    v.map(|t| {
+                        let dt = match unit {
+                            TimeUnit::Nanoseconds => {
+                                Some(chrono::DateTime::from_timestamp_nanos(t))
+                            }
+                            TimeUnit::Microseconds => chrono::DateTime::from_timestamp_micros(t),
+                            TimeUnit::Milliseconds => chrono::DateTime::from_timestamp_millis(t),
+                        };
+                        dt.map(Value::Date).ok_or_else(|| {
+                            Error::InvalidDate(format!("{t} {unit} in column '{name}'"))
+                        })
+                    })
+                    .transpose()
+                    .map(or_null)
+                })
+                .collect::<Result<_>>()?
+        }
+        dtype if dtype.is_primitive_numeric() => series
+            .cast(&DataType::Float64)?
+            .f64()?
+            .into_iter()
+            .map(|v| or_null(v.map(Value::from)))
+            .collect(),
+        other => {
+            return Err(Error::TypeMismatch {
+                path: crate::path::escape_key(name),
+                expected: "a boolean, numeric, string or datetime column".to_string(),
+                actual: other.to_string(),
+            });
+        }
+    })
+}
+
+fn number(value: &Value) -> Option<f64> {
+    Some(match value {
+        Value::Number(n) => *n,
+        Value::Int(n) => *n as f64,
+        Value::UInt(n) => *n as f64,
+        Value::NaN => f64::NAN,
+        Value::PosInfinity => f64::INFINITY,
+        Value::NegInfinity => f64::NEG_INFINITY,
+        Value::NegZero => -0.0,
+        _ => return None,
+    })
+}
+
+fn values_of(kind: ColumnKind) -> &'static str {
+    match kind {
+        ColumnKind::Boolean => "boolean",
+        ColumnKind::Number => "number",
+        ColumnKind::String => "string",
+        #[cfg(feature = "chrono")]
+        ColumnKind::Date => "Date",
+        #[cfg(feature = "bigint")]
+        ColumnKind::BigInt => "bigint",
+    }
+}
+
+fn mismatch(path: String, expected: &str, actual: &Value) -> Error {
+    Error::TypeMismatch {
+        path,
+        expected: expected.to_string(),
+        actual: actual.type_name().to_string(),
+    }
+}
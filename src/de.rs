@@ -0,0 +1,381 @@
+//! Deserializing superjson payloads into any `serde::Deserialize` type.
+//!
+//! [`Value`] implements `serde::Deserializer`, so a restored value can drive
+//! the `Deserialize` implementation of a user type, and [`from_str`] parses
+//! an envelope straight into one. Extended types are presented the way the
+//! usual Rust types expect them:
+//!
+//! | Value | Presented as |
+//! |-------|--------------|
+//! | `undefined` | `null`: `None` for an `Option` |
+//! | `NaN`, the infinities, `-0` | an `f64` |
+//! | Date | an RFC 3339 string, which is what `chrono::DateTime` reads |
+//! | bigint | an integer if it fits in 128 bits, or the `(sign, digits)` tuple `num_bigint::BigInt` reads; otherwise a decimal string |
+//! | Set | a sequence, for a `Vec`, `HashSet` or `BTreeSet` |
+//! | Map | a map with non-string keys, for a `HashMap` or `BTreeMap` |
+//! | RegExp | its `/source/flags` string |
+//! | URL | its string |
+//! | Error | a struct with `name`, `message` and optional `cause` |
+//! | custom | its payload |
+//!
+//! Enums are read in the externally tagged form `serde_json` uses.
+//!
+//! # Examples
+//! ```
+//! use std::collections::{BTreeMap, HashSet};
+//!
+//! use serde::Deserialize;
+//!
+//! #[derive(Deserialize)]
+//! struct Room {
+//!     members: HashSet<String>,
+//!     seats: BTreeMap<u32, String>,
+//!     topic: Option<String>,
+//! }
+//!
+//! let room: Room = superjson_rs::from_str(
+//!     r#"{"json":{"members":["ada"],"seats":[[1,"ada"]]},
+//!         "meta":{"values":{"members":["set"],"seats":["map"]}}}"#,
+//! )
+//! .unwrap();
+//! assert!(room.members.contains("ada"));
+//! assert_eq!(room.seats[&1], "ada");
+//! assert_eq!(room.topic, None);
+//! ```
+
+use std::fmt;
+
+use serde::de::value::{MapDeserializer, SeqDeserializer, StringDeserializer};
+use serde::de::{
+    self, DeserializeOwned, DeserializeSeed, EnumAccess, IntoDeserializer, Unexpected,
+    VariantAccess, Visitor,
+};
+use serde::forward_to_deserialize_any;
+
+use crate::serialize::{self, MAX_SAFE_INTEGER};
+use crate::value::Map;
+use crate::{Error, Result, SuperJson, Value, deserialize, types};
+
+/// Parse a superjson string and deserialize its value into a `T`.
+pub fn from_str<T: DeserializeOwned>(s: &str) -> Result<T> {
+    from_value(crate::parse(s)?)
+}
+
+/// Restore an envelope and deserialize its value into a `T`.
+pub fn from_superjson<T: DeserializeOwned>(superjson: &SuperJson) -> Result<T> {
+    from_value(deserialize::deserialize(superjson)?)
+}
+
+/// Deserialize a value into a `T`.
+pub fn from_value<T: DeserializeOwned>(value: Value) -> Result<T> {
+    T::deserialize(value)
+}
+
+impl de::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error::Json(<serde_json::Error as de::Error>::custom(msg))
+    }
+}
+
+impl<'de> IntoDeserializer<'de, Error> for Value {
+    type Deserializer = Value;
+
+    fn into_deserializer(self) -> Value {
+        self
+    }
+}
+
+fn visit_number<'de, V: Visitor<'de>>(n: f64, visitor: V) -> Result<V::Value> {
+    if n.fract() == 0.0 && n.abs() <= MAX_SAFE_INTEGER {
+        if n < 0.0 {
+            visitor.visit_i64(n as i64)
+        } else {
+            visitor.visit_u64(n as u64)
+        }
+    } else {
+        visitor.visit_f64(n)
+    }
+}
+
+fn visit_seq<'de, V: Visitor<'de>>(items: Vec<Value>, visitor: V) -> Result<V::Value> {
+    let mut seq = SeqDeserializer::new(items.into_iter());
+    let value = visitor.visit_seq(&mut seq)?;
+    seq.end()?;
+    Ok(value)
+}
+
+fn visit_map<'de, V, I, K>(entries: I, visitor: V) -> Result<V::Value>
+where
+    V: Visitor<'de>,
+    I: Iterator<Item = (K, Value)>,
+    K: IntoDeserializer<'de, Error>,
+{
+    let mut map = MapDeserializer::new(entries);
+    let value = visitor.visit_map(&mut map)?;
+    map.end()?;
+    Ok(value)
+}
+
+impl<'de> de::Deserializer<'de> for Value {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self {
+            Value::Null | Value::Undefined => visitor.visit_unit(),
+            Value::Bool(b) => visitor.visit_bool(b),
+            Value::Number(n) => visit_number(n, visitor),
+            Value::Int(n) if n < 0 => visitor.visit_i64(n),
+            Value::Int(n) => visitor.visit_u64(n as u64),
+            Value::UInt(n) => visitor.visit_u64(n),
+            Value::NaN => visitor.visit_f64(f64::NAN),
+            Value::PosInfinity => visitor.visit_f64(f64::INFINITY),
+            Value::NegInfinity => visitor.visit_f64(f64::NEG_INFINITY),
+            Value::NegZero => visitor.visit_f64(-0.0),
+            Value::String(s) | Value::Symbol(s) => visitor.visit_string(s),
+            Value::Url(url) => visitor.visit_string(crate::value::url_into_string(url)),
+            Value::Bytes(bytes) => visitor.visit_byte_buf(bytes),
+            Value::Array(items) | Value::Set(items) => visit_seq(items, visitor),
+            Value::Object(map) | Value::Class { fields: map, .. } => {
+                visit_map(map.into_iter(), visitor)
+            }
+            Value::Map(entries) => visit_map(entries.into_iter(), visitor),
+            #[cfg(feature = "chrono")]
+            Value::Date(dt) => {
+                visitor.visit_string(dt.to_rfc3339_opts(chrono::SecondsFormat::Millis, true))
+            }
+            #[cfg(feature = "chrono")]
+            Value::OffsetDate(dt) => {
+                visitor.visit_string(dt.to_rfc3339_opts(chrono::SecondsFormat::Millis, true))
+            }
+            #[cfg(feature = "chrono")]
+            Value::InvalidDate => visitor.visit_str(crate::value::INVALID_DATE),
+            #[cfg(feature = "bigint")]
+            Value::BigInt(n) => {
+                if let Ok(n) = i64::try_from(&n) {
+                    visitor.visit_i64(n)
+                } else if let Ok(n) = u64::try_from(&n) {
+                    visitor.visit_u64(n)
+                } else if let Ok(n) = i128::try_from(&n) {
+                    visitor.visit_i128(n)
+                } else if let Ok(n) = u128::try_from(&n) {
+                    visitor.visit_u128(n)
+                } else {
+                    visitor.visit_string(n.to_string())
+                }
+            }
+            Value::RegExp { source, flags } => visitor.visit_string(format!("/{source}/{flags}")),
+            Value::Error {
+                name,
+                message,
+                stack,
+                cause,
+                extra,
+            } => {
+                let mut fields = Map::new();
+                fields.insert("name".to_string(), Value::String(name));
+                fields.insert("message".to_string(), Value::String(message));
+                if let Some(stack) = stack {
+                    fields.insert("stack".to_string(), Value::String(stack));
+                }
+                if let Some(cause) = cause {
+                    fields.insert("cause".to_string(), *cause);
+                }
+                fields.extend(extra);
+                visit_map(fields.into_iter(), visitor)
+            }
+            Value::Custom { value, .. } => value.deserialize_any(visitor),
+            Value::Unknown { raw, .. } => Value::from_json(raw).deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self {
+            Value::Null | Value::Undefined => visitor.visit_none(),
+            other => visitor.visit_some(other),
+        }
+    }
+
+    /// A `Value` is read from its envelope, as other formats store it.
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        if name == types::VALUE {
+            let envelope = serde_json::to_value(serialize::serialize(&self)?)?;
+            return visitor.visit_newtype_struct(Value::from_json(envelope));
+        }
+        visitor.visit_newtype_struct(self)
+    }
+
+    /// A bigint read as a tuple is `num_bigint::BigInt`'s `(sign, digits)`.
+    fn deserialize_tuple<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value> {
+        match self {
+            #[cfg(feature = "bigint")]
+            Value::BigInt(n) if len == 2 => {
+                let (sign, digits) = n.to_u32_digits();
+                let sign = match sign {
+                    num_bigint::Sign::Minus => -1.0,
+                    num_bigint::Sign::NoSign => 0.0,
+                    num_bigint::Sign::Plus => 1.0,
+                };
+                let digits = digits
+                    .into_iter()
+                    .map(|d| Value::Number(d.into()))
+                    .collect();
+                visit_seq(vec![Value::Number(sign), Value::Array(digits)], visitor)
+            }
+            other => {
+                let _ = len;
+                other.deserialize_any(visitor)
+            }
+        }
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        match self {
+            Value::String(variant) => {
+                let variant: StringDeserializer<Error> = variant.into_deserializer();
+                visitor.visit_enum(variant)
+            }
+            Value::Object(map) if map.len() == 1 => {
+                let (name, value) = map.into_iter().next().expect("the map has one entry");
+                visitor.visit_enum(Variant { name, value })
+            }
+            other => Err(de::Error::invalid_type(
+                Unexpected::Other(other.type_name()),
+                &"a variant name or an object with a single key",
+            )),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple_struct map struct identifier
+        ignored_any
+    }
+}
+
+/// An enum variant written as `{name: value}`.
+struct Variant {
+    name: String,
+    value: Value,
+}
+
+impl<'de> EnumAccess<'de> for Variant {
+    type Error = Error;
+    type Variant = Value;
+
+    fn variant_seed<S: DeserializeSeed<'de>>(self, seed: S) -> Result<(S::Value, Value)> {
+        let name: StringDeserializer<Error> = self.name.into_deserializer();
+        Ok((seed.deserialize(name)?, self.value))
+    }
+}
+
+impl<'de> VariantAccess<'de> for Value {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<()> {
+        match self {
+            Value::Null | Value::Undefined => Ok(()),
+            other => Err(de::Error::invalid_type(
+                Unexpected::Other(other.type_name()),
+                &"a unit variant",
+            )),
+        }
+    }
+
+    fn newtype_variant_seed<S: DeserializeSeed<'de>>(self, seed: S) -> Result<S::Value> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value> {
+        de::Deserializer::deserialize_any(self, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        de::Deserializer::deserialize_any(self, visitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    enum Shape {
+        Dot,
+        Circle { r: f64 },
+        Line(f64, f64),
+    }
+
+    #[test]
+    fn test_enums_and_special_numbers() {
+        let shapes: Vec<Shape> = from_str(
+            r#"{"json":["Dot",{"Circle":{"r":"Infinity"}},{"Line":[1,"-0"]}],
+                "meta":{"values":{"1.Circle.r":["number"],"2.Line.1":["number"]}}}"#,
+        )
+        .unwrap();
+        assert_eq!(shapes[0], Shape::Dot);
+        assert_eq!(shapes[1], Shape::Circle { r: f64::INFINITY });
+        let Shape::Line(_, end) = shapes[2] else {
+            panic!("expected a line");
+        };
+        assert!(end == 0.0 && end.is_sign_negative());
+    }
+
+    #[test]
+    fn test_round_trip_through_to_string() {
+        let original: Vec<(u8, Option<String>)> = vec![(1, None), (2, Some("b".into()))];
+        let text = crate::to_string(&original).unwrap();
+        assert_eq!(
+            from_str::<Vec<(u8, Option<String>)>>(&text).unwrap(),
+            original
+        );
+
+        let undefined: Option<u8> =
+            from_str(r#"{"json":null,"meta":{"values":["undefined"]}}"#).unwrap();
+        assert_eq!(undefined, None);
+        assert!(from_str::<u8>(r#"{"json":"x"}"#).is_err());
+    }
+
+    #[cfg(all(feature = "chrono", feature = "bigint"))]
+    #[test]
+    fn test_dates_and_bigints() {
+        use chrono::TimeZone;
+
+        #[derive(Deserialize)]
+        struct Row {
+            at: chrono::DateTime<chrono::Utc>,
+            id: num_bigint::BigInt,
+            small: i128,
+        }
+
+        let row: Row = from_str(
+            r#"{"json":{"at":"2024-05-01T12:00:00.250Z","id":"-123456789012345678901234567890","small":"42"},
+                "meta":{"values":{"at":["Date"],"id":["bigint"],"small":["bigint"]}}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            row.at,
+            chrono::Utc.timestamp_millis_opt(1_714_564_800_250).unwrap()
+        );
+        assert_eq!(
+            row.id,
+            "-123456789012345678901234567890"
+                .parse::<num_bigint::BigInt>()
+                .unwrap()
+        );
+        assert_eq!(row.small, 42);
+    }
+}
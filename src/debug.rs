@@ -0,0 +1,260 @@
+//! Self-describing JSON for human inspection.
+//!
+//! [`Value::to_debug_json`] writes a value as a single JSON document with
+//! types tagged inline, rather than split between `json` and `meta` as in an
+//! envelope. The output is meant for logs and for reading; it is not parsed
+//! back.
+//!
+//! # Format
+//!
+//! Plain JSON values are written as themselves. Every other value becomes
+//! an object with a `$type` tag, its [`type_name`](Value::type_name), and a
+//! `value`:
+//!
+//! | Value | Output |
+//! |-------|--------|
+//! | `undefined` | `{"$type":"undefined"}` |
+//! | Date, bigint, RegExp, URL, symbol, special numbers | `{"$type":..., "value": <encoding as in an envelope>}` |
+//! | Set | `{"$type":"Set","value":[...]}` |
+//! | Map | `{"$type":"Map","value":[[key, value], ...]}` |
+//! | Error | `{"$type":"Error","value":{"name":...,"message":...,"cause":...,...}}` |
+//! | custom | `{"$type":"custom","name":...,"value": <payload>}` |
+//! | class instance | `{"$type":"class","name":...,"value":{...}}` |
+//!
+//! A plain object that has a `$type` key of its own is wrapped as
+//! `{"$type":"object","value":{...}}`, so tags are never ambiguous.
+
+use serde_json::json;
+
+use crate::Value;
+
+const TAG: &str = "$type";
+
+/// The container a frame is writing.
+enum Kind<'a> {
+    Array,
+    Object(Vec<&'a String>),
+    Class(&'a str, Vec<&'a String>),
+    Set,
+    Map,
+    Error {
+        /// The `name`, `message` and any `stack` of the error.
+        error: Box<serde_json::Value>,
+        has_cause: bool,
+        extra: Vec<&'a String>,
+    },
+}
+
+struct Frame<'a> {
+    kind: Kind<'a>,
+    children: std::vec::IntoIter<&'a Value>,
+    json: Vec<serde_json::Value>,
+}
+
+impl<'a> Frame<'a> {
+    fn new(kind: Kind<'a>, children: Vec<&'a Value>) -> Self {
+        Frame {
+            kind,
+            json: Vec::with_capacity(children.len()),
+            children: children.into_iter(),
+        }
+    }
+
+    fn finish(self) -> serde_json::Value {
+        match self.kind {
+            Kind::Array => serde_json::Value::Array(self.json),
+            Kind::Object(keys) => {
+                let tagged = keys.iter().any(|key| *key == TAG);
+                let map: serde_json::Map<String, serde_json::Value> =
+                    keys.into_iter().cloned().zip(self.json).collect();
+                if tagged {
+                    json!({ TAG: "object", "value": map })
+                } else {
+                    serde_json::Value::Object(map)
+                }
+            }
+            Kind::Class(name, keys) => {
+                let map: serde_json::Map<String, serde_json::Value> =
+                    keys.into_iter().cloned().zip(self.json).collect();
+                json!({ TAG: "class", "name": name, "value": map })
+            }
+            Kind::Set => json!({ TAG: "Set", "value": self.json }),
+            Kind::Map => {
+                let mut json = self.json.into_iter();
+                let mut entries = Vec::new();
+                while let (Some(key), Some(value)) = (json.next(), json.next()) {
+                    entries.push(json!([key, value]));
+                }
+                json!({ TAG: "Map", "value": entries })
+            }
+            Kind::Error {
+                mut error,
+                has_cause,
+                extra,
+            } => {
+                let mut json = self.json.into_iter();
+                if has_cause && let Some(cause) = json.next() {
+                    error["cause"] = cause;
+                }
+                for (key, value) in extra.into_iter().zip(json) {
+                    error[key] = value;
+                }
+                json!({ TAG: "Error", "value": *error })
+            }
+        }
+    }
+}
+
+/// Write a scalar directly, or open a frame for a container.
+fn begin(value: &Value) -> Result<serde_json::Value, Frame<'_>> {
+    let frame = match value {
+        Value::Null => return Ok(serde_json::Value::Null),
+        Value::Bool(b) => return Ok(json!(b)),
+        Value::Number(n) => return Ok(json!(n)),
+        Value::Int(n) => return Ok(json!(n)),
+        Value::UInt(n) => return Ok(json!(n)),
+        Value::String(s) => return Ok(json!(s)),
+        Value::Undefined => return Ok(json!({ TAG: "undefined" })),
+        Value::Array(items) => Frame::new(Kind::Array, items.iter().collect()),
+        Value::Object(map) => {
+            let (keys, children) = map.iter().unzip();
+            Frame::new(Kind::Object(keys), children)
+        }
+        Value::Class { name, fields } => {
+            let (keys, children) = fields.iter().unzip();
+            Frame::new(Kind::Class(name, keys), children)
+        }
+        Value::Set(items) => Frame::new(Kind::Set, items.iter().collect()),
+        Value::Map(entries) => Frame::new(
+            Kind::Map,
+            entries
+                .iter()
+                .flat_map(|(key, value)| [key, value])
+                .collect(),
+        ),
+        Value::Error {
+            name,
+            message,
+            stack,
+            cause,
+            extra,
+        } => {
+            let (keys, values): (Vec<_>, Vec<_>) = extra.iter().unzip();
+            let mut error = json!({ "name": name, "message": message });
+            if let Some(stack) = stack {
+                error["stack"] = json!(stack);
+            }
+            Frame::new(
+                Kind::Error {
+                    error: Box::new(error),
+                    has_cause: cause.is_some(),
+                    extra: keys,
+                },
+                cause.as_deref().into_iter().chain(values).collect(),
+            )
+        }
+        Value::Custom { name, value } => {
+            return Ok(json!({ TAG: "custom", "name": name, "value": encoding(value) }));
+        }
+        Value::Unknown { type_name, raw } => return Ok(json!({ TAG: type_name, "value": raw })),
+        other => return Ok(json!({ TAG: other.type_name(), "value": encoding(other) })),
+    };
+    Err(frame)
+}
+
+/// The JSON-compatible encoding of a value, as in the `json` part of an
+/// envelope.
+fn encoding(value: &Value) -> serde_json::Value {
+    // Only leaves and custom payloads, which hold JSON types only, are
+    // encoded here, and neither can fail to serialize.
+    crate::serialize::serialize(value)
+        .map(|superjson| superjson.json)
+        .unwrap_or(serde_json::Value::Null)
+}
+
+impl Value {
+    /// Write this value as one self-describing JSON document, with types
+    /// tagged inline. See the [module documentation](self) for the format.
+    ///
+    /// # Examples
+    /// ```
+    /// use serde_json::json;
+    /// use superjson_rs::Value;
+    ///
+    /// let value = Value::from(vec![Value::Set(vec![Value::from("a")]), Value::NaN]);
+    /// assert_eq!(
+    ///     value.to_debug_json(),
+    ///     json!([{"$type": "Set", "value": ["a"]}, {"$type": "NaN", "value": "NaN"}])
+    /// );
+    /// ```
+    pub fn to_debug_json(&self) -> serde_json::Value {
+        let mut stack: Vec<Frame> = Vec::new();
+        let mut step = begin(self);
+
+        loop {
+            match step {
+                Err(frame) => stack.push(frame),
+                Ok(json) => match stack.last_mut() {
+                    None => return json,
+                    Some(parent) => parent.json.push(json),
+                },
+            }
+
+            let frame = stack.last_mut().expect("stack is non-empty after a push");
+            step = match frame.children.next() {
+                Some(child) => begin(child),
+                None => Ok(stack.pop().expect("stack is non-empty").finish()),
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::value::Map;
+
+    #[test]
+    fn test_extended_containers() {
+        let value = Value::Map(vec![(
+            Value::from("k"),
+            Value::Error {
+                name: "TypeError".into(),
+                message: "bad".into(),
+                stack: None,
+                cause: Some(Box::new(Value::Undefined)),
+                extra: Map::new(),
+            },
+        )]);
+        assert_eq!(
+            value.to_debug_json(),
+            json!({"$type": "Map", "value": [["k", {"$type": "Error", "value": {
+                "name": "TypeError",
+                "message": "bad",
+                "cause": {"$type": "undefined"}
+            }}]]})
+        );
+    }
+
+    #[test]
+    fn test_tag_collisions_are_wrapped() {
+        let mut map = Map::new();
+        map.insert("$type".into(), Value::from("Date"));
+        assert_eq!(
+            Value::Object(map).to_debug_json(),
+            json!({"$type": "object", "value": {"$type": "Date"}})
+        );
+    }
+
+    #[test]
+    fn test_custom() {
+        let value = Value::Custom {
+            name: "money".into(),
+            value: Box::new(Value::from("1.50 EUR")),
+        };
+        assert_eq!(
+            value.to_debug_json(),
+            json!({"$type": "custom", "name": "money", "value": "1.50 EUR"})
+        );
+    }
+}
@@ -1,94 +1,1418 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+
+#[cfg(feature = "chrono")]
 use chrono::DateTime;
 use indexmap::IndexMap;
+#[cfg(feature = "bigint")]
 use num_bigint::BigInt;
+use serde::Deserialize;
+use serde_json::value::RawValue;
 
+use crate::borrowed::Value as BorrowedValue;
+use crate::bytes::{BYTES, BytesEncoding};
+use crate::codec::{Hook, apply_hooks};
 use crate::error::Error;
-use crate::{AnnotationValues, Result, SuperJson, TypeAnnotation, Value};
+use crate::forensic::{ForensicValue, RawJson};
+use crate::passthrough::{RawContainer, Value as PassthroughValue};
+use crate::pool::ValuePool;
+use crate::serialize::{is_error_prop, keeps_error_stack};
+use crate::stats::ParseStats;
+use crate::value::{Map, json_number};
+use crate::warning::Warning;
+use crate::{AnnotationValues, Meta, Result, SuperJson, TypeAnnotation, Value, envelope};
+
+/// Default nesting limit applied by [`ParseOptions`].
+///
+/// Matches the recursion limit `serde_json` applies when parsing text.
+pub const DEFAULT_MAX_DEPTH: usize = 128;
+
+/// Default limit on annotation tree nodes applied by [`ParseOptions`].
+pub const DEFAULT_MAX_ANNOTATIONS: usize = 100_000;
+
+/// Default limit on values copied to restore shared references, applied by
+/// [`ParseOptions`].
+pub const DEFAULT_MAX_SHARED_VALUES: usize = 1_000_000;
+
+/// Limits applied while parsing untrusted superjson input.
+#[derive(Debug, Clone)]
+pub struct ParseOptions {
+    /// Maximum nesting depth of the `json` tree and of any single annotation
+    /// tree in `meta.values`.
+    pub max_depth: usize,
+    /// Maximum number of annotation tree nodes in `meta.values`: its entries
+    /// plus all of their nested children.
+    ///
+    /// Checked before any annotation is interpreted, since a small `json`
+    /// paired with a huge annotation map is cheap to send and costly to
+    /// restore.
+    pub max_annotations: usize,
+    /// Maximum number of values created by copying the shared values listed
+    /// in `meta.referentialEqualities` to the other paths they are shared
+    /// with.
+    ///
+    /// A deduplicated payload names each shared value once, so nesting them
+    /// lets a small payload restore to an exponentially larger tree.
+    pub max_shared_values: usize,
+    /// Fail with [`Error::UnsupportedVersion`] on a `meta.v` newer than
+    /// [`META_VERSION`](crate::META_VERSION), instead of reading the payload
+    /// as the latest known version with a [`Warning::UnknownVersion`].
+    pub strict_version: bool,
+    /// Collect [`ParseStats`] for [`parse_detailed`](crate::parse_detailed).
+    pub collect_stats: bool,
+    /// How [`Value::Bytes`] are read.
+    pub bytes_encoding: BytesEncoding,
+    /// The custom type name [`Value::Bytes`] are read from, or
+    /// [`BYTES`] if `None`. Custom values under this name whose payload is
+    /// not a string in `bytes_encoding` are left as they are, with a
+    /// [`Warning::InvalidBytes`].
+    pub bytes_name: Option<String>,
+    /// Properties of an `Error` besides `name`, `message` and `cause` to
+    /// restore into [`Value::Error`]'s `extra` map; others are dropped.
+    /// Listing `stack` restores the stack trace into its `stack`.
+    pub error_props: Vec<String>,
+    /// Restore values under leaf annotations this crate does not recognise
+    /// as [`Value::Unknown`]s, which are written back as they were, instead
+    /// of failing with [`Error::InvalidTypeAnnotation`]. An unrecognised
+    /// annotation with children of its own is still an error.
+    pub keep_unknown_types: bool,
+    /// Restore Dates written with a UTC offset other than `Z`, such as
+    /// `2024-01-01T09:00:00.000+09:00`, as [`Value::OffsetDate`]s that keep
+    /// it, instead of converting them to UTC with a
+    /// [`Warning::CoercedDate`].
+    #[cfg(feature = "chrono")]
+    pub preserve_date_offsets: bool,
+    /// What to do with a `Date` that holds no valid date.
+    #[cfg(feature = "chrono")]
+    pub invalid_dates: InvalidDates,
+}
+
+/// What to do with a `Date` that holds no valid date, such as
+/// `"Invalid Date"`, which JavaScript writes for `new Date(NaN)`.
+#[cfg(feature = "chrono")]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum InvalidDates {
+    /// Fail with [`Error::InvalidDate`].
+    #[default]
+    Error,
+    /// Restore it as a [`Value::InvalidDate`].
+    Keep,
+    /// Restore it as the Unix epoch.
+    Epoch,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        ParseOptions {
+            max_depth: DEFAULT_MAX_DEPTH,
+            max_annotations: DEFAULT_MAX_ANNOTATIONS,
+            max_shared_values: DEFAULT_MAX_SHARED_VALUES,
+            strict_version: false,
+            collect_stats: false,
+            bytes_encoding: BytesEncoding::default(),
+            bytes_name: None,
+            error_props: Vec::new(),
+            keep_unknown_types: false,
+            #[cfg(feature = "chrono")]
+            preserve_date_offsets: false,
+            #[cfg(feature = "chrono")]
+            invalid_dates: InvalidDates::default(),
+        }
+    }
+}
 
 /// Deserialize a superjson `{json, meta}` representation back into a `Value`.
 ///
 /// This restores extended types (Date, BigInt, Set, etc.) from their
 /// JSON-compatible representations using the tree-structured type annotations.
 pub fn deserialize(superjson: &SuperJson) -> Result<Value> {
-    let values = superjson.meta.as_ref().and_then(|m| m.values.as_ref());
+    deserialize_with(superjson, &ParseOptions::default())
+}
+
+/// Deserialize a superjson representation, enforcing the limits in `options`.
+///
+/// The JSON tree is walked with an explicit work stack, so nesting beyond
+/// `options.max_depth` is reported as [`Error::DepthLimitExceeded`] instead of
+/// overflowing the thread's stack.
+pub fn deserialize_with(superjson: &SuperJson, options: &ParseOptions) -> Result<Value> {
+    deserialize_detailed(superjson, options, &[], &mut Vec::new(), None)
+}
+
+/// Deserialize an envelope held as a JSON object, such as the output of
+/// [`serialize_to_json`](crate::serialize::serialize_to_json).
+///
+/// The envelope is checked as [`parse`](crate::parse) checks text: legacy
+/// `meta.values` are accepted and `meta.v` is compared against
+/// [`META_VERSION`](crate::META_VERSION).
+pub fn deserialize_json(envelope: serde_json::Value) -> Result<Value> {
+    deserialize_json_with(envelope, &ParseOptions::default())
+}
+
+/// Deserialize an envelope held as a JSON object, enforcing the limits in
+/// `options`.
+pub fn deserialize_json_with(envelope: serde_json::Value, options: &ParseOptions) -> Result<Value> {
+    let raw = envelope::RawSuperJson::deserialize(envelope)?;
+    let superjson = raw.into_superjson(options, &mut Vec::new())?;
+    deserialize_with(&superjson, options)
+}
+
+/// Deserialize the `json` and `meta` halves of an envelope, as
+/// [`serialize_split`](crate::serialize::serialize_split) produces them.
+///
+/// # Examples
+/// ```
+/// use superjson_rs::deserialize::deserialize_split;
+/// use superjson_rs::serialize::serialize_split;
+/// use superjson_rs::Value;
+///
+/// let value = Value::Map(vec![(Value::from(1), Value::NaN)]);
+/// let (json, meta) = serialize_split(&value).unwrap();
+/// assert_eq!(json, serde_json::json!([[1, "NaN"]]));
+/// assert_eq!(deserialize_split(json, meta).unwrap(), value);
+/// ```
+pub fn deserialize_split(json: serde_json::Value, meta: Option<Meta>) -> Result<Value> {
+    deserialize_split_with(json, meta, &ParseOptions::default())
+}
+
+/// Deserialize the `json` and `meta` halves of an envelope, enforcing the
+/// limits in `options`.
+pub fn deserialize_split_with(
+    json: serde_json::Value,
+    meta: Option<Meta>,
+    options: &ParseOptions,
+) -> Result<Value> {
+    deserialize_with(&SuperJson { json, meta }, options)
+}
+
+/// Deserialize a superjson representation, applying `hooks` to restored
+/// values, appending any non-fatal issues to `warnings` and, if given,
+/// counting what was restored into `stats`.
+pub(crate) fn deserialize_detailed(
+    superjson: &SuperJson,
+    options: &ParseOptions,
+    hooks: &[Hook],
+    warnings: &mut Vec<Warning>,
+    stats: Option<&mut ParseStats>,
+) -> Result<Value> {
+    deserialize_pooled_detailed(
+        superjson,
+        options,
+        hooks,
+        warnings,
+        stats,
+        &mut ValuePool::new(),
+    )
+}
+
+/// Deserialize a superjson representation, taking the buffers of its
+/// arrays, objects and strings from `pool`.
+pub(crate) fn deserialize_pooled(
+    superjson: &SuperJson,
+    options: &ParseOptions,
+    pool: &mut ValuePool,
+) -> Result<Value> {
+    deserialize_pooled_detailed(superjson, options, &[], &mut Vec::new(), None, pool)
+}
+
+/// [`deserialize_detailed`], building arrays, objects and strings out of
+/// the buffers in `pool`, whether or not they carry annotations.
+fn deserialize_pooled_detailed(
+    superjson: &SuperJson,
+    options: &ParseOptions,
+    hooks: &[Hook],
+    warnings: &mut Vec<Warning>,
+    mut stats: Option<&mut ParseStats>,
+    pool: &mut ValuePool,
+) -> Result<Value> {
+    #[cfg(feature = "hash")]
+    verify_checksum(superjson)?;
+    if let (Some(stats), Some(values)) = (stats.as_deref_mut(), annotation_values(superjson)) {
+        stats.annotations = values.iter().count();
+    }
+    if annotation_values(superjson).is_none() && hooks.is_empty() && stats.is_none() {
+        let mut value = deserialize_plain(&superjson.json, options.max_depth, pool)?;
+        restore_shared(&mut value, superjson, options, warnings)?;
+        return Ok(value);
+    }
+    let mut diagnostics = Diagnostics::new(options, hooks, warnings, stats);
+    diagnostics.pool = std::mem::take(pool);
+    let value = deserialize_tree(
+        &superjson.json,
+        root_ctx(superjson),
+        options.max_depth,
+        &mut diagnostics,
+    );
+    // Hand the buffers back even if restoring failed.
+    *pool = diagnostics.pool;
+    let mut value = value?;
+    restore_shared(&mut value, superjson, options, diagnostics.warnings)?;
+    Ok(value)
+}
+
+/// Copy each value `meta.referentialEqualities` lists as shared to the other
+/// paths it is shared with.
+///
+/// JS superjson with `dedupe: true`, like
+/// [`SerializeOptions::dedupe`](crate::SerializeOptions::dedupe), writes a
+/// shared value once and `null` at its other paths. A [`Value`] cannot
+/// share a subtree, so each of them gets a copy. An object that contains
+/// itself cannot be copied, and is reported as a
+/// [`Warning::CyclicReference`].
+fn restore_shared(
+    value: &mut Value,
+    superjson: &SuperJson,
+    options: &ParseOptions,
+    warnings: &mut Vec<Warning>,
+) -> Result<()> {
+    let Some(equalities) = superjson
+        .meta
+        .as_ref()
+        .and_then(|meta| meta.referential_equalities.as_ref())
+    else {
+        return Ok(());
+    };
+    let invalid = |reason: String| Error::InvalidReferentialEquality(reason);
+
+    // Paths shared with the root come first, in an array, if there are any.
+    let (root, others) = match equalities {
+        serde_json::Value::Object(others) => (None, Some(others)),
+        serde_json::Value::Array(parts) => match parts.as_slice() {
+            [root] => (Some(root), None),
+            [root, serde_json::Value::Object(others)] => (Some(root), Some(others)),
+            _ => return Err(invalid(format!("malformed annotation {equalities}"))),
+        },
+        _ => return Err(invalid(format!("malformed annotation {equalities}"))),
+    };
+    let groups = root.map(|paths| ("", paths)).into_iter().chain(
+        others
+            .into_iter()
+            .flatten()
+            .map(|(path, paths)| (path.as_str(), paths)),
+    );
+
+    let mut copied = 0;
+    for (shared_path, paths) in groups {
+        let paths = paths
+            .as_array()
+            .ok_or_else(|| invalid(format!("the paths sharing '{shared_path}' are not a list")))?;
+        let shared_segments = crate::path::parse(shared_path);
+        let shared = value
+            .pointer(shared_path)
+            .ok_or_else(|| invalid(format!("no value at '{shared_path}'")))?
+            .clone();
+        let size = count_values(&shared);
+        for path in paths {
+            let path = path
+                .as_str()
+                .ok_or_else(|| invalid(format!("the path {path} is not a string")))?;
+            let segments = crate::path::parse(path);
+            if segments.starts_with(&shared_segments) || shared_segments.starts_with(&segments) {
+                warnings.push(Warning::CyclicReference {
+                    path: path.to_string(),
+                    shared: shared_path.to_string(),
+                });
+                continue;
+            }
+            copied += size;
+            if copied > options.max_shared_values {
+                return Err(invalid(format!(
+                    "restoring shared values would copy more than {} values",
+                    options.max_shared_values
+                )));
+            }
+            *crate::patch::resolve_mut(value, path, segments)? = shared.clone();
+        }
+    }
+    Ok(())
+}
+
+/// The number of values in `value`, counting itself.
+pub(crate) fn count_values(value: &Value) -> usize {
+    let mut stack = vec![value];
+    let mut count = 0;
+    while let Some(value) = stack.pop() {
+        count += 1;
+        match value {
+            Value::Array(items) | Value::Set(items) => stack.extend(items),
+            Value::Object(map) | Value::Class { fields: map, .. } => stack.extend(map.values()),
+            Value::Map(entries) => stack.extend(entries.iter().flat_map(|(k, v)| [k, v])),
+            Value::Error { cause, extra, .. } => {
+                stack.extend(cause.as_deref());
+                stack.extend(extra.values());
+            }
+            Value::Custom { value, .. } => stack.push(value),
+            _ => {}
+        }
+    }
+    count
+}
+
+/// Check `meta.checksum`, if present, against the `json` section.
+#[cfg(feature = "hash")]
+fn verify_checksum(superjson: &SuperJson) -> Result<()> {
+    match superjson.meta.as_ref().and_then(|m| m.checksum.as_ref()) {
+        Some(expected) => check_checksum(expected, &superjson.json),
+        None => Ok(()),
+    }
+}
+
+/// Check `expected`, a `meta.checksum`, against the `json` section.
+#[cfg(feature = "hash")]
+fn check_checksum(expected: &str, json: &serde_json::Value) -> Result<()> {
+    let actual = crate::hash::json_hash(json).to_string();
+    if !expected.eq_ignore_ascii_case(&actual) {
+        return Err(Error::ChecksumMismatch {
+            expected: expected.to_string(),
+            actual,
+        });
+    }
+    Ok(())
+}
+
+/// How [`deserialize_into`] combines incoming data with an existing value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MergeStrategy {
+    /// Replace the existing value entirely.
+    Replace,
+    /// Overwrite the top-level fields of an existing object, keeping the
+    /// fields the update does not mention.
+    Shallow,
+    /// Merge objects field by field at every level. Anything else, including
+    /// arrays, sets and maps, is replaced.
+    #[default]
+    Deep,
+}
+
+/// Deserialize a superjson representation directly into `target`, merging
+/// it with the existing contents according to `strategy`.
+///
+/// Only the parts of `target` the update touches are rebuilt: unchanged
+/// fields are left in place rather than restored and merged afterwards.
+/// Where an existing object is merged, fields new to it are appended.
+///
+/// If an error is returned, `target` may already have been partially
+/// updated.
+pub fn deserialize_into(
+    superjson: &SuperJson,
+    target: &mut Value,
+    strategy: MergeStrategy,
+    options: &ParseOptions,
+) -> Result<()> {
+    #[cfg(feature = "hash")]
+    verify_checksum(superjson)?;
+    let mut warnings = Vec::new();
+    let mut diagnostics = Diagnostics::new(options, &[], &mut warnings, None);
+    let mut stack = vec![(
+        &mut *target,
+        &superjson.json,
+        root_ctx(superjson),
+        Vec::new(),
+    )];
+
+    while let Some((target, json, ctx, path)) = stack.pop() {
+        let depth = path.len();
+        let merge = match strategy {
+            MergeStrategy::Replace => false,
+            MergeStrategy::Shallow => depth == 0,
+            MergeStrategy::Deep => true,
+        };
+        let annotations = match ctx {
+            Ctx::Plain => Ok(Annotations::None),
+            Ctx::Children(annotations) => Ok(annotations),
+            typed => Err(typed),
+        };
+
+        match (merge, annotations, json, target) {
+            (true, Ok(annotations), serde_json::Value::Object(map), Value::Object(existing)) => {
+                check_depth(depth, options.max_depth)?;
+                // New fields get a placeholder, which is then replaced like
+                // any other field that cannot be merged.
+                for key in map.keys() {
+                    if !existing.contains_key(key) {
+                        existing.insert(key.clone(), Value::Undefined);
+                    }
+                }
+                for (key, slot) in existing.iter_mut() {
+                    if let Some(child) = map.get(key) {
+                        let key = crate::path::escape_key(key);
+                        let ctx = annotations.child(&key);
+                        let mut child_path = path.clone();
+                        child_path.push(key);
+                        stack.push((slot, child, ctx, child_path));
+                    }
+                }
+            }
+            (_, annotations, json, target) => {
+                let ctx = annotations.map_or_else(|typed| typed, Ctx::Children);
+                diagnostics.path = path;
+                *target = deserialize_subtree(json, ctx, depth, options, &mut diagnostics)?;
+            }
+        }
+    }
+    restore_shared(target, superjson, options, diagnostics.warnings)
+}
+
+/// Restore `json`, the `json` part of `superjson` as received, keeping
+/// duplicate keys in plain objects.
+///
+/// Annotations are taken from `superjson`. Values with an annotation of
+/// their own are restored by [`deserialize_tree`].
+pub(crate) fn deserialize_forensic(
+    json: &RawJson,
+    superjson: &SuperJson,
+    options: &ParseOptions,
+) -> Result<ForensicValue> {
+    let mut warnings = Vec::new();
+    let mut diagnostics = Diagnostics::new(options, &[], &mut warnings, None);
+    let mut stack: Vec<ForensicFrame> = Vec::new();
+    let mut step = begin_forensic(json, root_ctx(superjson), 0, options, &mut diagnostics)?;
+
+    loop {
+        match step {
+            ForensicStep::Descend(frame) => {
+                check_depth(stack.len(), options.max_depth)?;
+                stack.push(frame);
+            }
+            ForensicStep::Done(value) => {
+                match stack.last_mut() {
+                    None => return Ok(value),
+                    Some(parent) => parent.values.push(value),
+                }
+                diagnostics.path.pop();
+            }
+        }
+
+        let depth = stack.len();
+        let frame = stack.last_mut().expect("stack is non-empty after a push");
+        step = match frame.children.next() {
+            Some(child) => {
+                let key = match &frame.keys {
+                    Some(keys) => crate::path::escape_key(&keys[frame.values.len()]),
+                    None => frame.values.len().to_string(),
+                };
+                let ctx = frame.annotations.child(&key);
+                diagnostics.path.push(key);
+                begin_forensic(child, ctx, depth, options, &mut diagnostics)?
+            }
+            None => {
+                let frame = stack.pop().expect("stack is non-empty");
+                let value = match frame.keys {
+                    Some(keys) => {
+                        ForensicValue::Object(keys.into_iter().zip(frame.values).collect())
+                    }
+                    None => ForensicValue::Array(frame.values),
+                };
+                ForensicStep::Done(value)
+            }
+        };
+    }
+}
+
+/// A plain object or array whose children are being restored with
+/// duplicate keys kept.
+struct ForensicFrame<'a> {
+    /// The keys of an object, in the order received; `None` for an array.
+    keys: Option<Vec<String>>,
+    children: std::vec::IntoIter<&'a RawJson>,
+    annotations: Annotations<'a>,
+    values: Vec<ForensicValue>,
+}
+
+enum ForensicStep<'a> {
+    Done(ForensicValue),
+    Descend(ForensicFrame<'a>),
+}
+
+/// Restore a scalar or annotated value directly, or open a frame for a plain
+/// container `depth` levels below the root.
+fn begin_forensic<'a>(
+    json: &'a RawJson,
+    ctx: Ctx<'a>,
+    depth: usize,
+    options: &ParseOptions,
+    diagnostics: &mut Diagnostics,
+) -> Result<ForensicStep<'a>> {
+    let annotations = match ctx {
+        Ctx::Plain => Annotations::None,
+        Ctx::Children(annotations) => annotations,
+        Ctx::Typed(_) => {
+            let value = deserialize_subtree(&json.to_json(), ctx, depth, options, diagnostics)?;
+            return Ok(ForensicStep::Done(ForensicValue::Value(value)));
+        }
+    };
+
+    let (keys, children) = match json {
+        RawJson::Scalar(scalar) => {
+            let value = deserialize_tree(scalar, Ctx::Plain, 0, diagnostics)?;
+            return Ok(ForensicStep::Done(ForensicValue::Value(value)));
+        }
+        RawJson::Array(items) => (None, items.iter().collect()),
+        RawJson::Object(fields) => (
+            Some(fields.iter().map(|(key, _)| key.clone()).collect()),
+            fields.iter().map(|(_, value)| value).collect::<Vec<_>>(),
+        ),
+    };
+    Ok(ForensicStep::Descend(ForensicFrame {
+        keys,
+        values: Vec::with_capacity(children.len()),
+        children: children.into_iter(),
+        annotations,
+    }))
+}
+
+/// Restore `json`, the `json` part of an envelope as read with borrowed
+/// strings, under the annotations of `meta`, appending any non-fatal issues
+/// to `warnings`.
+///
+/// Plain objects and arrays are kept as they are. Values with an annotation
+/// of their own are restored by [`deserialize_tree`].
+pub(crate) fn deserialize_borrowed<'a>(
+    mut json: BorrowedValue<'a>,
+    meta: Option<&Meta>,
+    options: &ParseOptions,
+    warnings: &mut Vec<Warning>,
+) -> Result<BorrowedValue<'a>> {
+    #[cfg(feature = "hash")]
+    if let Some(expected) = meta.and_then(|m| m.checksum.as_deref()) {
+        check_checksum(expected, &json.to_json())?;
+    }
+    let mut diagnostics = Diagnostics::new(options, &[], warnings, None);
+    let ctx = values_ctx(meta.and_then(|m| m.values.as_ref()));
+    restore_borrowed(&mut json, ctx, 0, options, &mut diagnostics)?;
+    Ok(json)
+}
+
+/// Restore `value`, `depth` levels below the root, in place.
+///
+/// Borrowed values are only ever read by `serde_json`, whose recursion limit
+/// bounds their depth, so they are walked recursively.
+fn restore_borrowed(
+    value: &mut BorrowedValue<'_>,
+    ctx: Ctx<'_>,
+    depth: usize,
+    options: &ParseOptions,
+    diagnostics: &mut Diagnostics,
+) -> Result<()> {
+    let annotations = match ctx {
+        Ctx::Plain => Annotations::None,
+        Ctx::Children(annotations) => annotations,
+        Ctx::Typed(_) => {
+            let restored = deserialize_subtree(&value.to_json(), ctx, depth, options, diagnostics)?;
+            *value = BorrowedValue::Extended(restored);
+            return Ok(());
+        }
+    };
+    match value {
+        BorrowedValue::Array(items) => {
+            check_depth(depth, options.max_depth)?;
+            for (i, item) in items.iter_mut().enumerate() {
+                let key = || i.to_string();
+                restore_borrowed_child(item, &annotations, key, depth, options, diagnostics)?;
+            }
+        }
+        BorrowedValue::Object(map) => {
+            check_depth(depth, options.max_depth)?;
+            for (key, field) in map.iter_mut() {
+                let key = || crate::path::escape_key(key);
+                restore_borrowed_child(field, &annotations, key, depth, options, diagnostics)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Restore `value`, a child of a container `depth` levels below the root,
+/// under the path key `key` returns.
+///
+/// Keys are only built, for lookups and for the paths of warnings, when
+/// there are annotations below.
+fn restore_borrowed_child(
+    value: &mut BorrowedValue<'_>,
+    annotations: &Annotations<'_>,
+    key: impl FnOnce() -> String,
+    depth: usize,
+    options: &ParseOptions,
+    diagnostics: &mut Diagnostics,
+) -> Result<()> {
+    if let Annotations::None = annotations {
+        return restore_borrowed(value, Ctx::Plain, depth + 1, options, diagnostics);
+    }
+    let key = key();
+    let ctx = annotations.child(&key);
+    diagnostics.path.push(key);
+    restore_borrowed(value, ctx, depth + 1, options, diagnostics)?;
+    diagnostics.path.pop();
+    Ok(())
+}
+
+/// Restore `json`, the `json` part of an envelope as received, under the
+/// annotations of `meta`, leaving subtrees without annotations as they are
+/// and appending any non-fatal issues to `warnings`.
+///
+/// Values with an annotation of their own are restored by
+/// [`deserialize_tree`].
+pub(crate) fn deserialize_passthrough<'a>(
+    json: &'a RawValue,
+    meta: Option<&Meta>,
+    options: &ParseOptions,
+    warnings: &mut Vec<Warning>,
+) -> Result<PassthroughValue<'a>> {
+    #[cfg(feature = "hash")]
+    if let Some(expected) = meta.and_then(|m| m.checksum.as_deref()) {
+        check_checksum(expected, &serde_json::from_str(json.get())?)?;
+    }
+    let mut diagnostics = Diagnostics::new(options, &[], warnings, None);
+    let ctx = values_ctx(meta.and_then(|m| m.values.as_ref()));
+    restore_passthrough(json, ctx, 0, options, &mut diagnostics)
+}
+
+/// Restore `raw`, `depth` levels below the root.
+///
+/// Only containers with annotations below them are descended into, and
+/// those were read by `serde_json`, whose recursion limit bounds their
+/// depth, so they are walked recursively.
+fn restore_passthrough<'a>(
+    raw: &'a RawValue,
+    ctx: Ctx<'_>,
+    depth: usize,
+    options: &ParseOptions,
+    diagnostics: &mut Diagnostics,
+) -> Result<PassthroughValue<'a>> {
+    let annotations = match ctx {
+        Ctx::Plain => return Ok(PassthroughValue::Raw(raw)),
+        Ctx::Children(annotations) => annotations,
+        Ctx::Typed(_) => {
+            let restored = deserialize_subtree(
+                &serde_json::from_str(raw.get())?,
+                ctx,
+                depth,
+                options,
+                diagnostics,
+            )?;
+            return Ok(PassthroughValue::Extended(restored));
+        }
+    };
+
+    let container = RawContainer::split(raw)?;
+    if !matches!(container, RawContainer::Scalar) {
+        check_depth(depth, options.max_depth)?;
+    }
+    Ok(match container {
+        RawContainer::Scalar => PassthroughValue::Raw(raw),
+        RawContainer::Array(items) => PassthroughValue::Array(
+            items
+                .into_iter()
+                .enumerate()
+                .map(|(i, item)| {
+                    let key = i.to_string();
+                    restore_passthrough_child(item, &annotations, key, depth, options, diagnostics)
+                })
+                .collect::<Result<_>>()?,
+        ),
+        RawContainer::Object(fields) => PassthroughValue::Object(
+            fields
+                .into_iter()
+                .map(|(key, field)| {
+                    let path_key = crate::path::escape_key(&key);
+                    let value = restore_passthrough_child(
+                        field,
+                        &annotations,
+                        path_key,
+                        depth,
+                        options,
+                        diagnostics,
+                    )?;
+                    Ok((key, value))
+                })
+                .collect::<Result<_>>()?,
+        ),
+    })
+}
 
+/// Restore `raw`, a child of a container `depth` levels below the root,
+/// under the path key `key`.
+fn restore_passthrough_child<'a>(
+    raw: &'a RawValue,
+    annotations: &Annotations<'_>,
+    key: String,
+    depth: usize,
+    options: &ParseOptions,
+    diagnostics: &mut Diagnostics,
+) -> Result<PassthroughValue<'a>> {
+    let ctx = annotations.child(&key);
+    diagnostics.path.push(key);
+    let value = restore_passthrough(raw, ctx, depth + 1, options, diagnostics)?;
+    diagnostics.path.pop();
+    Ok(value)
+}
+
+fn annotation_values(superjson: &SuperJson) -> Option<&AnnotationValues> {
+    superjson.meta.as_ref().and_then(|m| m.values.as_ref())
+}
+
+/// How the root of `superjson.json` should be interpreted.
+fn root_ctx(superjson: &SuperJson) -> Ctx<'_> {
+    values_ctx(annotation_values(superjson))
+}
+
+/// How the root of a `json` part annotated by `values` should be interpreted.
+fn values_ctx(values: Option<&AnnotationValues>) -> Ctx<'_> {
     match values {
-        Some(AnnotationValues::Root(ann)) => deserialize_annotated(&superjson.json, ann),
+        Some(AnnotationValues::Root(ann)) => Ctx::Typed(ann),
         Some(AnnotationValues::Children(children)) => {
-            deserialize_with_children(&superjson.json, children)
+            Ctx::Children(Annotations::from_map(children))
         }
-        None => deserialize_plain(&superjson.json),
+        None => Ctx::Plain,
     }
 }
 
-/// Deserialize a JSON value that has no annotation at all.
-fn deserialize_plain(json: &serde_json::Value) -> Result<Value> {
-    match json {
-        serde_json::Value::Null => Ok(Value::Null),
-        serde_json::Value::Bool(b) => Ok(Value::Bool(*b)),
-        serde_json::Value::Number(n) => Ok(Value::Number(n.as_f64().ok_or_else(|| {
-            Error::TypeMismatch {
-                path: String::new(),
-                expected: "f64-compatible number".to_string(),
-                actual: format!("{n}"),
+/// Tracks the path of the value being restored, so warnings can say where
+/// they were found and hooks can select it, and optionally tallies restored
+/// values.
+struct Diagnostics<'w> {
+    /// Escaped path keys from the root to the current value.
+    path: Vec<String>,
+    hooks: &'w [Hook],
+    warnings: &'w mut Vec<Warning>,
+    stats: Option<&'w mut ParseStats>,
+    /// How bytes found along the way are encoded.
+    bytes_encoding: BytesEncoding,
+    /// The custom type name bytes are found under.
+    bytes_name: &'w str,
+    /// Extra properties of Errors to keep.
+    error_props: &'w [String],
+    /// Restore unrecognised leaf annotations as [`Value::Unknown`]s.
+    keep_unknown_types: bool,
+    /// Keep the offsets of Dates rather than converting them to UTC.
+    #[cfg(feature = "chrono")]
+    preserve_date_offsets: bool,
+    /// What to do with Dates that hold no valid date.
+    #[cfg(feature = "chrono")]
+    invalid_dates: InvalidDates,
+    /// Buffers restored values are built from.
+    pool: ValuePool,
+}
+
+impl<'w> Diagnostics<'w> {
+    /// Diagnostics for restoring a value under `options`, starting at the
+    /// root with an empty pool.
+    fn new(
+        options: &'w ParseOptions,
+        hooks: &'w [Hook],
+        warnings: &'w mut Vec<Warning>,
+        stats: Option<&'w mut ParseStats>,
+    ) -> Self {
+        Diagnostics {
+            path: Vec::new(),
+            hooks,
+            warnings,
+            stats,
+            bytes_encoding: options.bytes_encoding,
+            bytes_name: options.bytes_name.as_deref().unwrap_or(BYTES),
+            error_props: &options.error_props,
+            keep_unknown_types: options.keep_unknown_types,
+            #[cfg(feature = "chrono")]
+            preserve_date_offsets: options.preserve_date_offsets,
+            #[cfg(feature = "chrono")]
+            invalid_dates: options.invalid_dates,
+            pool: ValuePool::new(),
+        }
+    }
+
+    fn path(&self) -> String {
+        self.path.join(".")
+    }
+
+    fn warn(&mut self, warning: impl FnOnce(String) -> Warning) {
+        let path = self.path();
+        self.warnings.push(warning(path));
+    }
+
+    /// Apply any hooks matching the current path to a restored value.
+    fn apply_hooks(&self, value: Value) -> Value {
+        if self.hooks.is_empty() {
+            return value;
+        }
+        apply_hooks(self.hooks, &self.path(), &value).unwrap_or(value)
+    }
+}
+
+/// How a JSON value should be interpreted.
+enum Ctx<'a> {
+    /// No annotation applies to this value or its descendants.
+    Plain,
+    /// The value itself is an extended type.
+    Typed(&'a TypeAnnotation),
+    /// The value is a plain container with annotated descendants.
+    Children(Annotations<'a>),
+}
+
+/// The annotations applying to the children of a container, keyed by path
+/// relative to the container.
+enum Annotations<'a> {
+    None,
+    /// The node for this container in a trie of an annotation map's paths.
+    Trie(Rc<PathTrie<'a>>),
+}
+
+impl<'a> Annotations<'a> {
+    fn from_node(annotation: &'a TypeAnnotation) -> Self {
+        match annotation.children() {
+            Some(children) => Self::from_map(children),
+            None => Annotations::None,
+        }
+    }
+
+    fn from_map(children: &'a IndexMap<String, TypeAnnotation>) -> Self {
+        Annotations::Trie(Rc::new(PathTrie::new(children)))
+    }
+
+    /// Determine how the child at `key`, an escaped path, should be
+    /// interpreted. The key of a map entry's key or value, such as `0.1`,
+    /// has two segments.
+    ///
+    /// A child at key "foo" might have:
+    /// - A direct annotation: `children["foo"] = Leaf("Date")`
+    /// - Sub-children annotations: `children["foo.bar"] = Leaf("Date")`
+    /// - No annotation: deserialize as plain JSON
+    fn child(&self, key: &str) -> Ctx<'a> {
+        let Annotations::Trie(root) = self else {
+            return Ctx::Plain;
+        };
+        let mut node = root;
+        for segment in escaped_segments(key) {
+            match node.children.get(segment) {
+                Some(child) => node = child,
+                None => return Ctx::Plain,
+            }
+        }
+        match node.annotation {
+            Some(annotation) => Ctx::Typed(annotation),
+            None => Ctx::Children(Annotations::Trie(Rc::clone(node))),
+        }
+    }
+}
+
+/// The paths of an annotation map, split into their escaped segments, so
+/// that a container finds its children's annotations without scanning the
+/// paths of its siblings'.
+///
+/// Nodes are shared, so a child takes its subtree without copying it.
+#[derive(Default)]
+struct PathTrie<'a> {
+    /// The annotation of the path ending at this node, if any.
+    annotation: Option<&'a TypeAnnotation>,
+    children: HashMap<&'a str, Rc<PathTrie<'a>>>,
+}
+
+impl<'a> PathTrie<'a> {
+    fn new(map: &'a IndexMap<String, TypeAnnotation>) -> Self {
+        let mut root = PathTrie::default();
+        for (path, annotation) in map {
+            let mut node = &mut root;
+            for segment in escaped_segments(path) {
+                node = Rc::get_mut(node.children.entry(segment).or_default())
+                    .expect("nodes are not shared while the trie is built");
+            }
+            node.annotation = Some(annotation);
+        }
+        root
+    }
+}
+
+impl Drop for PathTrie<'_> {
+    /// Free a long path's nodes without recursion.
+    fn drop(&mut self) {
+        let mut stack: Vec<_> = self.children.drain().map(|(_, node)| node).collect();
+        while let Some(node) = stack.pop() {
+            if let Ok(mut node) = Rc::try_unwrap(node) {
+                stack.extend(node.children.drain().map(|(_, node)| node));
+            }
+        }
+    }
+}
+
+/// Split `path` at its unescaped dots, leaving each segment escaped as
+/// [`escape_key`](crate::path::escape_key) escapes keys.
+fn escaped_segments(path: &str) -> impl Iterator<Item = &str> {
+    let mut rest = Some(path);
+    std::iter::from_fn(move || {
+        let current = rest?;
+        let mut escaped = false;
+        for (i, byte) in current.bytes().enumerate() {
+            match byte {
+                _ if escaped => escaped = false,
+                b'\\' => escaped = true,
+                b'.' => {
+                    rest = Some(&current[i + 1..]);
+                    return Some(&current[..i]);
+                }
+                _ => {}
+            }
+        }
+        rest = None;
+        Some(current)
+    })
+}
+
+/// The outcome of starting to deserialize a single JSON value.
+enum Step<'a> {
+    /// The value was restored without visiting any children.
+    Done(Value),
+    /// The value is a container whose children must be restored first.
+    Descend(Frame<'a>),
+}
+
+/// The container a frame is restoring.
+enum Kind<'a> {
+    Array,
+    Object(&'a serde_json::Map<String, serde_json::Value>),
+    Class {
+        name: String,
+        fields: &'a serde_json::Map<String, serde_json::Value>,
+    },
+    Set,
+    Map,
+    Error {
+        name: String,
+        message: String,
+        stack: Option<String>,
+        has_cause: bool,
+        /// The extra properties, after the cause among the children.
+        extra: Vec<String>,
+    },
+    /// The payload of a custom type, itself restored as `payload`.
+    Custom {
+        name: String,
+        payload: Box<Kind<'a>>,
+    },
+}
+
+/// A container whose children are being deserialized.
+struct Frame<'a> {
+    kind: Kind<'a>,
+    /// Children in input order, with the path key their annotations use.
+    children: std::vec::IntoIter<(String, &'a serde_json::Value)>,
+    annotations: Annotations<'a>,
+    values: Vec<Value>,
+}
+
+impl<'a> Frame<'a> {
+    fn new(
+        kind: Kind<'a>,
+        children: Vec<(String, &'a serde_json::Value)>,
+        annotations: Annotations<'a>,
+        pool: &mut ValuePool,
+    ) -> Self {
+        Frame {
+            kind,
+            values: pool.vec(children.len()),
+            children: children.into_iter(),
+            annotations,
+        }
+    }
+
+    /// Assemble the container once all of its children have been restored.
+    fn finish(self, pool: &mut ValuePool) -> Value {
+        let values = self.values;
+        match self.kind {
+            Kind::Custom { name, payload } => Value::Custom {
+                name,
+                value: Box::new(
+                    Frame {
+                        kind: *payload,
+                        children: Vec::new().into_iter(),
+                        annotations: Annotations::None,
+                        values,
+                    }
+                    .finish(pool),
+                ),
+            },
+            Kind::Array => Value::Array(values),
+            Kind::Object(map) => Value::Object(pooled_map(map, values, pool)),
+            Kind::Class { name, fields } => Value::Class {
+                name,
+                fields: pooled_map(fields, values, pool),
+            },
+            Kind::Set => Value::Set(values),
+            Kind::Map => {
+                let mut entries = Vec::with_capacity(values.len() / 2);
+                let mut iter = values.into_iter();
+                while let (Some(k), Some(v)) = (iter.next(), iter.next()) {
+                    entries.push((k, v));
+                }
+                Value::Map(entries)
+            }
+            Kind::Error {
+                name,
+                message,
+                stack,
+                has_cause,
+                extra,
+            } => {
+                let mut values = values.into_iter();
+                let cause = if has_cause { values.next() } else { None };
+                Value::Error {
+                    name,
+                    message,
+                    stack,
+                    cause: cause.map(Box::new),
+                    extra: extra.into_iter().zip(values).collect(),
+                }
+            }
+        }
+    }
+}
+
+/// Restore `json`, `depth` levels below the root of the value being
+/// restored, reporting a value nested too deeply against the limit in
+/// `options` rather than against what is left of it.
+fn deserialize_subtree(
+    json: &serde_json::Value,
+    ctx: Ctx<'_>,
+    depth: usize,
+    options: &ParseOptions,
+    diagnostics: &mut Diagnostics,
+) -> Result<Value> {
+    deserialize_tree(
+        json,
+        ctx,
+        options.max_depth.saturating_sub(depth),
+        diagnostics,
+    )
+    .map_err(|e| match e {
+        Error::DepthLimitExceeded(_) => Error::DepthLimitExceeded(options.max_depth),
+        e => e,
+    })
+}
+
+/// Fail with [`Error::DepthLimitExceeded`] if a container `depth` levels
+/// below the root would be nested deeper than `max_depth` allows.
+fn check_depth(depth: usize, max_depth: usize) -> Result<()> {
+    if depth >= max_depth {
+        return Err(Error::DepthLimitExceeded(max_depth));
+    }
+    Ok(())
+}
+
+/// Pair the keys of `json` with their restored `values` in a pooled map,
+/// returning the emptied `values` to the pool.
+fn pooled_map(
+    json: &serde_json::Map<String, serde_json::Value>,
+    mut values: Vec<Value>,
+    pool: &mut ValuePool,
+) -> Map<String, Value> {
+    let mut map = pool.map(values.len());
+    for (key, value) in json.keys().zip(values.drain(..)) {
+        map.insert(pool.string(key), value);
+    }
+    pool.put_vec(values);
+    map
+}
+
+/// Deserialize a JSON tree using an explicit work stack, so that nesting depth
+/// is bounded by `max_depth` rather than by the thread's call stack.
+fn deserialize_tree(
+    json: &serde_json::Value,
+    ctx: Ctx<'_>,
+    max_depth: usize,
+    diagnostics: &mut Diagnostics,
+) -> Result<Value> {
+    let mut stack: Vec<Frame> = Vec::new();
+    let mut step = begin(json, ctx, diagnostics)?;
+
+    loop {
+        match step {
+            Step::Descend(frame) => {
+                check_depth(stack.len(), max_depth)?;
+                stack.push(frame);
+                if let Some(stats) = diagnostics.stats.as_deref_mut() {
+                    stats.max_depth = stats.max_depth.max(stack.len());
+                }
+            }
+            Step::Done(value) => {
+                let value = diagnostics.apply_hooks(value);
+                if let Some(stats) = diagnostics.stats.as_deref_mut() {
+                    stats.record(&value);
+                }
+                match stack.last_mut() {
+                    None => return Ok(value),
+                    Some(parent) => parent.values.push(value),
+                }
+                diagnostics.path.pop();
+            }
+        }
+
+        let frame = stack.last_mut().expect("stack is non-empty after a push");
+        step = match frame.children.next() {
+            Some((key, child)) => {
+                let ctx = frame.annotations.child(&key);
+                diagnostics.path.push(key);
+                begin(child, ctx, diagnostics)?
             }
-        })?)),
-        serde_json::Value::String(s) => Ok(Value::String(s.clone())),
-        serde_json::Value::Array(arr) => {
-            let values: Result<Vec<_>> = arr.iter().map(deserialize_plain).collect();
-            Ok(Value::Array(values?))
+            None => {
+                let frame = stack.pop().expect("stack is non-empty");
+                Step::Done(frame.finish(&mut diagnostics.pool))
+            }
+        };
+    }
+}
+
+/// A container whose children are being restored by [`deserialize_plain`].
+enum PlainFrame<'a> {
+    Array {
+        items: std::slice::Iter<'a, serde_json::Value>,
+        values: Vec<Value>,
+    },
+    Object {
+        fields: serde_json::map::Iter<'a>,
+        /// The key of the field being restored.
+        key: Option<&'a String>,
+        map: Map<String, Value>,
+    },
+}
+
+impl<'a> PlainFrame<'a> {
+    fn next_child(&mut self) -> Option<&'a serde_json::Value> {
+        match self {
+            PlainFrame::Array { items, .. } => items.next(),
+            PlainFrame::Object { fields, key, .. } => fields.next().map(|(k, v)| {
+                *key = Some(k);
+                v
+            }),
         }
-        serde_json::Value::Object(map) => {
-            let mut obj = IndexMap::with_capacity(map.len());
-            for (key, val) in map {
-                obj.insert(key.clone(), deserialize_plain(val)?);
+    }
+
+    fn push(&mut self, value: Value, pool: &mut ValuePool) {
+        match self {
+            PlainFrame::Array { values, .. } => values.push(value),
+            PlainFrame::Object { key, map, .. } => {
+                let key = key.take().expect("a child was started");
+                map.insert(pool.string(key), value);
             }
-            Ok(Value::Object(obj))
+        }
+    }
+
+    fn finish(self) -> Value {
+        match self {
+            PlainFrame::Array { values, .. } => Value::Array(values),
+            PlainFrame::Object { map, .. } => Value::Object(map),
         }
     }
 }
 
-/// Deserialize a JSON value that has a direct type annotation.
-fn deserialize_annotated(json: &serde_json::Value, annotation: &TypeAnnotation) -> Result<Value> {
+/// Restore JSON that carries no annotations, skipping the annotation and
+/// path bookkeeping of [`deserialize_tree`].
+fn deserialize_plain(
+    json: &serde_json::Value,
+    max_depth: usize,
+    pool: &mut ValuePool,
+) -> Result<Value> {
+    let mut stack: Vec<PlainFrame> = Vec::new();
+    let mut next = Some(json);
+
+    loop {
+        let frame = match next.take() {
+            None => None,
+            Some(serde_json::Value::Array(items)) => Some(PlainFrame::Array {
+                items: items.iter(),
+                values: pool.vec(items.len()),
+            }),
+            Some(serde_json::Value::Object(map)) => Some(PlainFrame::Object {
+                fields: map.iter(),
+                key: None,
+                map: pool.map(map.len()),
+            }),
+            Some(scalar) => {
+                let value = match scalar {
+                    serde_json::Value::Bool(b) => Value::Bool(*b),
+                    serde_json::Value::Number(n) => json_number(n),
+                    serde_json::Value::String(s) => Value::String(pool.string(s)),
+                    _ => Value::Null,
+                };
+                match stack.last_mut() {
+                    None => return Ok(value),
+                    Some(parent) => parent.push(value, pool),
+                }
+                None
+            }
+        };
+        if let Some(frame) = frame {
+            check_depth(stack.len(), max_depth)?;
+            stack.push(frame);
+        }
+
+        // Finish containers until one has a child left to start.
+        while next.is_none() {
+            let parent = stack.last_mut().expect("a container is open");
+            match parent.next_child() {
+                Some(child) => next = Some(child),
+                None => {
+                    let value = stack.pop().expect("stack is non-empty").finish();
+                    match stack.last_mut() {
+                        None => return Ok(value),
+                        Some(parent) => parent.push(value, pool),
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Restore a scalar directly, or open a frame for a container.
+fn begin<'a>(
+    json: &'a serde_json::Value,
+    ctx: Ctx<'a>,
+    diagnostics: &mut Diagnostics,
+) -> Result<Step<'a>> {
+    match ctx {
+        Ctx::Typed(ann) => begin_annotated(json, ann, diagnostics),
+        Ctx::Plain => begin_plain(json, Annotations::None, &mut diagnostics.pool),
+        Ctx::Children(annotations) => begin_plain(json, annotations, &mut diagnostics.pool),
+    }
+}
+
+/// Begin a JSON value that has no annotation of its own.
+fn begin_plain<'a>(
+    json: &'a serde_json::Value,
+    annotations: Annotations<'a>,
+    pool: &mut ValuePool,
+) -> Result<Step<'a>> {
+    match json {
+        serde_json::Value::Null => Ok(Step::Done(Value::Null)),
+        serde_json::Value::Bool(b) => Ok(Step::Done(Value::Bool(*b))),
+        serde_json::Value::Number(n) => Ok(Step::Done(json_number(n))),
+        serde_json::Value::String(s) => Ok(Step::Done(Value::String(pool.string(s)))),
+        serde_json::Value::Array(arr) => Ok(Step::Descend(Frame::new(
+            Kind::Array,
+            indexed(arr),
+            annotations,
+            pool,
+        ))),
+        serde_json::Value::Object(map) => Ok(Step::Descend(Frame::new(
+            Kind::Object(map),
+            map.iter()
+                .map(|(key, val)| (crate::path::escape_key(key), val))
+                .collect(),
+            annotations,
+            pool,
+        ))),
+    }
+}
+
+/// Begin a JSON value that has a direct type annotation.
+fn begin_annotated<'a>(
+    json: &'a serde_json::Value,
+    annotation: &'a TypeAnnotation,
+    diagnostics: &mut Diagnostics,
+) -> Result<Step<'a>> {
+    if let TypeAnnotation::Custom(name) = annotation
+        && name == diagnostics.bytes_name
+    {
+        let encoding = diagnostics.bytes_encoding;
+        if let Some(bytes) = json.as_str().and_then(|s| encoding.decode(s)) {
+            return Ok(Step::Done(Value::Bytes(bytes)));
+        }
+        diagnostics.warn(|path| Warning::InvalidBytes {
+            path,
+            encoding: encoding.name().to_string(),
+        });
+    }
+
+    if let TypeAnnotation::Custom(name) = annotation {
+        // The payload is plain JSON, restored as such and then wrapped.
+        let name = name.clone();
+        return Ok(
+            match begin_plain(json, Annotations::None, &mut diagnostics.pool)? {
+                Step::Done(value) => Step::Done(Value::Custom {
+                    name,
+                    value: Box::new(value),
+                }),
+                Step::Descend(mut frame) => {
+                    frame.kind = Kind::Custom {
+                        name,
+                        payload: Box::new(frame.kind),
+                    };
+                    Step::Descend(frame)
+                }
+            },
+        );
+    }
+
+    if let TypeAnnotation::Symbol(id) = annotation {
+        // Symbols are identified by their annotation alone.
+        return Ok(Step::Done(Value::Symbol(id.clone())));
+    }
+
+    if let TypeAnnotation::Class(name, _) = annotation {
+        let fields = json.as_object().ok_or_else(|| Error::TypeMismatch {
+            path: String::new(),
+            expected: format!("object for class '{name}'"),
+            actual: format!("{json}"),
+        })?;
+        return Ok(Step::Descend(Frame::new(
+            Kind::Class {
+                name: name.clone(),
+                fields,
+            },
+            fields
+                .iter()
+                .map(|(key, val)| (crate::path::escape_key(key), val))
+                .collect(),
+            Annotations::from_node(annotation),
+            &mut diagnostics.pool,
+        )));
+    }
+
+    if let TypeAnnotation::Compound(kind, name) = annotation {
+        return Err(Error::InvalidTypeAnnotation(format!(
+            "unknown type [\"{kind}\", \"{name}\"]"
+        )));
+    }
+
     let type_name = annotation.type_name();
-    let inner_children = annotation.children();
 
     match type_name {
-        "undefined" => Ok(Value::Undefined),
+        "undefined" => Ok(Step::Done(Value::Undefined)),
 
-        "Date" => {
-            let s = expect_str(json, type_name)?;
-            let dt = DateTime::parse_from_rfc3339(s)
-                .map(|dt| dt.with_timezone(&chrono::Utc))
-                .map_err(|e| Error::InvalidDate(format!("{s}: {e}")))?;
-            Ok(Value::Date(dt))
-        }
+        #[cfg(feature = "chrono")]
+        "Date" => restore_date(json, diagnostics).map(Step::Done),
+
+        #[cfg(not(feature = "chrono"))]
+        "Date" => Err(Error::UnsupportedType {
+            name: type_name.to_string(),
+            feature: "chrono",
+        }),
 
+        #[cfg(feature = "bigint")]
         "bigint" => {
             let s = expect_str(json, type_name)?;
             let n: BigInt = s
                 .parse()
                 .map_err(|e| Error::InvalidBigInt(format!("{s}: {e}")))?;
-            Ok(Value::BigInt(n))
+            Ok(Step::Done(Value::BigInt(n)))
         }
 
+        #[cfg(not(feature = "bigint"))]
+        "bigint" => Err(Error::UnsupportedType {
+            name: type_name.to_string(),
+            feature: "bigint",
+        }),
+
         "set" => {
             let arr = expect_array(json, type_name)?;
-            let empty = IndexMap::new();
-            let children = inner_children.unwrap_or(&empty);
-            let mut items = Vec::with_capacity(arr.len());
-            for (i, item) in arr.iter().enumerate() {
-                let key = i.to_string();
-                items.push(deserialize_child(item, &key, children)?);
-            }
-            Ok(Value::Set(items))
+            Ok(Step::Descend(Frame::new(
+                Kind::Set,
+                indexed(arr),
+                Annotations::from_node(annotation),
+                &mut diagnostics.pool,
+            )))
         }
 
         "map" => {
             let arr = expect_array(json, type_name)?;
-            let empty = IndexMap::new();
-            let children = inner_children.unwrap_or(&empty);
-            let mut entries = Vec::with_capacity(arr.len());
+            let mut children = Vec::with_capacity(arr.len() * 2);
             for (i, entry) in arr.iter().enumerate() {
                 let pair = entry.as_array().ok_or_else(|| Error::TypeMismatch {
                     path: format!("{i}"),
@@ -103,22 +1427,24 @@ fn deserialize_annotated(json: &serde_json::Value, annotation: &TypeAnnotation)
                     });
                 }
 
-                let key_path = format!("{i}.0");
-                let val_path = format!("{i}.1");
-                let key = deserialize_child(&pair[0], &key_path, children)?;
-                let val = deserialize_child(&pair[1], &val_path, children)?;
-                entries.push((key, val));
+                children.push((format!("{i}.0"), &pair[0]));
+                children.push((format!("{i}.1"), &pair[1]));
             }
-            Ok(Value::Map(entries))
+            Ok(Step::Descend(Frame::new(
+                Kind::Map,
+                children,
+                Annotations::from_node(annotation),
+                &mut diagnostics.pool,
+            )))
         }
 
         "number" => {
             let s = expect_str(json, type_name)?;
             match s {
-                "NaN" => Ok(Value::NaN),
-                "Infinity" => Ok(Value::PosInfinity),
-                "-Infinity" => Ok(Value::NegInfinity),
-                "-0" => Ok(Value::NegZero),
+                "NaN" => Ok(Step::Done(Value::NaN)),
+                "Infinity" => Ok(Step::Done(Value::PosInfinity)),
+                "-Infinity" => Ok(Step::Done(Value::NegInfinity)),
+                "-0" => Ok(Step::Done(Value::NegZero)),
                 _ => Err(Error::TypeMismatch {
                     path: String::new(),
                     expected: "NaN, Infinity, -Infinity, or -0".to_string(),
@@ -129,12 +1455,18 @@ fn deserialize_annotated(json: &serde_json::Value, annotation: &TypeAnnotation)
 
         "regexp" => {
             let s = expect_str(json, type_name)?;
-            parse_regexp(s)
+            parse_regexp(s).map(Step::Done)
         }
 
         "URL" => {
             let s = expect_str(json, type_name)?;
-            Ok(Value::Url(s.to_string()))
+            #[cfg(feature = "url")]
+            let url = s
+                .parse()
+                .map_err(|e| Error::InvalidUrl(format!("{s}: {e}")))?;
+            #[cfg(not(feature = "url"))]
+            let url = s.to_string();
+            Ok(Step::Done(Value::Url(url)))
         }
 
         "Error" => {
@@ -164,20 +1496,45 @@ fn deserialize_annotated(json: &serde_json::Value, annotation: &TypeAnnotation)
                 })?
                 .to_string();
 
-            let empty = IndexMap::new();
-            let children = inner_children.unwrap_or(&empty);
+            let stack = obj
+                .get("stack")
+                .and_then(|v| v.as_str())
+                .filter(|_| keeps_error_stack(diagnostics.error_props))
+                .map(str::to_string);
 
-            let cause = if let Some(cause_json) = obj.get("cause") {
-                Some(Box::new(deserialize_child(cause_json, "cause", children)?))
-            } else {
-                None
-            };
+            let mut children: Vec<_> = obj
+                .get("cause")
+                .map(|cause| ("cause".to_string(), cause))
+                .into_iter()
+                .collect();
+            let has_cause = !children.is_empty();
+            let mut extra = Vec::new();
+            for (key, value) in obj {
+                if is_error_prop(diagnostics.error_props, key) {
+                    children.push((crate::path::escape_key(key), value));
+                    extra.push(key.clone());
+                }
+            }
 
-            Ok(Value::Error {
-                name,
-                message,
-                cause,
-            })
+            Ok(Step::Descend(Frame::new(
+                Kind::Error {
+                    name,
+                    message,
+                    stack,
+                    has_cause,
+                    extra,
+                },
+                children,
+                Annotations::from_node(annotation),
+                &mut diagnostics.pool,
+            )))
+        }
+
+        _ if diagnostics.keep_unknown_types && matches!(annotation, TypeAnnotation::Leaf(_)) => {
+            Ok(Step::Done(Value::Unknown {
+                type_name: type_name.to_string(),
+                raw: json.clone(),
+            }))
         }
 
         _ => Err(Error::InvalidTypeAnnotation(format!(
@@ -186,69 +1543,57 @@ fn deserialize_annotated(json: &serde_json::Value, annotation: &TypeAnnotation)
     }
 }
 
-/// Deserialize a JSON value (object or array) whose children have annotations.
-fn deserialize_with_children(
-    json: &serde_json::Value,
-    children: &IndexMap<String, TypeAnnotation>,
-) -> Result<Value> {
-    match json {
-        serde_json::Value::Array(arr) => {
-            let mut values = Vec::with_capacity(arr.len());
-            for (i, item) in arr.iter().enumerate() {
-                let key = i.to_string();
-                values.push(deserialize_child(item, &key, children)?);
+/// Restore the JSON form of a `Date`: an RFC 3339 string or, as
+/// `new Date(ms)` takes it, milliseconds since the epoch. Anything else is
+/// handled as [`ParseOptions::invalid_dates`] says.
+#[cfg(feature = "chrono")]
+fn restore_date(json: &serde_json::Value, diagnostics: &mut Diagnostics) -> Result<Value> {
+    let detail = match json {
+        serde_json::Value::String(s) => {
+            if let Some(dt) = parse_utc_millis(s) {
+                return Ok(Value::Date(dt));
             }
-            Ok(Value::Array(values))
-        }
-        serde_json::Value::Object(map) => {
-            let mut obj = IndexMap::with_capacity(map.len());
-            for (key, val) in map {
-                obj.insert(
-                    key.clone(),
-                    deserialize_child(val, &crate::path::escape_key(key), children)?,
-                );
+            match DateTime::parse_from_rfc3339(s) {
+                Ok(dt) if dt.offset().local_minus_utc() == 0 => {
+                    return Ok(Value::Date(dt.to_utc()));
+                }
+                Ok(dt) if diagnostics.preserve_date_offsets => return Ok(Value::OffsetDate(dt)),
+                Ok(dt) => {
+                    diagnostics.warn(|path| Warning::CoercedDate {
+                        path,
+                        original: s.to_string(),
+                    });
+                    return Ok(Value::Date(dt.to_utc()));
+                }
+                Err(e) => format!("{s}: {e}"),
             }
-            Ok(Value::Object(obj))
         }
-        _ => {
-            // If no annotation matches, deserialize as plain
-            deserialize_plain(json)
+        serde_json::Value::Number(n) => {
+            // `new Date(ms)` drops any fraction of a millisecond.
+            let dt = n
+                .as_f64()
+                .filter(|ms| ms.is_finite())
+                .and_then(|ms| DateTime::from_timestamp_millis(ms.trunc() as i64));
+            match dt {
+                Some(dt) => return Ok(Value::Date(dt)),
+                None => format!("{n}: out of range"),
+            }
         }
+        other => format!("expected a string or milliseconds, got {other}"),
+    };
+    match diagnostics.invalid_dates {
+        InvalidDates::Error => Err(Error::InvalidDate(detail)),
+        InvalidDates::Keep => Ok(Value::InvalidDate),
+        InvalidDates::Epoch => Ok(Value::Date(DateTime::UNIX_EPOCH)),
     }
 }
 
-/// Deserialize a child value by looking up its annotation in the children map.
-///
-/// The children map uses dot-notation paths. A child at key "foo" might have:
-/// - A direct annotation: `children["foo"] = Leaf("Date")`
-/// - Sub-children annotations: `children["foo.bar"] = Leaf("Date")`
-/// - No annotation: deserialize as plain JSON
-fn deserialize_child(
-    json: &serde_json::Value,
-    key: &str,
-    children: &IndexMap<String, TypeAnnotation>,
-) -> Result<Value> {
-    // Check for a direct annotation on this child
-    if let Some(ann) = children.get(key) {
-        return deserialize_annotated(json, ann);
-    }
-
-    // Check for sub-children annotations (paths starting with "key.")
-    let prefix = format!("{key}.");
-    let sub_children: IndexMap<String, TypeAnnotation> = children
-        .iter()
-        .filter_map(|(k, v)| {
-            k.strip_prefix(&prefix)
-                .map(|rest| (rest.to_string(), v.clone()))
-        })
-        .collect();
-
-    if !sub_children.is_empty() {
-        return deserialize_with_children(json, &sub_children);
-    }
-
-    // No annotation - deserialize as plain JSON
-    deserialize_plain(json)
+/// Pair each array item with its index as a path key.
+fn indexed(arr: &[serde_json::Value]) -> Vec<(String, &serde_json::Value)> {
+    arr.iter()
+        .enumerate()
+        .map(|(i, item)| (i.to_string(), item))
+        .collect()
 }
 
 fn expect_str<'a>(json: &'a serde_json::Value, type_name: &str) -> Result<&'a str> {
@@ -289,10 +1634,33 @@ fn parse_regexp(s: &str) -> Result<Value> {
     Ok(Value::RegExp { source, flags })
 }
 
+/// Parse a date in exactly the `YYYY-MM-DDTHH:MM:SS.mmmZ` form that
+/// `Date.prototype.toJSON` and [`serialize`](crate::serialize) write, which
+/// is most dates in practice, without the generality of an RFC 3339 parser.
+///
+/// Returns `None` for any other form, including out-of-range fields, so the
+/// caller can fall back to chrono.
+#[cfg(feature = "chrono")]
+fn parse_utc_millis(s: &str) -> Option<DateTime<chrono::Utc>> {
+    let b: &[u8; 24] = s.as_bytes().try_into().ok()?;
+    if [b[4], b[7], b[10], b[13], b[16], b[19], b[23]] != *b"--T::.Z" {
+        return None;
+    }
+    let num = |range: std::ops::Range<usize>| {
+        b[range].iter().try_fold(0u32, |n, &c| {
+            c.is_ascii_digit().then(|| n * 10 + u32::from(c - b'0'))
+        })
+    };
+    let date = chrono::NaiveDate::from_ymd_opt(num(0..4)? as i32, num(5..7)?, num(8..10)?)?;
+    let datetime =
+        date.and_hms_milli_opt(num(11..13)?, num(14..16)?, num(17..19)?, num(20..23)?)?;
+    Some(datetime.and_utc())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::Meta;
+    #[cfg(feature = "chrono")]
     use chrono::TimeZone;
     use serde_json::json;
 
@@ -302,11 +1670,13 @@ mod tests {
             meta: Some(Meta {
                 values: Some(AnnotationValues::Root(ann)),
                 referential_equalities: None,
-                v: Some(1),
+                v: Some(crate::META_VERSION),
+                checksum: None,
             }),
         }
     }
 
+    #[cfg(feature = "chrono")]
     fn make_superjson_children(
         json: serde_json::Value,
         children: IndexMap<String, TypeAnnotation>,
@@ -316,7 +1686,8 @@ mod tests {
             meta: Some(Meta {
                 values: Some(AnnotationValues::Children(children)),
                 referential_equalities: None,
-                v: Some(1),
+                v: Some(crate::META_VERSION),
+                checksum: None,
             }),
         }
     }
@@ -355,6 +1726,7 @@ mod tests {
         assert_eq!(deserialize(&sj).unwrap(), Value::Undefined);
     }
 
+    #[cfg(feature = "chrono")]
     #[test]
     fn test_deserialize_date() {
         let sj = make_superjson_root(
@@ -365,6 +1737,192 @@ mod tests {
         assert_eq!(deserialize(&sj).unwrap(), expected);
     }
 
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn test_preserve_date_offsets() {
+        let text = r#"{"json":["2024-01-01T09:00:00.000+09:00","2024-01-01T00:00:00.000Z"],"meta":{"values":{"0":["Date"],"1":["Date"]}}}"#;
+        let utc = Value::Date(chrono::Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap());
+        assert_eq!(
+            crate::parse(text).unwrap(),
+            Value::Array(vec![utc.clone(), utc.clone()])
+        );
+
+        let options = ParseOptions {
+            preserve_date_offsets: true,
+            ..ParseOptions::default()
+        };
+        let value = crate::parse_with(text, &options).unwrap();
+        let Value::Array(items) = &value else {
+            panic!("expected an array");
+        };
+        assert!(
+            matches!(&items[0], Value::OffsetDate(dt) if dt.offset().local_minus_utc() == 9 * 3600)
+        );
+        assert_eq!(items[0].as_offset_date(), utc.as_offset_date());
+        assert_eq!(items[1], utc);
+        assert!(crate::stringify(&value).unwrap().starts_with(
+            r#"{"json":["2024-01-01T09:00:00.000+09:00","2024-01-01T00:00:00.000Z"]"#
+        ));
+    }
+
+    #[test]
+    fn test_deserialize_json() {
+        let value = Value::Array(vec![Value::Undefined, Value::NegZero]);
+        let envelope = crate::serialize::serialize_to_json(&value).unwrap();
+        assert_eq!(deserialize_json(envelope).unwrap(), value);
+
+        // Legacy annotations are read as `parse` reads them.
+        let legacy = json!({"json": {"n": "-Infinity"}, "meta": {"values": {"n": "number"}}});
+        assert_eq!(
+            deserialize_json(legacy).unwrap().pointer("n"),
+            Some(&Value::NegInfinity)
+        );
+
+        let newer = json!({"json": 1, "meta": {"v": 2}});
+        let options = ParseOptions {
+            strict_version: true,
+            ..ParseOptions::default()
+        };
+        assert!(matches!(
+            deserialize_json_with(newer, &options),
+            Err(Error::UnsupportedVersion(2))
+        ));
+        assert!(matches!(
+            deserialize_json(json!({"meta": {}})),
+            Err(Error::Json(_))
+        ));
+    }
+
+    #[test]
+    fn test_restore_shared() {
+        let text = r#"{"json":{"a":[0,0],"b":[null,null],"c":[null,null]},
+                       "meta":{"referentialEqualities":{"a":["b.0","b.1"],"b":["c.0","c.1"]}}}"#;
+        let value = crate::parse(text).unwrap();
+        let a = Value::Array(vec![Value::Int(0), Value::Int(0)]);
+        let b = Value::Array(vec![a.clone(), a]);
+        assert_eq!(value.pointer("c"), Some(&Value::Array(vec![b.clone(), b])));
+
+        // 2 copies of 3 values, then 2 of 7.
+        let limited = |max_shared_values| {
+            let options = ParseOptions {
+                max_shared_values,
+                ..ParseOptions::default()
+            };
+            crate::parse_with(text, &options)
+        };
+        assert!(limited(20).is_ok());
+        assert!(matches!(
+            limited(19),
+            Err(Error::InvalidReferentialEquality(_))
+        ));
+
+        for invalid in [
+            r#"{"json":{"a":{}},"meta":{"referentialEqualities":{"x":["a"]}}}"#,
+            r#"{"json":{"a":{}},"meta":{"referentialEqualities":{"a":"b"}}}"#,
+            r#"{"json":{"a":{}},"meta":{"referentialEqualities":"a"}}"#,
+        ] {
+            assert!(
+                matches!(
+                    crate::parse(invalid),
+                    Err(Error::InvalidReferentialEquality(_))
+                ),
+                "{invalid}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_keep_unknown_types() {
+        let text = r#"{"json":{"at":{"epochNs":"0"},"n":1},"meta":{"values":{"at":["Temporal.Instant"]},"v":1}}"#;
+        assert!(matches!(
+            crate::parse(text),
+            Err(Error::InvalidTypeAnnotation(_))
+        ));
+
+        let options = ParseOptions {
+            keep_unknown_types: true,
+            ..ParseOptions::default()
+        };
+        let value = crate::parse_with(text, &options).unwrap();
+        assert_eq!(
+            value.pointer("at"),
+            Some(&Value::Unknown {
+                type_name: "Temporal.Instant".to_string(),
+                raw: json!({"epochNs": "0"}),
+            })
+        );
+        let superjson = crate::serialize::serialize(&value).unwrap();
+        assert_eq!(superjson.json, json!({"at": {"epochNs": "0"}, "n": 1}));
+        assert_eq!(
+            serde_json::to_value(superjson.meta).unwrap(),
+            json!({"values": {"at": ["Temporal.Instant"]}, "v": 1})
+        );
+
+        // Annotations with children are not passed through.
+        let nested = r#"{"json":{"a":[1]},"meta":{"values":{"a":["Tuple",{"0":["undefined"]}]}}}"#;
+        assert!(matches!(
+            crate::parse_with(nested, &options),
+            Err(Error::InvalidTypeAnnotation(_))
+        ));
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn test_invalid_dates() {
+        let text = r#"{"json":["Invalid Date",null,86400000],"meta":{"values":{"0":["Date"],"1":["Date"],"2":["Date"]}}}"#;
+        assert!(matches!(crate::parse(text), Err(Error::InvalidDate(_))));
+
+        let parse = |invalid_dates| {
+            let options = ParseOptions {
+                invalid_dates,
+                ..ParseOptions::default()
+            };
+            crate::parse_with(text, &options).unwrap()
+        };
+        let day = Value::Date(chrono::Utc.with_ymd_and_hms(1970, 1, 2, 0, 0, 0).unwrap());
+        let kept = parse(InvalidDates::Keep);
+        assert_eq!(
+            kept,
+            Value::Array(vec![Value::InvalidDate, Value::InvalidDate, day.clone()])
+        );
+        assert!(
+            crate::stringify(&kept).unwrap().starts_with(
+                r#"{"json":["Invalid Date","Invalid Date","1970-01-02T00:00:00.000Z"]"#
+            )
+        );
+        let epoch = Value::Date(DateTime::UNIX_EPOCH);
+        assert_eq!(
+            parse(InvalidDates::Epoch),
+            Value::Array(vec![epoch.clone(), epoch, day])
+        );
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn test_date_fast_path_matches_chrono() {
+        for s in [
+            "2024-02-29T23:59:59.999Z",
+            "0001-01-01T00:00:00.000Z",
+            "9999-12-31T12:34:56.789Z",
+        ] {
+            let expected = DateTime::parse_from_rfc3339(s).unwrap();
+            assert_eq!(parse_utc_millis(s), Some(expected.to_utc()), "{s}");
+        }
+        // Other forms and out-of-range fields are left to chrono.
+        for s in [
+            "2023-02-29T00:00:00.000Z",
+            "2024-01-01T24:00:00.000Z",
+            "2024-01-01T00:00:00.000+00:00",
+            "2024-01-01T00:00:00Z",
+            "2024-01-01t00:00:00.000z",
+            "+2024-01-01T00:00:00.00Z",
+            "2024-01-01T00:00:00.0a0Z",
+        ] {
+            assert_eq!(parse_utc_millis(s), None, "{s}");
+        }
+    }
+
+    #[cfg(feature = "bigint")]
     #[test]
     fn test_deserialize_bigint() {
         let sj = make_superjson_root(json!("42"), TypeAnnotation::Leaf("bigint".into()));
@@ -452,6 +2010,7 @@ mod tests {
         );
     }
 
+    #[cfg(feature = "chrono")]
     #[test]
     fn test_deserialize_object_with_children() {
         let mut children = IndexMap::new();
@@ -470,6 +2029,7 @@ mod tests {
         assert_eq!(obj.get("name").unwrap(), &Value::String("test".into()));
     }
 
+    #[cfg(feature = "chrono")]
     #[test]
     fn test_deserialize_deeply_nested() {
         let mut children = IndexMap::new();
@@ -491,6 +2051,7 @@ mod tests {
         );
     }
 
+    #[cfg(all(feature = "chrono", feature = "bigint"))]
     #[test]
     fn test_deserialize_array_with_children() {
         let mut children = IndexMap::new();
@@ -507,4 +2068,273 @@ mod tests {
         );
         assert_eq!(arr[2], Value::BigInt(BigInt::from(999)));
     }
+
+    #[test]
+    fn test_escaped_segments() {
+        let segments = |path| escaped_segments(path).collect::<Vec<_>>();
+        assert_eq!(segments(""), [""]);
+        assert_eq!(segments("a.0.b"), ["a", "0", "b"]);
+        assert_eq!(segments(r"a\.b.c\\.d"), [r"a\.b", r"c\\", "d"]);
+        assert_eq!(segments("a."), ["a", ""]);
+    }
+
+    #[test]
+    fn test_annotation_paths_by_segment() {
+        let mut children = IndexMap::new();
+        children.insert(r"a\.b".to_string(), TypeAnnotation::Leaf("number".into()));
+        children.insert("a.b".to_string(), TypeAnnotation::Leaf("undefined".into()));
+        children.insert("ab.c".to_string(), TypeAnnotation::Leaf("number".into()));
+        // Far deeper than the data, which is never looked up.
+        children.insert(
+            vec!["x"; 100_000].join("."),
+            TypeAnnotation::Leaf("number".into()),
+        );
+        let sj = SuperJson {
+            json: json!({"a.b": "NaN", "a": {"b": null}, "ab": {"c": "Infinity", "d": "NaN"}, "x": {}}),
+            meta: Some(Meta {
+                values: Some(AnnotationValues::Children(children)),
+                referential_equalities: None,
+                v: Some(crate::META_VERSION),
+                checksum: None,
+            }),
+        };
+        let value = deserialize(&sj).unwrap();
+        let object = value.as_object().unwrap();
+        assert_eq!(object["a.b"], Value::NaN);
+        assert_eq!(value.pointer("a.b"), Some(&Value::Undefined));
+        assert_eq!(value.pointer("ab.c"), Some(&Value::PosInfinity));
+        assert_eq!(value.pointer("ab.d"), Some(&Value::from("NaN")));
+    }
+
+    #[cfg(not(feature = "chrono"))]
+    #[test]
+    fn test_deserialize_date_without_feature() {
+        let sj = make_superjson_root(
+            json!("1970-01-01T00:00:00.000Z"),
+            TypeAnnotation::Leaf("Date".into()),
+        );
+        let err = deserialize(&sj).unwrap_err();
+        assert!(matches!(
+            err,
+            Error::UnsupportedType {
+                feature: "chrono",
+                ..
+            }
+        ));
+    }
+
+    fn merge(target: &str, update: serde_json::Value, strategy: MergeStrategy) -> Value {
+        let mut value = crate::parse(target).unwrap();
+        let update: SuperJson = serde_json::from_value(update).unwrap();
+        deserialize_into(&update, &mut value, strategy, &ParseOptions::default()).unwrap();
+        value
+    }
+
+    const STATE: &str = r#"{"json":{"a":{"x":1,"y":2},"b":[1]}}"#;
+
+    #[test]
+    fn test_deserialize_into_strategies() {
+        let update = json!({"json": {"a": {"x": 10}, "c": 3}});
+        let parse = |s| crate::parse(s).unwrap();
+        assert_eq!(
+            merge(STATE, update.clone(), MergeStrategy::Deep),
+            parse(r#"{"json":{"a":{"x":10,"y":2},"b":[1],"c":3}}"#)
+        );
+        assert_eq!(
+            merge(STATE, update.clone(), MergeStrategy::Shallow),
+            parse(r#"{"json":{"a":{"x":10},"b":[1],"c":3}}"#)
+        );
+        assert_eq!(
+            merge(STATE, update, MergeStrategy::Replace),
+            parse(r#"{"json":{"a":{"x":10},"c":3}}"#)
+        );
+    }
+
+    #[test]
+    fn test_deserialize_into_restores_annotated_fields() {
+        let update = json!({
+            "json": {"a": {"y": [2, 3]}, "b": "NaN"},
+            "meta": {"values": {"a.y": ["set"], "b": ["number"]}}
+        });
+        let value = merge(STATE, update, MergeStrategy::Deep);
+        let obj = value.as_object().unwrap();
+        let a = obj["a"].as_object().unwrap();
+        assert_eq!(a["x"], Value::from(1));
+        assert_eq!(a["y"], Value::Set(vec![Value::from(2), Value::from(3)]));
+        assert_eq!(obj["b"], Value::NaN);
+    }
+
+    #[test]
+    fn test_deserialize_into_replaces_non_objects() {
+        let update = json!({"json": {"a": 5}});
+        let value = merge(STATE, update, MergeStrategy::Deep);
+        assert_eq!(value.as_object().unwrap()["a"], Value::from(5));
+
+        let update = json!({"json": [1], "meta": {"values": ["set"]}});
+        let value = merge(STATE, update, MergeStrategy::Deep);
+        assert_eq!(value, Value::Set(vec![Value::from(1)]));
+    }
+
+    #[test]
+    fn test_deserialize_into_depth_limit() {
+        let mut value = crate::parse(STATE).unwrap();
+        let update: SuperJson =
+            serde_json::from_value(json!({"json": {"a": {"x": [[1]]}}})).unwrap();
+        let options = ParseOptions {
+            max_depth: 2,
+            ..ParseOptions::default()
+        };
+        assert!(matches!(
+            deserialize_into(&update, &mut value, MergeStrategy::Deep, &options),
+            Err(Error::DepthLimitExceeded(2))
+        ));
+    }
+
+    #[test]
+    fn test_deserialize_custom() {
+        let sj = make_superjson_root(
+            json!({"amount": "1.5", "big": 12345678901234567890u64}),
+            TypeAnnotation::Custom("money".into()),
+        );
+        let mut warnings = Vec::new();
+        let value =
+            deserialize_detailed(&sj, &ParseOptions::default(), &[], &mut warnings, None).unwrap();
+        let Value::Custom { name, value } = value else {
+            panic!("expected a custom value, got {value:?}");
+        };
+        assert_eq!(name, "money");
+        assert_eq!(value.as_object().unwrap()["amount"], Value::from("1.5"));
+        // The payload is restored like any other JSON.
+        assert_eq!(
+            value.as_object().unwrap()["big"],
+            Value::UInt(12_345_678_901_234_567_890)
+        );
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn test_deserialize_class() {
+        let value = crate::parse(
+            r#"{"json":{"p":{"x":1,"tags":["a"]}},"meta":{"values":{"p":[["class","Point"],{"tags":["set"]}]}}}"#,
+        )
+        .unwrap();
+        let Value::Class { name, fields } = value.pointer("p").unwrap() else {
+            panic!("expected a class instance, got {value:?}");
+        };
+        assert_eq!(name, "Point");
+        assert_eq!(fields["x"], Value::from(1));
+        assert_eq!(fields["tags"], Value::Set(vec![Value::from("a")]));
+        assert_eq!(value.pointer("p.tags.0"), Some(&Value::from("a")));
+
+        assert!(matches!(
+            crate::parse(r#"{"json":[1],"meta":{"values":[["class","Point"]]}}"#),
+            Err(Error::TypeMismatch { .. })
+        ));
+        assert!(matches!(
+            crate::parse(r#"{"json":{},"meta":{"values":[["class","Point"],{},{}]}}"#),
+            Err(Error::InvalidTypeAnnotation(_))
+        ));
+    }
+
+    #[test]
+    fn test_deserialize_symbol() {
+        // JS writes the symbol's description, which may be missing.
+        let value = crate::parse(
+            r#"{"json":{"a":"Admin role","b":null},"meta":{"values":{"a":[["symbol","admin"]],"b":[["symbol","guest"]]}}}"#,
+        )
+        .unwrap();
+        assert_eq!(value.pointer("a"), Some(&Value::Symbol("admin".into())));
+        assert_eq!(value.pointer("b"), Some(&Value::Symbol("guest".into())));
+    }
+
+    #[cfg(feature = "url")]
+    #[test]
+    fn test_deserialize_url() {
+        let text = r#"{"json":"HTTPS://Example.COM:443/a/../b?q=1 2","meta":{"values":["URL"]}}"#;
+        let value = crate::parse(text).unwrap();
+        assert_eq!(
+            value,
+            Value::Url("https://example.com/b?q=1%202".parse().unwrap())
+        );
+        assert!(
+            crate::stringify(&value)
+                .unwrap()
+                .starts_with(r#"{"json":"https://example.com/b?q=1%202""#)
+        );
+
+        let text = r#"{"json":"/relative","meta":{"values":["URL"]}}"#;
+        assert!(matches!(crate::parse(text), Err(Error::InvalidUrl(_))));
+    }
+
+    #[test]
+    fn test_deserialize_bytes() {
+        let text = r#"{"json":{"a":"3q0=","b":{"n":1}},"meta":{"values":{"a":[["custom","bytes"]],"b":[["custom","bytes"]]}}}"#;
+        let value = crate::parse(text).unwrap();
+        assert_eq!(value.pointer("a"), Some(&Value::Bytes(vec![0xde, 0xad])));
+        // Another transformer's data under the same name is left alone.
+        assert!(matches!(value.pointer("b"), Some(Value::Custom { .. })));
+
+        let options = ParseOptions {
+            bytes_encoding: BytesEncoding::Hex,
+            ..ParseOptions::default()
+        };
+        let text = r#"{"json":"DEAD00","meta":{"values":[["custom","bytes"]]}}"#;
+        assert_eq!(
+            crate::parse_with(text, &options).unwrap(),
+            Value::Bytes(vec![0xde, 0xad, 0])
+        );
+        // Anything else under the name is kept as it was, with a warning.
+        let custom = |value: Value| Value::Custom {
+            name: BYTES.to_string(),
+            value: Box::new(value),
+        };
+        let parsed = crate::parse_detailed(text, &ParseOptions::default()).unwrap();
+        assert_eq!(parsed.value, custom(Value::from("DEAD00")));
+        assert!(matches!(
+            parsed.warnings.as_slice(),
+            [Warning::InvalidBytes { path, encoding }] if path.is_empty() && encoding == "base64"
+        ));
+        let text = r#"{"json":{"b":[1]},"meta":{"values":{"b":[["custom","bytes"]]},"v":1}}"#;
+        let parsed = crate::parse_detailed(text, &ParseOptions::default()).unwrap();
+        assert_eq!(
+            parsed.value.pointer("b"),
+            Some(&custom(Value::from(vec![Value::from(1)])))
+        );
+        assert!(matches!(
+            parsed.warnings.as_slice(),
+            [Warning::InvalidBytes { path, .. }] if path == "b"
+        ));
+        assert_eq!(crate::stringify(&parsed.value).unwrap(), text);
+
+        // Under another name, as Node apps register `Buffer`.
+        let text = r#"{"json":["aGk=","aGk="],"meta":{"values":{"0":[["custom","buffer"]],"1":[["custom","bytes"]]}}}"#;
+        let options = ParseOptions {
+            bytes_name: Some("buffer".to_string()),
+            ..ParseOptions::default()
+        };
+        let value = crate::parse_with(text, &options).unwrap();
+        assert_eq!(value.pointer("0"), Some(&Value::Bytes(b"hi".to_vec())));
+        assert!(matches!(value.pointer("1"), Some(Value::Custom { .. })));
+    }
+
+    #[test]
+    fn test_plain_fast_path_matches_tree() {
+        let json = json!({"a": [1, {"b.c": 18446744073709551615u64}], "d": null, "e": "x"});
+        let fast = deserialize_plain(&json, DEFAULT_MAX_DEPTH, &mut ValuePool::new()).unwrap();
+
+        let mut warnings = Vec::new();
+        let options = ParseOptions::default();
+        let mut diagnostics = Diagnostics::new(&options, &[], &mut warnings, None);
+        let slow =
+            deserialize_tree(&json, Ctx::Plain, DEFAULT_MAX_DEPTH, &mut diagnostics).unwrap();
+        assert_eq!(fast, slow);
+        assert!(warnings.is_empty());
+        assert_eq!(fast.pointer("a.1.b\\.c"), Some(&Value::UInt(u64::MAX)));
+
+        assert!(deserialize_plain(&json, 3, &mut ValuePool::new()).is_ok());
+        assert!(matches!(
+            deserialize_plain(&json, 2, &mut ValuePool::new()),
+            Err(Error::DepthLimitExceeded(2))
+        ));
+    }
 }
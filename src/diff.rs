@@ -0,0 +1,276 @@
+use std::fmt;
+
+use crate::path::{self, PathSegment};
+use crate::value::{Map, Value};
+
+/// A single difference between two values.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+    /// The path exists only in the new value.
+    Added(Value),
+    /// The path exists only in the old value.
+    Removed(Value),
+    /// The path exists in both values with different contents.
+    Replaced { old: Value, new: Value },
+}
+
+/// A difference located at a path within the compared values.
+///
+/// Paths use the same segments as superjson annotations: object keys, array
+/// and set indices, and `i.0` / `i.1` for the key and value of a map entry.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Difference {
+    pub path: Vec<PathSegment>,
+    pub change: Change,
+}
+
+impl fmt::Display for Difference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.path.is_empty() {
+            write!(f, "<root>: ")?;
+        } else {
+            write!(f, "{}: ", path::join(&self.path))?;
+        }
+        match &self.change {
+            Change::Added(v) => write!(f, "added {v}"),
+            Change::Removed(v) => write!(f, "removed {v}"),
+            Change::Replaced { old, new } => write!(f, "{old} → {new}"),
+        }
+    }
+}
+
+/// Compare two values structurally and list every difference, in document
+/// order of `old`.
+///
+/// Containers of the same kind are compared child by child, so a change deep
+/// inside a large document is reported at its own path rather than as a
+/// replacement of the whole tree.
+///
+/// # Examples
+/// ```
+/// use superjson_rs::Value;
+/// use superjson_rs::diff::diff;
+///
+/// let old = Value::Array(vec![Value::from(1), Value::from(2)]);
+/// let new = Value::Array(vec![Value::from(1), Value::from(3)]);
+/// let changes = diff(&old, &new);
+/// assert_eq!(changes.len(), 1);
+/// assert_eq!(changes[0].to_string(), "1: 2 → 3");
+/// ```
+pub fn diff(old: &Value, new: &Value) -> Vec<Difference> {
+    let mut differences = Vec::new();
+    let mut stack = vec![Work::Compare(Vec::new(), old, new)];
+
+    while let Some(work) = stack.pop() {
+        let (path, old, new) = match work {
+            Work::Report(difference) => {
+                differences.push(*difference);
+                continue;
+            }
+            Work::Compare(path, old, new) => (path, old, new),
+        };
+
+        // Collected in document order, then pushed in reverse onto the stack.
+        let mut pending = Vec::new();
+
+        match (old, new) {
+            (Value::Array(a), Value::Array(b)) | (Value::Set(a), Value::Set(b)) => {
+                for (i, (a_val, b_val)) in a.iter().zip(b).enumerate() {
+                    pending.push(Work::Compare(child_path(&path, i), a_val, b_val));
+                }
+                for (i, a_val) in a.iter().enumerate().skip(b.len()) {
+                    pending.push(report(child_path(&path, i), Change::Removed(a_val.clone())));
+                }
+                for (i, b_val) in b.iter().enumerate().skip(a.len()) {
+                    pending.push(report(child_path(&path, i), Change::Added(b_val.clone())));
+                }
+            }
+            (Value::Object(a), Value::Object(b))
+            | (Value::Class { fields: a, .. }, Value::Class { fields: b, .. })
+                if class_name(old) == class_name(new) =>
+            {
+                compare_fields(&path, a, b, &mut pending);
+            }
+            (Value::Map(a), Value::Map(b)) => {
+                for (i, ((ak, av), (bk, bv))) in a.iter().zip(b).enumerate() {
+                    let entry = child_path(&path, i);
+                    pending.push(Work::Compare(child_path(&entry, 0), ak, bk));
+                    pending.push(Work::Compare(child_path(&entry, 1), av, bv));
+                }
+                let entry = |(k, v): &(Value, Value)| Value::Array(vec![k.clone(), v.clone()]);
+                for (i, e) in a.iter().enumerate().skip(b.len()) {
+                    pending.push(report(child_path(&path, i), Change::Removed(entry(e))));
+                }
+                for (i, e) in b.iter().enumerate().skip(a.len()) {
+                    pending.push(report(child_path(&path, i), Change::Added(entry(e))));
+                }
+            }
+            (
+                Value::Error {
+                    name: a_name,
+                    message: a_message,
+                    stack: a_stack,
+                    cause: a_cause,
+                    extra: a_extra,
+                },
+                Value::Error {
+                    name: b_name,
+                    message: b_message,
+                    stack: b_stack,
+                    cause: b_cause,
+                    extra: b_extra,
+                },
+            ) if a_name == b_name && a_message == b_message && a_stack == b_stack => {
+                let child = child_path(&path, "cause");
+                match (a_cause, b_cause) {
+                    (Some(a), Some(b)) => pending.push(Work::Compare(child, a, b)),
+                    (Some(a), None) => pending.push(report(child, Change::Removed((**a).clone()))),
+                    (None, Some(b)) => pending.push(report(child, Change::Added((**b).clone()))),
+                    (None, None) => {}
+                }
+                compare_fields(&path, a_extra, b_extra, &mut pending);
+            }
+            _ => {
+                if old != new {
+                    differences.push(Difference {
+                        path,
+                        change: Change::Replaced {
+                            old: old.clone(),
+                            new: new.clone(),
+                        },
+                    });
+                }
+            }
+        }
+
+        stack.extend(pending.into_iter().rev());
+    }
+
+    differences
+}
+
+/// Compare the fields of two objects, or of two class instances or Errors.
+fn compare_fields<'a>(
+    path: &[PathSegment],
+    a: &'a Map<String, Value>,
+    b: &'a Map<String, Value>,
+    pending: &mut Vec<Work<'a>>,
+) {
+    for (key, a_val) in a {
+        let child = child_path(path, key.as_str());
+        pending.push(match b.get(key) {
+            Some(b_val) => Work::Compare(child, a_val, b_val),
+            None => report(child, Change::Removed(a_val.clone())),
+        });
+    }
+    for (key, b_val) in b {
+        if !a.contains_key(key) {
+            let child = child_path(path, key.as_str());
+            pending.push(report(child, Change::Added(b_val.clone())));
+        }
+    }
+}
+
+/// The name of the class `value` is an instance of, if it is one.
+fn class_name(value: &Value) -> Option<&str> {
+    match value {
+        Value::Class { name, .. } => Some(name),
+        _ => None,
+    }
+}
+
+enum Work<'a> {
+    Compare(Vec<PathSegment>, &'a Value, &'a Value),
+    Report(Box<Difference>),
+}
+
+fn report<'a>(path: Vec<PathSegment>, change: Change) -> Work<'a> {
+    Work::Report(Box::new(Difference { path, change }))
+}
+
+fn child_path(path: &[PathSegment], segment: impl Into<PathSegment>) -> Vec<PathSegment> {
+    let mut child = Vec::with_capacity(path.len() + 1);
+    child.extend_from_slice(path);
+    child.push(segment.into());
+    child
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "chrono")]
+    use chrono::TimeZone;
+
+    fn obj(entries: &[(&str, Value)]) -> Value {
+        Value::Object(
+            entries
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.clone()))
+                .collect::<Map<_, _>>(),
+        )
+    }
+
+    #[test]
+    fn test_diff_equal() {
+        let v = obj(&[("a", Value::from(1)), ("b", Value::Set(vec![Value::Null]))]);
+        assert!(diff(&v, &v.clone()).is_empty());
+    }
+
+    #[test]
+    fn test_diff_nested_change() {
+        let old = obj(&[("user", obj(&[("age", Value::from(30))]))]);
+        let new = obj(&[("user", obj(&[("age", Value::from(31))]))]);
+        let changes = diff(&old, &new);
+        assert_eq!(
+            changes,
+            vec![Difference {
+                path: vec![
+                    PathSegment::Key("user".into()),
+                    PathSegment::Key("age".into())
+                ],
+                change: Change::Replaced {
+                    old: Value::from(30),
+                    new: Value::from(31),
+                },
+            }]
+        );
+        assert_eq!(changes[0].to_string(), "user.age: 30 → 31");
+    }
+
+    #[test]
+    fn test_diff_added_and_removed_keys() {
+        let old = obj(&[("a", Value::from(1)), ("b", Value::from(2))]);
+        let new = obj(&[("a", Value::from(1)), ("c", Value::from(3))]);
+        let rendered: Vec<_> = diff(&old, &new).iter().map(|d| d.to_string()).collect();
+        assert_eq!(rendered, vec!["b: removed 2", "c: added 3"]);
+    }
+
+    #[test]
+    fn test_diff_array_length() {
+        let old = Value::Array(vec![Value::from(1)]);
+        let new = Value::Array(vec![Value::from(2), Value::from(3)]);
+        let rendered: Vec<_> = diff(&old, &new).iter().map(|d| d.to_string()).collect();
+        assert_eq!(rendered, vec!["0: 1 → 2", "1: added 3"]);
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn test_diff_map_entry_value() {
+        let dt = chrono::Utc.timestamp_millis_opt(0).unwrap();
+        let old = Value::Map(vec![(Value::from("k"), Value::Date(dt))]);
+        let new = Value::Map(vec![(Value::from("k"), Value::Undefined)]);
+        let changes = diff(&old, &new);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(
+            changes[0].path,
+            vec![PathSegment::Index(0), PathSegment::Index(1)]
+        );
+    }
+
+    #[test]
+    fn test_diff_type_change_at_root() {
+        let changes = diff(&Value::Array(vec![]), &Value::Set(vec![]));
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].to_string(), "<root>: [] → Set {}");
+    }
+}
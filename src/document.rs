@@ -0,0 +1,264 @@
+//! A mutable document that re-stringifies incrementally.
+//!
+//! [`Document`] holds an object value split into its top-level fields and
+//! keeps the serialized form of each field until it is modified. Stringifying
+//! a large state after a small change then only serializes the changed
+//! fields; the rest of the output is copied from the cache.
+
+use crate::path::escape_key;
+use crate::value::Map;
+use crate::{Error, Result, Value, serialize};
+
+/// An object value with per-field serialization caching.
+///
+/// Any field borrowed mutably is assumed to have changed.
+///
+/// # Examples
+/// ```
+/// use superjson_rs::document::Document;
+/// use superjson_rs::{Value, parse, stringify};
+///
+/// let mut doc = Document::from_value(parse(r#"{"json":{"count":1,"tags":["a"]},
+///     "meta":{"values":{"tags":["set"]}}}"#).unwrap()).unwrap();
+/// doc.stringify().unwrap();
+///
+/// *doc.get_mut("count").unwrap() = Value::from(2);
+/// // Only `count` is serialized again.
+/// let s = doc.stringify().unwrap();
+/// assert_eq!(s, stringify(&doc.to_value()).unwrap());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Document {
+    fields: Map<String, Field>,
+}
+
+#[derive(Debug, Clone)]
+struct Field {
+    value: Value,
+    cache: Option<Cached>,
+}
+
+/// The serialized form of a field, as fragments of the envelope text.
+#[derive(Debug, Clone)]
+struct Cached {
+    /// `"key":json`
+    json: String,
+    /// `"path":annotation` entries joined by commas, or empty.
+    annotations: String,
+}
+
+impl Field {
+    fn new(value: Value) -> Self {
+        Field { value, cache: None }
+    }
+
+    fn cached(&mut self, key: &str) -> Result<&Cached> {
+        if self.cache.is_none() {
+            let (json, annotations) = serialize::serialize_child(&self.value, &escape_key(key))?;
+            let mut annotations_text = String::new();
+            for (path, annotation) in &annotations {
+                if !annotations_text.is_empty() {
+                    annotations_text.push(',');
+                }
+                annotations_text.push_str(&serde_json::to_string(path)?);
+                annotations_text.push(':');
+                annotations_text.push_str(&serde_json::to_string(annotation)?);
+            }
+            self.cache = Some(Cached {
+                json: format!("{}:{}", serde_json::to_string(key)?, json),
+                annotations: annotations_text,
+            });
+        }
+        Ok(self.cache.as_ref().expect("cache was just filled"))
+    }
+}
+
+impl Document {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Wrap an object value.
+    ///
+    /// # Errors
+    /// Returns [`Error::TypeMismatch`] if `value` is not an object.
+    pub fn from_value(value: Value) -> Result<Self> {
+        match value {
+            Value::Object(map) => Ok(Document {
+                fields: map
+                    .into_iter()
+                    .map(|(key, value)| (key, Field::new(value)))
+                    .collect(),
+            }),
+            other => Err(Error::TypeMismatch {
+                path: String::new(),
+                expected: "object".to_string(),
+                actual: other.type_name().to_string(),
+            }),
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.fields.get(key).map(|field| &field.value)
+    }
+
+    /// Borrow a field mutably, marking it as changed.
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut Value> {
+        let field = self.fields.get_mut(key)?;
+        field.cache = None;
+        Some(&mut field.value)
+    }
+
+    /// Set a field, returning its previous value.
+    pub fn insert(&mut self, key: impl Into<String>, value: Value) -> Option<Value> {
+        self.fields
+            .insert(key.into(), Field::new(value))
+            .map(|field| field.value)
+    }
+
+    pub fn remove(&mut self, key: &str) -> Option<Value> {
+        #[cfg(feature = "preserve_order")]
+        let removed = self.fields.shift_remove(key);
+        #[cfg(not(feature = "preserve_order"))]
+        let removed = self.fields.remove(key);
+        removed.map(|field| field.value)
+    }
+
+    pub fn len(&self) -> usize {
+        self.fields.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    pub fn to_value(&self) -> Value {
+        Value::Object(
+            self.fields
+                .iter()
+                .map(|(key, field)| (key.clone(), field.value.clone()))
+                .collect(),
+        )
+    }
+
+    pub fn into_value(self) -> Value {
+        Value::Object(
+            self.fields
+                .into_iter()
+                .map(|(key, field)| (key, field.value))
+                .collect(),
+        )
+    }
+
+    /// Stringify the document, serializing only fields changed since the
+    /// last call.
+    ///
+    /// The output is identical to [`stringify`](crate::stringify) on
+    /// [`to_value`](Self::to_value), down to the order of keys: fields in
+    /// insertion order with the `preserve_order` feature, sorted without
+    /// it.
+    pub fn stringify(&mut self) -> Result<String> {
+        let mut json = String::from("{\"json\":{");
+        let mut annotations = String::new();
+
+        for (i, (key, field)) in self.fields.iter_mut().enumerate() {
+            let cached = field.cached(key)?;
+            if i > 0 {
+                json.push(',');
+            }
+            json.push_str(&cached.json);
+            if !cached.annotations.is_empty() {
+                if !annotations.is_empty() {
+                    annotations.push(',');
+                }
+                annotations.push_str(&cached.annotations);
+            }
+        }
+
+        json.push('}');
+        if !annotations.is_empty() {
+            json.push_str(",\"meta\":{\"values\":{");
+            json.push_str(&annotations);
+            json.push_str("},\"v\":1}");
+        }
+        json.push('}');
+        Ok(json)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn doc() -> Document {
+        let value = crate::parse(
+            r#"{"json":{"a":{"b.c":[1]},"n":"NaN","s":"x"},
+                "meta":{"values":{"a.b\\.c":["set"],"n":["number"]}}}"#,
+        )
+        .unwrap();
+        Document::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_stringify_matches_full_serialization() {
+        let mut doc = doc();
+        assert_eq!(
+            doc.stringify().unwrap(),
+            crate::stringify(&doc.to_value()).unwrap()
+        );
+
+        doc.insert("u", Value::Undefined);
+        doc.remove("n");
+        *doc.get_mut("s").unwrap() = Value::Url("https://example.com".parse().unwrap());
+        assert_eq!(
+            doc.stringify().unwrap(),
+            crate::stringify(&doc.to_value()).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_stringify_keeps_key_order_of_full_serialization() {
+        let value = crate::parse(
+            r#"{"json":{"z":[1],"b":{"y":"NaN","c":[2]},"a":"NaN"},
+                "meta":{"values":{"z":["set"],"b.y":["number"],"b.c":["set"],"a":["number"]}}}"#,
+        )
+        .unwrap();
+        let mut doc = Document::from_value(value).unwrap();
+        let text = doc.stringify().unwrap();
+        assert_eq!(text, crate::stringify(&doc.to_value()).unwrap());
+        #[cfg(feature = "preserve_order")]
+        assert_eq!(
+            text,
+            r#"{"json":{"z":[1],"b":{"y":"NaN","c":[2]},"a":"NaN"},"meta":{"values":{"z":["set"],"b.y":["number"],"b.c":["set"],"a":["number"]},"v":1}}"#
+        );
+        #[cfg(not(feature = "preserve_order"))]
+        assert_eq!(
+            text,
+            r#"{"json":{"a":"NaN","b":{"c":[2],"y":"NaN"},"z":[1]},"meta":{"values":{"a":["number"],"b.c":["set"],"b.y":["number"],"z":["set"]},"v":1}}"#
+        );
+    }
+
+    #[test]
+    fn test_unchanged_fields_use_cache() {
+        let mut doc = doc();
+        doc.stringify().unwrap();
+        // A stale cache entry is only replaced when the field is modified.
+        doc.fields
+            .get_mut("s")
+            .unwrap()
+            .cache
+            .as_mut()
+            .unwrap()
+            .json = "\"s\":0".into();
+        assert!(doc.stringify().unwrap().contains("\"s\":0"));
+        doc.get_mut("s");
+        assert!(doc.stringify().unwrap().contains("\"s\":\"x\""));
+    }
+
+    #[test]
+    fn test_empty_document() {
+        let mut doc = Document::new();
+        assert_eq!(doc.stringify().unwrap(), r#"{"json":{}}"#);
+        assert!(Document::from_value(Value::Null).is_err());
+    }
+}
@@ -0,0 +1,111 @@
+//! ISO 8601 durations, as `Temporal.Duration` writes them.
+//!
+//! A duration is written in seconds, e.g. `PT90.5S`, as
+//! `Temporal.Duration.from({ seconds: 90.5 }).toString()` does. Reading
+//! accepts any combination of weeks, days, hours, minutes and seconds, with
+//! a fraction on the last of them; years and months have no fixed length and
+//! are rejected.
+
+use std::time::Duration;
+
+/// Write a duration of `duration`, negated if `negative`.
+pub(crate) fn format(negative: bool, duration: Duration) -> String {
+    let sign = if negative && !duration.is_zero() {
+        "-"
+    } else {
+        ""
+    };
+    let secs = duration.as_secs();
+    match duration.subsec_nanos() {
+        0 => format!("{sign}PT{secs}S"),
+        nanos => {
+            let fraction = format!("{nanos:09}");
+            format!("{sign}PT{secs}.{}S", fraction.trim_end_matches('0'))
+        }
+    }
+}
+
+/// Read a duration, returning whether it is negative and its length.
+pub(crate) fn parse(s: &str) -> Option<(bool, Duration)> {
+    let (negative, rest) = match s.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, s.strip_prefix('+').unwrap_or(s)),
+    };
+    let rest = rest.strip_prefix(['P', 'p'])?;
+    let (date, time) = match rest.split_once(['T', 't']) {
+        Some((date, time)) if !time.is_empty() => (date, time),
+        Some(_) => return None,
+        None if !rest.is_empty() => (rest, ""),
+        None => return None,
+    };
+
+    const DATE_UNITS: &[(char, u64)] = &[('W', 7 * 86_400), ('D', 86_400)];
+    const TIME_UNITS: &[(char, u64)] = &[('H', 3_600), ('M', 60), ('S', 1)];
+    let mut total = Duration::ZERO;
+    let mut fraction_seen = false;
+    for (mut part, units) in [(date, DATE_UNITS), (time, TIME_UNITS)] {
+        let mut units = units.iter();
+        while !part.is_empty() {
+            // Only the last component may have a fraction.
+            if fraction_seen {
+                return None;
+            }
+            let end = part.find(|c: char| c.is_ascii_alphabetic())?;
+            let number = &part[..end];
+            let unit = part[end..].chars().next()?.to_ascii_uppercase();
+            part = &part[end + 1..];
+            // Components must come in order, each at most once.
+            let &(_, unit_secs) = units.find(|(u, _)| *u == unit)?;
+
+            let (whole, fraction) = match number.split_once(['.', ',']) {
+                Some((whole, fraction)) => (whole, Some(fraction)),
+                None => (number, None),
+            };
+            let whole: u64 = digits(whole)?.parse().ok()?;
+            total = total.checked_add(Duration::from_secs(whole.checked_mul(unit_secs)?))?;
+            if let Some(fraction) = fraction {
+                let fraction = digits(fraction)?;
+                let nanos: u64 = format!("{:0<9}", &fraction[..fraction.len().min(9)])
+                    .parse()
+                    .ok()?;
+                total = total.checked_add(Duration::from_nanos(nanos * unit_secs))?;
+                fraction_seen = true;
+            }
+        }
+    }
+    Some((negative, total))
+}
+
+/// `s` if it is a non-empty run of ASCII digits.
+fn digits(s: &str) -> Option<&str> {
+    (!s.is_empty() && s.bytes().all(|b| b.is_ascii_digit())).then_some(s)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format() {
+        assert_eq!(format(false, Duration::ZERO), "PT0S");
+        assert_eq!(format(true, Duration::ZERO), "PT0S");
+        assert_eq!(format(false, Duration::from_millis(90_500)), "PT90.5S");
+        assert_eq!(format(true, Duration::new(1, 1)), "-PT1.000000001S");
+    }
+
+    #[test]
+    fn test_parse() {
+        let secs = |s| parse(s).map(|(negative, d)| (negative, d.as_secs_f64()));
+        assert_eq!(secs("PT90.5S"), Some((false, 90.5)));
+        assert_eq!(secs("-P1W2DT3H4M5S"), Some((true, 788_645.0)));
+        assert_eq!(secs("PT1,5M"), Some((false, 90.0)));
+        assert_eq!(secs("pt1h"), Some((false, 3_600.0)));
+        assert_eq!(secs("P0.5D"), Some((false, 43_200.0)));
+        for invalid in [
+            "", "P", "PT", "P1Y", "P1M", "PT1S1M", "PT1.5M1S", "PT1H1H", "PT.5S", "PT1.S", "1S",
+            "PT-1S",
+        ] {
+            assert_eq!(parse(invalid), None, "{invalid}");
+        }
+    }
+}
@@ -0,0 +1,227 @@
+//! Reading the `{json, meta}` envelope from the wire.
+//!
+//! The envelope is first read into raw structures that keep `meta.values` as
+//! plain JSON and remember any fields this crate does not understand, so that
+//! the caller's [`ParseOptions`] can be applied and ignored fields reported.
+
+use std::borrow::Cow;
+use std::fmt;
+use std::marker::PhantomData;
+
+use serde::de::{self, IgnoredAny, MapAccess, Visitor};
+use serde::{Deserialize, Deserializer};
+
+use crate::warning::Warning;
+use crate::{AnnotationValues, Error, META_VERSION, Meta, ParseOptions, Result, SuperJson};
+
+/// The envelope as read from the wire, before `meta.values` is checked
+/// against the caller's [`ParseOptions`].
+///
+/// The `json` part is read as a `J`, ordinarily a `serde_json::Value`.
+pub(crate) struct RawSuperJson<J = serde_json::Value> {
+    pub(crate) json: J,
+    pub(crate) meta: Option<RawMeta>,
+    /// Top-level fields other than `json` and `meta`.
+    pub(crate) unknown: Vec<String>,
+}
+
+pub(crate) struct RawMeta {
+    pub(crate) values: Option<serde_json::Value>,
+    pub(crate) referential_equalities: Option<serde_json::Value>,
+    pub(crate) v: Option<u8>,
+    pub(crate) checksum: Option<String>,
+    /// Fields of `meta` other than the ones above.
+    pub(crate) unknown: Vec<String>,
+}
+
+impl RawSuperJson {
+    /// Validate the raw envelope against `options`, recording ignored fields
+    /// and lenient annotation forms in `warnings`.
+    pub(crate) fn into_superjson(
+        self,
+        options: &ParseOptions,
+        warnings: &mut Vec<Warning>,
+    ) -> Result<SuperJson> {
+        let (json, meta) = self.into_parts(options, warnings)?;
+        Ok(SuperJson { json, meta })
+    }
+}
+
+impl<J> RawSuperJson<J> {
+    /// Validate the raw envelope against `options` as
+    /// [`into_superjson`](RawSuperJson::into_superjson) does, returning the
+    /// `json` part as read and the checked `meta`.
+    pub(crate) fn into_parts(
+        self,
+        options: &ParseOptions,
+        warnings: &mut Vec<Warning>,
+    ) -> Result<(J, Option<Meta>)> {
+        for name in self.unknown {
+            warnings.push(Warning::UnknownField { name });
+        }
+
+        let meta = match self.meta {
+            Some(raw) => {
+                match raw.v {
+                    Some(version) if version > META_VERSION && options.strict_version => {
+                        return Err(Error::UnsupportedVersion(version));
+                    }
+                    Some(version) if version > META_VERSION => {
+                        warnings.push(Warning::UnknownVersion { version });
+                    }
+                    _ => {}
+                }
+                for name in raw.unknown {
+                    warnings.push(Warning::UnknownField {
+                        name: format!("meta.{name}"),
+                    });
+                }
+                let values = match raw.values {
+                    Some(v) => Some(AnnotationValues::from_json_with_warnings(
+                        Cow::Owned(check_annotation_count(v, options.max_annotations)?),
+                        options.max_depth,
+                        warnings,
+                    )?),
+                    None => None,
+                };
+                Some(Meta {
+                    values,
+                    referential_equalities: raw.referential_equalities,
+                    v: raw.v,
+                    checksum: raw.checksum,
+                })
+            }
+            None => None,
+        };
+
+        Ok((self.json, meta))
+    }
+}
+
+/// Reject `values` if it holds more than `max` annotation tree nodes.
+///
+/// Only the shape of the tree is inspected; malformed annotations are left
+/// for [`AnnotationValues`] to report.
+fn check_annotation_count(values: serde_json::Value, max: usize) -> Result<serde_json::Value> {
+    let mut stack: Vec<&serde_json::Value> = match &values {
+        serde_json::Value::Object(map) => map.values().collect(),
+        serde_json::Value::Array(pairs) if crate::is_legacy_pairs(pairs) => pairs.iter().collect(),
+        root => vec![root],
+    };
+    let mut count = 0;
+    while let Some(node) = stack.pop() {
+        count += 1;
+        if count > max {
+            return Err(Error::AnnotationLimitExceeded(max));
+        }
+        if let Some(serde_json::Value::Object(children)) =
+            node.as_array().and_then(|node| node.get(1))
+        {
+            stack.extend(children.values());
+        }
+    }
+    Ok(values)
+}
+
+impl<'de, J: Deserialize<'de>> Deserialize<'de> for RawSuperJson<J> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        deserializer.deserialize_map(RawSuperJsonVisitor(PhantomData))
+    }
+}
+
+struct RawSuperJsonVisitor<J>(PhantomData<J>);
+
+impl<'de, J: Deserialize<'de>> Visitor<'de> for RawSuperJsonVisitor<J> {
+    type Value = RawSuperJson<J>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a superjson envelope: {\"json\": ..., \"meta\": ...}")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(
+        self,
+        mut map: A,
+    ) -> std::result::Result<RawSuperJson<J>, A::Error> {
+        let mut json: Option<J> = None;
+        let mut meta: Option<Option<RawMeta>> = None;
+        let mut unknown = Vec::new();
+
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "json" => set_once(&mut json, "json", map.next_value()?)?,
+                "meta" => set_once(&mut meta, "meta", map.next_value()?)?,
+                _ => {
+                    map.next_value::<IgnoredAny>()?;
+                    unknown.push(key);
+                }
+            }
+        }
+
+        Ok(RawSuperJson {
+            json: json.ok_or_else(|| de::Error::missing_field("json"))?,
+            meta: meta.flatten(),
+            unknown,
+        })
+    }
+}
+
+impl<'de> Deserialize<'de> for RawMeta {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        deserializer.deserialize_map(RawMetaVisitor)
+    }
+}
+
+struct RawMetaVisitor;
+
+impl<'de> Visitor<'de> for RawMetaVisitor {
+    type Value = RawMeta;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a superjson meta object")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> std::result::Result<RawMeta, A::Error> {
+        let mut values: Option<Option<serde_json::Value>> = None;
+        let mut referential_equalities: Option<Option<serde_json::Value>> = None;
+        let mut v: Option<Option<u8>> = None;
+        let mut checksum: Option<Option<String>> = None;
+        let mut unknown = Vec::new();
+
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "values" => set_once(&mut values, "values", map.next_value()?)?,
+                "referentialEqualities" => set_once(
+                    &mut referential_equalities,
+                    "referentialEqualities",
+                    map.next_value()?,
+                )?,
+                "v" => set_once(&mut v, "v", map.next_value()?)?,
+                "checksum" => set_once(&mut checksum, "checksum", map.next_value()?)?,
+                _ => {
+                    map.next_value::<IgnoredAny>()?;
+                    unknown.push(key);
+                }
+            }
+        }
+
+        Ok(RawMeta {
+            values: values.flatten(),
+            referential_equalities: referential_equalities.flatten(),
+            v: v.flatten(),
+            checksum: checksum.flatten(),
+            unknown,
+        })
+    }
+}
+
+fn set_once<T, E: de::Error>(
+    slot: &mut Option<T>,
+    name: &'static str,
+    value: T,
+) -> std::result::Result<(), E> {
+    if slot.is_some() {
+        return Err(de::Error::duplicate_field(name));
+    }
+    *slot = Some(value);
+    Ok(())
+}
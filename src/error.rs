@@ -5,6 +5,32 @@ pub enum Error {
     #[error("JSON error: {0}")]
     Json(#[from] serde_json::Error),
 
+    #[cfg(feature = "polars")]
+    #[error("polars error: {0}")]
+    Polars(#[from] polars::error::PolarsError),
+
+    #[cfg(feature = "msgpack")]
+    #[error("MessagePack encoding error: {0}")]
+    MessagePackEncode(#[from] rmp_serde::encode::Error),
+
+    #[cfg(feature = "msgpack")]
+    #[error("MessagePack decoding error: {0}")]
+    MessagePackDecode(#[from] rmp_serde::decode::Error),
+
+    #[cfg(feature = "yaml")]
+    #[error("YAML error: {0}")]
+    Yaml(#[from] serde_yaml::Error),
+
+    #[cfg(feature = "yaml")]
+    #[error("invalid annotated YAML: {0}")]
+    InvalidYaml(String),
+
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("JSON backend error: {0}")]
+    Backend(Box<dyn std::error::Error + Send + Sync>),
+
     #[error("invalid type annotation: {0}")]
     InvalidTypeAnnotation(String),
 
@@ -20,6 +46,72 @@ pub enum Error {
     #[error("invalid regexp: {0}")]
     InvalidRegExp(String),
 
+    #[error("regexp {pattern} uses {construct}, which the regex crate does not support")]
+    UnsupportedRegExp {
+        pattern: String,
+        construct: &'static str,
+    },
+
+    #[error("invalid referential equality: {0}")]
+    InvalidReferentialEquality(String),
+
+    #[error("invalid devalue payload: {0}")]
+    InvalidDevalue(String),
+
+    #[error("invalid CBOR: {0}")]
+    InvalidCbor(String),
+
+    #[error("tRPC error {code}: {message}")]
+    Trpc {
+        code: i64,
+        message: String,
+        data: Box<crate::Value>,
+    },
+
+    #[error("invalid tRPC response: {0}")]
+    InvalidTrpcResponse(String),
+
+    #[error("meta.v is {0}, newer than any version this crate understands")]
+    UnsupportedVersion(u8),
+
+    #[error("invalid URL: {0}")]
+    InvalidUrl(String),
+
+    #[error("type '{0}' is not registered as a custom type")]
+    UnregisteredCustomType(String),
+
+    #[error("symbol '{0}' is not registered")]
+    UnregisteredSymbol(String),
+
+    #[error("type '{name}' requires the `{feature}` feature")]
+    UnsupportedType { name: String, feature: &'static str },
+
+    #[error("number at '{path}' is outside JavaScript's safe integer range: {value}; use a BigInt")]
+    UnsafeInteger { path: String, value: f64 },
+
+    #[error("maximum nesting depth of {0} exceeded")]
+    DepthLimitExceeded(usize),
+
+    #[error("maximum of {0} type annotations exceeded")]
+    AnnotationLimitExceeded(usize),
+
+    #[error(
+        "checksum mismatch: meta.checksum is {expected}, but the json section hashes to {actual}"
+    )]
+    ChecksumMismatch { expected: String, actual: String },
+
+    #[error("envelope signature is missing or invalid")]
+    InvalidSignature,
+
+    #[error("invalid delta: {0}")]
+    InvalidDelta(String),
+
+    #[error("delta is based on state {expected}, but the current state hashes to {actual}")]
+    DeltaBaseMismatch { expected: String, actual: String },
+
+    #[error("Map has more than one entry with the key {0}")]
+    DuplicateMapKey(String),
+
     #[error("type mismatch at path '{path}': expected {expected}, got {actual}")]
     TypeMismatch {
         path: String,
@@ -0,0 +1,202 @@
+//! HTTP entity tags for superjson responses.
+//!
+//! A strong [`ETag`] identifies the exact bytes of a stringified envelope; a
+//! weak one identifies the value it encodes, so two envelopes that differ
+//! only in key order or number formatting share a weak tag. Comparison
+//! follows RFC 9110: `If-None-Match` uses weak comparison and `If-Match`
+//! uses strong comparison.
+
+use std::fmt;
+
+use sha2::{Digest, Sha256};
+
+use crate::Value;
+use crate::bytes::hex;
+
+/// An HTTP entity tag.
+///
+/// # Examples
+/// ```
+/// use superjson_rs::etag::ETag;
+/// use superjson_rs::{Value, stringify};
+///
+/// let value = Value::Set(vec![Value::from(1)]);
+/// let body = stringify(&value).unwrap();
+///
+/// let tag = ETag::strong(&body);
+/// assert!(tag.to_string().starts_with('"'));
+/// assert!(tag.matches_if_none_match(&tag.to_string()));
+///
+/// let weak = ETag::weak(&value);
+/// assert!(weak.to_string().starts_with("W/\""));
+/// assert!(!weak.matches_if_match(&weak.to_string()));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ETag {
+    weak: bool,
+    opaque: String,
+}
+
+impl ETag {
+    /// A strong tag for a stringified envelope, as sent in a response body.
+    pub fn strong(envelope: &str) -> Self {
+        let digest: [u8; 32] = Sha256::digest(envelope.as_bytes()).into();
+        ETag {
+            weak: false,
+            opaque: hex(&digest[..16]),
+        }
+    }
+
+    /// A weak tag for a value, from its [`content_hash`](Value::content_hash).
+    pub fn weak(value: &Value) -> Self {
+        ETag {
+            weak: true,
+            opaque: hex(&value.content_hash().as_bytes()[..16]),
+        }
+    }
+
+    /// Parse a single entity tag, such as `"abc"` or `W/"abc"`.
+    ///
+    /// Returns `None` if `s` is not a well-formed entity tag.
+    pub fn parse(s: &str) -> Option<Self> {
+        match parse_list(s.trim())?.as_slice() {
+            [tag] => Some(tag.clone()),
+            _ => None,
+        }
+    }
+
+    pub fn is_weak(&self) -> bool {
+        self.weak
+    }
+
+    /// The tag without the `W/` prefix or quotes.
+    pub fn opaque(&self) -> &str {
+        &self.opaque
+    }
+
+    /// Strong comparison: neither tag is weak and both are identical.
+    pub fn strong_eq(&self, other: &ETag) -> bool {
+        !self.weak && !other.weak && self.opaque == other.opaque
+    }
+
+    /// Weak comparison: the tags are identical, ignoring weakness.
+    pub fn weak_eq(&self, other: &ETag) -> bool {
+        self.opaque == other.opaque
+    }
+
+    /// Whether an `If-None-Match` header value matches this tag, meaning a
+    /// `GET` can be answered with `304 Not Modified`.
+    ///
+    /// A malformed header never matches.
+    pub fn matches_if_none_match(&self, header: &str) -> bool {
+        self.matches(header, ETag::weak_eq)
+    }
+
+    /// Whether an `If-Match` header value matches this tag, meaning a
+    /// conditional update may proceed.
+    ///
+    /// A malformed header never matches.
+    pub fn matches_if_match(&self, header: &str) -> bool {
+        self.matches(header, ETag::strong_eq)
+    }
+
+    fn matches(&self, header: &str, eq: fn(&ETag, &ETag) -> bool) -> bool {
+        let header = header.trim();
+        if header == "*" {
+            return true;
+        }
+        parse_list(header).is_some_and(|tags| tags.iter().any(|tag| eq(self, tag)))
+    }
+}
+
+impl fmt::Display for ETag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.weak {
+            write!(f, "W/")?;
+        }
+        write!(f, "\"{}\"", self.opaque)
+    }
+}
+
+/// Parse a comma-separated list of entity tags.
+fn parse_list(s: &str) -> Option<Vec<ETag>> {
+    let mut tags = Vec::new();
+    let mut rest = s;
+
+    loop {
+        rest = rest.trim_start_matches([' ', '\t', ',']);
+        if rest.is_empty() {
+            break;
+        }
+
+        let weak = match rest.strip_prefix("W/") {
+            Some(stripped) => {
+                rest = stripped;
+                true
+            }
+            None => false,
+        };
+        let quoted = rest.strip_prefix('"')?;
+        let end = quoted.find('"')?;
+        tags.push(ETag {
+            weak,
+            opaque: quoted[..end].to_string(),
+        });
+
+        rest = quoted[end + 1..].trim_start_matches([' ', '\t']);
+        if !rest.is_empty() && !rest.starts_with(',') {
+            return None;
+        }
+    }
+
+    (!tags.is_empty()).then_some(tags)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_roundtrip() {
+        for s in ["\"abc\"", "W/\"abc\"", "\"\""] {
+            assert_eq!(ETag::parse(s).unwrap().to_string(), s);
+        }
+        assert_eq!(ETag::parse("abc"), None);
+        assert_eq!(ETag::parse("\"a\", \"b\""), None);
+        assert_eq!(ETag::parse("\"a\" b"), None);
+    }
+
+    #[test]
+    fn test_if_none_match_uses_weak_comparison() {
+        let tag = ETag::strong("{\"json\":1}");
+        let header = format!("\"other\", W/\"{}\"", tag.opaque());
+        assert!(tag.matches_if_none_match(&header));
+        assert!(tag.matches_if_none_match("*"));
+        assert!(!tag.matches_if_none_match("\"other\""));
+        assert!(!tag.matches_if_none_match("garbage"));
+    }
+
+    #[test]
+    fn test_if_match_uses_strong_comparison() {
+        let tag = ETag::strong("{\"json\":1}");
+        assert!(tag.matches_if_match(&tag.to_string()));
+        assert!(!tag.matches_if_match(&format!("W/\"{}\"", tag.opaque())));
+        assert!(tag.matches_if_match("*"));
+    }
+
+    #[test]
+    fn test_weak_tag_ignores_key_order() {
+        let a = crate::parse(r#"{"json":{"a":1,"b":2}}"#).unwrap();
+        let b = crate::parse(r#"{"json":{"b":2,"a":1}}"#).unwrap();
+        assert_eq!(ETag::weak(&a), ETag::weak(&b));
+        assert!(ETag::weak(&a).is_weak());
+    }
+
+    #[test]
+    fn test_strong_tag_tracks_bytes() {
+        assert_ne!(
+            ETag::strong(r#"{"json":{"a":1,"b":2}}"#),
+            ETag::strong(r#"{"json":{"b":2,"a":1}}"#)
+        );
+    }
+}
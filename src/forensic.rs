@@ -0,0 +1,258 @@
+//! Parsing that keeps duplicate object keys.
+//!
+//! JSON parsers disagree on which of two identical keys in an object wins,
+//! which lets a payload mean one thing to a gateway and another to the
+//! service behind it. [`parse`] keeps every key exactly as received, in
+//! order, so tooling can inspect such payloads instead of normalizing them.
+//!
+//! Only plain objects and arrays are kept as received. A value with a type
+//! annotation, such as a `Set` or an `Error`, is restored as usual, keeping
+//! the last of any duplicate keys within it.
+
+use std::collections::HashSet;
+use std::fmt;
+
+use indexmap::IndexSet;
+use serde::de::{self, MapAccess, SeqAccess, Visitor};
+use serde::{Deserialize, Deserializer};
+
+use crate::path::escape_key;
+use crate::{ParseOptions, Result, Value, deserialize, envelope};
+
+/// A parsed value whose plain objects keep duplicate keys.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ForensicValue {
+    /// A scalar, or a value with a type annotation.
+    Value(Value),
+    Array(Vec<ForensicValue>),
+    /// Fields in the order received, including repeated keys.
+    Object(Vec<(String, ForensicValue)>),
+}
+
+impl ForensicValue {
+    /// The paths of object keys that appear more than once in the same
+    /// object, each listed once, in the order they are repeated.
+    ///
+    /// # Examples
+    /// ```
+    /// use superjson_rs::forensic;
+    ///
+    /// let parsed = forensic::parse(r#"{"json":{"user":{"role":"guest","role":"admin"}}}"#).unwrap();
+    /// assert_eq!(parsed.duplicate_keys(), ["user.role"]);
+    /// ```
+    pub fn duplicate_keys(&self) -> Vec<String> {
+        let mut duplicates = Vec::new();
+        let mut stack = vec![(String::new(), self)];
+
+        while let Some((path, value)) = stack.pop() {
+            let child_path = |key: &str| {
+                if path.is_empty() {
+                    key.to_string()
+                } else {
+                    format!("{path}.{key}")
+                }
+            };
+            match value {
+                ForensicValue::Value(_) => {}
+                ForensicValue::Array(items) => {
+                    for (i, item) in items.iter().enumerate().rev() {
+                        stack.push((child_path(&i.to_string()), item));
+                    }
+                }
+                ForensicValue::Object(fields) => {
+                    let mut seen = HashSet::new();
+                    let mut repeated = IndexSet::new();
+                    for (key, _) in fields {
+                        if !seen.insert(key) {
+                            repeated.insert(key);
+                        }
+                    }
+                    duplicates.extend(repeated.into_iter().map(|key| child_path(&escape_key(key))));
+                    for (key, field) in fields.iter().rev() {
+                        stack.push((child_path(&escape_key(key)), field));
+                    }
+                }
+            }
+        }
+        duplicates
+    }
+}
+
+/// Parse a superjson JSON string, keeping duplicate object keys.
+///
+/// # Examples
+/// ```
+/// use superjson_rs::forensic::{self, ForensicValue};
+/// use superjson_rs::Value;
+///
+/// let parsed = forensic::parse(r#"{"json":{"amount":1,"amount":1000}}"#).unwrap();
+/// assert_eq!(
+///     parsed,
+///     ForensicValue::Object(vec![
+///         ("amount".into(), ForensicValue::Value(Value::from(1))),
+///         ("amount".into(), ForensicValue::Value(Value::from(1000))),
+///     ])
+/// );
+/// ```
+pub fn parse(s: &str) -> Result<ForensicValue> {
+    parse_with(s, &ParseOptions::default())
+}
+
+/// Parse a superjson JSON string with explicit limits, keeping duplicate
+/// object keys.
+pub fn parse_with(s: &str, options: &ParseOptions) -> Result<ForensicValue> {
+    // The envelope is validated as for an ordinary parse; only its `json`
+    // part is read again with every key kept.
+    let envelope: envelope::RawSuperJson = serde_json::from_str(s)?;
+    let superjson = envelope.into_superjson(options, &mut Vec::new())?;
+    let raw: RawEnvelope = serde_json::from_str(s)?;
+    deserialize::deserialize_forensic(&raw.json, &superjson, options)
+}
+
+/// JSON as received, with every object key kept.
+///
+/// Values are only ever read by `serde_json`, whose recursion limit bounds
+/// their depth, so converting them recursively is safe.
+#[derive(Debug)]
+pub(crate) enum RawJson {
+    Scalar(serde_json::Value),
+    Array(Vec<RawJson>),
+    Object(Vec<(String, RawJson)>),
+}
+
+impl RawJson {
+    /// The value as ordinary JSON, where the last of any duplicate keys wins.
+    pub(crate) fn to_json(&self) -> serde_json::Value {
+        match self {
+            RawJson::Scalar(value) => value.clone(),
+            RawJson::Array(items) => items.iter().map(RawJson::to_json).collect(),
+            RawJson::Object(fields) => fields
+                .iter()
+                .map(|(key, value)| (key.clone(), value.to_json()))
+                .collect(),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for RawJson {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        deserializer.deserialize_any(RawJsonVisitor)
+    }
+}
+
+struct RawJsonVisitor;
+
+impl<'de> Visitor<'de> for RawJsonVisitor {
+    type Value = RawJson;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("any JSON value")
+    }
+
+    fn visit_unit<E: de::Error>(self) -> std::result::Result<RawJson, E> {
+        Ok(RawJson::Scalar(serde_json::Value::Null))
+    }
+
+    fn visit_bool<E: de::Error>(self, b: bool) -> std::result::Result<RawJson, E> {
+        Ok(RawJson::Scalar(b.into()))
+    }
+
+    fn visit_i64<E: de::Error>(self, n: i64) -> std::result::Result<RawJson, E> {
+        Ok(RawJson::Scalar(n.into()))
+    }
+
+    fn visit_u64<E: de::Error>(self, n: u64) -> std::result::Result<RawJson, E> {
+        Ok(RawJson::Scalar(n.into()))
+    }
+
+    fn visit_f64<E: de::Error>(self, n: f64) -> std::result::Result<RawJson, E> {
+        Ok(RawJson::Scalar(n.into()))
+    }
+
+    fn visit_str<E: de::Error>(self, s: &str) -> std::result::Result<RawJson, E> {
+        Ok(RawJson::Scalar(s.into()))
+    }
+
+    fn visit_string<E: de::Error>(self, s: String) -> std::result::Result<RawJson, E> {
+        Ok(RawJson::Scalar(s.into()))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> std::result::Result<RawJson, A::Error> {
+        let mut items = Vec::new();
+        while let Some(item) = seq.next_element()? {
+            items.push(item);
+        }
+        Ok(RawJson::Array(items))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> std::result::Result<RawJson, A::Error> {
+        let mut fields = Vec::new();
+        while let Some(entry) = map.next_entry()? {
+            fields.push(entry);
+        }
+        Ok(RawJson::Object(fields))
+    }
+}
+
+/// The `json` part of an envelope; everything else is read by
+/// [`envelope::RawSuperJson`].
+#[derive(Deserialize)]
+struct RawEnvelope {
+    json: RawJson,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_duplicates_are_kept_in_order() {
+        let parsed =
+            parse(r#"{"json":[{"a":1,"b":2,"a":3}],"meta":{"values":{"0.a":["undefined"]}}}"#);
+        let ForensicValue::Array(items) = parsed.unwrap() else {
+            panic!("expected an array");
+        };
+        let ForensicValue::Object(fields) = &items[0] else {
+            panic!("expected an object");
+        };
+        let keys: Vec<_> = fields.iter().map(|(key, _)| key.as_str()).collect();
+        assert_eq!(keys, ["a", "b", "a"]);
+        // Both fields at the annotated path are restored with its type.
+        assert_eq!(fields[0].1, ForensicValue::Value(Value::Undefined));
+        assert_eq!(fields[2].1, ForensicValue::Value(Value::Undefined));
+    }
+
+    #[test]
+    fn test_annotated_values_are_restored() {
+        let parsed = parse(
+            r#"{"json":{"s":[1],"k.k":"NaN","k.k":"Infinity"},
+                "meta":{"values":{"s":["set"],"k\\.k":["number"]}}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            parsed,
+            ForensicValue::Object(vec![
+                (
+                    "s".into(),
+                    ForensicValue::Value(Value::Set(vec![Value::from(1)]))
+                ),
+                ("k.k".into(), ForensicValue::Value(Value::NaN)),
+                ("k.k".into(), ForensicValue::Value(Value::PosInfinity)),
+            ])
+        );
+        assert_eq!(parsed.duplicate_keys(), ["k\\.k"]);
+    }
+
+    #[test]
+    fn test_depth_limit() {
+        let options = ParseOptions {
+            max_depth: 2,
+            ..ParseOptions::default()
+        };
+        assert!(parse_with(r#"{"json":{"a":{"b":1}}}"#, &options).is_ok());
+        assert!(matches!(
+            parse_with(r#"{"json":{"a":{"b":{"c":1}}}}"#, &options),
+            Err(crate::Error::DepthLimitExceeded(2))
+        ));
+    }
+}
@@ -0,0 +1,31 @@
+//! Encodings of [`Value`](crate::Value)s other than superjson's JSON text.
+
+use crate::{
+    AnnotationValues, META_VERSION, Meta, ParseOptions, Result, SuperJson, TypeAnnotation, Value,
+};
+
+pub mod cbor;
+pub mod devalue;
+#[cfg(feature = "msgpack")]
+pub mod msgpack;
+
+/// Restore `json` as superjson restores a value annotated `type_name`, so
+/// that the options for that type apply in every encoding.
+pub(crate) fn restore(
+    type_name: &str,
+    json: serde_json::Value,
+    options: &ParseOptions,
+) -> Result<Value> {
+    let superjson = SuperJson {
+        json,
+        meta: Some(Meta {
+            values: Some(AnnotationValues::Root(TypeAnnotation::Leaf(
+                type_name.to_string(),
+            ))),
+            referential_equalities: None,
+            v: Some(META_VERSION),
+            checksum: None,
+        }),
+    };
+    crate::deserialize::deserialize_with(&superjson, options)
+}
@@ -0,0 +1,719 @@
+//! [CBOR](https://www.rfc-editor.org/rfc/rfc8949) with semantic tags.
+//!
+//! Unlike superjson's envelope, CBOR describes extended types in the data
+//! itself: JSON types and the special numbers map onto CBOR's own, and the
+//! rest onto registered tags.
+//!
+//! | Value | CBOR |
+//! |-------|------|
+//! | `undefined` | the simple value `undefined` |
+//! | integer | an integer |
+//! | number, `NaN`, `±Infinity`, `-0` | a double-precision float |
+//! | bytes | a byte string |
+//! | object | a map with text keys |
+//! | Date | tag 1, seconds since the epoch: an integer, or a float with milliseconds |
+//! | Date with an offset | tag 0, an RFC 3339 string |
+//! | invalid Date | tag 1, `NaN` |
+//! | bigint | tag 2 or 3, a bignum |
+//! | URL | tag 32, a URI |
+//! | Set | tag 258, an array |
+//! | Map | tag 259, a map with keys of any type |
+//! | RegExp | tag 21066, an array of source and flags |
+//!
+//! Errors, class instances, custom values, symbols and values of unknown
+//! types have no tag, and cannot be encoded.
+//!
+//! Decoding also accepts half- and single-precision floats, indefinite-length
+//! items, tag 0 dates in any offset, and the self-described CBOR tag 55799.
+//! A map with keys other than text outside tag 259 is read as a Map. Dates
+//! follow [`ParseOptions::invalid_dates`](crate::ParseOptions) and
+//! `preserve_date_offsets`, and nesting is limited by `max_depth`.
+//!
+//! # Examples
+//! ```
+//! use superjson_rs::Value;
+//! use superjson_rs::format::cbor;
+//!
+//! let value = Value::Set(vec![Value::from(1), Value::NaN]);
+//! let bytes = cbor::to_vec(&value).unwrap();
+//! assert_eq!(bytes, [0xd9, 0x01, 0x02, 0x82, 0x01, 0xfb, 0x7f, 0xf8, 0, 0, 0, 0, 0, 0]);
+//! assert_eq!(cbor::from_slice(&bytes).unwrap(), value);
+//! ```
+
+#[cfg(feature = "chrono")]
+use chrono::SecondsFormat;
+#[cfg(feature = "bigint")]
+use num_bigint::{BigInt, Sign};
+
+use super::restore;
+use crate::value::Map;
+use crate::{Error, ParseOptions, Result, Value};
+
+const UNSIGNED: u8 = 0;
+const NEGATIVE: u8 = 1;
+const BYTES: u8 = 2;
+const TEXT: u8 = 3;
+const ARRAY: u8 = 4;
+const MAP: u8 = 5;
+const TAG: u8 = 6;
+const SIMPLE: u8 = 7;
+
+const FALSE: u8 = 0xf4;
+const TRUE: u8 = 0xf5;
+const NULL: u8 = 0xf6;
+const UNDEFINED: u8 = 0xf7;
+const FLOAT64: u8 = 0xfb;
+const BREAK: u8 = 0xff;
+
+const DATE_TEXT: u64 = 0;
+const DATE_EPOCH: u64 = 1;
+const POSITIVE_BIGNUM: u64 = 2;
+const NEGATIVE_BIGNUM: u64 = 3;
+const URI: u64 = 32;
+const SET: u64 = 258;
+const JS_MAP: u64 = 259;
+const REGEXP: u64 = 21066;
+const SELF_DESCRIBED: u64 = 55799;
+
+/// Encode `value` as CBOR.
+///
+/// # Errors
+/// Returns [`Error::TypeMismatch`] for a value no CBOR tag describes.
+pub fn to_vec(value: &Value) -> Result<Vec<u8>> {
+    let mut out = Vec::new();
+    let mut stack = vec![Item::Value(value)];
+
+    while let Some(item) = stack.pop() {
+        let value = match item {
+            Item::Key(key) => {
+                text(&mut out, key);
+                continue;
+            }
+            Item::Value(value) => value,
+        };
+        match value {
+            Value::Null => out.push(NULL),
+            Value::Undefined => out.push(UNDEFINED),
+            Value::Bool(b) => out.push(if *b { TRUE } else { FALSE }),
+            Value::Int(n) if *n >= 0 => header(&mut out, UNSIGNED, *n as u64),
+            Value::Int(n) => header(&mut out, NEGATIVE, !*n as u64),
+            Value::UInt(n) => header(&mut out, UNSIGNED, *n),
+            Value::Number(n) => float(&mut out, *n),
+            Value::NaN => float(&mut out, f64::NAN),
+            Value::PosInfinity => float(&mut out, f64::INFINITY),
+            Value::NegInfinity => float(&mut out, f64::NEG_INFINITY),
+            Value::NegZero => float(&mut out, -0.0),
+            Value::String(s) => text(&mut out, s),
+            Value::Bytes(bytes) => {
+                header(&mut out, BYTES, bytes.len() as u64);
+                out.extend_from_slice(bytes);
+            }
+            Value::Array(items) => {
+                header(&mut out, ARRAY, items.len() as u64);
+                stack.extend(items.iter().rev().map(Item::Value));
+            }
+            Value::Set(items) => {
+                header(&mut out, TAG, SET);
+                header(&mut out, ARRAY, items.len() as u64);
+                stack.extend(items.iter().rev().map(Item::Value));
+            }
+            Value::Object(map) => {
+                header(&mut out, MAP, map.len() as u64);
+                for (k, v) in map.iter().rev() {
+                    stack.push(Item::Value(v));
+                    stack.push(Item::Key(k));
+                }
+            }
+            Value::Map(entries) => {
+                header(&mut out, TAG, JS_MAP);
+                header(&mut out, MAP, entries.len() as u64);
+                for (k, v) in entries.iter().rev() {
+                    stack.push(Item::Value(v));
+                    stack.push(Item::Value(k));
+                }
+            }
+
+            #[cfg(feature = "chrono")]
+            Value::Date(dt) => {
+                header(&mut out, TAG, DATE_EPOCH);
+                let millis = dt.timestamp_millis();
+                if millis % 1000 == 0 {
+                    let seconds = millis / 1000;
+                    match u64::try_from(seconds) {
+                        Ok(seconds) => header(&mut out, UNSIGNED, seconds),
+                        Err(_) => header(&mut out, NEGATIVE, !seconds as u64),
+                    }
+                } else {
+                    float(&mut out, millis as f64 / 1000.0);
+                }
+            }
+            #[cfg(feature = "chrono")]
+            Value::OffsetDate(dt) => {
+                header(&mut out, TAG, DATE_TEXT);
+                text(&mut out, &dt.to_rfc3339_opts(SecondsFormat::Millis, true));
+            }
+            #[cfg(feature = "chrono")]
+            Value::InvalidDate => {
+                header(&mut out, TAG, DATE_EPOCH);
+                float(&mut out, f64::NAN);
+            }
+            #[cfg(feature = "bigint")]
+            Value::BigInt(n) => {
+                let (tag, magnitude) = if n.sign() == Sign::Minus {
+                    (NEGATIVE_BIGNUM, -n - 1u8)
+                } else {
+                    (POSITIVE_BIGNUM, n.clone())
+                };
+                let (_, bytes) = magnitude.to_bytes_be();
+                header(&mut out, TAG, tag);
+                header(&mut out, BYTES, bytes.len() as u64);
+                out.extend_from_slice(&bytes);
+            }
+            Value::Url(url) => {
+                header(&mut out, TAG, URI);
+                text(&mut out, url.as_str());
+            }
+            Value::RegExp { source, flags } => {
+                header(&mut out, TAG, REGEXP);
+                header(&mut out, ARRAY, 2);
+                text(&mut out, source);
+                text(&mut out, flags);
+            }
+
+            Value::Error { .. }
+            | Value::Class { .. }
+            | Value::Custom { .. }
+            | Value::Symbol(_)
+            | Value::Unknown { .. } => {
+                return Err(Error::TypeMismatch {
+                    path: String::new(),
+                    expected: "a value CBOR can encode".to_string(),
+                    actual: value.type_name().to_string(),
+                });
+            }
+        }
+    }
+    Ok(out)
+}
+
+/// What is left to encode: a value, or the key of an object entry.
+enum Item<'a> {
+    Value(&'a Value),
+    Key(&'a str),
+}
+
+/// Write the head of an item of type `major` whose argument is `n`.
+fn header(out: &mut Vec<u8>, major: u8, n: u64) {
+    let major = major << 5;
+    if n < 24 {
+        out.push(major | n as u8);
+    } else if let Ok(n) = u8::try_from(n) {
+        out.extend_from_slice(&[major | 24, n]);
+    } else if let Ok(n) = u16::try_from(n) {
+        out.push(major | 25);
+        out.extend_from_slice(&n.to_be_bytes());
+    } else if let Ok(n) = u32::try_from(n) {
+        out.push(major | 26);
+        out.extend_from_slice(&n.to_be_bytes());
+    } else {
+        out.push(major | 27);
+        out.extend_from_slice(&n.to_be_bytes());
+    }
+}
+
+fn text(out: &mut Vec<u8>, s: &str) {
+    header(out, TEXT, s.len() as u64);
+    out.extend_from_slice(s.as_bytes());
+}
+
+fn float(out: &mut Vec<u8>, n: f64) {
+    out.push(FLOAT64);
+    out.extend_from_slice(&n.to_bits().to_be_bytes());
+}
+
+/// Decode a value from CBOR.
+pub fn from_slice(bytes: &[u8]) -> Result<Value> {
+    from_slice_with(bytes, &ParseOptions::default())
+}
+
+/// Decode a value from CBOR, enforcing the limits in `options`.
+///
+/// # Errors
+/// Returns [`Error::InvalidCbor`] for malformed input, a tag this module
+/// does not read, or bytes left over after the value.
+pub fn from_slice_with(bytes: &[u8], options: &ParseOptions) -> Result<Value> {
+    let mut decoder = Decoder { bytes, pos: 0 };
+    let value = decoder.decode(options)?;
+    if decoder.pos < bytes.len() {
+        return Err(invalid(format!(
+            "{} bytes left over after the value",
+            bytes.len() - decoder.pos
+        )));
+    }
+    Ok(value)
+}
+
+fn invalid(reason: String) -> Error {
+    Error::InvalidCbor(reason)
+}
+
+struct Decoder<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+/// A container or tag whose contents are being decoded.
+enum Frame {
+    Array {
+        items: Vec<Value>,
+        /// Items still to come, or `None` until a break.
+        remaining: Option<u64>,
+    },
+    Map {
+        entries: Vec<(Value, Value)>,
+        key: Option<Value>,
+        remaining: Option<u64>,
+    },
+    Tag(u64),
+}
+
+impl Decoder<'_> {
+    fn decode(&mut self, options: &ParseOptions) -> Result<Value> {
+        let mut stack: Vec<Frame> = Vec::new();
+
+        loop {
+            let Some(mut value) = self.begin(&mut stack, options)? else {
+                continue;
+            };
+            // Hand the value to the frames it completes, innermost first.
+            loop {
+                match stack.last_mut() {
+                    None => return Ok(value),
+                    Some(Frame::Tag(tag)) => {
+                        let tag = *tag;
+                        stack.pop();
+                        value = tagged(tag, value, options)?;
+                    }
+                    Some(Frame::Array { items, remaining }) => {
+                        items.push(value);
+                        if *remaining != Some(items.len() as u64) {
+                            break;
+                        }
+                        value = finish(&mut stack);
+                    }
+                    Some(Frame::Map {
+                        entries,
+                        key,
+                        remaining,
+                    }) => {
+                        match key.take() {
+                            None => {
+                                *key = Some(value);
+                                break;
+                            }
+                            Some(key) => entries.push((key, value)),
+                        }
+                        if *remaining != Some(entries.len() as u64) {
+                            break;
+                        }
+                        value = finish(&mut stack);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Read the next item: a finished value, or the start of a container or
+    /// tag, which is pushed onto `stack`.
+    fn begin(&mut self, stack: &mut Vec<Frame>, options: &ParseOptions) -> Result<Option<Value>> {
+        let initial = self.byte()?;
+        let (major, info) = (initial >> 5, initial & 0x1f);
+
+        if initial == BREAK {
+            return match stack.last() {
+                Some(Frame::Array {
+                    remaining: None, ..
+                })
+                | Some(Frame::Map {
+                    remaining: None,
+                    key: None,
+                    ..
+                }) => Ok(Some(finish(stack))),
+                _ => Err(invalid("unexpected break".to_string())),
+            };
+        }
+        if major == SIMPLE {
+            return self.simple(info).map(Some);
+        }
+
+        let argument = self.argument(info)?;
+        let value = match (major, argument) {
+            (UNSIGNED, Some(n)) => match i64::try_from(n) {
+                Ok(n) => Value::Int(n),
+                Err(_) => Value::UInt(n),
+            },
+            (NEGATIVE, Some(n)) => match i64::try_from(n) {
+                Ok(n) => Value::Int(!n),
+                Err(_) => negative_bignum(&n.to_be_bytes())?,
+            },
+            (BYTES, _) => Value::Bytes(self.string(BYTES, argument)?),
+            (TEXT, _) => Value::String(
+                String::from_utf8(self.string(TEXT, argument)?)
+                    .map_err(|e| invalid(format!("invalid UTF-8 in text: {e}")))?,
+            ),
+            (ARRAY | MAP | TAG, _) => {
+                if stack.len() >= options.max_depth {
+                    return Err(Error::DepthLimitExceeded(options.max_depth));
+                }
+                // Never reserve more than the input could hold.
+                let capacity = argument.unwrap_or(0).min(self.left() as u64) as usize;
+                stack.push(match major {
+                    ARRAY => Frame::Array {
+                        items: Vec::with_capacity(capacity),
+                        remaining: argument,
+                    },
+                    MAP => Frame::Map {
+                        entries: Vec::with_capacity(capacity / 2),
+                        key: None,
+                        remaining: argument,
+                    },
+                    _ => match argument {
+                        Some(SELF_DESCRIBED) => return Ok(None),
+                        Some(tag) => Frame::Tag(tag),
+                        None => return Err(invalid("indefinite-length tag".to_string())),
+                    },
+                });
+                if argument == Some(0) && major != TAG {
+                    return Ok(Some(finish(stack)));
+                }
+                return Ok(None);
+            }
+            _ => {
+                return Err(invalid(format!(
+                    "indefinite-length integer of type {major}"
+                )));
+            }
+        };
+        Ok(Some(value))
+    }
+
+    fn simple(&mut self, info: u8) -> Result<Value> {
+        let float = match info {
+            20 => return Ok(Value::Bool(false)),
+            21 => return Ok(Value::Bool(true)),
+            22 => return Ok(Value::Null),
+            23 => return Ok(Value::Undefined),
+            25 => half(u16::from_be_bytes(self.array()?)),
+            26 => f64::from(f32::from_be_bytes(self.array()?)),
+            27 => f64::from_be_bytes(self.array()?),
+            other => return Err(invalid(format!("unsupported simple value {other}"))),
+        };
+        Ok(Value::from(float))
+    }
+
+    /// The argument of an item's head, or `None` for an indefinite length.
+    fn argument(&mut self, info: u8) -> Result<Option<u64>> {
+        Ok(Some(match info {
+            0..=23 => u64::from(info),
+            24 => u64::from(self.byte()?),
+            25 => u64::from(u16::from_be_bytes(self.array()?)),
+            26 => u64::from(u32::from_be_bytes(self.array()?)),
+            27 => u64::from_be_bytes(self.array()?),
+            31 => return Ok(None),
+            other => return Err(invalid(format!("reserved additional information {other}"))),
+        }))
+    }
+
+    /// The contents of a byte or text string, joining the chunks of an
+    /// indefinite-length one.
+    fn string(&mut self, major: u8, length: Option<u64>) -> Result<Vec<u8>> {
+        if let Some(length) = length {
+            return Ok(self.take(length)?.to_vec());
+        }
+        let mut out = Vec::new();
+        loop {
+            let initial = self.byte()?;
+            if initial == BREAK {
+                return Ok(out);
+            }
+            match self.argument(initial & 0x1f)? {
+                Some(length) if initial >> 5 == major => out.extend_from_slice(self.take(length)?),
+                _ => {
+                    return Err(invalid(
+                        "invalid chunk in an indefinite-length string".into(),
+                    ));
+                }
+            }
+        }
+    }
+
+    fn left(&self) -> usize {
+        self.bytes.len() - self.pos
+    }
+
+    fn take(&mut self, n: u64) -> Result<&[u8]> {
+        let n = usize::try_from(n)
+            .ok()
+            .filter(|&n| n <= self.left())
+            .ok_or_else(|| invalid("unexpected end of input".to_string()))?;
+        let bytes = &self.bytes[self.pos..self.pos + n];
+        self.pos += n;
+        Ok(bytes)
+    }
+
+    fn byte(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N]> {
+        Ok(self.take(N as u64)?.try_into().expect("took N bytes"))
+    }
+}
+
+/// Pop the container on top of `stack` and build its value.
+fn finish(stack: &mut Vec<Frame>) -> Value {
+    match stack.pop() {
+        Some(Frame::Array { items, .. }) => Value::Array(items),
+        Some(Frame::Map { entries, .. }) => {
+            let keyed_by_text = entries.iter().all(|(k, _)| matches!(k, Value::String(_)));
+            if keyed_by_text && !matches!(stack.last(), Some(Frame::Tag(JS_MAP))) {
+                Value::Object(
+                    entries
+                        .into_iter()
+                        .map(|(k, v)| match k {
+                            Value::String(k) => (k, v),
+                            _ => unreachable!("all keys are text"),
+                        })
+                        .collect::<Map<_, _>>(),
+                )
+            } else {
+                Value::Map(entries)
+            }
+        }
+        _ => unreachable!("only containers are finished"),
+    }
+}
+
+/// Widen an IEEE 754 half-precision float.
+fn half(bits: u16) -> f64 {
+    let sign = if bits & 0x8000 != 0 { -1.0 } else { 1.0 };
+    let exponent = i32::from((bits >> 10) & 0x1f);
+    let mantissa = f64::from(bits & 0x3ff);
+    sign * match exponent {
+        0 => mantissa * 2f64.powi(-24),
+        31 if mantissa == 0.0 => f64::INFINITY,
+        31 => f64::NAN,
+        _ => (1.0 + mantissa / 1024.0) * 2f64.powi(exponent - 15),
+    }
+}
+
+/// Apply the meaning of `tag` to its content.
+fn tagged(tag: u64, content: Value, options: &ParseOptions) -> Result<Value> {
+    let mismatch = |content: Value| {
+        invalid(format!(
+            "tag {tag} does not apply to {}",
+            content.type_name()
+        ))
+    };
+    match (tag, content) {
+        (DATE_TEXT, Value::String(s)) => restore("Date", serde_json::json!(s), options),
+        (DATE_EPOCH, seconds @ (Value::Int(_) | Value::UInt(_) | Value::Number(_))) => {
+            let millis = (seconds.as_f64().unwrap_or(f64::NAN) * 1000.0).round();
+            restore("Date", serde_json::json!(millis), options)
+        }
+        (DATE_EPOCH, Value::NaN | Value::PosInfinity | Value::NegInfinity) => {
+            restore("Date", serde_json::json!("Invalid Date"), options)
+        }
+        (DATE_EPOCH, Value::NegZero) => restore("Date", serde_json::json!(0), options),
+        (POSITIVE_BIGNUM, Value::Bytes(bytes)) => positive_bignum(&bytes),
+        (NEGATIVE_BIGNUM, Value::Bytes(bytes)) => negative_bignum(&bytes),
+        (URI, Value::String(s)) => restore("URL", serde_json::json!(s), options),
+        (SET, Value::Array(items)) => Ok(Value::Set(items)),
+        (JS_MAP, map @ Value::Map(_)) => Ok(map),
+        (REGEXP, Value::Array(parts)) => match <[Value; 2]>::try_from(parts) {
+            Ok([Value::String(source), Value::String(flags)]) => {
+                Ok(Value::RegExp { source, flags })
+            }
+            Ok(parts) => Err(mismatch(Value::Array(parts.into()))),
+            // The flags may be left out.
+            Err(parts) => match <[Value; 1]>::try_from(parts) {
+                Ok([Value::String(source)]) => Ok(Value::RegExp {
+                    source,
+                    flags: String::new(),
+                }),
+                Ok(parts) => Err(mismatch(Value::Array(parts.into()))),
+                Err(parts) => Err(mismatch(Value::Array(parts))),
+            },
+        },
+        (
+            DATE_TEXT | DATE_EPOCH | POSITIVE_BIGNUM | NEGATIVE_BIGNUM | URI | SET | JS_MAP
+            | REGEXP,
+            content,
+        ) => Err(mismatch(content)),
+        (tag, _) => Err(invalid(format!("unsupported tag {tag}"))),
+    }
+}
+
+#[cfg(feature = "bigint")]
+fn positive_bignum(bytes: &[u8]) -> Result<Value> {
+    Ok(Value::BigInt(BigInt::from_bytes_be(Sign::Plus, bytes)))
+}
+
+/// The bignum `-1 - n` for the big-endian magnitude `n`.
+#[cfg(feature = "bigint")]
+fn negative_bignum(bytes: &[u8]) -> Result<Value> {
+    Ok(Value::BigInt(
+        -BigInt::from_bytes_be(Sign::Plus, bytes) - 1u8,
+    ))
+}
+
+#[cfg(not(feature = "bigint"))]
+fn positive_bignum(_: &[u8]) -> Result<Value> {
+    Err(Error::UnsupportedType {
+        name: "bigint".to_string(),
+        feature: "bigint",
+    })
+}
+
+#[cfg(not(feature = "bigint"))]
+fn negative_bignum(bytes: &[u8]) -> Result<Value> {
+    positive_bignum(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encoding() {
+        let cases: Vec<(Value, &[u8])> = vec![
+            (Value::Undefined, &[0xf7]),
+            (Value::from(23), &[0x17]),
+            (Value::from(-500), &[0x39, 0x01, 0xf3]),
+            (
+                Value::UInt(u64::MAX),
+                &[0x1b, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff],
+            ),
+            (Value::from("é"), &[0x62, 0xc3, 0xa9]),
+            (Value::Bytes(vec![1, 2]), &[0x42, 1, 2]),
+            (
+                Value::Map(vec![(Value::from(1), Value::Null)]),
+                &[0xd9, 0x01, 0x03, 0xa1, 0x01, 0xf6],
+            ),
+            (
+                Value::RegExp {
+                    source: "a".into(),
+                    flags: "".into(),
+                },
+                &[0xd9, 0x52, 0x4a, 0x82, 0x61, b'a', 0x60],
+            ),
+        ];
+        for (value, bytes) in cases {
+            assert_eq!(to_vec(&value).unwrap(), bytes, "{value:?}");
+            assert_eq!(from_slice(bytes).unwrap(), value);
+        }
+        assert!(matches!(
+            to_vec(&Value::Symbol("s".into())),
+            Err(Error::TypeMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let mut map = Map::new();
+        map.insert("z".to_string(), Value::NegZero);
+        map.insert("inf".to_string(), Value::NegInfinity);
+        map.insert("half".to_string(), Value::Number(0.5));
+        map.insert(
+            "url".to_string(),
+            Value::Url("https://example.com/".parse().unwrap()),
+        );
+        map.insert(
+            "nested".to_string(),
+            Value::Array(vec![Value::Set(vec![]), Value::Object(Map::new())]),
+        );
+        let value = Value::Object(map);
+        assert_eq!(from_slice(&to_vec(&value).unwrap()).unwrap(), value);
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn test_dates() {
+        use chrono::DateTime;
+
+        let whole = Value::Date(DateTime::from_timestamp_millis(-2000).unwrap());
+        assert_eq!(to_vec(&whole).unwrap(), [0xc1, 0x21]);
+        let fraction = Value::Date(DateTime::from_timestamp_millis(1_700_000_000_123).unwrap());
+        assert_eq!(from_slice(&to_vec(&fraction).unwrap()).unwrap(), fraction);
+
+        let offset =
+            Value::OffsetDate(DateTime::parse_from_rfc3339("2024-01-01T09:00:00+09:00").unwrap());
+        let bytes = to_vec(&offset).unwrap();
+        assert_eq!(bytes[0], 0xc0);
+        let options = ParseOptions {
+            preserve_date_offsets: true,
+            ..ParseOptions::default()
+        };
+        assert_eq!(from_slice_with(&bytes, &options).unwrap(), offset);
+
+        let invalid = to_vec(&Value::InvalidDate).unwrap();
+        assert!(matches!(from_slice(&invalid), Err(Error::InvalidDate(_))));
+    }
+
+    #[cfg(feature = "bigint")]
+    #[test]
+    fn test_bignums() {
+        for n in ["0", "18446744073709551616", "-1", "-18446744073709551617"] {
+            let value = Value::BigInt(n.parse().unwrap());
+            assert_eq!(from_slice(&to_vec(&value).unwrap()).unwrap(), value, "{n}");
+        }
+        // -2^64, beyond an i64, as a plain negative integer.
+        assert_eq!(
+            from_slice(&[0x3b, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]).unwrap(),
+            Value::BigInt("-18446744073709551616".parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn test_decoding() {
+        // Indefinite-length array and text, a half float, and the
+        // self-described CBOR tag.
+        let bytes = [
+            0xd9, 0xd9, 0xf7, 0x9f, 0x7f, 0x61, b'a', 0x61, b'b', 0xff, 0xf9, 0x3c, 0x00, 0xff,
+        ];
+        assert_eq!(
+            from_slice(&bytes).unwrap(),
+            Value::Array(vec![Value::from("ab"), Value::Number(1.0)])
+        );
+        // A map with a non-text key.
+        assert_eq!(
+            from_slice(&[0xa1, 0x01, 0x02]).unwrap(),
+            Value::Map(vec![(Value::from(1), Value::from(2))])
+        );
+
+        for bytes in [
+            &[0x82, 0x01][..],
+            &[0x01, 0x02],
+            &[0xff],
+            &[0x1c],
+            &[0xc6, 0x01],
+            &[0xc2, 0x01],
+            &[0x9b, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff],
+            &[0x62, 0xff, 0xfe],
+        ] {
+            assert!(
+                matches!(from_slice(bytes), Err(Error::InvalidCbor(_))),
+                "{bytes:?}"
+            );
+        }
+
+        let options = ParseOptions {
+            max_depth: 2,
+            ..ParseOptions::default()
+        };
+        assert!(matches!(
+            from_slice_with(&[0x81, 0x81, 0x81, 0x01], &options),
+            Err(Error::DepthLimitExceeded(2))
+        ));
+    }
+}
@@ -0,0 +1,735 @@
+//! The [devalue](https://github.com/Rich-Harris/devalue) encoding, as used
+//! by SvelteKit.
+//!
+//! devalue writes a value as a flat JSON array: the root is entry `0`, and
+//! arrays, objects and the payloads of other types refer to their contents
+//! by index. `undefined`, `NaN`, the infinities and `-0` are negative
+//! indices, and a payload that is one of them alone is just that number.
+//!
+//! | Value | Entry |
+//! |-------|-------|
+//! | `null`, boolean, number, string | the JSON value, shared by equal values |
+//! | array | `[i, ...]` |
+//! | object | `{"key": i, ...}` |
+//! | Set | `["Set", i, ...]` |
+//! | Map | `["Map", key, value, ...]` |
+//! | Date | `["Date", "1970-01-01T00:00:00.000Z"]`, or `""` if invalid |
+//! | bigint | `["BigInt", "1"]` |
+//! | RegExp | `["RegExp", "source", "flags"]` |
+//! | URL | `["URL", "https://..."]` |
+//! | bytes | `["Uint8Array", i]`, its buffer being `["ArrayBuffer", "base64"]` |
+//! | custom | `["name", i]`, as a devalue reducer named `name` writes it |
+//!
+//! Errors, class instances, symbols and values of unknown types have no
+//! devalue encoding. Other typed arrays are not read.
+//!
+//! An entry referred to more than once is restored as a copy at each place,
+//! within [`ParseOptions::max_shared_values`].
+//!
+//! # Examples
+//! ```
+//! use superjson_rs::format::devalue;
+//! use superjson_rs::{Value, parse};
+//!
+//! let value = parse(r#"{"json":{"n":"NaN","tags":["a"]},
+//!                       "meta":{"values":{"n":["number"],"tags":["set"]}}}"#).unwrap();
+//! let text = devalue::stringify(&value).unwrap();
+//! assert_eq!(text, r#"[{"n":-3,"tags":1},["Set",2],"a"]"#);
+//! assert_eq!(devalue::parse(&text).unwrap(), value);
+//! ```
+
+use std::collections::HashMap;
+
+#[cfg(feature = "chrono")]
+use chrono::{SecondsFormat, Utc};
+
+use super::restore;
+use crate::bytes::BytesEncoding;
+use crate::deserialize::count_values;
+use crate::value::Map;
+use crate::{Error, ParseOptions, Result, Value};
+
+const UNDEFINED: i64 = -1;
+const HOLE: i64 = -2;
+const NAN: i64 = -3;
+const POSITIVE_INFINITY: i64 = -4;
+const NEGATIVE_INFINITY: i64 = -5;
+const NEGATIVE_ZERO: i64 = -6;
+
+/// Write `value` in the devalue encoding.
+///
+/// Like devalue's own output, the text is safe to embed in a `<script>`
+/// element.
+///
+/// # Errors
+/// Returns [`Error::TypeMismatch`] for a value devalue cannot encode.
+pub fn stringify(value: &Value) -> Result<String> {
+    let mut flattener = Flattener {
+        entries: Vec::new(),
+        primitives: HashMap::new(),
+    };
+    let root = flattener.flatten(value)?;
+    if root < 0 {
+        return Ok(root.to_string());
+    }
+    let text = serde_json::to_string(&flattener.entries)?;
+    // `<` only occurs within strings, where an escape means the same.
+    Ok(text
+        .replace('<', "\\u003C")
+        .replace('\u{2028}', "\\u2028")
+        .replace('\u{2029}', "\\u2029"))
+}
+
+/// Read a value in the devalue encoding.
+pub fn parse(s: &str) -> Result<Value> {
+    parse_with(s, &ParseOptions::default())
+}
+
+/// Read a value in the devalue encoding, enforcing the limits in `options`.
+///
+/// Nesting is limited by `max_depth`, and copies of entries referred to more
+/// than once by `max_shared_values`. Dates follow `invalid_dates`.
+pub fn parse_with(s: &str, options: &ParseOptions) -> Result<Value> {
+    let entries = match serde_json::from_str(s)? {
+        serde_json::Value::Array(entries) if !entries.is_empty() => entries,
+        serde_json::Value::Number(n) => {
+            return n
+                .as_i64()
+                .filter(|&index| index < 0)
+                .and_then(constant)
+                .ok_or_else(|| invalid(format!("{n} is not a constant")));
+        }
+        other => {
+            return Err(invalid(format!(
+                "expected an array of entries, got {other}"
+            )));
+        }
+    };
+    let entries = entries
+        .into_iter()
+        .map(|entry| decode(entry, options))
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut references = vec![0u32; entries.len()];
+    for entry in &entries {
+        for &child in &entry.children {
+            if let Some(count) = usize::try_from(child)
+                .ok()
+                .and_then(|i| references.get_mut(i))
+            {
+                *count = count.saturating_add(1);
+            }
+        }
+    }
+    Hydrator {
+        shared: vec![None; entries.len()],
+        on_stack: vec![false; entries.len()],
+        entries,
+        references,
+        copied: 0,
+        options,
+    }
+    .hydrate()
+}
+
+fn invalid(reason: String) -> Error {
+    Error::InvalidDevalue(reason)
+}
+
+/// The value of a negative index.
+fn constant(index: i64) -> Option<Value> {
+    match index {
+        // Holes in sparse arrays read as `undefined`, as they do in
+        // JavaScript.
+        UNDEFINED | HOLE => Some(Value::Undefined),
+        NAN => Some(Value::NaN),
+        POSITIVE_INFINITY => Some(Value::PosInfinity),
+        NEGATIVE_INFINITY => Some(Value::NegInfinity),
+        NEGATIVE_ZERO => Some(Value::NegZero),
+        _ => None,
+    }
+}
+
+/// Assigns entries to values in the order devalue does: each value before
+/// its contents.
+struct Flattener {
+    entries: Vec<serde_json::Value>,
+    /// The entries of primitives, which devalue shares between equal values.
+    primitives: HashMap<String, usize>,
+}
+
+/// A container whose contents are being flattened.
+struct FlattenFrame<'a> {
+    index: usize,
+    value: &'a Value,
+    children: Vec<&'a Value>,
+    indices: Vec<i64>,
+}
+
+impl Flattener {
+    /// Flatten `value`, returning its index.
+    fn flatten(&mut self, value: &Value) -> Result<i64> {
+        let mut stack: Vec<FlattenFrame> = Vec::new();
+        let mut next = self.begin(value, &mut stack)?;
+
+        loop {
+            if let Some(index) = next {
+                match stack.last_mut() {
+                    None => return Ok(index),
+                    Some(frame) => frame.indices.push(index),
+                }
+            }
+            let frame = stack.last().expect("stack is non-empty");
+            next = match frame.children.get(frame.indices.len()) {
+                Some(&child) => self.begin(child, &mut stack)?,
+                None => {
+                    let frame = stack.pop().expect("stack is non-empty");
+                    let index = frame.index;
+                    self.entries[index] = finish(frame);
+                    Some(index as i64)
+                }
+            };
+        }
+    }
+
+    /// Write a value that has no contents and return its index, or reserve
+    /// an index for a container and open a frame for it.
+    fn begin<'a>(
+        &mut self,
+        value: &'a Value,
+        stack: &mut Vec<FlattenFrame<'a>>,
+    ) -> Result<Option<i64>> {
+        let entry = match value {
+            Value::Undefined => return Ok(Some(UNDEFINED)),
+            Value::NaN => return Ok(Some(NAN)),
+            Value::PosInfinity => return Ok(Some(POSITIVE_INFINITY)),
+            Value::NegInfinity => return Ok(Some(NEGATIVE_INFINITY)),
+            Value::NegZero => return Ok(Some(NEGATIVE_ZERO)),
+            Value::Number(n) if n.is_nan() => return Ok(Some(NAN)),
+            Value::Number(n) if n.is_infinite() => {
+                return Ok(Some(if *n > 0.0 {
+                    POSITIVE_INFINITY
+                } else {
+                    NEGATIVE_INFINITY
+                }));
+            }
+            Value::Number(n) if *n == 0.0 && n.is_sign_negative() => {
+                return Ok(Some(NEGATIVE_ZERO));
+            }
+
+            Value::Null => return Ok(Some(self.primitive(serde_json::Value::Null))),
+            Value::Bool(b) => return Ok(Some(self.primitive(serde_json::json!(b)))),
+            Value::Number(n) => return Ok(Some(self.primitive(serde_json::json!(n)))),
+            Value::Int(n) => return Ok(Some(self.primitive(serde_json::json!(n)))),
+            Value::UInt(n) => return Ok(Some(self.primitive(serde_json::json!(n)))),
+            Value::String(s) => return Ok(Some(self.primitive(serde_json::json!(s)))),
+
+            Value::Array(items) | Value::Set(items) => {
+                return Ok(self.open(value, items.iter().collect(), stack));
+            }
+            Value::Object(map) => return Ok(self.open(value, map.values().collect(), stack)),
+            Value::Map(entries) => {
+                let children = entries.iter().flat_map(|(k, v)| [k, v]).collect();
+                return Ok(self.open(value, children, stack));
+            }
+            Value::Custom { value: payload, .. } => {
+                return Ok(self.open(value, vec![payload.as_ref()], stack));
+            }
+
+            #[cfg(feature = "chrono")]
+            Value::Date(dt) => {
+                serde_json::json!(["Date", dt.to_rfc3339_opts(SecondsFormat::Millis, true)])
+            }
+            #[cfg(feature = "chrono")]
+            Value::OffsetDate(dt) => serde_json::json!([
+                "Date",
+                dt.with_timezone(&Utc)
+                    .to_rfc3339_opts(SecondsFormat::Millis, true)
+            ]),
+            #[cfg(feature = "chrono")]
+            Value::InvalidDate => serde_json::json!(["Date", ""]),
+            #[cfg(feature = "bigint")]
+            Value::BigInt(n) => serde_json::json!(["BigInt", n.to_string()]),
+            Value::RegExp { source, flags } if flags.is_empty() => {
+                serde_json::json!(["RegExp", source])
+            }
+            Value::RegExp { source, flags } => serde_json::json!(["RegExp", source, flags]),
+            Value::Url(url) => serde_json::json!(["URL", url.as_str()]),
+            Value::Bytes(bytes) => {
+                let index = self.entries.len();
+                self.entries
+                    .push(serde_json::json!(["Uint8Array", index + 1]));
+                self.entries.push(serde_json::json!([
+                    "ArrayBuffer",
+                    BytesEncoding::Base64.encode(bytes)
+                ]));
+                return Ok(Some(index as i64));
+            }
+
+            Value::Error { .. }
+            | Value::Class { .. }
+            | Value::Symbol(_)
+            | Value::Unknown { .. } => {
+                return Err(Error::TypeMismatch {
+                    path: String::new(),
+                    expected: "a value devalue can encode".to_string(),
+                    actual: value.type_name().to_string(),
+                });
+            }
+        };
+        self.entries.push(entry);
+        Ok(Some(self.entries.len() as i64 - 1))
+    }
+
+    /// The index of the primitive `json`, shared with any equal one.
+    fn primitive(&mut self, json: serde_json::Value) -> i64 {
+        // Integral numbers are one JavaScript number however they are held.
+        let key = match json.as_f64() {
+            Some(n) if n.fract() == 0.0 && n.abs() < 2f64.powi(53) => (n as i64).to_string(),
+            _ => json.to_string(),
+        };
+        let index = *self.primitives.entry(key).or_insert_with(|| {
+            self.entries.push(json);
+            self.entries.len() - 1
+        });
+        index as i64
+    }
+
+    /// Reserve an index for the container `value` and open a frame for its
+    /// `children`.
+    fn open<'a>(
+        &mut self,
+        value: &'a Value,
+        children: Vec<&'a Value>,
+        stack: &mut Vec<FlattenFrame<'a>>,
+    ) -> Option<i64> {
+        let index = self.entries.len();
+        self.entries.push(serde_json::Value::Null);
+        stack.push(FlattenFrame {
+            index,
+            value,
+            indices: Vec::with_capacity(children.len()),
+            children,
+        });
+        None
+    }
+}
+
+/// The entry of a container whose contents have been flattened.
+fn finish(frame: FlattenFrame) -> serde_json::Value {
+    let FlattenFrame { value, indices, .. } = frame;
+    let tagged = |tag: &str| {
+        std::iter::once(serde_json::json!(tag))
+            .chain(indices.iter().map(|&i| serde_json::json!(i)))
+            .collect()
+    };
+    match value {
+        Value::Array(_) => serde_json::json!(indices),
+        Value::Object(map) => serde_json::Value::Object(
+            map.keys()
+                .cloned()
+                .zip(indices.iter().map(|&i| serde_json::json!(i)))
+                .collect(),
+        ),
+        Value::Set(_) => serde_json::Value::Array(tagged("Set")),
+        Value::Map(_) => serde_json::Value::Array(tagged("Map")),
+        Value::Custom { name, .. } => serde_json::Value::Array(tagged(name)),
+        _ => unreachable!("only containers open a frame"),
+    }
+}
+
+/// An entry read from the payload: a finished value, or a container and the
+/// indices of its contents.
+struct Entry {
+    kind: Kind,
+    children: Vec<i64>,
+}
+
+enum Kind {
+    Leaf(Value),
+    Array,
+    Object(Vec<String>),
+    Set,
+    Map,
+    Custom(String),
+    /// A `Uint8Array` over the `ArrayBuffer` that is its only child.
+    Bytes,
+    /// A boxed primitive, `Object(value)`, restored as the primitive.
+    Boxed,
+}
+
+/// Read one entry of the payload.
+fn decode(entry: serde_json::Value, options: &ParseOptions) -> Result<Entry> {
+    let leaf = |value| {
+        Ok(Entry {
+            kind: Kind::Leaf(value),
+            children: Vec::new(),
+        })
+    };
+    let index = |json: &serde_json::Value| {
+        json.as_i64()
+            .ok_or_else(|| invalid(format!("expected an index, got {json}")))
+    };
+    let indices = |items: &[serde_json::Value]| items.iter().map(index).collect::<Result<Vec<_>>>();
+
+    let mut items = match entry {
+        serde_json::Value::Array(items) => items,
+        serde_json::Value::Object(map) => {
+            let mut keys = Vec::with_capacity(map.len());
+            let mut children = Vec::with_capacity(map.len());
+            for (key, child) in map {
+                children.push(index(&child)?);
+                keys.push(key);
+            }
+            return Ok(Entry {
+                kind: Kind::Object(keys),
+                children,
+            });
+        }
+        primitive => return leaf(Value::from_json_checked(primitive)?),
+    };
+    let Some(serde_json::Value::String(tag)) = items.first() else {
+        return Ok(Entry {
+            kind: Kind::Array,
+            children: indices(&items)?,
+        });
+    };
+
+    let text = |items: &[serde_json::Value], i: usize| match items.get(i) {
+        Some(serde_json::Value::String(s)) => Ok(s.clone()),
+        other => Err(invalid(format!(
+            "expected a string in {}, got {other:?}",
+            items[0]
+        ))),
+    };
+    match tag.as_str() {
+        "Set" => Ok(Entry {
+            kind: Kind::Set,
+            children: indices(&items[1..])?,
+        }),
+        "Map" if items.len() % 2 == 1 => Ok(Entry {
+            kind: Kind::Map,
+            children: indices(&items[1..])?,
+        }),
+        "Object" if items.len() == 2 => Ok(Entry {
+            kind: Kind::Boxed,
+            children: vec![index(&items[1])?],
+        }),
+        "null" if items.len() % 2 == 1 => {
+            let mut keys = Vec::with_capacity(items.len() / 2);
+            let mut children = Vec::with_capacity(items.len() / 2);
+            for pair in items[1..].chunks(2) {
+                keys.push(text(pair, 0)?);
+                children.push(index(&pair[1])?);
+            }
+            Ok(Entry {
+                kind: Kind::Object(keys),
+                children,
+            })
+        }
+        "Date" => {
+            // devalue writes an invalid Date as "", superjson as "Invalid Date".
+            let date = match text(&items, 1)? {
+                s if s.is_empty() => "Invalid Date".to_string(),
+                s => s,
+            };
+            leaf(restore("Date", serde_json::json!(date), options)?)
+        }
+        "BigInt" => leaf(restore("bigint", items.swap_remove(1), options)?),
+        "URL" => leaf(restore("URL", items.swap_remove(1), options)?),
+        "RegExp" => leaf(Value::RegExp {
+            source: text(&items, 1)?,
+            flags: if items.len() > 2 {
+                text(&items, 2)?
+            } else {
+                String::new()
+            },
+        }),
+        "ArrayBuffer" => {
+            let base64 = text(&items, 1)?;
+            let bytes = BytesEncoding::Base64
+                .decode(&base64)
+                .ok_or_else(|| invalid(format!("invalid base64 {base64}")))?;
+            leaf(Value::Bytes(bytes))
+        }
+        "Uint8Array" => match items.get(1) {
+            // Older releases of devalue wrote the buffer inline.
+            Some(serde_json::Value::String(base64)) => {
+                let bytes = BytesEncoding::Base64
+                    .decode(base64)
+                    .ok_or_else(|| invalid(format!("invalid base64 {base64}")))?;
+                leaf(Value::Bytes(bytes))
+            }
+            _ => Ok(Entry {
+                kind: Kind::Bytes,
+                children: vec![index(items.get(1).unwrap_or(&serde_json::Value::Null))?],
+            }),
+        },
+        _ if items.len() == 2 && items[1].is_i64() => Ok(Entry {
+            kind: Kind::Custom(tag.clone()),
+            children: vec![index(&items[1])?],
+        }),
+        _ => Err(invalid(format!(
+            "unsupported entry {}",
+            serde_json::Value::Array(items)
+        ))),
+    }
+}
+
+/// Rebuilds the tree of values from the decoded entries.
+struct Hydrator<'o> {
+    entries: Vec<Entry>,
+    /// How many times each entry is referred to.
+    references: Vec<u32>,
+    /// Finished values of entries referred to more than once.
+    shared: Vec<Option<Value>>,
+    /// Whether each entry is a container being restored, which its contents
+    /// cannot refer to.
+    on_stack: Vec<bool>,
+    copied: usize,
+    options: &'o ParseOptions,
+}
+
+/// A container whose contents are being restored.
+struct HydrateFrame {
+    index: usize,
+    values: Vec<Value>,
+}
+
+impl Hydrator<'_> {
+    fn hydrate(mut self) -> Result<Value> {
+        let mut stack: Vec<HydrateFrame> = Vec::new();
+        let mut next = self.begin(0, &mut stack)?;
+
+        loop {
+            if let Some(value) = next {
+                match stack.last_mut() {
+                    None => return Ok(value),
+                    Some(frame) => frame.values.push(value),
+                }
+            }
+            let frame = stack.last().expect("stack is non-empty");
+            next = match self.entries[frame.index].children.get(frame.values.len()) {
+                Some(&child) => self.begin(child, &mut stack)?,
+                None => {
+                    let frame = stack.pop().expect("stack is non-empty");
+                    Some(self.finish(frame)?)
+                }
+            };
+        }
+    }
+
+    /// Restore the entry at `index` directly, or open a frame for it.
+    fn begin(&mut self, index: i64, stack: &mut Vec<HydrateFrame>) -> Result<Option<Value>> {
+        let Ok(i) = usize::try_from(index) else {
+            return constant(index)
+                .map(Some)
+                .ok_or_else(|| invalid(format!("{index} is not a constant")));
+        };
+        if i >= self.entries.len() {
+            return Err(invalid(format!("index {i} is out of range")));
+        }
+        if let Some(value) = &self.shared[i] {
+            self.copied += count_values(value);
+            if self.copied > self.options.max_shared_values {
+                return Err(invalid(format!(
+                    "restoring shared entries would copy more than {} values",
+                    self.options.max_shared_values
+                )));
+            }
+            return Ok(Some(value.clone()));
+        }
+        if self.on_stack[i] {
+            return Err(invalid(format!(
+                "entry {i} contains itself, a cycle a value cannot hold"
+            )));
+        }
+
+        if let Kind::Leaf(value) = &mut self.entries[i].kind {
+            let value = std::mem::replace(value, Value::Null);
+            return Ok(Some(self.keep(i, value)));
+        }
+        if stack.len() >= self.options.max_depth {
+            return Err(Error::DepthLimitExceeded(self.options.max_depth));
+        }
+        self.on_stack[i] = true;
+        stack.push(HydrateFrame {
+            index: i,
+            values: Vec::with_capacity(self.entries[i].children.len()),
+        });
+        Ok(None)
+    }
+
+    /// Assemble a container once its contents have been restored.
+    fn finish(&mut self, frame: HydrateFrame) -> Result<Value> {
+        let HydrateFrame { index, values } = frame;
+        self.on_stack[index] = false;
+        let only = |mut values: Vec<Value>| values.pop().expect("one child was decoded");
+        let value = match std::mem::replace(&mut self.entries[index].kind, Kind::Array) {
+            Kind::Array => Value::Array(values),
+            Kind::Set => Value::Set(values),
+            Kind::Object(keys) => {
+                Value::Object(keys.into_iter().zip(values).collect::<Map<_, _>>())
+            }
+            Kind::Map => {
+                let mut entries = Vec::with_capacity(values.len() / 2);
+                let mut values = values.into_iter();
+                while let (Some(k), Some(v)) = (values.next(), values.next()) {
+                    entries.push((k, v));
+                }
+                Value::Map(entries)
+            }
+            Kind::Custom(name) => Value::Custom {
+                name,
+                value: Box::new(only(values)),
+            },
+            Kind::Bytes => match only(values) {
+                bytes @ Value::Bytes(_) => bytes,
+                other => {
+                    return Err(invalid(format!(
+                        "expected an ArrayBuffer for a Uint8Array, got {}",
+                        other.type_name()
+                    )));
+                }
+            },
+            Kind::Boxed => only(values),
+            Kind::Leaf(_) => unreachable!("leaves open no frame"),
+        };
+        Ok(self.keep(index, value))
+    }
+
+    /// Keep a copy of the value of entry `index` if it is referred to again.
+    fn keep(&mut self, index: usize, value: Value) -> Value {
+        if self.references[index] > 1 {
+            self.shared[index] = Some(value.clone());
+        }
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stringify() {
+        let mut map = Map::new();
+        map.insert("a".into(), Value::from("x"));
+        map.insert("b".into(), Value::from("x"));
+        map.insert(
+            "c".into(),
+            Value::Array(vec![Value::Undefined, Value::NegZero]),
+        );
+        map.insert(
+            "m".into(),
+            Value::Map(vec![(Value::from(1), Value::Set(vec![]))]),
+        );
+        map.insert(
+            "r".into(),
+            Value::RegExp {
+                source: "</script>".into(),
+                flags: "g".into(),
+            },
+        );
+        assert_eq!(
+            stringify(&Value::Object(map)).unwrap(),
+            r#"[{"a":1,"b":1,"c":2,"m":3,"r":6},"x",[-1,-6],["Map",4,5],1,["Set"],["RegExp","\u003C/script>","g"]]"#
+        );
+        assert_eq!(stringify(&Value::Undefined).unwrap(), "-1");
+        assert_eq!(stringify(&Value::from(1)).unwrap(), "[1]");
+        assert!(stringify(&Value::Symbol("s".into())).is_err());
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut map = Map::new();
+        map.insert("bytes".into(), Value::Bytes(vec![0xde, 0xad]));
+        map.insert(
+            "custom".into(),
+            Value::Custom {
+                name: "Vector".into(),
+                value: Box::new(Value::Array(vec![Value::from(1), Value::from(2)])),
+            },
+        );
+        map.insert(
+            "url".into(),
+            Value::Url("https://example.com/".parse().unwrap()),
+        );
+        map.insert(
+            "list".into(),
+            Value::Array(vec![Value::Null, Value::Bool(true)]),
+        );
+        let value = Value::Object(map);
+        let text = stringify(&value).unwrap();
+        assert_eq!(parse(&text).unwrap(), value);
+    }
+
+    #[test]
+    fn test_parse_devalue_output() {
+        // devalue.stringify({ a: obj, b: obj, s: new Set([1]) }) for a shared
+        // `obj = { x: 1 }`, with a hole and a null-prototype object.
+        let text = r#"[{"a":1,"b":1,"s":3,"h":4,"n":5},{"x":2},1,["Set",2],[-2,2],["null","k",2],["Object",2]]"#;
+        let value = parse(text).unwrap();
+        let shared = value.pointer("a").unwrap();
+        assert_eq!(value.pointer("b"), Some(shared));
+        assert_eq!(shared.pointer("x"), Some(&Value::Int(1)));
+        assert_eq!(value.pointer("s"), Some(&Value::Set(vec![Value::Int(1)])));
+        assert_eq!(
+            value.pointer("h"),
+            Some(&Value::Array(vec![Value::Undefined, Value::Int(1)]))
+        );
+        assert_eq!(value.pointer("n.k"), Some(&Value::Int(1)));
+        assert_eq!(parse("-3").unwrap(), Value::NaN);
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn test_dates() {
+        use crate::InvalidDates;
+
+        let date = Value::Date(chrono::DateTime::from_timestamp_millis(1).unwrap());
+        let text = stringify(&date).unwrap();
+        assert_eq!(text, r#"[["Date","1970-01-01T00:00:00.001Z"]]"#);
+        assert_eq!(parse(&text).unwrap(), date);
+
+        let invalid = r#"[["Date",""]]"#;
+        assert_eq!(stringify(&Value::InvalidDate).unwrap(), invalid);
+        assert!(matches!(parse(invalid), Err(Error::InvalidDate(_))));
+        let options = ParseOptions {
+            invalid_dates: InvalidDates::Keep,
+            ..ParseOptions::default()
+        };
+        assert_eq!(parse_with(invalid, &options).unwrap(), Value::InvalidDate);
+    }
+
+    #[test]
+    fn test_parse_rejects_malformed() {
+        for text in [
+            "[]",
+            "0",
+            r#"[[1]]"#,
+            r#"[[5]]"#,
+            r#"[[-9]]"#,
+            r#"[["Map",1]]"#,
+            r#"[["Int16Array","AAA="]]"#,
+            r#"{"a":1}"#,
+        ] {
+            assert!(
+                matches!(parse(text), Err(Error::InvalidDevalue(_))),
+                "{text}"
+            );
+        }
+
+        // Each level refers to the one below twice.
+        let text = r#"[[1,1],[2,2],[3,3],[4,4],"x"]"#;
+        let options = ParseOptions {
+            max_shared_values: 10,
+            ..ParseOptions::default()
+        };
+        assert!(parse(text).is_ok());
+        assert!(matches!(
+            parse_with(text, &options),
+            Err(Error::InvalidDevalue(_))
+        ));
+    }
+}
@@ -0,0 +1,103 @@
+//! superjson envelopes over [MessagePack](https://msgpack.org).
+//!
+//! The `{json, meta}` envelope is written as a MessagePack map with the same
+//! keys and structure it has as JSON, so annotations keep their meaning and
+//! only the text overhead goes. It is read back as [`parse`](crate::parse)
+//! reads text: `meta.values` in any of the forms superjson has used,
+//! `meta.v` compared against [`META_VERSION`](crate::META_VERSION), and the
+//! limits in [`ParseOptions`] enforced.
+//!
+//! # Examples
+//! ```
+//! use superjson_rs::Value;
+//! use superjson_rs::format::msgpack;
+//!
+//! let value = Value::Map(vec![(Value::from(1), Value::Set(vec![Value::NaN]))]);
+//! let bytes = msgpack::to_vec(&value).unwrap();
+//! assert!(bytes.len() < superjson_rs::stringify(&value).unwrap().len());
+//! assert_eq!(msgpack::from_slice(&bytes).unwrap(), value);
+//! ```
+
+use crate::envelope::RawSuperJson;
+use crate::{ParseOptions, Result, SerializeOptions, Value, deserialize, serialize};
+
+/// Serialize `value` into a MessagePack-encoded envelope.
+pub fn to_vec(value: &Value) -> Result<Vec<u8>> {
+    to_vec_with(value, &SerializeOptions::default())
+}
+
+/// Serialize `value` into a MessagePack-encoded envelope, applying
+/// `options`.
+///
+/// `meta` is always written as the annotation tree: `meta_format` only
+/// concerns JSON text.
+pub fn to_vec_with(value: &Value, options: &SerializeOptions) -> Result<Vec<u8>> {
+    let superjson = serialize::serialize_with(value, options)?;
+    Ok(rmp_serde::to_vec_named(&superjson)?)
+}
+
+/// Restore a value from a MessagePack-encoded envelope.
+pub fn from_slice(bytes: &[u8]) -> Result<Value> {
+    from_slice_with(bytes, &ParseOptions::default())
+}
+
+/// Restore a value from a MessagePack-encoded envelope, enforcing the limits
+/// in `options`.
+pub fn from_slice_with(bytes: &[u8], options: &ParseOptions) -> Result<Value> {
+    let raw: RawSuperJson = rmp_serde::from_slice(bytes)?;
+    let superjson = raw.into_superjson(options, &mut Vec::new())?;
+    deserialize::deserialize_with(&superjson, options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::value::Map;
+    use crate::{Error, parse};
+
+    #[test]
+    fn test_roundtrip() {
+        let mut map = Map::new();
+        map.insert("big".to_string(), Value::UInt(u64::MAX));
+        map.insert("neg".to_string(), Value::Int(-3));
+        map.insert("float".to_string(), Value::Number(0.5));
+        map.insert("missing".to_string(), Value::Undefined);
+        map.insert("bytes".to_string(), Value::Bytes(vec![0, 255]));
+        map.insert(
+            "re".to_string(),
+            Value::RegExp {
+                source: "a+".to_string(),
+                flags: "g".to_string(),
+            },
+        );
+        let value = Value::Object(map);
+        assert_eq!(from_slice(&to_vec(&value).unwrap()).unwrap(), value);
+    }
+
+    #[test]
+    fn test_same_envelope_as_json() {
+        let text =
+            r#"{"json":{"n":"-Infinity","s":[1]},"meta":{"values":{"n":"number","s":"set"}}}"#;
+        let json: serde_json::Value = serde_json::from_str(text).unwrap();
+        let bytes = rmp_serde::to_vec_named(&json).unwrap();
+        assert_eq!(from_slice(&bytes).unwrap(), parse(text).unwrap());
+    }
+
+    #[test]
+    fn test_limits_and_errors() {
+        let nested = Value::Array(vec![Value::Array(vec![Value::Array(vec![])])]);
+        let options = ParseOptions {
+            max_depth: 1,
+            ..ParseOptions::default()
+        };
+        let bytes = to_vec(&nested).unwrap();
+        assert!(matches!(
+            from_slice_with(&bytes, &options),
+            Err(Error::DepthLimitExceeded(1))
+        ));
+        assert!(matches!(
+            from_slice(&[0xc1]),
+            Err(Error::MessagePackDecode(_))
+        ));
+    }
+}
@@ -1,27 +1,117 @@
+pub mod annotation;
+pub mod backend;
+pub mod batch;
+pub mod borrowed;
+pub mod bytes;
+mod canonical;
+pub mod codec;
+pub mod codegen;
+#[cfg(feature = "polars")]
+pub mod dataframe;
+pub mod de;
+pub mod debug;
 pub mod deserialize;
+pub mod diff;
+pub mod document;
+mod duration;
+mod envelope;
 pub mod error;
+#[cfg(feature = "hash")]
+pub mod etag;
+pub mod forensic;
+pub mod format;
+#[cfg(feature = "hash")]
+pub mod hash;
+pub mod mask;
+pub mod next;
+#[cfg(feature = "utoipa")]
+pub mod openapi;
+pub mod pages;
+pub mod passthrough;
+pub mod patch;
 pub mod path;
+pub mod plain_json;
+pub mod pool;
+#[cfg(feature = "prost")]
+pub mod protobuf;
+pub mod reader;
+#[cfg(feature = "regex")]
+mod regexp;
+pub mod schema;
+#[cfg(feature = "schemars")]
+mod schemars;
+pub mod ser;
 pub mod serialize;
+#[cfg(feature = "signed")]
+pub mod signed;
+pub mod stats;
+#[cfg(feature = "hash")]
+pub mod sync;
+pub mod testing;
+pub mod trpc;
+pub mod typed;
+pub mod types;
+#[cfg(feature = "valuable")]
+mod valuable;
 pub mod value;
+pub mod value_map;
+pub mod verify;
+pub mod warning;
+pub mod writer;
+#[cfg(feature = "yaml")]
+mod yaml;
 
+pub use batch::{parse_batch, parse_batch_with, stringify_batch};
+pub use bytes::BytesEncoding;
+pub use codec::SuperJsonCodec;
+pub use de::{from_str, from_value};
+#[cfg(feature = "chrono")]
+pub use deserialize::InvalidDates;
+pub use deserialize::{
+    DEFAULT_MAX_ANNOTATIONS, DEFAULT_MAX_DEPTH, DEFAULT_MAX_SHARED_VALUES, MergeStrategy,
+    ParseOptions,
+};
 pub use error::{Error, Result};
-pub use value::Value;
+#[cfg(feature = "hash")]
+pub use hash::ContentHash;
+pub use plain_json::PlainJsonPolicy;
+pub use ser::{to_string, to_value};
+pub use serialize::{MetaFormat, SerializeOptions, UnsafeIntegers};
+#[cfg(feature = "signed")]
+pub use signed::{parse_verified, stringify_signed};
+pub use stats::ParseStats;
+#[cfg(feature = "derive")]
+pub use superjson_derive::{FromSuperJson, ToSuperJson};
+pub use typed::{FromSuperJson, ToSuperJson, TypedSuperJson};
+pub use value::{Map, Value};
+pub use verify::{RoundtripReport, verify_roundtrip};
+pub use warning::Warning;
+
+use std::borrow::Cow;
+use std::fmt;
 
 use indexmap::IndexMap;
-use serde::de::{self, SeqAccess, Visitor};
 use serde::ser::SerializeSeq;
 use serde::{Deserialize, Serialize};
-use std::fmt;
 
 /// The superjson serialized representation, consisting of a JSON-compatible value
 /// and optional metadata for type annotations.
+///
+/// The `json` part is a `serde_json::Value` unless another document type is
+/// named; see [`backend::serialize_into`] for envelopes of other JSON
+/// engines.
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct SuperJson {
-    pub json: serde_json::Value,
+pub struct SuperJson<J = serde_json::Value> {
+    pub json: J,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub meta: Option<Meta>,
 }
 
+/// The latest `meta.v` this crate understands, and the one it writes.
+///
+/// Payloads of superjson releases that predate the field have no `v`.
+pub const META_VERSION: u8 = 1;
+
 /// Metadata containing type annotations and referential equality information.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -30,8 +120,147 @@ pub struct Meta {
     pub values: Option<AnnotationValues>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub referential_equalities: Option<serde_json::Value>,
+    /// The version of the metadata format; see [`META_VERSION`].
     #[serde(skip_serializing_if = "Option::is_none")]
     pub v: Option<u8>,
+    /// A SHA-256 of the canonical form of `json`, as lowercase hex, written
+    /// when `SerializeOptions::checksum` is set. Parsing verifies it when
+    /// the `hash` feature is enabled.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub checksum: Option<String>,
+}
+
+impl<J> SuperJson<J> {
+    /// The envelope's `meta.v`, if it has one.
+    pub fn version(&self) -> Option<u8> {
+        self.meta.as_ref().and_then(|meta| meta.v)
+    }
+}
+
+impl SuperJson {
+    /// The envelope as indented JSON, with keys in the order
+    /// [`stringify`] writes them in.
+    pub fn to_string_pretty(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// The envelope as canonical JSON, in the JSON Canonicalization Scheme
+    /// of RFC 8785: without whitespace, with object keys sorted, `meta`'s
+    /// included, and with numbers written as JavaScript writes them.
+    ///
+    /// Equal envelopes have the same canonical bytes however they were
+    /// produced, and a JS implementation of the scheme, such as the
+    /// `canonicalize` package, produces the same bytes from the parsed
+    /// envelope, so they can be hashed or signed on either side.
+    ///
+    /// # Examples
+    /// ```
+    /// use superjson_rs::SuperJson;
+    ///
+    /// let envelope: SuperJson =
+    ///     serde_json::from_str(r#"{"meta":{"v":1,"values":{"b":["set"]}},"json":{"b":[],"a":1.50}}"#)
+    ///         .unwrap();
+    /// assert_eq!(
+    ///     envelope.canonical_bytes(),
+    ///     br#"{"json":{"a":1.5,"b":[]},"meta":{"v":1,"values":{"b":["set"]}}}"#
+    /// );
+    /// ```
+    pub fn canonical_bytes(&self) -> Vec<u8> {
+        let envelope = serde_json::to_value(self).expect("an envelope is always valid JSON");
+        canonical::to_jcs(&envelope).into_bytes()
+    }
+}
+
+/// Summarizes the payload for logs, e.g.
+/// `root: object, 2 annotations: user.created → Date, tags → set`.
+///
+/// At most five annotations are listed.
+///
+/// # Examples
+/// ```
+/// use superjson_rs::SuperJson;
+///
+/// let superjson: SuperJson = serde_json::from_str(
+///     r#"{"json":{"n":"NaN","tags":[],"u":null},"meta":{"values":{"n":["number"],"tags":["set"]}}}"#,
+/// )
+/// .unwrap();
+/// assert_eq!(superjson.to_string(), "root: object, 2 annotations: n → number, tags → set");
+///
+/// let plain: SuperJson = serde_json::from_str(r#"{"json":[1]}"#).unwrap();
+/// assert_eq!(plain.to_string(), "root: array, no annotations");
+/// ```
+impl fmt::Display for SuperJson {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let root = match self.meta.as_ref().and_then(|meta| meta.values.as_ref()) {
+            Some(AnnotationValues::Root(ann)) => ann.type_name(),
+            _ => match &self.json {
+                serde_json::Value::Null => "null",
+                serde_json::Value::Bool(_) => "boolean",
+                serde_json::Value::Number(_) => "number",
+                serde_json::Value::String(_) => "string",
+                serde_json::Value::Array(_) => "array",
+                serde_json::Value::Object(_) => "object",
+            },
+        };
+        write!(f, "root: {root}, ")?;
+        match &self.meta {
+            Some(meta) => write!(f, "{meta}"),
+            None => f.write_str("no annotations"),
+        }
+    }
+}
+
+/// Lists the annotations by path, e.g. `2 annotations: a → Date, b → set`.
+///
+/// At most five annotations are listed.
+///
+/// # Examples
+/// ```
+/// use superjson_rs::{Value, serialize::serialize};
+///
+/// let value = Value::Set((0..7).map(|_| Value::Undefined).collect());
+/// let meta = serialize(&value).unwrap().meta.unwrap();
+/// assert_eq!(
+///     meta.to_string(),
+///     "8 annotations: <root> → set, 0 → undefined, 1 → undefined, 2 → undefined, \
+///      3 → undefined, … (3 more)"
+/// );
+/// ```
+impl fmt::Display for Meta {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        const LISTED: usize = 5;
+
+        let annotations: Vec<_> = self
+            .values
+            .iter()
+            .flat_map(|values| values.iter())
+            .collect();
+        match annotations.len() {
+            0 => f.write_str("no annotations")?,
+            1 => f.write_str("1 annotation: ")?,
+            n => write!(f, "{n} annotations: ")?,
+        }
+        for (i, (path, ann)) in annotations.iter().take(LISTED).enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            let path = if path.is_empty() { "<root>" } else { path };
+            match ann {
+                TypeAnnotation::Custom(name) => write!(f, "{path} → custom {name}")?,
+                TypeAnnotation::Class(name, _) => write!(f, "{path} → class {name}")?,
+                TypeAnnotation::Symbol(id) => write!(f, "{path} → symbol {id}")?,
+                TypeAnnotation::Compound(kind, name) => write!(f, "{path} → {kind} {name}")?,
+                ann => write!(f, "{path} → {}", ann.type_name())?,
+            }
+        }
+        if annotations.len() > LISTED {
+            write!(f, ", … ({} more)", annotations.len() - LISTED)?;
+        }
+        if self.referential_equalities.is_some() {
+            f.write_str(", with referential equalities")?;
+        }
+        Ok(())
+    }
 }
 
 /// A type annotation node in the annotation tree.
@@ -45,21 +274,197 @@ pub enum TypeAnnotation {
     Leaf(String),
     /// A node annotation with inner annotations: `["typeName", {children}]`
     Node(String, IndexMap<String, TypeAnnotation>),
+    /// A value written by a custom transformer: `[["custom", "name"]]`
+    Custom(String),
+    /// An instance of a registered class, with the annotations of its fields:
+    /// `[["class", "Name"]]` or `[["class", "Name"], {children}]`
+    Class(String, IndexMap<String, TypeAnnotation>),
+    /// A registered symbol: `[["symbol", "identifier"]]`
+    Symbol(String),
+    /// Any other composite identifier, such as the
+    /// `[["typed-array", "Int8Array"]]` of a typed array: its kind and name.
+    ///
+    /// It is read and written back as it is, but values under it cannot be
+    /// restored.
+    Compound(String, String),
 }
 
 impl TypeAnnotation {
     pub fn type_name(&self) -> &str {
         match self {
             TypeAnnotation::Leaf(name) | TypeAnnotation::Node(name, _) => name,
+            TypeAnnotation::Custom(_) => "custom",
+            TypeAnnotation::Class(..) => "class",
+            TypeAnnotation::Symbol(_) => "symbol",
+            TypeAnnotation::Compound(kind, _) => kind,
         }
     }
 
     pub fn children(&self) -> Option<&IndexMap<String, TypeAnnotation>> {
         match self {
-            TypeAnnotation::Leaf(_) => None,
-            TypeAnnotation::Node(_, children) => Some(children),
+            TypeAnnotation::Leaf(_)
+            | TypeAnnotation::Custom(_)
+            | TypeAnnotation::Symbol(_)
+            | TypeAnnotation::Compound(..) => None,
+            TypeAnnotation::Node(_, children) | TypeAnnotation::Class(_, children) => {
+                Some(children)
+            }
         }
     }
+
+    /// Build a type annotation from its JSON form.
+    ///
+    /// Annotation trees nested more than `max_depth` levels deep are rejected
+    /// with [`Error::DepthLimitExceeded`] before any further recursion.
+    pub fn from_json(json: &serde_json::Value, max_depth: usize) -> Result<Self> {
+        Self::from_json_at(
+            Cow::Borrowed(json),
+            &mut Vec::new(),
+            1,
+            max_depth,
+            &mut Vec::new(),
+        )
+    }
+
+    /// Build a type annotation from JSON it borrows or may consume.
+    ///
+    /// Only the names and keys the annotation keeps are copied out of
+    /// borrowed JSON, and owned JSON has them moved out instead. The path of
+    /// a node is only joined when a warning needs it. `path` holds the keys
+    /// leading to `json` and is left as it was found.
+    pub(crate) fn from_json_at<'a>(
+        mut json: Cow<'a, serde_json::Value>,
+        path: &mut Vec<Cow<'a, str>>,
+        depth: usize,
+        max_depth: usize,
+        warnings: &mut Vec<Warning>,
+    ) -> Result<Self> {
+        use serde_json::Value as Json;
+
+        if depth > max_depth {
+            return Err(Error::DepthLimitExceeded(max_depth));
+        }
+
+        let invalid = |json: &Json| {
+            Error::InvalidTypeAnnotation(format!(
+                "expected [\"typeName\"] or [\"typeName\", {{children}}], got {json}"
+            ))
+        };
+        let lenient = |warnings: &mut Vec<Warning>, path: &[Cow<str>], detail: &str| {
+            warnings.push(Warning::LenientAnnotation {
+                path: path.join("."),
+                detail: detail.to_string(),
+            });
+        };
+        let Some(arr) = json.as_array() else {
+            return Err(invalid(&json));
+        };
+        match arr.as_slice() {
+            [Json::Array(pair)] => match pair.as_slice() {
+                [Json::String(kind), Json::String(_)] if kind != "class" => {
+                    let mut pair = take_item(&mut json, 0);
+                    let kind = take_string(&mut pair, 0);
+                    let name = take_string(&mut pair, 1);
+                    return Ok(match kind.as_str() {
+                        "custom" => TypeAnnotation::Custom(name),
+                        "symbol" => TypeAnnotation::Symbol(name),
+                        _ => TypeAnnotation::Compound(kind, name),
+                    });
+                }
+                [kind, Json::String(_)] if kind == "class" => {}
+                _ => return Err(invalid(&json)),
+            },
+            [Json::Array(pair), Json::Object(_)] if matches!(pair.as_slice(), [kind, Json::String(_)] if kind == "class") =>
+                {}
+            [Json::Array(_), ..] => return Err(invalid(&json)),
+            [Json::String(_)] => return Ok(TypeAnnotation::Leaf(take_string(&mut json, 0))),
+            [Json::String(_), Json::Null] => {
+                lenient(warnings, path, "null children treated as a leaf");
+                return Ok(TypeAnnotation::Leaf(take_string(&mut json, 0)));
+            }
+            [Json::String(_), Json::Object(map)] if map.is_empty() => {
+                lenient(warnings, path, "empty children treated as a leaf");
+                return Ok(TypeAnnotation::Leaf(take_string(&mut json, 0)));
+            }
+            [Json::String(_), Json::Object(_)] => {
+                let name = take_string(&mut json, 0);
+                let map = take_item(&mut json, 1);
+                let children = Self::children_from_json(map, path, depth, max_depth, warnings)?;
+                return Ok(TypeAnnotation::Node(name, children));
+            }
+            _ => return Err(invalid(&json)),
+        }
+
+        // A class, `[["class", name]]` with optional children.
+        let name = take_string(&mut take_item(&mut json, 0), 1);
+        let children = match json.as_array().map(Vec::len) {
+            Some(2) => {
+                let map = take_item(&mut json, 1);
+                Self::children_from_json(map, path, depth, max_depth, warnings)?
+            }
+            _ => IndexMap::new(),
+        };
+        Ok(TypeAnnotation::Class(name, children))
+    }
+
+    /// Build the children of a node at `depth` from their JSON form, the
+    /// object `map`.
+    fn children_from_json<'a>(
+        map: Cow<'a, serde_json::Value>,
+        path: &mut Vec<Cow<'a, str>>,
+        depth: usize,
+        max_depth: usize,
+        warnings: &mut Vec<Warning>,
+    ) -> Result<IndexMap<String, TypeAnnotation>> {
+        let mut children = IndexMap::new();
+        let mut insert = |key: Cow<'a, str>, child: Cow<'a, serde_json::Value>| {
+            path.push(key);
+            let child = Self::from_json_at(child, path, depth + 1, max_depth, warnings)?;
+            let key = path.pop().expect("the key was pushed above");
+            children.insert(key.into_owned(), child);
+            Ok::<_, Error>(())
+        };
+        match map {
+            Cow::Borrowed(serde_json::Value::Object(map)) => {
+                for (key, child) in map {
+                    insert(Cow::Borrowed(key), Cow::Borrowed(child))?;
+                }
+            }
+            Cow::Owned(serde_json::Value::Object(map)) => {
+                for (key, child) in map {
+                    insert(Cow::Owned(key), Cow::Owned(child))?;
+                }
+            }
+            _ => unreachable!("children are an object"),
+        }
+        Ok(children)
+    }
+}
+
+/// The item at `index` of the JSON array `json`, moved out of it if owned.
+fn take_item<'a>(
+    json: &mut Cow<'a, serde_json::Value>,
+    index: usize,
+) -> Cow<'a, serde_json::Value> {
+    match json {
+        Cow::Borrowed(json) => Cow::Borrowed(&json[index]),
+        Cow::Owned(json) => Cow::Owned(json[index].take()),
+    }
+}
+
+/// The string at `index` of the JSON array `json`, moved out of it if
+/// owned and copied if borrowed.
+fn take_string(json: &mut Cow<'_, serde_json::Value>, index: usize) -> String {
+    let item = match json {
+        Cow::Borrowed(json) => &json[index],
+        Cow::Owned(json) => match &mut json[index] {
+            serde_json::Value::String(s) => return std::mem::take(s),
+            other => other,
+        },
+    };
+    item.as_str()
+        .expect("the item was matched as a string")
+        .to_string()
 }
 
 impl Serialize for TypeAnnotation {
@@ -79,6 +484,30 @@ impl Serialize for TypeAnnotation {
                 seq.serialize_element(children)?;
                 seq.end()
             }
+            TypeAnnotation::Custom(name) => {
+                let mut seq = serializer.serialize_seq(Some(1))?;
+                seq.serialize_element(&["custom", name.as_str()])?;
+                seq.end()
+            }
+            TypeAnnotation::Symbol(id) => {
+                let mut seq = serializer.serialize_seq(Some(1))?;
+                seq.serialize_element(&["symbol", id.as_str()])?;
+                seq.end()
+            }
+            TypeAnnotation::Compound(kind, name) => {
+                let mut seq = serializer.serialize_seq(Some(1))?;
+                seq.serialize_element(&[kind.as_str(), name.as_str()])?;
+                seq.end()
+            }
+            TypeAnnotation::Class(name, children) => {
+                let len = if children.is_empty() { 1 } else { 2 };
+                let mut seq = serializer.serialize_seq(Some(len))?;
+                seq.serialize_element(&["class", name.as_str()])?;
+                if !children.is_empty() {
+                    seq.serialize_element(children)?;
+                }
+                seq.end()
+            }
         }
     }
 }
@@ -87,33 +516,15 @@ impl<'de> Deserialize<'de> for TypeAnnotation {
     fn deserialize<D: serde::Deserializer<'de>>(
         deserializer: D,
     ) -> std::result::Result<Self, D::Error> {
-        deserializer.deserialize_seq(TypeAnnotationVisitor)
-    }
-}
-
-struct TypeAnnotationVisitor;
-
-impl<'de> Visitor<'de> for TypeAnnotationVisitor {
-    type Value = TypeAnnotation;
-
-    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-        formatter.write_str("a type annotation array: [\"typeName\"] or [\"typeName\", {children}]")
-    }
-
-    fn visit_seq<A: SeqAccess<'de>>(
-        self,
-        mut seq: A,
-    ) -> std::result::Result<TypeAnnotation, A::Error> {
-        let name: String = seq
-            .next_element()?
-            .ok_or_else(|| de::Error::invalid_length(0, &"at least 1 element"))?;
-
-        let children: Option<IndexMap<String, TypeAnnotation>> = seq.next_element()?;
-
-        match children {
-            Some(c) => Ok(TypeAnnotation::Node(name, c)),
-            None => Ok(TypeAnnotation::Leaf(name)),
-        }
+        let value = serde_json::Value::deserialize(deserializer)?;
+        TypeAnnotation::from_json_at(
+            Cow::Owned(value),
+            &mut Vec::new(),
+            1,
+            DEFAULT_MAX_DEPTH,
+            &mut Vec::new(),
+        )
+        .map_err(serde::de::Error::custom)
     }
 }
 
@@ -142,26 +553,170 @@ impl Serialize for AnnotationValues {
     }
 }
 
+impl AnnotationValues {
+    /// Build annotation values from the JSON form of `meta.values`.
+    ///
+    /// Each annotation tree is limited to `max_depth` levels of nesting.
+    ///
+    /// The flat `{"path": "typeName"}` map of older superjson releases (see
+    /// [`MetaFormat::Legacy`]) is accepted too, as is the list of
+    /// `["path", "typeName"]` pairs of earlier ones, and converted to a tree.
+    pub fn from_json(json: &serde_json::Value, max_depth: usize) -> Result<Self> {
+        Self::from_json_with_warnings(Cow::Borrowed(json), max_depth, &mut Vec::new())
+    }
+
+    /// Build annotation values from JSON they borrow or may consume, as
+    /// [`TypeAnnotation::from_json_at`] does.
+    pub(crate) fn from_json_with_warnings(
+        json: Cow<'_, serde_json::Value>,
+        max_depth: usize,
+        warnings: &mut Vec<Warning>,
+    ) -> Result<Self> {
+        match json.as_ref() {
+            serde_json::Value::Array(items) if is_legacy_pairs(items) => Self::from_legacy_json(
+                items
+                    .iter()
+                    .map(|pair| (pair[0].as_str().unwrap_or_default(), &pair[1])),
+                max_depth,
+            ),
+            serde_json::Value::Array(_) => Ok(AnnotationValues::Root(
+                TypeAnnotation::from_json_at(json, &mut Vec::new(), 1, max_depth, warnings)?,
+            )),
+            serde_json::Value::Object(map) if map.values().any(|v| v.is_string()) => {
+                Self::from_legacy_json(
+                    map.iter().map(|(path, name)| (path.as_str(), name)),
+                    max_depth,
+                )
+            }
+            serde_json::Value::String(_) => match json.into_owned() {
+                serde_json::Value::String(name) => {
+                    Ok(AnnotationValues::Root(TypeAnnotation::Leaf(name)))
+                }
+                _ => unreachable!("the name was matched above"),
+            },
+            serde_json::Value::Object(_) => Ok(AnnotationValues::Children(
+                TypeAnnotation::children_from_json(json, &mut Vec::new(), 0, max_depth, warnings)?,
+            )),
+            _ => Err(Error::InvalidTypeAnnotation(
+                "expected array or object for annotation values".to_string(),
+            )),
+        }
+    }
+
+    /// Read the flat `(path, typeName)` entries written by superjson releases
+    /// before the annotation tree format, nesting each annotation under the
+    /// annotation of its closest ancestor. The empty path is the root.
+    fn from_legacy_json<'a>(
+        entries: impl Iterator<Item = (&'a str, &'a serde_json::Value)>,
+        max_depth: usize,
+    ) -> Result<Self> {
+        let mut entries = entries
+            .map(|(path, name)| match name.as_str() {
+                Some(name) => Ok((path, name)),
+                None => Err(Error::InvalidTypeAnnotation(format!(
+                    "expected a type name at '{path}' in legacy annotations, got {name}"
+                ))),
+            })
+            .collect::<Result<Vec<_>>>()?;
+        // Ancestors are placed before their descendants.
+        entries.sort_by_key(|(path, _)| path::parse(path).len());
+
+        let mut root = None;
+        let mut children = IndexMap::new();
+        for (path, name) in entries {
+            let ann = TypeAnnotation::Leaf(name.to_string());
+            match &mut root {
+                None if path.is_empty() => root = Some(ann),
+                None => insert_legacy(&mut children, path, ann, 1, max_depth)?,
+                Some(root) => insert_legacy(node_children(root), path, ann, 2, max_depth)?,
+            }
+        }
+
+        Ok(match root {
+            Some(mut root) => {
+                if !children.is_empty() {
+                    *node_children(&mut root) = children;
+                }
+                AnnotationValues::Root(root)
+            }
+            None => AnnotationValues::Children(children),
+        })
+    }
+}
+
+/// Whether `items` is a list of `["path", "typeName"]` pairs rather than a
+/// root annotation.
+///
+/// A root annotation is a type name with optional children, so a list of
+/// two or more pairs is never one; a single pair is, if it is one of the
+/// composite identifiers JS superjson writes, such as `["custom", "Decimal"]`.
+pub(crate) fn is_legacy_pairs(items: &[serde_json::Value]) -> bool {
+    let is_pair = |item: &serde_json::Value| match item.as_array().map(Vec::as_slice) {
+        Some([path, name]) => path.is_string() && name.is_string(),
+        _ => false,
+    };
+    match items {
+        [] => false,
+        [pair] => {
+            is_pair(pair)
+                && !matches!(
+                    pair[0].as_str(),
+                    Some("custom" | "class" | "symbol" | "typed-array")
+                )
+        }
+        items => items.iter().all(is_pair),
+    }
+}
+
+/// The children of `ann`, turning a leaf into a node.
+fn node_children(ann: &mut TypeAnnotation) -> &mut IndexMap<String, TypeAnnotation> {
+    if let TypeAnnotation::Leaf(name) = ann {
+        *ann = TypeAnnotation::Node(std::mem::take(name), IndexMap::new());
+    }
+    match ann {
+        TypeAnnotation::Node(_, children) => children,
+        _ => unreachable!("legacy annotations are leaves or nodes"),
+    }
+}
+
+/// Insert `ann` at the flat `path` into `map`, beneath any annotation at an
+/// ancestor path; `depth` is the depth of the entries of `map`.
+fn insert_legacy(
+    mut map: &mut IndexMap<String, TypeAnnotation>,
+    mut path: &str,
+    ann: TypeAnnotation,
+    mut depth: usize,
+    max_depth: usize,
+) -> Result<()> {
+    loop {
+        if depth > max_depth {
+            return Err(Error::DepthLimitExceeded(max_depth));
+        }
+        let parent = map.keys().find(|key| {
+            path.strip_prefix(key.as_str())
+                .is_some_and(|rest| rest.starts_with('.'))
+        });
+        let Some(parent) = parent.cloned() else {
+            map.insert(path.to_string(), ann);
+            return Ok(());
+        };
+        path = &path[parent.len() + 1..];
+        map = node_children(map.get_mut(&parent).expect("key was just found"));
+        depth += 1;
+    }
+}
+
 impl<'de> Deserialize<'de> for AnnotationValues {
     fn deserialize<D: serde::Deserializer<'de>>(
         deserializer: D,
     ) -> std::result::Result<Self, D::Error> {
         let value = serde_json::Value::deserialize(deserializer)?;
-        match &value {
-            serde_json::Value::Array(_) => {
-                let ann: TypeAnnotation =
-                    serde_json::from_value(value).map_err(de::Error::custom)?;
-                Ok(AnnotationValues::Root(ann))
-            }
-            serde_json::Value::Object(_) => {
-                let map: IndexMap<String, TypeAnnotation> =
-                    serde_json::from_value(value).map_err(de::Error::custom)?;
-                Ok(AnnotationValues::Children(map))
-            }
-            _ => Err(de::Error::custom(
-                "expected array or object for annotation values",
-            )),
-        }
+        AnnotationValues::from_json_with_warnings(
+            Cow::Owned(value),
+            DEFAULT_MAX_DEPTH,
+            &mut Vec::new(),
+        )
+        .map_err(serde::de::Error::custom)
     }
 }
 
@@ -172,18 +727,104 @@ impl<'de> Deserialize<'de> for AnnotationValues {
 ///
 /// # Examples
 /// ```
+/// # #[cfg(feature = "chrono")] {
 /// use superjson_rs::{Value, stringify};
 /// use chrono::TimeZone;
 ///
 /// let value = Value::Date(chrono::Utc.timestamp_millis_opt(0).unwrap());
 /// let json_str = stringify(&value).unwrap();
 /// assert!(json_str.contains("Date"));
+/// # }
 /// ```
 pub fn stringify(value: &Value) -> Result<String> {
     let superjson = serialize::serialize(value)?;
     serde_json::to_string(&superjson).map_err(Error::from)
 }
 
+/// Serialize a `Value` into an indented superjson JSON string, for reading.
+///
+/// Keys keep the order [`stringify`] writes them in.
+///
+/// # Examples
+/// ```
+/// use superjson_rs::{Value, stringify_pretty};
+///
+/// let value = Value::from(vec![Value::NaN]);
+/// assert_eq!(
+///     stringify_pretty(&value).unwrap(),
+///     r#"{
+///   "json": [
+///     "NaN"
+///   ],
+///   "meta": {
+///     "values": {
+///       "0": [
+///         "number"
+///       ]
+///     },
+///     "v": 1
+///   }
+/// }"#
+/// );
+/// ```
+pub fn stringify_pretty(value: &Value) -> Result<String> {
+    serialize::serialize(value)?.to_string_pretty()
+}
+
+/// Serialize a `Value` into a superjson JSON string, applying the checks in
+/// `options`.
+///
+/// # Examples
+/// ```
+/// use superjson_rs::{Error, SerializeOptions, UnsafeIntegers, Value, stringify_with};
+///
+/// let options = SerializeOptions {
+///     unsafe_integers: UnsafeIntegers::Error,
+///     ..SerializeOptions::default()
+/// };
+/// let id = Value::Object([("id".to_string(), Value::Number(2f64.powi(60)))].into_iter().collect());
+/// assert!(matches!(stringify_with(&id, &options), Err(Error::UnsafeInteger { .. })));
+/// ```
+pub fn stringify_with(value: &Value, options: &SerializeOptions) -> Result<String> {
+    stringify_detailed(value, options).map(|stringified| stringified.text)
+}
+
+/// The result of [`stringify_detailed`].
+#[derive(Debug, Clone)]
+pub struct Stringified {
+    pub text: String,
+    /// Non-fatal issues noticed while serializing, in the order they were
+    /// found.
+    pub warnings: Vec<Warning>,
+}
+
+/// Serialize a `Value` into a superjson JSON string, also reporting
+/// non-fatal [`Warning`]s.
+///
+/// # Examples
+/// ```
+/// use superjson_rs::{SerializeOptions, UnsafeIntegers, Value, Warning, stringify_detailed};
+///
+/// let options = SerializeOptions {
+///     unsafe_integers: UnsafeIntegers::Warn,
+///     ..SerializeOptions::default()
+/// };
+/// let ids = Value::Array(vec![Value::from(1), Value::Number(2f64.powi(60))]);
+/// let stringified = stringify_detailed(&ids, &options).unwrap();
+/// assert!(matches!(
+///     &stringified.warnings[..],
+///     [Warning::UnsafeInteger { path, .. }] if path == "1"
+/// ));
+/// ```
+pub fn stringify_detailed(value: &Value, options: &SerializeOptions) -> Result<Stringified> {
+    let mut warnings = Vec::new();
+    let superjson = serialize::serialize_detailed(value, options, &[], &mut warnings)?;
+    Ok(Stringified {
+        text: serialize::to_text(&superjson, options)?,
+        warnings,
+    })
+}
+
 /// Parse a superjson JSON string back into a `Value`.
 ///
 /// # Examples
@@ -196,6 +837,221 @@ pub fn stringify(value: &Value) -> Result<String> {
 /// assert_eq!(parsed, value);
 /// ```
 pub fn parse(s: &str) -> Result<Value> {
-    let superjson: SuperJson = serde_json::from_str(s)?;
-    deserialize::deserialize(&superjson)
+    parse_with(s, &ParseOptions::default())
+}
+
+/// Parse a superjson JSON string with explicit limits.
+///
+/// # Examples
+/// ```
+/// use superjson_rs::{Error, ParseOptions, parse_with};
+///
+/// let deep = r#"{"json":[[1]],"meta":{"values":["set",{"0":["set"]}],"v":1}}"#;
+/// let options = ParseOptions {
+///     max_depth: 1,
+///     ..ParseOptions::default()
+/// };
+/// assert!(matches!(parse_with(deep, &options), Err(Error::DepthLimitExceeded(1))));
+/// ```
+pub fn parse_with(s: &str, options: &ParseOptions) -> Result<Value> {
+    parse_detailed(s, options).map(|parsed| parsed.value)
+}
+
+/// Parse a superjson envelope written in JSON5, as hand-written fixtures and
+/// config files often are: with comments, trailing commas, unquoted keys and
+/// single-quoted strings.
+///
+/// JSON5's `Infinity` and `NaN` cannot be held in the `json` section and
+/// read as `null`; annotate them as superjson does instead.
+///
+/// # Examples
+/// ```
+/// use superjson_rs::{Value, parse_json5};
+///
+/// let text = "{
+///     // Written by hand.
+///     json: { tags: ['a', 'b',], },
+///     meta: { values: { tags: ['set'] } },
+/// }";
+/// let value = parse_json5(text).unwrap();
+/// assert_eq!(value.pointer("tags"), Some(&Value::Set(vec![Value::from("a"), Value::from("b")])));
+/// ```
+#[cfg(feature = "json5")]
+pub fn parse_json5(s: &str) -> Result<Value> {
+    backend::parse_with(&backend::Json5, s, &ParseOptions::default())
+}
+
+/// Parse a superjson envelope from UTF-8 bytes, such as a request body,
+/// without first checking them as a `&str`.
+///
+/// To leave the parts of a large body without annotations unparsed, see
+/// [`passthrough::parse`].
+///
+/// # Examples
+/// ```
+/// use superjson_rs::{Value, parse_from_slice};
+///
+/// let value = parse_from_slice(br#"{"json":"NaN","meta":{"values":["number"]}}"#).unwrap();
+/// assert_eq!(value, Value::NaN);
+/// ```
+pub fn parse_from_slice(bytes: &[u8]) -> Result<Value> {
+    parse_from_slice_with(bytes, &ParseOptions::default())
+}
+
+/// Parse a superjson envelope from UTF-8 bytes with explicit limits.
+pub fn parse_from_slice_with(bytes: &[u8], options: &ParseOptions) -> Result<Value> {
+    let envelope: envelope::RawSuperJson = serde_json::from_slice(bytes)?;
+    let superjson = envelope.into_superjson(options, &mut Vec::new())?;
+    deserialize::deserialize_with(&superjson, options)
+}
+
+/// Parse a superjson JSON string, borrowing keys and strings from `s`
+/// instead of copying them.
+///
+/// Suited to proxies and routers that inspect payloads on a hot path. Keys
+/// and strings that need no unescaping borrow from `s`; values with a type
+/// annotation are restored as owned [`Value`]s. See the
+/// [`borrowed`] module for details.
+///
+/// `meta.referentialEqualities` are not restored: values that JS superjson
+/// wrote once with `dedupe` stay `null` at their other paths. Use [`parse`]
+/// for such payloads.
+///
+/// # Examples
+/// ```
+/// use std::borrow::Cow;
+/// use superjson_rs::{Value, borrowed, parse_borrowed};
+///
+/// let text = r#"{"json":{"route":"/users","tags":["a"]},"meta":{"values":{"tags":["set"]}}}"#;
+/// let value = parse_borrowed(text).unwrap();
+/// assert!(matches!(value.pointer("route"), Some(borrowed::Value::String(Cow::Borrowed("/users")))));
+/// assert_eq!(
+///     value.pointer("tags"),
+///     Some(&borrowed::Value::Extended(Value::Set(vec![Value::from("a")])))
+/// );
+/// ```
+pub fn parse_borrowed(s: &str) -> Result<borrowed::Value<'_>> {
+    parse_borrowed_with(s, &ParseOptions::default())
+}
+
+/// Parse a superjson JSON string with explicit limits, borrowing keys and
+/// strings from `s`; see [`parse_borrowed`].
+pub fn parse_borrowed_with<'a>(s: &'a str, options: &ParseOptions) -> Result<borrowed::Value<'a>> {
+    parse_borrowed_detailed(s, options).map(|parsed| parsed.value)
+}
+
+/// Parse a superjson JSON string with explicit limits, borrowing keys and
+/// strings from `s` as [`parse_borrowed`] does and also reporting non-fatal
+/// [`Warning`]s.
+///
+/// # Examples
+/// ```
+/// # #[cfg(feature = "chrono")] {
+/// use superjson_rs::{ParseOptions, Warning, parse_borrowed_detailed};
+///
+/// let s = r#"{"json":{"at":"2024-01-01T09:00:00.000+09:00"},"meta":{"values":{"at":["Date"]},"extra":1}}"#;
+/// let parsed = parse_borrowed_detailed(s, &ParseOptions::default()).unwrap();
+/// assert_eq!(parsed.warnings.len(), 2);
+/// assert_eq!(parsed.warnings[0], Warning::UnknownField { name: "meta.extra".into() });
+/// # }
+/// ```
+pub fn parse_borrowed_detailed<'a>(
+    s: &'a str,
+    options: &ParseOptions,
+) -> Result<borrowed::Parsed<'a>> {
+    let envelope: envelope::RawSuperJson<borrowed::Value<'a>> = serde_json::from_str(s)?;
+    let mut warnings = Vec::new();
+    let (json, meta) = envelope.into_parts(options, &mut warnings)?;
+    let value = deserialize::deserialize_borrowed(json, meta.as_ref(), options, &mut warnings)?;
+    Ok(borrowed::Parsed { value, warnings })
+}
+
+/// Parse a superjson JSON string and merge it into `target` in place.
+///
+/// Suited to update streams that patch a large document: fields the update
+/// does not touch are kept as they are, without rebuilding the document.
+/// See [`deserialize::deserialize_into`] for the details.
+///
+/// # Examples
+/// ```
+/// use superjson_rs::{MergeStrategy, parse, parse_into};
+///
+/// let mut state = parse(r#"{"json":{"user":{"name":"ada","age":36},"online":false}}"#).unwrap();
+/// let update = r#"{"json":{"user":{"age":37},"tags":[]},"meta":{"values":{"tags":["set"]}}}"#;
+/// parse_into(&mut state, update, MergeStrategy::Deep).unwrap();
+///
+/// let expected = r#"{"json":{"user":{"name":"ada","age":37},"online":false,"tags":[]},"meta":{"values":{"tags":["set"]}}}"#;
+/// assert_eq!(state, parse(expected).unwrap());
+/// ```
+pub fn parse_into(target: &mut Value, s: &str, strategy: MergeStrategy) -> Result<()> {
+    let options = ParseOptions::default();
+    let envelope: envelope::RawSuperJson = serde_json::from_str(s)?;
+    let superjson = envelope.into_superjson(&options, &mut Vec::new())?;
+    deserialize::deserialize_into(&superjson, target, strategy, &options)
+}
+
+/// The result of [`parse_detailed`].
+#[derive(Debug, Clone)]
+pub struct Parsed {
+    pub value: Value,
+    /// Non-fatal issues noticed while parsing, in the order they were found.
+    pub warnings: Vec<Warning>,
+    /// Payload statistics, present when [`ParseOptions::collect_stats`] is set.
+    pub stats: Option<ParseStats>,
+    /// The payload's `meta.v`, or `None` if it was written by a release
+    /// that predates the field or has no `meta` at all.
+    pub version: Option<u8>,
+}
+
+/// Parse a superjson JSON string, also reporting non-fatal [`Warning`]s.
+///
+/// # Examples
+/// ```
+/// # #[cfg(feature = "chrono")] {
+/// use superjson_rs::{ParseOptions, Warning, parse_detailed};
+///
+/// let s = r#"{"json":"2024-01-01T09:00:00.000+09:00","meta":{"values":["Date"],"extra":1}}"#;
+/// let parsed = parse_detailed(s, &ParseOptions::default()).unwrap();
+/// assert_eq!(parsed.warnings.len(), 2);
+/// assert_eq!(parsed.warnings[0], Warning::UnknownField { name: "meta.extra".into() });
+/// # }
+/// ```
+pub fn parse_detailed(s: &str, options: &ParseOptions) -> Result<Parsed> {
+    parse_detailed_in(&backend::SerdeJson, s, options, &[])
+}
+
+pub(crate) fn parse_detailed_in<B: backend::JsonBackend>(
+    backend: &B,
+    s: &str,
+    options: &ParseOptions,
+    hooks: &[codec::Hook],
+) -> Result<Parsed> {
+    // Only read the clock when asked to: `Instant` is unavailable on some
+    // WASM targets.
+    let started = options.collect_stats.then(std::time::Instant::now);
+    let mut stats = options.collect_stats.then(|| ParseStats {
+        bytes: s.len(),
+        ..ParseStats::default()
+    });
+
+    let envelope: envelope::RawSuperJson = backend.parse(s)?;
+    let mut warnings = Vec::new();
+    let superjson = envelope.into_superjson(options, &mut warnings)?;
+    let value = deserialize::deserialize_detailed(
+        &superjson,
+        options,
+        hooks,
+        &mut warnings,
+        stats.as_mut(),
+    )?;
+
+    if let (Some(stats), Some(started)) = (stats.as_mut(), started) {
+        stats.elapsed = started.elapsed();
+    }
+    Ok(Parsed {
+        value,
+        warnings,
+        version: superjson.version(),
+        stats,
+    })
 }
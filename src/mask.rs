@@ -0,0 +1,229 @@
+//! Ready-made maskers for personally identifiable information.
+//!
+//! Each masker is a `Fn(&Value) -> Value` that can be registered as a
+//! serialize hook, so that raw PII never reaches the wire:
+//!
+//! ```
+//! use superjson_rs::{SuperJsonCodec, mask};
+//!
+//! let mut codec = SuperJsonCodec::new();
+//! codec
+//!     .on_serialize("**.email", mask::email)
+//!     .on_serialize("payment.card", mask::credit_card)
+//!     .on_serialize("**.phone", mask::phone);
+//!
+//! let s = r#"{"json":{"user":{"email":"ada@example.com","phone":"+1 555 123 4567"},
+//!             "payment":{"card":"4111 1111 1111 1111"}}}"#;
+//! let out = codec.stringify(&codec.parse(s).unwrap()).unwrap();
+//! assert!(out.contains(r#""email":"a***@example.com""#));
+//! assert!(out.contains(r#""phone":"+* *** *** **67""#));
+//! assert!(out.contains(r#""card":"**** **** **** 1111""#));
+//! ```
+//!
+//! Strings and numbers are masked; other values are returned unchanged.
+//! [`email`], [`credit_card`] and [`phone`] never return a string as-is: one
+//! that does not look like the expected kind of data is masked entirely.
+//!
+//! To mask by content rather than by path, wrap a masker in [`deep`] and
+//! register it at the root.
+
+use crate::Value;
+
+/// Mask an email address, keeping the first character of the local part and
+/// the domain: `ada@example.com` becomes `a***@example.com`.
+pub fn email(value: &Value) -> Value {
+    mask_text(value, |s| match s.rfind('@') {
+        Some(at) if at > 0 => {
+            let first = s.chars().next().expect("local part is non-empty");
+            format!("{first}***{}", &s[at..])
+        }
+        _ => "***".to_string(),
+    })
+}
+
+/// Mask a payment card number, keeping the last four digits and any
+/// separators: `4111-1111-1111-1111` becomes `****-****-****-1111`.
+///
+/// Numbers with fewer than 12 digits are masked entirely.
+pub fn credit_card(value: &Value) -> Value {
+    mask_text(value, |s| mask_digits(s, 12, 4))
+}
+
+/// Mask a phone number, keeping the last two digits and any formatting:
+/// `+1 (555) 123-4567` becomes `+* (***) ***-**67`.
+///
+/// Numbers with fewer than 7 digits are masked entirely.
+pub fn phone(value: &Value) -> Value {
+    mask_text(value, |s| mask_digits(s, 7, 2))
+}
+
+/// Replace every match of `pattern` in a string with `replacement`, which
+/// may refer to capture groups as `$1` or `$name`.
+///
+/// Unlike the other maskers, strings without a match are left unchanged, so
+/// this is suitable for [`deep`] masking of free-form text.
+///
+/// # Errors
+/// Returns [`Error::InvalidRegExp`](crate::Error::InvalidRegExp) if
+/// `pattern` does not compile.
+///
+/// # Examples
+/// ```
+/// use superjson_rs::{Value, mask};
+///
+/// let ssn = mask::regex(r"\b\d{3}-\d{2}-(\d{4})\b", "***-**-$1").unwrap();
+/// assert_eq!(ssn(&Value::from("SSN 123-45-6789")), Value::from("SSN ***-**-6789"));
+/// ```
+#[cfg(feature = "regex")]
+pub fn regex(
+    pattern: &str,
+    replacement: &str,
+) -> crate::Result<impl Fn(&Value) -> Value + Send + Sync + 'static> {
+    let regex = ::regex::Regex::new(pattern)
+        .map_err(|e| crate::Error::InvalidRegExp(format!("{pattern}: {e}")))?;
+    let replacement = replacement.to_string();
+    Ok(move |value: &Value| match value {
+        Value::String(s) => Value::String(regex.replace_all(s, replacement.as_str()).into_owned()),
+        other => other.clone(),
+    })
+}
+
+/// Apply `masker` to every string and number inside a value, including map
+/// keys, rather than to the value as a whole.
+///
+/// # Examples
+/// ```
+/// use superjson_rs::{Value, mask};
+///
+/// let masked = mask::deep(mask::email)(&Value::Set(vec![Value::from("ada@example.com")]));
+/// assert_eq!(masked, Value::Set(vec![Value::from("a***@example.com")]));
+/// ```
+pub fn deep<F>(masker: F) -> impl Fn(&Value) -> Value + Send + Sync + 'static
+where
+    F: Fn(&Value) -> Value + Send + Sync + 'static,
+{
+    move |value: &Value| {
+        let mut out = value.clone();
+        let mut stack = vec![&mut out];
+        while let Some(value) = stack.pop() {
+            match value {
+                Value::String(_) | Value::Number(_) | Value::Int(_) | Value::UInt(_) => {
+                    *value = masker(value)
+                }
+                Value::Array(items) | Value::Set(items) => stack.extend(items.iter_mut()),
+                Value::Object(map) | Value::Class { fields: map, .. } => {
+                    stack.extend(map.values_mut())
+                }
+                Value::Map(entries) => {
+                    for (k, v) in entries.iter_mut() {
+                        stack.push(k);
+                        stack.push(v);
+                    }
+                }
+                Value::Error { cause, extra, .. } => {
+                    stack.extend(cause.as_deref_mut());
+                    stack.extend(extra.values_mut());
+                }
+                _ => {}
+            }
+        }
+        out
+    }
+}
+
+/// Apply `mask` to the text of a string or number.
+fn mask_text(value: &Value, mask: impl Fn(&str) -> String) -> Value {
+    match value {
+        Value::String(s) => Value::String(mask(s)),
+        Value::Number(n) => Value::String(mask(&n.to_string())),
+        Value::Int(n) => Value::String(mask(&n.to_string())),
+        Value::UInt(n) => Value::String(mask(&n.to_string())),
+        other => other.clone(),
+    }
+}
+
+/// Replace all but the last `keep` digits with `*`, leaving other characters
+/// in place. Text with fewer than `min_digits` digits has every character
+/// masked.
+fn mask_digits(s: &str, min_digits: usize, keep: usize) -> String {
+    let digits = s.chars().filter(char::is_ascii_digit).count();
+    if digits < min_digits {
+        return "*".repeat(s.chars().count());
+    }
+
+    let mut seen = 0;
+    s.chars()
+        .map(|c| {
+            if !c.is_ascii_digit() {
+                return c;
+            }
+            seen += 1;
+            if seen > digits - keep { c } else { '*' }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_email() {
+        assert_eq!(
+            email(&Value::from("ada@example.com")),
+            Value::from("a***@example.com")
+        );
+        assert_eq!(email(&Value::from("not an email")), Value::from("***"));
+        assert_eq!(email(&Value::from("@example.com")), Value::from("***"));
+        assert_eq!(email(&Value::Null), Value::Null);
+    }
+
+    #[test]
+    fn test_credit_card() {
+        assert_eq!(
+            credit_card(&Value::from("4111-1111-1111-1111")),
+            Value::from("****-****-****-1111")
+        );
+        assert_eq!(
+            credit_card(&Value::Number(4111111111111111.0)),
+            Value::from("************1111")
+        );
+        assert_eq!(credit_card(&Value::from("12 34")), Value::from("*****"));
+    }
+
+    #[test]
+    fn test_phone() {
+        assert_eq!(
+            phone(&Value::from("+1 (555) 123-4567")),
+            Value::from("+* (***) ***-**67")
+        );
+        assert_eq!(phone(&Value::from("911")), Value::from("***"));
+    }
+
+    #[test]
+    fn test_deep_reaches_nested_values() {
+        let value = Value::Map(vec![(
+            Value::from("ada@example.com"),
+            Value::Array(vec![Value::from("bob@example.com"), Value::Bool(true)]),
+        )]);
+        assert_eq!(
+            deep(email)(&value),
+            Value::Map(vec![(
+                Value::from("a***@example.com"),
+                Value::Array(vec![Value::from("b***@example.com"), Value::Bool(true)]),
+            )])
+        );
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn test_regex() {
+        let masker = regex(r"\d", "#").unwrap();
+        assert_eq!(masker(&Value::from("a1b22")), Value::from("a#b##"));
+        assert_eq!(masker(&Value::from(12)), Value::from(12));
+        assert!(matches!(
+            regex("(", "").err(),
+            Some(crate::Error::InvalidRegExp(_))
+        ));
+    }
+}
@@ -0,0 +1,184 @@
+//! Page props in the shape `babel-plugin-superjson-next` uses.
+//!
+//! With [superjson-next](https://github.com/blitz-js/babel-plugin-superjson-next),
+//! a Next.js page's `getServerSideProps` or `getStaticProps` returns the
+//! `json` half of its props' envelope as the props themselves, with `meta`
+//! added under the key `_superjson` when there is one:
+//!
+//! ```json
+//! {"props": {"createdAt": "2024-01-01T00:00:00.000Z", "_superjson": {"values": {"createdAt": ["Date"]}, "v": 1}}}
+//! ```
+//!
+//! The page component then restores its props from that object. A Rust
+//! server that pre-renders pages for the plugin writes props with
+//! [`serialize_props`], and one that reads them back, such as from a page's
+//! `__NEXT_DATA__`, uses [`deserialize_props`].
+
+use crate::{Error, ParseOptions, Result, SuperJson, Value, deserialize, serialize};
+
+/// The key under which the plugin stores `meta` in the props.
+pub const META_KEY: &str = "_superjson";
+
+/// Serialize `props`, which must be an object, into the props object the
+/// plugin's page wrapper expects.
+///
+/// # Errors
+/// Returns [`Error::TypeMismatch`] if `props` is not an object.
+///
+/// # Examples
+/// ```
+/// use superjson_rs::{Map, Value, next};
+///
+/// let mut props = Map::new();
+/// props.insert("tags".to_string(), Value::Set(vec![Value::from("a")]));
+/// assert_eq!(
+///     next::serialize_props(&Value::Object(props)).unwrap(),
+///     serde_json::json!({"tags": ["a"], "_superjson": {"values": {"tags": ["set"]}, "v": 1}})
+/// );
+/// ```
+pub fn serialize_props(props: &Value) -> Result<serde_json::Value> {
+    serialize_props_excluding(props, &[])
+}
+
+/// Serialize `props` as [`serialize_props`] does, but leave the props
+/// named in `exclude` out of the envelope, as the plugin's `exclude` option
+/// does.
+///
+/// Excluded props are written as plain JSON, without annotations, and an
+/// excluded prop that is `undefined` is left out.
+pub fn serialize_props_excluding(props: &Value, exclude: &[&str]) -> Result<serde_json::Value> {
+    let Value::Object(map) = props else {
+        return Err(Error::TypeMismatch {
+            path: String::new(),
+            expected: "an object of page props".to_string(),
+            actual: props.type_name().to_string(),
+        });
+    };
+    let mut included = map.clone();
+    let mut excluded = Vec::with_capacity(exclude.len());
+    for &key in exclude {
+        #[cfg(feature = "preserve_order")]
+        let value = included.shift_remove(key);
+        #[cfg(not(feature = "preserve_order"))]
+        let value = included.remove(key);
+        if let Some(value) = value.filter(|v| !matches!(v, Value::Undefined)) {
+            excluded.push((key, serialize::serialize(&value)?.json));
+        }
+    }
+
+    let SuperJson { json, meta } = serialize::serialize(&Value::Object(included))?;
+    let serde_json::Value::Object(mut out) = json else {
+        unreachable!("an object serializes to an object")
+    };
+    if let Some(meta) = meta {
+        out.insert(META_KEY.to_string(), serde_json::to_value(meta)?);
+    }
+    for (key, json) in excluded {
+        out.insert(key.to_string(), json);
+    }
+    Ok(serde_json::Value::Object(out))
+}
+
+/// Restore page props written by the plugin, or by [`serialize_props`].
+///
+/// Props without a `_superjson` key are restored as plain JSON.
+///
+/// # Errors
+/// Returns [`Error::TypeMismatch`] if `props` is not an object.
+///
+/// # Examples
+/// ```
+/// use superjson_rs::{Value, next};
+///
+/// let props = serde_json::json!({"n": "NaN", "_superjson": {"values": {"n": ["number"]}}});
+/// assert_eq!(next::deserialize_props(props).unwrap().pointer("n"), Some(&Value::NaN));
+/// ```
+pub fn deserialize_props(props: serde_json::Value) -> Result<Value> {
+    deserialize_props_with(props, &ParseOptions::default())
+}
+
+/// Restore page props, enforcing the limits in `options`.
+pub fn deserialize_props_with(props: serde_json::Value, options: &ParseOptions) -> Result<Value> {
+    let serde_json::Value::Object(mut props) = props else {
+        return Err(Error::TypeMismatch {
+            path: String::new(),
+            expected: "an object of page props".to_string(),
+            actual: Value::from_json(props).type_name().to_string(),
+        });
+    };
+    let mut envelope = serde_json::Map::new();
+    if let Some(meta) = props.remove(META_KEY).filter(|meta| !meta.is_null()) {
+        envelope.insert("meta".to_string(), meta);
+    }
+    envelope.insert("json".to_string(), serde_json::Value::Object(props));
+    deserialize::deserialize_json_with(serde_json::Value::Object(envelope), options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Map;
+
+    fn props() -> Value {
+        let mut map = Map::new();
+        map.insert("big".to_string(), Value::PosInfinity);
+        map.insert("raw".to_string(), Value::NaN);
+        map.insert("gone".to_string(), Value::Undefined);
+        map.insert("list".to_string(), Value::Array(vec![Value::from(1)]));
+        Value::Object(map)
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let json = serialize_props(&props()).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "big": "Infinity",
+                "raw": "NaN",
+                "gone": null,
+                "list": [1],
+                "_superjson": {
+                    "values": {"big": ["number"], "raw": ["number"], "gone": ["undefined"]},
+                    "v": 1
+                }
+            })
+        );
+        assert_eq!(deserialize_props(json).unwrap(), props());
+    }
+
+    #[test]
+    fn test_exclude() {
+        let json = serialize_props_excluding(&props(), &["raw", "gone", "missing"]).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "big": "Infinity",
+                "raw": "NaN",
+                "list": [1],
+                "_superjson": {"values": {"big": ["number"]}, "v": 1}
+            })
+        );
+        let restored = deserialize_props(json).unwrap();
+        assert_eq!(restored.pointer("raw"), Some(&Value::from("NaN")));
+        assert_eq!(restored.pointer("gone"), None);
+    }
+
+    #[test]
+    fn test_plain_props() {
+        let json = serialize_props(&Value::Object(Map::new())).unwrap();
+        assert_eq!(json, serde_json::json!({}));
+        assert_eq!(
+            deserialize_props(serde_json::json!({"a": 1, "_superjson": null})).unwrap(),
+            Value::from_json(serde_json::json!({"a": 1}))
+        );
+        assert!(matches!(
+            serialize_props(&Value::from(1)),
+            Err(Error::TypeMismatch { .. })
+        ));
+        assert!(matches!(
+            deserialize_props(serde_json::json!([])),
+            Err(Error::TypeMismatch { .. })
+        ));
+    }
+}
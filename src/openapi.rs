@@ -0,0 +1,300 @@
+//! OpenAPI schemas for superjson endpoints, via utoipa.
+//!
+//! The envelope types implement [`ToSchema`], and [`Envelope<T>`] describes
+//! a response or request body that is a superjson envelope whose `json` part
+//! is a `T`:
+//!
+//! ```
+//! use superjson_rs::openapi::Envelope;
+//! use utoipa::openapi::schema::{Object, Schema, Type};
+//! use utoipa::openapi::RefOr;
+//! use utoipa::{PartialSchema, ToSchema};
+//!
+//! struct User;
+//!
+//! impl PartialSchema for User {
+//!     fn schema() -> RefOr<Schema> {
+//!         Object::with_type(Type::Object).into()
+//!     }
+//! }
+//!
+//! impl ToSchema for User {}
+//!
+//! assert_eq!(Envelope::<User>::name(), "SuperJson_User");
+//! let mut schemas = Vec::new();
+//! Envelope::<User>::schemas(&mut schemas);
+//! let names: Vec<_> = schemas.iter().map(|(name, _)| name.as_str()).collect();
+//! assert_eq!(names, ["User", "SuperJsonMeta", "AnnotationValues", "TypeAnnotation"]);
+//! ```
+//!
+//! With utoipa's derive macros, that is `body = Envelope<User>` in a
+//! `#[utoipa::path]` response.
+//!
+//! The schema of `T` is used for `json` as is, so it should describe the
+//! wire form: a `Date` as a `date-time` string, a `Set` as an array, and so
+//! on, which is what the usual utoipa schemas of `chrono` and collection
+//! types already say.
+
+use std::borrow::Cow;
+use std::marker::PhantomData;
+
+use utoipa::openapi::schema::{
+    AnyOfBuilder, ArrayBuilder, ArrayItems, ObjectBuilder, Schema, SchemaType, Type,
+};
+use utoipa::openapi::{Ref, RefOr};
+use utoipa::{PartialSchema, ToSchema};
+
+use crate::{AnnotationValues, Meta, SuperJson, TypeAnnotation};
+
+type Schemas = Vec<(String, RefOr<Schema>)>;
+
+/// A superjson envelope whose `json` part is a `T`, for documenting
+/// endpoints. It is never constructed.
+pub struct Envelope<T>(PhantomData<T>);
+
+impl<T: ToSchema> PartialSchema for Envelope<T> {
+    fn schema() -> RefOr<Schema> {
+        envelope(Ref::from_schema_name(T::name()).into())
+    }
+}
+
+impl<T: ToSchema> ToSchema for Envelope<T> {
+    fn name() -> Cow<'static, str> {
+        format!("SuperJson_{}", T::name()).into()
+    }
+
+    fn schemas(schemas: &mut Schemas) {
+        schemas.push((T::name().into(), T::schema()));
+        T::schemas(schemas);
+        push::<Meta>(schemas);
+    }
+}
+
+impl PartialSchema for SuperJson {
+    fn schema() -> RefOr<Schema> {
+        envelope(
+            ObjectBuilder::new()
+                .schema_type(SchemaType::AnyValue)
+                .into(),
+        )
+    }
+}
+
+impl ToSchema for SuperJson {
+    fn name() -> Cow<'static, str> {
+        "SuperJson".into()
+    }
+
+    fn schemas(schemas: &mut Schemas) {
+        push::<Meta>(schemas);
+    }
+}
+
+impl PartialSchema for Meta {
+    fn schema() -> RefOr<Schema> {
+        ObjectBuilder::new()
+            .property("values", Ref::from_schema_name(AnnotationValues::name()))
+            .property(
+                "referentialEqualities",
+                ObjectBuilder::new().schema_type(SchemaType::AnyValue),
+            )
+            .property(
+                "v",
+                ObjectBuilder::new()
+                    .schema_type(Type::Integer)
+                    .minimum(Some(0))
+                    .maximum(Some(255)),
+            )
+            .property("checksum", ObjectBuilder::new().schema_type(Type::String))
+            .into()
+    }
+}
+
+impl ToSchema for Meta {
+    fn name() -> Cow<'static, str> {
+        "SuperJsonMeta".into()
+    }
+
+    fn schemas(schemas: &mut Schemas) {
+        push::<AnnotationValues>(schemas);
+    }
+}
+
+impl PartialSchema for AnnotationValues {
+    fn schema() -> RefOr<Schema> {
+        let annotation = || Ref::from_schema_name(TypeAnnotation::name());
+        AnyOfBuilder::new()
+            .item(annotation())
+            .item(ObjectBuilder::new().additional_properties(Some(annotation())))
+            .into()
+    }
+}
+
+impl ToSchema for AnnotationValues {
+    fn schemas(schemas: &mut Schemas) {
+        push::<TypeAnnotation>(schemas);
+    }
+}
+
+impl PartialSchema for TypeAnnotation {
+    fn schema() -> RefOr<Schema> {
+        let string = || ObjectBuilder::new().schema_type(Type::String);
+        let children = || {
+            ObjectBuilder::new()
+                .additional_properties(Some(Ref::from_schema_name(TypeAnnotation::name())))
+        };
+        // `[kind, name]`, such as `["custom", name]` or `["symbol", id]`.
+        let identifier = ArrayBuilder::new()
+            .prefix_items([string().build(), string().build()])
+            .items(ArrayItems::False)
+            .min_items(Some(2));
+        let class = ArrayBuilder::new()
+            .prefix_items([
+                string().enum_values(Some(["class"])).build(),
+                string().build(),
+            ])
+            .items(ArrayItems::False)
+            .min_items(Some(2));
+        AnyOfBuilder::new()
+            .description(Some(
+                "A type name with optional annotations of children by key, a class with optional annotations of its fields, or a `[kind, name]` identifier such as a custom type or symbol.",
+            ))
+            .item(
+                ArrayBuilder::new()
+                    .prefix_items([string().build(), children().build()])
+                    .items(ArrayItems::False)
+                    .min_items(Some(1)),
+            )
+            .item(
+                ArrayBuilder::new()
+                    .prefix_items([Schema::from(class.build()), children().build().into()])
+                    .items(ArrayItems::False)
+                    .min_items(Some(1)),
+            )
+            .item(
+                ArrayBuilder::new()
+                    .prefix_items([identifier.build()])
+                    .items(ArrayItems::False)
+                    .min_items(Some(1)),
+            )
+            .into()
+    }
+}
+
+impl ToSchema for TypeAnnotation {}
+
+/// An envelope schema whose `json` property is `json`.
+fn envelope(json: RefOr<Schema>) -> RefOr<Schema> {
+    ObjectBuilder::new()
+        .description(Some(
+            "A superjson envelope: JSON data plus the type annotations needed to restore it.",
+        ))
+        .property("json", json)
+        .required("json")
+        .property("meta", Ref::from_schema_name(Meta::name()))
+        .into()
+}
+
+/// Add `T` and the schemas it refers to, unless already present.
+fn push<T: ToSchema>(schemas: &mut Schemas) {
+    let name = T::name();
+    if !schemas.iter().any(|(existing, _)| *existing == name) {
+        schemas.push((name.into(), T::schema()));
+        T::schemas(schemas);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_envelope_schema() {
+        let schema = serde_json::to_value(Envelope::<String>::schema()).unwrap();
+        assert_eq!(
+            schema["properties"]["json"],
+            json!({ "$ref": "#/components/schemas/String" })
+        );
+        assert_eq!(schema["required"], json!(["json"]));
+
+        let annotation = serde_json::to_value(TypeAnnotation::schema()).unwrap();
+        assert_eq!(
+            annotation["anyOf"][0]["prefixItems"][1]["additionalProperties"],
+            json!({ "$ref": "#/components/schemas/TypeAnnotation" })
+        );
+    }
+
+    #[test]
+    fn test_envelope_schema_accepts_identifiers() {
+        let mut schemas = vec![(SuperJson::name().into(), SuperJson::schema())];
+        SuperJson::schemas(&mut schemas);
+        let schemas: serde_json::Map<_, _> = schemas
+            .into_iter()
+            .map(|(name, schema)| (name, serde_json::to_value(schema).unwrap()))
+            .collect();
+        let document = json!({
+            "$ref": "#/components/schemas/SuperJson",
+            "components": { "schemas": schemas }
+        });
+        let validator = jsonschema::validator_for(&document).unwrap();
+        let symbols = crate::Value::Set(vec![crate::Value::Symbol("admin".into())]);
+        let symbols = crate::serialize::serialize(&symbols).unwrap();
+        // An identifier this crate does not recognise, kept as is.
+        let typed_array = json!({
+            "json": [1, 2],
+            "meta": { "values": [["typed-array", "Int8Array"]], "v": 1 }
+        });
+        for envelope in [serde_json::to_value(symbols).unwrap(), typed_array] {
+            assert!(validator.is_valid(&envelope), "{envelope}");
+        }
+    }
+
+    #[test]
+    fn test_envelope_schema_accepts_classes() {
+        let mut schemas = vec![(SuperJson::name().into(), SuperJson::schema())];
+        SuperJson::schemas(&mut schemas);
+        let schemas: serde_json::Map<_, _> = schemas
+            .into_iter()
+            .map(|(name, schema)| (name, serde_json::to_value(schema).unwrap()))
+            .collect();
+        let document = json!({
+            "$ref": "#/components/schemas/SuperJson",
+            "components": { "schemas": schemas }
+        });
+        let validator = jsonschema::validator_for(&document).unwrap();
+        let mut fields = crate::value::Map::new();
+        fields.insert("x".into(), crate::Value::NaN);
+        let point = crate::Value::Class {
+            name: "Point".into(),
+            fields,
+        };
+        for value in [
+            point.clone(),
+            crate::Value::Array(vec![
+                point,
+                crate::Value::Class {
+                    name: "Empty".into(),
+                    fields: crate::value::Map::new(),
+                },
+            ]),
+        ] {
+            let envelope = crate::serialize::serialize(&value).unwrap();
+            let envelope = serde_json::to_value(envelope).unwrap();
+            assert!(validator.is_valid(&envelope), "{envelope}");
+        }
+        assert!(!validator.is_valid(&json!({"json": {}, "meta": {"values": [["class", 1]]}})));
+    }
+
+    #[test]
+    fn test_schemas_are_listed_once() {
+        let mut schemas = Vec::new();
+        SuperJson::schemas(&mut schemas);
+        Meta::schemas(&mut schemas);
+        let names: Vec<_> = schemas.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(
+            names,
+            ["SuperJsonMeta", "AnnotationValues", "TypeAnnotation"]
+        );
+    }
+}
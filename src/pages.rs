@@ -0,0 +1,131 @@
+//! Splitting a large array into independent envelopes.
+//!
+//! [`paginate`] cuts a root array into pages of at most `chunk_size` items.
+//! Each page is a complete envelope holding an array, with annotations
+//! indexed from the start of the page, so pages can be sent, stored and
+//! parsed one at a time. [`reassemble`] joins restored pages back together.
+
+use indexmap::IndexMap;
+
+use crate::{AnnotationValues, Error, Meta, Result, SuperJson, Value, deserialize, serialize};
+
+/// Split the array `value` into envelopes of at most `chunk_size` items.
+///
+/// An empty array yields no pages.
+///
+/// # Errors
+/// Returns [`Error::TypeMismatch`] if `value` is not an array.
+///
+/// # Panics
+/// Panics if `chunk_size` is 0.
+///
+/// # Examples
+/// ```
+/// use superjson_rs::pages::{paginate, reassemble};
+/// use superjson_rs::Value;
+///
+/// let rows = Value::from(vec![Value::from(1), Value::Undefined, Value::NaN]);
+/// let pages = paginate(&rows, 2).unwrap();
+/// assert_eq!(
+///     serde_json::to_string(&pages[1]).unwrap(),
+///     r#"{"json":["NaN"],"meta":{"values":{"0":["number"]},"v":1}}"#
+/// );
+/// assert_eq!(reassemble(&pages).unwrap(), rows);
+/// ```
+pub fn paginate(value: &Value, chunk_size: usize) -> Result<Vec<SuperJson>> {
+    assert!(chunk_size > 0, "chunk_size must be non-zero");
+    let items = match value {
+        Value::Array(items) => items,
+        other => {
+            return Err(Error::TypeMismatch {
+                path: String::new(),
+                expected: "array".to_string(),
+                actual: other.type_name().to_string(),
+            });
+        }
+    };
+
+    items
+        .chunks(chunk_size)
+        .map(|chunk| {
+            let mut json = Vec::with_capacity(chunk.len());
+            let mut annotations = IndexMap::new();
+            for (i, item) in chunk.iter().enumerate() {
+                let (item_json, item_annotations) =
+                    serialize::serialize_child(item, &i.to_string())?;
+                json.push(item_json);
+                annotations.extend(item_annotations);
+            }
+            let meta = (!annotations.is_empty()).then(|| Meta {
+                values: Some(AnnotationValues::Children(annotations)),
+                referential_equalities: None,
+                v: Some(crate::META_VERSION),
+                checksum: None,
+            });
+            Ok(SuperJson {
+                json: serde_json::Value::Array(json),
+                meta,
+            })
+        })
+        .collect()
+}
+
+/// Restore pages made by [`paginate`] and join their items, in order, into
+/// one array.
+///
+/// # Errors
+/// Returns the first error restoring a page, or [`Error::TypeMismatch`] for
+/// a page that does not hold an array.
+pub fn reassemble(pages: &[SuperJson]) -> Result<Value> {
+    let mut items = Vec::new();
+    for (i, page) in pages.iter().enumerate() {
+        match deserialize::deserialize(page)? {
+            Value::Array(page_items) => items.extend(page_items),
+            other => {
+                return Err(Error::TypeMismatch {
+                    path: format!("page {i}"),
+                    expected: "array".to_string(),
+                    actual: other.type_name().to_string(),
+                });
+            }
+        }
+    }
+    Ok(Value::Array(items))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pages_stand_alone() {
+        let rows: Vec<Value> = (0..5)
+            .map(|i| Value::Set(vec![Value::from(i)]))
+            .chain([Value::from("plain")])
+            .collect();
+        let rows = Value::Array(rows);
+        let pages = paginate(&rows, 4).unwrap();
+        assert_eq!(pages.len(), 2);
+
+        // Each page parses on its own, with annotations re-indexed.
+        let text = serde_json::to_string(&pages[1]).unwrap();
+        assert_eq!(
+            crate::parse(&text).unwrap(),
+            Value::from(vec![Value::Set(vec![Value::from(4)]), Value::from("plain")])
+        );
+        assert_eq!(reassemble(&pages).unwrap(), rows);
+    }
+
+    #[test]
+    fn test_plain_pages_and_errors() {
+        let pages = paginate(&Value::from(vec![1, 2, 3]), 2).unwrap();
+        assert!(pages.iter().all(|page| page.meta.is_none()));
+        assert!(paginate(&Value::from(vec![0; 0]), 3).unwrap().is_empty());
+        assert!(matches!(
+            paginate(&Value::Null, 3),
+            Err(Error::TypeMismatch { .. })
+        ));
+        let not_array = crate::serialize::serialize(&Value::from(1)).unwrap();
+        assert!(reassemble(&[not_array]).is_err());
+    }
+}
@@ -0,0 +1,404 @@
+//! Parsing that leaves unannotated subtrees as raw JSON text.
+//!
+//! A gateway that only rewrites a few typed fields of a large body does not
+//! need the rest of it as values. [`parse`] reads the envelope's annotations
+//! first and then only descends into the parts of `json` that lead to an
+//! annotated value. Every subtree without annotations at or below it is
+//! kept as a [`RawValue`], the text it was received as, which is checked to
+//! be valid JSON but not otherwise parsed. [`Value::stringify`] writes the
+//! envelope back, copying raw subtrees verbatim.
+//!
+//! As raw subtrees are never restored, the depth limit of
+//! [`ParseOptions`] only applies to the containers on the way to annotated
+//! values, and to those values themselves. `meta.referentialEqualities` are
+//! not restored.
+
+use std::borrow::Cow;
+use std::fmt;
+
+use indexmap::IndexMap;
+use serde::Deserialize;
+use serde::de::{Deserializer, MapAccess, Visitor};
+use serde_json::value::RawValue;
+
+use crate::borrowed::Key;
+use crate::path::{PathSegment, escape_key};
+use crate::value::Map;
+use crate::{
+    AnnotationValues, META_VERSION, Meta, ParseOptions, Result, TypeAnnotation, Warning,
+    deserialize, envelope,
+};
+
+/// A parsed value whose unannotated subtrees are kept as raw JSON text.
+#[derive(Debug, Clone)]
+pub enum Value<'a> {
+    /// A subtree with no annotations at or below it, as received.
+    Raw(&'a RawValue),
+    /// An array with annotated values below it.
+    Array(Vec<Value<'a>>),
+    /// An object with annotated values below it.
+    Object(Map<Cow<'a, str>, Value<'a>>),
+    /// A value with a type annotation of its own, restored as an owned
+    /// [`Value`](crate::Value).
+    Extended(crate::Value),
+}
+
+/// Parse a superjson envelope, keeping subtrees without annotations as
+/// raw JSON text.
+///
+/// # Examples
+/// ```
+/// use superjson_rs::passthrough::{self, Value as Passthrough};
+/// use superjson_rs::Value;
+///
+/// let body = br#"{"json":{"items":[1,2,3],"user":{"name":"ada","seen":"NaN"}},
+///                 "meta":{"values":{"user.seen":["number"]}}}"#;
+/// let mut parsed = passthrough::parse(body).unwrap();
+/// assert!(matches!(parsed.pointer("items"), Some(Passthrough::Raw(raw)) if raw.get() == "[1,2,3]"));
+///
+/// *parsed.pointer_mut("user.seen").unwrap() = Passthrough::Extended(Value::PosInfinity);
+/// assert_eq!(
+///     parsed.stringify().unwrap(),
+///     r#"{"json":{"items":[1,2,3],"user":{"name":"ada","seen":"Infinity"}},"meta":{"values":{"user.seen":["number"]},"v":1}}"#
+/// );
+/// ```
+pub fn parse(bytes: &[u8]) -> Result<Value<'_>> {
+    parse_with(bytes, &ParseOptions::default())
+}
+
+/// Parse a superjson envelope with explicit limits, keeping subtrees without
+/// annotations as raw JSON text.
+pub fn parse_with<'a>(bytes: &'a [u8], options: &ParseOptions) -> Result<Value<'a>> {
+    parse_detailed(bytes, options).map(|parsed| parsed.value)
+}
+
+/// The result of [`parse_detailed`].
+#[derive(Debug, Clone)]
+pub struct Parsed<'a> {
+    pub value: Value<'a>,
+    /// Non-fatal issues noticed while parsing, in the order they were found.
+    pub warnings: Vec<Warning>,
+}
+
+/// Parse a superjson envelope with explicit limits, keeping subtrees without
+/// annotations as raw JSON text and also reporting non-fatal [`Warning`]s.
+///
+/// # Examples
+/// ```
+/// # #[cfg(feature = "chrono")] {
+/// use superjson_rs::passthrough;
+/// use superjson_rs::{ParseOptions, Warning};
+///
+/// let body = br#"{"json":{"at":"2024-01-01T09:00:00.000+09:00"},"meta":{"values":{"at":["Date"]}}}"#;
+/// let parsed = passthrough::parse_detailed(body, &ParseOptions::default()).unwrap();
+/// assert!(matches!(&parsed.warnings[..], [Warning::CoercedDate { path, .. }] if path == "at"));
+/// # }
+/// ```
+pub fn parse_detailed<'a>(bytes: &'a [u8], options: &ParseOptions) -> Result<Parsed<'a>> {
+    let envelope: envelope::RawSuperJson<&'a RawValue> = serde_json::from_slice(bytes)?;
+    let mut warnings = Vec::new();
+    let (json, meta) = envelope.into_parts(options, &mut warnings)?;
+    let value = deserialize::deserialize_passthrough(json, meta.as_ref(), options, &mut warnings)?;
+    Ok(Parsed { value, warnings })
+}
+
+impl<'a> Value<'a> {
+    /// Look up a value by superjson path, such as `"users.0.name"`.
+    ///
+    /// Only objects and arrays that were descended into are searched; a path
+    /// into a raw subtree or an extended value is `None`.
+    pub fn pointer(&self, path: &str) -> Option<&Value<'a>> {
+        let mut current = self;
+        for segment in crate::path::parse(path) {
+            current = match (current, segment) {
+                (Value::Object(map), segment) => map.get(key_of(segment).as_str())?,
+                (Value::Array(items), PathSegment::Index(index)) => items.get(index)?,
+                _ => return None,
+            };
+        }
+        Some(current)
+    }
+
+    /// Look up a value by superjson path for replacing it, as
+    /// [`pointer`](Value::pointer) does.
+    pub fn pointer_mut(&mut self, path: &str) -> Option<&mut Value<'a>> {
+        let mut current = self;
+        for segment in crate::path::parse(path) {
+            current = match (current, segment) {
+                (Value::Object(map), segment) => map.get_mut(key_of(segment).as_str())?,
+                (Value::Array(items), PathSegment::Index(index)) => items.get_mut(index)?,
+                _ => return None,
+            };
+        }
+        Some(current)
+    }
+
+    /// Parse the raw subtrees, producing an owned [`Value`](crate::Value).
+    ///
+    /// Each raw subtree is limited to
+    /// [`DEFAULT_MAX_DEPTH`](crate::DEFAULT_MAX_DEPTH) levels of nesting.
+    pub fn into_owned(self) -> Result<crate::Value> {
+        Ok(match self {
+            Value::Raw(raw) => crate::Value::from_json_str(raw.get())?,
+            Value::Array(items) => crate::Value::Array(
+                items
+                    .into_iter()
+                    .map(Value::into_owned)
+                    .collect::<Result<_>>()?,
+            ),
+            Value::Object(map) => crate::Value::Object(
+                map.into_iter()
+                    .map(|(key, value)| Ok((key.into_owned(), value.into_owned()?)))
+                    .collect::<Result<_>>()?,
+            ),
+            Value::Extended(value) => value,
+        })
+    }
+
+    /// Write the value as a superjson envelope, with raw subtrees copied as
+    /// received and annotations for the extended values.
+    pub fn stringify(&self) -> Result<String> {
+        let mut writer = EnvelopeWriter {
+            json: String::new(),
+            path: Vec::new(),
+            root: None,
+            children: IndexMap::new(),
+        };
+        writer.write(self)?;
+
+        let values = match writer.root {
+            Some(ann) => Some(AnnotationValues::Root(ann)),
+            None if writer.children.is_empty() => None,
+            None => Some(AnnotationValues::Children(writer.children)),
+        };
+        let mut text = format!("{{\"json\":{}", writer.json);
+        if let Some(values) = values {
+            let meta = Meta {
+                values: Some(values),
+                referential_equalities: None,
+                v: Some(META_VERSION),
+                checksum: None,
+            };
+            text.push_str(",\"meta\":");
+            text.push_str(&serde_json::to_string(&meta)?);
+        }
+        text.push('}');
+        Ok(text)
+    }
+}
+
+/// The key a path segment names in an object.
+fn key_of(segment: PathSegment) -> String {
+    match segment {
+        PathSegment::Key(key) => key,
+        PathSegment::Index(index) => index.to_string(),
+    }
+}
+
+/// Writes the `json` part of an envelope, collecting the annotations of the
+/// extended values it meets.
+struct EnvelopeWriter {
+    json: String,
+    /// Escaped path keys from the root to the current value.
+    path: Vec<String>,
+    /// The annotation of an extended value at the root.
+    root: Option<TypeAnnotation>,
+    children: IndexMap<String, TypeAnnotation>,
+}
+
+impl EnvelopeWriter {
+    /// The path of `key` below the current value.
+    fn child_path(&self, key: &str) -> String {
+        if self.path.is_empty() {
+            key.to_string()
+        } else {
+            format!("{}.{key}", self.path.join("."))
+        }
+    }
+
+    fn write(&mut self, value: &Value) -> Result<()> {
+        match value {
+            Value::Raw(raw) => self.json.push_str(raw.get()),
+            Value::Array(items) => {
+                self.json.push('[');
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        self.json.push(',');
+                    }
+                    self.path.push(i.to_string());
+                    self.write(item)?;
+                    self.path.pop();
+                }
+                self.json.push(']');
+            }
+            Value::Object(map) => {
+                self.json.push('{');
+                for (i, (key, field)) in map.iter().enumerate() {
+                    if i > 0 {
+                        self.json.push(',');
+                    }
+                    self.json.push_str(&serde_json::to_string(key.as_ref())?);
+                    self.json.push(':');
+                    self.path.push(escape_key(key));
+                    self.write(field)?;
+                    self.path.pop();
+                }
+                self.json.push('}');
+            }
+            Value::Extended(value) => {
+                let superjson = crate::serialize::serialize(value)?;
+                self.json.push_str(&serde_json::to_string(&superjson.json)?);
+                match superjson.meta.and_then(|meta| meta.values) {
+                    Some(AnnotationValues::Root(ann)) if self.path.is_empty() => {
+                        self.root = Some(ann);
+                    }
+                    Some(AnnotationValues::Root(ann)) => {
+                        self.children.insert(self.path.join("."), ann);
+                    }
+                    Some(AnnotationValues::Children(children)) => {
+                        for (key, ann) in children {
+                            self.children.insert(self.child_path(&key), ann);
+                        }
+                    }
+                    None => {}
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A raw value split into its children, one level deep.
+pub(crate) enum RawContainer<'a> {
+    Array(Vec<&'a RawValue>),
+    Object(Vec<(Cow<'a, str>, &'a RawValue)>),
+    Scalar,
+}
+
+impl<'a> RawContainer<'a> {
+    pub(crate) fn split(raw: &'a RawValue) -> Result<Self> {
+        let text = raw.get();
+        Ok(match text.trim_start().as_bytes().first() {
+            Some(b'[') => RawContainer::Array(serde_json::from_str(text)?),
+            Some(b'{') => RawContainer::Object(serde_json::from_str::<RawFields>(text)?.0),
+            _ => RawContainer::Scalar,
+        })
+    }
+}
+
+/// The fields of a raw object, in the order received.
+struct RawFields<'a>(Vec<(Cow<'a, str>, &'a RawValue)>);
+
+impl<'de: 'a, 'a> Deserialize<'de> for RawFields<'a> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        deserializer.deserialize_map(RawFieldsVisitor)
+    }
+}
+
+struct RawFieldsVisitor;
+
+impl<'de> Visitor<'de> for RawFieldsVisitor {
+    type Value = RawFields<'de>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a JSON object")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(
+        self,
+        mut map: A,
+    ) -> std::result::Result<RawFields<'de>, A::Error> {
+        let mut fields = Vec::new();
+        while let Some((Key(key), value)) = map.next_entry()? {
+            fields.push((key, value));
+        }
+        Ok(RawFields(fields))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_only_annotated_paths_are_descended() {
+        let body = br#"{"json":{"a":{"b":[1,{"c":"x"}],"d":"NaN"},"e":[]},
+                        "meta":{"values":{"a.d":["number"]}}}"#;
+        let parsed = parse(body).unwrap();
+        let Value::Object(root) = &parsed else {
+            panic!("expected an object");
+        };
+        assert!(matches!(&root["e"], Value::Raw(raw) if raw.get() == "[]"));
+        assert!(
+            matches!(parsed.pointer("a.b"), Some(Value::Raw(raw)) if raw.get() == r#"[1,{"c":"x"}]"#)
+        );
+        assert!(matches!(
+            parsed.pointer("a.d"),
+            Some(Value::Extended(crate::Value::NaN))
+        ));
+        assert_eq!(
+            parsed.into_owned().unwrap(),
+            crate::parse(std::str::from_utf8(body).unwrap()).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_stringify_roundtrips() {
+        let text = r#"{"json":{"k.k":{"n":"-0"},"plain":{"a":1},"s":[["x"],1]},"meta":{"values":{"k\\.k.n":["number"],"s":["set",{"0":["set"]}]},"v":1}}"#;
+        let parsed = parse(text.as_bytes()).unwrap();
+        assert_eq!(parsed.stringify().unwrap(), text);
+
+        let root = parse(br#"{"json":"NaN","meta":{"values":["number"]}}"#).unwrap();
+        assert!(matches!(root, Value::Extended(crate::Value::NaN)));
+        assert_eq!(
+            root.stringify().unwrap(),
+            r#"{"json":"NaN","meta":{"values":["number"],"v":1}}"#
+        );
+        let plain = parse(br#"{"json": [1, 2]}"#).unwrap();
+        assert_eq!(plain.stringify().unwrap(), r#"{"json":[1, 2]}"#);
+    }
+
+    #[test]
+    fn test_warnings_are_reported() {
+        let body = br#"{"json":{"a":[{"u":null}],"b":{"c":1}},"meta":{"values":{"a.0.u":["undefined"]},"extra":1}}"#;
+        let parsed = parse_detailed(body, &ParseOptions::default()).unwrap();
+        assert!(matches!(
+            parsed.value.pointer("a.0.u"),
+            Some(Value::Extended(crate::Value::Undefined))
+        ));
+        assert_eq!(
+            parsed.warnings,
+            [Warning::UnknownField {
+                name: "meta.extra".into()
+            }]
+        );
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn test_date_warnings_have_their_path() {
+        let body = br#"{"json":{"a":[{"at":"2024-01-01T09:00:00.000+09:00"}]},"meta":{"values":{"a.0.at":["Date"]}}}"#;
+        let parsed = parse_detailed(body, &ParseOptions::default()).unwrap();
+        assert!(matches!(
+            parsed.warnings.as_slice(),
+            [Warning::CoercedDate { path, .. }] if path == "a.0.at"
+        ));
+    }
+
+    #[test]
+    fn test_depth_limit() {
+        let options = ParseOptions {
+            max_depth: 2,
+            ..ParseOptions::default()
+        };
+        // Raw subtrees are not counted.
+        assert!(parse_with(br#"{"json":{"a":{"b":{"c":1}}}}"#, &options).is_ok());
+        assert!(matches!(
+            parse_with(
+                br#"{"json":{"a":{"b":{"c":1}}},"meta":{"values":{"a.b.c":["undefined"]}}}"#,
+                &options
+            ),
+            Err(crate::Error::DepthLimitExceeded(2))
+        ));
+    }
+}
@@ -0,0 +1,251 @@
+//! Partial updates addressed to a subtree of a document.
+//!
+//! A [`SuperJsonPatch`] pairs a superjson path with an envelope. The
+//! envelope is an ordinary one rooted at the target subtree: its
+//! annotations are relative to that subtree, not to the whole document, so
+//! a server can produce it with [`serialize`](crate::serialize::serialize)
+//! on the new subtree alone.
+
+use serde::{Deserialize, Serialize};
+
+use crate::deserialize::{self, MergeStrategy};
+use crate::path::{self, PathSegment};
+use crate::{Error, ParseOptions, Result, SuperJson, Value, envelope, serialize};
+
+/// An envelope to be applied at `path` within an existing value.
+///
+/// On the wire this is `{"path": "...", "envelope": {"json": ..., "meta": ...}}`.
+///
+/// # Examples
+/// ```
+/// use superjson_rs::patch::SuperJsonPatch;
+/// use superjson_rs::{MergeStrategy, Value, parse};
+///
+/// let mut doc = parse(r#"{"json":{"rooms":[{"name":"lobby","members":[]}]},
+///                         "meta":{"values":{"rooms.0.members":["set"]}}}"#).unwrap();
+///
+/// let members = Value::Set(vec![Value::from("ada")]);
+/// let patch = SuperJsonPatch::new("rooms.0.members", &members).unwrap();
+/// patch.apply(&mut doc, MergeStrategy::Replace).unwrap();
+///
+/// let room = &doc.as_object().unwrap()["rooms"].as_array().unwrap()[0];
+/// assert_eq!(room.as_object().unwrap()["members"], members);
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SuperJsonPatch {
+    pub path: String,
+    pub envelope: SuperJson,
+}
+
+impl SuperJsonPatch {
+    /// A patch setting the subtree at `path` to `value`.
+    pub fn new(path: &str, value: &Value) -> Result<Self> {
+        Ok(SuperJsonPatch {
+            path: path.to_string(),
+            envelope: serialize::serialize(value)?,
+        })
+    }
+
+    /// Apply the patch to `target`, combining the envelope with the subtree
+    /// at `path` according to `strategy`.
+    ///
+    /// A missing object field at the end of the path is created. Any other
+    /// missing step is reported as [`Error::InvalidPath`].
+    pub fn apply(&self, target: &mut Value, strategy: MergeStrategy) -> Result<()> {
+        self.apply_with(target, strategy, &ParseOptions::default())
+    }
+
+    /// Apply the patch, enforcing the limits in `options`.
+    ///
+    /// The depth limit counts from the root of `target`, so a patch cannot
+    /// grow a document deeper than parsing it whole would allow.
+    pub fn apply_with(
+        &self,
+        target: &mut Value,
+        strategy: MergeStrategy,
+        options: &ParseOptions,
+    ) -> Result<()> {
+        let segments = path::parse(&self.path);
+        let exceeded = || Error::DepthLimitExceeded(options.max_depth);
+        let subtree_options = ParseOptions {
+            max_depth: options
+                .max_depth
+                .checked_sub(segments.len())
+                .ok_or_else(exceeded)?,
+            ..options.clone()
+        };
+        let slot = resolve_mut(target, &self.path, segments)?;
+        deserialize::deserialize_into(&self.envelope, slot, strategy, &subtree_options).map_err(
+            |e| match e {
+                Error::DepthLimitExceeded(_) => exceeded(),
+                e => e,
+            },
+        )
+    }
+}
+
+/// Parse the envelope `s` and apply it at `path` within `target`.
+///
+/// Equivalent to applying a [`SuperJsonPatch`] built from `path` and `s`.
+pub fn apply_at_path(
+    target: &mut Value,
+    path: &str,
+    s: &str,
+    strategy: MergeStrategy,
+) -> Result<()> {
+    let options = ParseOptions::default();
+    let raw: envelope::RawSuperJson = serde_json::from_str(s)?;
+    let patch = SuperJsonPatch {
+        path: path.to_string(),
+        envelope: raw.into_superjson(&options, &mut Vec::new())?,
+    };
+    patch.apply_with(target, strategy, &options)
+}
+
+/// Find the value at `segments` within `target`.
+///
+/// Paths are resolved as by [`Value::pointer`].
+pub(crate) fn resolve_mut<'a>(
+    target: &'a mut Value,
+    path: &str,
+    segments: Vec<PathSegment>,
+) -> Result<&'a mut Value> {
+    let missing = || Error::InvalidPath(format!("no value at '{path}'"));
+    let count = segments.len();
+    let mut segments = segments.into_iter().enumerate();
+    let mut current = target;
+
+    while let Some((i, segment)) = segments.next() {
+        let last = i + 1 == count;
+        current = match (current, segment) {
+            (Value::Object(map) | Value::Class { fields: map, .. }, segment) => {
+                let key = match segment {
+                    PathSegment::Key(key) => key,
+                    PathSegment::Index(index) => index.to_string(),
+                };
+                if last && !map.contains_key(&key) {
+                    map.insert(key.clone(), Value::Undefined);
+                }
+                map.get_mut(&key).ok_or_else(missing)?
+            }
+            (Value::Array(items) | Value::Set(items), PathSegment::Index(index)) => {
+                items.get_mut(index).ok_or_else(missing)?
+            }
+            (Value::Map(entries), PathSegment::Index(index)) => {
+                let entry = entries.get_mut(index).ok_or_else(missing)?;
+                match segments.next() {
+                    Some((_, PathSegment::Index(0))) => &mut entry.0,
+                    Some((_, PathSegment::Index(1))) => &mut entry.1,
+                    _ => return Err(missing()),
+                }
+            }
+            (Value::Error { cause, extra, .. }, segment) => {
+                let key = match segment {
+                    PathSegment::Key(key) if key == "cause" => {
+                        current = cause.as_deref_mut().ok_or_else(missing)?;
+                        continue;
+                    }
+                    PathSegment::Key(key) => key,
+                    PathSegment::Index(index) => index.to_string(),
+                };
+                if last && !extra.contains_key(&key) {
+                    extra.insert(key.clone(), Value::Undefined);
+                }
+                extra.get_mut(&key).ok_or_else(missing)?
+            }
+            _ => return Err(missing()),
+        };
+    }
+    Ok(current)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn doc() -> Value {
+        crate::parse(
+            r#"{"json":{"a":{"b":[1,2]},"m":[["k",{"x":1}]]},"meta":{"values":{"m":["map"]}}}"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_apply_reroots_annotations() {
+        let mut value = doc();
+        let patch: SuperJsonPatch = serde_json::from_str(
+            r#"{"path":"a.b.1","envelope":{"json":[1,"NaN"],"meta":{"values":["set",{"1":["number"]}]}}}"#,
+        )
+        .unwrap();
+        patch.apply(&mut value, MergeStrategy::Replace).unwrap();
+        assert_eq!(
+            value.pointer("a.b.1").unwrap(),
+            &Value::Set(vec![Value::from(1), Value::NaN])
+        );
+        assert_eq!(value.pointer("a.b.0").unwrap(), &Value::from(1));
+    }
+
+    #[test]
+    fn test_apply_merges_into_map_value() {
+        let mut value = doc();
+        apply_at_path(
+            &mut value,
+            "m.0.1",
+            r#"{"json":{"y":2}}"#,
+            MergeStrategy::Deep,
+        )
+        .unwrap();
+        let expected = crate::parse(r#"{"json":{"x":1,"y":2}}"#).unwrap();
+        assert_eq!(value.pointer("m.0.1").unwrap(), &expected);
+    }
+
+    #[test]
+    fn test_apply_creates_missing_field() {
+        let mut value = doc();
+        apply_at_path(&mut value, "a.c", r#"{"json":true}"#, MergeStrategy::Deep).unwrap();
+        assert_eq!(value.pointer("a.c").unwrap(), &Value::Bool(true));
+    }
+
+    #[test]
+    fn test_apply_at_leading_zero_key() {
+        let mut value = crate::parse(r#"{"json":{"007":"bond","7":"seven"}}"#).unwrap();
+        assert_eq!(value.pointer("007"), Some(&Value::from("bond")));
+        apply_at_path(
+            &mut value,
+            "007",
+            r#"{"json":"james"}"#,
+            MergeStrategy::Replace,
+        )
+        .unwrap();
+        assert_eq!(value.pointer("007"), Some(&Value::from("james")));
+        assert_eq!(value.pointer("7"), Some(&Value::from("seven")));
+    }
+
+    #[test]
+    fn test_apply_missing_path() {
+        let mut value = doc();
+        for path in ["x.y", "a.b.5", "m.0.2", "m.0"] {
+            assert!(
+                matches!(
+                    apply_at_path(&mut value, path, r#"{"json":1}"#, MergeStrategy::Deep),
+                    Err(Error::InvalidPath(_))
+                ),
+                "{path}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_apply_depth_counts_from_document_root() {
+        let mut value = doc();
+        let patch = SuperJsonPatch::new("a.b", &Value::Array(vec![Value::Array(vec![])])).unwrap();
+        let options = ParseOptions {
+            max_depth: 3,
+            ..ParseOptions::default()
+        };
+        assert!(matches!(
+            patch.apply_with(&mut value, MergeStrategy::Replace, &options),
+            Err(Error::DepthLimitExceeded(3))
+        ));
+    }
+}
@@ -8,6 +8,18 @@ pub enum PathSegment {
     Index(usize),
 }
 
+impl From<usize> for PathSegment {
+    fn from(index: usize) -> Self {
+        PathSegment::Index(index)
+    }
+}
+
+impl From<&str> for PathSegment {
+    fn from(key: &str) -> Self {
+        PathSegment::Key(key.to_string())
+    }
+}
+
 /// Escape a key for use in a superjson dot-notation path.
 ///
 /// Backslashes are escaped as `\\` and dots are escaped as `\.`.
@@ -100,10 +112,132 @@ pub fn parse(path: &str) -> Vec<PathSegment> {
     segments
 }
 
+/// An index for a segment written as an array index is, such as `7`, and a
+/// key for anything else, such as `007` or `+7`, so that the object key keeps
+/// its text.
 fn make_segment(s: &str) -> PathSegment {
-    s.parse::<usize>()
+    let canonical = s.bytes().all(|b| b.is_ascii_digit()) && (s == "0" || !s.starts_with('0'));
+    canonical
+        .then(|| s.parse::<usize>().ok())
+        .flatten()
         .map(PathSegment::Index)
-        .unwrap_or_else(|_| PathSegment::Key(s.to_string()))
+        .unwrap_or_else(|| PathSegment::Key(s.to_string()))
+}
+
+/// A pattern selecting superjson paths, such as `"user.email"`.
+///
+/// Patterns use the same dot notation and escaping as paths. A `*` segment
+/// matches any single segment and a `**` segment matches any number of
+/// segments, including none.
+///
+/// # Examples
+/// ```
+/// use superjson_rs::path::PathPattern;
+///
+/// let pattern = PathPattern::new("users.*.email");
+/// assert!(pattern.matches("users.0.email"));
+/// assert!(!pattern.matches("users.0.name"));
+///
+/// let anywhere = PathPattern::new("**.password");
+/// assert!(anywhere.matches("password"));
+/// assert!(anywhere.matches("a.b.password"));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct PathPattern {
+    segments: Vec<PatternSegment>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum PatternSegment {
+    /// An escaped path segment that must match exactly.
+    Literal(String),
+    /// `*`
+    Any,
+    /// `**`
+    AnyDepth,
+}
+
+impl PathPattern {
+    pub fn new(pattern: &str) -> Self {
+        let segments = split(pattern)
+            .into_iter()
+            .map(|segment| match segment {
+                "*" => PatternSegment::Any,
+                "**" => PatternSegment::AnyDepth,
+                literal => PatternSegment::Literal(literal.to_string()),
+            })
+            .collect();
+        PathPattern { segments }
+    }
+
+    /// Whether `path`, in escaped dot notation, matches this pattern.
+    pub fn matches(&self, path: &str) -> bool {
+        let path = split(path);
+
+        // Positions in the pattern that the path prefix read so far can be
+        // at; `**` makes this a set rather than a single position.
+        let mut states = vec![false; self.segments.len() + 1];
+        states[0] = true;
+        self.skip_any_depth(&mut states);
+
+        for segment in path {
+            let mut next = vec![false; states.len()];
+            for (i, pattern) in self.segments.iter().enumerate() {
+                if !states[i] {
+                    continue;
+                }
+                match pattern {
+                    PatternSegment::Literal(literal) if literal == segment => next[i + 1] = true,
+                    PatternSegment::Literal(_) => {}
+                    PatternSegment::Any => next[i + 1] = true,
+                    PatternSegment::AnyDepth => next[i] = true,
+                }
+            }
+            self.skip_any_depth(&mut next);
+            states = next;
+        }
+
+        states[self.segments.len()]
+    }
+
+    /// Let every `**` reachable in `states` also match zero segments.
+    fn skip_any_depth(&self, states: &mut [bool]) {
+        for (i, pattern) in self.segments.iter().enumerate() {
+            if states[i] && *pattern == PatternSegment::AnyDepth {
+                states[i + 1] = true;
+            }
+        }
+    }
+}
+
+impl From<&str> for PathPattern {
+    fn from(pattern: &str) -> Self {
+        PathPattern::new(pattern)
+    }
+}
+
+/// Split a path on unescaped dots, keeping each segment escaped.
+fn split(path: &str) -> Vec<&str> {
+    if path.is_empty() {
+        return vec![];
+    }
+
+    let mut segments = Vec::new();
+    let mut start = 0;
+    let mut escaped = false;
+    for (i, ch) in path.char_indices() {
+        match ch {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '.' => {
+                segments.push(&path[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    segments.push(&path[start..]);
+    segments
 }
 
 #[cfg(test)]
@@ -197,6 +331,20 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_parse_non_canonical_index_is_key() {
+        assert_eq!(
+            parse("0.007.01.+1.10"),
+            vec![
+                PathSegment::Index(0),
+                PathSegment::Key("007".into()),
+                PathSegment::Key("01".into()),
+                PathSegment::Key("+1".into()),
+                PathSegment::Index(10),
+            ]
+        );
+    }
+
     #[test]
     fn test_roundtrip() {
         let segments = vec![
@@ -216,4 +364,30 @@ mod tests {
         ];
         assert_eq!(parse(&join(&segments)), segments);
     }
+
+    #[test]
+    fn test_pattern_literal() {
+        let pattern = PathPattern::new("a.0.b");
+        assert!(pattern.matches("a.0.b"));
+        assert!(!pattern.matches("a.0"));
+        assert!(!pattern.matches("a.0.b.c"));
+    }
+
+    #[test]
+    fn test_pattern_escaped_dot() {
+        let pattern = PathPattern::new("a\\.b");
+        assert!(pattern.matches("a\\.b"));
+        assert!(!pattern.matches("a.b"));
+    }
+
+    #[test]
+    fn test_pattern_wildcards() {
+        assert!(PathPattern::new("*").matches("x"));
+        assert!(!PathPattern::new("*").matches(""));
+        assert!(PathPattern::new("**").matches(""));
+        assert!(PathPattern::new("a.**.z").matches("a.z"));
+        assert!(PathPattern::new("a.**.z").matches("a.b.c.z"));
+        assert!(!PathPattern::new("a.**.z").matches("a.b.c"));
+        assert!(PathPattern::new("**.*.id").matches("x.y.id"));
+    }
 }
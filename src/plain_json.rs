@@ -0,0 +1,411 @@
+//! Lossy export of a [`Value`] to vanilla JSON.
+//!
+//! A downstream system that only speaks JSON cannot read an envelope, and
+//! `meta` is the only place extended types survive. [`Value::into_plain_json`]
+//! drops it and degrades each extended type as a [`PlainJsonPolicy`] says.
+//! The defaults follow `JSON.stringify`, except that Dates and bigints keep
+//! their value as strings instead of being lost or throwing.
+//!
+//! Sets become arrays, RegExps their `/source/flags` string, URLs their
+//! string, symbols their identifier, bytes their base64 string, Errors an
+//! object with `name`, `message`, `cause` if present and their extra
+//! properties, and custom values their JSON form.
+
+use crate::Value;
+use crate::bytes::BytesEncoding;
+use crate::value::Map;
+
+type Json = serde_json::Value;
+
+/// How [`Value::into_plain_json`] degrades extended types.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PlainJsonPolicy {
+    pub dates: DatePolicy,
+    pub bigints: BigIntPolicy,
+    pub undefined: UndefinedPolicy,
+    pub maps: MapPolicy,
+    pub special_numbers: SpecialNumberPolicy,
+}
+
+/// How a Date is written.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DatePolicy {
+    /// An ISO 8601 string with milliseconds, as `Date.prototype.toJSON`
+    /// writes it.
+    #[default]
+    IsoString,
+    /// Milliseconds since the Unix epoch.
+    EpochMillis,
+}
+
+/// How a bigint is written.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BigIntPolicy {
+    /// Its decimal digits as a string.
+    #[default]
+    String,
+    /// A number, exact if it fits in 64 bits and rounded to the nearest
+    /// `f64` otherwise.
+    Number,
+}
+
+/// How `undefined` is written.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum UndefinedPolicy {
+    /// Omitted from objects and `null` elsewhere, as `JSON.stringify` does.
+    #[default]
+    Drop,
+    /// `null` everywhere.
+    Null,
+}
+
+/// How a Map is written.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MapPolicy {
+    /// An array of `[key, value]` pairs, as in an envelope.
+    #[default]
+    Entries,
+    /// An object. String keys are used as they are and other keys as their
+    /// compact JSON text, so `1` becomes `"1"`; of several entries with the
+    /// same key, the last wins.
+    Object,
+}
+
+/// How `NaN`, the infinities and `-0` are written.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SpecialNumberPolicy {
+    /// `null`, and `0` for `-0`, as `JSON.stringify` does.
+    #[default]
+    Null,
+    /// The strings `"NaN"`, `"Infinity"`, `"-Infinity"` and `"-0"`, as in an
+    /// envelope.
+    String,
+}
+
+/// A value rewritten as a plain container, still to be converted.
+enum Nested {
+    Array(Vec<Value>),
+    Object(Map<String, Value>),
+}
+
+/// A container whose elements are being converted.
+enum Frame {
+    Array(Vec<Json>, std::vec::IntoIter<Value>),
+    Object(
+        serde_json::Map<String, Json>,
+        <Map<String, Value> as IntoIterator>::IntoIter,
+        String,
+    ),
+}
+
+impl Value {
+    /// Convert to plain JSON, degrading extended types as `policy` says.
+    ///
+    /// # Examples
+    /// ```
+    /// use superjson_rs::plain_json::{MapPolicy, PlainJsonPolicy, SpecialNumberPolicy};
+    /// use superjson_rs::{Map, Value};
+    ///
+    /// let mut map = Map::new();
+    /// map.insert("by_id".to_string(), Value::Map(vec![(Value::from(1), Value::NaN)]));
+    /// map.insert("gone".to_string(), Value::Undefined);
+    /// map.insert("tags".to_string(), Value::Set(vec![Value::from("a")]));
+    ///
+    /// let value = Value::Object(map);
+    /// assert_eq!(
+    ///     value.clone().into_plain_json(&PlainJsonPolicy::default()).to_string(),
+    ///     r#"{"by_id":[[1,null]],"tags":["a"]}"#
+    /// );
+    ///
+    /// let policy = PlainJsonPolicy {
+    ///     maps: MapPolicy::Object,
+    ///     special_numbers: SpecialNumberPolicy::String,
+    ///     ..Default::default()
+    /// };
+    /// assert_eq!(
+    ///     value.into_plain_json(&policy).to_string(),
+    ///     r#"{"by_id":{"1":"NaN"},"tags":["a"]}"#
+    /// );
+    /// ```
+    pub fn into_plain_json(self, policy: &PlainJsonPolicy) -> serde_json::Value {
+        let mut stack = Vec::new();
+        let mut next = self;
+        loop {
+            let mut done = match policy.degrade(next) {
+                Ok(json) => json,
+                Err(Nested::Array(items)) => {
+                    let len = items.len();
+                    let mut items = items.into_iter();
+                    match items.next() {
+                        Some(first) => {
+                            stack.push(Frame::Array(Vec::with_capacity(len), items));
+                            next = first;
+                            continue;
+                        }
+                        None => Json::Array(Vec::new()),
+                    }
+                }
+                Err(Nested::Object(map)) => {
+                    let mut entries = map.into_iter();
+                    match policy.next_field(&mut entries) {
+                        Some((key, first)) => {
+                            stack.push(Frame::Object(serde_json::Map::new(), entries, key));
+                            next = first;
+                            continue;
+                        }
+                        None => Json::Object(serde_json::Map::new()),
+                    }
+                }
+            };
+
+            // Hand the finished value to its parent, finishing every parent
+            // that has no elements left.
+            loop {
+                match stack.last_mut() {
+                    None => return done,
+                    Some(Frame::Array(items, rest)) => {
+                        items.push(done);
+                        if let Some(item) = rest.next() {
+                            next = item;
+                            break;
+                        }
+                    }
+                    Some(Frame::Object(map, rest, key)) => {
+                        map.insert(std::mem::take(key), done);
+                        if let Some((k, item)) = policy.next_field(rest) {
+                            *key = k;
+                            next = item;
+                            break;
+                        }
+                    }
+                }
+                done = match stack.pop() {
+                    Some(Frame::Array(items, _)) => Json::Array(items),
+                    Some(Frame::Object(map, _, _)) => Json::Object(map),
+                    None => unreachable!("the stack was just checked"),
+                };
+            }
+        }
+    }
+}
+
+impl PlainJsonPolicy {
+    /// The JSON for `value`, or `value` rewritten as a plain array or object
+    /// whose elements still need converting.
+    fn degrade(&self, value: Value) -> Result<Json, Nested> {
+        Ok(match value {
+            Value::Null => Json::Null,
+            Value::Bool(b) => Json::Bool(b),
+            Value::Number(n) => number(n),
+            Value::Int(n) => Json::from(n),
+            Value::UInt(n) => Json::from(n),
+            Value::String(s) | Value::Symbol(s) => Json::String(s),
+            Value::Url(url) => Json::String(crate::value::url_into_string(url)),
+            Value::Bytes(bytes) => Json::String(BytesEncoding::Base64.encode(&bytes)),
+            Value::Array(items) => return Err(Nested::Array(items)),
+            Value::Object(map) => return Err(Nested::Object(map)),
+            Value::Undefined => Json::Null,
+            #[cfg(feature = "chrono")]
+            Value::Date(dt) => match self.dates {
+                DatePolicy::IsoString => {
+                    Json::String(dt.to_rfc3339_opts(chrono::SecondsFormat::Millis, true))
+                }
+                DatePolicy::EpochMillis => Json::from(dt.timestamp_millis()),
+            },
+            #[cfg(feature = "chrono")]
+            Value::OffsetDate(dt) => match self.dates {
+                DatePolicy::IsoString => {
+                    Json::String(dt.to_rfc3339_opts(chrono::SecondsFormat::Millis, true))
+                }
+                DatePolicy::EpochMillis => Json::from(dt.timestamp_millis()),
+            },
+            // As `JSON.stringify` writes an invalid `Date`.
+            #[cfg(feature = "chrono")]
+            Value::InvalidDate => Json::Null,
+            #[cfg(feature = "bigint")]
+            Value::BigInt(n) => match self.bigints {
+                BigIntPolicy::String => Json::String(n.to_string()),
+                BigIntPolicy::Number => {
+                    use num_traits::ToPrimitive;
+
+                    if let Some(i) = n.to_i64() {
+                        Json::from(i)
+                    } else if let Some(u) = n.to_u64() {
+                        Json::from(u)
+                    } else {
+                        n.to_f64().map_or(Json::Null, number)
+                    }
+                }
+            },
+            Value::Set(items) => return Err(Nested::Array(items)),
+            Value::Map(entries) => match self.maps {
+                MapPolicy::Entries => {
+                    return Err(Nested::Array(
+                        entries
+                            .into_iter()
+                            .map(|(key, value)| Value::Array(vec![key, value]))
+                            .collect(),
+                    ));
+                }
+                MapPolicy::Object => {
+                    let mut map = Map::new();
+                    for (key, value) in entries {
+                        let key = match key {
+                            Value::String(key) => key,
+                            other => other.into_plain_json(self).to_string(),
+                        };
+                        map.insert(key, value);
+                    }
+                    return Err(Nested::Object(map));
+                }
+            },
+            Value::NaN | Value::PosInfinity | Value::NegInfinity | Value::NegZero => {
+                match self.special_numbers {
+                    SpecialNumberPolicy::Null if value == Value::NegZero => Json::from(0),
+                    SpecialNumberPolicy::Null => Json::Null,
+                    SpecialNumberPolicy::String => Json::String(value.type_name().to_string()),
+                }
+            }
+            Value::RegExp { source, flags } => Json::String(format!("/{source}/{flags}")),
+            Value::Error {
+                name,
+                message,
+                stack,
+                cause,
+                extra,
+            } => {
+                let mut map = Map::new();
+                map.insert("name".to_string(), Value::String(name));
+                map.insert("message".to_string(), Value::String(message));
+                if let Some(stack) = stack {
+                    map.insert("stack".to_string(), Value::String(stack));
+                }
+                if let Some(cause) = cause {
+                    map.insert("cause".to_string(), *cause);
+                }
+                map.extend(extra);
+                return Err(Nested::Object(map));
+            }
+            Value::Custom { value, .. } => return self.degrade(*value),
+            Value::Unknown { raw, .. } => return self.degrade(Value::from_json(raw)),
+            Value::Class { fields, .. } => return Err(Nested::Object(fields)),
+        })
+    }
+
+    /// The next field of an object that is written at all.
+    fn next_field(
+        &self,
+        fields: &mut impl Iterator<Item = (String, Value)>,
+    ) -> Option<(String, Value)> {
+        match self.undefined {
+            UndefinedPolicy::Drop => fields.find(|(_, value)| !value.is_undefined()),
+            UndefinedPolicy::Null => fields.next(),
+        }
+    }
+}
+
+/// `n`, written without a fraction if it is a safe integer.
+fn number(n: f64) -> Json {
+    if n.fract() == 0.0 && n.abs() <= crate::serialize::MAX_SAFE_INTEGER {
+        Json::from(n as i64)
+    } else {
+        serde_json::Number::from_f64(n).map_or(Json::Null, Json::Number)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Value {
+        let mut map = Map::new();
+        map.insert("u".to_string(), Value::Undefined);
+        map.insert(
+            "list".to_string(),
+            Value::from(vec![Value::Undefined, Value::NegZero, Value::NaN]),
+        );
+        map.insert(
+            "m".to_string(),
+            Value::Map(vec![
+                (Value::from("k"), Value::Set(vec![Value::from(1)])),
+                (Value::from(vec![1, 2]), Value::Undefined),
+            ]),
+        );
+        map.insert(
+            "e".to_string(),
+            Value::Error {
+                name: "TypeError".to_string(),
+                message: "bad".to_string(),
+                stack: None,
+                cause: None,
+                extra: Map::new(),
+            },
+        );
+        map.insert(
+            "r".to_string(),
+            Value::RegExp {
+                source: "a+".to_string(),
+                flags: "g".to_string(),
+            },
+        );
+        Value::Object(map)
+    }
+
+    #[test]
+    fn test_default_policy_follows_json_stringify() {
+        let json = sample().into_plain_json(&PlainJsonPolicy::default());
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "list": [null, 0, null],
+                "m": [["k", [1]], [[1, 2], null]],
+                "e": {"name": "TypeError", "message": "bad"},
+                "r": "/a+/g",
+            })
+        );
+    }
+
+    #[test]
+    fn test_other_policies() {
+        let policy = PlainJsonPolicy {
+            undefined: UndefinedPolicy::Null,
+            maps: MapPolicy::Object,
+            special_numbers: SpecialNumberPolicy::String,
+            ..Default::default()
+        };
+        let json = sample().into_plain_json(&policy);
+        assert_eq!(json["u"], Json::Null);
+        assert_eq!(json["list"], serde_json::json!([null, "-0", "NaN"]));
+        assert_eq!(json["m"], serde_json::json!({"k": [1], "[1,2]": null}));
+    }
+
+    #[cfg(all(feature = "chrono", feature = "bigint"))]
+    #[test]
+    fn test_dates_and_bigints() {
+        use chrono::TimeZone;
+        use num_bigint::BigInt;
+
+        let value = Value::from(vec![
+            Value::Date(chrono::Utc.timestamp_millis_opt(1_500).unwrap()),
+            Value::BigInt(BigInt::from(u64::MAX)),
+            Value::BigInt(BigInt::from(u64::MAX) * 2),
+        ]);
+        assert_eq!(
+            value.clone().into_plain_json(&PlainJsonPolicy::default()),
+            serde_json::json!([
+                "1970-01-01T00:00:01.500Z",
+                "18446744073709551615",
+                "36893488147419103230"
+            ])
+        );
+        let policy = PlainJsonPolicy {
+            dates: DatePolicy::EpochMillis,
+            bigints: BigIntPolicy::Number,
+            ..Default::default()
+        };
+        assert_eq!(
+            value.into_plain_json(&policy),
+            serde_json::json!([1500, u64::MAX, 36893488147419103230.0])
+        );
+    }
+}
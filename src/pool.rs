@@ -0,0 +1,269 @@
+//! Reusing the allocations of dropped values.
+//!
+//! A server restoring thousands of similar payloads a second allocates and
+//! frees the same vectors, maps and strings over and over. A [`ValuePool`]
+//! keeps the buffers of values handed to [`ValuePool::recycle`], emptied,
+//! and [`ValuePool::parse`] builds the next value out of them.
+//!
+//! Payloads with type annotations are restored from pooled buffers too,
+//! though only the arrays, sets, objects, class fields and string values in
+//! them are; other extended values, such as the entries of a `Map`, are allocated
+//! as usual. Without the `preserve_order` feature, objects are `BTreeMap`s,
+//! which keep no allocation to reuse.
+//!
+//! # Examples
+//! ```
+//! use superjson_rs::pool::ValuePool;
+//!
+//! let mut pool = ValuePool::new();
+//! for _ in 0..3 {
+//!     let value = pool.parse(r#"{"json":{"tags":["a","b"]}}"#).unwrap();
+//!     assert_eq!(value.pointer("tags.1").and_then(|v| v.as_str()), Some("b"));
+//!     pool.recycle(value);
+//! }
+//! assert!(pool.len() > 0);
+//! ```
+
+use crate::value::Map;
+use crate::{ParseOptions, Result, Value, deserialize, envelope};
+
+/// How many buffers of each kind a pool keeps by default.
+pub const DEFAULT_POOL_LIMIT: usize = 4096;
+
+/// Emptied buffers of dropped values, ready for reuse.
+#[derive(Debug)]
+pub struct ValuePool {
+    vecs: Vec<Vec<Value>>,
+    maps: Vec<Map<String, Value>>,
+    strings: Vec<String>,
+    limit: usize,
+}
+
+impl Default for ValuePool {
+    fn default() -> Self {
+        ValuePool::new()
+    }
+}
+
+impl ValuePool {
+    /// An empty pool keeping up to [`DEFAULT_POOL_LIMIT`] buffers of each
+    /// kind.
+    pub fn new() -> Self {
+        ValuePool::with_limit(DEFAULT_POOL_LIMIT)
+    }
+
+    /// An empty pool keeping up to `limit` buffers of each kind; the rest are
+    /// freed as usual.
+    pub fn with_limit(limit: usize) -> Self {
+        ValuePool {
+            vecs: Vec::new(),
+            maps: Vec::new(),
+            strings: Vec::new(),
+            limit,
+        }
+    }
+
+    /// The number of buffers held.
+    pub fn len(&self) -> usize {
+        self.vecs.len() + self.maps.len() + self.strings.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Free every buffer held.
+    pub fn clear(&mut self) {
+        self.vecs.clear();
+        self.maps.clear();
+        self.strings.clear();
+    }
+
+    /// Take `value` apart and keep its buffers for later values.
+    pub fn recycle(&mut self, value: Value) {
+        let mut stack = vec![value];
+        while let Some(value) = stack.pop() {
+            match value {
+                Value::Array(mut items) | Value::Set(mut items) => {
+                    stack.append(&mut items);
+                    self.put_vec(items);
+                }
+                Value::Object(mut map) => {
+                    for (key, value) in drain(&mut map) {
+                        self.put_string(key);
+                        stack.push(value);
+                    }
+                    if self.maps.len() < self.limit {
+                        self.maps.push(map);
+                    }
+                }
+                Value::Map(entries) => {
+                    for (key, value) in entries {
+                        stack.push(key);
+                        stack.push(value);
+                    }
+                }
+                Value::String(s) | Value::Symbol(s) => self.put_string(s),
+                Value::Url(url) => self.put_string(crate::value::url_into_string(url)),
+                Value::RegExp { source, flags } => {
+                    self.put_string(source);
+                    self.put_string(flags);
+                }
+                Value::Error {
+                    name,
+                    message,
+                    stack: error_stack,
+                    cause,
+                    mut extra,
+                } => {
+                    self.put_string(name);
+                    self.put_string(message);
+                    if let Some(error_stack) = error_stack {
+                        self.put_string(error_stack);
+                    }
+                    stack.extend(cause.map(|cause| *cause));
+                    for (key, value) in drain(&mut extra) {
+                        self.put_string(key);
+                        stack.push(value);
+                    }
+                }
+                Value::Custom { name, value } => {
+                    self.put_string(name);
+                    stack.push(*value);
+                }
+                Value::Unknown { type_name, .. } => self.put_string(type_name),
+                Value::Class { name, mut fields } => {
+                    self.put_string(name);
+                    for (key, value) in drain(&mut fields) {
+                        self.put_string(key);
+                        stack.push(value);
+                    }
+                    if self.maps.len() < self.limit {
+                        self.maps.push(fields);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Parse a superjson string, building its arrays, objects and strings
+    /// out of pooled buffers.
+    pub fn parse(&mut self, s: &str) -> Result<Value> {
+        self.parse_with(s, &ParseOptions::default())
+    }
+
+    /// Parse a superjson string with the limits in `options`, building its
+    /// arrays, objects and strings out of pooled buffers.
+    pub fn parse_with(&mut self, s: &str, options: &ParseOptions) -> Result<Value> {
+        let envelope: envelope::RawSuperJson = serde_json::from_str(s)?;
+        let superjson = envelope.into_superjson(options, &mut Vec::new())?;
+        deserialize::deserialize_pooled(&superjson, options, self)
+    }
+
+    pub(crate) fn vec(&mut self, capacity: usize) -> Vec<Value> {
+        match self.vecs.pop() {
+            Some(mut vec) => {
+                vec.reserve(capacity);
+                vec
+            }
+            None => Vec::with_capacity(capacity),
+        }
+    }
+
+    pub(crate) fn map(&mut self, capacity: usize) -> Map<String, Value> {
+        let mut map = self.maps.pop().unwrap_or_default();
+        reserve(&mut map, capacity);
+        map
+    }
+
+    pub(crate) fn string(&mut self, s: &str) -> String {
+        match self.strings.pop() {
+            Some(mut string) => {
+                string.push_str(s);
+                string
+            }
+            None => s.to_string(),
+        }
+    }
+
+    pub(crate) fn put_vec(&mut self, vec: Vec<Value>) {
+        if vec.capacity() > 0 && self.vecs.len() < self.limit {
+            self.vecs.push(vec);
+        }
+    }
+
+    fn put_string(&mut self, mut s: String) {
+        if s.capacity() > 0 && self.strings.len() < self.limit {
+            s.clear();
+            self.strings.push(s);
+        }
+    }
+}
+
+#[cfg(feature = "preserve_order")]
+fn drain(map: &mut Map<String, Value>) -> impl Iterator<Item = (String, Value)> + '_ {
+    map.drain(..)
+}
+
+#[cfg(not(feature = "preserve_order"))]
+fn drain(map: &mut Map<String, Value>) -> impl Iterator<Item = (String, Value)> + use<> {
+    std::mem::take(map).into_iter()
+}
+
+#[cfg(feature = "preserve_order")]
+fn reserve(map: &mut Map<String, Value>, capacity: usize) {
+    map.reserve(capacity);
+}
+
+#[cfg(not(feature = "preserve_order"))]
+fn reserve(_map: &mut Map<String, Value>, _capacity: usize) {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pooled_parse_matches_parse() {
+        let text = r#"{"json":{"a":[1,"x",{"b":null}],"c":true}}"#;
+        let mut pool = ValuePool::new();
+        let first = pool.parse(text).unwrap();
+        assert_eq!(first, crate::parse(text).unwrap());
+
+        pool.recycle(first);
+        let held = pool.len();
+        assert!(held > 0);
+        let second = pool.parse(text).unwrap();
+        assert_eq!(second, crate::parse(text).unwrap());
+        assert!(pool.len() < held);
+    }
+
+    #[test]
+    fn test_annotated_parse_reuses_buffers() {
+        let text = r#"{"json":{"s":["a","b"],"o":{"k":"v","u":null}},
+            "meta":{"values":{"s":["set"],"o.u":["undefined"]}}}"#;
+        let mut pool = ValuePool::new();
+        let first = pool.parse(text).unwrap();
+        assert_eq!(first, crate::parse(text).unwrap());
+
+        pool.recycle(first);
+        let held = pool.len();
+        assert!(held > 0);
+        let second = pool.parse(text).unwrap();
+        assert_eq!(second, crate::parse(text).unwrap());
+        assert!(pool.len() < held);
+    }
+
+    #[test]
+    fn test_annotated_payloads_and_limit() {
+        let text = r#"{"json":[["a"],null],"meta":{"values":{"0":["set"],"1":["undefined"]}}}"#;
+        let mut pool = ValuePool::with_limit(1);
+        let value = pool.parse(text).unwrap();
+        assert_eq!(value, crate::parse(text).unwrap());
+
+        pool.recycle(value);
+        assert!(pool.len() <= 3);
+        pool.clear();
+        assert!(pool.is_empty());
+    }
+}
@@ -0,0 +1,336 @@
+//! Conversions to and from the protobuf well-known types in `prost-types`.
+//!
+//! * `google.protobuf.Value` and `Struct` hold plain JSON, and convert to
+//!   and from [`Value`]s made of JSON types only.
+//! * A whole [`SuperJson`] envelope converts to and from a `Struct` with
+//!   `json` and `meta` fields, which carries every superjson type through a
+//!   protobuf message without loss.
+//! * `Timestamp` converts to and from [`Value::Date`].
+//! * `Duration` converts to and from a [`Value::Number`] of milliseconds,
+//!   the usual representation of a duration in JavaScript.
+//!
+//! Protobuf numbers are `f64`s, as in JavaScript. A `NaN` or infinite
+//! `number_value` becomes `null`, as `JSON.stringify` would write it. The
+//! fields of a `Struct` are sorted, so object keys do not keep their order.
+//!
+//! # Examples
+//! ```
+//! use superjson_rs::{SuperJson, Value, serialize::serialize};
+//!
+//! let value = Value::from(vec![Value::Set(vec![Value::from("a")]), Value::Undefined]);
+//! let message = prost_types::Struct::from(&serialize(&value).unwrap());
+//!
+//! let envelope = SuperJson::try_from(message).unwrap();
+//! assert_eq!(superjson_rs::deserialize::deserialize(&envelope).unwrap(), value);
+//! ```
+
+use std::collections::btree_map;
+
+use prost_types::value::Kind;
+
+use crate::serialize::MAX_SAFE_INTEGER;
+use crate::{Error, Result, SuperJson, Value};
+
+/// Requires a value made of JSON types only.
+///
+/// # Errors
+/// Returns [`Error::TypeMismatch`] at the first extended-type value.
+impl TryFrom<&Value> for prost_types::Value {
+    type Error = Error;
+
+    fn try_from(value: &Value) -> Result<Self> {
+        if let Some((path, actual)) = value.extended_paths().into_iter().next() {
+            return Err(Error::TypeMismatch {
+                path,
+                expected: "a JSON value".to_string(),
+                actual: actual.to_string(),
+            });
+        }
+        Ok(json_to_proto(crate::serialize::serialize(value)?.json))
+    }
+}
+
+/// Requires a `Value::Object` made of JSON types only.
+impl TryFrom<&Value> for prost_types::Struct {
+    type Error = Error;
+
+    fn try_from(value: &Value) -> Result<Self> {
+        match prost_types::Value::try_from(value)?.kind {
+            Some(Kind::StructValue(s)) => Ok(s),
+            _ => Err(Error::TypeMismatch {
+                path: String::new(),
+                expected: "object".to_string(),
+                actual: value.type_name().to_string(),
+            }),
+        }
+    }
+}
+
+/// # Errors
+/// Returns [`Error::DepthLimitExceeded`] for values nested more than
+/// [`DEFAULT_MAX_DEPTH`](crate::DEFAULT_MAX_DEPTH) levels deep.
+impl TryFrom<prost_types::Value> for Value {
+    type Error = Error;
+
+    fn try_from(value: prost_types::Value) -> Result<Self> {
+        Value::from_json_checked(proto_to_json(value))
+    }
+}
+
+impl TryFrom<prost_types::Struct> for Value {
+    type Error = Error;
+
+    fn try_from(s: prost_types::Struct) -> Result<Self> {
+        Value::try_from(prost_types::Value {
+            kind: Some(Kind::StructValue(s)),
+        })
+    }
+}
+
+impl From<&SuperJson> for prost_types::Struct {
+    fn from(superjson: &SuperJson) -> Self {
+        let envelope =
+            serde_json::to_value(superjson).expect("a SuperJson envelope serializes to JSON");
+        match json_to_proto(envelope).kind {
+            Some(Kind::StructValue(s)) => s,
+            _ => unreachable!("a SuperJson envelope serializes to an object"),
+        }
+    }
+}
+
+/// # Errors
+/// Returns an error if the struct is not a well-formed envelope.
+impl TryFrom<prost_types::Struct> for SuperJson {
+    type Error = Error;
+
+    fn try_from(s: prost_types::Struct) -> Result<Self> {
+        let envelope = proto_to_json(prost_types::Value {
+            kind: Some(Kind::StructValue(s)),
+        });
+        Ok(serde_json::from_value(envelope)?)
+    }
+}
+
+/// # Errors
+/// Returns [`Error::InvalidDate`] if the timestamp is out of range.
+#[cfg(feature = "chrono")]
+impl TryFrom<prost_types::Timestamp> for Value {
+    type Error = Error;
+
+    fn try_from(ts: prost_types::Timestamp) -> Result<Self> {
+        u32::try_from(ts.nanos)
+            .ok()
+            .and_then(|nanos| chrono::DateTime::from_timestamp(ts.seconds, nanos))
+            .map(Value::Date)
+            .ok_or_else(|| Error::InvalidDate(ts.to_string()))
+    }
+}
+
+/// Requires a `Value::Date`.
+#[cfg(feature = "chrono")]
+impl TryFrom<&Value> for prost_types::Timestamp {
+    type Error = Error;
+
+    fn try_from(value: &Value) -> Result<Self> {
+        let dt = value.as_offset_date().ok_or_else(|| Error::TypeMismatch {
+            path: String::new(),
+            expected: "Date".to_string(),
+            actual: value.type_name().to_string(),
+        })?;
+        Ok(prost_types::Timestamp {
+            seconds: dt.timestamp(),
+            nanos: dt.timestamp_subsec_nanos() as i32,
+        })
+    }
+}
+
+/// Converts to a number of milliseconds.
+impl From<prost_types::Duration> for Value {
+    fn from(duration: prost_types::Duration) -> Self {
+        Value::Number(duration.seconds as f64 * 1000.0 + f64::from(duration.nanos) / 1e6)
+    }
+}
+
+/// Requires a finite number of milliseconds.
+impl TryFrom<&Value> for prost_types::Duration {
+    type Error = Error;
+
+    fn try_from(value: &Value) -> Result<Self> {
+        let ms = match value {
+            Value::Number(ms) if ms.is_finite() => *ms,
+            Value::Int(_) | Value::UInt(_) => value.as_f64().expect("integers are numbers"),
+            other => {
+                return Err(Error::TypeMismatch {
+                    path: String::new(),
+                    expected: "a finite number of milliseconds".to_string(),
+                    actual: other.type_name().to_string(),
+                });
+            }
+        };
+        // Seconds and nanoseconds share the sign of the duration.
+        let seconds = (ms / 1000.0).trunc();
+        Ok(prost_types::Duration {
+            seconds: seconds as i64,
+            nanos: ((ms - seconds * 1000.0) * 1e6).round() as i32,
+        })
+    }
+}
+
+/// A protobuf container being filled with converted children.
+enum ProtoFrame {
+    List(
+        std::vec::IntoIter<serde_json::Value>,
+        Vec<prost_types::Value>,
+    ),
+    Struct(
+        serde_json::map::IntoIter,
+        btree_map::BTreeMap<String, prost_types::Value>,
+        String,
+    ),
+}
+
+fn json_to_proto(json: serde_json::Value) -> prost_types::Value {
+    let mut stack: Vec<ProtoFrame> = Vec::new();
+    let mut next = Some(json);
+
+    loop {
+        let mut done = next.take().and_then(|json| {
+            let kind = match json {
+                serde_json::Value::Null => Kind::NullValue(0),
+                serde_json::Value::Bool(b) => Kind::BoolValue(b),
+                serde_json::Value::Number(n) => Kind::NumberValue(n.as_f64().unwrap_or(f64::NAN)),
+                serde_json::Value::String(s) => Kind::StringValue(s),
+                serde_json::Value::Array(items) => {
+                    let len = items.len();
+                    stack.push(ProtoFrame::List(items.into_iter(), Vec::with_capacity(len)));
+                    return None;
+                }
+                serde_json::Value::Object(map) => {
+                    stack.push(ProtoFrame::Struct(
+                        map.into_iter(),
+                        btree_map::BTreeMap::new(),
+                        String::new(),
+                    ));
+                    return None;
+                }
+            };
+            Some(prost_types::Value { kind: Some(kind) })
+        });
+
+        loop {
+            let Some(frame) = stack.last_mut() else {
+                return done.expect("the root is converted before the stack empties");
+            };
+            let child = match frame {
+                ProtoFrame::List(items, values) => {
+                    values.extend(done.take());
+                    items.next()
+                }
+                ProtoFrame::Struct(entries, fields, key) => {
+                    if let Some(value) = done.take() {
+                        fields.insert(std::mem::take(key), value);
+                    }
+                    entries.next().map(|(next_key, child)| {
+                        *key = next_key;
+                        child
+                    })
+                }
+            };
+            if child.is_some() {
+                next = child;
+                break;
+            }
+            let kind = match stack.pop().expect("the stack is non-empty") {
+                ProtoFrame::List(_, values) => Kind::ListValue(prost_types::ListValue { values }),
+                ProtoFrame::Struct(_, fields, _) => {
+                    Kind::StructValue(prost_types::Struct { fields })
+                }
+            };
+            done = Some(prost_types::Value { kind: Some(kind) });
+        }
+    }
+}
+
+/// Integral numbers are written as integers, so that fields such as `meta.v`
+/// read back as the integers they were.
+fn json_number(n: f64) -> serde_json::Value {
+    if n.fract() == 0.0 && n.abs() <= MAX_SAFE_INTEGER {
+        serde_json::json!(n as i64)
+    } else {
+        serde_json::json!(n)
+    }
+}
+
+/// A JSON container being filled with converted children.
+enum JsonFrame {
+    List(
+        std::vec::IntoIter<prost_types::Value>,
+        Vec<serde_json::Value>,
+    ),
+    Struct(
+        btree_map::IntoIter<String, prost_types::Value>,
+        serde_json::Map<String, serde_json::Value>,
+        String,
+    ),
+}
+
+fn proto_to_json(value: prost_types::Value) -> serde_json::Value {
+    let mut stack: Vec<JsonFrame> = Vec::new();
+    let mut next = Some(value);
+
+    loop {
+        let mut done = next.take().and_then(|value| {
+            Some(match value.kind {
+                None | Some(Kind::NullValue(_)) => serde_json::Value::Null,
+                Some(Kind::BoolValue(b)) => serde_json::Value::Bool(b),
+                Some(Kind::NumberValue(n)) => json_number(n),
+                Some(Kind::StringValue(s)) => serde_json::Value::String(s),
+                Some(Kind::ListValue(list)) => {
+                    let len = list.values.len();
+                    stack.push(JsonFrame::List(
+                        list.values.into_iter(),
+                        Vec::with_capacity(len),
+                    ));
+                    return None;
+                }
+                Some(Kind::StructValue(s)) => {
+                    stack.push(JsonFrame::Struct(
+                        s.fields.into_iter(),
+                        serde_json::Map::new(),
+                        String::new(),
+                    ));
+                    return None;
+                }
+            })
+        });
+
+        loop {
+            let Some(frame) = stack.last_mut() else {
+                return done.expect("the root is converted before the stack empties");
+            };
+            let child = match frame {
+                JsonFrame::List(items, values) => {
+                    values.extend(done.take());
+                    items.next()
+                }
+                JsonFrame::Struct(entries, fields, key) => {
+                    if let Some(value) = done.take() {
+                        fields.insert(std::mem::take(key), value);
+                    }
+                    entries.next().map(|(next_key, child)| {
+                        *key = next_key;
+                        child
+                    })
+                }
+            };
+            if child.is_some() {
+                next = child;
+                break;
+            }
+            done = Some(match stack.pop().expect("the stack is non-empty") {
+                JsonFrame::List(_, values) => serde_json::Value::Array(values),
+                JsonFrame::Struct(_, fields, _) => serde_json::Value::Object(fields),
+            });
+        }
+    }
+}
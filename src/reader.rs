@@ -0,0 +1,148 @@
+//! Reading superjson lines: one envelope per line.
+//!
+//! The counterpart of [`SuperJsonLinesWriter`](crate::writer::SuperJsonLinesWriter).
+//! Lines are read and restored one at a time, so a file of millions of
+//! records is never held in memory at once.
+
+use std::io::BufRead;
+
+use crate::{Error, ParseOptions, Result, Value, parse_with};
+
+/// Reads superjson lines from `R`, yielding one `Result<Value>` per line.
+///
+/// Blank lines are skipped, and a `\r\n` line ending is accepted. A line
+/// that cannot be restored yields an error in its place without ending the
+/// iteration; an I/O error ends it.
+///
+/// # Examples
+/// ```
+/// use superjson_rs::Value;
+/// use superjson_rs::reader::SuperJsonLinesReader;
+///
+/// let text = "{\"json\":\"NaN\",\"meta\":{\"values\":[\"number\"]}}\n\nnot json\n{\"json\":2}\n";
+/// let mut reader = SuperJsonLinesReader::new(text.as_bytes());
+/// assert_eq!(reader.next().unwrap().unwrap(), Value::NaN);
+/// assert!(reader.next().unwrap().is_err());
+/// assert_eq!(reader.line_number(), 3);
+/// assert_eq!(reader.next().unwrap().unwrap(), Value::from(2));
+/// assert!(reader.next().is_none());
+/// ```
+#[derive(Debug)]
+pub struct SuperJsonLinesReader<R> {
+    inner: R,
+    options: ParseOptions,
+    line: String,
+    line_number: u64,
+    failed: bool,
+}
+
+impl<R: BufRead> SuperJsonLinesReader<R> {
+    pub fn new(inner: R) -> Self {
+        SuperJsonLinesReader::with_options(inner, ParseOptions::default())
+    }
+
+    /// Restore every line with `options`.
+    pub fn with_options(inner: R, options: ParseOptions) -> Self {
+        SuperJsonLinesReader {
+            inner,
+            options,
+            line: String::new(),
+            line_number: 0,
+            failed: false,
+        }
+    }
+
+    /// The 1-based number of the line the last item was read from, for
+    /// reporting where an error occurred.
+    pub fn line_number(&self) -> u64 {
+        self.line_number
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: BufRead> Iterator for SuperJsonLinesReader<R> {
+    type Item = Result<Value>;
+
+    fn next(&mut self) -> Option<Result<Value>> {
+        while !self.failed {
+            self.line.clear();
+            match self.inner.read_line(&mut self.line) {
+                Ok(0) => return None,
+                Ok(_) => {}
+                Err(e) => {
+                    self.failed = true;
+                    return Some(Err(Error::Io(e)));
+                }
+            }
+            self.line_number += 1;
+            let line = self.line.trim();
+            if !line.is_empty() {
+                return Some(parse_with(line, &self.options));
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{self, Read};
+
+    use super::*;
+    use crate::writer::SuperJsonLinesWriter;
+
+    #[test]
+    fn test_roundtrip() {
+        let values = vec![
+            Value::Undefined,
+            Value::from("line\nbreak"),
+            Value::Map(vec![(Value::from(1), Value::NegZero)]),
+        ];
+        let mut writer = SuperJsonLinesWriter::new(Vec::new());
+        for value in &values {
+            writer.write(value).unwrap();
+        }
+        let out = writer.into_inner();
+        assert_eq!(out.iter().filter(|&&b| b == b'\n').count(), values.len());
+
+        let read: Vec<Value> = SuperJsonLinesReader::new(out.as_slice())
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(read, values);
+    }
+
+    #[test]
+    fn test_options_apply_per_line() {
+        let options = ParseOptions {
+            max_depth: 2,
+            ..ParseOptions::default()
+        };
+        let text = "{\"json\":[[[1]]]}\r\n{\"json\":[1]}\r\n";
+        let items: Vec<_> = SuperJsonLinesReader::with_options(text.as_bytes(), options).collect();
+        assert!(matches!(items[0], Err(Error::DepthLimitExceeded(2))));
+        assert_eq!(
+            items[1].as_ref().unwrap(),
+            &Value::Array(vec![Value::from(1)])
+        );
+    }
+
+    #[test]
+    fn test_io_error_ends_iteration() {
+        struct Broken;
+        impl Read for Broken {
+            fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+                Err(io::Error::other("disconnected"))
+            }
+        }
+        let mut reader = SuperJsonLinesReader::new(io::BufReader::new(Broken));
+        assert!(matches!(reader.next(), Some(Err(Error::Io(_)))));
+        assert!(reader.next().is_none());
+    }
+}
@@ -0,0 +1,290 @@
+//! Compiling [`Value::RegExp`]s with the `regex` crate.
+//!
+//! JavaScript and the `regex` crate share most of their syntax, but not all
+//! of it means the same thing. The pattern is rewritten where the two differ:
+//!
+//! * `\d`, `\w` and `\b` only know ASCII in JavaScript, and are spelled out
+//!   as ASCII classes.
+//! * `\uXXXX`, `\u{X...}`, `\cX` and `\0` become `\x{...}` escapes, with
+//!   surrogate pairs joined into one code point.
+//! * `[^]` matches any character and `[]` none.
+//! * `[`, `&` and `~` in a class are literal in JavaScript but not in
+//!   `regex`, and are escaped.
+//!
+//! The flags `i`, `m` and `s` set the matching options; `u` changes nothing,
+//! as `regex` is always Unicode-aware, and `g` and `d` only change how
+//! JavaScript reports matches. Lookaround, backreferences and the `y` and
+//! `v` flags have no counterpart and are reported with
+//! [`Error::UnsupportedRegExp`].
+
+use std::iter::Peekable;
+use std::str::Chars;
+
+use regex::{Regex, RegexBuilder};
+
+use crate::{Error, Result, Value};
+
+impl Value {
+    /// Compile a [`Value::RegExp`] into a [`Regex`] that matches as the
+    /// JavaScript one does; see the [module documentation](self) for what is
+    /// translated.
+    ///
+    /// # Errors
+    /// Returns [`Error::UnsupportedRegExp`] for a construct `regex` cannot
+    /// express, [`Error::InvalidRegExp`] for a pattern that does not compile,
+    /// and [`Error::TypeMismatch`] for any other variant.
+    ///
+    /// # Examples
+    /// ```
+    /// use superjson_rs::{Error, Value};
+    ///
+    /// let value = Value::RegExp { source: r"^\d+$".into(), flags: "i".into() };
+    /// let regex = value.compile_regexp().unwrap();
+    /// assert!(regex.is_match("42"));
+    /// assert!(!regex.is_match("٤٢"));
+    ///
+    /// let value = Value::RegExp { source: r"(?<=\$)\d+".into(), flags: "".into() };
+    /// assert!(matches!(value.compile_regexp(), Err(Error::UnsupportedRegExp { .. })));
+    /// ```
+    pub fn compile_regexp(&self) -> Result<Regex> {
+        let Value::RegExp { source, flags } = self else {
+            return Err(Error::TypeMismatch {
+                path: String::new(),
+                expected: "RegExp".to_string(),
+                actual: self.type_name().to_string(),
+            });
+        };
+        let unsupported = |construct| Error::UnsupportedRegExp {
+            pattern: format!("/{source}/{flags}"),
+            construct,
+        };
+
+        let pattern = translate(source, flags.contains('u')).map_err(unsupported)?;
+        let mut builder = RegexBuilder::new(&pattern);
+        for flag in flags.chars() {
+            match flag {
+                'i' => builder.case_insensitive(true),
+                'm' => builder.multi_line(true),
+                's' => builder.dot_matches_new_line(true),
+                'u' | 'g' | 'd' => &mut builder,
+                'y' => return Err(unsupported("the sticky flag 'y'")),
+                'v' => return Err(unsupported("the flag 'v'")),
+                other => {
+                    return Err(Error::InvalidRegExp(format!(
+                        "/{source}/{flags}: unknown flag '{other}'"
+                    )));
+                }
+            };
+        }
+        builder
+            .build()
+            .map_err(|e| Error::InvalidRegExp(format!("/{source}/{flags}: {e}")))
+    }
+}
+
+/// Rewrite a JavaScript pattern in `regex` syntax, or name the construct
+/// that cannot be.
+fn translate(source: &str, unicode: bool) -> std::result::Result<String, &'static str> {
+    let mut out = String::with_capacity(source.len());
+    let mut chars = source.chars().peekable();
+    let mut in_class = false;
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                let Some(escaped) = chars.next() else {
+                    // Left for `regex` to reject.
+                    out.push('\\');
+                    break;
+                };
+                escape(escaped, &mut chars, in_class, unicode, &mut out)?;
+            }
+            '[' if in_class => out.push_str("\\["),
+            '[' => match (chars.next_if_eq(&'^'), chars.next_if_eq(&']')) {
+                (Some(_), Some(_)) => out.push_str("(?s:.)"),
+                (None, Some(_)) => out.push_str(r"[^\x{0}-\x{10FFFF}]"),
+                (caret, None) => {
+                    out.push('[');
+                    out.extend(caret);
+                    in_class = true;
+                }
+            },
+            ']' if in_class => {
+                out.push(']');
+                in_class = false;
+            }
+            '&' | '~' if in_class => {
+                out.push('\\');
+                out.push(c);
+            }
+            '(' if !in_class && chars.peek() == Some(&'?') => {
+                let ahead: String = chars.clone().take(3).collect();
+                if ahead.starts_with("?=") || ahead.starts_with("?!") {
+                    return Err("lookahead");
+                }
+                if ahead == "?<=" || ahead == "?<!" {
+                    return Err("lookbehind");
+                }
+                out.push('(');
+            }
+            other => out.push(other),
+        }
+    }
+    Ok(out)
+}
+
+/// Rewrite the escape `\escaped`, reading any further characters it takes.
+fn escape(
+    escaped: char,
+    chars: &mut Peekable<Chars>,
+    in_class: bool,
+    unicode: bool,
+    out: &mut String,
+) -> std::result::Result<(), &'static str> {
+    match escaped {
+        'd' if in_class => out.push_str("0-9"),
+        'd' => out.push_str("[0-9]"),
+        'D' => out.push_str("[^0-9]"),
+        'w' if in_class => out.push_str("0-9A-Za-z_"),
+        'w' => out.push_str("[0-9A-Za-z_]"),
+        'W' => out.push_str("[^0-9A-Za-z_]"),
+        // A backspace in a class, a word boundary outside one.
+        'b' if in_class => out.push_str(r"\x{8}"),
+        'b' | 'B' => {
+            out.push_str(r"(?-u:\");
+            out.push(escaped);
+            out.push(')');
+        }
+        '1'..='9' => return Err("backreferences"),
+        'k' if chars.peek() == Some(&'<') => return Err("backreferences"),
+        '0' if !chars.peek().is_some_and(char::is_ascii_digit) => out.push_str(r"\x{0}"),
+        'c' => match chars.next_if(char::is_ascii_alphabetic) {
+            Some(letter) => push_code_point(out, u32::from(letter) % 32),
+            None => out.push_str(r"\\c"),
+        },
+        'u' => match code_unit(chars, unicode) {
+            Some(high @ 0xD800..=0xDBFF) => {
+                let mut ahead = chars.clone();
+                let low = (ahead.next() == Some('\\') && ahead.next() == Some('u'))
+                    .then(|| code_unit(&mut ahead, unicode))
+                    .flatten()
+                    .filter(|low| (0xDC00..=0xDFFF).contains(low))
+                    .ok_or("a lone surrogate")?;
+                *chars = ahead;
+                push_code_point(out, 0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00));
+            }
+            Some(0xDC00..=0xDFFF) => return Err("a lone surrogate"),
+            Some(code) => push_code_point(out, code),
+            // Outside Unicode mode, a malformed `\u` is a literal `u`.
+            None => out.push('u'),
+        },
+        '/' => out.push('/'),
+        other => {
+            out.push('\\');
+            out.push(other);
+        }
+    }
+    Ok(())
+}
+
+/// Read the code unit after `\u`: four hex digits, or with the `u` flag a
+/// braced code point. Nothing is consumed if there is neither.
+fn code_unit(chars: &mut Peekable<Chars>, unicode: bool) -> Option<u32> {
+    let mut ahead = chars.clone();
+    let digits: String = if unicode && ahead.next_if_eq(&'{').is_some() {
+        let digits: String = ahead.by_ref().take_while(|&c| c != '}').collect();
+        if digits.is_empty() || digits.len() > 6 {
+            return None;
+        }
+        digits
+    } else {
+        ahead.by_ref().take(4).collect()
+    };
+    if digits.len() < 4 && !unicode || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let code = u32::from_str_radix(&digits, 16).ok()?;
+    *chars = ahead;
+    Some(code)
+}
+
+fn push_code_point(out: &mut String, code: u32) {
+    out.push_str(&format!(r"\x{{{code:X}}}"));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn compile(source: &str, flags: &str) -> Result<Regex> {
+        Value::RegExp {
+            source: source.to_string(),
+            flags: flags.to_string(),
+        }
+        .compile_regexp()
+    }
+
+    #[test]
+    fn test_flags() {
+        assert!(compile("^ab$", "i").unwrap().is_match("AB"));
+        assert!(compile("^b$", "m").unwrap().is_match("a\nb"));
+        assert!(!compile("^b$", "").unwrap().is_match("a\nb"));
+        assert!(compile("a.b", "s").unwrap().is_match("a\nb"));
+        assert!(compile("a", "gud").is_ok());
+        assert!(matches!(
+            compile("a", "y"),
+            Err(Error::UnsupportedRegExp { .. })
+        ));
+        assert!(matches!(compile("a", "x"), Err(Error::InvalidRegExp(_))));
+    }
+
+    #[test]
+    fn test_translate() {
+        let translated = |source| translate(source, false).unwrap();
+        assert_eq!(translated(r"\d+\W"), "[0-9]+[^0-9A-Za-z_]");
+        assert_eq!(translated(r"[\d\-.]"), r"[0-9\-.]");
+        assert_eq!(translated(r"[[&&~]"), r"[\[\&\&\~]");
+        assert_eq!(translated(r"a\/b"), "a/b");
+        assert_eq!(translated(r"\cJ\0"), r"\x{A}\x{0}");
+        assert_eq!(translated(r"\u00e9\uD83D\uDE00"), r"\x{E9}\x{1F600}");
+        assert_eq!(translated(r"\u{41}"), "u{41}");
+        assert_eq!(translate(r"\u{1F600}", true).unwrap(), r"\x{1F600}");
+        assert_eq!(translated(r"(?<year>\d{4})"), "(?<year>[0-9]{4})");
+    }
+
+    #[test]
+    fn test_matches_as_javascript() {
+        let regex = compile(r"^\w+\b", "").unwrap();
+        assert!(regex.is_match("abc def"));
+        assert!(!regex.is_match("été"));
+        assert!(compile("^[^]$", "").unwrap().is_match("\n"));
+        assert!(!compile("[]", "").unwrap().is_match("anything"));
+        assert!(compile(r"^\uD83D\uDE00$", "").unwrap().is_match("😀"));
+    }
+
+    #[test]
+    fn test_unsupported() {
+        for (source, construct) in [
+            ("a(?=b)", "lookahead"),
+            ("a(?!b)", "lookahead"),
+            ("(?<=a)b", "lookbehind"),
+            ("(?<!a)b", "lookbehind"),
+            (r"(a)\1", "backreferences"),
+            (r"(?<x>a)\k<x>", "backreferences"),
+            (r"\uD83D", "a lone surrogate"),
+        ] {
+            assert!(
+                matches!(
+                    compile(source, ""),
+                    Err(Error::UnsupportedRegExp { construct: c, .. }) if c == construct
+                ),
+                "{source}"
+            );
+        }
+        assert!(matches!(compile("(", ""), Err(Error::InvalidRegExp(_))));
+        assert!(matches!(
+            Value::Null.compile_regexp(),
+            Err(Error::TypeMismatch { .. })
+        ));
+    }
+}
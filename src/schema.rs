@@ -0,0 +1,1137 @@
+//! Typed schemas inferred from sample values.
+//!
+//! [`infer`] describes the shape of a value, including superjson's extended
+//! types, and [`infer_all`] combines several samples into one schema with
+//! unions and optional fields. [`Schema::validate`] then checks values, and
+//! [`Schema::validate_envelope`] checks envelopes before they are
+//! deserialized. [`Schema::to_typescript`] and [`Schema::to_json_schema`]
+//! describe the data for other tools.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use indexmap::IndexMap;
+use serde_json::json;
+
+use crate::bytes::BYTES;
+use crate::path::escape_key;
+use crate::{SuperJson, TypeAnnotation, Value};
+
+/// The inferred type of a value.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Schema {
+    /// No value was seen, such as the elements of an empty array.
+    Never,
+    Null,
+    Undefined,
+    Boolean,
+    /// Any number, including `NaN`, `Infinity`, `-Infinity` and `-0`.
+    Number,
+    String,
+    Date,
+    BigInt,
+    RegExp,
+    Url,
+    Error,
+    /// Data written by the custom transformer with this name.
+    Custom(String),
+    /// A registered symbol.
+    Symbol,
+    /// A value under an annotation of this name that is not recognised.
+    Unknown(String),
+    Bytes,
+    Array(Box<Schema>),
+    Set(Box<Schema>),
+    Map(Box<Schema>, Box<Schema>),
+    /// An object, with fields in the order they were first seen. Class
+    /// instances are described by the object of their fields.
+    Object(IndexMap<String, Field>),
+    /// Any of several schemas, none of which is itself a union and no two of
+    /// which are of the same kind.
+    Union(Vec<Schema>),
+}
+
+/// A field of an object schema.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Field {
+    pub schema: Schema,
+    /// Whether the field was missing from at least one sample.
+    pub optional: bool,
+}
+
+/// Infer the schema of a single value.
+///
+/// # Examples
+/// ```
+/// use superjson_rs::Value;
+/// use superjson_rs::schema::{Schema, infer};
+///
+/// let value = Value::Set(vec![Value::from(1), Value::from("a")]);
+/// assert_eq!(
+///     infer(&value),
+///     Schema::Set(Box::new(Schema::Union(vec![Schema::Number, Schema::String])))
+/// );
+/// ```
+pub fn infer(value: &Value) -> Schema {
+    let mut stack: Vec<Frame> = Vec::new();
+    let mut step = begin(value);
+
+    loop {
+        match step {
+            Step::Descend(frame) => stack.push(*frame),
+            Step::Done(schema) => match stack.last_mut() {
+                None => return schema,
+                Some(parent) => parent.add(schema),
+            },
+        }
+
+        let frame = stack.last_mut().expect("stack is non-empty after a push");
+        step = match frame.children.next() {
+            Some(child) => begin(child),
+            None => Step::Done(stack.pop().expect("stack is non-empty").finish()),
+        };
+    }
+}
+
+/// Infer one schema describing every sample.
+///
+/// Object fields missing from some samples are marked optional, and values
+/// of different kinds at the same position become a [`Schema::Union`].
+///
+/// # Examples
+/// ```
+/// use superjson_rs::schema::{Schema, infer_all};
+/// use superjson_rs::{Map, Value};
+///
+/// let mut a = Map::new();
+/// a.insert("id".to_string(), Value::from(1));
+/// let mut b = Map::new();
+/// b.insert("id".to_string(), Value::from(2));
+/// b.insert("note".to_string(), Value::from("x"));
+///
+/// let Schema::Object(fields) = infer_all([&Value::Object(a), &Value::Object(b)]) else {
+///     panic!("expected an object");
+/// };
+/// assert!(!fields["id"].optional);
+/// assert!(fields["note"].optional);
+/// ```
+pub fn infer_all<'a>(samples: impl IntoIterator<Item = &'a Value>) -> Schema {
+    samples
+        .into_iter()
+        .fold(Schema::Never, |schema, sample| schema.merge(infer(sample)))
+}
+
+impl Schema {
+    /// Combine two schemas into one that describes values of either.
+    pub fn merge(self, other: Schema) -> Schema {
+        match (self, other) {
+            (Schema::Never, schema) | (schema, Schema::Never) => schema,
+            (Schema::Union(members), other) => merge_into_union(members, other),
+            (schema, Schema::Union(members)) => {
+                // Keep `schema` first so members stay in the order first seen.
+                let mut merged = vec![schema];
+                for member in members {
+                    merged = match merge_into_union(merged, member) {
+                        Schema::Union(members) => members,
+                        single => vec![single],
+                    };
+                }
+                union(merged)
+            }
+            (Schema::Array(a), Schema::Array(b)) => Schema::Array(Box::new(a.merge(*b))),
+            (Schema::Set(a), Schema::Set(b)) => Schema::Set(Box::new(a.merge(*b))),
+            (Schema::Map(ak, av), Schema::Map(bk, bv)) => {
+                Schema::Map(Box::new(ak.merge(*bk)), Box::new(av.merge(*bv)))
+            }
+            (Schema::Object(a), Schema::Object(b)) => Schema::Object(merge_fields(a, b)),
+            (a, b) if a.same_kind(&b) => a,
+            (a, b) => Schema::Union(vec![a, b]),
+        }
+    }
+
+    /// Whether two schemas describe the same kind of value, ignoring what
+    /// they contain.
+    fn same_kind(&self, other: &Schema) -> bool {
+        match (self, other) {
+            (Schema::Custom(a), Schema::Custom(b)) | (Schema::Unknown(a), Schema::Unknown(b)) => {
+                a == b
+            }
+            _ => std::mem::discriminant(self) == std::mem::discriminant(other),
+        }
+    }
+}
+
+fn merge_into_union(mut members: Vec<Schema>, other: Schema) -> Schema {
+    let others = match other {
+        Schema::Union(others) => others,
+        other => vec![other],
+    };
+    for other in others {
+        match members.iter().position(|m| m.same_kind(&other)) {
+            Some(i) => {
+                let member = std::mem::replace(&mut members[i], Schema::Never);
+                members[i] = member.merge(other);
+            }
+            None => members.push(other),
+        }
+    }
+    union(members)
+}
+
+fn union(mut members: Vec<Schema>) -> Schema {
+    if members.len() == 1 {
+        members.pop().expect("one member")
+    } else {
+        Schema::Union(members)
+    }
+}
+
+fn merge_fields(
+    mut a: IndexMap<String, Field>,
+    mut b: IndexMap<String, Field>,
+) -> IndexMap<String, Field> {
+    for (key, field) in a.iter_mut() {
+        match b.shift_remove(key) {
+            Some(other) => {
+                let schema = std::mem::replace(&mut field.schema, Schema::Never);
+                field.schema = schema.merge(other.schema);
+                field.optional |= other.optional;
+            }
+            None => field.optional = true,
+        }
+    }
+    for (key, mut field) in b {
+        field.optional = true;
+        a.insert(key, field);
+    }
+    a
+}
+
+/// A place where a value does not match a schema.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Violation {
+    /// Escaped dot-notation path of the offending value.
+    pub path: String,
+    /// The expected type, e.g. `"Date"` or `"number | string"`.
+    pub expected: String,
+    /// The type found, as named by [`Value::type_name`], or `"missing"` for a
+    /// required field that is absent.
+    pub actual: String,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let path = if self.path.is_empty() {
+            "<root>"
+        } else {
+            &self.path
+        };
+        write!(f, "{path}: expected {}, got {}", self.expected, self.actual)
+    }
+}
+
+impl Schema {
+    /// Check `value` against this schema, reporting every violation.
+    ///
+    /// Object fields not described by the schema are allowed.
+    ///
+    /// # Examples
+    /// ```
+    /// use superjson_rs::Value;
+    /// use superjson_rs::schema::infer;
+    ///
+    /// let schema = infer(&Value::Set(vec![Value::from(1)]));
+    /// assert!(schema.validate(&Value::Set(vec![Value::NaN])).is_ok());
+    ///
+    /// let violations = schema
+    ///     .validate(&Value::Set(vec![Value::from(1), Value::from("2")]))
+    ///     .unwrap_err();
+    /// assert_eq!(violations[0].to_string(), "1: expected number, got string");
+    /// ```
+    pub fn validate(&self, value: &Value) -> Result<(), Vec<Violation>> {
+        let mut violations = Vec::new();
+        let mut stack = vec![(String::new(), self, value)];
+
+        while let Some((path, schema, value)) = stack.pop() {
+            let Some(schema) = resolve(schema, value.type_name(), &path, &mut violations) else {
+                continue;
+            };
+
+            // Collected in document order, then pushed in reverse.
+            let mut pending = Vec::new();
+            match (schema, value) {
+                (Schema::Array(elements), Value::Array(items))
+                | (Schema::Set(elements), Value::Set(items)) => {
+                    for (i, item) in items.iter().enumerate() {
+                        pending.push((child(&path, &i.to_string()), &**elements, item));
+                    }
+                }
+                (Schema::Map(keys, values), Value::Map(entries)) => {
+                    for (i, (k, v)) in entries.iter().enumerate() {
+                        pending.push((child(&path, &format!("{i}.0")), &**keys, k));
+                        pending.push((child(&path, &format!("{i}.1")), &**values, v));
+                    }
+                }
+                (Schema::Object(fields), Value::Object(map) | Value::Class { fields: map, .. }) => {
+                    for (key, field) in fields {
+                        let path = child(&path, &escape_key(key));
+                        match map.get(key) {
+                            Some(v) => pending.push((path, &field.schema, v)),
+                            None => missing(path, field, &mut violations),
+                        }
+                    }
+                }
+                _ => {}
+            }
+            stack.extend(pending.into_iter().rev());
+        }
+
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(violations)
+        }
+    }
+
+    /// Check a superjson envelope against this schema without deserializing
+    /// it, using the annotations in `meta` to determine extended types.
+    ///
+    /// Only the shape of the payload is checked; a value that matches but is
+    /// malformed, such as an unparseable date, is still rejected by
+    /// [`deserialize`](crate::deserialize::deserialize).
+    ///
+    /// # Examples
+    /// ```
+    /// use superjson_rs::SuperJson;
+    /// use superjson_rs::schema::Schema;
+    ///
+    /// let envelope: SuperJson =
+    ///     serde_json::from_str(r#"{"json":[1],"meta":{"values":["set"]}}"#).unwrap();
+    /// let schema = Schema::Array(Box::new(Schema::Number));
+    /// let violations = schema.validate_envelope(&envelope).unwrap_err();
+    /// assert_eq!(violations[0].to_string(), "<root>: expected array, got Set");
+    /// ```
+    pub fn validate_envelope(&self, superjson: &SuperJson) -> Result<(), Vec<Violation>> {
+        let annotations: HashMap<String, &str> = superjson
+            .meta
+            .as_ref()
+            .and_then(|meta| meta.values.as_ref())
+            .map(|values| {
+                values
+                    .iter()
+                    .map(|(path, ann)| match ann {
+                        TypeAnnotation::Custom(name) if name == BYTES => (path, "Uint8Array"),
+                        ann => (path, ann.type_name()),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let mut violations = Vec::new();
+        let mut stack = vec![(String::new(), self, &superjson.json)];
+
+        while let Some((path, schema, json)) = stack.pop() {
+            let kind = match annotations.get(&path) {
+                Some(type_name) => annotated_kind(type_name),
+                None => json_kind(json),
+            };
+            let Some(schema) = resolve(schema, kind, &path, &mut violations) else {
+                continue;
+            };
+
+            let mut pending = Vec::new();
+            match (schema, json) {
+                (
+                    Schema::Array(elements) | Schema::Set(elements),
+                    serde_json::Value::Array(items),
+                ) => {
+                    for (i, item) in items.iter().enumerate() {
+                        pending.push((child(&path, &i.to_string()), &**elements, item));
+                    }
+                }
+                (Schema::Map(keys, values), serde_json::Value::Array(entries)) => {
+                    for (i, entry) in entries.iter().enumerate() {
+                        match entry.as_array().map(Vec::as_slice) {
+                            Some([k, v]) => {
+                                pending.push((child(&path, &format!("{i}.0")), &**keys, k));
+                                pending.push((child(&path, &format!("{i}.1")), &**values, v));
+                            }
+                            _ => violations.push(Violation {
+                                path: child(&path, &i.to_string()),
+                                expected: "[key, value] pair".to_string(),
+                                actual: json_kind(entry).to_string(),
+                            }),
+                        }
+                    }
+                }
+                (Schema::Object(fields), serde_json::Value::Object(map)) => {
+                    for (key, field) in fields {
+                        let path = child(&path, &escape_key(key));
+                        match map.get(key) {
+                            Some(v) => pending.push((path, &field.schema, v)),
+                            None => missing(path, field, &mut violations),
+                        }
+                    }
+                }
+                _ => {}
+            }
+            stack.extend(pending.into_iter().rev());
+        }
+
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(violations)
+        }
+    }
+
+    /// Whether this schema, which is not a union, accepts values of `kind`.
+    fn accepts(&self, kind: &str) -> bool {
+        match self {
+            Schema::Never | Schema::Union(_) => false,
+            Schema::Number => matches!(kind, "number" | "NaN" | "Infinity" | "-Infinity" | "-0"),
+            Schema::Custom(_) => kind == "custom",
+            // Values report "unknown"; envelopes the annotation itself.
+            Schema::Unknown(name) => kind == "unknown" || kind == name,
+            Schema::Object(_) => matches!(kind, "object" | "class"),
+            other => other.describe() == kind,
+        }
+    }
+
+    /// A short name for the type, matching [`Value::type_name`] where one
+    /// exists.
+    fn describe(&self) -> String {
+        match self {
+            Schema::Never => "never".to_string(),
+            Schema::Null => "null".to_string(),
+            Schema::Undefined => "undefined".to_string(),
+            Schema::Boolean => "boolean".to_string(),
+            Schema::Number => "number".to_string(),
+            Schema::String => "string".to_string(),
+            Schema::Date => "Date".to_string(),
+            Schema::BigInt => "bigint".to_string(),
+            Schema::RegExp => "RegExp".to_string(),
+            Schema::Url => "URL".to_string(),
+            Schema::Symbol => "symbol".to_string(),
+            Schema::Bytes => "Uint8Array".to_string(),
+            Schema::Error => "Error".to_string(),
+            Schema::Custom(name) | Schema::Unknown(name) => name.clone(),
+            Schema::Array(_) => "array".to_string(),
+            Schema::Set(_) => "Set".to_string(),
+            Schema::Map(_, _) => "Map".to_string(),
+            Schema::Object(_) => "object".to_string(),
+            Schema::Union(members) => members
+                .iter()
+                .map(Schema::describe)
+                .collect::<Vec<_>>()
+                .join(" | "),
+        }
+    }
+}
+
+/// Find the schema, or the union member, that accepts `kind`, recording a
+/// violation if there is none.
+fn resolve<'s>(
+    schema: &'s Schema,
+    kind: &str,
+    path: &str,
+    violations: &mut Vec<Violation>,
+) -> Option<&'s Schema> {
+    let found = match schema {
+        Schema::Union(members) => members.iter().find(|m| m.accepts(kind)),
+        schema => schema.accepts(kind).then_some(schema),
+    };
+    if found.is_none() {
+        violations.push(Violation {
+            path: path.to_string(),
+            expected: schema.describe(),
+            actual: kind.to_string(),
+        });
+    }
+    found
+}
+
+fn missing(path: String, field: &Field, violations: &mut Vec<Violation>) {
+    if !field.optional {
+        violations.push(Violation {
+            path,
+            expected: field.schema.describe(),
+            actual: "missing".to_string(),
+        });
+    }
+}
+
+fn child(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_string()
+    } else {
+        format!("{path}.{key}")
+    }
+}
+
+/// The [`Value::type_name`] of a value restored from an annotation.
+fn annotated_kind(type_name: &str) -> &str {
+    match type_name {
+        "set" => "Set",
+        "map" => "Map",
+        "regexp" => "RegExp",
+        other => other,
+    }
+}
+
+/// The [`Value::type_name`] of an unannotated JSON value.
+fn json_kind(json: &serde_json::Value) -> &'static str {
+    match json {
+        serde_json::Value::Null => "null",
+        serde_json::Value::Bool(_) => "boolean",
+        serde_json::Value::Number(_) => "number",
+        serde_json::Value::String(_) => "string",
+        serde_json::Value::Array(_) => "array",
+        serde_json::Value::Object(_) => "object",
+    }
+}
+
+impl Schema {
+    /// Emit a TypeScript declaration named `name` for this schema: an
+    /// `interface` for objects and a `type` alias otherwise.
+    ///
+    /// Extended types map to their JavaScript counterparts, e.g. `Date`,
+    /// `bigint`, `Set<T>` and `Map<K, V>`, as restored by JS superjson.
+    ///
+    /// # Examples
+    /// ```
+    /// use superjson_rs::schema::infer;
+    /// use superjson_rs::{Map, Value};
+    ///
+    /// let mut user = Map::new();
+    /// user.insert("id".to_string(), Value::from(1));
+    /// user.insert("roles".to_string(), Value::Set(vec![Value::from("admin")]));
+    ///
+    /// assert_eq!(
+    ///     infer(&Value::Object(user)).to_typescript("User"),
+    ///     "export interface User {\n  id: number;\n  roles: Set<string>;\n}\n"
+    /// );
+    /// ```
+    pub fn to_typescript(&self, name: &str) -> String {
+        match self {
+            Schema::Object(_) => format!("export interface {name} {}\n", self.typescript(0)),
+            _ => format!("export type {name} = {};\n", self.typescript(0)),
+        }
+    }
+
+    /// The TypeScript type expression for this schema, with nested object
+    /// members indented one level deeper than `indent`.
+    fn typescript(&self, indent: usize) -> String {
+        match self {
+            Schema::Never => "never".to_string(),
+            Schema::Null => "null".to_string(),
+            Schema::Undefined => "undefined".to_string(),
+            Schema::Boolean => "boolean".to_string(),
+            Schema::Number => "number".to_string(),
+            Schema::String => "string".to_string(),
+            Schema::Date => "Date".to_string(),
+            Schema::BigInt => "bigint".to_string(),
+            Schema::RegExp => "RegExp".to_string(),
+            Schema::Url => "URL".to_string(),
+            Schema::Symbol => "symbol".to_string(),
+            Schema::Bytes => "Uint8Array".to_string(),
+            Schema::Error => "Error".to_string(),
+            // Only the transformer knows the shape of its data.
+            Schema::Custom(_) | Schema::Unknown(_) => "unknown".to_string(),
+            Schema::Array(elements) => match **elements {
+                Schema::Union(_) => format!("({})[]", elements.typescript(indent)),
+                _ => format!("{}[]", elements.typescript(indent)),
+            },
+            Schema::Set(elements) => format!("Set<{}>", elements.typescript(indent)),
+            Schema::Map(keys, values) => format!(
+                "Map<{}, {}>",
+                keys.typescript(indent),
+                values.typescript(indent)
+            ),
+            Schema::Object(fields) if fields.is_empty() => "{}".to_string(),
+            Schema::Object(fields) => {
+                let pad = "  ".repeat(indent + 1);
+                let mut out = "{\n".to_string();
+                for (key, field) in fields {
+                    let optional = if field.optional { "?" } else { "" };
+                    out.push_str(&format!(
+                        "{pad}{}{optional}: {};\n",
+                        typescript_key(key),
+                        field.schema.typescript(indent + 1)
+                    ));
+                }
+                out.push_str(&"  ".repeat(indent));
+                out.push('}');
+                out
+            }
+            Schema::Union(members) => members
+                .iter()
+                .map(|m| m.typescript(indent))
+                .collect::<Vec<_>>()
+                .join(" | "),
+        }
+    }
+}
+
+impl Schema {
+    /// A JSON Schema (draft 2020-12) for the superjson envelope carrying
+    /// values of this schema.
+    ///
+    /// `json` is described in its wire form, e.g. a `Date` as a `date-time`
+    /// string and a `Map` as an array of pairs. `meta.values` is described
+    /// as a well-formed annotation tree, without tying annotations to paths.
+    ///
+    /// # Examples
+    /// ```
+    /// use serde_json::json;
+    /// use superjson_rs::schema::Schema;
+    ///
+    /// let schema = Schema::Set(Box::new(Schema::Date)).to_json_schema();
+    /// assert_eq!(
+    ///     schema["properties"]["json"],
+    ///     json!({"type": "array", "items": {"type": "string", "format": "date-time"}})
+    /// );
+    /// ```
+    pub fn to_json_schema(&self) -> serde_json::Value {
+        json!({
+            "$schema": "https://json-schema.org/draft/2020-12/schema",
+            "type": "object",
+            "properties": {
+                "json": self.wire_json_schema(),
+                "meta": {
+                    "type": "object",
+                    "properties": {
+                        "values": {
+                            "anyOf": [
+                                { "$ref": "#/$defs/annotation" },
+                                {
+                                    "type": "object",
+                                    "additionalProperties": { "$ref": "#/$defs/annotation" }
+                                }
+                            ]
+                        },
+                        "referentialEqualities": {},
+                        "v": { "type": "integer" }
+                    }
+                }
+            },
+            "required": ["json"],
+            "$defs": {
+                "annotation": {
+                    "anyOf": [
+                        {
+                            "type": "array",
+                            "prefixItems": [
+                                { "type": "string" },
+                                {
+                                    "type": "object",
+                                    "additionalProperties": { "$ref": "#/$defs/annotation" }
+                                }
+                            ],
+                            "minItems": 1,
+                            "maxItems": 2
+                        },
+                        {
+                            "type": "array",
+                            "prefixItems": [
+                                {
+                                    "type": "array",
+                                    "prefixItems": [{ "const": "class" }, { "type": "string" }],
+                                    "minItems": 2,
+                                    "maxItems": 2
+                                },
+                                {
+                                    "type": "object",
+                                    "additionalProperties": { "$ref": "#/$defs/annotation" }
+                                }
+                            ],
+                            "minItems": 1,
+                            "maxItems": 2
+                        },
+                        {
+                            "type": "array",
+                            "prefixItems": [{
+                                "type": "array",
+                                "prefixItems": [{ "type": "string" }, { "type": "string" }],
+                                "minItems": 2,
+                                "maxItems": 2
+                            }],
+                            "minItems": 1,
+                            "maxItems": 1
+                        }
+                    ]
+                }
+            }
+        })
+    }
+
+    /// A JSON Schema for the wire form of values of this schema.
+    fn wire_json_schema(&self) -> serde_json::Value {
+        match self {
+            Schema::Never => json!(false),
+            Schema::Null | Schema::Undefined => json!({ "type": "null" }),
+            Schema::Boolean => json!({ "type": "boolean" }),
+            Schema::Number => json!({
+                "anyOf": [
+                    { "type": "number" },
+                    { "enum": ["NaN", "Infinity", "-Infinity", "-0"] }
+                ]
+            }),
+            Schema::String => json!({ "type": "string" }),
+            Schema::Date => json!({ "type": "string", "format": "date-time" }),
+            Schema::BigInt => json!({ "type": "string", "pattern": "^-?[0-9]+$" }),
+            Schema::RegExp => json!({ "type": "string", "pattern": "^/.*/[a-z]*$" }),
+            Schema::Url => json!({ "type": "string", "format": "uri" }),
+            // JS writes a symbol's description, which may be missing.
+            Schema::Symbol => json!({ "type": ["string", "null"] }),
+            Schema::Bytes => json!({ "type": "string" }),
+            Schema::Custom(_) | Schema::Unknown(_) => json!(true),
+            Schema::Error => json!({
+                "type": "object",
+                "properties": {
+                    "name": { "type": "string" },
+                    "message": { "type": "string" }
+                },
+                "required": ["name", "message"]
+            }),
+            Schema::Array(elements) | Schema::Set(elements) => {
+                json!({ "type": "array", "items": elements.wire_json_schema() })
+            }
+            Schema::Map(keys, values) => json!({
+                "type": "array",
+                "items": {
+                    "type": "array",
+                    "prefixItems": [keys.wire_json_schema(), values.wire_json_schema()],
+                    "minItems": 2,
+                    "maxItems": 2
+                }
+            }),
+            Schema::Object(fields) => {
+                let properties: serde_json::Map<_, _> = fields
+                    .iter()
+                    .map(|(key, field)| (key.clone(), field.schema.wire_json_schema()))
+                    .collect();
+                let required: Vec<_> = fields
+                    .iter()
+                    .filter(|(_, field)| !field.optional)
+                    .map(|(key, _)| key.as_str())
+                    .collect();
+                json!({ "type": "object", "properties": properties, "required": required })
+            }
+            Schema::Union(members) => json!({
+                "anyOf": members.iter().map(Schema::wire_json_schema).collect::<Vec<_>>()
+            }),
+        }
+    }
+}
+
+/// Quote an object key unless it is a valid identifier.
+fn typescript_key(key: &str) -> String {
+    let mut chars = key.chars();
+    let is_identifier = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_' || c == '$')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$');
+    if is_identifier {
+        key.to_string()
+    } else {
+        serde_json::Value::from(key).to_string()
+    }
+}
+
+enum Step<'a> {
+    Done(Schema),
+    Descend(Box<Frame<'a>>),
+}
+
+enum Kind {
+    Array,
+    Set,
+    Map,
+    Object,
+}
+
+/// A container whose children are being inferred.
+struct Frame<'a> {
+    kind: Kind,
+    children: std::vec::IntoIter<&'a Value>,
+    /// Object keys, in the same order as `children`.
+    keys: std::vec::IntoIter<&'a String>,
+    /// Merged element schema for arrays and sets; merged key schema for maps.
+    elements: Schema,
+    /// Merged value schema for maps.
+    values: Schema,
+    fields: IndexMap<String, Field>,
+    /// Number of children added so far.
+    added: usize,
+}
+
+impl<'a> Frame<'a> {
+    fn new(kind: Kind, children: Vec<&'a Value>, keys: Vec<&'a String>) -> Self {
+        Frame {
+            kind,
+            children: children.into_iter(),
+            keys: keys.into_iter(),
+            elements: Schema::Never,
+            values: Schema::Never,
+            fields: IndexMap::new(),
+            added: 0,
+        }
+    }
+
+    fn add(&mut self, schema: Schema) {
+        match self.kind {
+            Kind::Array | Kind::Set => {
+                let elements = std::mem::replace(&mut self.elements, Schema::Never);
+                self.elements = elements.merge(schema);
+            }
+            // Map children alternate between keys and values.
+            Kind::Map if self.added.is_multiple_of(2) => {
+                let keys = std::mem::replace(&mut self.elements, Schema::Never);
+                self.elements = keys.merge(schema);
+            }
+            Kind::Map => {
+                let values = std::mem::replace(&mut self.values, Schema::Never);
+                self.values = values.merge(schema);
+            }
+            Kind::Object => {
+                let key = self.keys.next().expect("one key per object child");
+                self.fields.insert(
+                    key.clone(),
+                    Field {
+                        schema,
+                        optional: false,
+                    },
+                );
+            }
+        }
+        self.added += 1;
+    }
+
+    fn finish(self) -> Schema {
+        match self.kind {
+            Kind::Array => Schema::Array(Box::new(self.elements)),
+            Kind::Set => Schema::Set(Box::new(self.elements)),
+            Kind::Map => Schema::Map(Box::new(self.elements), Box::new(self.values)),
+            Kind::Object => Schema::Object(self.fields),
+        }
+    }
+}
+
+fn descend<'a>(kind: Kind, children: Vec<&'a Value>, keys: Vec<&'a String>) -> Step<'a> {
+    Step::Descend(Box::new(Frame::new(kind, children, keys)))
+}
+
+fn begin(value: &Value) -> Step<'_> {
+    let done = Step::Done;
+    match value {
+        Value::Null => done(Schema::Null),
+        Value::Bool(_) => done(Schema::Boolean),
+        Value::Number(_)
+        | Value::Int(_)
+        | Value::UInt(_)
+        | Value::NaN
+        | Value::PosInfinity
+        | Value::NegInfinity
+        | Value::NegZero => done(Schema::Number),
+        Value::String(_) => done(Schema::String),
+        Value::Undefined => done(Schema::Undefined),
+        #[cfg(feature = "chrono")]
+        Value::Date(_) | Value::OffsetDate(_) | Value::InvalidDate => done(Schema::Date),
+        #[cfg(feature = "bigint")]
+        Value::BigInt(_) => done(Schema::BigInt),
+        Value::RegExp { .. } => done(Schema::RegExp),
+        Value::Url(_) => done(Schema::Url),
+        Value::Symbol(_) => done(Schema::Symbol),
+        Value::Bytes(_) => done(Schema::Bytes),
+        Value::Error { .. } => done(Schema::Error),
+        Value::Custom { name, .. } => done(Schema::Custom(name.clone())),
+        Value::Unknown { type_name, .. } => done(Schema::Unknown(type_name.clone())),
+        Value::Array(items) => descend(Kind::Array, items.iter().collect(), vec![]),
+        Value::Set(items) => descend(Kind::Set, items.iter().collect(), vec![]),
+        Value::Map(entries) => descend(
+            Kind::Map,
+            entries.iter().flat_map(|(k, v)| [k, v]).collect(),
+            vec![],
+        ),
+        Value::Object(map) | Value::Class { fields: map, .. } => {
+            descend(Kind::Object, map.values().collect(), map.keys().collect())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::value::Map;
+
+    fn object(entries: &[(&str, Value)]) -> Value {
+        Value::Object(
+            entries
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.clone()))
+                .collect::<Map<_, _>>(),
+        )
+    }
+
+    fn field(schema: Schema, optional: bool) -> Field {
+        Field { schema, optional }
+    }
+
+    #[test]
+    fn test_infer_extended_types() {
+        let value = Value::Map(vec![(Value::from("k"), Value::Set(vec![Value::Undefined]))]);
+        assert_eq!(
+            infer(&value),
+            Schema::Map(
+                Box::new(Schema::String),
+                Box::new(Schema::Set(Box::new(Schema::Undefined)))
+            )
+        );
+        assert_eq!(infer(&Value::NaN), Schema::Number);
+        assert_eq!(
+            infer(&Value::Array(vec![])),
+            Schema::Array(Box::new(Schema::Never))
+        );
+    }
+
+    #[test]
+    fn test_infer_array_of_objects_marks_optional_fields() {
+        let value = Value::Array(vec![
+            object(&[("id", Value::from(1))]),
+            object(&[("id", Value::from("2")), ("tag", Value::Null)]),
+        ]);
+        let mut fields = IndexMap::new();
+        fields.insert(
+            "id".to_string(),
+            field(Schema::Union(vec![Schema::Number, Schema::String]), false),
+        );
+        fields.insert("tag".to_string(), field(Schema::Null, true));
+        assert_eq!(
+            infer(&value),
+            Schema::Array(Box::new(Schema::Object(fields)))
+        );
+    }
+
+    #[test]
+    fn test_merge_unions_flatten_and_combine_by_kind() {
+        let a = Schema::Union(vec![Schema::Null, Schema::Array(Box::new(Schema::Number))]);
+        let b = Schema::Union(vec![
+            Schema::Array(Box::new(Schema::String)),
+            Schema::Boolean,
+        ]);
+        assert_eq!(
+            a.merge(b),
+            Schema::Union(vec![
+                Schema::Null,
+                Schema::Array(Box::new(Schema::Union(vec![
+                    Schema::Number,
+                    Schema::String
+                ]))),
+                Schema::Boolean,
+            ])
+        );
+        assert_eq!(
+            Schema::String.merge(Schema::Union(vec![Schema::Null, Schema::String])),
+            Schema::Union(vec![Schema::String, Schema::Null])
+        );
+    }
+
+    #[test]
+    fn test_infer_all_without_samples() {
+        assert_eq!(infer_all([]), Schema::Never);
+    }
+
+    fn user_schema() -> Schema {
+        let mut fields = IndexMap::new();
+        fields.insert("name".to_string(), field(Schema::String, false));
+        fields.insert(
+            "tags".to_string(),
+            field(Schema::Set(Box::new(Schema::String)), false),
+        );
+        fields.insert("note".to_string(), field(Schema::String, true));
+        Schema::Object(fields)
+    }
+
+    #[test]
+    fn test_validate_reports_every_violation() {
+        let value = object(&[
+            ("tags", Value::Set(vec![Value::from("a"), Value::Null])),
+            ("note", Value::from(1)),
+            ("extra", Value::Undefined),
+        ]);
+        let rendered: Vec<_> = user_schema()
+            .validate(&value)
+            .unwrap_err()
+            .iter()
+            .map(|v| v.to_string())
+            .collect();
+        assert_eq!(
+            rendered,
+            vec![
+                "name: expected string, got missing",
+                "tags.1: expected string, got null",
+                "note: expected string, got number",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validate_union_descends_into_matching_member() {
+        let schema = Schema::Union(vec![
+            Schema::Null,
+            Schema::Map(Box::new(Schema::String), Box::new(Schema::Number)),
+        ]);
+        assert!(schema.validate(&Value::Null).is_ok());
+        let value = Value::Map(vec![(Value::from("k"), Value::Undefined)]);
+        let violations = schema.validate(&value).unwrap_err();
+        assert_eq!(
+            violations[0].to_string(),
+            "0.1: expected number, got undefined"
+        );
+        let violations = schema.validate(&Value::from(1)).unwrap_err();
+        assert_eq!(violations[0].expected, "null | Map");
+    }
+
+    #[test]
+    fn test_validate_envelope_uses_annotations() {
+        let ok = crate::serialize::serialize(&object(&[
+            ("name", Value::from("ada")),
+            ("tags", Value::Set(vec![Value::from("x")])),
+        ]))
+        .unwrap();
+        assert!(user_schema().validate_envelope(&ok).is_ok());
+
+        let bad: SuperJson = serde_json::from_value(serde_json::json!({
+            "json": { "name": "ada", "tags": ["x", null] },
+            "meta": { "values": { "tags": ["set", { "1": ["undefined"] }] } }
+        }))
+        .unwrap();
+        let violations = user_schema().validate_envelope(&bad).unwrap_err();
+        assert_eq!(
+            violations[0].to_string(),
+            "tags.1: expected string, got undefined"
+        );
+    }
+
+    #[test]
+    fn test_validate_envelope_escapes_keys() {
+        let mut fields = IndexMap::new();
+        fields.insert("a.b".to_string(), field(Schema::Undefined, false));
+        let schema = Schema::Object(fields);
+        let value = object(&[("a.b", Value::Undefined)]);
+        let envelope = crate::serialize::serialize(&value).unwrap();
+        assert!(schema.validate_envelope(&envelope).is_ok());
+        assert!(schema.validate(&value).is_ok());
+    }
+
+    #[test]
+    fn test_to_typescript_nested() {
+        let mut address = IndexMap::new();
+        address.insert("city".to_string(), field(Schema::String, false));
+        let mut fields = IndexMap::new();
+        fields.insert("created".to_string(), field(Schema::Date, false));
+        fields.insert("balance".to_string(), field(Schema::BigInt, true));
+        fields.insert("address".to_string(), field(Schema::Object(address), false));
+        fields.insert(
+            "first-name".to_string(),
+            field(Schema::Union(vec![Schema::String, Schema::Null]), false),
+        );
+        fields.insert(
+            "scores".to_string(),
+            field(
+                Schema::Map(
+                    Box::new(Schema::String),
+                    Box::new(Schema::Array(Box::new(Schema::Union(vec![
+                        Schema::Number,
+                        Schema::Undefined,
+                    ])))),
+                ),
+                false,
+            ),
+        );
+        assert_eq!(
+            Schema::Object(fields).to_typescript("Account"),
+            "export interface Account {
+  created: Date;
+  balance?: bigint;
+  address: {
+    city: string;
+  };
+  \"first-name\": string | null;
+  scores: Map<string, (number | undefined)[]>;
+}
+"
+        );
+    }
+
+    #[test]
+    fn test_to_json_schema_object() {
+        let schema = user_schema().to_json_schema();
+        assert_eq!(
+            schema["properties"]["json"],
+            json!({
+                "type": "object",
+                "properties": {
+                    "name": { "type": "string" },
+                    "tags": { "type": "array", "items": { "type": "string" } },
+                    "note": { "type": "string" }
+                },
+                "required": ["name", "tags"]
+            })
+        );
+        assert_eq!(schema["required"], json!(["json"]));
+    }
+
+    #[test]
+    fn test_to_json_schema_accepts_identifiers() {
+        let schema = Schema::Unknown("typed-array".into()).to_json_schema();
+        let symbols = Value::Set(vec![Value::Symbol("admin".into())]);
+        let symbols = crate::serialize::serialize(&symbols).unwrap();
+        // An identifier this crate does not recognise, kept as is.
+        let typed_array = json!({
+            "json": [1, 2],
+            "meta": { "values": [["typed-array", "Int8Array"]], "v": 1 }
+        });
+        for envelope in [serde_json::to_value(symbols).unwrap(), typed_array] {
+            assert!(jsonschema::is_valid(&schema, &envelope), "{envelope}");
+        }
+    }
+
+    #[test]
+    fn test_to_json_schema_accepts_classes() {
+        let mut fields = crate::value::Map::new();
+        fields.insert("x".into(), Value::NaN);
+        let point = Value::Class {
+            name: "Point".into(),
+            fields,
+        };
+        for value in [
+            point.clone(),
+            Value::Array(vec![
+                point,
+                Value::Class {
+                    name: "Empty".into(),
+                    fields: crate::value::Map::new(),
+                },
+            ]),
+        ] {
+            let schema = infer(&value).to_json_schema();
+            let envelope = crate::serialize::serialize(&value).unwrap();
+            let envelope = serde_json::to_value(envelope).unwrap();
+            assert!(jsonschema::is_valid(&schema, &envelope), "{envelope}");
+        }
+    }
+
+    #[test]
+    fn test_to_json_schema_map_and_union() {
+        let schema = Schema::Map(
+            Box::new(Schema::Union(vec![Schema::Null, Schema::Undefined])),
+            Box::new(Schema::Never),
+        );
+        assert_eq!(
+            schema.to_json_schema()["properties"]["json"]["items"]["prefixItems"],
+            json!([{ "anyOf": [{ "type": "null" }, { "type": "null" }] }, false])
+        );
+    }
+}
@@ -0,0 +1,434 @@
+//! [`JsonSchema`] implementations for the envelope types, so services that
+//! generate OpenAPI documents with schemars can describe superjson
+//! endpoints.
+//!
+//! The schemas describe the envelope as this crate writes it: `json` is any
+//! JSON value and `meta.values` is an annotation tree. For a schema of the
+//! `json` part of a particular payload, see
+//! [`Schema::to_json_schema`](crate::schema::Schema::to_json_schema).
+//!
+//! These schemas accept any envelope. For one that accepts only the
+//! envelopes a particular codec writes, such as to validate traffic in an
+//! API gateway, see
+//! [`SuperJsonCodec::wire_schema`](crate::SuperJsonCodec::wire_schema).
+
+use std::borrow::Cow;
+
+use ::schemars::{JsonSchema, Schema, SchemaGenerator, json_schema};
+use serde_json::json;
+
+use crate::serialize::{MetaFormat, SerializeOptions};
+use crate::{AnnotationValues, Meta, SuperJson, TypeAnnotation};
+
+impl JsonSchema for SuperJson {
+    fn schema_name() -> Cow<'static, str> {
+        "SuperJson".into()
+    }
+
+    fn schema_id() -> Cow<'static, str> {
+        "superjson_rs::SuperJson".into()
+    }
+
+    fn json_schema(generator: &mut SchemaGenerator) -> Schema {
+        let meta = generator.subschema_for::<Meta>();
+        json_schema!({
+            "description": "A superjson envelope: JSON data plus the type annotations needed to restore it.",
+            "type": "object",
+            "properties": {
+                "json": true,
+                "meta": meta
+            },
+            "required": ["json"]
+        })
+    }
+}
+
+impl JsonSchema for Meta {
+    fn schema_name() -> Cow<'static, str> {
+        "SuperJsonMeta".into()
+    }
+
+    fn schema_id() -> Cow<'static, str> {
+        "superjson_rs::Meta".into()
+    }
+
+    fn json_schema(generator: &mut SchemaGenerator) -> Schema {
+        let values = generator.subschema_for::<AnnotationValues>();
+        json_schema!({
+            "type": "object",
+            "properties": {
+                "values": values,
+                "referentialEqualities": true,
+                "v": { "type": "integer", "minimum": 0, "maximum": 255 },
+                "checksum": { "type": "string" }
+            }
+        })
+    }
+}
+
+impl JsonSchema for AnnotationValues {
+    fn schema_name() -> Cow<'static, str> {
+        "AnnotationValues".into()
+    }
+
+    fn schema_id() -> Cow<'static, str> {
+        "superjson_rs::AnnotationValues".into()
+    }
+
+    fn json_schema(generator: &mut SchemaGenerator) -> Schema {
+        let annotation = generator.subschema_for::<TypeAnnotation>();
+        json_schema!({
+            "anyOf": [
+                annotation,
+                { "type": "object", "additionalProperties": annotation }
+            ]
+        })
+    }
+}
+
+impl JsonSchema for TypeAnnotation {
+    fn schema_name() -> Cow<'static, str> {
+        "TypeAnnotation".into()
+    }
+
+    fn schema_id() -> Cow<'static, str> {
+        "superjson_rs::TypeAnnotation".into()
+    }
+
+    fn json_schema(generator: &mut SchemaGenerator) -> Schema {
+        let annotation = generator.subschema_for::<Self>();
+        json_schema!({
+            "description": "A type name with optional annotations of children by key, a class with optional annotations of its fields, or a `[kind, name]` identifier such as a custom type or symbol.",
+            "anyOf": [
+                {
+                    "type": "array",
+                    "prefixItems": [
+                        { "type": "string" },
+                        { "type": "object", "additionalProperties": annotation }
+                    ],
+                    "minItems": 1,
+                    "maxItems": 2
+                },
+                {
+                    "type": "array",
+                    "prefixItems": [
+                        {
+                            "type": "array",
+                            "prefixItems": [{ "const": "class" }, { "type": "string" }],
+                            "minItems": 2,
+                            "maxItems": 2
+                        },
+                        { "type": "object", "additionalProperties": annotation }
+                    ],
+                    "minItems": 1,
+                    "maxItems": 2
+                },
+                {
+                    "type": "array",
+                    "prefixItems": [{
+                        "type": "array",
+                        "prefixItems": [{ "type": "string" }, { "type": "string" }],
+                        "minItems": 2,
+                        "maxItems": 2
+                    }],
+                    "minItems": 1,
+                    "maxItems": 1
+                }
+            ]
+        })
+    }
+}
+
+/// The type names of the annotations serialization writes for the
+/// built-in types, without children.
+const LEAF_TYPES: &[&str] = &[
+    "undefined",
+    "number",
+    "Date",
+    #[cfg(feature = "bigint")]
+    "bigint",
+    "regexp",
+    "URL",
+    "set",
+    "map",
+    "Error",
+];
+
+/// The type names of the annotations that may carry the annotations of
+/// their children.
+const NODE_TYPES: &[&str] = &["set", "map", "Error"];
+
+/// The schema of the envelopes serialization with `options` writes, given
+/// the names of the registered custom types and symbols.
+pub(crate) fn wire_schema(
+    options: &SerializeOptions,
+    custom_types: &[&str],
+    symbols: &[String],
+) -> Schema {
+    let values = match options.meta_format {
+        MetaFormat::Tree => json!({
+            "anyOf": [
+                { "$ref": "#/$defs/TypeAnnotation" },
+                {
+                    "type": "object",
+                    "additionalProperties": { "$ref": "#/$defs/TypeAnnotation" }
+                }
+            ]
+        }),
+        MetaFormat::Legacy => json!({
+            "type": "object",
+            "additionalProperties": { "enum": LEAF_TYPES }
+        }),
+    };
+    let mut meta = serde_json::Map::new();
+    meta.insert("values".to_string(), values);
+    if options.meta_format == MetaFormat::Tree {
+        meta.insert("v".to_string(), json!({ "const": crate::META_VERSION }));
+    }
+    if options.referential_equalities || options.dedupe {
+        meta.insert(
+            "referentialEqualities".to_string(),
+            json!({
+                "type": "object",
+                "additionalProperties": { "type": "array", "items": { "type": "string" } }
+            }),
+        );
+    }
+    #[cfg(feature = "hash")]
+    if options.checksum {
+        meta.insert(
+            "checksum".to_string(),
+            json!({ "type": "string", "pattern": "^[0-9a-f]{64}$" }),
+        );
+    }
+
+    let mut defs = serde_json::Map::new();
+    defs.insert(
+        "SuperJsonMeta".to_string(),
+        json!({
+            "type": "object",
+            "properties": meta,
+            "additionalProperties": false
+        }),
+    );
+    if options.meta_format == MetaFormat::Tree {
+        defs.insert(
+            "TypeAnnotation".to_string(),
+            annotation_schema(options, custom_types, symbols),
+        );
+    }
+
+    json_schema!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "title": "SuperJson",
+        "description": "A superjson envelope: JSON data plus the type annotations needed to restore it.",
+        "type": "object",
+        "properties": {
+            "json": true,
+            "meta": { "$ref": "#/$defs/SuperJsonMeta" }
+        },
+        "required": ["json"],
+        "additionalProperties": false,
+        "$defs": defs
+    })
+}
+
+/// The schema of the annotations in the tree format, listing only the
+/// custom types and symbols the codec knows.
+fn annotation_schema(
+    options: &SerializeOptions,
+    custom_types: &[&str],
+    symbols: &[String],
+) -> serde_json::Value {
+    let children = json!({
+        "type": "object",
+        "additionalProperties": { "$ref": "#/$defs/TypeAnnotation" }
+    });
+    // An annotation whose only item is the pair `[kind, name]`.
+    let named = |kind: &str, names: serde_json::Value| {
+        json!({
+            "type": "array",
+            "prefixItems": [{
+                "type": "array",
+                "prefixItems": [{ "const": kind }, names],
+                "minItems": 2,
+                "maxItems": 2
+            }],
+            "minItems": 1,
+            "maxItems": 1
+        })
+    };
+
+    let mut custom_types = custom_types.to_vec();
+    let bytes = options.bytes_name.as_deref().unwrap_or(crate::bytes::BYTES);
+    if !custom_types.contains(&bytes) {
+        custom_types.push(bytes);
+    }
+    let mut any_of = vec![
+        json!({
+            "type": "array",
+            "prefixItems": [{ "enum": LEAF_TYPES }],
+            "minItems": 1,
+            "maxItems": 1
+        }),
+        json!({
+            "type": "array",
+            "prefixItems": [{ "enum": NODE_TYPES }, children],
+            "minItems": 2,
+            "maxItems": 2
+        }),
+        named("custom", json!({ "enum": custom_types })),
+    ];
+    if !symbols.is_empty() {
+        any_of.push(named("symbol", json!({ "enum": symbols })));
+    }
+    any_of.push(json!({
+        "type": "array",
+        "prefixItems": [
+            {
+                "type": "array",
+                "prefixItems": [{ "const": "class" }, { "type": "string" }],
+                "minItems": 2,
+                "maxItems": 2
+            },
+            children
+        ],
+        "minItems": 1,
+        "maxItems": 2
+    }));
+    json!({
+        "description": "A type name with optional annotations of children by key, or a custom type.",
+        "anyOf": any_of
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_envelope_schema_references_definitions() {
+        let schema = ::schemars::schema_for!(SuperJson);
+        let schema = schema.as_value();
+        assert_eq!(schema["required"], json!(["json"]));
+        assert_eq!(
+            schema["properties"]["meta"],
+            json!({ "$ref": "#/$defs/SuperJsonMeta" })
+        );
+        let defs = schema["$defs"].as_object().unwrap();
+        let mut names: Vec<_> = defs.keys().map(String::as_str).collect();
+        names.sort_unstable();
+        assert_eq!(
+            names,
+            ["AnnotationValues", "SuperJsonMeta", "TypeAnnotation"]
+        );
+        // The annotation tree refers to itself rather than being inlined.
+        assert_eq!(
+            defs["TypeAnnotation"]["anyOf"][0]["prefixItems"][1]["additionalProperties"],
+            json!({ "$ref": "#/$defs/TypeAnnotation" })
+        );
+    }
+
+    #[test]
+    fn test_envelope_schema_accepts_identifiers() {
+        let schema = ::schemars::schema_for!(SuperJson);
+        let validator = jsonschema::validator_for(schema.as_value()).unwrap();
+        let symbols = crate::Value::Set(vec![crate::Value::Symbol("admin".into())]);
+        let symbols = crate::serialize::serialize(&symbols).unwrap();
+        // An identifier this crate does not recognise, kept as is.
+        let typed_array = json!({
+            "json": [1, 2],
+            "meta": { "values": [["typed-array", "Int8Array"]], "v": 1 }
+        });
+        for envelope in [serde_json::to_value(symbols).unwrap(), typed_array] {
+            assert!(validator.is_valid(&envelope), "{envelope}");
+        }
+    }
+
+    #[test]
+    fn test_envelope_schema_accepts_classes() {
+        let schema = ::schemars::schema_for!(SuperJson);
+        let validator = jsonschema::validator_for(schema.as_value()).unwrap();
+        let mut fields = crate::value::Map::new();
+        fields.insert("x".into(), crate::Value::NaN);
+        let point = crate::Value::Class {
+            name: "Point".into(),
+            fields,
+        };
+        for value in [
+            point.clone(),
+            crate::Value::Array(vec![
+                point,
+                crate::Value::Class {
+                    name: "Empty".into(),
+                    fields: crate::value::Map::new(),
+                },
+            ]),
+        ] {
+            let envelope = crate::serialize::serialize(&value).unwrap();
+            let envelope = serde_json::to_value(envelope).unwrap();
+            assert!(validator.is_valid(&envelope), "{envelope}");
+        }
+        assert!(!validator.is_valid(&json!({"json": {}, "meta": {"values": [["class", 1]]}})));
+    }
+
+    #[test]
+    fn test_wire_schema_follows_codec() {
+        let mut codec = crate::SuperJsonCodec::new();
+        codec
+            .register_custom("decimal.js", |n: &i64| crate::Value::from(*n), |_| Ok(0i64))
+            .register_symbol("admin");
+        let schema = codec.wire_schema();
+        let schema = schema.as_value();
+        assert_eq!(schema["additionalProperties"], json!(false));
+        let meta = &schema["$defs"]["SuperJsonMeta"];
+        let mut keys: Vec<_> = meta["properties"].as_object().unwrap().keys().collect();
+        keys.sort_unstable();
+        assert_eq!(keys, ["v", "values"]);
+        assert_eq!(meta["properties"]["v"], json!({ "const": 1 }));
+
+        let any_of = schema["$defs"]["TypeAnnotation"]["anyOf"]
+            .as_array()
+            .unwrap();
+        let named = |kind: &str| {
+            any_of
+                .iter()
+                .find(|a| a["prefixItems"][0]["prefixItems"][0]["const"] == kind)
+                .map(|a| a["prefixItems"][0]["prefixItems"][1].clone())
+        };
+        assert_eq!(
+            named("custom"),
+            Some(json!({ "enum": ["decimal.js", "bytes"] }))
+        );
+        assert_eq!(named("symbol"), Some(json!({ "enum": ["admin"] })));
+        assert_eq!(named("class"), Some(json!({ "type": "string" })));
+        assert!(
+            any_of[0]["prefixItems"][0]["enum"]
+                .as_array()
+                .unwrap()
+                .contains(&json!("Date"))
+        );
+    }
+
+    #[test]
+    fn test_wire_schema_follows_options() {
+        let mut codec = crate::SuperJsonCodec::new();
+        codec.serialize_options(SerializeOptions {
+            meta_format: MetaFormat::Legacy,
+            dedupe: true,
+            ..SerializeOptions::default()
+        });
+        let schema = codec.wire_schema();
+        let schema = schema.as_value();
+        let defs = schema["$defs"].as_object().unwrap();
+        assert!(!defs.contains_key("TypeAnnotation"));
+        let properties = &defs["SuperJsonMeta"]["properties"];
+        assert!(properties.get("v").is_none());
+        assert_eq!(properties["values"]["type"], json!("object"));
+        assert_eq!(
+            properties["referentialEqualities"]["additionalProperties"]["items"],
+            json!({ "type": "string" })
+        );
+    }
+}
@@ -0,0 +1,1134 @@
+//! Serializing any `serde::Serialize` type to a superjson envelope.
+//!
+//! [`to_superjson`] runs the type's `Serialize` implementation against a
+//! serializer that writes the `json` tree and its annotations directly, with
+//! no intermediate [`Value`]. Serde's data model is mapped as
+//! follows:
+//!
+//! | Serde | Envelope |
+//! |-------|----------|
+//! | `f32` / `f64` `NaN`, infinities and `-0.0` | `"NaN"` etc., annotated `number` |
+//! | integers outside JavaScript's safe range, and all `i128` / `u128` values beyond it | a string annotated `bigint` (needs the `bigint` feature) |
+//! | a map whose keys are all strings | an object |
+//! | a map with any other keys | `[[key, value], ...]`, annotated `map` |
+//! | a string written with `collect_str` that is an RFC 3339 timestamp, such as a `chrono::DateTime` | a string annotated `Date` (with the `chrono` feature) |
+//! | enums | externally tagged, as `serde_json` writes them |
+//!
+//! Anything else is written as `serde_json` would write it. Serde does not
+//! distinguish sets from sequences, so a `HashSet` becomes a plain array;
+//! build a [`Value::Set`] for a JavaScript `Set`.
+//!
+//! [`to_value`] maps the same way into a [`Value`], with the
+//! extended variants in place of the annotations.
+//!
+//! Serialization recurses through serde, so very deep values are limited by
+//! the thread's stack rather than by a depth limit.
+//!
+//! # Examples
+//! ```
+//! use std::collections::BTreeMap;
+//!
+//! use serde::Serialize;
+//!
+//! #[derive(Serialize)]
+//! struct Stats {
+//!     by_id: BTreeMap<u32, &'static str>,
+//!     ratio: f64,
+//! }
+//!
+//! let stats = Stats { by_id: BTreeMap::from([(7, "seven")]), ratio: f64::NAN };
+//! assert_eq!(
+//!     superjson_rs::to_string(&stats).unwrap(),
+//!     r#"{"json":{"by_id":[[7,"seven"]],"ratio":"NaN"},"meta":{"values":{"by_id":["map"],"ratio":["number"]},"v":1}}"#
+//! );
+//! ```
+
+use std::fmt;
+
+use indexmap::IndexMap;
+use serde::Serialize;
+use serde::ser;
+use serde_json::json;
+
+use crate::path::escape_key;
+use crate::serialize::{
+    self, AnnotationResult, Serialized, children_annotation, collect_child_annotation, leaf,
+    make_typed_annotation,
+};
+use crate::value::Map;
+use crate::{
+    AnnotationValues, Error, Result, SuperJson, TypeAnnotation, Value, deserialize, types,
+};
+
+/// Serialize `value` to a superjson envelope.
+pub fn to_superjson<T: Serialize + ?Sized>(value: &T) -> Result<SuperJson> {
+    let (json, annotation) = value.serialize(EnvelopeSerializer)?;
+    Ok(serialize::envelope(json, annotation))
+}
+
+/// Serialize `value` to a superjson string.
+pub fn to_string<T: Serialize + ?Sized>(value: &T) -> Result<String> {
+    Ok(serde_json::to_string(&to_superjson(value)?)?)
+}
+
+impl ser::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error::Json(<serde_json::Error as ser::Error>::custom(msg))
+    }
+}
+
+/// Writes one value as JSON plus its annotation.
+struct EnvelopeSerializer;
+
+fn number(n: f64) -> Serialized {
+    let special = |s: &str| (json!(s), Some(leaf("number")));
+    if n.is_nan() {
+        special("NaN")
+    } else if n == f64::INFINITY {
+        special("Infinity")
+    } else if n == f64::NEG_INFINITY {
+        special("-Infinity")
+    } else if n == 0.0 && n.is_sign_negative() {
+        special("-0")
+    } else {
+        (json!(n), None)
+    }
+}
+
+fn integer(n: i128) -> Result<Serialized> {
+    if n.unsigned_abs() <= serialize::MAX_SAFE_INTEGER as u128 {
+        Ok((json!(n as i64), None))
+    } else {
+        unsafe_integer(n)
+    }
+}
+
+/// An integer a JavaScript number cannot hold exactly.
+fn unsafe_integer(n: impl fmt::Display) -> Result<Serialized> {
+    #[cfg(feature = "bigint")]
+    return Ok((json!(n.to_string()), Some(leaf("bigint"))));
+    #[cfg(not(feature = "bigint"))]
+    {
+        let _ = n;
+        Err(Error::UnsupportedType {
+            name: "bigint".to_string(),
+            feature: "bigint",
+        })
+    }
+}
+
+/// `{variant: value}`, the externally tagged form of an enum variant.
+fn tagged(variant: &'static str, (json, annotation): Serialized) -> Serialized {
+    let mut annotations = IndexMap::new();
+    collect_child_annotation(&mut annotations, &escape_key(variant), annotation);
+    let mut map = serde_json::Map::new();
+    map.insert(variant.to_string(), json);
+    (
+        serde_json::Value::Object(map),
+        children_annotation(annotations),
+    )
+}
+
+/// The annotation of an array whose elements carry `annotation`, typed as
+/// `type_name`.
+fn retyped(type_name: &str, annotation: Option<AnnotationResult>) -> AnnotationResult {
+    match annotation {
+        Some(AnnotationResult::Children(inner)) => make_typed_annotation(type_name, inner),
+        _ => leaf(type_name),
+    }
+}
+
+impl ser::Serializer for EnvelopeSerializer {
+    type Ok = Serialized;
+    type Error = Error;
+    type SerializeSeq = SeqSerializer;
+    type SerializeTuple = SeqSerializer;
+    type SerializeTupleStruct = SeqSerializer;
+    type SerializeTupleVariant = SeqSerializer;
+    type SerializeMap = MapSerializer;
+    type SerializeStruct = StructSerializer;
+    type SerializeStructVariant = StructSerializer;
+
+    fn serialize_bool(self, v: bool) -> Result<Serialized> {
+        Ok((json!(v), None))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Serialized> {
+        integer(v.into())
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Serialized> {
+        integer(v.into())
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Serialized> {
+        integer(v.into())
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Serialized> {
+        integer(v.into())
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<Serialized> {
+        integer(v)
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Serialized> {
+        integer(v.into())
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Serialized> {
+        integer(v.into())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Serialized> {
+        integer(v.into())
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Serialized> {
+        integer(v.into())
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<Serialized> {
+        match i128::try_from(v) {
+            Ok(v) => integer(v),
+            Err(_) => unsafe_integer(v),
+        }
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Serialized> {
+        Ok(number(v.into()))
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Serialized> {
+        Ok(number(v))
+    }
+
+    fn serialize_char(self, v: char) -> Result<Serialized> {
+        Ok((json!(v), None))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Serialized> {
+        Ok((json!(v), None))
+    }
+
+    fn collect_str<T: fmt::Display + ?Sized>(self, value: &T) -> Result<Serialized> {
+        let s = value.to_string();
+        #[cfg(feature = "chrono")]
+        if let Ok(dt) = chrono::DateTime::parse_from_rfc3339(&s) {
+            let dt = dt
+                .with_timezone(&chrono::Utc)
+                .to_rfc3339_opts(chrono::SecondsFormat::Millis, true);
+            return Ok((json!(dt), Some(leaf("Date"))));
+        }
+        Ok((json!(s), None))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Serialized> {
+        Ok((json!(v), None))
+    }
+
+    fn serialize_none(self) -> Result<Serialized> {
+        Ok((serde_json::Value::Null, None))
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Serialized> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Serialized> {
+        Ok((serde_json::Value::Null, None))
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Serialized> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<Serialized> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        name: &'static str,
+        value: &T,
+    ) -> Result<Serialized> {
+        let (json, annotation) = value.serialize(self)?;
+        Ok(match name {
+            types::DATE => (json, Some(leaf("Date"))),
+            types::BIGINT => (json, Some(leaf("bigint"))),
+            types::SET => (json, Some(retyped("set", annotation))),
+            types::MAP => (json, Some(retyped("map", annotation))),
+            types::UNDEFINED => (serde_json::Value::Null, Some(leaf("undefined"))),
+            types::VALUE => {
+                let superjson: SuperJson = serde_json::from_value(json)?;
+                let annotation =
+                    superjson
+                        .meta
+                        .and_then(|meta| meta.values)
+                        .map(|values| match values {
+                            AnnotationValues::Root(annotation) => {
+                                AnnotationResult::Typed(annotation)
+                            }
+                            AnnotationValues::Children(children) => {
+                                AnnotationResult::Children(children)
+                            }
+                        });
+                (superjson.json, annotation)
+            }
+            _ => (json, annotation),
+        })
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Serialized> {
+        Ok(tagged(variant, value.serialize(self)?))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SeqSerializer> {
+        Ok(SeqSerializer::new(len.unwrap_or(0), None))
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SeqSerializer> {
+        Ok(SeqSerializer::new(len, None))
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, len: usize) -> Result<SeqSerializer> {
+        Ok(SeqSerializer::new(len, None))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<SeqSerializer> {
+        Ok(SeqSerializer::new(len, Some(variant)))
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<MapSerializer> {
+        Ok(MapSerializer {
+            entries: Vec::with_capacity(len.unwrap_or(0)),
+            key: None,
+        })
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<StructSerializer> {
+        Ok(StructSerializer::new(None))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<StructSerializer> {
+        Ok(StructSerializer::new(Some(variant)))
+    }
+}
+
+/// Collects the elements of a sequence, tuple or tuple variant.
+struct SeqSerializer {
+    json: Vec<serde_json::Value>,
+    annotations: IndexMap<String, TypeAnnotation>,
+    variant: Option<&'static str>,
+}
+
+impl SeqSerializer {
+    fn new(len: usize, variant: Option<&'static str>) -> Self {
+        SeqSerializer {
+            json: Vec::with_capacity(len),
+            annotations: IndexMap::new(),
+            variant,
+        }
+    }
+
+    fn push<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        let (json, annotation) = value.serialize(EnvelopeSerializer)?;
+        let key = self.json.len().to_string();
+        collect_child_annotation(&mut self.annotations, &key, annotation);
+        self.json.push(json);
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Serialized> {
+        let array = (
+            serde_json::Value::Array(self.json),
+            children_annotation(self.annotations),
+        );
+        Ok(match self.variant {
+            Some(variant) => tagged(variant, array),
+            None => array,
+        })
+    }
+}
+
+impl ser::SerializeSeq for SeqSerializer {
+    type Ok = Serialized;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Serialized> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTuple for SeqSerializer {
+    type Ok = Serialized;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Serialized> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleStruct for SeqSerializer {
+    type Ok = Serialized;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Serialized> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleVariant for SeqSerializer {
+    type Ok = Serialized;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Serialized> {
+        self.finish()
+    }
+}
+
+/// Collects the fields of a struct or struct variant.
+struct StructSerializer {
+    json: serde_json::Map<String, serde_json::Value>,
+    annotations: IndexMap<String, TypeAnnotation>,
+    variant: Option<&'static str>,
+}
+
+impl StructSerializer {
+    fn new(variant: Option<&'static str>) -> Self {
+        StructSerializer {
+            json: serde_json::Map::new(),
+            annotations: IndexMap::new(),
+            variant,
+        }
+    }
+
+    fn push<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T) -> Result<()> {
+        let (json, annotation) = value.serialize(EnvelopeSerializer)?;
+        collect_child_annotation(&mut self.annotations, &escape_key(key), annotation);
+        self.json.insert(key.to_string(), json);
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Serialized> {
+        let object = (
+            serde_json::Value::Object(self.json),
+            children_annotation(self.annotations),
+        );
+        Ok(match self.variant {
+            Some(variant) => tagged(variant, object),
+            None => object,
+        })
+    }
+}
+
+impl ser::SerializeStruct for StructSerializer {
+    type Ok = Serialized;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        self.push(key, value)
+    }
+
+    fn end(self) -> Result<Serialized> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStructVariant for StructSerializer {
+    type Ok = Serialized;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        self.push(key, value)
+    }
+
+    fn end(self) -> Result<Serialized> {
+        self.finish()
+    }
+}
+
+/// Collects the entries of a map, which becomes an object if every key is a
+/// plain string and a superjson Map otherwise.
+struct MapSerializer {
+    entries: Vec<(Serialized, Serialized)>,
+    key: Option<Serialized>,
+}
+
+impl ser::SerializeMap for MapSerializer {
+    type Ok = Serialized;
+    type Error = Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<()> {
+        self.key = Some(key.serialize(EnvelopeSerializer)?);
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        let key = self
+            .key
+            .take()
+            .expect("serialize_key is called before serialize_value");
+        self.entries
+            .push((key, value.serialize(EnvelopeSerializer)?));
+        Ok(())
+    }
+
+    fn end(self) -> Result<Serialized> {
+        let string_keys = self
+            .entries
+            .iter()
+            .all(|((key, annotation), _)| key.is_string() && annotation.is_none());
+        let mut annotations = IndexMap::new();
+
+        if string_keys {
+            let mut json = serde_json::Map::with_capacity(self.entries.len());
+            for ((key, _), (value, annotation)) in self.entries {
+                let serde_json::Value::String(key) = key else {
+                    unreachable!("every key is a string");
+                };
+                collect_child_annotation(&mut annotations, &escape_key(&key), annotation);
+                json.insert(key, value);
+            }
+            return Ok((
+                serde_json::Value::Object(json),
+                children_annotation(annotations),
+            ));
+        }
+
+        let mut pairs = Vec::with_capacity(self.entries.len());
+        for (i, ((key, key_annotation), (value, value_annotation))) in
+            self.entries.into_iter().enumerate()
+        {
+            collect_child_annotation(&mut annotations, &format!("{i}.0"), key_annotation);
+            collect_child_annotation(&mut annotations, &format!("{i}.1"), value_annotation);
+            pairs.push(json!([key, value]));
+        }
+        Ok((
+            serde_json::Value::Array(pairs),
+            Some(make_typed_annotation("map", annotations)),
+        ))
+    }
+}
+
+/// Serialize `value` to a [`Value`], mapping serde's data model as
+/// [`to_superjson`] does.
+///
+/// # Examples
+/// ```
+/// use std::collections::HashMap;
+///
+/// use superjson_rs::Value;
+///
+/// let value = superjson_rs::to_value(&HashMap::from([(1, f64::INFINITY)])).unwrap();
+/// assert_eq!(value, Value::Map(vec![(Value::from(1), Value::PosInfinity)]));
+/// ```
+pub fn to_value<T: Serialize + ?Sized>(value: &T) -> Result<Value> {
+    value.serialize(ValueSerializer)
+}
+
+/// Writes one value as a [`Value`].
+struct ValueSerializer;
+
+fn number_value(n: f64) -> Value {
+    if n.is_nan() {
+        Value::NaN
+    } else if n == f64::INFINITY {
+        Value::PosInfinity
+    } else if n == f64::NEG_INFINITY {
+        Value::NegInfinity
+    } else if n == 0.0 && n.is_sign_negative() {
+        Value::NegZero
+    } else {
+        Value::Number(n)
+    }
+}
+
+fn integer_value(n: i128) -> Result<Value> {
+    if n.unsigned_abs() <= serialize::MAX_SAFE_INTEGER as u128 {
+        Ok(Value::Int(n as i64))
+    } else {
+        unsafe_integer_value(n)
+    }
+}
+
+/// An integer a JavaScript number cannot hold exactly.
+fn unsafe_integer_value(n: impl fmt::Display) -> Result<Value> {
+    #[cfg(feature = "bigint")]
+    return Ok(Value::BigInt(
+        n.to_string().parse().expect("integers are valid bigints"),
+    ));
+    #[cfg(not(feature = "bigint"))]
+    {
+        let _ = n;
+        Err(Error::UnsupportedType {
+            name: "bigint".to_string(),
+            feature: "bigint",
+        })
+    }
+}
+
+/// `{variant: value}`, the externally tagged form of an enum variant.
+fn tagged_value(variant: &'static str, value: Value) -> Value {
+    let mut map = Map::new();
+    map.insert(variant.to_string(), value);
+    Value::Object(map)
+}
+
+impl ser::Serializer for ValueSerializer {
+    type Ok = Value;
+    type Error = Error;
+    type SerializeSeq = ValueSeqSerializer;
+    type SerializeTuple = ValueSeqSerializer;
+    type SerializeTupleStruct = ValueSeqSerializer;
+    type SerializeTupleVariant = ValueSeqSerializer;
+    type SerializeMap = ValueMapSerializer;
+    type SerializeStruct = ValueStructSerializer;
+    type SerializeStructVariant = ValueStructSerializer;
+
+    fn serialize_bool(self, v: bool) -> Result<Value> {
+        Ok(Value::Bool(v))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Value> {
+        integer_value(v.into())
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Value> {
+        integer_value(v.into())
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Value> {
+        integer_value(v.into())
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Value> {
+        integer_value(v.into())
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<Value> {
+        integer_value(v)
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Value> {
+        integer_value(v.into())
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Value> {
+        integer_value(v.into())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Value> {
+        integer_value(v.into())
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Value> {
+        integer_value(v.into())
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<Value> {
+        match i128::try_from(v) {
+            Ok(v) => integer_value(v),
+            Err(_) => unsafe_integer_value(v),
+        }
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Value> {
+        Ok(number_value(v.into()))
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Value> {
+        Ok(number_value(v))
+    }
+
+    fn serialize_char(self, v: char) -> Result<Value> {
+        Ok(Value::String(v.to_string()))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Value> {
+        Ok(Value::String(v.to_string()))
+    }
+
+    fn collect_str<T: fmt::Display + ?Sized>(self, value: &T) -> Result<Value> {
+        let s = value.to_string();
+        #[cfg(feature = "chrono")]
+        if let Ok(dt) = chrono::DateTime::parse_from_rfc3339(&s) {
+            return Ok(Value::Date(dt.with_timezone(&chrono::Utc)));
+        }
+        Ok(Value::String(s))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Value> {
+        Ok(Value::Array(
+            v.iter().map(|&b| Value::Int(b.into())).collect(),
+        ))
+    }
+
+    fn serialize_none(self) -> Result<Value> {
+        Ok(Value::Null)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Value> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Value> {
+        Ok(Value::Null)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Value> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<Value> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        name: &'static str,
+        value: &T,
+    ) -> Result<Value> {
+        Ok(match (name, value.serialize(self)?) {
+            #[cfg(feature = "chrono")]
+            (types::DATE, Value::String(s)) => match chrono::DateTime::parse_from_rfc3339(&s) {
+                Ok(dt) => Value::Date(dt.with_timezone(&chrono::Utc)),
+                Err(_) => Value::String(s),
+            },
+            #[cfg(feature = "bigint")]
+            (types::BIGINT, Value::String(s)) => match s.parse() {
+                Ok(n) => Value::BigInt(n),
+                Err(_) => Value::String(s),
+            },
+            (types::SET, Value::Array(items)) => Value::Set(items),
+            (types::MAP, Value::Array(pairs)) => Value::Map(
+                pairs
+                    .into_iter()
+                    .map(|pair| {
+                        let Value::Array(pair) = pair else {
+                            unreachable!("JsMap entries are serialized as tuples");
+                        };
+                        let [key, value]: [Value; 2] =
+                            pair.try_into().expect("JsMap entries are pairs");
+                        (key, value)
+                    })
+                    .collect(),
+            ),
+            (types::UNDEFINED, _) => Value::Undefined,
+            (types::VALUE, envelope) => {
+                let json = serialize::serialize(&envelope)?.json;
+                deserialize::deserialize(&serde_json::from_value(json)?)?
+            }
+            (_, value) => value,
+        })
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Value> {
+        Ok(tagged_value(variant, value.serialize(self)?))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<ValueSeqSerializer> {
+        Ok(ValueSeqSerializer::new(len.unwrap_or(0), None))
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<ValueSeqSerializer> {
+        Ok(ValueSeqSerializer::new(len, None))
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, len: usize) -> Result<ValueSeqSerializer> {
+        Ok(ValueSeqSerializer::new(len, None))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<ValueSeqSerializer> {
+        Ok(ValueSeqSerializer::new(len, Some(variant)))
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<ValueMapSerializer> {
+        Ok(ValueMapSerializer {
+            entries: Vec::with_capacity(len.unwrap_or(0)),
+            key: None,
+        })
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<ValueStructSerializer> {
+        Ok(ValueStructSerializer {
+            map: Map::new(),
+            variant: None,
+        })
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<ValueStructSerializer> {
+        Ok(ValueStructSerializer {
+            map: Map::new(),
+            variant: Some(variant),
+        })
+    }
+}
+
+/// Collects the elements of a sequence, tuple or tuple variant as a
+/// [`Value`].
+struct ValueSeqSerializer {
+    items: Vec<Value>,
+    variant: Option<&'static str>,
+}
+
+impl ValueSeqSerializer {
+    fn new(len: usize, variant: Option<&'static str>) -> Self {
+        ValueSeqSerializer {
+            items: Vec::with_capacity(len),
+            variant,
+        }
+    }
+
+    fn push<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.items.push(value.serialize(ValueSerializer)?);
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Value> {
+        let array = Value::Array(self.items);
+        Ok(match self.variant {
+            Some(variant) => tagged_value(variant, array),
+            None => array,
+        })
+    }
+}
+
+impl ser::SerializeSeq for ValueSeqSerializer {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTuple for ValueSeqSerializer {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleStruct for ValueSeqSerializer {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleVariant for ValueSeqSerializer {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value> {
+        self.finish()
+    }
+}
+
+/// Collects the fields of a struct or struct variant as a [`Value`].
+struct ValueStructSerializer {
+    map: Map<String, Value>,
+    variant: Option<&'static str>,
+}
+
+impl ValueStructSerializer {
+    fn push<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T) -> Result<()> {
+        self.map
+            .insert(key.to_string(), value.serialize(ValueSerializer)?);
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Value> {
+        let object = Value::Object(self.map);
+        Ok(match self.variant {
+            Some(variant) => tagged_value(variant, object),
+            None => object,
+        })
+    }
+}
+
+impl ser::SerializeStruct for ValueStructSerializer {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        self.push(key, value)
+    }
+
+    fn end(self) -> Result<Value> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStructVariant for ValueStructSerializer {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        self.push(key, value)
+    }
+
+    fn end(self) -> Result<Value> {
+        self.finish()
+    }
+}
+
+/// Collects the entries of a map, which becomes an object if every key is a
+/// string and a [`Value::Map`] otherwise.
+struct ValueMapSerializer {
+    entries: Vec<(Value, Value)>,
+    key: Option<Value>,
+}
+
+impl ser::SerializeMap for ValueMapSerializer {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<()> {
+        self.key = Some(key.serialize(ValueSerializer)?);
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        let key = self
+            .key
+            .take()
+            .expect("serialize_key is called before serialize_value");
+        self.entries.push((key, value.serialize(ValueSerializer)?));
+        Ok(())
+    }
+
+    fn end(self) -> Result<Value> {
+        if !self
+            .entries
+            .iter()
+            .all(|(key, _)| matches!(key, Value::String(_)))
+        {
+            return Ok(Value::Map(self.entries));
+        }
+        let mut map = Map::new();
+        for (key, value) in self.entries {
+            let Value::String(key) = key else {
+                unreachable!("every key is a string");
+            };
+            map.insert(key, value);
+        }
+        Ok(Value::Object(map))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[derive(Serialize)]
+    struct Plain {
+        name: String,
+        tags: Vec<&'static str>,
+        score: Option<f32>,
+        nested: HashMap<String, bool>,
+    }
+
+    #[test]
+    fn test_plain_types_match_serde_json() {
+        let plain = Plain {
+            name: "ada".into(),
+            tags: vec!["a", "b"],
+            score: None,
+            nested: HashMap::from([("x".to_string(), true)]),
+        };
+        let envelope = to_superjson(&plain).unwrap();
+        assert!(envelope.meta.is_none());
+        assert_eq!(envelope.json, serde_json::to_value(&plain).unwrap());
+    }
+
+    #[derive(Serialize)]
+    enum Event {
+        Moved { dx: f64 },
+        Pair(i8, f64),
+        Ping,
+    }
+
+    #[test]
+    fn test_variants_carry_annotations() {
+        let events = vec![
+            Event::Moved { dx: f64::INFINITY },
+            Event::Pair(1, -0.0),
+            Event::Ping,
+        ];
+        let text = to_string(&events).unwrap();
+        assert_eq!(
+            crate::parse(&text).unwrap(),
+            crate::parse(
+                r#"{"json":[{"Moved":{"dx":"Infinity"}},{"Pair":[1,"-0"]},"Ping"],
+                    "meta":{"values":{"0.Moved.dx":["number"],"1.Pair.1":["number"]}}}"#
+            )
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_non_string_keys_make_a_map() {
+        let map = HashMap::from([((1u8, 2u8), "pair")]);
+        let value = crate::parse(&to_string(&map).unwrap()).unwrap();
+        assert_eq!(
+            value,
+            Value::Map(vec![(
+                Value::from(vec![Value::from(1), Value::from(2)]),
+                Value::from("pair")
+            )])
+        );
+    }
+
+    #[cfg(feature = "bigint")]
+    #[test]
+    fn test_unsafe_integers_become_bigints() {
+        let value = crate::parse(&to_string(&(u64::MAX, 9_007_199_254_740_991i64)).unwrap());
+        assert_eq!(
+            value.unwrap(),
+            Value::from(vec![
+                Value::BigInt(u64::MAX.into()),
+                Value::from(9_007_199_254_740_991i64)
+            ])
+        );
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn test_chrono_dates_become_dates() {
+        use chrono::TimeZone;
+
+        let dt = chrono::Utc.timestamp_millis_opt(1_700_000_000_123).unwrap();
+        let value = crate::parse(&to_string(&[dt]).unwrap()).unwrap();
+        assert_eq!(value, Value::from(vec![Value::Date(dt)]));
+    }
+
+    #[test]
+    fn test_to_value_matches_the_envelope() {
+        let source = || {
+            (
+                vec![
+                    Event::Moved { dx: f64::NAN },
+                    Event::Pair(-3, -0.0),
+                    Event::Ping,
+                ],
+                HashMap::from([((1u8, 2u8), "pair")]),
+                u128::MAX,
+            )
+        };
+        let value = to_value(&source());
+        let parsed = to_string(&source()).and_then(|text| crate::parse(&text));
+        assert_eq!(value.ok(), parsed.ok());
+    }
+
+    #[test]
+    fn test_to_value_roundtrips_through_from_value() {
+        let mut scores = HashMap::new();
+        scores.insert(7u32, vec![f64::NEG_INFINITY, 1.5]);
+        let value = to_value(&scores).unwrap();
+        assert!(matches!(value, Value::Map(_)));
+        let restored: HashMap<u32, Vec<f64>> = crate::from_value(value).unwrap();
+        assert_eq!(restored, scores);
+    }
+}
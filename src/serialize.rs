@@ -1,12 +1,116 @@
+use std::collections::HashMap;
+
+#[cfg(feature = "chrono")]
 use chrono::SecondsFormat;
 use indexmap::IndexMap;
 use serde_json::json;
 
-use crate::{AnnotationValues, Meta, Result, SuperJson, TypeAnnotation, Value};
+use crate::backend::{JsonBackend, SerdeJson};
+use crate::bytes::{BYTES, BytesEncoding};
+use crate::codec::{Hook, apply_hooks};
+use crate::warning::Warning;
+use crate::{AnnotationValues, Error, Meta, Result, SuperJson, TypeAnnotation, Value};
+
+/// The largest integer a JavaScript number can represent exactly,
+/// `Number.MAX_SAFE_INTEGER`.
+pub const MAX_SAFE_INTEGER: f64 = 9_007_199_254_740_991.0;
+
+/// Checks applied while serializing.
+#[derive(Debug, Clone, Default)]
+pub struct SerializeOptions {
+    /// How to treat numbers whose magnitude exceeds [`MAX_SAFE_INTEGER`].
+    pub unsafe_integers: UnsafeIntegers,
+    /// Emit BigInts within the safe integer range as plain numbers, without
+    /// an annotation.
+    ///
+    /// This shrinks the metadata of payloads full of small `i64` IDs, but
+    /// such a value parses back as a [`Value::Int`], not a BigInt: the
+    /// round trip is no longer exact.
+    pub downcast_safe_bigints: bool,
+    /// The shape of `meta` in stringified output.
+    ///
+    /// A [`SuperJson`] always holds the tree format; this only affects
+    /// [`stringify_with`](crate::stringify_with) and the other functions
+    /// producing text.
+    pub meta_format: MetaFormat,
+    /// Record a checksum of the `json` section in `meta.checksum`.
+    ///
+    /// Parsing recomputes it and fails with [`Error::ChecksumMismatch`] if
+    /// the payload was altered in transit. The checksum covers the canonical
+    /// form of `json`, so re-encoding that only reorders keys or respells
+    /// numbers does not break it.
+    #[cfg(feature = "hash")]
+    pub checksum: bool,
+    /// How [`Value::Bytes`] are written.
+    pub bytes_encoding: BytesEncoding,
+    /// The custom type name [`Value::Bytes`] are annotated with, or
+    /// [`BYTES`] if `None`.
+    pub bytes_name: Option<String>,
+    /// Keys of [`Value::Error`]'s `extra` map to write alongside `name`,
+    /// `message` and `cause`; others are dropped, as JS superjson drops
+    /// properties missing from its `allowErrorProps`. Listing `stack`
+    /// writes [`Value::Error`]'s `stack` too.
+    pub error_props: Vec<String>,
+    /// Record containers equal to one written earlier in
+    /// `meta.referentialEqualities`.
+    ///
+    /// JS superjson lists the paths an object is shared between, and parsing
+    /// in JavaScript makes them one object again. A [`Value`] cannot share a
+    /// subtree, so equal arrays, objects, sets, maps, errors and class
+    /// instances stand in for shared ones. Unless [`dedupe`](Self::dedupe)
+    /// is set, each copy is still written in full, so parsers ignoring the
+    /// metadata read the same value.
+    pub referential_equalities: bool,
+    /// Write containers equal to one written earlier as `null`, recording
+    /// where they belong in `meta.referentialEqualities`, as JS superjson's
+    /// `dedupe: true` does.
+    ///
+    /// This shrinks payloads that repeat large values, and parsing, in this
+    /// crate or in JS superjson, copies them back. Parsers that ignore the
+    /// metadata read `null`s instead.
+    pub dedupe: bool,
+    /// Write stringified output in its canonical form, that of
+    /// [`SuperJson::canonical_bytes`], so that equal envelopes are written
+    /// byte for byte alike and can be signed.
+    ///
+    /// Like [`meta_format`](Self::meta_format), this only affects the
+    /// functions producing text.
+    pub canonical: bool,
+}
+
+/// The shape of `meta.values` in an envelope.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MetaFormat {
+    /// The annotation tree of current superjson releases, e.g.
+    /// `{"values": {"a": ["set", {"0": ["Date"]}]}, "v": 1}`.
+    #[default]
+    Tree,
+    /// The flat map of paths to type names of older releases, e.g.
+    /// `{"values": {"a": "set", "a.0": "Date"}}`, for consumers that
+    /// predate the tree format. The root annotation, if any, has the empty
+    /// path. Custom types, class instances and symbols cannot be written in
+    /// this format.
+    Legacy,
+}
+
+/// What to do with a number outside JavaScript's safe integer range.
+///
+/// Such a number already lost precision as an `f64`, and a JavaScript client
+/// cannot distinguish it from its neighbours; it should be sent as a BigInt.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum UnsafeIntegers {
+    /// Serialize it without comment.
+    #[default]
+    Allow,
+    /// Serialize it and report a [`Warning::UnsafeInteger`].
+    Warn,
+    /// Fail with [`Error::UnsafeInteger`].
+    Error,
+}
 
 /// Internal result from serializing a value.
 /// Tracks whether the annotation applies to the value itself or its children.
-enum AnnotationResult {
+pub(crate) enum AnnotationResult {
     /// This value itself is an extended type (Date, Set, etc.)
     Typed(TypeAnnotation),
     /// This value is a plain object/array with annotated descendants.
@@ -19,8 +123,144 @@ enum AnnotationResult {
 /// values and records type annotations in the metadata using the tree format
 /// compatible with JS superjson.
 pub fn serialize(value: &Value) -> Result<SuperJson> {
-    let (json, annotation) = serialize_value(value)?;
+    serialize_with(value, &SerializeOptions::default())
+}
+
+/// Serialize a `Value`, applying the checks in `options`.
+///
+/// Warnings are discarded; use
+/// [`stringify_detailed`](crate::stringify_detailed) to receive them.
+pub fn serialize_with(value: &Value, options: &SerializeOptions) -> Result<SuperJson> {
+    serialize_detailed(value, options, &[], &mut Vec::new())
+}
+
+/// Serialize a `Value` into the envelope as a JSON object, for interfaces
+/// such as tRPC's `DataTransformer` that pass `{json, meta}` around as data
+/// rather than text.
+///
+/// # Examples
+/// ```
+/// use superjson_rs::{Value, serialize::serialize_to_json};
+///
+/// let envelope = serialize_to_json(&Value::Set(vec![Value::from(1)])).unwrap();
+/// assert_eq!(envelope, serde_json::json!({"json": [1], "meta": {"values": ["set"], "v": 1}}));
+/// ```
+pub fn serialize_to_json(value: &Value) -> Result<serde_json::Value> {
+    Ok(serde_json::to_value(serialize(value)?)?)
+}
+
+/// Serialize a `Value` into its `json` and `meta` halves, to be sent on
+/// separate channels. `meta` is `None` if nothing needed an annotation.
+///
+/// [`deserialize_split`](crate::deserialize::deserialize_split) puts them
+/// back together.
+pub fn serialize_split(value: &Value) -> Result<(serde_json::Value, Option<Meta>)> {
+    let SuperJson { json, meta } = serialize(value)?;
+    Ok((json, meta))
+}
+
+/// Serialize only the subtree of `value` at `path` as a standalone envelope.
+///
+/// Annotations in the result are relative to the subtree, exactly as if it
+/// had been serialized on its own. Paths are resolved by [`Value::pointer`].
+///
+/// # Errors
+/// Returns [`Error::InvalidPath`] if there is no value at `path`.
+///
+/// # Examples
+/// ```
+/// use superjson_rs::{parse, serialize::serialize_at};
+///
+/// let doc = parse(r#"{"json":{"room":{"members":["ada"]}},
+///                     "meta":{"values":{"room.members":["set"]}}}"#).unwrap();
+/// let out = serialize_at(&doc, "room").unwrap();
+/// assert_eq!(serde_json::to_string(&out).unwrap(),
+///            r#"{"json":{"members":["ada"]},"meta":{"values":{"members":["set"]},"v":1}}"#);
+/// ```
+pub fn serialize_at(value: &Value, path: &str) -> Result<SuperJson> {
+    let subtree = value
+        .pointer(path)
+        .ok_or_else(|| Error::InvalidPath(format!("no value at '{path}'")))?;
+    serialize(subtree)
+}
+
+/// Serialize, replacing values at paths matched by `hooks` first and
+/// appending any non-fatal issues to `warnings`.
+pub(crate) fn serialize_detailed(
+    value: &Value,
+    options: &SerializeOptions,
+    hooks: &[Hook],
+    warnings: &mut Vec<Warning>,
+) -> Result<SuperJson> {
+    let superjson = serialize_annotated(value, options, hooks, warnings)?;
+    #[cfg(feature = "hash")]
+    if options.checksum {
+        return Ok(with_checksum(superjson));
+    }
+    Ok(superjson)
+}
 
+/// Record the checksum of `superjson.json` in its `meta`.
+#[cfg(feature = "hash")]
+fn with_checksum(mut superjson: SuperJson) -> SuperJson {
+    let checksum = crate::hash::json_hash(&superjson.json).to_string();
+    superjson
+        .meta
+        .get_or_insert_with(|| Meta {
+            values: None,
+            referential_equalities: None,
+            v: Some(crate::META_VERSION),
+            checksum: None,
+        })
+        .checksum = Some(checksum);
+    superjson
+}
+
+fn serialize_annotated(
+    value: &Value,
+    options: &SerializeOptions,
+    hooks: &[Hook],
+    warnings: &mut Vec<Warning>,
+) -> Result<SuperJson> {
+    // Most payloads hold no extended types at all.
+    if hooks.is_empty()
+        && options.unsafe_integers == UnsafeIntegers::Allow
+        && !options.referential_equalities
+        && !options.dedupe
+        && let Some(json) = serialize_plain(value)
+    {
+        return Ok(SuperJson { json, meta: None });
+    }
+
+    let mut serializer = Serializer {
+        base: String::new(),
+        hooks,
+        options,
+        warnings,
+        identities: (options.referential_equalities || options.dedupe)
+            .then(|| Identities::new(value)),
+    };
+    let (json, annotation) = serialize_value(value, &mut serializer)?;
+    let mut superjson = envelope(json, annotation);
+    let equalities = serializer
+        .identities
+        .and_then(|identities| identities.equalities(options.dedupe));
+    if let Some(equalities) = equalities {
+        superjson
+            .meta
+            .get_or_insert_with(|| Meta {
+                values: None,
+                referential_equalities: None,
+                v: Some(crate::META_VERSION),
+                checksum: None,
+            })
+            .referential_equalities = Some(equalities);
+    }
+    Ok(superjson)
+}
+
+/// The envelope of a serialized root value.
+pub(crate) fn envelope(json: serde_json::Value, annotation: Option<AnnotationResult>) -> SuperJson {
     let meta = annotation.map(|ann| {
         let values = match ann {
             AnnotationResult::Typed(t) => AnnotationValues::Root(t),
@@ -29,160 +269,676 @@ pub fn serialize(value: &Value) -> Result<SuperJson> {
         Meta {
             values: Some(values),
             referential_equalities: None,
-            v: Some(1),
+            v: Some(crate::META_VERSION),
+            checksum: None,
         }
     });
+    SuperJson { json, meta }
+}
 
-    Ok(SuperJson { json, meta })
+/// Stringify `superjson` with `meta` in the shape `options` asks for.
+pub(crate) fn to_text(superjson: &SuperJson, options: &SerializeOptions) -> Result<String> {
+    to_text_in(&SerdeJson, superjson, options)
 }
 
-fn serialize_value(value: &Value) -> Result<(serde_json::Value, Option<AnnotationResult>)> {
-    match value {
-        // Standard JSON types - no annotation needed
-        Value::Null => Ok((serde_json::Value::Null, None)),
-        Value::Bool(b) => Ok((json!(*b), None)),
-        Value::Number(n) => Ok((json!(*n), None)),
-        Value::String(s) => Ok((json!(s), None)),
-
-        Value::Array(arr) => serialize_container_children(arr.iter(), ContainerKind::Array),
-        Value::Object(map) => {
-            let mut json_map = serde_json::Map::new();
-            let mut children = IndexMap::new();
-
-            for (key, val) in map {
-                let (json_val, ann) = serialize_value(val)?;
-                json_map.insert(key.clone(), json_val);
-                collect_child_annotation(&mut children, &crate::path::escape_key(key), ann);
-            }
+pub(crate) fn to_text_in<B: JsonBackend>(
+    backend: &B,
+    superjson: &SuperJson,
+    options: &SerializeOptions,
+) -> Result<String> {
+    let values = superjson
+        .meta
+        .as_ref()
+        .and_then(|meta| meta.values.as_ref());
+    let (MetaFormat::Legacy, Some(values)) = (options.meta_format, values) else {
+        if options.canonical {
+            return Ok(crate::canonical::to_jcs(&serde_json::to_value(superjson)?));
+        }
+        return backend.emit(superjson);
+    };
 
-            let annotation = if children.is_empty() {
-                None
-            } else {
-                Some(AnnotationResult::Children(children))
-            };
-            Ok((serde_json::Value::Object(json_map), annotation))
+    let mut flat = serde_json::Map::new();
+    for (path, annotation) in values.iter() {
+        if let TypeAnnotation::Custom(name)
+        | TypeAnnotation::Class(name, _)
+        | TypeAnnotation::Symbol(name)
+        | TypeAnnotation::Compound(_, name) = annotation
+        {
+            return Err(Error::InvalidTypeAnnotation(format!(
+                "{} type '{name}' at '{path}' cannot be written in the legacy meta format",
+                annotation.type_name()
+            )));
         }
+        flat.insert(path, json!(annotation.type_name()));
+    }
+    let mut meta = json!({ "values": flat });
+    if let Some(equalities) = superjson
+        .meta
+        .as_ref()
+        .and_then(|m| m.referential_equalities.as_ref())
+    {
+        meta["referentialEqualities"] = equalities.clone();
+    }
+    if let Some(checksum) = superjson.meta.as_ref().and_then(|m| m.checksum.as_ref()) {
+        meta["checksum"] = json!(checksum);
+    }
+    let envelope = json!({ "json": superjson.json, "meta": meta });
+    if options.canonical {
+        return Ok(crate::canonical::to_jcs(&envelope));
+    }
+    backend.emit(&envelope)
+}
 
-        // Extended types - require annotation
-        Value::Undefined => Ok((serde_json::Value::Null, Some(leaf("undefined")))),
+/// Serialize `value` as the child at `key` of some container, returning its
+/// JSON and its annotations keyed by path relative to that container.
+pub(crate) fn serialize_child(
+    value: &Value,
+    key: &str,
+) -> Result<(serde_json::Value, IndexMap<String, TypeAnnotation>)> {
+    let mut serializer = Serializer {
+        base: key.to_string(),
+        hooks: &[],
+        options: &SerializeOptions::default(),
+        warnings: &mut Vec::new(),
+        identities: None,
+    };
+    let (json, annotation) = serialize_value(value, &mut serializer)?;
+    let mut annotations = IndexMap::new();
+    collect_child_annotation(&mut annotations, key, annotation);
+    Ok((json, annotations))
+}
 
-        Value::Date(dt) => {
-            let s = dt.to_rfc3339_opts(SecondsFormat::Millis, true);
-            Ok((json!(s), Some(leaf("Date"))))
-        }
+pub(crate) type Serialized = (serde_json::Value, Option<AnnotationResult>);
 
-        Value::BigInt(n) => Ok((json!(n.to_string()), Some(leaf("bigint")))),
+#[cfg(feature = "bigint")]
+fn is_safe_integer(n: i64) -> bool {
+    n.unsigned_abs() <= MAX_SAFE_INTEGER as u64
+}
+
+/// The configuration and diagnostics of one serialization.
+struct Serializer<'s> {
+    /// Escaped path of the value being serialized, from the envelope root.
+    base: String,
+    hooks: &'s [Hook],
+    options: &'s SerializeOptions,
+    warnings: &'s mut Vec<Warning>,
+    /// The containers seen so far, if referential equalities are recorded.
+    identities: Option<Identities>,
+}
 
-        Value::Set(items) => {
-            let (json_val, inner) = serialize_container_inner(items.iter(), ContainerKind::Array)?;
-            let annotation = make_typed_annotation("set", inner);
-            Ok((json_val, Some(annotation)))
+/// The paths of equal containers, for `meta.referentialEqualities`.
+struct Identities {
+    /// The digest of the canonical encoding of every value in the tree
+    /// being serialized, keyed by address.
+    digests: HashMap<*const Value, u128>,
+    /// How many values in the tree have each digest.
+    counts: HashMap<u128, usize>,
+    /// The paths each distinct container found more than once in the tree
+    /// was found at, in order, keyed by its digest.
+    paths: IndexMap<u128, Vec<String>>,
+    /// How many copies of earlier containers enclose the value being
+    /// serialized. JS superjson does not look inside a shared object twice,
+    /// so nothing in them is recorded.
+    within_copy: usize,
+}
+
+impl Identities {
+    /// Digest `value` and everything in it, all at once.
+    fn new(value: &Value) -> Self {
+        let digests = crate::canonical::digests(value);
+        let mut counts = HashMap::new();
+        for digest in digests.values() {
+            *counts.entry(*digest).or_default() += 1;
+        }
+        Identities {
+            digests,
+            counts,
+            paths: IndexMap::new(),
+            within_copy: 0,
         }
+    }
 
-        Value::Map(entries) => {
-            let mut json_arr = Vec::with_capacity(entries.len());
-            let mut inner_children = IndexMap::new();
+    /// The `referentialEqualities` annotation, if any container was found
+    /// more than once.
+    ///
+    /// As in JS superjson, the shortest path represents the others, or the
+    /// first if the others were written as `null`. The root is never among
+    /// them, as no tree is equal to one of its subtrees.
+    fn equalities(self, dedupe: bool) -> Option<serde_json::Value> {
+        let mut equalities = serde_json::Map::new();
+        for mut paths in self.paths.into_values() {
+            if paths.len() < 2 {
+                continue;
+            }
+            if !dedupe {
+                // Stable, so paths of one length stay in document order.
+                paths.sort_by_key(|path| crate::path::parse(path).len());
+            }
+            let representative = paths.remove(0);
+            equalities.insert(representative, json!(paths));
+        }
+        (!equalities.is_empty()).then_some(serde_json::Value::Object(equalities))
+    }
+}
 
-            for (i, (k, v)) in entries.iter().enumerate() {
-                let (json_key, key_ann) = serialize_value(k)?;
-                let (json_val, val_ann) = serialize_value(v)?;
+impl Serializer<'_> {
+    /// The path of the value about to be serialized within the frames on
+    /// `stack`.
+    fn path(&self, stack: &[Frame]) -> String {
+        let keys = stack
+            .iter()
+            .map(|frame| frame.children[frame.json.len()].0.as_str());
+        let mut segments: Vec<&str> = Vec::with_capacity(stack.len() + 1);
+        if !self.base.is_empty() {
+            segments.push(&self.base);
+        }
+        segments.extend(keys);
+        segments.join(".")
+    }
 
-                json_arr.push(json!([json_key, json_val]));
+    /// Serialize `value`, found at `path`, on its own without hooks.
+    fn nested(&mut self, value: &Value, path: String) -> Result<Serialized> {
+        let mut nested = Serializer {
+            base: path,
+            hooks: &[],
+            options: self.options,
+            warnings: self.warnings,
+            identities: None,
+        };
+        serialize_value(value, &mut nested)
+    }
 
-                let i_str = i.to_string();
-                collect_child_annotation(&mut inner_children, &format!("{i_str}.0"), key_ann);
-                collect_child_annotation(&mut inner_children, &format!("{i_str}.1"), val_ann);
-            }
+    /// Record the path of `value`, about to be serialized within the frames
+    /// on `stack`, returning whether it copies a container seen before.
+    fn is_copy(&mut self, value: &Value, stack: &[Frame]) -> bool {
+        let container = matches!(
+            value,
+            Value::Array(_)
+                | Value::Set(_)
+                | Value::Object(_)
+                | Value::Class { .. }
+                | Value::Map(_)
+                | Value::Error { .. }
+        );
+        if !container
+            || self
+                .identities
+                .as_ref()
+                .is_none_or(|ids| ids.within_copy > 0)
+        {
+            return false;
+        }
+        let identities = self.identities.as_ref().expect("identities are recorded");
+        // Paths are only built for containers found more than once.
+        let Some(&digest) = identities.digests.get(&std::ptr::from_ref(value)) else {
+            return false;
+        };
+        if identities.counts[&digest] < 2 {
+            return false;
+        }
+        let path = self.path(stack);
+        let identities = self.identities.as_mut().expect("identities are recorded");
+        let paths = identities.paths.entry(digest).or_default();
+        paths.push(path);
+        paths.len() > 1
+    }
 
-            let annotation = make_typed_annotation("map", inner_children);
-            Ok((serde_json::Value::Array(json_arr), Some(annotation)))
+    fn check_number(&mut self, n: f64, stack: &[Frame]) -> Result<()> {
+        if n.abs() <= MAX_SAFE_INTEGER {
+            return Ok(());
         }
+        match self.options.unsafe_integers {
+            UnsafeIntegers::Allow => {}
+            UnsafeIntegers::Warn => {
+                let path = self.path(stack);
+                self.warnings
+                    .push(Warning::UnsafeInteger { path, value: n });
+            }
+            UnsafeIntegers::Error => {
+                return Err(Error::UnsafeInteger {
+                    path: self.path(stack),
+                    value: n,
+                });
+            }
+        }
+        Ok(())
+    }
+}
 
-        Value::NegZero => Ok((json!("-0"), Some(leaf("number")))),
-        Value::NaN => Ok((json!("NaN"), Some(leaf("number")))),
-        Value::PosInfinity => Ok((json!("Infinity"), Some(leaf("number")))),
-        Value::NegInfinity => Ok((json!("-Infinity"), Some(leaf("number")))),
+/// The outcome of starting to serialize a single value.
+enum Step<'a> {
+    /// The value was serialized without visiting any children.
+    Done(Serialized),
+    /// The value is a container whose children must be serialized first.
+    Descend(Frame<'a>),
+}
 
-        Value::RegExp { source, flags } => {
-            Ok((json!(format!("/{source}/{flags}")), Some(leaf("regexp"))))
+/// A container whose children are being serialized.
+struct Frame<'a> {
+    value: &'a Value,
+    /// Children in output order, with the path key each one is annotated under.
+    children: Vec<(String, &'a Value)>,
+    json: Vec<serde_json::Value>,
+    annotations: IndexMap<String, TypeAnnotation>,
+    /// Whether the container copies one serialized earlier.
+    copy: bool,
+}
+
+/// Serialize a value tree using an explicit work stack, so that nesting depth
+/// is limited by available memory rather than by the thread's call stack.
+fn serialize_value(value: &Value, serializer: &mut Serializer) -> Result<Serialized> {
+    let mut stack: Vec<Frame> = Vec::new();
+    let mut step = begin_hooked(value, &stack, serializer)?;
+
+    loop {
+        match step {
+            Step::Descend(frame) => {
+                if let Some(identities) = serializer.identities.as_mut() {
+                    identities.within_copy += usize::from(frame.copy);
+                }
+                stack.push(frame);
+            }
+            Step::Done((json, ann)) => match stack.last_mut() {
+                None => return Ok((json, ann)),
+                Some(parent) => {
+                    let key = &parent.children[parent.json.len()].0;
+                    collect_child_annotation(&mut parent.annotations, key, ann);
+                    parent.json.push(json);
+                }
+            },
         }
 
-        Value::Url(s) => Ok((json!(s), Some(leaf("URL")))),
+        let frame = stack.last().expect("stack is non-empty after a push");
+        step = match frame.children.get(frame.json.len()) {
+            Some(&(_, child)) => begin_hooked(child, &stack, serializer)?,
+            None => {
+                let frame = stack.pop().expect("stack is non-empty");
+                if let Some(identities) = serializer.identities.as_mut() {
+                    identities.within_copy -= usize::from(frame.copy);
+                }
+                Step::Done(frame.finish(serializer.options))
+            }
+        };
+    }
+}
 
-        Value::Error {
-            name,
-            message,
-            cause,
-        } => {
-            let mut json_map = serde_json::Map::new();
-            json_map.insert("name".to_string(), json!(name));
-            json_map.insert("message".to_string(), json!(message));
+/// A plain container whose children are being serialized by
+/// [`serialize_plain`].
+enum PlainFrame<'a> {
+    Array {
+        items: std::slice::Iter<'a, Value>,
+        json: Vec<serde_json::Value>,
+    },
+    Object {
+        fields: std::vec::IntoIter<(&'a String, &'a Value)>,
+        /// The key of the field being serialized.
+        key: Option<&'a String>,
+        json: serde_json::Map<String, serde_json::Value>,
+    },
+}
 
-            let mut inner_children = IndexMap::new();
+impl<'a> PlainFrame<'a> {
+    fn next_child(&mut self) -> Option<&'a Value> {
+        match self {
+            PlainFrame::Array { items, .. } => items.next(),
+            PlainFrame::Object { fields, key, .. } => fields.next().map(|(k, v)| {
+                *key = Some(k);
+                v
+            }),
+        }
+    }
 
-            if let Some(cause_val) = cause {
-                let (cause_json, cause_ann) = serialize_value(cause_val)?;
-                json_map.insert("cause".to_string(), cause_json);
-                collect_child_annotation(&mut inner_children, "cause", cause_ann);
+    fn push(&mut self, value: serde_json::Value) {
+        match self {
+            PlainFrame::Array { json, .. } => json.push(value),
+            PlainFrame::Object { key, json, .. } => {
+                let key = key.take().expect("a child was started");
+                json.insert(key.clone(), value);
             }
+        }
+    }
 
-            let annotation = make_typed_annotation("Error", inner_children);
-            Ok((serde_json::Value::Object(json_map), Some(annotation)))
+    fn finish(self) -> serde_json::Value {
+        match self {
+            PlainFrame::Array { json, .. } => serde_json::Value::Array(json),
+            PlainFrame::Object { json, .. } => serde_json::Value::Object(json),
         }
     }
 }
 
-#[derive(Clone, Copy)]
-enum ContainerKind {
-    Array,
+/// Serialize a value made only of JSON types, skipping all annotation
+/// bookkeeping.
+///
+/// Returns `None` as soon as an extended type is found, leaving the value to
+/// [`serialize_value`].
+fn serialize_plain(value: &Value) -> Option<serde_json::Value> {
+    let mut stack: Vec<PlainFrame> = Vec::new();
+    let mut next = Some(value);
+
+    loop {
+        let mut done = match next.take() {
+            None => None,
+            Some(Value::Null) => Some(serde_json::Value::Null),
+            Some(Value::Bool(b)) => Some(json!(*b)),
+            Some(Value::Number(n)) => Some(json!(*n)),
+            Some(Value::Int(n)) => Some(json!(*n)),
+            Some(Value::UInt(n)) => Some(json!(*n)),
+            Some(Value::String(s)) => Some(json!(s)),
+            Some(Value::Array(items)) => {
+                stack.push(PlainFrame::Array {
+                    items: items.iter(),
+                    json: Vec::with_capacity(items.len()),
+                });
+                None
+            }
+            Some(Value::Object(map)) => {
+                stack.push(PlainFrame::Object {
+                    fields: map.iter().collect::<Vec<_>>().into_iter(),
+                    key: None,
+                    json: serde_json::Map::new(),
+                });
+                None
+            }
+            Some(_) => return None,
+        };
+
+        // Hand finished values to their parents until a child is left to
+        // start.
+        while next.is_none() {
+            let Some(frame) = stack.last_mut() else {
+                return done;
+            };
+            if let Some(json) = done.take() {
+                frame.push(json);
+            }
+            match frame.next_child() {
+                Some(child) => next = Some(child),
+                None => done = stack.pop().map(PlainFrame::finish),
+            }
+        }
+    }
 }
 
-/// Serialize children of a plain container (Array) and collect annotations.
-/// Returns the JSON value and an optional AnnotationResult::Children.
-fn serialize_container_children<'a>(
-    items: impl Iterator<Item = &'a Value>,
-    _kind: ContainerKind,
-) -> Result<(serde_json::Value, Option<AnnotationResult>)> {
-    let mut json_arr = Vec::new();
-    let mut children = IndexMap::new();
+/// Like [`begin`], but if a hook matches the value's path, serialize the
+/// hook's output in its place.
+///
+/// Hooks are not applied inside that output, so this recurses at most once.
+fn begin_hooked<'a>(
+    value: &'a Value,
+    stack: &[Frame],
+    serializer: &mut Serializer,
+) -> Result<Step<'a>> {
+    if serializer.hooks.is_empty() {
+        return begin(value, stack, serializer);
+    }
 
-    for (i, item) in items.enumerate() {
-        let (json_val, ann) = serialize_value(item)?;
-        json_arr.push(json_val);
-        collect_child_annotation(&mut children, &i.to_string(), ann);
+    let path = serializer.path(stack);
+    match apply_hooks(serializer.hooks, &path, value) {
+        Some(replacement) => serializer.nested(&replacement, path).map(Step::Done),
+        None => begin(value, stack, serializer),
     }
+}
 
-    let annotation = if children.is_empty() {
-        None
-    } else {
-        Some(AnnotationResult::Children(children))
+/// Serialize a scalar directly, or open a frame for a container.
+fn begin<'a>(value: &'a Value, stack: &[Frame], serializer: &mut Serializer) -> Result<Step<'a>> {
+    let done = |json, ann| Step::Done((json, ann));
+    let copy = serializer.is_copy(value, stack);
+    if copy && serializer.options.dedupe {
+        return Ok(done(serde_json::Value::Null, None));
+    }
+
+    let mut step = match value {
+        // Standard JSON types - no annotation needed
+        Value::Null => done(serde_json::Value::Null, None),
+        Value::Bool(b) => done(json!(*b), None),
+        Value::Number(n) => {
+            serializer.check_number(*n, stack)?;
+            done(json!(*n), None)
+        }
+        Value::Int(n) => {
+            serializer.check_number(*n as f64, stack)?;
+            done(json!(*n), None)
+        }
+        Value::UInt(n) => {
+            serializer.check_number(*n as f64, stack)?;
+            done(json!(*n), None)
+        }
+        Value::String(s) => done(json!(s), None),
+
+        Value::Array(items) | Value::Set(items) => Step::Descend(Frame::new(
+            value,
+            items
+                .iter()
+                .enumerate()
+                .map(|(i, item)| (i.to_string(), item))
+                .collect(),
+        )),
+        Value::Object(map) | Value::Class { fields: map, .. } => Step::Descend(Frame::new(
+            value,
+            map.iter()
+                .map(|(key, val)| (crate::path::escape_key(key), val))
+                .collect(),
+        )),
+        Value::Map(entries) => {
+            let mut children = Vec::with_capacity(entries.len() * 2);
+            for (i, (k, v)) in entries.iter().enumerate() {
+                children.push((format!("{i}.0"), k));
+                children.push((format!("{i}.1"), v));
+            }
+            Step::Descend(Frame::new(value, children))
+        }
+        Value::Error { cause, extra, .. } => Step::Descend(Frame::new(
+            value,
+            cause
+                .iter()
+                .map(|c| ("cause".to_string(), c.as_ref()))
+                .chain(
+                    extra
+                        .iter()
+                        .filter(|(key, _)| is_error_prop(&serializer.options.error_props, key))
+                        .map(|(key, val)| (crate::path::escape_key(key), val)),
+                )
+                .collect(),
+        )),
+
+        // Extended types - require annotation
+        Value::Undefined => done(serde_json::Value::Null, Some(leaf("undefined"))),
+
+        #[cfg(feature = "chrono")]
+        Value::Date(dt) => {
+            let s = dt.to_rfc3339_opts(SecondsFormat::Millis, true);
+            done(json!(s), Some(leaf("Date")))
+        }
+        #[cfg(feature = "chrono")]
+        Value::OffsetDate(dt) => {
+            let s = dt.to_rfc3339_opts(SecondsFormat::Millis, true);
+            done(json!(s), Some(leaf("Date")))
+        }
+        #[cfg(feature = "chrono")]
+        Value::InvalidDate => done(json!(crate::value::INVALID_DATE), Some(leaf("Date"))),
+
+        #[cfg(feature = "bigint")]
+        Value::BigInt(n) => match i64::try_from(n) {
+            Ok(small) if serializer.options.downcast_safe_bigints && is_safe_integer(small) => {
+                done(json!(small), None)
+            }
+            _ => done(json!(n.to_string()), Some(leaf("bigint"))),
+        },
+
+        Value::NegZero => done(json!("-0"), Some(leaf("number"))),
+        Value::NaN => done(json!("NaN"), Some(leaf("number"))),
+        Value::PosInfinity => done(json!("Infinity"), Some(leaf("number"))),
+        Value::NegInfinity => done(json!("-Infinity"), Some(leaf("number"))),
+
+        Value::RegExp { source, flags } => {
+            done(json!(format!("/{source}/{flags}")), Some(leaf("regexp")))
+        }
+
+        Value::Url(url) => done(json!(url.as_str()), Some(leaf("URL"))),
+
+        Value::Unknown { type_name, raw } => done(raw.clone(), Some(leaf(type_name))),
+
+        Value::Bytes(bytes) => done(
+            json!(serializer.options.bytes_encoding.encode(bytes)),
+            Some(AnnotationResult::Typed(TypeAnnotation::Custom(
+                serializer
+                    .options
+                    .bytes_name
+                    .as_deref()
+                    .unwrap_or(BYTES)
+                    .to_string(),
+            ))),
+        ),
+
+        // JS superjson writes the symbol's description, which it ignores
+        // when parsing; registered symbols are described by their
+        // identifier unless given another.
+        Value::Symbol(id) => done(
+            json!(id),
+            Some(AnnotationResult::Typed(TypeAnnotation::Symbol(id.clone()))),
+        ),
+
+        Value::Custom { name, value } => {
+            // Custom payloads are opaque to superjson and cannot carry
+            // annotations of their own, so this recurses at most once.
+            let (json, annotation) = serializer.nested(value, serializer.path(stack))?;
+            if annotation.is_some() {
+                return Err(Error::TypeMismatch {
+                    path: String::new(),
+                    expected: format!("plain JSON payload for custom type '{name}'"),
+                    actual: value.to_string(),
+                });
+            }
+            done(
+                json,
+                Some(AnnotationResult::Typed(TypeAnnotation::Custom(
+                    name.clone(),
+                ))),
+            )
+        }
     };
-    Ok((serde_json::Value::Array(json_arr), annotation))
+    if let Step::Descend(frame) = &mut step {
+        frame.copy = copy;
+    }
+    Ok(step)
 }
 
-/// Serialize children of an extended container (Set) and collect inner annotations.
-/// Returns the JSON value and the inner children map (for use in TypeAnnotation::Node).
-fn serialize_container_inner<'a>(
-    items: impl Iterator<Item = &'a Value>,
-    _kind: ContainerKind,
-) -> Result<(serde_json::Value, IndexMap<String, TypeAnnotation>)> {
-    let mut json_arr = Vec::new();
-    let mut inner_children = IndexMap::new();
+impl<'a> Frame<'a> {
+    fn new(value: &'a Value, children: Vec<(String, &'a Value)>) -> Self {
+        Frame {
+            value,
+            json: Vec::with_capacity(children.len()),
+            children,
+            annotations: IndexMap::new(),
+            copy: false,
+        }
+    }
+
+    /// Assemble the container once all of its children have been serialized.
+    fn finish(self, options: &SerializeOptions) -> Serialized {
+        let Frame {
+            value,
+            json,
+            annotations,
+            ..
+        } = self;
 
-    for (i, item) in items.enumerate() {
-        let (json_val, ann) = serialize_value(item)?;
-        json_arr.push(json_val);
-        collect_child_annotation(&mut inner_children, &i.to_string(), ann);
+        match value {
+            Value::Array(_) => (
+                serde_json::Value::Array(json),
+                children_annotation(annotations),
+            ),
+            Value::Object(map) => {
+                let json_map = map.keys().cloned().zip(json).collect();
+                (
+                    serde_json::Value::Object(json_map),
+                    children_annotation(annotations),
+                )
+            }
+            Value::Class { name, fields } => {
+                let json_map = fields.keys().cloned().zip(json).collect();
+                (
+                    serde_json::Value::Object(json_map),
+                    Some(AnnotationResult::Typed(TypeAnnotation::Class(
+                        name.clone(),
+                        annotations,
+                    ))),
+                )
+            }
+            Value::Set(_) => (
+                serde_json::Value::Array(json),
+                Some(make_typed_annotation("set", annotations)),
+            ),
+            Value::Map(_) => {
+                let mut pairs = Vec::with_capacity(json.len() / 2);
+                let mut iter = json.into_iter();
+                while let (Some(k), Some(v)) = (iter.next(), iter.next()) {
+                    pairs.push(json!([k, v]));
+                }
+                (
+                    serde_json::Value::Array(pairs),
+                    Some(make_typed_annotation("map", annotations)),
+                )
+            }
+            Value::Error {
+                name,
+                message,
+                stack,
+                cause,
+                extra,
+            } => {
+                let mut json = json.into_iter();
+                let mut json_map = serde_json::Map::new();
+                json_map.insert("name".to_string(), json!(name));
+                json_map.insert("message".to_string(), json!(message));
+                if let Some(stack) = stack
+                    && keeps_error_stack(&options.error_props)
+                {
+                    json_map.insert("stack".to_string(), json!(stack));
+                }
+                if cause.is_some() {
+                    json_map.extend(json.next().map(|json| ("cause".to_string(), json)));
+                }
+                let keys = extra
+                    .keys()
+                    .filter(|key| is_error_prop(&options.error_props, key));
+                json_map.extend(keys.cloned().zip(json));
+                (
+                    serde_json::Value::Object(json_map),
+                    Some(make_typed_annotation("Error", annotations)),
+                )
+            }
+            _ => unreachable!("only containers open a frame"),
+        }
     }
+}
+
+/// Whether `key` is one of the allowed extra `props` of an Error; `name`,
+/// `message`, `stack` and `cause` never are.
+pub(crate) fn is_error_prop(props: &[String], key: &str) -> bool {
+    !matches!(key, "name" | "message" | "stack" | "cause") && props.iter().any(|prop| prop == key)
+}
+
+/// Whether the `stack` of an Error is among the allowed `props`.
+pub(crate) fn keeps_error_stack(props: &[String]) -> bool {
+    props.iter().any(|prop| prop == "stack")
+}
 
-    Ok((serde_json::Value::Array(json_arr), inner_children))
+pub(crate) fn children_annotation(
+    children: IndexMap<String, TypeAnnotation>,
+) -> Option<AnnotationResult> {
+    if children.is_empty() {
+        None
+    } else {
+        Some(AnnotationResult::Children(children))
+    }
 }
 
 /// Collect a child's annotation into a parent's children map.
 ///
 /// - If the child is a `Typed` annotation, insert it directly at `key`.
 /// - If the child has `Children`, flatten them by prepending `key.` to each path.
-fn collect_child_annotation(
+pub(crate) fn collect_child_annotation(
     children: &mut IndexMap<String, TypeAnnotation>,
     key: &str,
     annotation: Option<AnnotationResult>,
@@ -200,11 +956,11 @@ fn collect_child_annotation(
     }
 }
 
-fn leaf(type_name: &str) -> AnnotationResult {
+pub(crate) fn leaf(type_name: &str) -> AnnotationResult {
     AnnotationResult::Typed(TypeAnnotation::Leaf(type_name.to_string()))
 }
 
-fn make_typed_annotation(
+pub(crate) fn make_typed_annotation(
     type_name: &str,
     inner: IndexMap<String, TypeAnnotation>,
 ) -> AnnotationResult {
@@ -218,7 +974,10 @@ fn make_typed_annotation(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::value::Map;
+    #[cfg(feature = "chrono")]
     use chrono::TimeZone;
+    #[cfg(feature = "bigint")]
     use num_bigint::BigInt;
 
     #[test]
@@ -249,6 +1008,7 @@ mod tests {
         assert!(result.meta.is_none());
     }
 
+    #[cfg(feature = "chrono")]
     #[test]
     fn test_serialize_date() {
         let dt = chrono::Utc.timestamp_millis_opt(0).unwrap();
@@ -262,6 +1022,7 @@ mod tests {
         );
     }
 
+    #[cfg(feature = "bigint")]
     #[test]
     fn test_serialize_bigint() {
         let result = serialize(&Value::BigInt(BigInt::from(42))).unwrap();
@@ -359,9 +1120,10 @@ mod tests {
         );
     }
 
+    #[cfg(feature = "chrono")]
     #[test]
     fn test_serialize_nested_object_with_date() {
-        let mut obj = IndexMap::new();
+        let mut obj = Map::new();
         let dt = chrono::Utc.timestamp_millis_opt(0).unwrap();
         obj.insert("created".to_string(), Value::Date(dt));
         obj.insert("name".to_string(), Value::String("test".into()));
@@ -379,12 +1141,13 @@ mod tests {
         );
     }
 
+    #[cfg(feature = "chrono")]
     #[test]
     fn test_serialize_deeply_nested_date() {
-        let mut inner = IndexMap::new();
+        let mut inner = Map::new();
         let dt = chrono::Utc.timestamp_millis_opt(0).unwrap();
         inner.insert("date".to_string(), Value::Date(dt));
-        let mut outer = IndexMap::new();
+        let mut outer = Map::new();
         outer.insert("meeting".to_string(), Value::Object(inner));
 
         let result = serialize(&Value::Object(outer)).unwrap();
@@ -399,6 +1162,7 @@ mod tests {
         );
     }
 
+    #[cfg(all(feature = "chrono", feature = "bigint"))]
     #[test]
     fn test_serialize_array_with_mixed_types() {
         let arr = Value::Array(vec![
@@ -420,7 +1184,7 @@ mod tests {
 
     #[test]
     fn test_serialize_object_with_set_containing_extended() {
-        let mut obj = IndexMap::new();
+        let mut obj = Map::new();
         obj.insert(
             "a".to_string(),
             Value::Set(vec![
@@ -442,4 +1206,385 @@ mod tests {
             AnnotationValues::Children(expected)
         );
     }
+
+    #[test]
+    fn test_serialize_at_reroots_annotations() {
+        let value = Value::Map(vec![(
+            Value::from("k"),
+            Value::Array(vec![Value::Null, Value::Set(vec![Value::Undefined])]),
+        )]);
+        let out = serialize_at(&value, "0.1").unwrap();
+        assert_eq!(out.json, json!([null, [null]]));
+        assert_eq!(
+            serde_json::to_value(out.meta.unwrap().values.unwrap()).unwrap(),
+            json!({"1": ["set", {"0": ["undefined"]}]})
+        );
+    }
+
+    #[test]
+    fn test_serialize_at_missing_path() {
+        let value = Value::Array(vec![Value::Null]);
+        assert!(matches!(
+            serialize_at(&value, "1"),
+            Err(Error::InvalidPath(_))
+        ));
+        assert!(matches!(
+            serialize_at(&value, "0.a"),
+            Err(Error::InvalidPath(_))
+        ));
+        assert_eq!(serialize_at(&value, "").unwrap().json, json!([null]));
+    }
+
+    #[test]
+    fn test_serialize_custom() {
+        let value = Value::Array(vec![Value::Custom {
+            name: "money".into(),
+            value: Box::new(Value::Array(vec![Value::from(150), Value::from("EUR")])),
+        }]);
+        let out = serialize(&value).unwrap();
+        assert_eq!(out.json, json!([[150, "EUR"]]));
+        assert_eq!(
+            serde_json::to_value(out.meta.unwrap().values.unwrap()).unwrap(),
+            json!({"0": [["custom", "money"]]})
+        );
+    }
+
+    #[test]
+    fn test_serialize_custom_rejects_extended_payload() {
+        let value = Value::Custom {
+            name: "money".into(),
+            value: Box::new(Value::NaN),
+        };
+        assert!(matches!(
+            serialize(&value),
+            Err(crate::Error::TypeMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn test_serialize_class() {
+        let mut fields = Map::new();
+        fields.insert("x".into(), Value::from(1));
+        fields.insert("y".into(), Value::NaN);
+        let value = Value::Array(vec![
+            Value::Class {
+                name: "Point".into(),
+                fields: fields.clone(),
+            },
+            Value::Class {
+                name: "Empty".into(),
+                fields: Map::new(),
+            },
+        ]);
+        let out = serialize(&value).unwrap();
+        assert_eq!(out.json, json!([{"x": 1, "y": "NaN"}, {}]));
+        assert_eq!(
+            serde_json::to_value(out.meta.unwrap().values.unwrap()).unwrap(),
+            json!({"0": [["class", "Point"], {"y": ["number"]}], "1": [["class", "Empty"]]})
+        );
+
+        let options = SerializeOptions {
+            meta_format: MetaFormat::Legacy,
+            ..SerializeOptions::default()
+        };
+        let value = Value::Class {
+            name: "Point".into(),
+            fields,
+        };
+        assert!(matches!(
+            crate::stringify_with(&value, &options),
+            Err(crate::Error::InvalidTypeAnnotation(_))
+        ));
+    }
+
+    #[test]
+    fn test_serialize_symbol() {
+        let value = Value::Set(vec![Value::Symbol("admin".into())]);
+        let out = serialize(&value).unwrap();
+        assert_eq!(out.json, json!(["admin"]));
+        assert_eq!(
+            serde_json::to_value(out.meta.unwrap().values.unwrap()).unwrap(),
+            json!(["set", {"0": [["symbol", "admin"]]}])
+        );
+    }
+
+    #[test]
+    fn test_serialize_bytes() {
+        let value = Value::Array(vec![Value::Bytes(vec![0xde, 0xad])]);
+        let out = serialize(&value).unwrap();
+        assert_eq!(out.json, json!(["3q0="]));
+        assert_eq!(
+            serde_json::to_value(out.meta.unwrap().values.unwrap()).unwrap(),
+            json!({"0": [["custom", "bytes"]]})
+        );
+
+        let options = SerializeOptions {
+            bytes_encoding: BytesEncoding::Hex,
+            ..SerializeOptions::default()
+        };
+        let out = serialize_with(&value, &options).unwrap();
+        assert_eq!(out.json, json!(["dead"]));
+    }
+
+    #[test]
+    fn test_referential_equalities() {
+        let mut shared = Map::new();
+        shared.insert("x".into(), Value::Set(vec![Value::from(1)]));
+        let shared = Value::Object(shared);
+        let mut deep = Map::new();
+        deep.insert("d".into(), shared.clone());
+        let mut map = Map::new();
+        map.insert("deep".into(), Value::Object(deep));
+        map.insert("list".into(), Value::Array(vec![shared.clone()]));
+        map.insert("top".into(), shared);
+        let value = Value::Object(map);
+
+        let options = SerializeOptions {
+            referential_equalities: true,
+            ..SerializeOptions::default()
+        };
+        let out = serialize_with(&value, &options).unwrap();
+        assert_eq!(out.json, serialize(&value).unwrap().json);
+        let meta = out.meta.unwrap();
+        assert_eq!(
+            meta.referential_equalities,
+            Some(json!({"top": ["deep.d", "list.0"]}))
+        );
+        assert_eq!(
+            serde_json::to_value(meta.values.unwrap()).unwrap(),
+            json!({"deep.d.x": ["set"], "list.0.x": ["set"], "top.x": ["set"]})
+        );
+
+        let plain = Value::Array(vec![Value::from(vec![1.0]), Value::from(vec![1.0])]);
+        let out = serialize_with(&plain, &options).unwrap();
+        assert_eq!(
+            out.meta.unwrap().referential_equalities,
+            Some(json!({"0": ["1"]}))
+        );
+        assert!(serialize(&plain).unwrap().meta.is_none());
+    }
+
+    #[test]
+    fn test_dedupe() {
+        let shared = Value::Set(vec![Value::from(vec![1.0])]);
+        let mut map = Map::new();
+        map.insert("deep".into(), Value::Array(vec![shared.clone()]));
+        map.insert("top".into(), shared);
+        let value = Value::Object(map);
+
+        let options = SerializeOptions {
+            dedupe: true,
+            ..SerializeOptions::default()
+        };
+        let out = serialize_with(&value, &options).unwrap();
+        assert_eq!(out.json, json!({"deep": [[[1.0]]], "top": null}));
+        let meta = out.meta.as_ref().unwrap();
+        assert_eq!(
+            meta.referential_equalities,
+            Some(json!({"deep.0": ["top"]}))
+        );
+        assert_eq!(
+            serde_json::to_value(meta.values.as_ref().unwrap()).unwrap(),
+            json!({"deep.0": ["set"]})
+        );
+        assert_eq!(crate::deserialize::deserialize(&out).unwrap(), value);
+    }
+
+    #[test]
+    fn test_referential_equalities_deep() {
+        // Each container used to be encoded in full to look it up, which
+        // took seconds at this depth.
+        let depth = 8_000;
+        let mut value = Value::Array(vec![Value::from(vec![1.0]), Value::from(vec![1.0])]);
+        for _ in 0..depth {
+            value = Value::Array(vec![value]);
+        }
+        let inner = vec!["0"; depth].join(".");
+        for dedupe in [false, true] {
+            let options = SerializeOptions {
+                referential_equalities: !dedupe,
+                dedupe,
+                ..SerializeOptions::default()
+            };
+            let started = std::time::Instant::now();
+            let out = serialize_with(&value, &options).unwrap();
+            assert!(started.elapsed() < std::time::Duration::from_secs(2));
+            assert_eq!(
+                out.meta.unwrap().referential_equalities,
+                Some(json!({format!("{inner}.0"): [format!("{inner}.1")]}))
+            );
+        }
+        // Dropped without recursion, which would overflow the stack.
+        crate::pool::ValuePool::with_limit(0).recycle(value);
+    }
+
+    #[test]
+    fn test_canonical_text() {
+        let mut map = Map::new();
+        map.insert("z".into(), Value::Number(1e21));
+        map.insert("a".into(), Value::NaN);
+        let value = Value::Object(map);
+
+        for meta_format in [MetaFormat::Tree, MetaFormat::Legacy] {
+            let options = SerializeOptions {
+                canonical: true,
+                meta_format,
+                ..SerializeOptions::default()
+            };
+            let text = crate::stringify_with(&value, &options).unwrap();
+            let expected = match meta_format {
+                MetaFormat::Tree => {
+                    r#"{"json":{"a":"NaN","z":1e+21},"meta":{"v":1,"values":{"a":["number"]}}}"#
+                }
+                MetaFormat::Legacy => {
+                    r#"{"json":{"a":"NaN","z":1e+21},"meta":{"values":{"a":"number"}}}"#
+                }
+            };
+            assert_eq!(text, expected);
+            assert_eq!(crate::parse(&text).unwrap(), value);
+        }
+        let superjson = serialize(&value).unwrap();
+        assert_eq!(
+            superjson.canonical_bytes(),
+            crate::stringify_with(
+                &value,
+                &SerializeOptions {
+                    canonical: true,
+                    ..SerializeOptions::default()
+                }
+            )
+            .unwrap()
+            .into_bytes()
+        );
+    }
+
+    fn unsafe_integers(mode: UnsafeIntegers) -> SerializeOptions {
+        SerializeOptions {
+            unsafe_integers: mode,
+            ..SerializeOptions::default()
+        }
+    }
+
+    #[test]
+    fn test_unsafe_integer_warnings() {
+        let big = 2f64.powi(53) + 2.0;
+        let mut inner = Map::new();
+        inner.insert("a.b".into(), Value::Number(-big));
+        inner.insert("ok".into(), Value::Number(MAX_SAFE_INTEGER));
+        let value = Value::Array(vec![
+            Value::Object(inner),
+            Value::Custom {
+                name: "id".into(),
+                value: Box::new(Value::Number(big)),
+            },
+        ]);
+
+        let mut warnings = Vec::new();
+        let options = unsafe_integers(UnsafeIntegers::Warn);
+        serialize_detailed(&value, &options, &[], &mut warnings).unwrap();
+        let paths: Vec<_> = warnings
+            .iter()
+            .map(|w| match w {
+                Warning::UnsafeInteger { path, .. } => path.as_str(),
+                other => panic!("unexpected warning {other}"),
+            })
+            .collect();
+        assert_eq!(paths, ["0.a\\.b", "1"]);
+
+        assert!(serialize(&value).is_ok());
+        assert!(matches!(
+            serialize_with(&value, &unsafe_integers(UnsafeIntegers::Error)),
+            Err(Error::UnsafeInteger { ref path, .. }) if path == "0.a\\.b"
+        ));
+    }
+
+    #[cfg(feature = "bigint")]
+    #[test]
+    fn test_downcast_safe_bigints() {
+        let options = SerializeOptions {
+            downcast_safe_bigints: true,
+            ..SerializeOptions::default()
+        };
+        let value = Value::Array(vec![
+            Value::BigInt(BigInt::from(-9_007_199_254_740_991i64)),
+            Value::BigInt(BigInt::from(9_007_199_254_740_992i64)),
+            Value::BigInt(BigInt::from(u64::MAX)),
+        ]);
+        let out = serialize_with(&value, &options).unwrap();
+        assert_eq!(
+            out.json,
+            json!([
+                -9_007_199_254_740_991i64,
+                "9007199254740992",
+                "18446744073709551615"
+            ])
+        );
+        assert_eq!(
+            serde_json::to_value(out.meta.unwrap().values.unwrap()).unwrap(),
+            json!({"1": ["bigint"], "2": ["bigint"]})
+        );
+
+        let exact = serialize(&value).unwrap();
+        assert_eq!(exact.json[0], json!("-9007199254740991"));
+    }
+
+    #[test]
+    fn test_plain_fast_path_matches_full_serialization() {
+        let mut map = Map::new();
+        map.insert(
+            "a.b".into(),
+            Value::Array(vec![Value::from(1), Value::Null]),
+        );
+        map.insert("c".into(), Value::Object(Map::new()));
+        let value = Value::Object(map);
+        let mut serializer = Serializer {
+            base: String::new(),
+            hooks: &[],
+            options: &SerializeOptions::default(),
+            warnings: &mut Vec::new(),
+            identities: None,
+        };
+        let (json, annotation) = serialize_value(&value, &mut serializer).unwrap();
+        assert!(annotation.is_none());
+        assert_eq!(serialize_plain(&value), Some(json));
+
+        let extended = Value::Array(vec![Value::from(1), Value::Set(vec![])]);
+        assert_eq!(serialize_plain(&extended), None);
+        assert!(serialize(&extended).unwrap().meta.is_some());
+    }
+
+    #[cfg(feature = "hash")]
+    #[test]
+    fn test_checksum_is_verified_on_parse() {
+        let options = SerializeOptions {
+            checksum: true,
+            ..SerializeOptions::default()
+        };
+        let plain = serialize_with(&Value::from(vec![1.5, 2.0]), &options).unwrap();
+        let meta = plain.meta.as_ref().unwrap();
+        assert!(meta.values.is_none());
+        assert_eq!(meta.checksum.as_ref().unwrap().len(), 64);
+
+        let mut map = Map::new();
+        map.insert("b".into(), Value::Set(vec![Value::from(1)]));
+        map.insert("a".into(), Value::from("x"));
+        let value = Value::Object(map);
+        let text = crate::stringify_with(&value, &options).unwrap();
+        assert_eq!(crate::parse(&text).unwrap(), value);
+
+        // Reordering keys and respelling numbers keep the checksum valid.
+        let respelled = text.replace(r#""b":[1]"#, r#""b":[1.0]"#);
+        let reordered = respelled.replace(r#"{"a":"x","b":[1.0]}"#, r#"{"b":[1.0],"a":"x"}"#);
+        assert!(reordered.starts_with(r#"{"json":{"b":[1.0],"a":"x"}"#));
+        assert_eq!(
+            crate::parse(&reordered).unwrap().content_hash(),
+            value.content_hash()
+        );
+
+        let tampered = text.replace(r#""x""#, r#""y""#);
+        assert!(matches!(
+            crate::parse(&tampered),
+            Err(Error::ChecksumMismatch { .. })
+        ));
+    }
 }
@@ -0,0 +1,122 @@
+//! Envelopes signed with HMAC-SHA256.
+//!
+//! [`stringify_signed`] adds a top-level `signature` field to the envelope:
+//! the HMAC-SHA256, as lowercase hex, of the canonical form of the rest of
+//! it. [`parse_verified`] checks the signature before restoring anything, so
+//! a payload that went through storage the client controls, such as a
+//! cookie or `localStorage`, can be trusted again on return.
+//!
+//! The canonical form is the one [`Value::content_hash`] digests, applied to
+//! the envelope as plain JSON. Re-encoding that only reorders keys or
+//! respells numbers keeps the signature valid.
+//!
+//! Signing proves who wrote the payload, not when: a client can always send
+//! back an older payload it was given. Put an expiry in the value if that
+//! matters.
+//!
+//! # Examples
+//! ```
+//! use superjson_rs::{Error, Value, parse_verified, stringify_signed};
+//!
+//! let key = b"server secret";
+//! let value = Value::Set(vec![Value::from("admin")]);
+//! let text = stringify_signed(&value, key).unwrap();
+//! assert_eq!(parse_verified(&text, key).unwrap(), value);
+//!
+//! let forged = text.replace("admin", "owner");
+//! assert!(matches!(parse_verified(&forged, key), Err(Error::InvalidSignature)));
+//! ```
+
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use crate::bytes::{hex, unhex};
+use crate::{Error, ParseOptions, Result, Value, canonical, deserialize, envelope, serialize};
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// The top-level envelope field holding the signature.
+const SIGNATURE_FIELD: &str = "signature";
+
+/// Serialize `value` and sign the envelope with `key`.
+pub fn stringify_signed(value: &Value, key: &[u8]) -> Result<String> {
+    let mut envelope = serde_json::to_value(serialize::serialize(value)?)?;
+    let signature = hex(&mac(&envelope, key).finalize().into_bytes());
+    if let serde_json::Value::Object(fields) = &mut envelope {
+        fields.insert(SIGNATURE_FIELD.to_string(), signature.into());
+    }
+    Ok(serde_json::to_string(&envelope)?)
+}
+
+/// Check the signature of an envelope written by [`stringify_signed`] with
+/// `key`, then restore its value.
+///
+/// # Errors
+/// Returns [`Error::InvalidSignature`] if the signature is missing or does
+/// not match, before any annotation is interpreted.
+pub fn parse_verified(s: &str, key: &[u8]) -> Result<Value> {
+    let mut envelope: serde_json::Value = serde_json::from_str(s)?;
+    let signature = envelope
+        .as_object_mut()
+        .and_then(|fields| fields.remove(SIGNATURE_FIELD))
+        .and_then(|signature| signature.as_str().and_then(unhex))
+        .ok_or(Error::InvalidSignature)?;
+    mac(&envelope, key)
+        .verify_slice(&signature)
+        .map_err(|_| Error::InvalidSignature)?;
+
+    let options = ParseOptions::default();
+    let raw: envelope::RawSuperJson = serde_json::from_value(envelope)?;
+    let superjson = raw.into_superjson(&options, &mut Vec::new())?;
+    deserialize::deserialize_with(&superjson, &options)
+}
+
+fn mac(envelope: &serde_json::Value, key: &[u8]) -> HmacSha256 {
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC accepts keys of any length");
+    canonical::encode_json(envelope, &mut mac);
+    mac
+}
+
+impl canonical::Sink for HmacSha256 {
+    fn write(&mut self, bytes: &[u8]) {
+        self.update(bytes);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signature_depends_on_key_and_meta() {
+        let value = Value::from(vec![Value::Undefined, Value::from(1)]);
+        let text = stringify_signed(&value, b"k1").unwrap();
+        assert_eq!(parse_verified(&text, b"k1").unwrap(), value);
+        assert!(matches!(
+            parse_verified(&text, b"k2"),
+            Err(Error::InvalidSignature)
+        ));
+
+        // Meta is signed too: retyping a value is as bad as changing it.
+        let stripped = text.replace(r#"["undefined"]"#, r#"["number"]"#);
+        assert_ne!(stripped, text);
+        assert!(matches!(
+            parse_verified(&stripped, b"k1"),
+            Err(Error::InvalidSignature)
+        ));
+    }
+
+    #[test]
+    fn test_missing_or_malformed_signature() {
+        let unsigned = crate::stringify(&Value::from(1)).unwrap();
+        assert!(matches!(
+            parse_verified(&unsigned, b"k"),
+            Err(Error::InvalidSignature)
+        ));
+        let malformed = r#"{"json":1,"signature":"zz"}"#;
+        assert!(matches!(
+            parse_verified(malformed, b"k"),
+            Err(Error::InvalidSignature)
+        ));
+    }
+}
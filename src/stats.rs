@@ -0,0 +1,36 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use crate::Value;
+
+/// Statistics about a parsed payload, for per-endpoint telemetry.
+///
+/// Collected during the normal deserialization pass when
+/// [`ParseOptions::collect_stats`](crate::ParseOptions::collect_stats) is set.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ParseStats {
+    /// Size of the input text in bytes.
+    pub bytes: usize,
+    /// Total number of values restored, including containers.
+    pub nodes: usize,
+    /// Number of values restored per type, keyed by [`Value::type_name`].
+    pub node_counts: BTreeMap<&'static str, usize>,
+    /// Number of entries in the annotation tree, including nested ones.
+    pub annotations: usize,
+    /// Deepest container nesting encountered; a scalar root has depth 0.
+    pub max_depth: usize,
+    /// Wall-clock time spent parsing.
+    pub elapsed: Duration,
+}
+
+impl ParseStats {
+    pub(crate) fn record(&mut self, value: &Value) {
+        self.nodes += 1;
+        *self.node_counts.entry(value.type_name()).or_insert(0) += 1;
+    }
+
+    /// The number of restored values of the given type.
+    pub fn count(&self, type_name: &str) -> usize {
+        self.node_counts.get(type_name).copied().unwrap_or(0)
+    }
+}
@@ -0,0 +1,271 @@
+//! Incremental updates of a shared document.
+//!
+//! Instead of sending a whole document after every change, a server can
+//! send a [`Delta`]: the operations turning the previous state into the new
+//! one, each carrying its value as a superjson envelope. A delta names the
+//! [`content_hash`](Value::content_hash) of the state it applies to, so a
+//! client that missed an update finds out and can fetch the full document
+//! again.
+//!
+//! On the wire a delta is
+//! `{"v": 1, "base": "<hex>", "ops": [{"op": "set", "path": "...", "value": {"json": ..., "meta": ...}}, {"op": "remove", "path": "..."}]}`.
+//!
+//! # Examples
+//! ```
+//! use superjson_rs::sync::{apply_delta, make_delta};
+//! use superjson_rs::{Map, Value};
+//!
+//! let mut old = Map::new();
+//! old.insert("title".to_string(), Value::from("draft"));
+//! old.insert("tags".to_string(), Value::Set(vec![Value::from("a")]));
+//! let old = Value::Object(old);
+//!
+//! let mut new = old.clone();
+//! if let Value::Object(map) = &mut new {
+//!     map.insert("tags".to_string(), Value::Set(vec![Value::from("a"), Value::from("b")]));
+//! }
+//!
+//! let delta = make_delta(&old, &new).unwrap();
+//! let wire = serde_json::to_string(&delta).unwrap();
+//! assert!(wire.contains(r#""op":"set","path":"tags.1""#));
+//!
+//! let mut state = old.clone();
+//! apply_delta(&mut state, &serde_json::from_str(&wire).unwrap()).unwrap();
+//! assert_eq!(state, new);
+//! ```
+
+use serde::{Deserialize, Serialize};
+
+use crate::diff::{Change, diff};
+use crate::path::{self, PathSegment};
+use crate::{Error, Result, SuperJson, Value, deserialize, patch, serialize};
+
+/// The version of the delta format written by [`make_delta`].
+pub const DELTA_VERSION: u8 = 1;
+
+/// The changes turning one state of a document into another.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Delta {
+    /// The format version, [`DELTA_VERSION`].
+    pub v: u8,
+    /// The content hash, as lowercase hex, of the state the delta applies to.
+    pub base: String,
+    /// The operations, applied in order.
+    pub ops: Vec<DeltaOp>,
+}
+
+/// One operation of a [`Delta`], at a superjson path.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "camelCase")]
+pub enum DeltaOp {
+    /// Set the value at `path`: replace it, add an object field, or append
+    /// an element to an array, set or map.
+    Set { path: String, value: Box<SuperJson> },
+    /// Remove the object field, element or map entry at `path`.
+    Remove { path: String },
+}
+
+/// The delta turning `old` into `new`.
+///
+/// Removals come first, from the end of each container backwards, so that
+/// the indices of later operations stay valid.
+pub fn make_delta(old: &Value, new: &Value) -> Result<Delta> {
+    let mut removals = Vec::new();
+    let mut ops = Vec::new();
+    for difference in diff(old, new) {
+        let path = path::join(&difference.path);
+        match difference.change {
+            Change::Removed(_) => removals.push(DeltaOp::Remove { path }),
+            Change::Added(value) | Change::Replaced { new: value, .. } => {
+                ops.push(DeltaOp::Set {
+                    path,
+                    value: Box::new(serialize::serialize(&value)?),
+                });
+            }
+        }
+    }
+    removals.reverse();
+    removals.append(&mut ops);
+    Ok(Delta {
+        v: DELTA_VERSION,
+        base: old.content_hash().to_string(),
+        ops: removals,
+    })
+}
+
+/// Apply `delta` to `state`.
+///
+/// # Errors
+/// Returns [`Error::DeltaBaseMismatch`], leaving `state` untouched, if
+/// `state` is not the state the delta was made from, and
+/// [`Error::InvalidDelta`] for an unknown format version. An operation on a
+/// path that does not fit `state` fails with [`Error::InvalidPath`]; by then
+/// earlier operations have been applied.
+pub fn apply_delta(state: &mut Value, delta: &Delta) -> Result<()> {
+    if delta.v != DELTA_VERSION {
+        return Err(Error::InvalidDelta(format!(
+            "unsupported version {}",
+            delta.v
+        )));
+    }
+    let actual = state.content_hash().to_string();
+    if !delta.base.eq_ignore_ascii_case(&actual) {
+        return Err(Error::DeltaBaseMismatch {
+            expected: delta.base.clone(),
+            actual,
+        });
+    }
+
+    for op in &delta.ops {
+        match op {
+            DeltaOp::Set { path, value } => set(state, path, deserialize::deserialize(value)?)?,
+            DeltaOp::Remove { path } => remove(state, path)?,
+        }
+    }
+    Ok(())
+}
+
+fn set(state: &mut Value, path: &str, value: Value) -> Result<()> {
+    let mut segments = path::parse(path);
+    if let Ok(slot) = patch::resolve_mut(state, path, segments.clone()) {
+        *slot = value;
+        return Ok(());
+    }
+
+    let missing = || Error::InvalidPath(format!("no value at '{path}'"));
+    let last = segments.pop().ok_or_else(missing)?;
+    match (patch::resolve_mut(state, path, segments)?, last) {
+        (Value::Array(items) | Value::Set(items), PathSegment::Index(i)) if i == items.len() => {
+            items.push(value);
+        }
+        (Value::Map(entries), PathSegment::Index(i)) if i == entries.len() => match value {
+            Value::Array(pair) if pair.len() == 2 => {
+                let mut pair = pair.into_iter();
+                let (Some(key), Some(value)) = (pair.next(), pair.next()) else {
+                    unreachable!("the pair has two elements");
+                };
+                entries.push((key, value));
+            }
+            other => {
+                return Err(Error::TypeMismatch {
+                    path: path.to_string(),
+                    expected: "a [key, value] map entry".to_string(),
+                    actual: other.type_name().to_string(),
+                });
+            }
+        },
+        (Value::Error { cause, .. }, PathSegment::Key(key)) if key == "cause" => {
+            *cause = Some(Box::new(value));
+        }
+        _ => return Err(missing()),
+    }
+    Ok(())
+}
+
+fn remove(state: &mut Value, path: &str) -> Result<()> {
+    let missing = || Error::InvalidPath(format!("no value at '{path}'"));
+    let mut segments = path::parse(path);
+    let last = segments.pop().ok_or_else(missing)?;
+    let removed = match (patch::resolve_mut(state, path, segments)?, last) {
+        (Value::Error { cause, .. }, PathSegment::Key(key)) if key == "cause" => {
+            cause.take().is_some()
+        }
+        (
+            Value::Object(map) | Value::Class { fields: map, .. } | Value::Error { extra: map, .. },
+            last,
+        ) => {
+            let key = match last {
+                PathSegment::Key(key) => key,
+                PathSegment::Index(index) => index.to_string(),
+            };
+            #[cfg(feature = "preserve_order")]
+            let removed = map.shift_remove(&key);
+            #[cfg(not(feature = "preserve_order"))]
+            let removed = map.remove(&key);
+            removed.is_some()
+        }
+        (Value::Array(items) | Value::Set(items), PathSegment::Index(i)) if i < items.len() => {
+            items.remove(i);
+            true
+        }
+        (Value::Map(entries), PathSegment::Index(i)) if i < entries.len() => {
+            entries.remove(i);
+            true
+        }
+        _ => false,
+    };
+    if removed { Ok(()) } else { Err(missing()) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::value::Map;
+
+    fn roundtrip(old: &Value, new: &Value) {
+        let delta = make_delta(old, new).unwrap();
+        let wire = serde_json::to_string(&delta).unwrap();
+        let mut state = old.clone();
+        apply_delta(&mut state, &serde_json::from_str(&wire).unwrap()).unwrap();
+        assert_eq!(&state, new);
+    }
+
+    #[test]
+    fn test_delta_roundtrips() {
+        let mut a = Map::new();
+        a.insert("keep".into(), Value::from(1));
+        a.insert("drop".into(), Value::Undefined);
+        a.insert("list".into(), Value::from(vec![1, 2, 3]));
+        a.insert("m".into(), Value::Map(vec![(Value::from("k"), Value::NaN)]));
+        let old = Value::Object(a);
+
+        let mut b = Map::new();
+        b.insert("keep".into(), Value::from(1));
+        b.insert("list".into(), Value::from(vec![1]));
+        b.insert(
+            "m".into(),
+            Value::Map(vec![
+                (Value::from("k"), Value::PosInfinity),
+                (Value::from(2), Value::Set(vec![])),
+            ]),
+        );
+        b.insert("new".into(), Value::Set(vec![Value::NegZero]));
+        let new = Value::Object(b);
+
+        roundtrip(&old, &new);
+        roundtrip(&new, &old);
+        roundtrip(&old, &Value::from("replaced"));
+
+        let error = |extra: Vec<(&str, Value)>| Value::Error {
+            name: "Error".into(),
+            message: "failed".into(),
+            stack: None,
+            cause: None,
+            extra: extra.into_iter().map(|(k, v)| (k.to_string(), v)).collect(),
+        };
+        let bare = error(vec![]);
+        let coded = error(vec![("code", Value::from(1)), ("retry", Value::Bool(true))]);
+        let recoded = error(vec![("code", Value::from(2)), ("0", Value::Null)]);
+        roundtrip(&bare, &coded);
+        roundtrip(&coded, &recoded);
+        roundtrip(&recoded, &bare);
+    }
+
+    #[test]
+    fn test_stale_state_is_rejected() {
+        let old = Value::from(vec![1, 2]);
+        let delta = make_delta(&old, &Value::from(vec![1, 3])).unwrap();
+        let mut other = Value::from(vec![1, 4]);
+        assert!(matches!(
+            apply_delta(&mut other, &delta),
+            Err(Error::DeltaBaseMismatch { .. })
+        ));
+        assert_eq!(other, Value::from(vec![1, 4]));
+
+        let future = Delta { v: 2, ..delta };
+        assert!(matches!(
+            apply_delta(&mut old.clone(), &future),
+            Err(Error::InvalidDelta(_))
+        ));
+    }
+}
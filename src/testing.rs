@@ -0,0 +1,81 @@
+//! Helpers for tests that assert on superjson values and envelopes.
+//!
+//! [`assert_superjson_eq!`](crate::assert_superjson_eq) reports mismatches
+//! path by path instead of dumping both trees, and [`snapshot`] renders an
+//! envelope as stable, indented JSON suitable for snapshot tools like `insta`.
+
+use std::fmt::Write;
+
+use crate::diff::diff;
+use crate::{Result, SuperJson, Value, serialize};
+
+/// Render the superjson envelope of `value` as indented JSON.
+///
+/// The output is deterministic for a given value, so it can be committed as
+/// a snapshot and compared across runs.
+///
+/// # Examples
+/// ```
+/// use superjson_rs::Value;
+/// use superjson_rs::testing::snapshot;
+///
+/// let rendered = snapshot(&Value::Set(vec![Value::from(1)])).unwrap();
+/// assert!(rendered.contains("\"set\""));
+/// ```
+pub fn snapshot(value: &Value) -> Result<String> {
+    snapshot_envelope(&serialize::serialize(value)?)
+}
+
+/// Render an existing envelope as indented JSON.
+pub fn snapshot_envelope(superjson: &SuperJson) -> Result<String> {
+    Ok(serde_json::to_string_pretty(superjson)?)
+}
+
+/// Describe how `left` differs from `right`, one path per line.
+///
+/// Returns `None` if the values are structurally equal.
+pub fn describe_differences(left: &Value, right: &Value) -> Option<String> {
+    let differences = diff(left, right);
+    if differences.is_empty() {
+        return None;
+    }
+
+    let mut out = format!("{} difference(s) (left → right):", differences.len());
+    for difference in &differences {
+        let _ = write!(out, "\n  {difference}");
+    }
+    Some(out)
+}
+
+#[doc(hidden)]
+#[track_caller]
+pub fn assert_superjson_eq_impl(left: &Value, right: &Value, context: Option<std::fmt::Arguments>) {
+    if let Some(description) = describe_differences(left, right) {
+        match context {
+            Some(context) => panic!("assertion `left == right` failed: {context}\n{description}"),
+            None => panic!("assertion `left == right` failed\n{description}"),
+        }
+    }
+}
+
+/// Assert that two [`Value`]s are structurally equal.
+///
+/// On failure, the panic message lists each differing path instead of the
+/// full `Debug` output of both values.
+///
+/// # Examples
+/// ```
+/// use superjson_rs::{Value, assert_superjson_eq};
+///
+/// let value = Value::Array(vec![Value::Undefined]);
+/// assert_superjson_eq!(value, Value::Array(vec![Value::Undefined]));
+/// ```
+#[macro_export]
+macro_rules! assert_superjson_eq {
+    ($left:expr, $right:expr $(,)?) => {
+        $crate::testing::assert_superjson_eq_impl(&$left, &$right, None)
+    };
+    ($left:expr, $right:expr, $($arg:tt)+) => {
+        $crate::testing::assert_superjson_eq_impl(&$left, &$right, Some(format_args!($($arg)+)))
+    };
+}
@@ -0,0 +1,310 @@
+//! tRPC's batched HTTP responses.
+//!
+//! With `httpBatchLink`, tRPC answers several calls in one response: an
+//! array with one item per call, in the order they were made. A call that
+//! succeeded holds its return value as a superjson envelope under
+//! `result.data`; one that failed holds its error shape, serialized the same
+//! way, under `error`:
+//!
+//! ```json
+//! [
+//!   {"result": {"data": {"json": "2024-01-01T00:00:00.000Z", "meta": {"values": ["Date"], "v": 1}}}},
+//!   {"error": {"json": {"message": "Not found", "code": -32004, "data": {"code": "NOT_FOUND", "httpStatus": 404}}}}
+//! ]
+//! ```
+//!
+//! [`parse_batch`] reads each item into a `Result`, an error becoming an
+//! [`Error::Trpc`], and [`stringify_batch`] writes `Result`s back. Unlike
+//! [`crate::parse_batch`], a response to a single, unbatched call is read as
+//! a batch of one.
+
+use serde::Serialize;
+
+use crate::value::Map;
+use crate::{Error, ParseOptions, Result, SuperJson, Value, deserialize, serialize, typed};
+
+/// tRPC's error codes: the name in `data.code`, the JSON-RPC code in
+/// `code` and the HTTP status in `data.httpStatus`.
+const ERROR_CODES: &[(&str, i64, u16)] = &[
+    ("PARSE_ERROR", -32700, 400),
+    ("BAD_REQUEST", -32600, 400),
+    ("INTERNAL_SERVER_ERROR", -32603, 500),
+    ("NOT_IMPLEMENTED", -32603, 501),
+    ("BAD_GATEWAY", -32603, 502),
+    ("SERVICE_UNAVAILABLE", -32603, 503),
+    ("GATEWAY_TIMEOUT", -32603, 504),
+    ("UNAUTHORIZED", -32001, 401),
+    ("FORBIDDEN", -32003, 403),
+    ("NOT_FOUND", -32004, 404),
+    ("METHOD_NOT_SUPPORTED", -32005, 405),
+    ("TIMEOUT", -32008, 408),
+    ("CONFLICT", -32009, 409),
+    ("PRECONDITION_FAILED", -32012, 412),
+    ("PAYLOAD_TOO_LARGE", -32013, 413),
+    ("UNSUPPORTED_MEDIA_TYPE", -32015, 415),
+    ("UNPROCESSABLE_CONTENT", -32022, 422),
+    ("TOO_MANY_REQUESTS", -32029, 429),
+    ("CLIENT_CLOSED_REQUEST", -32099, 499),
+];
+
+/// An [`Error::Trpc`] with the code tRPC uses for `code`, such as
+/// `"NOT_FOUND"`, and the default `data` tRPC's error formatter writes.
+///
+/// An unknown `code` is kept in `data.code`, and is otherwise treated as
+/// `INTERNAL_SERVER_ERROR`.
+///
+/// # Examples
+/// ```
+/// use superjson_rs::{Error, trpc};
+///
+/// let Error::Trpc { code, data, .. } = trpc::error("NOT_FOUND", "no such post") else {
+///     unreachable!()
+/// };
+/// assert_eq!(code, -32004);
+/// assert_eq!(data.pointer("httpStatus").and_then(|v| v.as_i64()), Some(404));
+/// ```
+pub fn error(code: &str, message: impl Into<String>) -> Error {
+    let (_, json_rpc_code, http_status) = ERROR_CODES
+        .iter()
+        .find(|(name, ..)| *name == code)
+        .copied()
+        .unwrap_or(ERROR_CODES[2]);
+    let mut data = Map::new();
+    data.insert("code".to_string(), Value::from(code));
+    data.insert(
+        "httpStatus".to_string(),
+        Value::from(i64::from(http_status)),
+    );
+    Error::Trpc {
+        code: json_rpc_code,
+        message: message.into(),
+        data: Box::new(Value::Object(data)),
+    }
+}
+
+/// Serialize the results of several calls into a batch response.
+///
+/// An [`Error::Trpc`] is written as its error shape; any other error as an
+/// `INTERNAL_SERVER_ERROR` carrying its message.
+///
+/// # Examples
+/// ```
+/// use superjson_rs::{Value, trpc};
+///
+/// let results = [Ok(Value::NaN), Err(trpc::error("FORBIDDEN", "no"))];
+/// let batch: serde_json::Value =
+///     serde_json::from_str(&trpc::stringify_batch(&results).unwrap()).unwrap();
+/// assert_eq!(
+///     batch,
+///     serde_json::json!([
+///         {"result": {"data": {"json": "NaN", "meta": {"values": ["number"], "v": 1}}}},
+///         {"error": {"json": {"message": "no", "code": -32003, "data": {"code": "FORBIDDEN", "httpStatus": 403}}}}
+///     ])
+/// );
+/// ```
+pub fn stringify_batch(results: &[Result<Value>]) -> Result<String> {
+    let items = results
+        .iter()
+        .map(|result| {
+            Ok(match result {
+                Ok(value) => Item::Result {
+                    data: serialize::serialize(value)?,
+                },
+                Err(Error::Trpc {
+                    code,
+                    message,
+                    data,
+                }) => error_item(*code, message, data)?,
+                Err(other) => {
+                    let Error::Trpc {
+                        code,
+                        message,
+                        data,
+                    } = error("INTERNAL_SERVER_ERROR", other.to_string())
+                    else {
+                        unreachable!("error() returns Error::Trpc")
+                    };
+                    error_item(code, &message, &data)?
+                }
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(serde_json::to_string(&items)?)
+}
+
+/// One item of a batch response.
+#[derive(Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Item {
+    Result { data: SuperJson },
+    Error(SuperJson),
+}
+
+fn error_item(code: i64, message: &str, data: &Value) -> Result<Item> {
+    let mut shape = Map::new();
+    shape.insert("message".to_string(), Value::from(message));
+    shape.insert("code".to_string(), Value::from(code));
+    shape.insert("data".to_string(), data.clone());
+    Ok(Item::Error(serialize::serialize(&Value::Object(shape))?))
+}
+
+/// Parse a batch response, restoring each call's result independently.
+///
+/// The outer `Result` fails only if `s` is neither a JSON array nor an
+/// object. A call that failed yields an [`Error::Trpc`] in its slot; an item
+/// that is neither a result nor an error yields
+/// [`Error::InvalidTrpcResponse`].
+///
+/// # Examples
+/// ```
+/// use superjson_rs::{Error, Value, trpc};
+///
+/// let items = trpc::parse_batch(
+///     r#"[{"result":{"data":{"json":"-Infinity","meta":{"values":["number"]}}}},
+///         {"error":{"json":{"message":"Not found","code":-32004,"data":{"code":"NOT_FOUND"}}}}]"#,
+/// )
+/// .unwrap();
+/// assert_eq!(items[0].as_ref().unwrap(), &Value::NegInfinity);
+/// assert!(matches!(&items[1], Err(Error::Trpc { code: -32004, message, .. }) if message == "Not found"));
+/// ```
+pub fn parse_batch(s: &str) -> Result<Vec<Result<Value>>> {
+    parse_batch_with(s, &ParseOptions::default())
+}
+
+/// Parse a batch response, applying `options` to every result and error.
+pub fn parse_batch_with(s: &str, options: &ParseOptions) -> Result<Vec<Result<Value>>> {
+    let items = match serde_json::from_str(s)? {
+        serde_json::Value::Array(items) => items,
+        item @ serde_json::Value::Object(_) => vec![item],
+        other => {
+            return Err(Error::InvalidTrpcResponse(format!(
+                "expected an array of results, got {other}"
+            )));
+        }
+    };
+    Ok(items
+        .into_iter()
+        .map(|item| parse_item(item, options))
+        .collect())
+}
+
+fn parse_item(item: serde_json::Value, options: &ParseOptions) -> Result<Value> {
+    let serde_json::Value::Object(mut item) = item else {
+        return Err(Error::InvalidTrpcResponse(format!(
+            "expected a result or an error, got {item}"
+        )));
+    };
+    if let Some(error) = item.remove("error") {
+        return Err(parse_error(deserialize::deserialize_json_with(
+            error, options,
+        )?));
+    }
+    match item.remove("result") {
+        // A procedure that returns nothing may leave out `data`.
+        Some(serde_json::Value::Object(mut result)) => match result.remove("data") {
+            Some(data) => deserialize::deserialize_json_with(data, options),
+            None => Ok(Value::Undefined),
+        },
+        _ => Err(Error::InvalidTrpcResponse(
+            "expected a `result` object or an `error`".to_string(),
+        )),
+    }
+}
+
+/// The [`Error::Trpc`] for a restored error shape.
+fn parse_error(shape: Value) -> Error {
+    let Value::Object(mut shape) = shape else {
+        return Error::InvalidTrpcResponse(format!(
+            "expected an error shape, got {}",
+            shape.type_name()
+        ));
+    };
+    let (Value::String(message), Some(code)) = (
+        typed::take(&mut shape, "message"),
+        typed::take(&mut shape, "code").as_i64(),
+    ) else {
+        return Error::InvalidTrpcResponse(
+            "an error shape needs a string `message` and an integer `code`".to_string(),
+        );
+    };
+    Error::Trpc {
+        code,
+        message,
+        data: Box::new(typed::take(&mut shape, "data")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roundtrip() {
+        let results = vec![
+            Ok(Value::Set(vec![Value::from("a")])),
+            Ok(Value::Undefined),
+            Err(error("UNAUTHORIZED", "log in first")),
+            Err(error("I_AM_A_TEAPOT", "short and stout")),
+        ];
+        let text = stringify_batch(&results).unwrap();
+        let parsed = parse_batch(&text).unwrap();
+        assert_eq!(parsed.len(), 4);
+        assert_eq!(parsed[0].as_ref().unwrap(), results[0].as_ref().unwrap());
+        assert_eq!(parsed[1].as_ref().unwrap(), &Value::Undefined);
+        for (parsed, original) in parsed[2..].iter().zip(&results[2..]) {
+            let (
+                Err(Error::Trpc {
+                    code,
+                    message,
+                    data,
+                }),
+                Err(Error::Trpc {
+                    code: c,
+                    message: m,
+                    data: d,
+                }),
+            ) = (parsed, original)
+            else {
+                panic!("expected tRPC errors, got {parsed:?}");
+            };
+            assert_eq!((code, message, data), (c, m, d));
+        }
+        assert!(matches!(parsed[3], Err(Error::Trpc { code: -32603, .. })));
+    }
+
+    #[test]
+    fn test_other_errors_are_internal() {
+        let text = stringify_batch(&[Err(Error::InvalidDate("x".into()))]).unwrap();
+        let Err(Error::Trpc { message, data, .. }) = parse_batch(&text).unwrap().remove(0) else {
+            panic!("expected a tRPC error");
+        };
+        assert_eq!(message, "invalid date: x");
+        assert_eq!(
+            data.pointer("code"),
+            Some(&Value::from("INTERNAL_SERVER_ERROR"))
+        );
+    }
+
+    #[test]
+    fn test_parse_items() {
+        let items = parse_batch(
+            r#"[{"result":{}},{"result":{"data":{"json":1}}},{"error":{"json":"oops"}},
+                {"error":{"json":{"message":"m","code":-32600,"stack":"..."}}},7]"#,
+        )
+        .unwrap();
+        assert!(matches!(items[0], Ok(Value::Undefined)));
+        assert_eq!(items[1].as_ref().unwrap(), &Value::from(1));
+        assert!(matches!(items[2], Err(Error::InvalidTrpcResponse(_))));
+        assert!(
+            matches!(&items[3], Err(Error::Trpc { code: -32600, data, .. }) if **data == Value::Undefined)
+        );
+        assert!(matches!(items[4], Err(Error::InvalidTrpcResponse(_))));
+
+        let single = parse_batch(r#"{"result":{"data":{"json":"x"}}}"#).unwrap();
+        assert_eq!(single[0].as_ref().unwrap(), &Value::from("x"));
+        assert!(matches!(
+            parse_batch("1"),
+            Err(Error::InvalidTrpcResponse(_))
+        ));
+    }
+}
@@ -0,0 +1,325 @@
+//! Conversions between Rust structs and [`Value`]s with per-field type hints.
+//!
+//! [`to_string`](crate::to_string) and [`from_str`](crate::from_str) can
+//! only annotate what serde's data model tells them about: a timestamp kept
+//! as a `String` or as milliseconds in an `i64`, or an id too precious for an
+//! `f64`, go out as plain JSON. [`ToSuperJson`] and [`FromSuperJson`] convert
+//! a struct field by field, and the `derive` feature generates them from
+//! hints on the fields:
+//!
+//! | Hint | Field types | Written as | Read from |
+//! |------|-------------|------------|-----------|
+//! | none | any `Serialize` / `Deserialize` type | as [`to_string`](crate::to_string) writes it | as [`from_str`](crate::from_str) reads it |
+//! | `#[superjson(date)]` | an RFC 3339 string or milliseconds since the epoch | `Date` | an RFC 3339 string, falling back to milliseconds |
+//! | `#[superjson(bigint)]` | an integer or a decimal string | `bigint` | an integer, falling back to a decimal string |
+//! | `#[superjson(set)]` | any sequence | `Set` | any sequence |
+//!
+//! `null` and `undefined`, such as a `None` in an `Option` field, are
+//! written and read as they are whatever the hint. Fields without a hint
+//! only need serde's traits; the derive does not require them of the struct
+//! itself, but a generic struct's impls require them of the field types
+//! that mention its type parameters. Keys follow serde's `rename` and
+//! `rename_all` attributes, so a struct deriving serde's traits as well
+//! uses the same keys either way; serde's other attributes, such as `skip`
+//! and `flatten`, are ignored. Raw identifiers lose their `r#`. Deriving
+//! needs named fields, and the generated code names the crate
+//! `superjson_rs`.
+//!
+//! [`TypedSuperJson<T>`] is an envelope tagged with the type it holds, for
+//! code that passes envelopes along and decodes them only now and then.
+//!
+//! # Examples
+//! ```
+//! # #[cfg(feature = "derive")] {
+//! use superjson_rs::{FromSuperJson, ToSuperJson};
+//!
+//! #[derive(Debug, PartialEq, ToSuperJson, FromSuperJson)]
+//! struct Order {
+//!     #[superjson(bigint)]
+//!     id: String,
+//!     #[superjson(date)]
+//!     placed_at: i64,
+//!     #[superjson(set)]
+//!     tags: Vec<String>,
+//!     note: Option<String>,
+//! }
+//!
+//! let order = Order {
+//!     id: "9007199254740993".to_string(),
+//!     placed_at: 0,
+//!     tags: vec!["gift".to_string()],
+//!     note: None,
+//! };
+//! let superjson = order.to_superjson().unwrap();
+//! assert_eq!(
+//!     serde_json::to_string(&superjson.meta).unwrap(),
+//!     r#"{"values":{"id":["bigint"],"placed_at":["Date"],"tags":["set"]},"v":1}"#
+//! );
+//! assert_eq!(Order::from_superjson(&superjson).unwrap(), order);
+//! # }
+//! ```
+
+use std::fmt;
+use std::marker::PhantomData;
+
+#[doc(hidden)]
+pub use serde::Serialize;
+#[doc(hidden)]
+pub use serde::de::DeserializeOwned;
+
+use crate::value::Map;
+use crate::{Error, Result, SuperJson, Value, de, deserialize, ser, serialize};
+
+/// A type that converts itself to a [`Value`].
+pub trait ToSuperJson {
+    fn to_value(&self) -> Result<Value>;
+
+    /// Convert to a value and serialize it to an envelope.
+    fn to_superjson(&self) -> Result<SuperJson> {
+        serialize::serialize(&self.to_value()?)
+    }
+}
+
+/// A type that builds itself from a [`Value`].
+pub trait FromSuperJson: Sized {
+    fn from_value(value: Value) -> Result<Self>;
+
+    /// Restore an envelope and build from its value.
+    fn from_superjson(superjson: &SuperJson) -> Result<Self> {
+        Self::from_value(deserialize::deserialize(superjson)?)
+    }
+}
+
+/// An envelope known to hold a `T`, decoded only on demand.
+///
+/// The raw [`SuperJson`] is kept as it arrived, so a proxy can forward it
+/// untouched and [`decode`](Self::decode) it only when it needs to look
+/// inside. It serializes and deserializes exactly as a [`SuperJson`] does.
+///
+/// # Examples
+/// ```
+/// use serde::{Deserialize, Serialize};
+/// use superjson_rs::TypedSuperJson;
+///
+/// #[derive(Debug, PartialEq, Serialize, Deserialize)]
+/// struct Ping {
+///     seq: u64,
+///     rtt: f64,
+/// }
+///
+/// let text = r#"{"json":{"seq":1,"rtt":"Infinity"},"meta":{"values":{"rtt":["number"]}}}"#;
+/// let envelope: TypedSuperJson<Ping> = serde_json::from_str(text).unwrap();
+/// assert_eq!(envelope.decode().unwrap(), Ping { seq: 1, rtt: f64::INFINITY });
+///
+/// let forwarded = serde_json::to_string(&envelope).unwrap();
+/// assert_eq!(forwarded, serde_json::to_string(envelope.as_raw()).unwrap());
+/// ```
+#[derive(Serialize, serde::Deserialize)]
+#[serde(transparent, bound = "")]
+pub struct TypedSuperJson<T> {
+    raw: SuperJson,
+    #[serde(skip)]
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T> TypedSuperJson<T> {
+    /// Treat `raw` as an envelope of a `T`, without checking it.
+    pub fn from_raw(raw: SuperJson) -> Self {
+        TypedSuperJson {
+            raw,
+            marker: PhantomData,
+        }
+    }
+
+    pub fn as_raw(&self) -> &SuperJson {
+        &self.raw
+    }
+
+    pub fn into_raw(self) -> SuperJson {
+        self.raw
+    }
+}
+
+impl<T: Serialize> TypedSuperJson<T> {
+    /// Serialize `value` to an envelope.
+    pub fn encode(value: &T) -> Result<Self> {
+        ser::to_superjson(value).map(TypedSuperJson::from_raw)
+    }
+}
+
+impl<T: DeserializeOwned> TypedSuperJson<T> {
+    /// Restore the envelope and deserialize it into a `T`.
+    pub fn decode(&self) -> Result<T> {
+        de::from_superjson(&self.raw)
+    }
+}
+
+impl<T> Clone for TypedSuperJson<T> {
+    fn clone(&self) -> Self {
+        TypedSuperJson::from_raw(self.raw.clone())
+    }
+}
+
+impl<T> fmt::Debug for TypedSuperJson<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("TypedSuperJson").field(&self.raw).finish()
+    }
+}
+
+impl<T> From<TypedSuperJson<T>> for SuperJson {
+    fn from(typed: TypedSuperJson<T>) -> Self {
+        typed.raw
+    }
+}
+
+/// The fields of `value`, which must be an object, for a
+/// [`FromSuperJson`] implementation of the struct `name`.
+pub fn fields(value: Value, name: &str) -> Result<Map<String, Value>> {
+    match value {
+        Value::Object(map) => Ok(map),
+        other => Err(Error::TypeMismatch {
+            path: String::new(),
+            expected: format!("an object for {name}"),
+            actual: other.type_name().to_string(),
+        }),
+    }
+}
+
+/// Take the field `key` out of `map`; a missing field is `undefined`.
+pub fn take(map: &mut Map<String, Value>, key: &str) -> Value {
+    #[cfg(feature = "preserve_order")]
+    let value = map.shift_remove(key);
+    #[cfg(not(feature = "preserve_order"))]
+    let value = map.remove(key);
+    value.unwrap_or(Value::Undefined)
+}
+
+fn mismatch(path: &str, expected: &str, actual: &Value) -> Error {
+    Error::TypeMismatch {
+        path: path.to_string(),
+        expected: expected.to_string(),
+        actual: actual.type_name().to_string(),
+    }
+}
+
+/// Fields without a hint.
+pub mod plain {
+    use super::*;
+
+    pub fn to_value<T: Serialize + ?Sized>(value: &T, _path: &str) -> Result<Value> {
+        ser::to_value(value)
+    }
+
+    pub fn from_value<T: DeserializeOwned>(value: Value, _path: &str) -> Result<T> {
+        de::from_value(value)
+    }
+}
+
+/// Fields hinted `#[superjson(date)]`.
+#[cfg(feature = "chrono")]
+pub mod date {
+    use chrono::{DateTime, Utc};
+
+    use super::*;
+
+    pub fn to_value<T: Serialize + ?Sized>(value: &T, path: &str) -> Result<Value> {
+        let value = plain::to_value(value, path)?;
+        let date = match &value {
+            Value::Date(_)
+            | Value::OffsetDate(_)
+            | Value::InvalidDate
+            | Value::Null
+            | Value::Undefined => {
+                return Ok(value);
+            }
+            Value::String(s) => DateTime::parse_from_rfc3339(s)
+                .ok()
+                .map(|dt| dt.with_timezone(&Utc)),
+            Value::Number(n) if n.fract() == 0.0 && n.abs() <= i64::MAX as f64 => {
+                DateTime::from_timestamp_millis(*n as i64)
+            }
+            Value::Int(_) | Value::UInt(_) => {
+                value.as_i64().and_then(DateTime::from_timestamp_millis)
+            }
+            _ => None,
+        };
+        date.map(Value::Date)
+            .ok_or_else(|| mismatch(path, "an RFC 3339 string or milliseconds", &value))
+    }
+
+    pub fn from_value<T: DeserializeOwned>(value: Value, path: &str) -> Result<T> {
+        let Value::Date(dt) = value else {
+            return plain::from_value(value, path);
+        };
+        plain::from_value(Value::Date(dt), path)
+            .or_else(|e| plain::from_value(Value::from(dt.timestamp_millis()), path).map_err(|_| e))
+    }
+}
+
+/// Fields hinted `#[superjson(bigint)]`.
+#[cfg(feature = "bigint")]
+pub mod bigint {
+    use num_bigint::{BigInt, Sign};
+    use num_traits::FromPrimitive;
+
+    use super::*;
+
+    pub fn to_value<T: Serialize + ?Sized>(value: &T, path: &str) -> Result<Value> {
+        let value = plain::to_value(value, path)?;
+        let n = match &value {
+            Value::BigInt(_) | Value::Null | Value::Undefined => return Ok(value),
+            Value::Number(n) if n.fract() == 0.0 => BigInt::from_f64(*n),
+            Value::Int(n) => Some(BigInt::from(*n)),
+            Value::UInt(n) => Some(BigInt::from(*n)),
+            Value::String(s) => s.parse().ok(),
+            Value::Array(parts) => from_parts(parts),
+            _ => None,
+        };
+        n.map(Value::BigInt)
+            .ok_or_else(|| mismatch(path, "an integer or a decimal string", &value))
+    }
+
+    /// A `num_bigint::BigInt` as serde writes it: `(sign, u32 digits)`.
+    fn from_parts(parts: &[Value]) -> Option<BigInt> {
+        let [sign, Value::Array(digits)] = parts else {
+            return None;
+        };
+        let sign = match sign.as_i64()? {
+            -1 => Sign::Minus,
+            0 => Sign::NoSign,
+            1 => Sign::Plus,
+            _ => return None,
+        };
+        let digits = digits
+            .iter()
+            .map(|d| d.as_u64().and_then(|d| u32::try_from(d).ok()))
+            .collect::<Option<Vec<_>>>()?;
+        Some(BigInt::new(sign, digits))
+    }
+
+    pub fn from_value<T: DeserializeOwned>(value: Value, path: &str) -> Result<T> {
+        let Value::BigInt(n) = value else {
+            return plain::from_value(value, path);
+        };
+        let text = n.to_string();
+        plain::from_value(Value::BigInt(n), path)
+            .or_else(|e| plain::from_value(Value::String(text), path).map_err(|_| e))
+    }
+}
+
+/// Fields hinted `#[superjson(set)]`.
+pub mod set {
+    use super::*;
+
+    pub fn to_value<T: Serialize + ?Sized>(value: &T, path: &str) -> Result<Value> {
+        match plain::to_value(value, path)? {
+            Value::Array(items) => Ok(Value::Set(items)),
+            value @ (Value::Set(_) | Value::Null | Value::Undefined) => Ok(value),
+            other => Err(mismatch(path, "a sequence", &other)),
+        }
+    }
+
+    pub fn from_value<T: DeserializeOwned>(value: Value, path: &str) -> Result<T> {
+        plain::from_value(value, path)
+    }
+}
@@ -0,0 +1,334 @@
+//! Wrapper types that carry superjson semantics through plain serde structs.
+//!
+//! Serde's data model has no dates, bigints, sets or `undefined`, so
+//! [`to_string`](crate::to_string) can only guess at them. Wrapping a field
+//! in one of these types says what it is: serialized with
+//! [`to_string`](crate::to_string), [`to_superjson`](crate::ser::to_superjson)
+//! or [`to_value`](crate::to_value), it gets the matching annotation.
+//!
+//! | Type | Annotation | Through any other serializer |
+//! |------|------------|------------------------------|
+//! | [`JsDate`] | `Date` | an ISO 8601 string |
+//! | [`BigIntString`] | `bigint` | a decimal string |
+//! | [`JsSet<T>`] | `set` | an array |
+//! | [`JsMap<K, V>`] | `map` | an array of `[key, value]` pairs |
+//! | [`Undefined<T>`] | `undefined` when `None` | `null` when `None` |
+//!
+//! Each type deserializes from what its own serializer writes and from what
+//! [`from_str`](crate::from_str) presents the restored type as, so the
+//! wrappers read back from either side.
+//!
+//! # Examples
+//! ```
+//! use serde::{Deserialize, Serialize};
+//! use superjson_rs::types::{JsSet, Undefined};
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct Profile {
+//!     #[serde(default)]
+//!     nickname: Undefined<String>,
+//!     roles: JsSet<String>,
+//! }
+//!
+//! let profile = Profile {
+//!     nickname: Undefined(None),
+//!     roles: JsSet(vec!["admin".to_string()]),
+//! };
+//! let text = superjson_rs::to_string(&profile).unwrap();
+//! assert_eq!(
+//!     text,
+//!     r#"{"json":{"nickname":null,"roles":["admin"]},"meta":{"values":{"nickname":["undefined"],"roles":["set"]},"v":1}}"#
+//! );
+//!
+//! let restored: Profile = superjson_rs::from_str(&text).unwrap();
+//! assert_eq!(restored.roles.0, ["admin"]);
+//! assert_eq!(restored.nickname.0, None);
+//! ```
+
+use std::fmt;
+use std::marker::PhantomData;
+
+#[cfg(feature = "chrono")]
+use chrono::{DateTime, SecondsFormat, Utc};
+#[cfg(feature = "bigint")]
+use num_bigint::BigInt;
+#[cfg(feature = "bigint")]
+use serde::de;
+use serde::de::{MapAccess, SeqAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// The newtype struct names the crate's serializers recognize.
+pub(crate) const DATE: &str = "$superjson::Date";
+pub(crate) const BIGINT: &str = "$superjson::bigint";
+pub(crate) const SET: &str = "$superjson::set";
+pub(crate) const MAP: &str = "$superjson::map";
+pub(crate) const UNDEFINED: &str = "$superjson::undefined";
+pub(crate) const VALUE: &str = "$superjson::Value";
+
+/// A JavaScript `Date`.
+#[cfg(feature = "chrono")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct JsDate(pub DateTime<Utc>);
+
+#[cfg(feature = "chrono")]
+impl Serialize for JsDate {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let iso = self.0.to_rfc3339_opts(SecondsFormat::Millis, true);
+        serializer.serialize_newtype_struct(DATE, &iso)
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl<'de> Deserialize<'de> for JsDate {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        DateTime::deserialize(deserializer).map(JsDate)
+    }
+}
+
+/// A JavaScript `bigint`, written as a decimal string.
+#[cfg(feature = "bigint")]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct BigIntString(pub BigInt);
+
+#[cfg(feature = "bigint")]
+impl Serialize for BigIntString {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_newtype_struct(BIGINT, &self.0.to_string())
+    }
+}
+
+#[cfg(feature = "bigint")]
+impl<'de> Deserialize<'de> for BigIntString {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct BigIntVisitor;
+
+        impl Visitor<'_> for BigIntVisitor {
+            type Value = BigIntString;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("an integer or a decimal string")
+            }
+
+            fn visit_i64<E: de::Error>(self, v: i64) -> Result<BigIntString, E> {
+                Ok(BigIntString(v.into()))
+            }
+
+            fn visit_u64<E: de::Error>(self, v: u64) -> Result<BigIntString, E> {
+                Ok(BigIntString(v.into()))
+            }
+
+            fn visit_i128<E: de::Error>(self, v: i128) -> Result<BigIntString, E> {
+                Ok(BigIntString(v.into()))
+            }
+
+            fn visit_u128<E: de::Error>(self, v: u128) -> Result<BigIntString, E> {
+                Ok(BigIntString(v.into()))
+            }
+
+            fn visit_str<E: de::Error>(self, v: &str) -> Result<BigIntString, E> {
+                v.parse()
+                    .map(BigIntString)
+                    .map_err(|_| E::invalid_value(de::Unexpected::Str(v), &self))
+            }
+        }
+
+        deserializer.deserialize_any(BigIntVisitor)
+    }
+}
+
+/// A JavaScript `Set`, in insertion order.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct JsSet<T>(pub Vec<T>);
+
+impl<T> Default for JsSet<T> {
+    fn default() -> Self {
+        JsSet(Vec::new())
+    }
+}
+
+impl<T> FromIterator<T> for JsSet<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        JsSet(iter.into_iter().collect())
+    }
+}
+
+impl<T: Serialize> Serialize for JsSet<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_newtype_struct(SET, &self.0)
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for JsSet<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Vec::deserialize(deserializer).map(JsSet)
+    }
+}
+
+/// A JavaScript `Map`, whose keys may be of any type, in insertion order.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct JsMap<K, V>(pub Vec<(K, V)>);
+
+impl<K, V> Default for JsMap<K, V> {
+    fn default() -> Self {
+        JsMap(Vec::new())
+    }
+}
+
+impl<K, V> FromIterator<(K, V)> for JsMap<K, V> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        JsMap(iter.into_iter().collect())
+    }
+}
+
+impl<K: Serialize, V: Serialize> Serialize for JsMap<K, V> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_newtype_struct(MAP, &self.0)
+    }
+}
+
+/// Reads the `[[key, value], ...]` pairs other serializers write, and the
+/// map [`from_str`](crate::from_str) presents a restored `Map` as.
+impl<'de, K: Deserialize<'de>, V: Deserialize<'de>> Deserialize<'de> for JsMap<K, V> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct MapVisitor<K, V>(PhantomData<(K, V)>);
+
+        impl<'de, K: Deserialize<'de>, V: Deserialize<'de>> Visitor<'de> for MapVisitor<K, V> {
+            type Value = JsMap<K, V>;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a map or a sequence of [key, value] pairs")
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<JsMap<K, V>, A::Error> {
+                let mut entries = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+                while let Some(entry) = seq.next_element()? {
+                    entries.push(entry);
+                }
+                Ok(JsMap(entries))
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<JsMap<K, V>, A::Error> {
+                let mut entries = Vec::with_capacity(map.size_hint().unwrap_or(0));
+                while let Some(entry) = map.next_entry()? {
+                    entries.push(entry);
+                }
+                Ok(JsMap(entries))
+            }
+        }
+
+        deserializer.deserialize_any(MapVisitor(PhantomData))
+    }
+}
+
+/// An optional value that is `undefined`, rather than `null`, when absent.
+///
+/// Add `#[serde(default)]` to read a struct field of this type that is
+/// missing from the input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Undefined<T>(pub Option<T>);
+
+impl<T> Default for Undefined<T> {
+    fn default() -> Self {
+        Undefined(None)
+    }
+}
+
+impl<T> From<Option<T>> for Undefined<T> {
+    fn from(value: Option<T>) -> Self {
+        Undefined(value)
+    }
+}
+
+impl<T: Serialize> Serialize for Undefined<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match &self.0 {
+            Some(value) => value.serialize(serializer),
+            None => serializer.serialize_newtype_struct(UNDEFINED, &()),
+        }
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Undefined<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Option::deserialize(deserializer).map(Undefined)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Value;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Record {
+        set: JsSet<f64>,
+        map: JsMap<u8, JsSet<String>>,
+        gone: Undefined<u8>,
+        kept: Undefined<u8>,
+    }
+
+    fn record() -> Record {
+        Record {
+            set: JsSet(vec![f64::INFINITY, 1.0]),
+            map: JsMap(vec![(1, JsSet(vec!["a".to_string()]))]),
+            gone: Undefined(None),
+            kept: Undefined(Some(3)),
+        }
+    }
+
+    #[test]
+    fn test_wrappers_annotate() {
+        let value = crate::to_value(&record()).unwrap();
+        let Value::Object(map) = &value else {
+            panic!("expected an object");
+        };
+        assert_eq!(
+            map["set"],
+            Value::Set(vec![Value::PosInfinity, Value::from(1.0)])
+        );
+        assert_eq!(
+            map["map"],
+            Value::Map(vec![(Value::from(1), Value::Set(vec![Value::from("a")]))])
+        );
+        assert_eq!(map["gone"], Value::Undefined);
+        assert_eq!(map["kept"], Value::from(3));
+
+        let text = crate::to_string(&record()).unwrap();
+        assert_eq!(crate::parse(&text).unwrap(), value);
+        assert_eq!(crate::from_str::<Record>(&text).unwrap(), record());
+    }
+
+    #[test]
+    fn test_plain_serde_json_fallback() {
+        let json = serde_json::to_string(&record()).unwrap();
+        assert_eq!(
+            json,
+            r#"{"set":[null,1.0],"map":[[1,["a"]]],"gone":null,"kept":3}"#
+        );
+        let map: JsMap<u8, JsSet<String>> = serde_json::from_str(r#"[[1,["a"]]]"#).unwrap();
+        assert_eq!(map, record().map);
+    }
+
+    #[cfg(all(feature = "chrono", feature = "bigint"))]
+    #[test]
+    fn test_dates_and_bigints() {
+        use chrono::TimeZone;
+
+        let dt = JsDate(Utc.timestamp_millis_opt(1_500).unwrap());
+        let n = BigIntString(BigInt::from(u128::MAX) * 3);
+        let value = crate::to_value(&(dt, n.clone())).unwrap();
+        assert_eq!(
+            value,
+            Value::from(vec![Value::Date(dt.0), Value::BigInt(n.0.clone())])
+        );
+        let text = crate::to_string(&(dt, n.clone())).unwrap();
+        assert_eq!(
+            crate::from_str::<(JsDate, BigIntString)>(&text).unwrap(),
+            (dt, n.clone())
+        );
+        assert_eq!(
+            serde_json::to_string(&(dt, &n)).unwrap(),
+            format!(r#"["1970-01-01T00:00:01.500Z","{}"]"#, n.0)
+        );
+    }
+}
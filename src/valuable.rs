@@ -0,0 +1,244 @@
+//! [`Valuable`] support, for recording values as structured `tracing`
+//! fields.
+//!
+//! With the `valuable` feature, a [`Value`] can be passed to a `tracing`
+//! event as `field = value.as_value()` and is inspected by the subscriber
+//! only if the event is recorded, instead of being stringified on every call.
+//!
+//! Plain JSON maps onto valuable's own shapes: `null` and `undefined` are
+//! units, numbers (including `NaN`, the infinities and `-0`) are `f64`s,
+//! arrays are lists and objects are maps with string keys. Every other type
+//! is a struct named by its [`type_name`](Value::type_name):
+//!
+//! | Value | Shape |
+//! |-------|-------|
+//! | Date, bigint, URL, symbol | `Date("2024-01-01T00:00:00.000Z")` |
+//! | Set, bytes | `Set([...])` |
+//! | Map | `Map({key: value, ...})` |
+//! | RegExp | `RegExp { source, flags }` |
+//! | Error | `Error { name, message, cause, <stack>, <extra properties> }` |
+//! | custom | `<name>(payload)` |
+//! | class instance | `<name> { field, ... }` |
+//!
+//! Visiting is driven by the subscriber and recurses once per level of
+//! nesting, like visiting any other nested [`Valuable`].
+
+use ::valuable::{
+    Fields, Mappable, NamedField, NamedValues, StructDef, Structable, Valuable, Visit,
+};
+
+use crate::Value;
+
+static REGEXP_FIELDS: &[NamedField<'static>] =
+    &[NamedField::new("source"), NamedField::new("flags")];
+
+static ERROR_FIELDS: &[NamedField<'static>] = &[
+    NamedField::new("name"),
+    NamedField::new("message"),
+    NamedField::new("cause"),
+];
+
+impl Valuable for Value {
+    fn as_value(&self) -> ::valuable::Value<'_> {
+        match self {
+            Value::Null | Value::Undefined => ::valuable::Value::Unit,
+            Value::Bool(b) => ::valuable::Value::Bool(*b),
+            Value::Number(n) => ::valuable::Value::F64(*n),
+            Value::Int(n) => ::valuable::Value::I64(*n),
+            Value::UInt(n) => ::valuable::Value::U64(*n),
+            Value::NaN => ::valuable::Value::F64(f64::NAN),
+            Value::PosInfinity => ::valuable::Value::F64(f64::INFINITY),
+            Value::NegInfinity => ::valuable::Value::F64(f64::NEG_INFINITY),
+            Value::NegZero => ::valuable::Value::F64(-0.0),
+            Value::String(s) => ::valuable::Value::String(s),
+            Value::Array(items) => ::valuable::Value::Listable(items),
+            Value::Object(_) => ::valuable::Value::Mappable(self),
+            _ => ::valuable::Value::Structable(self),
+        }
+    }
+
+    fn visit(&self, visit: &mut dyn Visit) {
+        match self {
+            Value::Array(items) => items.visit(visit),
+            Value::Object(map) => {
+                for (key, value) in map {
+                    visit.visit_entry(::valuable::Value::String(key), value.as_value());
+                }
+            }
+            #[cfg(feature = "chrono")]
+            Value::Date(dt) => {
+                let s = dt.to_rfc3339_opts(chrono::SecondsFormat::Millis, true);
+                visit.visit_unnamed_fields(&[::valuable::Value::String(&s)]);
+            }
+            #[cfg(feature = "chrono")]
+            Value::OffsetDate(dt) => {
+                let s = dt.to_rfc3339_opts(chrono::SecondsFormat::Millis, true);
+                visit.visit_unnamed_fields(&[::valuable::Value::String(&s)]);
+            }
+            #[cfg(feature = "chrono")]
+            Value::InvalidDate => {
+                visit.visit_unnamed_fields(&[::valuable::Value::String(crate::value::INVALID_DATE)])
+            }
+            #[cfg(feature = "bigint")]
+            Value::BigInt(n) => {
+                let s = n.to_string();
+                visit.visit_unnamed_fields(&[::valuable::Value::String(&s)]);
+            }
+            Value::Bytes(bytes) => visit.visit_unnamed_fields(&[bytes.as_value()]),
+            Value::Url(url) => {
+                visit.visit_unnamed_fields(&[::valuable::Value::String(url.as_str())])
+            }
+            Value::Symbol(s) => visit.visit_unnamed_fields(&[::valuable::Value::String(s)]),
+            Value::Set(items) => visit.visit_unnamed_fields(&[::valuable::Value::Listable(items)]),
+            Value::Map(entries) => {
+                let entries = Entries(entries);
+                visit.visit_unnamed_fields(&[::valuable::Value::Mappable(&entries)]);
+            }
+            Value::RegExp { source, flags } => {
+                visit.visit_named_fields(&NamedValues::new(
+                    REGEXP_FIELDS,
+                    &[
+                        ::valuable::Value::String(source),
+                        ::valuable::Value::String(flags),
+                    ],
+                ));
+            }
+            Value::Error {
+                name,
+                message,
+                stack,
+                cause,
+                extra,
+            } => {
+                let cause = cause
+                    .as_deref()
+                    .map_or(::valuable::Value::Unit, Valuable::as_value);
+                let mut names = ERROR_FIELDS.to_vec();
+                let mut values = vec![
+                    ::valuable::Value::String(name),
+                    ::valuable::Value::String(message),
+                    cause,
+                ];
+                if let Some(stack) = stack {
+                    names.push(NamedField::new("stack"));
+                    values.push(::valuable::Value::String(stack));
+                }
+                names.extend(extra.keys().map(|key| NamedField::new(key)));
+                values.extend(extra.values().map(Valuable::as_value));
+                visit.visit_named_fields(&NamedValues::new(&names, &values));
+            }
+            Value::Custom { value, .. } => visit.visit_unnamed_fields(&[value.as_value()]),
+            Value::Unknown { raw, .. } => {
+                let s = raw.to_string();
+                visit.visit_unnamed_fields(&[::valuable::Value::String(&s)]);
+            }
+            Value::Class { fields, .. } => {
+                let names: Vec<_> = fields.keys().map(|key| NamedField::new(key)).collect();
+                let values: Vec<_> = fields.values().map(Valuable::as_value).collect();
+                visit.visit_named_fields(&NamedValues::new(&names, &values));
+            }
+            scalar => visit.visit_value(scalar.as_value()),
+        }
+    }
+}
+
+/// Implemented for the `Object` variant only, which is the one
+/// [`as_value`](Valuable::as_value) reports as a map.
+impl Mappable for Value {
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.as_object().map_or(0, |map| map.len());
+        (len, Some(len))
+    }
+}
+
+/// Implemented for the variants [`as_value`](Valuable::as_value) reports as
+/// structs.
+impl Structable for Value {
+    fn definition(&self) -> StructDef<'_> {
+        match self {
+            Value::RegExp { .. } => StructDef::new_static("RegExp", Fields::Named(REGEXP_FIELDS)),
+            Value::Error {
+                stack: None, extra, ..
+            } if extra.is_empty() => StructDef::new_static("Error", Fields::Named(ERROR_FIELDS)),
+            Value::Error { .. } => StructDef::new_dynamic("Error", Fields::Named(&[])),
+            Value::Custom { name, .. }
+            | Value::Unknown {
+                type_name: name, ..
+            } => StructDef::new_dynamic(name, Fields::Unnamed(1)),
+            Value::Class { name, .. } => StructDef::new_dynamic(name, Fields::Named(&[])),
+            other => StructDef::new_static(other.type_name(), Fields::Unnamed(1)),
+        }
+    }
+}
+
+/// The entries of a `Map`, as a valuable map.
+struct Entries<'a>(&'a [(Value, Value)]);
+
+impl Valuable for Entries<'_> {
+    fn as_value(&self) -> ::valuable::Value<'_> {
+        ::valuable::Value::Mappable(self)
+    }
+
+    fn visit(&self, visit: &mut dyn Visit) {
+        for (key, value) in self.0 {
+            visit.visit_entry(key.as_value(), value.as_value());
+        }
+    }
+}
+
+impl Mappable for Entries<'_> {
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.0.len(), Some(self.0.len()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::value::Map;
+
+    fn debug(value: &Value) -> String {
+        format!("{:?}", value.as_value())
+    }
+
+    #[test]
+    fn test_plain_json() {
+        let mut map = Map::new();
+        map.insert(
+            "ids".to_string(),
+            Value::from(vec![Value::from(1), Value::Null]),
+        );
+        assert_eq!(debug(&Value::Object(map)), r#"{"ids": [1, ()]}"#);
+        assert_eq!(debug(&Value::NaN), "NaN");
+    }
+
+    #[test]
+    fn test_extended_types_are_named() {
+        let value = Value::Map(vec![(
+            Value::Set(vec![Value::from("a")]),
+            Value::Error {
+                name: "TypeError".into(),
+                message: "bad".into(),
+                stack: None,
+                cause: None,
+                extra: Map::new(),
+            },
+        )]);
+        assert_eq!(
+            debug(&value),
+            r#"Map({Set(["a"]): Error { name: "TypeError", message: "bad", cause: () }})"#
+        );
+        let custom = Value::Custom {
+            name: "money".into(),
+            value: Box::new(Value::from("1.50 EUR")),
+        };
+        assert_eq!(debug(&custom), r#"money("1.50 EUR")"#);
+        let mut fields = Map::new();
+        fields.insert("x".to_string(), Value::from(1));
+        let class = Value::Class {
+            name: "Point".into(),
+            fields,
+        };
+        assert_eq!(debug(&class), "Point { x: 1 }");
+    }
+}
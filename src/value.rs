@@ -1,25 +1,91 @@
-use chrono::{DateTime, Utc};
+#[cfg(feature = "chrono")]
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+#[cfg(feature = "preserve_order")]
 use indexmap::IndexMap;
+#[cfg(feature = "bigint")]
 use num_bigint::BigInt;
 use std::fmt;
 
+use crate::path::PathSegment;
+
+/// The map type backing [`Value::Object`].
+///
+/// With the default `preserve_order` feature this is an [`IndexMap`] that keeps
+/// keys in insertion order, as a JavaScript object does. Without it, this is a
+/// [`BTreeMap`](std::collections::BTreeMap) that iterates keys in sorted order.
+#[cfg(feature = "preserve_order")]
+pub type Map<K, V> = IndexMap<K, V>;
+
+/// The map type backing [`Value::Object`].
+///
+/// With the default `preserve_order` feature this is an `IndexMap` that keeps
+/// keys in insertion order, as a JavaScript object does. Without it, this is a
+/// [`BTreeMap`](std::collections::BTreeMap) that iterates keys in sorted order.
+#[cfg(not(feature = "preserve_order"))]
+pub type Map<K, V> = std::collections::BTreeMap<K, V>;
+
+/// The URL held by [`Value::Url`].
+///
+/// With the `url` feature this is a [`url::Url`], so URLs are validated when
+/// parsed and written as JavaScript's `new URL(...).toString()` writes them.
+#[cfg(feature = "url")]
+pub type Url = url::Url;
+
+/// The URL held by [`Value::Url`].
+///
+/// With the `url` feature this is a `url::Url`, so URLs are validated when
+/// parsed and written as JavaScript's `new URL(...).toString()` writes them.
+/// Without it, this is the URL's text as it arrived.
+#[cfg(not(feature = "url"))]
+pub type Url = String;
+
+/// The text of `url`, without copying it.
+pub(crate) fn url_into_string(url: Url) -> String {
+    #[cfg(feature = "url")]
+    return url.into();
+    #[cfg(not(feature = "url"))]
+    url
+}
+
 /// A rich value type that represents all data types supported by superjson.
 ///
 /// This extends standard JSON types with additional types like `Date`, `BigInt`,
 /// `Set`, `Map`, `undefined`, special numbers, and `RegExp`.
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone)]
 pub enum Value {
     // Standard JSON types
     Null,
     Bool(bool),
     Number(f64),
+    /// An integer, read from a JSON number without a fraction or exponent or
+    /// converted from a Rust integer. It is kept exactly and written without
+    /// a fractional part, as JavaScript writes integral numbers.
+    Int(i64),
+    /// An integer above `i64::MAX`; smaller ones are [`Value::Int`]s.
+    UInt(u64),
     String(String),
     Array(Vec<Value>),
-    Object(IndexMap<String, Value>),
+    Object(Map<String, Value>),
 
     // Extended types (superjson-specific)
     Undefined,
+    #[cfg(feature = "chrono")]
     Date(DateTime<Utc>),
+    /// A `Date` that keeps the UTC offset it was written with, restored
+    /// instead of a [`Value::Date`] when
+    /// [`ParseOptions::preserve_date_offsets`](crate::ParseOptions::preserve_date_offsets)
+    /// is set. It is written with its offset, e.g.
+    /// `2024-01-01T09:00:00.000+09:00`, which JavaScript's `Date` reads as
+    /// the same instant.
+    #[cfg(feature = "chrono")]
+    OffsetDate(DateTime<FixedOffset>),
+    /// A `Date` that holds no valid date, as `new Date(NaN)` does. Written
+    /// as `"Invalid Date"`, and restored only when
+    /// [`ParseOptions::invalid_dates`](crate::ParseOptions::invalid_dates)
+    /// keeps it.
+    #[cfg(feature = "chrono")]
+    InvalidDate,
+    #[cfg(feature = "bigint")]
     BigInt(BigInt),
     Set(Vec<Value>),
     Map(Vec<(Value, Value)>),
@@ -31,11 +97,48 @@ pub enum Value {
         source: String,
         flags: String,
     },
-    Url(String),
+    Url(Url),
+    /// An `Error`, with the properties beyond `name`, `message` and `cause`
+    /// that were allowed to travel with it in `extra`; see
+    /// [`SerializeOptions::error_props`](crate::SerializeOptions::error_props).
+    /// Its JavaScript stack trace is kept in `stack`, and likewise only
+    /// travels when `"stack"` is allowed.
     Error {
         name: String,
         message: String,
+        stack: Option<String>,
         cause: Option<Box<Value>>,
+        extra: Map<String, Value>,
+    },
+    /// Data written by a custom transformer registered under `name`, in its
+    /// JSON form. Annotated as `["custom", name]`.
+    ///
+    /// `value` is plain JSON data: it cannot contain extended types.
+    Custom {
+        name: String,
+        value: Box<Value>,
+    },
+    /// An instance of a class registered under `name`, as the object of its
+    /// own fields. Annotated as `["class", name]`.
+    Class {
+        name: String,
+        fields: Map<String, Value>,
+    },
+    /// A symbol registered under this identifier. Annotated as
+    /// `["symbol", identifier]`.
+    Symbol(String),
+    /// Binary data, such as a JS `Uint8Array` or Node `Buffer`. Written as a
+    /// string under the custom annotation `["custom", "bytes"]`; see the
+    /// [`bytes`](crate::bytes) module.
+    Bytes(Vec<u8>),
+    /// A value under an annotation this crate does not recognise, kept in
+    /// its JSON form and written back under the same annotation. Restored
+    /// only when
+    /// [`ParseOptions::keep_unknown_types`](crate::ParseOptions::keep_unknown_types)
+    /// is set.
+    Unknown {
+        type_name: String,
+        raw: serde_json::Value,
     },
 }
 
@@ -45,6 +148,8 @@ impl fmt::Display for Value {
             Value::Null => write!(f, "null"),
             Value::Bool(b) => write!(f, "{b}"),
             Value::Number(n) => write!(f, "{n}"),
+            Value::Int(n) => write!(f, "{n}"),
+            Value::UInt(n) => write!(f, "{n}"),
             Value::String(s) => write!(f, "\"{s}\""),
             Value::Array(arr) => {
                 write!(f, "[")?;
@@ -67,7 +172,13 @@ impl fmt::Display for Value {
                 write!(f, "}}")
             }
             Value::Undefined => write!(f, "undefined"),
+            #[cfg(feature = "chrono")]
             Value::Date(dt) => write!(f, "Date({})", dt.to_rfc3339()),
+            #[cfg(feature = "chrono")]
+            Value::OffsetDate(dt) => write!(f, "Date({})", dt.to_rfc3339()),
+            #[cfg(feature = "chrono")]
+            Value::InvalidDate => write!(f, "Invalid Date"),
+            #[cfg(feature = "bigint")]
             Value::BigInt(n) => write!(f, "{n}n"),
             Value::Set(items) => {
                 write!(f, "Set {{")?;
@@ -99,13 +210,32 @@ impl fmt::Display for Value {
                 name,
                 message,
                 cause,
+                extra,
+                ..
             } => {
                 write!(f, "{name}(\"{message}\")")?;
+                for (k, v) in extra {
+                    write!(f, " {k}: {v}")?;
+                }
                 if let Some(c) = cause {
                     write!(f, " caused by {c}")?;
                 }
                 Ok(())
             }
+            Value::Custom { name, value } => write!(f, "{name}({value})"),
+            Value::Symbol(id) => write!(f, "Symbol({id})"),
+            Value::Bytes(bytes) => write!(f, "Uint8Array({})", bytes.len()),
+            Value::Unknown { type_name, raw } => write!(f, "{type_name}({raw})"),
+            Value::Class { name, fields } => {
+                write!(f, "{name} {{")?;
+                for (i, (k, v)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "\"{k}\": {v}")?;
+                }
+                write!(f, "}}")
+            }
         }
     }
 }
@@ -120,13 +250,27 @@ impl From<bool> for Value {
 
 impl From<i32> for Value {
     fn from(n: i32) -> Self {
-        Value::Number(f64::from(n))
+        Value::Int(n.into())
     }
 }
 
 impl From<i64> for Value {
     fn from(n: i64) -> Self {
-        Value::Number(n as f64)
+        Value::Int(n)
+    }
+}
+
+impl From<u32> for Value {
+    fn from(n: u32) -> Self {
+        Value::Int(n.into())
+    }
+}
+
+/// An [`Int`](Value::Int) if `n` fits in an `i64`, else a
+/// [`UInt`](Value::UInt).
+impl From<u64> for Value {
+    fn from(n: u64) -> Self {
+        i64::try_from(n).map_or(Value::UInt(n), Value::Int)
     }
 }
 
@@ -166,19 +310,544 @@ impl<T: Into<Value>> From<Vec<T>> for Value {
     }
 }
 
+/// Lifts a plain JSON tree, keeping the order of object keys.
+///
+/// Integers are kept exactly, as [`Value::Int`] or [`Value::UInt`], and
+/// other numbers become [`Value::Number`]; everything else maps to the JSON
+/// variant of the same name. Unlike [`Value::from_json_str`], nesting is not
+/// limited.
+impl From<serde_json::Value> for Value {
+    fn from(json: serde_json::Value) -> Self {
+        use serde_json::Value as Json;
+
+        /// A container whose elements are being converted.
+        enum Frame {
+            Array(Vec<Value>, std::vec::IntoIter<Json>),
+            Object(Map<String, Value>, serde_json::map::IntoIter, String),
+        }
+
+        let mut stack = Vec::new();
+        let mut next = json;
+        loop {
+            let mut done = match next {
+                Json::Null => Value::Null,
+                Json::Bool(b) => Value::Bool(b),
+                Json::Number(n) => json_number(&n),
+                Json::String(s) => Value::String(s),
+                Json::Array(items) => {
+                    let len = items.len();
+                    let mut items = items.into_iter();
+                    match items.next() {
+                        Some(first) => {
+                            stack.push(Frame::Array(Vec::with_capacity(len), items));
+                            next = first;
+                            continue;
+                        }
+                        None => Value::Array(Vec::new()),
+                    }
+                }
+                Json::Object(map) => {
+                    let mut entries = map.into_iter();
+                    match entries.next() {
+                        Some((key, first)) => {
+                            stack.push(Frame::Object(Map::new(), entries, key));
+                            next = first;
+                            continue;
+                        }
+                        None => Value::Object(Map::new()),
+                    }
+                }
+            };
+
+            // Hand the finished value to its parent, finishing every parent
+            // that has no elements left.
+            loop {
+                match stack.last_mut() {
+                    None => return done,
+                    Some(Frame::Array(items, rest)) => {
+                        items.push(done);
+                        if let Some(item) = rest.next() {
+                            next = item;
+                            break;
+                        }
+                    }
+                    Some(Frame::Object(map, rest, key)) => {
+                        map.insert(std::mem::take(key), done);
+                        if let Some((k, item)) = rest.next() {
+                            *key = k;
+                            next = item;
+                            break;
+                        }
+                    }
+                }
+                done = match stack.pop() {
+                    Some(Frame::Array(items, _)) => Value::Array(items),
+                    Some(Frame::Object(map, _, _)) => Value::Object(map),
+                    None => unreachable!("the stack was just checked"),
+                };
+            }
+        }
+    }
+}
+
+/// The value of a JSON number: integers exactly, as [`Value::Int`] or
+/// [`Value::UInt`], and anything else as a [`Value::Number`].
+pub(crate) fn json_number(n: &serde_json::Number) -> Value {
+    if let Some(i) = n.as_i64() {
+        Value::Int(i)
+    } else if let Some(u) = n.as_u64() {
+        Value::UInt(u)
+    } else {
+        Value::Number(n.as_f64().unwrap_or(f64::NAN))
+    }
+}
+
+#[cfg(feature = "chrono")]
 impl From<DateTime<Utc>> for Value {
     fn from(dt: DateTime<Utc>) -> Self {
         Value::Date(dt)
     }
 }
 
+#[cfg(feature = "chrono")]
+impl From<DateTime<FixedOffset>> for Value {
+    fn from(dt: DateTime<FixedOffset>) -> Self {
+        Value::OffsetDate(dt)
+    }
+}
+
+/// How a [`Value::InvalidDate`] is written, as JavaScript's
+/// `String(new Date(NaN))` reads.
+#[cfg(feature = "chrono")]
+pub(crate) const INVALID_DATE: &str = "Invalid Date";
+
+/// The custom type name of a calendar date without a time zone, such as
+/// `2024-03-01`, matching `Temporal.PlainDate`.
+#[cfg(feature = "chrono")]
+pub const PLAIN_DATE: &str = "PlainDate";
+
+/// The custom type name of a wall-clock time without a time zone, such as
+/// `09:30:00`, matching `Temporal.PlainTime`.
+#[cfg(feature = "chrono")]
+pub const PLAIN_TIME: &str = "PlainTime";
+
+/// The custom type name of a date and time without a time zone, such as
+/// `2024-03-01T09:30:00`, matching `Temporal.PlainDateTime`.
+#[cfg(feature = "chrono")]
+pub const PLAIN_DATE_TIME: &str = "PlainDateTime";
+
+/// The custom type name of a UUID, held as its hyphenated lowercase text.
+///
+/// To exchange UUIDs under another name, register `uuid::Uuid` with
+/// [`SuperJsonCodec::register_custom`](crate::SuperJsonCodec::register_custom)
+/// instead.
+#[cfg(feature = "uuid")]
+pub const UUID: &str = "uuid";
+
+/// The custom type name of a duration, held as ISO 8601 text such as
+/// `PT90.5S`, matching `Temporal.Duration`.
+pub const DURATION: &str = "Duration";
+
+/// The custom type name of a decimal number, held as its text, matching the
+/// usual `registerCustom` for Decimal.js:
+///
+/// ```js
+/// SuperJSON.registerCustom<Decimal, string>(
+///   { isApplicable: (v) => Decimal.isDecimal(v), serialize: (v) => v.toJSON(), deserialize: (v) => new Decimal(v) },
+///   'Decimal',
+/// );
+/// ```
+///
+/// Apps registering Decimal.js under another name can register
+/// `rust_decimal::Decimal` with
+/// [`SuperJsonCodec::register_custom`](crate::SuperJsonCodec::register_custom)
+/// instead.
+#[cfg(feature = "rust_decimal")]
+pub const DECIMAL: &str = "Decimal";
+
+/// Converts to a [`PLAIN_DATE`] custom value holding the ISO 8601 date.
+#[cfg(feature = "chrono")]
+impl From<NaiveDate> for Value {
+    fn from(date: NaiveDate) -> Self {
+        plain(PLAIN_DATE, date.format("%Y-%m-%d").to_string())
+    }
+}
+
+/// Converts to a [`PLAIN_TIME`] custom value holding the ISO 8601 time.
+#[cfg(feature = "chrono")]
+impl From<NaiveTime> for Value {
+    fn from(time: NaiveTime) -> Self {
+        plain(PLAIN_TIME, time.format("%H:%M:%S%.f").to_string())
+    }
+}
+
+/// Converts to a [`PLAIN_DATE_TIME`] custom value holding the ISO 8601 date
+/// and time.
+#[cfg(feature = "chrono")]
+impl From<NaiveDateTime> for Value {
+    fn from(dt: NaiveDateTime) -> Self {
+        plain(
+            PLAIN_DATE_TIME,
+            dt.format("%Y-%m-%dT%H:%M:%S%.f").to_string(),
+        )
+    }
+}
+
+/// Converts to a [`UUID`] custom value.
+///
+/// # Examples
+/// ```
+/// use superjson_rs::Value;
+///
+/// let id = uuid::Uuid::parse_str("67E55044-10B1-426F-9247-BB680E5FE0C8").unwrap();
+/// let text = superjson_rs::stringify(&Value::from(id)).unwrap();
+/// assert_eq!(
+///     text,
+///     r#"{"json":"67e55044-10b1-426f-9247-bb680e5fe0c8","meta":{"values":[["custom","uuid"]],"v":1}}"#
+/// );
+/// assert_eq!(uuid::Uuid::try_from(superjson_rs::parse(&text).unwrap()).unwrap(), id);
+/// ```
+#[cfg(feature = "uuid")]
+impl From<uuid::Uuid> for Value {
+    fn from(uuid: uuid::Uuid) -> Self {
+        plain(UUID, uuid.to_string())
+    }
+}
+
+/// Converts to a [`DECIMAL`] custom value holding the number's text, which
+/// keeps its scale: `1.50` stays `"1.50"`.
+#[cfg(feature = "rust_decimal")]
+impl From<rust_decimal::Decimal> for Value {
+    fn from(n: rust_decimal::Decimal) -> Self {
+        plain(DECIMAL, n.to_string())
+    }
+}
+
+/// Converts to a [`DURATION`] custom value holding the duration in seconds,
+/// e.g. `PT90.5S`.
+///
+/// # Examples
+/// ```
+/// use std::time::Duration;
+/// use superjson_rs::Value;
+///
+/// let text = superjson_rs::stringify(&Value::from(Duration::from_millis(1500))).unwrap();
+/// assert_eq!(text, r#"{"json":"PT1.5S","meta":{"values":[["custom","Duration"]],"v":1}}"#);
+/// let value = superjson_rs::parse(r#"{"json":"PT1M30S","meta":{"values":[["custom","Duration"]]}}"#).unwrap();
+/// assert_eq!(value.as_duration(), Some(Duration::from_secs(90)));
+/// ```
+impl From<std::time::Duration> for Value {
+    fn from(duration: std::time::Duration) -> Self {
+        plain(DURATION, crate::duration::format(false, duration))
+    }
+}
+
+/// Converts to a [`DURATION`] custom value, e.g. `-PT5S`.
+#[cfg(feature = "chrono")]
+impl From<chrono::TimeDelta> for Value {
+    fn from(delta: chrono::TimeDelta) -> Self {
+        let negative = delta < chrono::TimeDelta::zero();
+        let magnitude = delta.abs().to_std().expect("the magnitude is non-negative");
+        plain(DURATION, crate::duration::format(negative, magnitude))
+    }
+}
+
+fn plain(name: &str, text: String) -> Value {
+    Value::Custom {
+        name: name.to_string(),
+        value: Box::new(Value::String(text)),
+    }
+}
+
+/// Converts to UTC; the original offset is not preserved, matching how a
+/// JavaScript `Date` only stores an instant. Fails for years beyond
+/// chrono's range, which `time`'s `large-dates` feature allows.
+#[cfg(feature = "time")]
+impl TryFrom<time::OffsetDateTime> for Value {
+    type Error = crate::Error;
+
+    fn try_from(dt: time::OffsetDateTime) -> crate::Result<Value> {
+        DateTime::from_timestamp(dt.unix_timestamp(), dt.nanosecond())
+            .map(Value::Date)
+            .ok_or_else(|| crate::Error::InvalidDate(format!("{dt} is out of range")))
+    }
+}
+
+#[cfg(feature = "bigint")]
 impl From<BigInt> for Value {
     fn from(n: BigInt) -> Self {
         Value::BigInt(n)
     }
 }
 
+fn mismatch(expected: &str, value: &Value) -> crate::Error {
+    crate::Error::TypeMismatch {
+        path: String::new(),
+        expected: expected.to_string(),
+        actual: value.type_name().to_string(),
+    }
+}
+
+/// # Errors
+/// Returns [`Error::TypeMismatch`](crate::Error::TypeMismatch), naming the
+/// variant found, for anything but a boolean.
+///
+/// # Examples
+/// ```
+/// use superjson_rs::{Error, Value};
+///
+/// assert_eq!(bool::try_from(Value::Bool(true)).unwrap(), true);
+/// let err = bool::try_from(Value::Undefined).unwrap_err();
+/// assert!(matches!(err, Error::TypeMismatch { actual, .. } if actual == "undefined"));
+/// ```
+impl TryFrom<Value> for bool {
+    type Error = crate::Error;
+
+    fn try_from(value: Value) -> crate::Result<bool> {
+        match value {
+            Value::Bool(b) => Ok(b),
+            other => Err(mismatch("boolean", &other)),
+        }
+    }
+}
+
+/// Accepts `NaN`, the infinities and `-0` as well as plain numbers, and
+/// integers, rounded to the nearest `f64`.
+impl TryFrom<Value> for f64 {
+    type Error = crate::Error;
+
+    fn try_from(value: Value) -> crate::Result<f64> {
+        match value {
+            Value::Number(n) => Ok(n),
+            Value::Int(n) => Ok(n as f64),
+            Value::UInt(n) => Ok(n as f64),
+            Value::NaN => Ok(f64::NAN),
+            Value::PosInfinity => Ok(f64::INFINITY),
+            Value::NegInfinity => Ok(f64::NEG_INFINITY),
+            Value::NegZero => Ok(-0.0),
+            other => Err(mismatch("number", &other)),
+        }
+    }
+}
+
+/// Accepts an integer, a number without a fractional part, or a bigint,
+/// that fits in an `i64`.
+impl TryFrom<Value> for i64 {
+    type Error = crate::Error;
+
+    fn try_from(value: Value) -> crate::Result<i64> {
+        const EXPECTED: &str = "an integer within the range of i64";
+        match value {
+            Value::Int(n) => Ok(n),
+            // `i64::MAX as f64` rounds up to 2^63, which is out of range.
+            Value::Number(n) if n.fract() == 0.0 && n >= i64::MIN as f64 && n < i64::MAX as f64 => {
+                Ok(n as i64)
+            }
+            #[cfg(feature = "bigint")]
+            Value::BigInt(ref n) => i64::try_from(n).map_err(|_| mismatch(EXPECTED, &value)),
+            other => Err(mismatch(EXPECTED, &other)),
+        }
+    }
+}
+
+impl TryFrom<Value> for String {
+    type Error = crate::Error;
+
+    fn try_from(value: Value) -> crate::Result<String> {
+        match value {
+            Value::String(s) => Ok(s),
+            other => Err(mismatch("string", &other)),
+        }
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl TryFrom<Value> for DateTime<Utc> {
+    type Error = crate::Error;
+
+    fn try_from(value: Value) -> crate::Result<DateTime<Utc>> {
+        match value {
+            Value::Date(dt) => Ok(dt),
+            Value::OffsetDate(dt) => Ok(dt.to_utc()),
+            Value::InvalidDate => Err(crate::Error::InvalidDate(INVALID_DATE.to_string())),
+            other => Err(mismatch("Date", &other)),
+        }
+    }
+}
+
+/// Keeps the offset of an [`OffsetDate`](Value::OffsetDate); a `Date` is
+/// in UTC.
+#[cfg(feature = "time")]
+impl TryFrom<Value> for time::OffsetDateTime {
+    type Error = crate::Error;
+
+    fn try_from(value: Value) -> crate::Result<time::OffsetDateTime> {
+        match value.as_offset_date() {
+            Some(dt) => value
+                .as_offset_datetime()
+                .ok_or_else(|| crate::Error::InvalidDate(format!("{dt} is out of range"))),
+            None => Err(mismatch("Date", &value)),
+        }
+    }
+}
+
+/// Accepts a [`UUID`] custom value, or a string holding a UUID in any of the
+/// forms `uuid::Uuid::parse_str` reads.
+#[cfg(feature = "uuid")]
+impl TryFrom<Value> for uuid::Uuid {
+    type Error = crate::Error;
+
+    fn try_from(value: Value) -> crate::Result<uuid::Uuid> {
+        let text = match &value {
+            Value::String(s) => Some(s.as_str()),
+            other => other.plain_text(UUID),
+        };
+        text.and_then(|s| s.parse().ok())
+            .ok_or_else(|| mismatch("uuid", &value))
+    }
+}
+
+/// Accepts a [`DECIMAL`] custom value or a string, in plain or exponential
+/// notation as Decimal.js writes very large and small numbers.
+///
+/// # Examples
+/// ```
+/// use rust_decimal::Decimal;
+/// use superjson_rs::Value;
+///
+/// let text = r#"{"json":{"total":"19.90","tiny":"1e-7"},"meta":{"values":{"total":[["custom","Decimal"]],"tiny":[["custom","Decimal"]]},"v":1}}"#;
+/// let value = superjson_rs::parse(text).unwrap();
+/// let total = Decimal::try_from(value.pointer("total").unwrap().clone()).unwrap();
+/// assert_eq!(total.to_string(), "19.90");
+/// assert_eq!(value.pointer("tiny").unwrap().as_decimal(), Some(Decimal::new(1, 7)));
+/// ```
+#[cfg(feature = "rust_decimal")]
+impl TryFrom<Value> for rust_decimal::Decimal {
+    type Error = crate::Error;
+
+    fn try_from(value: Value) -> crate::Result<rust_decimal::Decimal> {
+        let text = match &value {
+            Value::String(s) => Some(s.as_str()),
+            other => other.plain_text(DECIMAL),
+        };
+        text.and_then(parse_decimal)
+            .ok_or_else(|| mismatch("Decimal", &value))
+    }
+}
+
+#[cfg(feature = "rust_decimal")]
+fn parse_decimal(s: &str) -> Option<rust_decimal::Decimal> {
+    s.parse()
+        .ok()
+        .or_else(|| rust_decimal::Decimal::from_scientific(s).ok())
+}
+
+/// Accepts a [`DURATION`] custom value or a string holding a non-negative
+/// ISO 8601 duration of weeks, days, hours, minutes and seconds.
+impl TryFrom<Value> for std::time::Duration {
+    type Error = crate::Error;
+
+    fn try_from(value: Value) -> crate::Result<std::time::Duration> {
+        match duration_text(&value).and_then(crate::duration::parse) {
+            Some((negative, duration)) if !negative || duration.is_zero() => Ok(duration),
+            _ => Err(mismatch("non-negative Duration", &value)),
+        }
+    }
+}
+
+/// Accepts a [`DURATION`] custom value or a string holding an ISO 8601
+/// duration of weeks, days, hours, minutes and seconds.
+#[cfg(feature = "chrono")]
+impl TryFrom<Value> for chrono::TimeDelta {
+    type Error = crate::Error;
+
+    fn try_from(value: Value) -> crate::Result<chrono::TimeDelta> {
+        let (negative, duration) = duration_text(&value)
+            .and_then(crate::duration::parse)
+            .ok_or_else(|| mismatch("Duration", &value))?;
+        let delta = chrono::TimeDelta::from_std(duration)
+            .map_err(|_| mismatch("Duration within chrono's range", &value))?;
+        Ok(if negative { -delta } else { delta })
+    }
+}
+
+fn duration_text(value: &Value) -> Option<&str> {
+    match value {
+        Value::String(s) => Some(s),
+        other => other.plain_text(DURATION),
+    }
+}
+
+#[cfg(feature = "bigint")]
+impl TryFrom<Value> for BigInt {
+    type Error = crate::Error;
+
+    fn try_from(value: Value) -> crate::Result<BigInt> {
+        match value {
+            Value::BigInt(n) => Ok(n),
+            other => Err(mismatch("bigint", &other)),
+        }
+    }
+}
+
+/// Accepts an array; a Set is a different type and is not converted.
+impl TryFrom<Value> for Vec<Value> {
+    type Error = crate::Error;
+
+    fn try_from(value: Value) -> crate::Result<Vec<Value>> {
+        match value {
+            Value::Array(items) => Ok(items),
+            other => Err(mismatch("array", &other)),
+        }
+    }
+}
+
+impl TryFrom<Value> for Map<String, Value> {
+    type Error = crate::Error;
+
+    fn try_from(value: Value) -> crate::Result<Map<String, Value>> {
+        match value {
+            Value::Object(map) => Ok(map),
+            other => Err(mismatch("object", &other)),
+        }
+    }
+}
+
 impl Value {
+    /// The JavaScript name of this value's type, e.g. `"Date"` or `"bigint"`.
+    ///
+    /// Special numbers are named after their value (`"NaN"`, `"Infinity"`,
+    /// `"-Infinity"`, `"-0"`).
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Null => "null",
+            Value::Bool(_) => "boolean",
+            Value::Number(_) | Value::Int(_) | Value::UInt(_) => "number",
+            Value::String(_) => "string",
+            Value::Array(_) => "array",
+            Value::Object(_) => "object",
+            Value::Class { .. } => "class",
+            Value::Symbol(_) => "symbol",
+            Value::Bytes(_) => "Uint8Array",
+            Value::Undefined => "undefined",
+            #[cfg(feature = "chrono")]
+            Value::Date(_) | Value::OffsetDate(_) | Value::InvalidDate => "Date",
+            #[cfg(feature = "bigint")]
+            Value::BigInt(_) => "bigint",
+            Value::Set(_) => "Set",
+            Value::Map(_) => "Map",
+            Value::NaN => "NaN",
+            Value::PosInfinity => "Infinity",
+            Value::NegInfinity => "-Infinity",
+            Value::NegZero => "-0",
+            Value::RegExp { .. } => "RegExp",
+            Value::Url(_) => "URL",
+            Value::Error { .. } => "Error",
+            Value::Custom { .. } => "custom",
+            Value::Unknown { .. } => "unknown",
+        }
+    }
+
     pub fn is_null(&self) -> bool {
         matches!(self, Value::Null)
     }
@@ -194,9 +863,32 @@ impl Value {
         }
     }
 
+    /// The number, with integers rounded to the nearest `f64`.
     pub fn as_f64(&self) -> Option<f64> {
         match self {
             Value::Number(n) => Some(*n),
+            Value::Int(n) => Some(*n as f64),
+            Value::UInt(n) => Some(*n as f64),
+            _ => None,
+        }
+    }
+
+    /// The integer, if this is an [`Int`](Value::Int) or a
+    /// [`UInt`](Value::UInt) that fits in an `i64`.
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Value::Int(n) => Some(*n),
+            Value::UInt(n) => i64::try_from(*n).ok(),
+            _ => None,
+        }
+    }
+
+    /// The integer, if this is a non-negative [`Int`](Value::Int) or a
+    /// [`UInt`](Value::UInt).
+    pub fn as_u64(&self) -> Option<u64> {
+        match self {
+            Value::Int(n) => u64::try_from(*n).ok(),
+            Value::UInt(n) => Some(*n),
             _ => None,
         }
     }
@@ -215,10 +907,634 @@ impl Value {
         }
     }
 
-    pub fn as_object(&self) -> Option<&IndexMap<String, Value>> {
+    /// The instant of a `Date`.
+    ///
+    /// Returns `None` for an [`OffsetDate`](Value::OffsetDate); use
+    /// [`as_offset_date`](Self::as_offset_date) to read either kind.
+    #[cfg(feature = "chrono")]
+    pub fn as_date(&self) -> Option<&DateTime<Utc>> {
+        match self {
+            Value::Date(dt) => Some(dt),
+            _ => None,
+        }
+    }
+
+    /// The date and offset of an `OffsetDate`, or of a `Date` at UTC.
+    #[cfg(feature = "chrono")]
+    pub fn as_offset_date(&self) -> Option<DateTime<FixedOffset>> {
+        match self {
+            Value::Date(dt) => Some(dt.fixed_offset()),
+            Value::OffsetDate(dt) => Some(*dt),
+            _ => None,
+        }
+    }
+
+    /// The date of a [`PLAIN_DATE`] custom value.
+    ///
+    /// Returns `None` for any other value, or if the text is not a valid
+    /// ISO 8601 date.
+    #[cfg(feature = "chrono")]
+    pub fn as_naive_date(&self) -> Option<NaiveDate> {
+        self.plain_text(PLAIN_DATE)?.parse().ok()
+    }
+
+    /// The time of a [`PLAIN_TIME`] custom value.
+    #[cfg(feature = "chrono")]
+    pub fn as_naive_time(&self) -> Option<NaiveTime> {
+        self.plain_text(PLAIN_TIME)?.parse().ok()
+    }
+
+    /// The date and time of a [`PLAIN_DATE_TIME`] custom value.
+    #[cfg(feature = "chrono")]
+    pub fn as_naive_date_time(&self) -> Option<NaiveDateTime> {
+        self.plain_text(PLAIN_DATE_TIME)?.parse().ok()
+    }
+
+    /// The UUID of a [`UUID`] custom value.
+    ///
+    /// Returns `None` for any other value, or if the text is not a UUID.
+    #[cfg(feature = "uuid")]
+    pub fn as_uuid(&self) -> Option<uuid::Uuid> {
+        self.plain_text(UUID)?.parse().ok()
+    }
+
+    /// The number of a [`DECIMAL`] custom value.
+    ///
+    /// Returns `None` for any other value, or if the text is not a decimal
+    /// that fits in a `rust_decimal::Decimal`.
+    #[cfg(feature = "rust_decimal")]
+    pub fn as_decimal(&self) -> Option<rust_decimal::Decimal> {
+        parse_decimal(self.plain_text(DECIMAL)?)
+    }
+
+    /// The length of a non-negative [`DURATION`] custom value.
+    ///
+    /// Returns `None` for any other value, or if the text is not an ISO 8601
+    /// duration of a fixed length.
+    pub fn as_duration(&self) -> Option<std::time::Duration> {
+        match crate::duration::parse(self.plain_text(DURATION)?)? {
+            (false, duration) => Some(duration),
+            (true, duration) => duration.is_zero().then_some(duration),
+        }
+    }
+
+    fn plain_text(&self, expected: &str) -> Option<&str> {
+        match self {
+            Value::Custom { name, value } if name == expected => value.as_str(),
+            _ => None,
+        }
+    }
+
+    /// The date as a `time::OffsetDateTime`, in UTC or in the offset of an
+    /// [`OffsetDate`](Value::OffsetDate).
+    ///
+    /// Returns `None` if this is not a `Date`, or if the date lies outside the
+    /// range supported by the `time` crate.
+    #[cfg(feature = "time")]
+    pub fn as_offset_datetime(&self) -> Option<time::OffsetDateTime> {
+        let dt = self.as_offset_date()?;
+        let offset = time::UtcOffset::from_whole_seconds(dt.offset().local_minus_utc()).ok()?;
+        time::OffsetDateTime::from_unix_timestamp(dt.timestamp())
+            .ok()?
+            .replace_nanosecond(dt.timestamp_subsec_nanos())
+            .ok()
+            .map(|dt| dt.to_offset(offset))
+    }
+
+    pub fn as_object(&self) -> Option<&Map<String, Value>> {
         match self {
             Value::Object(map) => Some(map),
             _ => None,
         }
     }
+
+    /// Look up a value by superjson path, such as `"users.0.name"`.
+    ///
+    /// Paths address values as annotations do: object keys, array and set
+    /// indices, `i.0` / `i.1` for the key and value of map entry `i`, field
+    /// names for a class instance, and `cause` or the name of an extra
+    /// property for an Error. The empty path is the value itself.
+    ///
+    /// # Examples
+    /// ```
+    /// use superjson_rs::Value;
+    ///
+    /// let value = Value::Map(vec![(Value::from("k"), Value::Set(vec![Value::from(1)]))]);
+    /// assert_eq!(value.pointer("0.1.0"), Some(&Value::from(1)));
+    /// assert_eq!(value.pointer("1"), None);
+    /// ```
+    pub fn pointer(&self, path: &str) -> Option<&Value> {
+        let mut segments = crate::path::parse(path).into_iter();
+        let mut current = self;
+
+        while let Some(segment) = segments.next() {
+            current = match (current, segment) {
+                (Value::Object(map) | Value::Class { fields: map, .. }, PathSegment::Key(key)) => {
+                    map.get(&key)?
+                }
+                (
+                    Value::Object(map) | Value::Class { fields: map, .. },
+                    PathSegment::Index(index),
+                ) => map.get(&index.to_string())?,
+                (Value::Array(items) | Value::Set(items), PathSegment::Index(index)) => {
+                    items.get(index)?
+                }
+                (Value::Map(entries), PathSegment::Index(index)) => {
+                    let (key, value) = entries.get(index)?;
+                    match segments.next()? {
+                        PathSegment::Index(0) => key,
+                        PathSegment::Index(1) => value,
+                        _ => return None,
+                    }
+                }
+                (Value::Error { cause, .. }, PathSegment::Key(key)) if key == "cause" => {
+                    cause.as_deref()?
+                }
+                (Value::Error { extra, .. }, PathSegment::Key(key)) => extra.get(&key)?,
+                _ => return None,
+            };
+        }
+        Some(current)
+    }
+
+    /// Parse bare JSON, without a superjson envelope.
+    ///
+    /// Only JSON types can result; nesting is limited to
+    /// [`DEFAULT_MAX_DEPTH`](crate::DEFAULT_MAX_DEPTH) levels.
+    ///
+    /// # Examples
+    /// ```
+    /// use superjson_rs::Value;
+    ///
+    /// let value = Value::from_json_str(r#"{"ids":[1,2]}"#).unwrap();
+    /// assert_eq!(value.pointer("ids.1"), Some(&Value::from(2)));
+    /// ```
+    pub fn from_json_str(s: &str) -> crate::Result<Value> {
+        Value::from_json_checked(serde_json::from_str(s)?)
+    }
+
+    /// Read bare JSON, without a superjson envelope, as
+    /// [`from_json_str`](Self::from_json_str) does.
+    pub fn from_json_reader(reader: impl std::io::Read) -> crate::Result<Value> {
+        Value::from_json_checked(serde_json::from_reader(reader)?)
+    }
+
+    /// Lift a plain JSON tree, as [`From<serde_json::Value>`] does.
+    ///
+    /// # Examples
+    /// ```
+    /// use serde_json::json;
+    /// use superjson_rs::Value;
+    ///
+    /// let mut value = Value::from_json(json!({
+    ///     "ids": [1, 2],
+    ///     "big": 9007199254740993u64,
+    ///     "max": u64::MAX,
+    /// }));
+    /// assert!(matches!(value.pointer("big"), Some(Value::Int(9007199254740993))));
+    /// assert!(matches!(value.pointer("max"), Some(Value::UInt(u64::MAX))));
+    /// if let Value::Object(map) = &mut value {
+    ///     map.insert("tags".to_string(), Value::Set(vec![Value::from("new")]));
+    /// }
+    /// assert_eq!(value.pointer("ids.1"), Some(&Value::from(2)));
+    /// assert_eq!(value.type_name(), "object");
+    /// ```
+    pub fn from_json(json: serde_json::Value) -> Value {
+        Value::from(json)
+    }
+
+    /// Restore bare JSON, limited to [`DEFAULT_MAX_DEPTH`](crate::DEFAULT_MAX_DEPTH)
+    /// levels of nesting.
+    pub(crate) fn from_json_checked(json: serde_json::Value) -> crate::Result<Value> {
+        crate::deserialize::deserialize(&crate::SuperJson { json, meta: None })
+    }
+
+    /// Write this value as bare JSON, without a superjson envelope.
+    ///
+    /// Extended types are written in their JSON-compatible encodings, as in
+    /// the `json` part of an envelope, and read back as plain values: a
+    /// `Date` becomes a string. Check [`is_plain_json`](Self::is_plain_json)
+    /// first where that matters.
+    ///
+    /// # Examples
+    /// ```
+    /// use superjson_rs::Value;
+    ///
+    /// let mut out = Vec::new();
+    /// Value::from(vec![Value::from("a"), Value::Set(vec![])]).to_json_writer(&mut out).unwrap();
+    /// assert_eq!(out, br#"["a",[]]"#);
+    /// ```
+    pub fn to_json_writer(&self, writer: impl std::io::Write) -> crate::Result<()> {
+        let superjson = crate::serialize::serialize(self)?;
+        serde_json::to_writer(writer, &superjson.json)?;
+        Ok(())
+    }
+
+    /// Whether this value is made only of JSON types, so that it needs no
+    /// superjson envelope.
+    ///
+    /// # Examples
+    /// ```
+    /// use superjson_rs::Value;
+    ///
+    /// assert!(Value::from(vec![1, 2]).is_plain_json());
+    /// assert!(!Value::from(vec![Value::Null, Value::Undefined]).is_plain_json());
+    /// ```
+    pub fn is_plain_json(&self) -> bool {
+        let mut stack = vec![self];
+        while let Some(value) = stack.pop() {
+            match value {
+                Value::Null
+                | Value::Bool(_)
+                | Value::Number(_)
+                | Value::Int(_)
+                | Value::UInt(_)
+                | Value::String(_) => {}
+                Value::Array(items) => stack.extend(items),
+                Value::Object(map) => stack.extend(map.values()),
+                _ => return false,
+            }
+        }
+        true
+    }
+
+    /// The paths of all extended-type values within this one, with their
+    /// [`type_name`](Self::type_name)s, in document order.
+    ///
+    /// Paths are those annotations use (see [`pointer`](Self::pointer)).
+    /// Values inside extended containers, such as the members of a `Set`, are
+    /// listed too; the payload of a custom value is not.
+    ///
+    /// # Examples
+    /// ```
+    /// use superjson_rs::Value;
+    ///
+    /// let value = Value::from(vec![
+    ///     Value::from(1),
+    ///     Value::Set(vec![Value::NaN]),
+    ///     Value::Undefined,
+    /// ]);
+    /// assert_eq!(
+    ///     value.extended_paths(),
+    ///     [
+    ///         ("1".to_string(), "Set"),
+    ///         ("1.0".to_string(), "NaN"),
+    ///         ("2".to_string(), "undefined"),
+    ///     ]
+    /// );
+    /// ```
+    pub fn extended_paths(&self) -> Vec<(String, &'static str)> {
+        let mut found = Vec::new();
+        let mut stack = vec![(String::new(), self)];
+
+        while let Some((path, value)) = stack.pop() {
+            let child = |key: &str| {
+                if path.is_empty() {
+                    key.to_string()
+                } else {
+                    format!("{path}.{key}")
+                }
+            };
+            let mut children: Vec<(String, &Value)> = match value {
+                Value::Array(items) | Value::Set(items) => items
+                    .iter()
+                    .enumerate()
+                    .map(|(i, item)| (child(&i.to_string()), item))
+                    .collect(),
+                Value::Object(map) | Value::Class { fields: map, .. } => map
+                    .iter()
+                    .map(|(key, item)| (child(&crate::path::escape_key(key)), item))
+                    .collect(),
+                Value::Map(entries) => entries
+                    .iter()
+                    .enumerate()
+                    .flat_map(|(i, (k, v))| {
+                        [(child(&format!("{i}.0")), k), (child(&format!("{i}.1")), v)]
+                    })
+                    .collect(),
+                Value::Error { cause, extra, .. } => cause
+                    .iter()
+                    .map(|cause| (child("cause"), cause.as_ref()))
+                    .chain(
+                        extra
+                            .iter()
+                            .map(|(key, item)| (child(&crate::path::escape_key(key)), item)),
+                    )
+                    .collect(),
+                _ => Vec::new(),
+            };
+            if !matches!(
+                value,
+                Value::Null
+                    | Value::Bool(_)
+                    | Value::Number(_)
+                    | Value::Int(_)
+                    | Value::UInt(_)
+                    | Value::String(_)
+                    | Value::Array(_)
+                    | Value::Object(_)
+            ) {
+                found.push((path, value.type_name()));
+            }
+            children.reverse();
+            stack.extend(children);
+        }
+        found
+    }
+}
+
+/// Compares a value with its JSON-compatible encoding, as produced in the
+/// `json` part of an envelope.
+///
+/// Values compare structurally, except that [`Number`](Value::Number),
+/// [`Int`](Value::Int) and [`UInt`](Value::UInt) compare by value, as
+/// JavaScript numbers do: `Value::Int(1)` equals `Value::Number(1.0)`.
+///
+/// # Examples
+/// ```
+/// use superjson_rs::Value;
+///
+/// assert_eq!(Value::Int(1), Value::Number(1.0));
+/// assert_eq!(Value::UInt(7), Value::Int(7));
+/// assert_ne!(Value::Int(9_007_199_254_740_993), Value::Number(9_007_199_254_740_992.0));
+/// ```
+impl PartialEq for Value {
+    fn eq(&self, other: &Value) -> bool {
+        match (self, other) {
+            (
+                Value::Number(_) | Value::Int(_) | Value::UInt(_),
+                Value::Number(_) | Value::Int(_) | Value::UInt(_),
+            ) => number_eq(self, other),
+            (Value::Null, Value::Null) => true,
+            (Value::Bool(a), Value::Bool(b)) => a == b,
+            (Value::String(a), Value::String(b)) => a == b,
+            (Value::Array(a), Value::Array(b)) => a == b,
+            (Value::Object(a), Value::Object(b)) => a == b,
+            (Value::Undefined, Value::Undefined) => true,
+            #[cfg(feature = "chrono")]
+            (Value::Date(a), Value::Date(b)) => a == b,
+            #[cfg(feature = "chrono")]
+            (Value::OffsetDate(a), Value::OffsetDate(b)) => a == b,
+            #[cfg(feature = "chrono")]
+            (Value::InvalidDate, Value::InvalidDate) => true,
+            #[cfg(feature = "bigint")]
+            (Value::BigInt(a), Value::BigInt(b)) => a == b,
+            (Value::Set(a), Value::Set(b)) => a == b,
+            (Value::Map(a), Value::Map(b)) => a == b,
+            (Value::NaN, Value::NaN) => true,
+            (Value::PosInfinity, Value::PosInfinity) => true,
+            (Value::NegInfinity, Value::NegInfinity) => true,
+            (Value::NegZero, Value::NegZero) => true,
+            (
+                Value::RegExp { source, flags },
+                Value::RegExp {
+                    source: other_source,
+                    flags: other_flags,
+                },
+            ) => source == other_source && flags == other_flags,
+            (Value::Url(a), Value::Url(b)) => a == b,
+            (
+                Value::Error {
+                    name,
+                    message,
+                    stack,
+                    cause,
+                    extra,
+                },
+                Value::Error {
+                    name: other_name,
+                    message: other_message,
+                    stack: other_stack,
+                    cause: other_cause,
+                    extra: other_extra,
+                },
+            ) => {
+                name == other_name
+                    && message == other_message
+                    && stack == other_stack
+                    && cause == other_cause
+                    && extra == other_extra
+            }
+            (
+                Value::Custom { name, value },
+                Value::Custom {
+                    name: other_name,
+                    value: other_value,
+                },
+            ) => name == other_name && value == other_value,
+            (
+                Value::Class { name, fields },
+                Value::Class {
+                    name: other_name,
+                    fields: other_fields,
+                },
+            ) => name == other_name && fields == other_fields,
+            (Value::Symbol(a), Value::Symbol(b)) => a == b,
+            (Value::Bytes(a), Value::Bytes(b)) => a == b,
+            (
+                Value::Unknown { type_name, raw },
+                Value::Unknown {
+                    type_name: other_type_name,
+                    raw: other_raw,
+                },
+            ) => type_name == other_type_name && raw == other_raw,
+            _ => false,
+        }
+    }
+}
+
+/// Whether two numeric values hold the same number, compared exactly: an
+/// integer only equals an `f64` that represents it without rounding.
+fn number_eq(a: &Value, b: &Value) -> bool {
+    fn exact(value: &Value) -> Option<i128> {
+        match value {
+            Value::Int(n) => Some((*n).into()),
+            Value::UInt(n) => Some((*n).into()),
+            // Integral `f64`s within this range convert without rounding.
+            Value::Number(n) if n.fract() == 0.0 && n.abs() < 2f64.powi(100) => Some(*n as i128),
+            _ => None,
+        }
+    }
+    match (a, b) {
+        (Value::Number(a), Value::Number(b)) => a == b,
+        _ => exact(a).is_some_and(|a| exact(b) == Some(a)),
+    }
+}
+
+/// Plain values compare structurally, with numbers compared by value, so
+/// `1` equals `1.0`. Extended values compare against their encodings: a
+/// `Date` equals its ISO string, a `Set` equals an array, `NaN` equals
+/// `"NaN"`. Annotations are not considered.
+///
+/// # Examples
+/// ```
+/// use serde_json::json;
+/// use superjson_rs::{Value, parse};
+///
+/// let value = parse(r#"{"json":{"ids":[1,2],"n":"NaN"},"meta":{"values":{"ids":["set"],"n":["number"]}}}"#).unwrap();
+/// assert_eq!(value, json!({"ids": [1, 2], "n": "NaN"}));
+/// assert_ne!(value, json!({"ids": [2, 1], "n": "NaN"}));
+/// ```
+impl PartialEq<serde_json::Value> for Value {
+    fn eq(&self, other: &serde_json::Value) -> bool {
+        match crate::serialize::serialize(self) {
+            Ok(superjson) => json_eq(&superjson.json, other),
+            Err(_) => false,
+        }
+    }
+}
+
+impl PartialEq<Value> for serde_json::Value {
+    fn eq(&self, other: &Value) -> bool {
+        other == self
+    }
+}
+
+/// Writes the full superjson envelope, `{"json": ..., "meta": ...}`, so a
+/// `Value` inside any serde type keeps its extended types. Use
+/// [`plain`] for bare JSON instead.
+///
+/// # Examples
+/// ```
+/// use serde::{Deserialize, Serialize};
+/// use superjson_rs::Value;
+///
+/// #[derive(Serialize, Deserialize)]
+/// struct Row {
+///     id: u32,
+///     payload: Value,
+/// }
+///
+/// let row = Row { id: 1, payload: Value::Set(vec![Value::from("a")]) };
+/// let text = serde_json::to_string(&row).unwrap();
+/// assert_eq!(text, r#"{"id":1,"payload":{"json":["a"],"meta":{"values":["set"],"v":1}}}"#);
+///
+/// let row: Row = serde_json::from_str(&text).unwrap();
+/// assert_eq!(row.payload, Value::Set(vec![Value::from("a")]));
+/// ```
+impl serde::Serialize for Value {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::Error;
+
+        let envelope = crate::serialize::serialize(self).map_err(S::Error::custom)?;
+        serializer.serialize_newtype_struct(crate::types::VALUE, &envelope)
+    }
+}
+
+/// Reads a superjson envelope with the default [`ParseOptions`](crate::ParseOptions).
+impl<'de> serde::Deserialize<'de> for Value {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_newtype_struct(crate::types::VALUE, EnvelopeVisitor)
+    }
+}
+
+struct EnvelopeVisitor;
+
+impl EnvelopeVisitor {
+    fn restore<E: serde::de::Error>(raw: crate::envelope::RawSuperJson) -> Result<Value, E> {
+        let options = crate::ParseOptions::default();
+        raw.into_superjson(&options, &mut Vec::new())
+            .and_then(|superjson| crate::deserialize::deserialize_with(&superjson, &options))
+            .map_err(E::custom)
+    }
+}
+
+impl<'de> serde::de::Visitor<'de> for EnvelopeVisitor {
+    type Value = Value;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a superjson envelope")
+    }
+
+    fn visit_newtype_struct<D: serde::Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<Value, D::Error> {
+        EnvelopeVisitor::restore(serde::Deserialize::deserialize(deserializer)?)
+    }
+
+    fn visit_map<A: serde::de::MapAccess<'de>>(self, map: A) -> Result<Value, A::Error> {
+        let deserializer = serde::de::value::MapAccessDeserializer::new(map);
+        EnvelopeVisitor::restore(serde::Deserialize::deserialize(deserializer)?)
+    }
+}
+
+/// `Value` as bare JSON, for `#[serde(with = "superjson_rs::value::plain")]`.
+///
+/// This form is lossy in the way [`Value::to_json_writer`] is: extended
+/// types are written in their JSON-compatible encodings and read back as
+/// plain values.
+///
+/// # Examples
+/// ```
+/// use serde::{Deserialize, Serialize};
+/// use superjson_rs::Value;
+///
+/// #[derive(Serialize, Deserialize)]
+/// struct Config {
+///     #[serde(with = "superjson_rs::value::plain")]
+///     extra: Value,
+/// }
+///
+/// let config = Config { extra: Value::from(vec![Value::NaN]) };
+/// assert_eq!(serde_json::to_string(&config).unwrap(), r#"{"extra":["NaN"]}"#);
+/// ```
+pub mod plain {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use super::Value;
+
+    pub fn serialize<S: Serializer>(value: &Value, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::Error;
+
+        crate::serialize::serialize(value)
+            .map_err(S::Error::custom)?
+            .json
+            .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Value, D::Error> {
+        Ok(Value::from_json(serde_json::Value::deserialize(
+            deserializer,
+        )?))
+    }
+}
+
+/// JSON equality with numbers compared by value rather than representation.
+fn json_eq(a: &serde_json::Value, b: &serde_json::Value) -> bool {
+    use serde_json::Value as Json;
+
+    let mut stack = vec![(a, b)];
+    while let Some(pair) = stack.pop() {
+        match pair {
+            (Json::Number(a), Json::Number(b)) => {
+                if a != b && a.as_f64() != b.as_f64() {
+                    return false;
+                }
+            }
+            (Json::Array(a), Json::Array(b)) => {
+                if a.len() != b.len() {
+                    return false;
+                }
+                stack.extend(a.iter().zip(b));
+            }
+            (Json::Object(a), Json::Object(b)) => {
+                if a.len() != b.len() {
+                    return false;
+                }
+                for (key, a) in a {
+                    match b.get(key) {
+                        Some(b) => stack.push((a, b)),
+                        None => return false,
+                    }
+                }
+            }
+            (a, b) => {
+                if a != b {
+                    return false;
+                }
+            }
+        }
+    }
+    true
 }
@@ -0,0 +1,274 @@
+//! A hash-indexed alternative to `Value::Map`'s entry list.
+//!
+//! [`Value::Map`] stores its entries as a `Vec<(Value, Value)>`, which keeps
+//! insertion order and allows keys of any type, but makes each lookup a
+//! linear scan. [`ValueMap`] keeps both properties and looks keys up by hash.
+//! Convert to it once to make many lookups, and back to a [`Value`] to
+//! serialize.
+
+use std::borrow::Borrow;
+use std::hash::{Hash, Hasher};
+
+use indexmap::IndexMap;
+
+use crate::{Error, Result, Value, canonical};
+
+/// A [`Value`] used as a map key.
+///
+/// Keys compare by content: two keys are equal when their values are equal,
+/// except that objects are compared regardless of key order and any two
+/// `NaN`s inside numbers are equal, as for content hashing.
+#[derive(Debug, Clone)]
+pub struct ValueKey {
+    value: Value,
+    encoding: Vec<u8>,
+}
+
+impl ValueKey {
+    pub fn new(value: Value) -> Self {
+        ValueKey {
+            encoding: canonical::to_bytes(&value),
+            value,
+        }
+    }
+
+    pub fn value(&self) -> &Value {
+        &self.value
+    }
+
+    pub fn into_value(self) -> Value {
+        self.value
+    }
+}
+
+impl PartialEq for ValueKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.encoding == other.encoding
+    }
+}
+
+impl Eq for ValueKey {}
+
+impl Hash for ValueKey {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.encoding.hash(state);
+    }
+}
+
+/// Lets a map be searched by the encoding of a key without building a
+/// [`ValueKey`].
+impl Borrow<[u8]> for ValueKey {
+    fn borrow(&self) -> &[u8] {
+        &self.encoding
+    }
+}
+
+/// An insertion-ordered map with [`Value`] keys and constant-time lookup.
+///
+/// # Examples
+/// ```
+/// use superjson_rs::Value;
+/// use superjson_rs::value_map::ValueMap;
+///
+/// let value = Value::Map(vec![
+///     (Value::from(1), Value::from("one")),
+///     (Value::Set(vec![Value::from(2)]), Value::from("set of two")),
+/// ]);
+/// let map = ValueMap::try_from(value).unwrap();
+/// assert_eq!(map.get(&Value::Set(vec![Value::from(2)])), Some(&Value::from("set of two")));
+/// assert_eq!(map.get(&Value::from("1")), None);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ValueMap {
+    entries: IndexMap<ValueKey, Value>,
+}
+
+impl ValueMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, key: &Value) -> Option<&Value> {
+        self.entries.get(canonical::to_bytes(key).as_slice())
+    }
+
+    pub fn get_mut(&mut self, key: &Value) -> Option<&mut Value> {
+        self.entries.get_mut(canonical::to_bytes(key).as_slice())
+    }
+
+    pub fn contains_key(&self, key: &Value) -> bool {
+        self.entries
+            .contains_key(canonical::to_bytes(key).as_slice())
+    }
+
+    /// Insert an entry, returning the previous value for an equal key.
+    ///
+    /// A new key is appended; an existing key keeps its position.
+    pub fn insert(&mut self, key: Value, value: Value) -> Option<Value> {
+        self.entries.insert(ValueKey::new(key), value)
+    }
+
+    /// Remove an entry, keeping the order of the others.
+    pub fn remove(&mut self, key: &Value) -> Option<Value> {
+        self.entries
+            .shift_remove(canonical::to_bytes(key).as_slice())
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The entries in insertion order.
+    pub fn iter(&self) -> impl Iterator<Item = (&Value, &Value)> {
+        self.entries.iter().map(|(key, value)| (key.value(), value))
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &Value> {
+        self.entries.keys().map(ValueKey::value)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &Value> {
+        self.entries.values()
+    }
+}
+
+/// Later entries replace earlier ones with an equal key.
+///
+/// Keys are equal by content, so this loses entries JavaScript would keep:
+/// `new Map(entries)` tells object, array and `Set` keys apart by identity,
+/// and only merges equal primitives. Use [`ValueMap::try_from`] on a
+/// [`Value::Map`] to be told about such duplicates instead.
+impl FromIterator<(Value, Value)> for ValueMap {
+    fn from_iter<I: IntoIterator<Item = (Value, Value)>>(iter: I) -> Self {
+        let mut map = ValueMap::new();
+        for (key, value) in iter {
+            map.insert(key, value);
+        }
+        map
+    }
+}
+
+impl IntoIterator for ValueMap {
+    type Item = (Value, Value);
+    type IntoIter = std::iter::Map<
+        indexmap::map::IntoIter<ValueKey, Value>,
+        fn((ValueKey, Value)) -> (Value, Value),
+    >;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries
+            .into_iter()
+            .map(|(key, value)| (key.into_value(), value))
+    }
+}
+
+/// Index a [`Value::Map`].
+///
+/// # Errors
+/// Returns [`Error::DuplicateMapKey`] if two entries have equal keys, which
+/// a map restored from JavaScript may have when its keys are objects, and
+/// [`Error::TypeMismatch`] for any other value.
+impl TryFrom<Value> for ValueMap {
+    type Error = Error;
+
+    fn try_from(value: Value) -> Result<Self> {
+        match value {
+            Value::Map(entries) => {
+                let mut map = ValueMap::new();
+                for (key, value) in entries {
+                    let key = ValueKey::new(key);
+                    if map.entries.contains_key(&key) {
+                        return Err(Error::DuplicateMapKey(key.into_value().to_string()));
+                    }
+                    map.entries.insert(key, value);
+                }
+                Ok(map)
+            }
+            other => Err(Error::TypeMismatch {
+                path: String::new(),
+                expected: "Map".to_string(),
+                actual: other.type_name().to_string(),
+            }),
+        }
+    }
+}
+
+impl From<ValueMap> for Value {
+    fn from(map: ValueMap) -> Self {
+        Value::Map(map.into_iter().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::value::Map;
+
+    #[test]
+    fn test_keys_compare_by_content() {
+        let object = |pairs: &[(&str, i32)]| {
+            Value::Object(
+                pairs
+                    .iter()
+                    .map(|(k, v)| (k.to_string(), Value::from(*v)))
+                    .collect::<Map<_, _>>(),
+            )
+        };
+        let mut map = ValueMap::new();
+        map.insert(object(&[("a", 1), ("b", 2)]), Value::from("first"));
+        assert_eq!(
+            map.get(&object(&[("b", 2), ("a", 1)])),
+            Some(&Value::from("first"))
+        );
+        assert!(!map.contains_key(&object(&[("a", 1)])));
+        assert!(!map.contains_key(&Value::NegZero));
+
+        map.insert(Value::Number(f64::NAN), Value::Null);
+        assert!(map.contains_key(&Value::Number(f64::NAN)));
+    }
+
+    #[test]
+    fn test_insertion_order_and_round_trip() {
+        let mut map: ValueMap = [
+            (Value::from(2), Value::from("b")),
+            (Value::from(1), Value::from("a")),
+            (Value::from(3), Value::from("c")),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(
+            map.insert(Value::from(2), Value::from("B")),
+            Some(Value::from("b"))
+        );
+        assert_eq!(map.remove(&Value::from(1)), Some(Value::from("a")));
+
+        let value = Value::from(map);
+        assert_eq!(
+            value,
+            Value::Map(vec![
+                (Value::from(2), Value::from("B")),
+                (Value::from(3), Value::from("c")),
+            ])
+        );
+        assert!(ValueMap::try_from(Value::Null).is_err());
+    }
+
+    #[test]
+    fn test_content_equal_keys() {
+        let set = || Value::Set(vec![Value::from(1)]);
+        let entries = vec![(set(), Value::from("a")), (set(), Value::from("b"))];
+
+        let map: ValueMap = entries.clone().into_iter().collect();
+        assert_eq!(map.len(), 1);
+        assert_eq!(map.get(&set()), Some(&Value::from("b")));
+
+        assert!(matches!(
+            ValueMap::try_from(Value::Map(entries)),
+            Err(Error::DuplicateMapKey(key)) if key == set().to_string()
+        ));
+    }
+}
@@ -0,0 +1,104 @@
+use thiserror::Error;
+
+use crate::diff::{Difference, diff};
+use crate::{Value, parse, stringify};
+
+/// Why a value failed to survive a stringify/parse roundtrip.
+#[derive(Debug, Error)]
+pub enum RoundtripReport {
+    #[error("stringify failed: {0}")]
+    Stringify(crate::Error),
+
+    #[error("parse of the stringified envelope failed: {error}")]
+    Parse {
+        error: crate::Error,
+        envelope: String,
+    },
+
+    #[error("value changed after roundtrip at {difference}")]
+    Mismatch {
+        /// The first divergent path, in document order.
+        difference: Box<Difference>,
+        envelope: String,
+    },
+}
+
+/// Stringify `value`, parse the result, and check that nothing changed.
+///
+/// Intended as a debug assertion when adding new value types or
+/// transformers: the report names the first path whose value did not
+/// survive, along with the envelope that was produced.
+///
+/// # Examples
+/// ```
+/// use superjson_rs::{RoundtripReport, Value, verify_roundtrip};
+///
+/// assert!(verify_roundtrip(&Value::Set(vec![Value::Undefined])).is_ok());
+///
+/// // A raw NaN inside `Number` is not representable in JSON.
+/// let report = verify_roundtrip(&Value::Array(vec![Value::Number(f64::NAN)])).unwrap_err();
+/// assert!(matches!(report, RoundtripReport::Mismatch { .. }));
+/// ```
+pub fn verify_roundtrip(value: &Value) -> Result<(), RoundtripReport> {
+    let envelope = stringify(value).map_err(RoundtripReport::Stringify)?;
+    let parsed = match parse(&envelope) {
+        Ok(parsed) => parsed,
+        Err(error) => return Err(RoundtripReport::Parse { error, envelope }),
+    };
+
+    match diff(value, &parsed).into_iter().next() {
+        Some(difference) => Err(RoundtripReport::Mismatch {
+            difference: Box::new(difference),
+            envelope,
+        }),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diff::Change;
+    use crate::path::PathSegment;
+    use crate::value::Map;
+    #[cfg(feature = "chrono")]
+    use chrono::TimeZone;
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn test_verify_roundtrip_ok() {
+        let mut obj = Map::new();
+        obj.insert(
+            "date".to_string(),
+            Value::Date(chrono::Utc.timestamp_millis_opt(0).unwrap()),
+        );
+        obj.insert("tags".to_string(), Value::Set(vec![Value::from("a")]));
+        assert!(verify_roundtrip(&Value::Object(obj)).is_ok());
+    }
+
+    #[test]
+    fn test_verify_roundtrip_reports_first_divergent_path() {
+        let mut obj = Map::new();
+        obj.insert("ok".to_string(), Value::from(1));
+        obj.insert("inf".to_string(), Value::Number(f64::INFINITY));
+        let report = verify_roundtrip(&Value::Object(obj)).unwrap_err();
+
+        match report {
+            RoundtripReport::Mismatch {
+                difference,
+                envelope,
+            } => {
+                assert_eq!(difference.path, vec![PathSegment::Key("inf".into())]);
+                assert!(matches!(
+                    difference.change,
+                    Change::Replaced {
+                        new: Value::Null,
+                        ..
+                    }
+                ));
+                assert!(envelope.contains("\"inf\":null"));
+            }
+            other => panic!("unexpected report: {other}"),
+        }
+    }
+}
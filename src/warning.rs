@@ -0,0 +1,71 @@
+use std::fmt;
+
+/// A non-fatal issue noticed while parsing or serializing.
+///
+/// Warnings never change the result; they let callers detect payloads
+/// that were accepted leniently so they can be logged or monitored.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Warning {
+    /// A field of the envelope or of `meta` was not recognised and was
+    /// ignored. `name` is the dotted location, e.g. `"meta.extra"`.
+    UnknownField { name: String },
+    /// An annotation was written in a non-canonical form that was accepted
+    /// anyway, such as `["Date", null]` or an empty children map.
+    LenientAnnotation { path: String, detail: String },
+    /// A date carried a non-UTC offset and was converted to UTC.
+    CoercedDate { path: String, original: String },
+    /// A JSON number could not be represented exactly. Integers are kept
+    /// as [`Value::Int`](crate::Value::Int) or
+    /// [`Value::UInt`](crate::Value::UInt), so this is no longer raised for
+    /// them.
+    LossyNumber { path: String, original: String },
+    /// A number being serialized lies outside JavaScript's safe integer
+    /// range, so a JavaScript client cannot read it exactly.
+    UnsafeInteger { path: String, value: f64 },
+    /// `meta.referentialEqualities` shares the value at `shared` with
+    /// `path`, which lies inside it or contains it. A [`Value`](crate::Value)
+    /// cannot hold the cycle, so the value at `path` was left as written.
+    CyclicReference { path: String, shared: String },
+    /// `meta.v` is newer than [`META_VERSION`](crate::META_VERSION). The
+    /// payload was read as the latest known version, which may misread
+    /// annotations introduced since.
+    UnknownVersion { version: u8 },
+    /// A value under the custom type bytes are read from was not a string
+    /// in the expected `encoding`, so it was kept as a
+    /// [`Value::Custom`](crate::Value::Custom) instead.
+    InvalidBytes { path: String, encoding: String },
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Warning::UnknownField { name } => write!(f, "ignored unknown field '{name}'"),
+            Warning::LenientAnnotation { path, detail } => {
+                write!(f, "lenient annotation at '{path}': {detail}")
+            }
+            Warning::CoercedDate { path, original } => {
+                write!(f, "date at '{path}' converted to UTC from {original}")
+            }
+            Warning::LossyNumber { path, original } => {
+                write!(f, "number at '{path}' lost precision: {original}")
+            }
+            Warning::UnsafeInteger { path, value } => write!(
+                f,
+                "number at '{path}' is outside JavaScript's safe integer range: {value}; use a BigInt"
+            ),
+            Warning::CyclicReference { path, shared } => write!(
+                f,
+                "reference at '{path}' to '{shared}' forms a cycle and was left as written"
+            ),
+            Warning::UnknownVersion { version } => write!(
+                f,
+                "meta.v is {version}, newer than the version {} this crate understands",
+                crate::META_VERSION
+            ),
+            Warning::InvalidBytes { path, encoding } => write!(
+                f,
+                "value at '{path}' is not {encoding} bytes and was kept as a custom value"
+            ),
+        }
+    }
+}
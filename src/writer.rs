@@ -0,0 +1,75 @@
+//! Writing superjson lines: one envelope per line.
+//!
+//! Each value is written as a compact envelope followed by `\n`. JSON text
+//! written without indentation never contains a raw newline, so every line
+//! holds exactly one envelope, with its own `meta`. This suits logs and
+//! exports of many records, which can then be read back one line at a time
+//! with [`SuperJsonLinesReader`](crate::reader::SuperJsonLinesReader).
+
+use std::io::Write;
+
+use crate::{Result, SerializeOptions, Value, stringify_with};
+
+/// Writes values to `W` as superjson lines.
+///
+/// Writes are not buffered; wrap a file or socket in a
+/// [`BufWriter`](std::io::BufWriter) when writing many values.
+///
+/// # Examples
+/// ```
+/// use superjson_rs::Value;
+/// use superjson_rs::writer::SuperJsonLinesWriter;
+///
+/// let mut writer = SuperJsonLinesWriter::new(Vec::new());
+/// writer.write(&Value::from(1)).unwrap();
+/// writer.write(&Value::Set(vec![Value::from("a")])).unwrap();
+/// assert_eq!(
+///     String::from_utf8(writer.into_inner()).unwrap(),
+///     "{\"json\":1}\n{\"json\":[\"a\"],\"meta\":{\"values\":[\"set\"],\"v\":1}}\n"
+/// );
+/// ```
+#[derive(Debug)]
+pub struct SuperJsonLinesWriter<W> {
+    inner: W,
+    options: SerializeOptions,
+}
+
+impl<W: Write> SuperJsonLinesWriter<W> {
+    pub fn new(inner: W) -> Self {
+        SuperJsonLinesWriter::with_options(inner, SerializeOptions::default())
+    }
+
+    /// Write every value with `options`.
+    pub fn with_options(inner: W, options: SerializeOptions) -> Self {
+        SuperJsonLinesWriter { inner, options }
+    }
+
+    /// Write `value` as one line.
+    ///
+    /// # Errors
+    /// Fails as [`stringify_with`] does, or with [`Error::Io`](crate::Error::Io)
+    /// if `W` cannot be written. Nothing is written if `value` cannot be
+    /// serialized.
+    pub fn write(&mut self, value: &Value) -> Result<()> {
+        let mut line = stringify_with(value, &self.options)?;
+        line.push('\n');
+        self.inner.write_all(line.as_bytes())?;
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> Result<()> {
+        Ok(self.inner.flush()?)
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
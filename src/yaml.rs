@@ -0,0 +1,467 @@
+//! YAML with explicit tags for extended types.
+//!
+//! JSON types are written as YAML's own, including `.nan`, `.inf`, `-.inf`
+//! and `-0.0` for the special numbers. The other types carry a local tag:
+//!
+//! | Value | YAML |
+//! |-------|------|
+//! | `undefined` | `!undefined null` |
+//! | Date | `!date 2024-01-01T00:00:00.000Z` |
+//! | bigint | `!bigint '9007199254740993'` |
+//! | Set | `!set [a, b]` |
+//! | Map | `!map [[key, value], ...]` |
+//! | RegExp | `!regexp /source/flags` |
+//! | URL | `!url https://example.com/` |
+//! | bytes | `!bytes` and the bytes in base64 |
+//! | any other type | `!superjson` and the value's envelope |
+//!
+//! Mapping keys that are numbers or booleans are read as their text, as
+//! JavaScript reads them; other keys that are not strings are rejected.
+//!
+//! # Examples
+//! ```
+//! use superjson_rs::Value;
+//!
+//! let value = Value::from_yaml_annotated("
+//! tags: !set [a, b]
+//! id: !bigint 9007199254740993
+//! ratio: .nan
+//! ").unwrap();
+//! assert_eq!(value.pointer("tags"), Some(&Value::Set(vec![Value::from("a"), Value::from("b")])));
+//! assert_eq!(value.pointer("ratio"), Some(&Value::NaN));
+//!
+//! let yaml = value.to_yaml_annotated().unwrap();
+//! assert!(yaml.contains("tags: !set\n- a\n- b\n"));
+//! assert_eq!(Value::from_yaml_annotated(&yaml).unwrap(), value);
+//! ```
+
+use serde_yaml::value::{Tag, TaggedValue};
+use serde_yaml::{Mapping, Value as Yaml};
+
+use crate::bytes::BytesEncoding;
+use crate::value::Map;
+use crate::{Error, ParseOptions, Result, Value, deserialize, format, serialize};
+
+impl Value {
+    /// Write this value as YAML, tagging extended types; see the
+    /// [module documentation](self) for the tags.
+    pub fn to_yaml_annotated(&self) -> Result<String> {
+        Ok(serde_yaml::to_string(&to_yaml(self)?)?)
+    }
+
+    /// Read YAML written by [`to_yaml_annotated`](Self::to_yaml_annotated)
+    /// or by hand in the same form.
+    pub fn from_yaml_annotated(s: &str) -> Result<Value> {
+        Value::from_yaml_annotated_with(s, &ParseOptions::default())
+    }
+
+    /// Read annotated YAML, enforcing the limits in `options`.
+    ///
+    /// # Errors
+    /// Returns [`Error::InvalidYaml`] for an unknown tag or a tag on content
+    /// it does not apply to.
+    pub fn from_yaml_annotated_with(s: &str, options: &ParseOptions) -> Result<Value> {
+        from_yaml(serde_yaml::from_str(s)?, options)
+    }
+}
+
+fn tagged(tag: &str, value: Yaml) -> Yaml {
+    Yaml::Tagged(Box::new(TaggedValue {
+        tag: Tag::new(tag),
+        value,
+    }))
+}
+
+fn float(n: f64) -> Yaml {
+    Yaml::Number(n.into())
+}
+
+/// A value to write: finished, or a container whose contents come first.
+enum Shape<'a> {
+    Done(Yaml),
+    Sequence(Option<&'static str>, Vec<&'a Value>),
+    Mapping(Vec<&'a str>, Vec<&'a Value>),
+    /// The entries of a Map, keys and values alternating.
+    Pairs(Vec<&'a Value>),
+}
+
+fn shape(value: &Value) -> Result<Shape<'_>> {
+    let done = |yaml| Ok(Shape::Done(yaml));
+    match value {
+        Value::Null => done(Yaml::Null),
+        Value::Bool(b) => done(Yaml::Bool(*b)),
+        Value::Int(n) => done(Yaml::Number((*n).into())),
+        Value::UInt(n) => done(Yaml::Number((*n).into())),
+        Value::Number(n) => done(float(*n)),
+        Value::NaN => done(float(f64::NAN)),
+        Value::PosInfinity => done(float(f64::INFINITY)),
+        Value::NegInfinity => done(float(f64::NEG_INFINITY)),
+        Value::NegZero => done(float(-0.0)),
+        Value::String(s) => done(Yaml::String(s.clone())),
+        Value::Undefined => done(tagged("undefined", Yaml::Null)),
+        Value::Array(items) => Ok(Shape::Sequence(None, items.iter().collect())),
+        Value::Set(items) => Ok(Shape::Sequence(Some("set"), items.iter().collect())),
+        Value::Object(map) => Ok(Shape::Mapping(
+            map.keys().map(String::as_str).collect(),
+            map.values().collect(),
+        )),
+        Value::Map(entries) => Ok(Shape::Pairs(
+            entries.iter().flat_map(|(k, v)| [k, v]).collect(),
+        )),
+        Value::RegExp { source, flags } => {
+            done(tagged("regexp", Yaml::String(format!("/{source}/{flags}"))))
+        }
+        Value::Url(url) => done(tagged("url", Yaml::String(url.as_str().to_string()))),
+        Value::Bytes(bytes) => done(tagged(
+            "bytes",
+            Yaml::String(BytesEncoding::Base64.encode(bytes)),
+        )),
+        // Dates and bigints are written as superjson writes them.
+        #[cfg(feature = "chrono")]
+        Value::Date(_) | Value::OffsetDate(_) | Value::InvalidDate => done(tagged(
+            "date",
+            serde_yaml::to_value(serialize::serialize(value)?.json)?,
+        )),
+        #[cfg(feature = "bigint")]
+        Value::BigInt(n) => done(tagged("bigint", Yaml::String(n.to_string()))),
+        Value::Error { .. }
+        | Value::Class { .. }
+        | Value::Custom { .. }
+        | Value::Symbol(_)
+        | Value::Unknown { .. } => done(tagged(
+            "superjson",
+            serde_yaml::to_value(serialize::serialize(value)?)?,
+        )),
+    }
+}
+
+fn to_yaml(value: &Value) -> Result<Yaml> {
+    let mut stack: Vec<(Shape, Vec<Yaml>)> = Vec::new();
+    let mut finished = open(shape(value)?, &mut stack);
+
+    loop {
+        if let Some(yaml) = finished {
+            match stack.last_mut() {
+                None => return Ok(yaml),
+                Some((_, done)) => done.push(yaml),
+            }
+        }
+        let (container, done) = stack.last().expect("stack is non-empty");
+        let children = match container {
+            Shape::Sequence(_, children) | Shape::Mapping(_, children) | Shape::Pairs(children) => {
+                children
+            }
+            Shape::Done(_) => unreachable!("finished values open no frame"),
+        };
+        finished = match children.get(done.len()) {
+            Some(&child) => open(shape(child)?, &mut stack),
+            None => {
+                let (shape, done) = stack.pop().expect("stack is non-empty");
+                Some(assemble(shape, done))
+            }
+        };
+    }
+}
+
+/// A finished value, or `None` once a container is pushed onto `stack`.
+fn open<'a>(shape: Shape<'a>, stack: &mut Vec<(Shape<'a>, Vec<Yaml>)>) -> Option<Yaml> {
+    match shape {
+        Shape::Done(yaml) => Some(yaml),
+        container => {
+            stack.push((container, Vec::new()));
+            None
+        }
+    }
+}
+
+/// Build a container from its written contents.
+fn assemble(shape: Shape, done: Vec<Yaml>) -> Yaml {
+    match shape {
+        Shape::Sequence(None, _) => Yaml::Sequence(done),
+        Shape::Sequence(Some(tag), _) => tagged(tag, Yaml::Sequence(done)),
+        Shape::Mapping(keys, _) => Yaml::Mapping(
+            keys.into_iter()
+                .map(|k| Yaml::String(k.to_string()))
+                .zip(done)
+                .collect::<Mapping>(),
+        ),
+        Shape::Pairs(_) => {
+            let mut done = done.into_iter();
+            let mut pairs = Vec::new();
+            while let (Some(k), Some(v)) = (done.next(), done.next()) {
+                pairs.push(Yaml::Sequence(vec![k, v]));
+            }
+            tagged("map", Yaml::Sequence(pairs))
+        }
+        Shape::Done(_) => unreachable!("finished values open no frame"),
+    }
+}
+
+fn invalid(reason: String) -> Error {
+    Error::InvalidYaml(reason)
+}
+
+/// A value read: finished, or a container and the YAML of its contents.
+enum Node {
+    Done(Value),
+    Array(Vec<Yaml>),
+    Set(Vec<Yaml>),
+    Object(Vec<String>, Vec<Yaml>),
+    /// The entries of a Map, keys and values alternating.
+    Map(Vec<Yaml>),
+}
+
+fn node(yaml: Yaml, options: &ParseOptions) -> Result<Node> {
+    let done = |value| Ok(Node::Done(value));
+    match yaml {
+        Yaml::Null => done(Value::Null),
+        Yaml::Bool(b) => done(Value::Bool(b)),
+        Yaml::Number(n) => done(if let Some(n) = n.as_i64() {
+            Value::Int(n)
+        } else if let Some(n) = n.as_u64() {
+            Value::UInt(n)
+        } else {
+            Value::from(n.as_f64().unwrap_or(f64::NAN))
+        }),
+        Yaml::String(s) => done(Value::String(s)),
+        Yaml::Sequence(items) => Ok(Node::Array(items)),
+        Yaml::Mapping(mapping) => {
+            let mut keys = Vec::with_capacity(mapping.len());
+            let mut values = Vec::with_capacity(mapping.len());
+            for (key, value) in mapping {
+                keys.push(match key {
+                    Yaml::String(s) => s,
+                    Yaml::Number(n) => n.to_string(),
+                    Yaml::Bool(b) => b.to_string(),
+                    other => {
+                        return Err(invalid(format!(
+                            "mapping keys must be strings, numbers or booleans, got {other:?}"
+                        )));
+                    }
+                });
+                values.push(value);
+            }
+            Ok(Node::Object(keys, values))
+        }
+        Yaml::Tagged(tagged) => {
+            let TaggedValue { tag, value } = *tagged;
+            let mismatch = |value: &Yaml| invalid(format!("{tag} does not apply to {value:?}"));
+            let scalar = |value: &Yaml| match value {
+                Yaml::String(s) => Ok(serde_json::Value::String(s.clone())),
+                Yaml::Number(n) => Ok(serde_json::to_value(n)?),
+                other => Err(mismatch(other)),
+            };
+
+            if tag == "undefined" {
+                done(Value::Undefined)
+            } else if tag == "set" {
+                match value {
+                    Yaml::Sequence(items) => Ok(Node::Set(items)),
+                    other => Err(mismatch(&other)),
+                }
+            } else if tag == "map" {
+                let Yaml::Sequence(pairs) = value else {
+                    return Err(mismatch(&value));
+                };
+                let mut children = Vec::with_capacity(pairs.len() * 2);
+                for pair in pairs {
+                    match pair {
+                        Yaml::Sequence(pair) if pair.len() == 2 => children.extend(pair),
+                        other => return Err(mismatch(&other)),
+                    }
+                }
+                Ok(Node::Map(children))
+            } else if tag == "date" {
+                done(format::restore("Date", scalar(&value)?, options)?)
+            } else if tag == "bigint" {
+                let text = match scalar(&value)? {
+                    serde_json::Value::Number(n) => serde_json::Value::String(n.to_string()),
+                    text => text,
+                };
+                done(format::restore("bigint", text, options)?)
+            } else if tag == "url" {
+                match value {
+                    Yaml::String(s) => done(format::restore("URL", s.into(), options)?),
+                    other => Err(mismatch(&other)),
+                }
+            } else if tag == "regexp" {
+                let regexp = match &value {
+                    Yaml::String(s) => s.strip_prefix('/').and_then(|s| s.rsplit_once('/')).map(
+                        |(source, flags)| Value::RegExp {
+                            source: source.to_string(),
+                            flags: flags.to_string(),
+                        },
+                    ),
+                    _ => None,
+                };
+                regexp.map(Node::Done).ok_or_else(|| mismatch(&value))
+            } else if tag == "bytes" {
+                match &value {
+                    Yaml::String(s) => BytesEncoding::Base64
+                        .decode(s)
+                        .map(|bytes| Node::Done(Value::Bytes(bytes)))
+                        .ok_or_else(|| mismatch(&value)),
+                    other => Err(mismatch(other)),
+                }
+            } else if tag == "superjson" {
+                let envelope = serde_json::to_value(&value)?;
+                done(deserialize::deserialize_json_with(envelope, options)?)
+            } else {
+                Err(invalid(format!("unknown tag {tag}")))
+            }
+        }
+    }
+}
+
+fn from_yaml(yaml: Yaml, options: &ParseOptions) -> Result<Value> {
+    /// A container whose contents are being read.
+    struct Frame {
+        node: Node,
+        done: Vec<Value>,
+    }
+
+    let mut stack: Vec<Frame> = Vec::new();
+    let mut finished = descend(node(yaml, options)?, &mut stack, options)?;
+
+    loop {
+        if let Some(value) = finished {
+            match stack.last_mut() {
+                None => return Ok(value),
+                Some(frame) => frame.done.push(value),
+            }
+        }
+        let frame = stack.last_mut().expect("stack is non-empty");
+        let children = match &mut frame.node {
+            Node::Array(children)
+            | Node::Set(children)
+            | Node::Object(_, children)
+            | Node::Map(children) => children,
+            Node::Done(_) => unreachable!("finished values open no frame"),
+        };
+        finished = if frame.done.len() < children.len() {
+            let child = std::mem::replace(&mut children[frame.done.len()], Yaml::Null);
+            descend(node(child, options)?, &mut stack, options)?
+        } else {
+            let Frame { node, done } = stack.pop().expect("stack is non-empty");
+            Some(match node {
+                Node::Array(_) => Value::Array(done),
+                Node::Set(_) => Value::Set(done),
+                Node::Object(keys, _) => {
+                    Value::Object(keys.into_iter().zip(done).collect::<Map<_, _>>())
+                }
+                Node::Map(_) => {
+                    let mut done = done.into_iter();
+                    let mut entries = Vec::new();
+                    while let (Some(k), Some(v)) = (done.next(), done.next()) {
+                        entries.push((k, v));
+                    }
+                    Value::Map(entries)
+                }
+                Node::Done(_) => unreachable!("finished values open no frame"),
+            })
+        };
+    }
+
+    /// A finished value, or `None` once a container is pushed onto `stack`.
+    fn descend(
+        node: Node,
+        stack: &mut Vec<Frame>,
+        options: &ParseOptions,
+    ) -> Result<Option<Value>> {
+        if let Node::Done(value) = node {
+            return Ok(Some(value));
+        }
+        if stack.len() >= options.max_depth {
+            return Err(Error::DepthLimitExceeded(options.max_depth));
+        }
+        stack.push(Frame {
+            node,
+            done: Vec::new(),
+        });
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roundtrip() {
+        let mut map = Map::new();
+        map.insert("missing".to_string(), Value::Undefined);
+        map.insert("zero".to_string(), Value::NegZero);
+        map.insert("inf".to_string(), Value::PosInfinity);
+        map.insert("half".to_string(), Value::Number(0.5));
+        map.insert("big".to_string(), Value::UInt(u64::MAX));
+        map.insert("text".to_string(), Value::from("123"));
+        map.insert(
+            "re".to_string(),
+            Value::RegExp {
+                source: "a/b".to_string(),
+                flags: "gi".to_string(),
+            },
+        );
+        map.insert("bytes".to_string(), Value::Bytes(vec![1, 2, 3]));
+        map.insert(
+            "pairs".to_string(),
+            Value::Map(vec![(Value::Set(vec![]), Value::Array(vec![Value::Null]))]),
+        );
+        map.insert("symbol".to_string(), Value::Symbol("sym".to_string()));
+        let value = Value::Object(map);
+
+        let yaml = value.to_yaml_annotated().unwrap();
+        assert!(yaml.contains("missing: !undefined null\n"), "{yaml}");
+        assert!(yaml.contains("re: !regexp /a/b/gi\n"), "{yaml}");
+        assert!(yaml.contains("symbol: !superjson\n"), "{yaml}");
+        assert_eq!(Value::from_yaml_annotated(&yaml).unwrap(), value);
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn test_dates() {
+        let yaml = "a: !date 2024-01-01T00:00:00.000Z\nb: !date 0\n";
+        let value = Value::from_yaml_annotated(yaml).unwrap();
+        assert_eq!(
+            value
+                .pointer("b")
+                .and_then(Value::as_date)
+                .map(|d| d.timestamp()),
+            Some(0)
+        );
+        assert_eq!(
+            value.to_yaml_annotated().unwrap(),
+            "a: !date 2024-01-01T00:00:00.000Z\nb: !date 1970-01-01T00:00:00.000Z\n"
+        );
+    }
+
+    #[test]
+    fn test_keys_and_errors() {
+        let value = Value::from_yaml_annotated("1: a\ntrue: b\n").unwrap();
+        assert_eq!(value.pointer("1"), Some(&Value::from("a")));
+        assert_eq!(value.pointer("true"), Some(&Value::from("b")));
+
+        for yaml in [
+            "[a]: b",
+            "!nope 1",
+            "!set 1",
+            "!map [[1]]",
+            "!regexp abc",
+            "!bytes '*'",
+        ] {
+            assert!(
+                matches!(Value::from_yaml_annotated(yaml), Err(Error::InvalidYaml(_))),
+                "{yaml}"
+            );
+        }
+
+        let options = ParseOptions {
+            max_depth: 2,
+            ..ParseOptions::default()
+        };
+        assert!(matches!(
+            Value::from_yaml_annotated_with("[[[1]]]", &options),
+            Err(Error::DepthLimitExceeded(2))
+        ));
+    }
+}
@@ -0,0 +1,398 @@
+//! Derive macros for `superjson_rs::ToSuperJson` and
+//! `superjson_rs::FromSuperJson`.
+//!
+//! Enable the `derive` feature of `superjson-rs` rather than depending on
+//! this crate directly; `superjson_rs::typed` documents the field hints.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use proc_macro2::TokenTree;
+use quote::{format_ident, quote};
+use syn::ext::IdentExt;
+use syn::meta::ParseNestedMeta;
+use syn::{Data, DeriveInput, Fields, Ident, LitStr, Token, Type, parse_macro_input};
+
+#[proc_macro_derive(ToSuperJson, attributes(superjson, serde))]
+pub fn derive_to_superjson(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_to(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+#[proc_macro_derive(FromSuperJson, attributes(superjson, serde))]
+pub fn derive_from_superjson(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_from(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Which of the two traits is being derived.
+#[derive(Clone, Copy)]
+enum Derive {
+    To,
+    From,
+}
+
+impl Derive {
+    fn name(self) -> &'static str {
+        match self {
+            Derive::To => "ToSuperJson",
+            Derive::From => "FromSuperJson",
+        }
+    }
+
+    /// The side of serde's `rename(serialize = .., deserialize = ..)` this
+    /// derive follows, and the other one.
+    fn sides(self) -> (&'static str, &'static str) {
+        match self {
+            Derive::To => ("serialize", "deserialize"),
+            Derive::From => ("deserialize", "serialize"),
+        }
+    }
+}
+
+/// A named field and the `superjson_rs::typed` module converting it.
+struct Field {
+    ident: Ident,
+    ty: Type,
+    key: LitStr,
+    hint: Ident,
+}
+
+fn fields(input: &DeriveInput, derive: Derive) -> syn::Result<Vec<Field>> {
+    let named = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(named) => &named.named,
+            _ => return Err(not_supported(input, derive)),
+        },
+        _ => return Err(not_supported(input, derive)),
+    };
+
+    let mut rename_all = None;
+    for attr in input.attrs.iter().filter(|a| a.path().is_ident("serde")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename_all") {
+                if let Some(rule) = serde_name(&meta, derive)? {
+                    rename_all = Some(RenameRule::parse(&rule)?);
+                }
+                Ok(())
+            } else {
+                skip(&meta)
+            }
+        })?;
+    }
+
+    let mut fields = Vec::new();
+    for field in named {
+        let ident = field.ident.clone().expect("named fields have names");
+        let mut hint = format_ident!("plain");
+        let mut rename = None;
+        for attr in &field.attrs {
+            if attr.path().is_ident("superjson") {
+                attr.parse_nested_meta(|meta| {
+                    if hint != "plain" {
+                        return Err(meta.error("a field takes one superjson hint"));
+                    }
+                    match meta.path.get_ident() {
+                        Some(name) if name == "date" || name == "bigint" || name == "set" => {
+                            hint = name.clone();
+                            Ok(())
+                        }
+                        _ => Err(meta.error("expected `date`, `bigint` or `set`")),
+                    }
+                })?;
+            } else if attr.path().is_ident("serde") {
+                attr.parse_nested_meta(|meta| {
+                    if meta.path.is_ident("rename") {
+                        rename = serde_name(&meta, derive)?.or(rename.take());
+                        Ok(())
+                    } else {
+                        skip(&meta)
+                    }
+                })?;
+            }
+        }
+        let key = rename.unwrap_or_else(|| {
+            let name = ident.unraw().to_string();
+            let name = rename_all.map_or(name.clone(), |rule| rule.apply(&name));
+            LitStr::new(&name, ident.span())
+        });
+        let ty = field.ty.clone();
+        fields.push(Field {
+            ident,
+            ty,
+            key,
+            hint,
+        });
+    }
+    Ok(fields)
+}
+
+/// The name given by serde's `rename` or `rename_all`, for this side: both
+/// `rename = "name"` and `rename(serialize = "a", deserialize = "b")`.
+fn serde_name(meta: &ParseNestedMeta, derive: Derive) -> syn::Result<Option<LitStr>> {
+    if meta.input.peek(Token![=]) {
+        return Ok(Some(meta.value()?.parse()?));
+    }
+    let (side, other) = derive.sides();
+    let mut name = None;
+    meta.parse_nested_meta(|inner| {
+        let value: LitStr = inner.value()?.parse()?;
+        if inner.path.is_ident(side) {
+            name = Some(value);
+        } else if !inner.path.is_ident(other) {
+            return Err(inner.error("expected `serialize` or `deserialize`"));
+        }
+        Ok(())
+    })?;
+    Ok(name)
+}
+
+/// Skip a serde attribute the derives do not use, such as `default`.
+fn skip(meta: &ParseNestedMeta) -> syn::Result<()> {
+    if meta.input.peek(Token![=]) {
+        meta.value()?.parse::<syn::Expr>()?;
+    } else if meta.input.peek(syn::token::Paren) {
+        meta.parse_nested_meta(|inner| skip(&inner))?;
+    }
+    Ok(())
+}
+
+/// A `#[serde(rename_all = "...")]` rule, applied to snake_case field
+/// names as serde applies it.
+#[derive(Clone, Copy)]
+enum RenameRule {
+    Lower,
+    Upper,
+    Pascal,
+    Camel,
+    Snake,
+    ScreamingSnake,
+    Kebab,
+    ScreamingKebab,
+}
+
+impl RenameRule {
+    fn parse(rule: &LitStr) -> syn::Result<Self> {
+        Ok(match rule.value().as_str() {
+            "lowercase" => RenameRule::Lower,
+            "UPPERCASE" => RenameRule::Upper,
+            "PascalCase" => RenameRule::Pascal,
+            "camelCase" => RenameRule::Camel,
+            "snake_case" => RenameRule::Snake,
+            "SCREAMING_SNAKE_CASE" => RenameRule::ScreamingSnake,
+            "kebab-case" => RenameRule::Kebab,
+            "SCREAMING-KEBAB-CASE" => RenameRule::ScreamingKebab,
+            _ => return Err(syn::Error::new_spanned(rule, "unknown rename rule")),
+        })
+    }
+
+    fn apply(self, field: &str) -> String {
+        match self {
+            RenameRule::Lower | RenameRule::Snake => field.to_string(),
+            RenameRule::Upper | RenameRule::ScreamingSnake => field.to_ascii_uppercase(),
+            RenameRule::Pascal => {
+                let mut pascal = String::new();
+                let mut capitalize = true;
+                for ch in field.chars() {
+                    if ch == '_' {
+                        capitalize = true;
+                    } else if capitalize {
+                        pascal.push(ch.to_ascii_uppercase());
+                        capitalize = false;
+                    } else {
+                        pascal.push(ch);
+                    }
+                }
+                pascal
+            }
+            RenameRule::Camel => {
+                let pascal = RenameRule::Pascal.apply(field);
+                let mut chars = pascal.chars();
+                chars.next().map_or(String::new(), |first| {
+                    first.to_ascii_lowercase().to_string() + chars.as_str()
+                })
+            }
+            RenameRule::Kebab => field.replace('_', "-"),
+            RenameRule::ScreamingKebab => field.to_ascii_uppercase().replace('_', "-"),
+        }
+    }
+}
+
+/// The `where` clause of the impl: the struct's own, plus `bound` on the
+/// type of each field that mentions one of its type parameters.
+fn where_clause(input: &DeriveInput, fields: &[Field], bound: TokenStream2) -> TokenStream2 {
+    let params: Vec<_> = input.generics.type_params().map(|p| &p.ident).collect();
+    let bounded = fields
+        .iter()
+        .map(|field| &field.ty)
+        .filter(|ty| mentions(quote!(#ty), &params))
+        .map(|ty| quote!(#ty: #bound,));
+    let predicates = input
+        .generics
+        .where_clause
+        .iter()
+        .flat_map(|clause| &clause.predicates);
+    quote! { where #(#predicates,)* #(#bounded)* }
+}
+
+/// Whether `tokens` name any of `params`.
+fn mentions(tokens: TokenStream2, params: &[&Ident]) -> bool {
+    tokens.into_iter().any(|token| match token {
+        TokenTree::Ident(ident) => params.contains(&&ident),
+        TokenTree::Group(group) => mentions(group.stream(), params),
+        _ => false,
+    })
+}
+
+fn not_supported(input: &DeriveInput, derive: Derive) -> syn::Error {
+    syn::Error::new_spanned(
+        &input.ident,
+        format!(
+            "{} can only be derived for structs with named fields",
+            derive.name()
+        ),
+    )
+}
+
+fn expand_to(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+    let (impl_generics, ty_generics, _) = input.generics.split_for_impl();
+    let fields = fields(input, Derive::To)?;
+    let where_clause = where_clause(input, &fields, quote!(::superjson_rs::typed::Serialize));
+    let inserts = fields.into_iter().map(|field| {
+        let Field {
+            ident, key, hint, ..
+        } = field;
+        quote! {
+            map.insert(
+                ::std::string::String::from(#key),
+                ::superjson_rs::typed::#hint::to_value(&self.#ident, #key)?,
+            );
+        }
+    });
+    Ok(quote! {
+        impl #impl_generics ::superjson_rs::typed::ToSuperJson for #name #ty_generics #where_clause {
+            fn to_value(&self) -> ::superjson_rs::Result<::superjson_rs::Value> {
+                #[allow(unused_mut)]
+                let mut map = ::superjson_rs::Map::new();
+                #(#inserts)*
+                ::std::result::Result::Ok(::superjson_rs::Value::Object(map))
+            }
+        }
+    })
+}
+
+fn expand_from(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+    let name_str = LitStr::new(&name.to_string(), name.span());
+    let (impl_generics, ty_generics, _) = input.generics.split_for_impl();
+    let fields = fields(input, Derive::From)?;
+    let where_clause = where_clause(
+        input,
+        &fields,
+        quote!(::superjson_rs::typed::DeserializeOwned),
+    );
+    let inits = fields.into_iter().map(|field| {
+        let Field {
+            ident, key, hint, ..
+        } = field;
+        quote! {
+            #ident: ::superjson_rs::typed::#hint::from_value(
+                ::superjson_rs::typed::take(&mut map, #key),
+                #key,
+            )?,
+        }
+    });
+    Ok(quote! {
+        impl #impl_generics ::superjson_rs::typed::FromSuperJson for #name #ty_generics #where_clause {
+            fn from_value(value: ::superjson_rs::Value) -> ::superjson_rs::Result<Self> {
+                #[allow(unused_mut, unused_variables)]
+                let mut map = ::superjson_rs::typed::fields(value, #name_str)?;
+                ::std::result::Result::Ok(#name { #(#inits)* })
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use syn::parse_quote;
+
+    fn keys(input: DeriveInput, derive: Derive) -> syn::Result<Vec<String>> {
+        Ok(fields(&input, derive)?
+            .into_iter()
+            .map(|field| field.key.value())
+            .collect())
+    }
+
+    #[test]
+    fn test_rename_rules() {
+        let cases = [
+            ("lowercase", "user_id"),
+            ("UPPERCASE", "USER_ID"),
+            ("PascalCase", "UserId"),
+            ("camelCase", "userId"),
+            ("snake_case", "user_id"),
+            ("SCREAMING_SNAKE_CASE", "USER_ID"),
+            ("kebab-case", "user-id"),
+            ("SCREAMING-KEBAB-CASE", "USER-ID"),
+        ];
+        for (rule, key) in cases {
+            let rule = LitStr::new(rule, proc_macro2::Span::call_site());
+            assert_eq!(RenameRule::parse(&rule).unwrap().apply("user_id"), key);
+        }
+    }
+
+    #[test]
+    fn test_keys() {
+        let input: DeriveInput = parse_quote! {
+            #[serde(rename_all(serialize = "camelCase"), deny_unknown_fields)]
+            struct S {
+                r#type: String,
+                #[serde(default, rename(deserialize = "in"))]
+                user_id: u32,
+            }
+        };
+        assert_eq!(keys(input.clone(), Derive::To).unwrap(), ["type", "userId"]);
+        assert_eq!(keys(input, Derive::From).unwrap(), ["type", "in"]);
+    }
+
+    #[test]
+    fn test_invalid_serde_attributes() {
+        let input: DeriveInput = parse_quote! {
+            #[serde(rename_all = "Title Case")]
+            struct S {
+                a: u8,
+            }
+        };
+        assert!(keys(input, Derive::To).is_err());
+        let input: DeriveInput = parse_quote! {
+            struct S {
+                #[serde(rename(both = "b"))]
+                a: u8,
+            }
+        };
+        assert!(keys(input, Derive::To).is_err());
+    }
+
+    #[test]
+    fn test_generic_bounds() {
+        let input: DeriveInput = parse_quote! {
+            struct S<T: Clone, U> where U: Copy {
+                items: Vec<T>,
+                count: usize,
+                other: U,
+            }
+        };
+        let fields = fields(&input, Derive::To).unwrap();
+        let clause = where_clause(&input, &fields, quote!(Bound)).to_string();
+        assert_eq!(
+            clause,
+            quote!(where U: Copy, Vec<T>: Bound, U: Bound,).to_string()
+        );
+    }
+}
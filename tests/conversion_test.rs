@@ -0,0 +1,78 @@
+//! Tests for the `TryFrom<Value>` conversions.
+
+use superjson_rs::{Error, Map, Value};
+
+fn actual(err: Error) -> String {
+    match err {
+        Error::TypeMismatch { actual, .. } => actual,
+        other => panic!("unexpected error: {other}"),
+    }
+}
+
+#[test]
+fn primitives() {
+    assert!(bool::try_from(Value::Bool(true)).unwrap());
+    assert_eq!(String::try_from(Value::from("x")).unwrap(), "x");
+    assert_eq!(f64::try_from(Value::from(1.5)).unwrap(), 1.5);
+    assert!(f64::try_from(Value::NaN).unwrap().is_nan());
+    assert!(f64::try_from(Value::NegZero).unwrap().is_sign_negative());
+    assert_eq!(i64::try_from(Value::from(-42)).unwrap(), -42);
+
+    assert_eq!(actual(String::try_from(Value::Null).unwrap_err()), "null");
+    assert_eq!(
+        actual(i64::try_from(Value::from(1.5)).unwrap_err()),
+        "number"
+    );
+    assert_eq!(
+        actual(i64::try_from(Value::from(1e19)).unwrap_err()),
+        "number"
+    );
+    assert_eq!(
+        actual(f64::try_from(Value::from("1")).unwrap_err()),
+        "string"
+    );
+}
+
+#[test]
+fn containers() {
+    let items = Vec::<Value>::try_from(Value::from(vec![1, 2])).unwrap();
+    assert_eq!(items, vec![Value::from(1), Value::from(2)]);
+    assert_eq!(
+        actual(Vec::<Value>::try_from(Value::Set(vec![])).unwrap_err()),
+        "Set"
+    );
+
+    let mut map = Map::new();
+    map.insert("a".to_string(), Value::Undefined);
+    assert_eq!(Map::try_from(Value::Object(map.clone())).unwrap(), map);
+    assert_eq!(
+        actual(Map::<String, Value>::try_from(Value::Map(vec![])).unwrap_err()),
+        "Map"
+    );
+}
+
+#[cfg(all(feature = "chrono", feature = "bigint"))]
+#[test]
+fn extended_types() {
+    use chrono::{DateTime, TimeZone, Utc};
+    use num_bigint::BigInt;
+
+    let dt = Utc.timestamp_millis_opt(0).unwrap();
+    assert_eq!(DateTime::<Utc>::try_from(Value::Date(dt)).unwrap(), dt);
+    assert_eq!(
+        actual(DateTime::<Utc>::try_from(Value::from("1970-01-01T00:00:00Z")).unwrap_err()),
+        "string"
+    );
+
+    let big = BigInt::from(u64::MAX);
+    assert_eq!(BigInt::try_from(Value::BigInt(big.clone())).unwrap(), big);
+    assert_eq!(
+        actual(BigInt::try_from(Value::from(1)).unwrap_err()),
+        "number"
+    );
+    assert_eq!(i64::try_from(Value::BigInt(BigInt::from(7))).unwrap(), 7);
+    assert_eq!(
+        actual(i64::try_from(Value::BigInt(big)).unwrap_err()),
+        "bigint"
+    );
+}
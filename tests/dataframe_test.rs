@@ -0,0 +1,149 @@
+//! Tests for the Polars `DataFrame` conversions.
+#![cfg(feature = "polars")]
+
+use polars::prelude::*;
+use superjson_rs::dataframe::{from_dataframe, to_dataframe};
+use superjson_rs::{Error, Map, Value};
+
+fn row(pairs: Vec<(&str, Value)>) -> Value {
+    Value::Object(
+        pairs
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v))
+            .collect::<Map<_, _>>(),
+    )
+}
+
+#[test]
+fn missing_and_undefined_fields_are_null() {
+    let rows = Value::from(vec![
+        row(vec![("a", Value::from(1)), ("b", Value::Undefined)]),
+        row(vec![("b", Value::from("x")), ("c", Value::Null)]),
+    ]);
+    let df = to_dataframe(&rows).unwrap();
+    let names: Vec<_> = df
+        .get_column_names()
+        .into_iter()
+        .map(|n| n.as_str())
+        .collect();
+    assert_eq!(names, ["a", "b", "c"]);
+    assert_eq!(df.column("a").unwrap().dtype(), &DataType::Float64);
+    assert_eq!(df.column("b").unwrap().dtype(), &DataType::String);
+    assert_eq!(df.column("c").unwrap().dtype(), &DataType::Null);
+
+    assert_eq!(
+        from_dataframe(&df).unwrap(),
+        Value::from(vec![
+            row(vec![
+                ("a", Value::from(1)),
+                ("b", Value::Null),
+                ("c", Value::Null)
+            ]),
+            row(vec![
+                ("a", Value::Null),
+                ("b", Value::from("x")),
+                ("c", Value::Null)
+            ]),
+        ])
+    );
+}
+
+#[cfg(all(feature = "chrono", feature = "bigint"))]
+#[test]
+fn dates_and_bigints_roundtrip() {
+    let date = chrono::DateTime::from_timestamp_millis(1_700_000_000_123).unwrap();
+    let rows = Value::from(vec![row(vec![
+        ("at", Value::Date(date)),
+        ("id", Value::BigInt(9_007_199_254_740_993i64.into())),
+    ])]);
+    let df = to_dataframe(&rows).unwrap();
+    assert_eq!(
+        df.column("at").unwrap().dtype(),
+        &DataType::Datetime(TimeUnit::Milliseconds, None)
+    );
+    assert_eq!(df.column("id").unwrap().dtype(), &DataType::Int64);
+    assert_eq!(from_dataframe(&df).unwrap(), rows);
+}
+
+#[cfg(feature = "bigint")]
+#[test]
+fn bigints_outside_i64_widen_the_column() {
+    let bigints = |digits: usize| {
+        Value::from(vec![
+            row(vec![("id", Value::BigInt(1.into()))]),
+            row(vec![("id", Value::Null)]),
+            row(vec![(
+                "id",
+                Value::BigInt(format!("-{}", "9".repeat(digits)).parse().unwrap()),
+            )]),
+        ])
+    };
+
+    let decimal = bigints(38);
+    let df = to_dataframe(&decimal).unwrap();
+    assert_eq!(
+        df.column("id").unwrap().dtype(),
+        &DataType::Decimal(Some(38), Some(0))
+    );
+    assert_eq!(from_dataframe(&df).unwrap(), decimal);
+
+    let df = to_dataframe(&bigints(39)).unwrap();
+    assert_eq!(df.column("id").unwrap().dtype(), &DataType::String);
+    assert_eq!(
+        from_dataframe(&df).unwrap(),
+        Value::from(vec![
+            row(vec![("id", Value::from("1"))]),
+            row(vec![("id", Value::Null)]),
+            row(vec![("id", Value::from(format!("-{}", "9".repeat(39))))]),
+        ])
+    );
+}
+
+#[test]
+fn special_numbers_roundtrip() {
+    let rows = Value::from(vec![
+        row(vec![("n", Value::NaN)]),
+        row(vec![("n", Value::NegZero)]),
+        row(vec![("n", Value::PosInfinity)]),
+        row(vec![("n", Value::NegInfinity)]),
+        row(vec![("n", Value::from(0))]),
+    ]);
+    let df = to_dataframe(&rows).unwrap();
+    assert_eq!(df.column("n").unwrap().dtype(), &DataType::Float64);
+    assert_eq!(from_dataframe(&df).unwrap(), rows);
+}
+
+#[test]
+fn mixed_columns_are_rejected_with_their_path() {
+    let rows = Value::from(vec![
+        row(vec![("a", Value::from(1))]),
+        row(vec![("a", Value::from("1"))]),
+    ]);
+    let err = to_dataframe(&rows).unwrap_err();
+    assert!(
+        matches!(&err, Error::TypeMismatch { path, expected, .. } if path == "1.a" && expected == "number"),
+        "{err}"
+    );
+
+    let nested = Value::from(vec![row(vec![("a", Value::Set(vec![]))])]);
+    assert!(matches!(
+        to_dataframe(&nested),
+        Err(Error::TypeMismatch { .. })
+    ));
+    assert!(matches!(
+        to_dataframe(&Value::from(1)),
+        Err(Error::TypeMismatch { .. })
+    ));
+}
+
+#[test]
+fn other_numeric_columns_become_numbers() {
+    let df = df!("n" => [1i32, 2]).unwrap();
+    assert_eq!(
+        from_dataframe(&df).unwrap(),
+        Value::from(vec![
+            row(vec![("n", Value::from(1))]),
+            row(vec![("n", Value::from(2))]),
+        ])
+    );
+}
@@ -0,0 +1,40 @@
+//! Tests for the `rust_decimal` conversions.
+#![cfg(feature = "rust_decimal")]
+
+use std::str::FromStr;
+
+use rust_decimal::Decimal;
+use superjson_rs::{Error, Value, parse, stringify};
+
+#[test]
+fn decimal_roundtrip_keeps_scale() {
+    let price = Decimal::from_str("1.50").unwrap();
+    let value = Value::from(vec![Value::from(price)]);
+
+    let text = stringify(&value).unwrap();
+    assert_eq!(
+        text,
+        r#"{"json":["1.50"],"meta":{"values":{"0":[["custom","Decimal"]]},"v":1}}"#
+    );
+    let restored = parse(&text).unwrap();
+    let decimal = restored.pointer("0").and_then(Value::as_decimal).unwrap();
+    assert_eq!(decimal.to_string(), "1.50");
+}
+
+#[test]
+fn decimal_from_exponential_notation() {
+    assert_eq!(
+        Decimal::try_from(Value::from("1.5e+3")).unwrap(),
+        Decimal::from(1500)
+    );
+}
+
+#[test]
+fn invalid_decimal_is_a_mismatch() {
+    for value in [Value::from("abc"), Value::from(1.5), Value::from("1e40")] {
+        assert!(matches!(
+            Decimal::try_from(value),
+            Err(Error::TypeMismatch { expected, .. }) if expected == "Decimal"
+        ));
+    }
+}
@@ -0,0 +1,173 @@
+#![cfg(all(feature = "derive", feature = "chrono", feature = "bigint"))]
+
+use std::collections::BTreeSet;
+
+use chrono::{DateTime, TimeZone, Utc};
+use num_bigint::BigInt;
+use superjson_rs::{Error, FromSuperJson, ToSuperJson, Value};
+
+#[derive(Debug, PartialEq, ToSuperJson, FromSuperJson)]
+struct Event {
+    #[superjson(date)]
+    at: String,
+    #[superjson(date)]
+    ends: Option<i64>,
+    #[superjson(date)]
+    logged: DateTime<Utc>,
+    #[superjson(bigint)]
+    id: u64,
+    #[superjson(bigint)]
+    total: BigInt,
+    #[superjson(set)]
+    tags: BTreeSet<String>,
+    title: String,
+}
+
+fn event() -> Event {
+    Event {
+        at: "2024-03-01T09:30:00Z".to_string(),
+        ends: None,
+        logged: Utc.timestamp_millis_opt(1_000).unwrap(),
+        id: 7,
+        total: BigInt::from(10).pow(30),
+        tags: BTreeSet::from(["a".to_string(), "b".to_string()]),
+        title: "launch".to_string(),
+    }
+}
+
+#[test]
+fn test_hints_annotate_fields() {
+    let value = event().to_value().unwrap();
+    let Value::Object(map) = &value else {
+        panic!("expected an object");
+    };
+    assert!(matches!(map["at"], Value::Date(_)));
+    assert_eq!(map["ends"], Value::Null);
+    assert!(matches!(map["logged"], Value::Date(_)));
+    assert_eq!(map["id"], Value::BigInt(BigInt::from(7)));
+    assert!(matches!(map["total"], Value::BigInt(_)));
+    assert!(matches!(map["tags"], Value::Set(_)));
+    assert_eq!(map["title"], Value::from("launch"));
+
+    let text = superjson_rs::stringify(&value).unwrap();
+    let restored = Event::from_value(superjson_rs::parse(&text).unwrap()).unwrap();
+    assert_eq!(restored.at, "2024-03-01T09:30:00.000Z");
+    assert_eq!(
+        Event {
+            at: event().at,
+            ..restored
+        },
+        event()
+    );
+}
+
+#[test]
+fn test_date_fields_read_as_milliseconds() {
+    let mut e = event();
+    e.ends = Some(86_400_000);
+    let superjson = e.to_superjson().unwrap();
+    assert_eq!(superjson.json["ends"], "1970-01-02T00:00:00.000Z");
+    assert_eq!(
+        Event::from_superjson(&superjson).unwrap().ends,
+        Some(86_400_000)
+    );
+}
+
+#[test]
+fn test_mismatched_fields_are_errors() {
+    let mut e = event();
+    e.at = "yesterday".to_string();
+    assert!(matches!(
+        e.to_value(),
+        Err(Error::TypeMismatch { path, .. }) if path == "at"
+    ));
+    assert!(matches!(
+        Event::from_value(Value::from(1)),
+        Err(Error::TypeMismatch { .. })
+    ));
+}
+
+#[derive(Debug, PartialEq, ToSuperJson, FromSuperJson)]
+struct Raw {
+    r#type: String,
+}
+
+#[test]
+fn test_raw_identifiers_lose_their_prefix() {
+    let raw = Raw {
+        r#type: "user".to_string(),
+    };
+    let superjson = raw.to_superjson().unwrap();
+    assert_eq!(superjson.json, serde_json::json!({"type": "user"}));
+    assert_eq!(Raw::from_superjson(&superjson).unwrap(), raw);
+}
+
+#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize, ToSuperJson, FromSuperJson)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct Renamed {
+    #[superjson(date)]
+    created_at: i64,
+    #[serde(rename = "ID", default)]
+    user_id: u32,
+    #[serde(rename(serialize = "out", deserialize = "in"))]
+    flow: u8,
+}
+
+#[test]
+fn test_serde_renames_are_followed() {
+    let renamed = Renamed {
+        created_at: 0,
+        user_id: 7,
+        flow: 1,
+    };
+    let keys = |value: &Value| {
+        let Value::Object(map) = value else {
+            panic!("expected an object");
+        };
+        let mut keys: Vec<_> = map.keys().cloned().collect();
+        keys.sort_unstable();
+        keys
+    };
+    let value = renamed.to_value().unwrap();
+    assert_eq!(keys(&value), ["ID", "createdAt", "out"]);
+    assert_eq!(
+        keys(&value),
+        keys(&superjson_rs::to_value(&renamed).unwrap())
+    );
+    assert!(matches!(value.pointer("createdAt"), Some(Value::Date(_))));
+
+    let text = r#"{"json":{"createdAt":"1970-01-01T00:00:00.000Z","ID":7,"in":1},"meta":{"values":{"createdAt":["Date"]}}}"#;
+    assert_eq!(
+        Renamed::from_value(superjson_rs::parse(text).unwrap()).unwrap(),
+        renamed
+    );
+}
+
+#[derive(Debug, PartialEq, ToSuperJson, FromSuperJson)]
+struct Page<T, M>
+where
+    M: Clone,
+{
+    #[superjson(set)]
+    items: Vec<T>,
+    total: usize,
+    marker: M,
+}
+
+#[test]
+fn test_generic_structs() {
+    let page = Page {
+        items: vec!["a".to_string()],
+        total: 1,
+        marker: (),
+    };
+    let superjson = page.to_superjson().unwrap();
+    assert!(matches!(
+        page.to_value().unwrap().pointer("items"),
+        Some(Value::Set(_))
+    ));
+    assert_eq!(
+        Page::<String, ()>::from_superjson(&superjson).unwrap(),
+        page
+    );
+}
@@ -0,0 +1,40 @@
+//! Tests for the duration conversions.
+
+use std::time::Duration;
+
+use superjson_rs::{Error, Value, parse, stringify};
+
+#[test]
+fn std_duration_roundtrip() {
+    let duration = Duration::new(3_723, 250_000_000);
+    let text = stringify(&Value::from(duration)).unwrap();
+    assert_eq!(
+        text,
+        r#"{"json":"PT3723.25S","meta":{"values":[["custom","Duration"]],"v":1}}"#
+    );
+    assert_eq!(Duration::try_from(parse(&text).unwrap()).unwrap(), duration);
+}
+
+#[test]
+fn calendar_and_negative_durations_are_rejected() {
+    for text in ["P1M", "-PT1S", "soon"] {
+        assert!(matches!(
+            Duration::try_from(Value::from(text)),
+            Err(Error::TypeMismatch { .. })
+        ));
+    }
+}
+
+#[cfg(feature = "chrono")]
+#[test]
+fn chrono_time_delta_roundtrip() {
+    let delta = -chrono::TimeDelta::milliseconds(90_061_500);
+    let value = Value::from(delta);
+    assert_eq!(value.to_string(), r#"Duration("-PT90061.5S")"#);
+    assert_eq!(chrono::TimeDelta::try_from(value.clone()).unwrap(), delta);
+    assert_eq!(value.as_duration(), None);
+    assert_eq!(
+        chrono::TimeDelta::try_from(Value::from("P1DT1H1M1.5S")).unwrap(),
+        -delta
+    );
+}
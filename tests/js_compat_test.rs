@@ -3,10 +3,11 @@
 //! Each test includes the equivalent JS code and expected output from the
 //! JavaScript superjson library.
 
+#[cfg(feature = "chrono")]
 use chrono::TimeZone;
-use indexmap::IndexMap;
+#[cfg(feature = "bigint")]
 use num_bigint::BigInt;
-use superjson_rs::{Value, parse, stringify};
+use superjson_rs::{Map, Value, parse, stringify};
 
 /// Helper: serialize a Value and parse the resulting JSON string
 /// to compare the raw JSON structure with expected JS output.
@@ -19,12 +20,13 @@ fn serialize_to_json(value: &Value) -> serde_json::Value {
 // JS superjson output compatibility tests
 // ============================================================
 
+#[cfg(feature = "chrono")]
 #[test]
 fn js_compat_simple_date() {
     // JS: SuperJSON.serialize({ date: new Date(0) })
     // → { json: { date: "1970-01-01T00:00:00.000Z" },
     //     meta: { values: { date: ["Date"] }, v: 1 } }
-    let mut obj = IndexMap::new();
+    let mut obj = Map::new();
     obj.insert(
         "date".to_string(),
         Value::Date(chrono::Utc.timestamp_millis_opt(0).unwrap()),
@@ -46,7 +48,7 @@ fn js_compat_set_with_undefined() {
     // JS: SuperJSON.serialize({ a: new Set([1, undefined, 2]) })
     // → { json: { a: [1, null, 2] },
     //     meta: { values: { a: ["set", { "1": ["undefined"] }] }, v: 1 } }
-    let mut obj = IndexMap::new();
+    let mut obj = Map::new();
     obj.insert(
         "a".to_string(),
         Value::Set(vec![
@@ -72,7 +74,7 @@ fn js_compat_map_with_nan_key() {
     // JS: SuperJSON.serialize({ a: new Map([[NaN, null]]) })
     // → { json: { a: [["NaN", null]] },
     //     meta: { values: { a: ["map", { "0.0": ["number"] }] }, v: 1 } }
-    let mut obj = IndexMap::new();
+    let mut obj = Map::new();
     obj.insert("a".to_string(), Value::Map(vec![(Value::NaN, Value::Null)]));
 
     let result = serialize_to_json(&Value::Object(obj));
@@ -86,17 +88,18 @@ fn js_compat_map_with_nan_key() {
     );
 }
 
+#[cfg(feature = "chrono")]
 #[test]
 fn js_compat_nested_object_date() {
     // JS: SuperJSON.serialize({ meeting: { date: new Date(0) } })
     // → { json: { meeting: { date: "1970-01-01T00:00:00.000Z" } },
     //     meta: { values: { "meeting.date": ["Date"] }, v: 1 } }
-    let mut inner = IndexMap::new();
+    let mut inner = Map::new();
     inner.insert(
         "date".to_string(),
         Value::Date(chrono::Utc.timestamp_millis_opt(0).unwrap()),
     );
-    let mut outer = IndexMap::new();
+    let mut outer = Map::new();
     outer.insert("meeting".to_string(), Value::Object(inner));
 
     let result = serialize_to_json(&Value::Object(outer));
@@ -125,6 +128,7 @@ fn js_compat_toplevel_set() {
     );
 }
 
+#[cfg(feature = "chrono")]
 #[test]
 fn js_compat_toplevel_date() {
     // JS: SuperJSON.serialize(new Date(0))
@@ -155,12 +159,13 @@ fn js_compat_toplevel_undefined() {
     );
 }
 
+#[cfg(feature = "bigint")]
 #[test]
 fn js_compat_bigint() {
     // JS: SuperJSON.serialize({ a: BigInt("1021312312412312312313") })
     // → { json: { a: "1021312312412312312313" },
     //     meta: { values: { a: ["bigint"] }, v: 1 } }
-    let mut obj = IndexMap::new();
+    let mut obj = Map::new();
     let big: BigInt = "1021312312412312312313".parse().unwrap();
     obj.insert("a".to_string(), Value::BigInt(big));
 
@@ -180,7 +185,7 @@ fn js_compat_regexp() {
     // JS: SuperJSON.serialize({ a: /hello/g })
     // → { json: { a: "/hello/g" },
     //     meta: { values: { a: ["regexp"] }, v: 1 } }
-    let mut obj = IndexMap::new();
+    let mut obj = Map::new();
     obj.insert(
         "a".to_string(),
         Value::RegExp {
@@ -203,7 +208,7 @@ fn js_compat_regexp() {
 #[test]
 fn js_compat_special_numbers() {
     // JS: SuperJSON.serialize({ a: Infinity, b: -Infinity, c: NaN })
-    let mut obj = IndexMap::new();
+    let mut obj = Map::new();
     obj.insert("a".to_string(), Value::PosInfinity);
     obj.insert("b".to_string(), Value::NegInfinity);
     obj.insert("c".to_string(), Value::NaN);
@@ -237,7 +242,7 @@ fn js_compat_neg_zero() {
 #[test]
 fn js_compat_neg_zero_in_object() {
     // JS: SuperJSON.serialize({ a: -0 })
-    let mut obj = IndexMap::new();
+    let mut obj = Map::new();
     obj.insert("a".to_string(), Value::NegZero);
 
     let result = serialize_to_json(&Value::Object(obj));
@@ -256,10 +261,10 @@ fn js_compat_url() {
     // JS: SuperJSON.serialize({ link: new URL("https://example.com") })
     // → { json: { link: "https://example.com/" },
     //     meta: { values: { link: ["URL"] }, v: 1 } }
-    let mut obj = IndexMap::new();
+    let mut obj = Map::new();
     obj.insert(
         "link".to_string(),
-        Value::Url("https://example.com/".into()),
+        Value::Url("https://example.com/".parse().unwrap()),
     );
 
     let result = serialize_to_json(&Value::Object(obj));
@@ -277,7 +282,7 @@ fn js_compat_url() {
 fn js_compat_toplevel_url() {
     // JS: SuperJSON.serialize(new URL("https://example.com"))
     // → { json: "https://example.com/", meta: { values: ["URL"], v: 1 } }
-    let result = serialize_to_json(&Value::Url("https://example.com/".into()));
+    let result = serialize_to_json(&Value::Url("https://example.com/".parse().unwrap()));
 
     assert_eq!(
         result,
@@ -294,7 +299,9 @@ fn js_compat_error_simple() {
     let result = serialize_to_json(&Value::Error {
         name: "Error".into(),
         message: "fail".into(),
+        stack: None,
         cause: None,
+        extra: Map::new(),
     });
 
     assert_eq!(
@@ -312,11 +319,15 @@ fn js_compat_error_with_cause() {
     let result = serialize_to_json(&Value::Error {
         name: "Error".into(),
         message: "outer".into(),
+        stack: None,
         cause: Some(Box::new(Value::Error {
             name: "Error".into(),
             message: "inner".into(),
+            stack: None,
             cause: None,
+            extra: Map::new(),
         })),
+        extra: Map::new(),
     });
 
     assert_eq!(
@@ -334,13 +345,15 @@ fn js_compat_error_with_cause() {
 #[test]
 fn js_compat_error_in_object() {
     // JS: SuperJSON.serialize({ err: new Error("fail") })
-    let mut obj = IndexMap::new();
+    let mut obj = Map::new();
     obj.insert(
         "err".to_string(),
         Value::Error {
             name: "Error".into(),
             message: "fail".into(),
+            stack: None,
             cause: None,
+            extra: Map::new(),
         },
     );
 
@@ -360,7 +373,7 @@ fn js_compat_no_meta_for_plain_json() {
     // JS: SuperJSON.serialize({ name: "Alice", age: 30 })
     // → { json: { name: "Alice", age: 30 } }
     // (no meta field)
-    let mut obj = IndexMap::new();
+    let mut obj = Map::new();
     obj.insert("name".to_string(), Value::String("Alice".into()));
     obj.insert("age".to_string(), Value::Number(30.0));
 
@@ -376,12 +389,13 @@ fn js_compat_no_meta_for_plain_json() {
     assert!(result.get("meta").is_none());
 }
 
+#[cfg(feature = "chrono")]
 #[test]
 fn js_compat_object_with_dot_in_key() {
     // JS: SuperJSON.serialize({ "a.b": new Date(0) })
     // → { json: { "a.b": "1970-01-01T00:00:00.000Z" },
     //     meta: { values: { "a\\.b": ["Date"] }, v: 1 } }
-    let mut obj = IndexMap::new();
+    let mut obj = Map::new();
     obj.insert(
         "a.b".to_string(),
         Value::Date(chrono::Utc.timestamp_millis_opt(0).unwrap()),
@@ -398,6 +412,7 @@ fn js_compat_object_with_dot_in_key() {
     );
 }
 
+#[cfg(feature = "chrono")]
 #[test]
 fn js_compat_deserialize_dot_in_key() {
     // Parse a JSON string where JS superjson escaped a dot in the key
@@ -418,6 +433,7 @@ fn js_compat_deserialize_dot_in_key() {
 // Deserialize JS superjson output
 // ============================================================
 
+#[cfg(feature = "chrono")]
 #[test]
 fn js_compat_deserialize_js_output() {
     // Parse a JSON string that JS superjson would produce
@@ -487,13 +503,13 @@ fn js_compat_deserialize_with_referential_equalities_record() {
         "meta": { "referentialEqualities": { "a": ["b"] } }
     }"#;
 
-    // Should parse without error (referentialEqualities is ignored but doesn't break parsing)
     let value = parse(js_output).unwrap();
     let obj = value.as_object().unwrap();
     assert_eq!(
         obj.get("a").unwrap().as_object().unwrap().get("x").unwrap(),
         &Value::Number(1.0)
     );
+    assert_eq!(obj.get("b"), obj.get("a"));
 }
 
 #[test]
@@ -506,7 +522,7 @@ fn js_compat_deserialize_with_referential_equalities_root_array() {
         "meta": { "referentialEqualities": [["a.self"]] }
     }"#;
 
-    // Should parse without error (previously this would fail with a deserialization error)
+    // The cycle cannot be restored, and is left as written.
     let value = parse(js_output).unwrap();
     let obj = value.as_object().unwrap();
     assert!(obj.get("a").is_some());
@@ -520,10 +536,169 @@ fn js_compat_deserialize_with_referential_equalities_mixed() {
         "meta": { "referentialEqualities": [["a.self"], { "a": ["b", "c"] }] }
     }"#;
 
-    // Should parse without error
     let value = parse(js_output).unwrap();
     let obj = value.as_object().unwrap();
     assert!(obj.get("a").is_some());
-    assert!(obj.get("b").is_some());
-    assert!(obj.get("c").is_some());
+    assert_eq!(obj.get("b"), obj.get("a"));
+    assert_eq!(obj.get("c"), obj.get("a"));
+}
+
+#[test]
+fn js_compat_custom_transformer() {
+    // JS: SuperJSON.registerCustom({ isApplicable: v => Decimal.isDecimal(v),
+    //       serialize: v => v.toJSON(), deserialize: v => new Decimal(v) }, "decimal.js")
+    //     SuperJSON.serialize({ price: new Decimal("1.50") })
+    // → { json: { price: "1.5" },
+    //     meta: { values: { price: [["custom", "decimal.js"]] }, v: 1 } }
+    let mut obj = Map::new();
+    obj.insert(
+        "price".to_string(),
+        Value::Custom {
+            name: "decimal.js".to_string(),
+            value: Box::new(Value::from("1.5")),
+        },
+    );
+    let value = Value::Object(obj);
+
+    let result = serialize_to_json(&value);
+    assert_eq!(
+        result,
+        serde_json::json!({
+            "json": { "price": "1.5" },
+            "meta": { "values": { "price": [["custom", "decimal.js"]] }, "v": 1 }
+        })
+    );
+    assert_eq!(parse(&result.to_string()).unwrap(), value);
+}
+
+#[test]
+fn js_compat_unknown_composite_identifier() {
+    // JS: SuperJSON.serialize({ bytes: new Int8Array([1, -1]) })
+    let js_output = r#"{"json":{"bytes":[1,-1]},"meta":{"values":{"bytes":[["typed-array","Int8Array"]]},"v":1}}"#;
+    let envelope: superjson_rs::SuperJson = serde_json::from_str(js_output).unwrap();
+    assert_eq!(serde_json::to_string(&envelope).unwrap(), js_output);
+    assert!(matches!(
+        parse(js_output),
+        Err(superjson_rs::Error::InvalidTypeAnnotation(_))
+    ));
+}
+
+// ============================================================
+// Legacy flat meta format
+// ============================================================
+
+#[test]
+fn js_compat_legacy_meta_parse() {
+    // Older releases: { json: { a: [[1, "NaN"]] }, meta: { values: { a: "map", "a.0.1": "number" } } }
+    let value = parse(r#"{"json":{"a":[[1,"NaN"]],"u":null},"meta":{"values":{"a.0.1":"number","a":"map","u":"undefined"}}}"#)
+        .unwrap();
+    let mut obj = Map::new();
+    obj.insert("a".into(), Value::Map(vec![(Value::from(1), Value::NaN)]));
+    obj.insert("u".into(), Value::Undefined);
+    assert_eq!(value, Value::Object(obj));
+
+    let root = parse(r#"{"json":[[]],"meta":{"values":{"":"set","0":"set"}}}"#).unwrap();
+    assert_eq!(root, Value::Set(vec![Value::Set(vec![])]));
+}
+
+#[test]
+fn js_compat_legacy_meta_pairs_parse() {
+    // Earlier releases: { meta: { values: [["a", "map"], ["a.0.1", "number"]] } }
+    let value = parse(
+        r#"{"json":{"a":[[1,"NaN"]],"u":null},"meta":{"values":[["a.0.1","number"],["a","map"],["u","undefined"]]}}"#,
+    )
+    .unwrap();
+    let mut obj = Map::new();
+    obj.insert("a".into(), Value::Map(vec![(Value::from(1), Value::NaN)]));
+    obj.insert("u".into(), Value::Undefined);
+    assert_eq!(value, Value::Object(obj));
+
+    let root = parse(r#"{"json":[[]],"meta":{"values":[["","set"],["0","set"]]}}"#).unwrap();
+    assert_eq!(root, Value::Set(vec![Value::Set(vec![])]));
+
+    // A single composite identifier is still a root annotation.
+    let custom = parse(r#"{"json":"1","meta":{"values":[["custom","money"]]}}"#).unwrap();
+    assert_eq!(
+        custom,
+        Value::Custom {
+            name: "money".into(),
+            value: Box::new(Value::from("1")),
+        }
+    );
+}
+
+#[test]
+fn js_compat_legacy_meta_rejects_mixed_forms() {
+    assert!(matches!(
+        parse(r#"{"json":{"a":1,"b":"NaN"},"meta":{"values":{"a":["number"],"b":"number"}}}"#),
+        Err(superjson_rs::Error::InvalidTypeAnnotation(_))
+    ));
+}
+
+#[test]
+fn js_compat_legacy_meta_emit() {
+    let options = superjson_rs::SerializeOptions {
+        meta_format: superjson_rs::MetaFormat::Legacy,
+        ..Default::default()
+    };
+    let value = Value::Set(vec![Value::from(vec![Value::NegZero])]);
+    let s = superjson_rs::stringify_with(&value, &options).unwrap();
+    assert_eq!(
+        s,
+        r#"{"json":[["-0"]],"meta":{"values":{"":"set","0.0":"number"}}}"#
+    );
+    assert_eq!(parse(&s).unwrap(), value);
+
+    let custom = Value::Custom {
+        name: "money".into(),
+        value: Box::new(Value::from("1")),
+    };
+    assert!(superjson_rs::stringify_with(&custom, &options).is_err());
+}
+
+// ============================================================
+// Referential equalities
+// ============================================================
+
+#[test]
+fn js_compat_dedupe_parse() {
+    // JS: const shared = { tags: new Set(["a"]) };
+    //     new SuperJSON({ dedupe: true }).serialize({ a: shared, b: [shared] })
+    let value = parse(
+        r#"{"json":{"a":{"tags":["a"]},"b":[null]},
+            "meta":{"values":{"a.tags":["set"]},"referentialEqualities":{"a":["b.0"]},"v":1}}"#,
+    )
+    .unwrap();
+    let mut shared = Map::new();
+    shared.insert("tags".into(), Value::Set(vec![Value::from("a")]));
+    let shared = Value::Object(shared);
+    let mut obj = Map::new();
+    obj.insert("a".into(), shared.clone());
+    obj.insert("b".into(), Value::Array(vec![shared]));
+    let obj = Value::Object(obj);
+    assert_eq!(value, obj);
+
+    let options = superjson_rs::SerializeOptions {
+        dedupe: true,
+        ..Default::default()
+    };
+    assert_eq!(
+        superjson_rs::stringify_with(&obj, &options).unwrap(),
+        r#"{"json":{"a":{"tags":["a"]},"b":[null]},"meta":{"values":{"a.tags":["set"]},"referentialEqualities":{"a":["b.0"]},"v":1}}"#
+    );
+}
+
+#[test]
+fn js_compat_referential_equalities_leave_cycles() {
+    // JS: const o = {}; o.self = o; SuperJSON.serialize(o)
+    let cycle = r#"{"json":{"self":null},"meta":{"referentialEqualities":[["self"]],"v":1}}"#;
+    let parsed = superjson_rs::parse_detailed(cycle, &Default::default()).unwrap();
+    assert_eq!(parsed.value.pointer("self"), Some(&Value::Null));
+    assert_eq!(
+        parsed.warnings,
+        [superjson_rs::Warning::CyclicReference {
+            path: "self".into(),
+            shared: "".into(),
+        }]
+    );
 }
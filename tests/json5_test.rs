@@ -0,0 +1,45 @@
+//! Tests for reading envelopes written in JSON5.
+#![cfg(feature = "json5")]
+
+use superjson_rs::backend::{self, Json5};
+use superjson_rs::{Error, ParseOptions, Value, parse, parse_json5, stringify};
+
+#[test]
+fn json5_relaxed_syntax() {
+    let text = r#"
+        /* A fixture. */
+        {
+            json: {
+                'created': "1970-01-01T00:00:00.000Z", // single or double quotes
+                count: 0x10,
+                ids: [1, 2, 3,],
+            },
+            meta: {values: {created: ["Date"], ids: ['set']}, v: 1,},
+        }
+    "#;
+    let value = parse_json5(text).unwrap();
+    assert_eq!(value.pointer("count"), Some(&Value::from(16)));
+    assert!(matches!(value.pointer("ids"), Some(Value::Set(ids)) if ids.len() == 3));
+    #[cfg(feature = "chrono")]
+    assert!(value.pointer("created").and_then(Value::as_date).is_some());
+}
+
+#[test]
+fn json5_reads_plain_envelopes() {
+    let value = Value::Map(vec![(Value::from(1), Value::NegZero)]);
+    let text = stringify(&value).unwrap();
+    assert_eq!(parse_json5(&text).unwrap(), parse(&text).unwrap());
+}
+
+#[test]
+fn json5_backend_applies_options() {
+    let options = ParseOptions {
+        max_depth: 1,
+        ..ParseOptions::default()
+    };
+    assert!(matches!(
+        backend::parse_with(&Json5, "{json: [[[1]]]}", &options),
+        Err(Error::DepthLimitExceeded(1))
+    ));
+    assert!(matches!(parse_json5("{json: }"), Err(Error::Backend(_))));
+}
@@ -0,0 +1,154 @@
+//! Tests for the limits applied when parsing untrusted superjson input.
+
+use superjson_rs::{
+    Error, Meta, ParseOptions, TypeAnnotation, Value, deserialize, parse, parse_with, serialize,
+};
+
+fn with_max_depth(max_depth: usize) -> ParseOptions {
+    ParseOptions {
+        max_depth,
+        ..ParseOptions::default()
+    }
+}
+
+/// Build a `["set", {"0": ["set", {"0": ...}]}]` annotation `depth` levels deep.
+fn nested_set_annotation(depth: usize) -> serde_json::Value {
+    let mut ann = serde_json::json!(["set"]);
+    for _ in 1..depth {
+        ann = serde_json::json!(["set", { "0": ann }]);
+    }
+    ann
+}
+
+fn nested_set_json(depth: usize) -> serde_json::Value {
+    let mut json = serde_json::json!([]);
+    for _ in 1..depth {
+        json = serde_json::json!([json]);
+    }
+    json
+}
+
+fn envelope(depth: usize) -> String {
+    serde_json::json!({
+        "json": nested_set_json(depth),
+        "meta": { "values": nested_set_annotation(depth), "v": 1 }
+    })
+    .to_string()
+}
+
+#[test]
+fn annotation_depth_within_limit() {
+    let options = with_max_depth(4);
+    assert!(parse_with(&envelope(4), &options).is_ok());
+}
+
+#[test]
+fn annotation_depth_exceeds_limit() {
+    let options = with_max_depth(4);
+    let err = parse_with(&envelope(5), &options).unwrap_err();
+    assert!(matches!(err, Error::DepthLimitExceeded(4)), "got {err}");
+}
+
+#[test]
+fn annotation_depth_default_limit() {
+    assert!(parse(&envelope(50)).is_ok());
+}
+
+#[test]
+fn annotation_depth_limit_applies_to_serde_impls() {
+    let meta = serde_json::json!({ "values": nested_set_annotation(200) });
+    let err = serde_json::from_value::<Meta>(meta).unwrap_err();
+    assert!(err.to_string().contains("depth"), "got {err}");
+}
+
+#[test]
+fn annotation_from_json_rejects_malformed() {
+    let err = TypeAnnotation::from_json(&serde_json::json!(["set", 1]), 8).unwrap_err();
+    assert!(matches!(err, Error::InvalidTypeAnnotation(_)));
+    for custom in [
+        serde_json::json!([[1, "Foo"]]),
+        serde_json::json!([["symbol", "Foo"], {}]),
+        serde_json::json!([["class", "Foo"], 1]),
+        serde_json::json!([["custom"]]),
+    ] {
+        let err = TypeAnnotation::from_json(&custom, 8).unwrap_err();
+        assert!(matches!(err, Error::InvalidTypeAnnotation(_)), "{custom}");
+    }
+}
+
+fn nested_array(depth: usize) -> Value {
+    let mut value = Value::Array(vec![]);
+    for _ in 1..depth {
+        value = Value::Array(vec![value]);
+    }
+    value
+}
+
+#[test]
+fn json_depth_exceeds_limit() {
+    let s = serde_json::json!({ "json": nested_set_json(10) }).to_string();
+    let options = with_max_depth(8);
+    let err = parse_with(&s, &options).unwrap_err();
+    assert!(matches!(err, Error::DepthLimitExceeded(8)), "got {err}");
+}
+
+#[test]
+fn deep_values_do_not_use_call_stack() {
+    let depth = 5_000;
+    let value = nested_array(depth);
+    let superjson = serialize::serialize(&value).unwrap();
+    assert!(superjson.meta.is_none());
+
+    let options = with_max_depth(depth);
+    let restored = deserialize::deserialize_with(&superjson, &options).unwrap();
+    assert!(matches!(restored, Value::Array(_)));
+
+    let options = with_max_depth(depth - 1);
+    let err = deserialize::deserialize_with(&superjson, &options).unwrap_err();
+    assert!(matches!(err, Error::DepthLimitExceeded(_)));
+}
+
+fn with_max_annotations(max_annotations: usize) -> ParseOptions {
+    ParseOptions {
+        max_annotations,
+        ..ParseOptions::default()
+    }
+}
+
+#[test]
+fn annotation_count_counts_nested_nodes() {
+    // Three nodes: "a", its child "0", and "b".
+    let s = r#"{"json":{"a":[[]],"b":"NaN"},
+        "meta":{"values":{"a":["set",{"0":["set"]}],"b":["number"]}}}"#;
+    assert!(parse_with(s, &with_max_annotations(3)).is_ok());
+    assert!(matches!(
+        parse_with(s, &with_max_annotations(2)),
+        Err(Error::AnnotationLimitExceeded(2))
+    ));
+}
+
+#[test]
+fn annotation_count_counts_legacy_pairs() {
+    let s = r#"{"json":{"a":"NaN","b":"NaN"},"meta":{"values":[["a","number"],["b","number"]]}}"#;
+    assert!(parse_with(s, &with_max_annotations(2)).is_ok());
+    assert!(matches!(
+        parse_with(s, &with_max_annotations(1)),
+        Err(Error::AnnotationLimitExceeded(1))
+    ));
+}
+
+#[test]
+fn annotation_count_checked_before_restoring() {
+    let values: serde_json::Map<String, serde_json::Value> = (0..1000)
+        .map(|i| (format!("x.{i}"), serde_json::json!(["Date"])))
+        .collect();
+    let s = serde_json::json!({"json": {}, "meta": {"values": values}}).to_string();
+    assert!(matches!(
+        parse_with(&s, &with_max_annotations(999)),
+        Err(Error::AnnotationLimitExceeded(999))
+    ));
+    assert!(matches!(
+        parse_with(&s, &ParseOptions::default()),
+        Ok(Value::Object(_))
+    ));
+}
@@ -0,0 +1,102 @@
+//! Tests for the protobuf well-known type conversions.
+#![cfg(feature = "prost")]
+
+use prost_types::value::Kind;
+use superjson_rs::{Error, Map, Value, parse};
+
+fn object(pairs: Vec<(&str, Value)>) -> Value {
+    Value::Object(
+        pairs
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v))
+            .collect::<Map<_, _>>(),
+    )
+}
+
+#[test]
+fn plain_json_roundtrip() {
+    let value = object(vec![
+        ("name", Value::from("ada")),
+        ("tags", Value::from(vec![Value::from(1), Value::Null])),
+        ("nested", object(vec![("ok", Value::Bool(true))])),
+    ]);
+    let message = prost_types::Struct::try_from(&value).unwrap();
+    assert_eq!(
+        message.fields["name"].kind,
+        Some(Kind::StringValue("ada".into()))
+    );
+    assert_eq!(Value::try_from(message).unwrap(), value);
+}
+
+#[test]
+fn extended_types_are_rejected_with_their_path() {
+    let value = object(vec![("items", Value::from(vec![Value::Undefined]))]);
+    let err = prost_types::Value::try_from(&value).unwrap_err();
+    assert!(
+        matches!(&err, Error::TypeMismatch { path, actual, .. } if path == "items.0" && actual == "undefined"),
+        "{err}"
+    );
+    assert!(prost_types::Struct::try_from(&Value::from(1)).is_err());
+}
+
+#[test]
+fn non_finite_numbers_become_null() {
+    let message = prost_types::Value {
+        kind: Some(Kind::NumberValue(f64::NAN)),
+    };
+    assert_eq!(Value::try_from(message).unwrap(), Value::Null);
+}
+
+#[test]
+fn envelope_roundtrip() {
+    let value = object(vec![
+        ("seen", Value::Set(vec![Value::from("a")])),
+        ("score", Value::NegInfinity),
+    ]);
+    let superjson = superjson_rs::serialize::serialize(&value).unwrap();
+    let message = prost_types::Struct::from(&superjson);
+    assert!(message.fields.contains_key("meta"));
+
+    let restored = superjson_rs::SuperJson::try_from(message).unwrap();
+    // `Struct` fields are sorted, so only the content survives, not the
+    // order of keys.
+    let text = serde_json::to_string(&restored).unwrap();
+    assert_eq!(parse(&text).unwrap(), value);
+    assert_eq!(restored.meta.unwrap().v, Some(1));
+}
+
+#[cfg(feature = "chrono")]
+#[test]
+fn timestamp_roundtrip() {
+    let ts = prost_types::Timestamp {
+        seconds: 1_700_000_000,
+        nanos: 123_000_000,
+    };
+    let value = Value::try_from(ts).unwrap();
+    assert_eq!(
+        value.as_date().unwrap().to_rfc3339(),
+        "2023-11-14T22:13:20.123+00:00"
+    );
+    assert_eq!(prost_types::Timestamp::try_from(&value).unwrap(), ts);
+
+    let invalid = prost_types::Timestamp {
+        seconds: 0,
+        nanos: -1,
+    };
+    assert!(matches!(
+        Value::try_from(invalid),
+        Err(Error::InvalidDate(_))
+    ));
+}
+
+#[test]
+fn duration_is_milliseconds() {
+    let duration = prost_types::Duration {
+        seconds: -1,
+        nanos: -500_000_000,
+    };
+    let value = Value::from(duration);
+    assert_eq!(value, Value::Number(-1500.0));
+    assert_eq!(prost_types::Duration::try_from(&value).unwrap(), duration);
+    assert!(prost_types::Duration::try_from(&Value::NaN).is_err());
+}
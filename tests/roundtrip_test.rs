@@ -1,7 +1,8 @@
+#[cfg(feature = "chrono")]
 use chrono::TimeZone;
-use indexmap::IndexMap;
+#[cfg(feature = "bigint")]
 use num_bigint::BigInt;
-use superjson_rs::{Value, parse, stringify};
+use superjson_rs::{Map, Value, parse, stringify};
 
 /// Helper to assert that a Value survives a roundtrip through stringify/parse.
 fn assert_roundtrip(value: Value) {
@@ -28,6 +29,46 @@ fn roundtrip_number() {
     assert_roundtrip(Value::Number(-100.0));
 }
 
+#[test]
+fn roundtrip_integers() {
+    assert_roundtrip(Value::Int(30));
+    assert_roundtrip(Value::Int(i64::MIN));
+    assert_roundtrip(Value::Int(9_007_199_254_740_993));
+    assert_roundtrip(Value::UInt(u64::MAX));
+}
+
+#[test]
+fn integers_equal_numbers_by_value() {
+    assert_eq!(Value::Int(1), Value::Number(1.0));
+    assert_eq!(Value::Number(-0.0), Value::Int(0));
+    assert_eq!(Value::UInt(7), Value::Int(7));
+    assert_eq!(
+        Value::from(vec![Value::Int(2)]),
+        Value::from(vec![Value::Number(2.0)])
+    );
+    assert_ne!(Value::Int(1), Value::Number(1.5));
+    assert_ne!(Value::UInt(u64::MAX), Value::Number(u64::MAX as f64));
+    assert_ne!(Value::Int(1), Value::String("1".into()));
+}
+
+#[test]
+fn integers_are_written_as_read() {
+    let text = r#"{"json":[30,-7,9007199254740993,18446744073709551615,30.0,1.5]}"#;
+    let value = parse(text).unwrap();
+    assert_eq!(
+        value,
+        Value::from(vec![
+            Value::Int(30),
+            Value::Int(-7),
+            Value::Int(9_007_199_254_740_993),
+            Value::UInt(u64::MAX),
+            Value::Number(30.0),
+            Value::Number(1.5),
+        ])
+    );
+    assert_eq!(stringify(&value).unwrap(), text);
+}
+
 #[test]
 fn roundtrip_string() {
     assert_roundtrip(Value::String("".into()));
@@ -48,7 +89,7 @@ fn roundtrip_array() {
 
 #[test]
 fn roundtrip_object() {
-    let mut obj = IndexMap::new();
+    let mut obj = Map::new();
     obj.insert("name".to_string(), Value::String("test".into()));
     obj.insert("count".to_string(), Value::Number(42.0));
     obj.insert("active".to_string(), Value::Bool(true));
@@ -60,18 +101,21 @@ fn roundtrip_undefined() {
     assert_roundtrip(Value::Undefined);
 }
 
+#[cfg(feature = "chrono")]
 #[test]
 fn roundtrip_date() {
     let dt = chrono::Utc.timestamp_millis_opt(1_700_000_000_000).unwrap();
     assert_roundtrip(Value::Date(dt));
 }
 
+#[cfg(feature = "chrono")]
 #[test]
 fn roundtrip_date_epoch() {
     let dt = chrono::Utc.timestamp_millis_opt(0).unwrap();
     assert_roundtrip(Value::Date(dt));
 }
 
+#[cfg(feature = "bigint")]
 #[test]
 fn roundtrip_bigint() {
     assert_roundtrip(Value::BigInt(BigInt::from(0)));
@@ -126,9 +170,10 @@ fn roundtrip_regexp() {
     });
 }
 
+#[cfg(feature = "chrono")]
 #[test]
 fn roundtrip_object_with_dot_in_key() {
-    let mut obj = IndexMap::new();
+    let mut obj = Map::new();
     obj.insert(
         "a.b".to_string(),
         Value::Date(chrono::Utc.timestamp_millis_opt(0).unwrap()),
@@ -138,25 +183,28 @@ fn roundtrip_object_with_dot_in_key() {
 
 #[test]
 fn roundtrip_object_with_backslash_in_key() {
-    let mut obj = IndexMap::new();
+    let mut obj = Map::new();
     obj.insert("a\\b".to_string(), Value::Undefined);
     assert_roundtrip(Value::Object(obj));
 }
 
+#[cfg(feature = "bigint")]
 #[test]
 fn roundtrip_nested_object_with_dot_in_key() {
-    let mut inner = IndexMap::new();
+    let mut inner = Map::new();
     inner.insert("x".to_string(), Value::BigInt(BigInt::from(42)));
-    let mut outer = IndexMap::new();
+    let mut outer = Map::new();
     outer.insert("a.b".to_string(), Value::Object(inner));
     assert_roundtrip(Value::Object(outer));
 }
 
 #[test]
 fn roundtrip_url() {
-    assert_roundtrip(Value::Url("https://example.com/".into()));
+    assert_roundtrip(Value::Url("https://example.com/".parse().unwrap()));
     assert_roundtrip(Value::Url(
-        "https://example.com/path?query=value&foo=bar#fragment".into(),
+        "https://example.com/path?query=value&foo=bar#fragment"
+            .parse()
+            .unwrap(),
     ));
 }
 
@@ -165,7 +213,9 @@ fn roundtrip_error_simple() {
     assert_roundtrip(Value::Error {
         name: "Error".into(),
         message: "something went wrong".into(),
+        stack: None,
         cause: None,
+        extra: Map::new(),
     });
 }
 
@@ -174,7 +224,9 @@ fn roundtrip_error_with_string_cause() {
     assert_roundtrip(Value::Error {
         name: "Error".into(),
         message: "fail".into(),
+        stack: None,
         cause: Some(Box::new(Value::String("root cause".into()))),
+        extra: Map::new(),
     });
 }
 
@@ -183,21 +235,28 @@ fn roundtrip_error_with_error_cause() {
     assert_roundtrip(Value::Error {
         name: "TypeError".into(),
         message: "outer".into(),
+        stack: None,
         cause: Some(Box::new(Value::Error {
             name: "Error".into(),
             message: "inner".into(),
+            stack: None,
             cause: None,
+            extra: Map::new(),
         })),
+        extra: Map::new(),
     });
 }
 
+#[cfg(feature = "chrono")]
 #[test]
 fn roundtrip_error_with_date_cause() {
     let dt = chrono::Utc.timestamp_millis_opt(0).unwrap();
     assert_roundtrip(Value::Error {
         name: "Error".into(),
         message: "fail".into(),
+        stack: None,
         cause: Some(Box::new(Value::Date(dt))),
+        extra: Map::new(),
     });
 }
 
@@ -206,22 +265,29 @@ fn roundtrip_error_deeply_nested_cause() {
     assert_roundtrip(Value::Error {
         name: "Error".into(),
         message: "level 1".into(),
+        stack: None,
         cause: Some(Box::new(Value::Error {
             name: "Error".into(),
             message: "level 2".into(),
+            stack: None,
             cause: Some(Box::new(Value::Error {
                 name: "Error".into(),
                 message: "level 3".into(),
+                stack: None,
                 cause: None,
+                extra: Map::new(),
             })),
+            extra: Map::new(),
         })),
+        extra: Map::new(),
     });
 }
 
+#[cfg(all(feature = "chrono", feature = "bigint"))]
 #[test]
 fn roundtrip_complex_nested_structure() {
     let dt = chrono::Utc.timestamp_millis_opt(0).unwrap();
-    let mut inner = IndexMap::new();
+    let mut inner = Map::new();
     inner.insert("date".to_string(), Value::Date(dt));
     inner.insert(
         "tags".to_string(),
@@ -231,7 +297,7 @@ fn roundtrip_complex_nested_structure() {
         ]),
     );
 
-    let mut obj = IndexMap::new();
+    let mut obj = Map::new();
     obj.insert("id".to_string(), Value::BigInt(BigInt::from(123)));
     obj.insert("data".to_string(), Value::Object(inner));
     obj.insert(
@@ -246,6 +312,7 @@ fn roundtrip_complex_nested_structure() {
     assert_roundtrip(Value::Object(obj));
 }
 
+#[cfg(all(feature = "chrono", feature = "bigint"))]
 #[test]
 fn roundtrip_set_with_extended_types() {
     let dt = chrono::Utc.timestamp_millis_opt(0).unwrap();
@@ -263,3 +330,222 @@ fn roundtrip_map_with_non_string_keys() {
         (Value::Number(2.0), Value::String("two".into())),
     ]));
 }
+
+#[test]
+fn roundtrip_object_key_order() {
+    let mut obj = Map::new();
+    obj.insert("b".to_string(), Value::from(1));
+    obj.insert("a".to_string(), Value::Undefined);
+    let keys: Vec<_> = obj.keys().cloned().collect();
+    if cfg!(feature = "preserve_order") {
+        assert_eq!(keys, ["b", "a"]);
+    } else {
+        assert_eq!(keys, ["a", "b"]);
+    }
+
+    let value = Value::Object(obj);
+    let parsed = parse(&stringify(&value).unwrap()).unwrap();
+    assert_eq!(parsed, value);
+    let parsed_keys: Vec<_> = parsed.as_object().unwrap().keys().cloned().collect();
+    assert_eq!(parsed_keys, keys);
+
+    // Key order of the wire text itself is kept, too.
+    let parsed = parse(r#"{"json":{"b":1,"a":2}}"#).unwrap();
+    let parsed_keys: Vec<_> = parsed
+        .as_object()
+        .unwrap()
+        .keys()
+        .map(String::as_str)
+        .collect();
+    if cfg!(feature = "preserve_order") {
+        assert_eq!(parsed_keys, ["b", "a"]);
+    } else {
+        assert_eq!(parsed_keys, ["a", "b"]);
+    }
+}
+
+#[cfg(feature = "chrono")]
+#[test]
+fn roundtrip_naive_date_time() {
+    use chrono::{NaiveDate, NaiveTime};
+
+    let date = NaiveDate::from_ymd_opt(2024, 2, 29).unwrap();
+    let time = NaiveTime::from_hms_milli_opt(9, 30, 0, 250).unwrap();
+    let dt = date.and_time(time);
+
+    let value = Value::Array(vec![date.into(), time.into(), dt.into()]);
+    assert_eq!(
+        stringify(&value).unwrap(),
+        r#"{"json":["2024-02-29","09:30:00.250","2024-02-29T09:30:00.250"],"meta":{"values":{"0":[["custom","PlainDate"]],"1":[["custom","PlainTime"]],"2":[["custom","PlainDateTime"]]},"v":1}}"#
+    );
+
+    let parsed = parse(&stringify(&value).unwrap()).unwrap();
+    let items = parsed.as_array().unwrap();
+    assert_eq!(items[0].as_naive_date(), Some(date));
+    assert_eq!(items[1].as_naive_time(), Some(time));
+    assert_eq!(items[2].as_naive_date_time(), Some(dt));
+    assert_eq!(items[0].as_naive_time(), None);
+}
+
+#[test]
+fn value_eq_json() {
+    use serde_json::json;
+
+    let mut obj = Map::new();
+    obj.insert("n".into(), Value::from(1));
+    obj.insert("u".into(), Value::Undefined);
+    obj.insert(
+        "m".into(),
+        Value::Map(vec![(Value::from("k"), Value::NegZero)]),
+    );
+    let value = Value::Object(obj);
+
+    assert_eq!(value, json!({"n": 1, "u": null, "m": [["k", "-0"]]}));
+    assert_eq!(json!({"n": 1.0, "u": null, "m": [["k", "-0"]]}), value);
+    assert_ne!(value, json!({"n": 1, "u": null, "m": [["k", 0]]}));
+    assert_ne!(value, json!({"n": 1, "m": [["k", "-0"]]}));
+    assert_ne!(Value::from(1), json!("1"));
+}
+
+#[cfg(feature = "chrono")]
+#[test]
+fn value_eq_json_date() {
+    let date = Value::Date(chrono::Utc.timestamp_millis_opt(0).unwrap());
+    assert_eq!(date, serde_json::json!("1970-01-01T00:00:00.000Z"));
+}
+
+#[test]
+fn extended_paths_match_annotations() {
+    let mut obj = Map::new();
+    obj.insert(
+        "m".into(),
+        Value::Map(vec![(Value::from("k"), Value::NegZero)]),
+    );
+    obj.insert(
+        "e".into(),
+        Value::Error {
+            name: "Error".into(),
+            message: "fail".into(),
+            stack: None,
+            cause: Some(Box::new(Value::Url("https://example.com".parse().unwrap()))),
+            extra: Map::new(),
+        },
+    );
+    obj.insert("a.b".into(), Value::from(vec![Value::PosInfinity]));
+    let value = Value::Object(obj);
+
+    let mut paths = value.extended_paths();
+    paths.sort();
+    let expected = [
+        ("a\\.b.0", "Infinity"),
+        ("e", "Error"),
+        ("e.cause", "URL"),
+        ("m", "Map"),
+        ("m.0.1", "-0"),
+    ];
+    assert_eq!(paths, expected.map(|(path, name)| (path.to_string(), name)));
+    for (path, _) in &paths {
+        assert!(value.pointer(path).is_some(), "{path}");
+    }
+    assert!(!value.is_plain_json());
+}
+
+#[test]
+fn bare_json_roundtrip() {
+    let text = r#"{"a":[1.5,null,true],"b":{"c":"d"}}"#;
+    let value = Value::from_json_str(text).unwrap();
+    assert!(value.is_plain_json());
+    assert_eq!(Value::from_json_reader(text.as_bytes()).unwrap(), value);
+
+    let mut out = Vec::new();
+    value.to_json_writer(&mut out).unwrap();
+    assert_eq!(
+        Value::from_json_str(std::str::from_utf8(&out).unwrap()).unwrap(),
+        value
+    );
+
+    // Bare JSON never carries annotations, even if it looks like an envelope.
+    let envelope = Value::from_json_str(r#"{"json":"NaN","meta":{"values":["number"]}}"#).unwrap();
+    assert_eq!(envelope.pointer("json"), Some(&Value::from("NaN")));
+    assert!(Value::from_json_str("{").is_err());
+}
+
+#[test]
+fn roundtrip_pretty() {
+    let mut obj = Map::new();
+    obj.insert("z".into(), Value::Set(vec![Value::from(1)]));
+    obj.insert("a".into(), Value::Undefined);
+    let value = Value::Object(obj);
+
+    let pretty = superjson_rs::stringify_pretty(&value).unwrap();
+    assert!(pretty.contains('\n'));
+    assert_eq!(parse(&pretty).unwrap(), value);
+    let compact: String = pretty
+        .lines()
+        .map(|line| line.trim_start().replace("\": ", "\":"))
+        .collect();
+    assert_eq!(compact, stringify(&value).unwrap());
+}
+
+#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+struct Stored {
+    id: u32,
+    payload: Value,
+}
+
+#[test]
+fn value_in_serde_types() {
+    let payload = Value::from(vec![Value::Set(vec![Value::NaN]), Value::Undefined]);
+    let stored = Stored {
+        id: 7,
+        payload: payload.clone(),
+    };
+
+    // Other formats store the whole envelope.
+    let text = serde_json::to_string(&stored).unwrap();
+    let envelope = stringify(&payload).unwrap();
+    assert_eq!(text, format!(r#"{{"id":7,"payload":{envelope}}}"#));
+    assert_eq!(serde_json::from_str::<Stored>(&text).unwrap(), stored);
+
+    // superjson's own serializers annotate it in place.
+    let text = superjson_rs::to_string(&stored).unwrap();
+    assert_eq!(parse(&text).unwrap().pointer("payload"), Some(&payload));
+    assert_eq!(superjson_rs::from_str::<Stored>(&text).unwrap(), stored);
+    assert_eq!(superjson_rs::to_string(&payload).unwrap(), envelope);
+}
+
+#[test]
+fn from_serde_json_value() {
+    let json = serde_json::json!({"b": [1, 2.5, null], "a": {"s": "x", "t": true}});
+    assert_eq!(Value::from(json.clone()), json);
+
+    let mut deep = serde_json::json!(0);
+    for _ in 0..1_000 {
+        deep = serde_json::json!([deep]);
+    }
+    let mut value = Value::from_json(deep);
+    for _ in 0..1_000 {
+        let Value::Array(mut items) = value else {
+            panic!("expected an array");
+        };
+        value = items.pop().unwrap();
+    }
+    assert_eq!(value, Value::from(0));
+}
+
+#[test]
+fn from_serde_json_value_keeps_large_integers() {
+    let value = Value::from(serde_json::json!([
+        9007199254740991u64,
+        9007199254740993u64,
+        -9007199254740993i64
+    ]));
+    assert_eq!(
+        value,
+        Value::from(vec![
+            Value::Int(9007199254740991),
+            Value::Int(9007199254740993),
+            Value::Int(-9007199254740993),
+        ])
+    );
+}
@@ -0,0 +1,47 @@
+//! Tests for payload statistics collected by `parse_detailed`.
+
+use superjson_rs::{ParseOptions, parse_detailed};
+
+fn options() -> ParseOptions {
+    ParseOptions {
+        collect_stats: true,
+        ..ParseOptions::default()
+    }
+}
+
+#[test]
+fn stats_are_absent_unless_requested() {
+    let parsed = parse_detailed(r#"{"json":1}"#, &ParseOptions::default()).unwrap();
+    assert!(parsed.stats.is_none());
+}
+
+#[cfg(feature = "chrono")]
+#[test]
+fn stats_count_nodes_per_type() {
+    let s = r#"{
+        "json": {"created": "1970-01-01T00:00:00.000Z", "tags": ["a", "b"], "n": 1},
+        "meta": {"values": {"created": ["Date"], "tags": ["set"]}, "v": 1}
+    }"#;
+    let stats = parse_detailed(s, &options()).unwrap().stats.unwrap();
+
+    assert_eq!(stats.bytes, s.len());
+    assert_eq!(stats.nodes, 6);
+    assert_eq!(stats.count("object"), 1);
+    assert_eq!(stats.count("Date"), 1);
+    assert_eq!(stats.count("Set"), 1);
+    assert_eq!(stats.count("string"), 2);
+    assert_eq!(stats.count("number"), 1);
+    assert_eq!(stats.count("bigint"), 0);
+    assert_eq!(stats.annotations, 2);
+    assert_eq!(stats.max_depth, 2);
+}
+
+#[test]
+fn stats_scalar_root_has_zero_depth() {
+    let stats = parse_detailed(r#"{"json":"x"}"#, &options())
+        .unwrap()
+        .stats
+        .unwrap();
+    assert_eq!(stats.nodes, 1);
+    assert_eq!(stats.max_depth, 0);
+}
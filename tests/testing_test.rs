@@ -0,0 +1,77 @@
+#![cfg(feature = "chrono")]
+
+use chrono::TimeZone;
+use superjson_rs::testing::{describe_differences, snapshot};
+use superjson_rs::{Map, Value, assert_superjson_eq};
+
+fn sample(age: f64) -> Value {
+    let mut user = Map::new();
+    user.insert(
+        "created".to_string(),
+        Value::Date(chrono::Utc.timestamp_millis_opt(0).unwrap()),
+    );
+    user.insert("age".to_string(), Value::Number(age));
+    let mut root = Map::new();
+    root.insert("user".to_string(), Value::Object(user));
+    Value::Object(root)
+}
+
+#[test]
+fn assert_superjson_eq_passes_for_equal_values() {
+    assert_superjson_eq!(sample(30.0), sample(30.0));
+    assert_superjson_eq!(sample(30.0), sample(30.0), "with context {}", 1);
+}
+
+#[test]
+#[should_panic(expected = "user.age: 30 → 31")]
+fn assert_superjson_eq_reports_differing_path() {
+    assert_superjson_eq!(sample(30.0), sample(31.0));
+}
+
+#[test]
+fn describe_differences_lists_each_path() {
+    let description = describe_differences(&sample(30.0), &Value::Null).unwrap();
+    let user = if cfg!(feature = "preserve_order") {
+        r#"{"created": Date(1970-01-01T00:00:00+00:00), "age": 30}"#
+    } else {
+        r#"{"age": 30, "created": Date(1970-01-01T00:00:00+00:00)}"#
+    };
+    assert_eq!(
+        description,
+        format!("1 difference(s) (left → right):\n  <root>: {{\"user\": {user}}} → null")
+    );
+    assert!(describe_differences(&sample(1.0), &sample(1.0)).is_none());
+}
+
+#[test]
+fn snapshot_is_stable_and_indented() {
+    let rendered = snapshot(&sample(30.0)).unwrap();
+    assert_eq!(rendered, snapshot(&sample(30.0)).unwrap());
+    // Fields are written in the order of the value's map.
+    let user = if cfg!(feature = "preserve_order") {
+        r#""created": "1970-01-01T00:00:00.000Z",
+      "age": 30.0"#
+    } else {
+        r#""age": 30.0,
+      "created": "1970-01-01T00:00:00.000Z""#
+    };
+    assert_eq!(
+        rendered,
+        r#"{
+  "json": {
+    "user": {
+      USER
+    }
+  },
+  "meta": {
+    "values": {
+      "user.created": [
+        "Date"
+      ]
+    },
+    "v": 1
+  }
+}"#
+        .replace("USER", user)
+    );
+}
@@ -0,0 +1,59 @@
+//! Tests for the `time` crate conversions.
+#![cfg(feature = "time")]
+
+use superjson_rs::{Error, Value, parse, stringify};
+use time::OffsetDateTime;
+use time::macros::datetime;
+
+#[test]
+fn offset_datetime_roundtrip() {
+    let dt = datetime!(2024-03-01 12:34:56.789 UTC);
+    let value = Value::try_from(dt).unwrap();
+    assert_eq!(value.as_offset_datetime(), Some(dt));
+
+    let restored = parse(&stringify(&value).unwrap()).unwrap();
+    assert_eq!(restored.as_offset_datetime(), Some(dt));
+}
+
+#[test]
+fn offset_is_normalized_to_utc() {
+    let dt = datetime!(2024-03-01 09:00 +09:00);
+    let value = Value::try_from(dt).unwrap();
+    assert_eq!(
+        value.as_offset_datetime(),
+        Some(datetime!(2024-03-01 00:00 UTC))
+    );
+}
+
+#[test]
+fn offset_date_keeps_its_offset() {
+    let dt = chrono::DateTime::parse_from_rfc3339("2024-03-01T09:00:00+09:00").unwrap();
+    let value = Value::OffsetDate(dt);
+    assert_eq!(
+        OffsetDateTime::try_from(value).unwrap(),
+        datetime!(2024-03-01 09:00 +09:00)
+    );
+}
+
+#[test]
+fn non_dates_are_not_converted() {
+    // With `time`'s `large-dates`, as enabled for these tests, every chrono
+    // date fits in an `OffsetDateTime`; without it, years past 9999 give
+    // `None` and `Error::InvalidDate`.
+    let far = chrono::DateTime::from_timestamp(400_000_000_000, 0).unwrap();
+    assert_eq!(
+        Value::Date(far).as_offset_datetime(),
+        Some(datetime!(+14645-06-30 15:06:40 UTC))
+    );
+    assert_eq!(Value::Null.as_offset_datetime(), None::<OffsetDateTime>);
+    assert!(matches!(
+        OffsetDateTime::try_from(Value::from("2024-03-01")),
+        Err(Error::TypeMismatch { .. })
+    ));
+}
+
+#[test]
+fn out_of_range_offset_datetime_is_an_error() {
+    let far = datetime!(+300000-01-01 00:00 UTC);
+    assert!(matches!(Value::try_from(far), Err(Error::InvalidDate(_))));
+}
@@ -0,0 +1,45 @@
+//! Tests for the `uuid` crate conversions.
+#![cfg(feature = "uuid")]
+
+use superjson_rs::{Error, Map, Value, parse, stringify};
+use uuid::Uuid;
+
+const ID: &str = "67e55044-10b1-426f-9247-bb680e5fe0c8";
+
+#[test]
+fn uuid_roundtrip() {
+    let id = Uuid::parse_str(ID).unwrap();
+    let mut map = Map::new();
+    map.insert("id".to_string(), Value::from(id));
+    let value = Value::Object(map);
+
+    let text = stringify(&value).unwrap();
+    assert_eq!(
+        text,
+        format!(
+            r#"{{"json":{{"id":"{ID}"}},"meta":{{"values":{{"id":[["custom","uuid"]]}},"v":1}}}}"#
+        )
+    );
+    let restored = parse(&text).unwrap();
+    assert_eq!(restored.pointer("id").and_then(Value::as_uuid), Some(id));
+}
+
+#[test]
+fn uuid_from_plain_string() {
+    let braced = format!("{{{}}}", ID.to_uppercase());
+    assert_eq!(
+        Uuid::try_from(Value::from(braced)).unwrap(),
+        Uuid::parse_str(ID).unwrap()
+    );
+    assert_eq!(Value::from(ID).as_uuid(), None);
+}
+
+#[test]
+fn invalid_uuid_is_a_mismatch() {
+    for value in [Value::from("not-a-uuid"), Value::from(1)] {
+        assert!(matches!(
+            Uuid::try_from(value),
+            Err(Error::TypeMismatch { expected, .. }) if expected == "uuid"
+        ));
+    }
+}
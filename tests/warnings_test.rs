@@ -0,0 +1,135 @@
+//! Tests for non-fatal warnings reported by `parse_detailed`.
+
+#[cfg(feature = "chrono")]
+use chrono::TimeZone;
+#[cfg(feature = "chrono")]
+use superjson_rs::Map;
+use superjson_rs::{ParseOptions, Value, Warning, parse_detailed};
+
+fn warnings(s: &str) -> Vec<Warning> {
+    parse_detailed(s, &ParseOptions::default())
+        .expect("parse failed")
+        .warnings
+}
+
+#[cfg(feature = "chrono")]
+#[test]
+fn canonical_payload_has_no_warnings() {
+    let mut obj = Map::new();
+    obj.insert(
+        "date".to_string(),
+        Value::Date(chrono::Utc.timestamp_millis_opt(0).unwrap()),
+    );
+    obj.insert("tags".to_string(), Value::Set(vec![Value::Undefined]));
+    let s = superjson_rs::stringify(&Value::Object(obj)).unwrap();
+    assert!(warnings(&s).is_empty());
+}
+
+#[test]
+fn unknown_envelope_and_meta_fields() {
+    let s = r#"{"json":1,"extra":true,"meta":{"v":1,"trace":"x"}}"#;
+    assert_eq!(
+        warnings(s),
+        vec![
+            Warning::UnknownField {
+                name: "extra".into()
+            },
+            Warning::UnknownField {
+                name: "meta.trace".into()
+            },
+        ]
+    );
+}
+
+#[cfg(feature = "bigint")]
+#[test]
+fn lenient_annotation_forms() {
+    let s = r#"{"json":{"a":[1],"b":"1"},"meta":{"values":{"a":["set",{}],"b":["bigint",null]}}}"#;
+    let parsed = parse_detailed(s, &ParseOptions::default()).unwrap();
+    assert_eq!(
+        parsed.warnings,
+        vec![
+            Warning::LenientAnnotation {
+                path: "a".into(),
+                detail: "empty children treated as a leaf".into()
+            },
+            Warning::LenientAnnotation {
+                path: "b".into(),
+                detail: "null children treated as a leaf".into()
+            },
+        ]
+    );
+    let obj = parsed.value.as_object().unwrap();
+    assert_eq!(obj["a"], Value::Set(vec![Value::Number(1.0)]));
+}
+
+#[test]
+fn lenient_annotation_reports_nested_path() {
+    let s = r#"{"json":{"a":[[1]]},"meta":{"values":{"a":["set",{"0":["set",null]}]}}}"#;
+    let parsed = parse_detailed(s, &ParseOptions::default()).unwrap();
+    assert_eq!(
+        parsed.warnings,
+        vec![Warning::LenientAnnotation {
+            path: "a.0".into(),
+            detail: "null children treated as a leaf".into()
+        }]
+    );
+}
+
+#[cfg(feature = "chrono")]
+#[test]
+fn coerced_date_reports_nested_path() {
+    let s = r#"{
+        "json": {"events": [{"at": "2024-01-01T09:00:00.000+09:00"}]},
+        "meta": {"values": {"events.0.at": ["Date"]}, "v": 1}
+    }"#;
+    let parsed = parse_detailed(s, &ParseOptions::default()).unwrap();
+    assert_eq!(
+        parsed.warnings,
+        vec![Warning::CoercedDate {
+            path: "events.0.at".into(),
+            original: "2024-01-01T09:00:00.000+09:00".into(),
+        }]
+    );
+}
+
+#[test]
+fn large_integers_are_exact() {
+    let s = r#"{"json":{"id":9007199254740993,"max":18446744073709551615}}"#;
+    assert_eq!(warnings(s), vec![]);
+    let value = superjson_rs::parse(s).unwrap();
+    assert_eq!(
+        value.pointer("id"),
+        Some(&Value::Int(9_007_199_254_740_993))
+    );
+    assert_eq!(value.pointer("max"), Some(&Value::UInt(u64::MAX)));
+}
+
+#[test]
+fn meta_version_is_reported() {
+    let options = ParseOptions::default();
+    let current = parse_detailed(r#"{"json":1,"meta":{"v":1}}"#, &options).unwrap();
+    assert_eq!(current.version, Some(superjson_rs::META_VERSION));
+    assert!(current.warnings.is_empty());
+
+    let unversioned = parse_detailed(r#"{"json":1}"#, &options).unwrap();
+    assert_eq!(unversioned.version, None);
+
+    let future = r#"{"json":1,"meta":{"v":2}}"#;
+    let parsed = parse_detailed(future, &options).unwrap();
+    assert_eq!(parsed.version, Some(2));
+    assert_eq!(
+        parsed.warnings,
+        vec![Warning::UnknownVersion { version: 2 }]
+    );
+
+    let strict = ParseOptions {
+        strict_version: true,
+        ..ParseOptions::default()
+    };
+    assert!(matches!(
+        parse_detailed(future, &strict),
+        Err(superjson_rs::Error::UnsupportedVersion(2))
+    ));
+    assert!(parse_detailed(r#"{"json":1}"#, &strict).is_ok());
+}